@@ -0,0 +1,88 @@
+//! Anomaly detection on a large synthetic log: `cargo bench --bench detection`
+//!
+//! Reports how long detection and aggregation take and how many copies of triggering events
+//! the findings hold. Findings share one copy per event instead of owning a clone each.
+use security_log_analyser::prelude::*;
+use std::collections::HashSet;
+use std::time::Instant;
+
+const EVENTS: usize = 50_000;
+/// One in this many processes is an Office document dropping a shell into a temporary directory
+const SUSPICIOUS_EVERY: usize = 4;
+
+fn process_event(record_id: usize) -> SysmonEvent {
+    let (parent, image) = if record_id.is_multiple_of(SUSPICIOUS_EVERY) {
+        (
+            r"C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE",
+            r"C:\Users\Bob\AppData\Local\Temp\cmd.exe",
+        )
+    } else {
+        (
+            r"C:\Windows\explorer.exe",
+            r"C:\Windows\System32\notepad.exe",
+        )
+    };
+    let (minutes, seconds) = (record_id / 60 % 60, record_id % 60);
+    let hours = record_id / 3600 % 24;
+    let xml = format!(
+        r#"<Event><System>
+        <Provider Name="Microsoft-Windows-Sysmon" Guid="{{5770385F-C22A-43E0-BF4C-06F5698FFBD9}}"/>
+        <EventID>1</EventID><Version>5</Version><Level>4</Level><Task>1</Task><Opcode>0</Opcode>
+        <Keywords>0x8000000000000000</Keywords>
+        <TimeCreated SystemTime="2025-01-01T{hours:02}:{minutes:02}:{seconds:02}.000Z"/>
+        <EventRecordID>{record_id}</EventRecordID><Execution ProcessID="1000" ThreadID="2000"/>
+        <Channel>Microsoft-Windows-Sysmon/Operational</Channel><Computer>BENCH-PC</Computer>
+        <Security UserID="S-1-5-18"/></System><EventData>
+        <Data Name="UtcTime">2025-01-01 {hours:02}:{minutes:02}:{seconds:02}.000</Data>
+        <Data Name="ProcessGuid">{{11111111-2222-3333-4444-555555555555}}</Data>
+        <Data Name="ProcessId">{pid}</Data>
+        <Data Name="Image">{image}</Data>
+        <Data Name="CommandLine">"{image}" /c echo {record_id}</Data>
+        <Data Name="CurrentDirectory">C:\Users\Bob\</Data>
+        <Data Name="User">BENCH-PC\Bob</Data>
+        <Data Name="LogonGuid">{{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}}</Data>
+        <Data Name="LogonId">0x3e7</Data><Data Name="TerminalSessionId">1</Data>
+        <Data Name="IntegrityLevel">Medium</Data><Data Name="Hashes">SHA256=1234</Data>
+        <Data Name="ParentProcessGuid">{{99999999-8888-7777-6666-555555555555}}</Data>
+        <Data Name="ParentProcessId">4</Data>
+        <Data Name="ParentImage">{parent}</Data>
+        <Data Name="ParentCommandLine">{parent}</Data>
+        </EventData></Event>"#,
+        pid = 10_000 + record_id,
+    );
+    SysmonEvent::from_str(xml).expect("synthetic event is valid")
+}
+
+fn main() {
+    let events: Vec<SysmonEvent> = (1..=EVENTS).map(process_event).collect();
+
+    let start = Instant::now();
+    let anomalies = detect_anomalies(&events);
+    let detection = start.elapsed();
+
+    let start = Instant::now();
+    let groups = aggregate_anomalies(&anomalies);
+    let cloned = anomalies.clone();
+    let aggregation = start.elapsed();
+
+    let copies: HashSet<*const SysmonEvent> = anomalies
+        .iter()
+        .filter_map(|anomaly| anomaly.event().map(|event| event as *const SysmonEvent))
+        .collect();
+    println!("events:            {EVENTS}");
+    println!(
+        "findings:          {} ({} groups)",
+        anomalies.len(),
+        groups.len()
+    );
+    println!(
+        "event copies:      {} (one per finding without sharing: {})",
+        copies.len(),
+        anomalies.len()
+    );
+    println!("detection:         {detection:?}");
+    println!(
+        "aggregate + clone: {aggregation:?} for {} findings",
+        cloned.len()
+    );
+}
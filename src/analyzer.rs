@@ -1,39 +1,309 @@
 #![allow(dead_code)]
-use crate::helpers::HasSystem;
-use crate::sysmon::{Event as SysmonEvent, NetworkEvent, ProcessCreateEvent};
+pub mod allowlist;
+pub mod assets;
+pub mod attack;
+pub mod command_line;
+pub mod config_file;
+pub mod detector;
+pub mod dropped;
+pub mod elevation;
+pub mod external;
+pub mod file_drops;
+pub mod inbound;
+pub mod ioc;
+pub mod known_good;
+pub mod lateral;
+pub mod lifetime;
+pub mod masquerading;
+pub mod parent_child;
+pub mod ports;
+pub mod prevalence;
+pub mod registry;
+pub mod rule_stats;
+pub mod script_block;
+pub mod severity;
+pub mod spoofing;
+pub mod streams;
+pub mod suppression;
+
+use crate::filters::Integrity;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::powershell::ScriptBlockParts;
+use crate::sysmon::{
+    DriverLoadEvent, Event as SysmonEvent, FileCreateEvent, FileDeleteEvent, FileStreamEvent,
+    NetworkEvent, PipeEvent, ProcessAccessEvent, ProcessCreateEvent, ProcessTamperingEvent,
+    ProcessTerminateEvent, RawAccessReadEvent, RegistryEvent, ScriptBlockEvent, WmiEvent,
+};
+use allowlist::Allowlist;
+use assets::{Asset, AssetInventory};
+use attack::Tactic;
 use chrono::{DateTime, Duration, Utc};
+use clap::ValueEnum;
+use detector::{CustomRule, Detector, DetectorRegistry};
+use dropped::DroppedExecutables;
+use external::ExecutableSource;
+use file_drops::DropLocation;
+use inbound::{InboundReason, InboundRules};
+use ioc::IocFeed;
+use known_good::KnownGoodHashes;
+use lateral::{CreatedService, FanOut, LateralConnection};
+use masquerading::Masquerade;
+use parent_child::ParentChildRule;
+use ports::{PortBaseline, PortProfile};
+use prevalence::{PrevalenceStore, Rarity, Sightings};
+use registry::RegistryFinding;
+use rule_stats::RuleProfile;
+use serde::Deserialize;
+use severity::SeverityOverrides;
+use spoofing::SpoofingReason;
+use std::cell::OnceCell;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Display;
-use tracing::info;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use streams::MarkOfTheWeb;
+use suppression::Suppression;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone)]
-pub enum Anomaly {
+pub enum AnomalyKind {
     UntrustedExecutable {
-        event: SysmonEvent,
+        event: Arc<SysmonEvent>,
         reason: String,
     },
     SuspiciousParentChild {
-        event: SysmonEvent,
+        event: Arc<SysmonEvent>,
         parent: String,
         child: String,
         reason: String,
+        severity: Severity,
     },
     DeepProcessTree {
-        event: SysmonEvent,
+        event: Arc<SysmonEvent>,
         depth: usize,
     },
     UnusualPort {
-        event: SysmonEvent,
+        event: Arc<SysmonEvent>,
         port: u16,
         process: String,
     },
     EventStorm {
-        event_id: u8,
+        event_id: u16,
+        count: usize,
+        time_window_seconds: i64,
+        /// First and last event of the burst
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        /// Process names with the most events of the burst, at most [`EVENT_STORM_TOP_PROCESSES`]
+        top_processes: Vec<(String, usize)>,
+    },
+    MassFileDeletion {
+        event: Arc<SysmonEvent>,
+        process: String,
+        deleted: usize,
+        directories: usize,
+        time_window_seconds: i64,
+    },
+    SuspiciousPipe {
+        event: Arc<SysmonEvent>,
+        pipe: String,
+        process: String,
+        reason: String,
+    },
+    SuspiciousCommandLine {
+        event: Arc<SysmonEvent>,
+        process: String,
+        indicators: Vec<&'static str>,
+        score: u32,
+    },
+    Beaconing {
+        event: Arc<SysmonEvent>,
+        process: String,
+        destination: String,
         count: usize,
+        interval_seconds: f64,
+        jitter: f64,
+    },
+    CredentialDumping {
+        event: Arc<SysmonEvent>,
+        process: String,
+        target: String,
+        granted_access: String,
+    },
+    WmiPersistence {
+        event: Arc<SysmonEvent>,
+        /// WMI class of the consumer, e.g. CommandLineEventConsumer
+        consumer_class: &'static str,
+        name: String,
+        /// Command line or script the consumer runs
+        destination: String,
+    },
+    SuspiciousDriver {
+        event: Arc<SysmonEvent>,
+        /// Path of the loaded driver
+        driver: String,
+        reason: String,
+        /// Whether the signature is revoked rather than missing or otherwise invalid
+        revoked: bool,
+    },
+    RawDiskAccess {
+        event: Arc<SysmonEvent>,
+        process: String,
+        device: String,
+    },
+    SysmonTampering {
+        event: Arc<SysmonEvent>,
+        /// What happened to the Sysmon service, e.g. `service stopped`
+        action: String,
+    },
+    IocMatch {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// Address or domain seen in the event
+        value: String,
+        /// Feed entry that matched (e.g. a CIDR block or parent domain)
+        indicator: String,
+        feed: String,
+    },
+    UnknownBinary {
+        event: Arc<SysmonEvent>,
+        process: String,
+        sha256: String,
+        /// Whether the binary runs from a system or program directory
+        sensitive: bool,
+    },
+    ProcessTampering {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// Kind of tampering reported by Sysmon, e.g. `Image is replaced`
+        tampering_type: String,
+    },
+    ExecutableStream {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// File and stream, e.g. `C:\Users\Public\report.txt:payload.exe`
+        target: String,
+        reason: &'static str,
+    },
+    DownloadExecuted {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// Origin recorded in the Mark-of-the-Web of the executed file
+        origin: MarkOfTheWeb,
+    },
+    ObfuscatedScriptBlock {
+        event: Arc<SysmonEvent>,
+        /// PowerShell image, or `PID n` when the block was not correlated with its process
+        process: String,
+        script_block_id: String,
+        indicators: Vec<&'static str>,
+        score: u32,
+    },
+    ExternalExecutable {
+        event: Arc<SysmonEvent>,
+        process: String,
+        source: ExecutableSource,
+    },
+    ParentSpoofing {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// Image of the claimed parent
+        parent: String,
+        reason: SpoofingReason,
+    },
+    SuspiciousFileDrop {
+        event: Arc<SysmonEvent>,
+        process: String,
+        target: String,
+        location: DropLocation,
+    },
+    /// Child of an Office application or of the Service Control Manager that exited quickly
+    ShortLivedProcess {
+        /// Creation of the process
+        event: Arc<SysmonEvent>,
+        process: String,
+        parent: String,
+        lifetime_ms: i64,
+    },
+    /// Process started from an executable written shortly before (FileExecutableDetected)
+    DroppedAndExecuted {
+        /// Creation of the process
+        event: Arc<SysmonEvent>,
+        /// FileExecutableDetected event of the file
+        dropped: Arc<SysmonEvent>,
+        process: String,
+        /// Image of the process that wrote the file
+        dropper: String,
+        /// Time between the drop and the start
+        elapsed_ms: i64,
+    },
+    /// Executable named like a well-known binary it is not
+    Masquerading {
+        event: Arc<SysmonEvent>,
+        /// Full path of the executable
+        image: String,
+        masquerade: Masquerade,
+    },
+    /// Registry value whose data is suspicious for its key
+    SuspiciousRegistryValue {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// TargetObject of the event
+        key: String,
+        finding: RegistryFinding,
+    },
+    /// Connection accepted from another machine that the inbound rules report
+    SuspiciousInbound {
+        event: Arc<SysmonEvent>,
+        process: String,
+        /// Local port the connection reached
+        port: u16,
+        /// Address of the remote peer
+        source: String,
+        reason: InboundReason,
+    },
+    /// Executable new to the prevalence store, or seen in few earlier runs on a single host
+    RareExecutable {
+        event: Arc<SysmonEvent>,
+        /// Full path of the executable
+        image: String,
+        rarity: Rarity,
+    },
+    /// Process at High or System integrity started by one at Medium or lower without a consent
+    /// prompt approving it
+    IntegrityEscalation {
+        event: Arc<SysmonEvent>,
+        process: String,
+        parent: String,
+        /// Account of the elevated process
+        user: String,
+        from: Integrity,
+        to: Integrity,
+    },
+    /// Process connecting to the SMB, RPC or WinRM ports of many internal hosts within a short
+    /// time, reported again for each service created on one of those hosts shortly after
+    LateralMovement {
+        /// Connection completing the fan-out, or the service creation
+        event: Arc<SysmonEvent>,
+        /// Computer of the connecting process
+        source: String,
+        process: String,
+        hosts: usize,
+        ports: Vec<u16>,
         time_window_seconds: i64,
+        service: Option<CreatedService>,
+    },
+    /// Finding of a [`Detector`] registered in [`DetectionConfig::detectors`]
+    Custom {
+        rule: &'static CustomRule,
+        event: Option<Arc<SysmonEvent>>,
+        severity: Severity,
+        description: String,
     },
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     Low,
     Medium,
@@ -50,57 +320,442 @@ impl Display for Severity {
         }
     }
 }
+/// Stable IDs of the built-in detection rules and their names, used in output and suppressions
+pub const RULE_IDS: &[(&str, &str)] = &[
+    ("SLA-001", "UntrustedExecutable"),
+    ("SLA-002", "SuspiciousParentChild"),
+    ("SLA-003", "DeepProcessTree"),
+    ("SLA-004", "UnusualPort"),
+    ("SLA-005", "EventStorm"),
+    ("SLA-006", "MassFileDeletion"),
+    ("SLA-007", "SuspiciousPipe"),
+    ("SLA-008", "SuspiciousCommandLine"),
+    ("SLA-009", "Beaconing"),
+    ("SLA-010", "CredentialDumping"),
+    ("SLA-011", "IocMatch"),
+    ("SLA-012", "WmiPersistence"),
+    ("SLA-013", "SuspiciousDriver"),
+    ("SLA-014", "RawDiskAccess"),
+    ("SLA-015", "SysmonTampering"),
+    ("SLA-016", "UnknownBinary"),
+    ("SLA-017", "ProcessTampering"),
+    ("SLA-018", "ExecutableStream"),
+    ("SLA-019", "DownloadExecuted"),
+    ("SLA-020", "ObfuscatedScriptBlock"),
+    ("SLA-021", "ExternalExecutable"),
+    ("SLA-022", "ParentSpoofing"),
+    ("SLA-023", "SuspiciousFileDrop"),
+    ("SLA-024", "ShortLivedProcess"),
+    ("SLA-025", "DroppedAndExecuted"),
+    ("SLA-026", "Masquerading"),
+    ("SLA-027", "SuspiciousRegistryValue"),
+    ("SLA-028", "SuspiciousInbound"),
+    ("SLA-029", "RareExecutable"),
+    ("SLA-030", "IntegrityEscalation"),
+    ("SLA-031", "LateralMovement"),
+];
+/// Names of the built-in detection rules, as reported by [`AnomalyKind::rule_name`]
+pub const RULE_NAMES: [&str; RULE_IDS.len()] = {
+    let mut names = [""; RULE_IDS.len()];
+    let mut i = 0;
+    while i < RULE_IDS.len() {
+        names[i] = RULE_IDS[i].1;
+        i += 1;
+    }
+    names
+};
+/// Name of the rule given by ID or name (case-insensitive)
+pub fn rule_name_of(rule: &str) -> Option<&'static str> {
+    RULE_IDS
+        .iter()
+        .find(|(id, name)| id.eq_ignore_ascii_case(rule) || name.eq_ignore_ascii_case(rule))
+        .map(|(_, name)| *name)
+}
+/// ID of the rule with this name
+pub fn rule_id_of(name: &str) -> Option<&'static str> {
+    RULE_IDS
+        .iter()
+        .find(|(_, rule)| *rule == name)
+        .map(|(id, _)| *id)
+}
+
+/// Tunable thresholds and threat-intel feeds of the detection rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionConfig {
+    /// Connections to the same destination needed before beaconing is considered
+    pub beacon_min_count: usize,
+    /// Maximum standard deviation of connection intervals relative to their mean
+    pub beacon_jitter: f64,
+    /// Network indicators reported when seen in connections or DNS queries
+    pub ioc_feeds: Vec<IocFeed>,
+    /// Known false positives dropped from the findings
+    pub suppressions: Vec<Suppression>,
+    /// Built-in parent/child rules followed by those of the config file
+    pub parent_child_rules: Vec<ParentChildRule>,
+    /// SHA256 hashes of trusted binaries; when set, unknown binaries are reported
+    pub known_good: Option<KnownGoodHashes>,
+    /// Detectors run in addition to the built-in rules
+    pub detectors: DetectorRegistry,
+    /// Uppercase drive letters of removable media, executables started from them are reported
+    pub removable_drives: Vec<char>,
+    /// Built-in expected destination ports of processes followed by the config file allowlist
+    pub port_profiles: Vec<PortProfile>,
+    /// Findings of a lower severity are dropped
+    pub min_severity: Option<Severity>,
+    /// Time after an executable is written within which starting it is reported
+    pub drop_execute_window: Duration,
+    /// Known-benign processes and destinations whose events are not analyzed
+    pub allowlist: Allowlist,
+    /// Expected listeners, workstations and admin subnets checked against inbound connections
+    pub inbound: InboundRules,
+    /// Prevalence store file; when set, batch runs report the executables rare in it, and the
+    /// commands add their runs to it with [`prevalence::record_run`]
+    pub prevalence: Option<PathBuf>,
+    /// Events of one ID within `event_storm_window` that make an event storm
+    pub event_storm_threshold: usize,
+    pub event_storm_window: Duration,
+    /// Severities set per rule, replacing those the rules assign
+    pub severity_overrides: SeverityOverrides,
+    /// Role, owner and criticality of computers; findings on them are raised or lowered to
+    /// their criticality
+    pub assets: Option<Arc<AssetInventory>>,
+    /// Name of the only rule evaluated, as `rules test` runs one; all rules when unset
+    pub only_rule: Option<&'static str>,
+}
+impl DetectionConfig {
+    /// Whether the finding is dropped, by a suppression or for its severity
+    pub fn is_suppressed(&self, anomaly: &Anomaly) -> bool {
+        self.min_severity
+            .is_some_and(|min_severity| anomaly.severity() < min_severity)
+            || self
+                .suppressions
+                .iter()
+                .any(|suppression| suppression.matches(anomaly))
+    }
+    /// Fail on severity overrides of rules that are neither built in nor reported by a
+    /// registered detector
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.severity_overrides.validate(&self.detectors)
+    }
+    /// Findings of the rules with their severities, without the suppressed ones
+    fn findings(&self, anomalies: Vec<AnomalyKind>) -> Vec<Anomaly> {
+        anomalies
+            .into_iter()
+            .map(|kind| Anomaly::new(kind, self))
+            .filter(|anomaly| !self.is_suppressed(anomaly))
+            .collect()
+    }
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            beacon_min_count: 10,
+            beacon_jitter: 0.1,
+            ioc_feeds: Vec::new(),
+            suppressions: Vec::new(),
+            parent_child_rules: ParentChildRule::defaults(),
+            known_good: None,
+            detectors: DetectorRegistry::new(),
+            removable_drives: Vec::new(),
+            port_profiles: PortProfile::defaults(),
+            min_severity: None,
+            drop_execute_window: Duration::minutes(10),
+            allowlist: Allowlist::default(),
+            inbound: InboundRules::default(),
+            prevalence: None,
+            event_storm_threshold: DEFAULT_EVENT_STORM_THRESHOLD,
+            event_storm_window: Duration::seconds(10),
+            severity_overrides: SeverityOverrides::default(),
+            only_rule: None,
+            assets: None,
+        }
+    }
+}
+/// Event under analysis. It is only copied once it triggers a finding, and then shared by all
+/// findings it triggers instead of being cloned into each of them.
+struct Trigger<'a> {
+    event: &'a SysmonEvent,
+    shared: OnceCell<Arc<SysmonEvent>>,
+}
+impl<'a> Trigger<'a> {
+    fn new(event: &'a SysmonEvent) -> Self {
+        Self {
+            event,
+            shared: OnceCell::new(),
+        }
+    }
+    fn shared(&self) -> Arc<SysmonEvent> {
+        self.shared
+            .get_or_init(|| Arc::new(self.event.clone()))
+            .clone()
+    }
+}
 /// Detect anomalies for a single live event (for `watch` command)
 pub fn detect_anomalies_live(event: &SysmonEvent, context: &VecDeque<SysmonEvent>) -> Vec<Anomaly> {
+    detect_anomalies_live_with_config(event, context, &DetectionConfig::default())
+}
+/// Detect anomalies for a single live event using custom rule thresholds
+pub fn detect_anomalies_live_with_config(
+    event: &SysmonEvent,
+    context: &VecDeque<SysmonEvent>,
+    config: &DetectionConfig,
+) -> Vec<Anomaly> {
+    if config.allowlist.matches(event) {
+        return Vec::new();
+    }
     let mut anomalies = Vec::new();
+    let trigger = Trigger::new(event);
+    if let Some(anomaly) = check_ioc(&trigger, &config.ioc_feeds) {
+        anomalies.push(anomaly);
+    }
     match &event {
         SysmonEvent::ProcessCreate(event) => {
-            if let Some(anomaly) = check_suspicious_parent_child(event) {
+            if let Some(anomaly) =
+                check_suspicious_parent_child(event, &trigger, &config.parent_child_rules)
+            {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_command_line(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) =
+                check_untrusted_executable(event, &trigger, config.known_good.as_ref())
+            {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_unknown_binary(event, &trigger, config.known_good.as_ref())
+            {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) =
+                check_external_executable(event, &trigger, &config.removable_drives)
+            {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_masquerading(event, &trigger, config.known_good.as_ref()) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_parent_spoofing_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_integrity_escalation_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_process_depth(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_event_storm_live(event, context, config) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_download_executed_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) =
+                check_dropped_executed_live(event, &trigger, context, config.drop_execute_window)
+            {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::FileCreate(event) => {
+            if let Some(anomaly) = check_file_drop(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::FileStreamCreate(event) => {
+            if let Some(anomaly) = check_executable_stream(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::OutboundNetwork(event) => {
+            if let Some(anomaly) = check_unusual_port_live(event, &trigger, context, config) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_beaconing_live(event, &trigger, context, config) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_lateral_movement_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::InboundNetwork(event) => {
+            if let Some(anomaly) = check_inbound(event, &trigger, &config.inbound) {
+                anomalies.push(anomaly);
+            } else if let Some(anomaly) =
+                check_unusual_listener_live(event, &trigger, context, config)
+            {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::FileDelete(event) => {
+            if let Some(anomaly) = check_mass_deletion_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::PipeCreated(event) | SysmonEvent::PipeConnected(event) => {
+            if let Some(anomaly) = check_suspicious_pipe(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::RegistrySetValue(event) => {
+            if let Some(anomaly) = check_registry_value(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+            if let Some(anomaly) = check_service_after_fan_out_live(event, &trigger, context) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::ProcessAccess(event) => {
+            if let Some(anomaly) = check_credential_dumping(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::WmiConsumer(event) => {
+            if let Some(anomaly) = check_wmi_persistence(event, &trigger) {
+                anomalies.push(anomaly);
+            }
+        }
+        SysmonEvent::DriverLoad(event) => {
+            if let Some(anomaly) = check_suspicious_driver(event, &trigger) {
                 anomalies.push(anomaly);
             }
-            if let Some(anomaly) = check_process_depth(event, context) {
+        }
+        SysmonEvent::RawAccessRead(event) => {
+            if let Some(anomaly) = check_raw_disk_access(event, &trigger) {
                 anomalies.push(anomaly);
             }
-            if let Some(anomaly) = check_event_storm_live(event, context) {
+        }
+        SysmonEvent::ServiceStateChange(_) | SysmonEvent::ConfigurationChange(_) => {
+            if let Some(anomaly) = check_sysmon_tampering(&trigger) {
                 anomalies.push(anomaly);
             }
         }
-        SysmonEvent::OutboundNetwork(event) | SysmonEvent::InboundNetwork(event) => {
-            if let Some(anomaly) = check_unusual_port(event) {
+        SysmonEvent::ProcessTampering(event) => {
+            anomalies.push(check_process_tampering(event, &trigger));
+        }
+        SysmonEvent::ProcessTerminate(event) => {
+            if let Some(anomaly) = check_short_lived_live(event, context) {
                 anomalies.push(anomaly);
             }
-            if let Some(anomaly) = check_unusual_port(event) {
+        }
+        SysmonEvent::ScriptBlock(event) => {
+            // Score the whole block once its last part arrives
+            let mut parts = ScriptBlockParts::default();
+            for earlier in context {
+                if let SysmonEvent::ScriptBlock(earlier) = earlier
+                    && earlier.event_data.script_block_id == event.event_data.script_block_id
+                {
+                    parts.add(earlier, || ());
+                }
+            }
+            if let Some(text) = parts.add(event, || ())
+                && let Some(anomaly) = check_script_block(event, &text, || trigger.shared())
+            {
                 anomalies.push(anomaly);
             }
         }
-        SysmonEvent::FileCreate(_event) => {}
+        SysmonEvent::ClipboardChange(_)
+        | SysmonEvent::FileBlock(_)
+        | SysmonEvent::FileExecutableDetected(_)
+        | SysmonEvent::DnsQuery(_)
+        | SysmonEvent::WmiFilter(_)
+        | SysmonEvent::WmiBinding(_)
+        | SysmonEvent::Logon(_) => {}
     }
-    anomalies
+    config.findings(anomalies)
 }
 
-impl Anomaly {
-    pub fn severity(&self) -> Severity {
+impl AnomalyKind {
+    /// Severity the rule assigns to the finding
+    pub fn default_severity(&self) -> Severity {
         match self {
-            Anomaly::UntrustedExecutable { reason, .. } => {
+            AnomalyKind::UntrustedExecutable { reason, .. } => {
                 if reason.contains("Invalid") {
                     Severity::High
                 } else {
                     Severity::Medium
                 }
             }
-            Anomaly::SuspiciousParentChild { .. } => Severity::High,
-            Anomaly::DeepProcessTree { depth, .. } if *depth > 7 => Severity::High,
-            Anomaly::DeepProcessTree { .. } => Severity::Medium,
-            Anomaly::UnusualPort { .. } => Severity::Medium,
-            Anomaly::EventStorm { .. } => Severity::High,
+            AnomalyKind::SuspiciousParentChild { severity, .. } => *severity,
+            AnomalyKind::DeepProcessTree { depth, .. } if *depth > 7 => Severity::High,
+            AnomalyKind::DeepProcessTree { .. } => Severity::Medium,
+            AnomalyKind::UnusualPort { .. } => Severity::Medium,
+            AnomalyKind::EventStorm { .. } => Severity::High,
+            AnomalyKind::MassFileDeletion { .. } => Severity::Critical,
+            AnomalyKind::SuspiciousPipe { .. } => Severity::High,
+            AnomalyKind::SuspiciousCommandLine { score, .. }
+                if *score >= command_line::HIGH_SEVERITY_SCORE =>
+            {
+                Severity::High
+            }
+            AnomalyKind::SuspiciousCommandLine { .. } => Severity::Medium,
+            AnomalyKind::Beaconing { .. } => Severity::High,
+            AnomalyKind::CredentialDumping { .. } => Severity::Critical,
+            AnomalyKind::WmiPersistence { .. } => Severity::High,
+            AnomalyKind::SuspiciousDriver { revoked: true, .. } => Severity::Critical,
+            AnomalyKind::SuspiciousDriver { .. } => Severity::High,
+            AnomalyKind::RawDiskAccess { .. } => Severity::High,
+            AnomalyKind::SysmonTampering { .. } => Severity::Critical,
+            AnomalyKind::IocMatch { .. } => Severity::Critical,
+            AnomalyKind::UnknownBinary {
+                sensitive: true, ..
+            } => Severity::High,
+            AnomalyKind::UnknownBinary { .. } => Severity::Low,
+            AnomalyKind::ProcessTampering { .. } => Severity::High,
+            AnomalyKind::ExecutableStream { .. } => Severity::High,
+            AnomalyKind::DownloadExecuted { .. } => Severity::High,
+            AnomalyKind::ObfuscatedScriptBlock { score, .. }
+                if *score >= script_block::HIGH_SEVERITY_SCORE =>
+            {
+                Severity::High
+            }
+            AnomalyKind::ObfuscatedScriptBlock { .. } => Severity::Medium,
+            AnomalyKind::ExternalExecutable { source, .. } if source.is_domain_share() => {
+                Severity::Medium
+            }
+            AnomalyKind::ExternalExecutable { .. } => Severity::High,
+            AnomalyKind::ParentSpoofing {
+                reason: SpoofingReason::ParentNotInLog,
+                ..
+            } => Severity::Low,
+            AnomalyKind::ParentSpoofing { .. } => Severity::High,
+            AnomalyKind::SuspiciousFileDrop {
+                location: DropLocation::StartupFolder,
+                ..
+            } => Severity::Medium,
+            AnomalyKind::SuspiciousFileDrop { .. } => Severity::High,
+            AnomalyKind::ShortLivedProcess { .. } => Severity::Medium,
+            AnomalyKind::DroppedAndExecuted { .. } => Severity::High,
+            AnomalyKind::Masquerading { .. } => Severity::High,
+            AnomalyKind::SuspiciousRegistryValue { .. } => Severity::High,
+            AnomalyKind::SuspiciousInbound {
+                reason: InboundReason::RemoteAdministration,
+                ..
+            } => Severity::High,
+            AnomalyKind::SuspiciousInbound { .. } => Severity::Medium,
+            AnomalyKind::RareExecutable {
+                rarity: Rarity::FirstSeen,
+                ..
+            } => Severity::Medium,
+            AnomalyKind::RareExecutable { .. } => Severity::Low,
+            AnomalyKind::IntegrityEscalation {
+                to: Integrity::System,
+                ..
+            } => Severity::Critical,
+            AnomalyKind::IntegrityEscalation { .. } => Severity::High,
+            AnomalyKind::LateralMovement {
+                service: Some(_), ..
+            } => Severity::Critical,
+            AnomalyKind::LateralMovement { .. } => Severity::High,
+            AnomalyKind::Custom { severity, .. } => *severity,
         }
     }
     pub fn description(&self) -> String {
         match self {
-            Anomaly::UntrustedExecutable { reason, .. } => {
+            AnomalyKind::UntrustedExecutable { reason, .. } => {
                 format!("Untrusted Executable: {reason}")
             }
-            Anomaly::SuspiciousParentChild {
+            AnomalyKind::SuspiciousParentChild {
                 parent,
                 child,
                 reason,
@@ -108,202 +763,3167 @@ impl Anomaly {
             } => {
                 format!("Suspicious Process Chain: {parent} -> {child} ({reason})")
             }
-            Anomaly::DeepProcessTree { depth, .. } => {
+            AnomalyKind::DeepProcessTree { depth, .. } => {
                 format!("Deep Process Nesting: {depth} levels")
             }
-            Anomaly::UnusualPort { port, process, .. } => {
+            AnomalyKind::UnusualPort { port, process, .. } => {
                 format!("Unusual Network Port: {port} used by {process}")
             }
-            Anomaly::EventStorm {
+            AnomalyKind::EventStorm {
                 event_id,
                 count,
                 time_window_seconds,
+                top_processes,
+                ..
             } => {
-                format!("Event Storm: ID {event_id} ({count} events in {time_window_seconds}s)")
+                let description =
+                    format!("Event Storm: ID {event_id} ({count} events in {time_window_seconds}s");
+                if top_processes.is_empty() {
+                    format!("{description})")
+                } else {
+                    let processes: Vec<String> = top_processes
+                        .iter()
+                        .map(|(process, count)| format!("{process} {count}"))
+                        .collect();
+                    format!("{description}, top processes: {})", processes.join(", "))
+                }
             }
-        }
-    }
-    pub fn event(&self) -> &SysmonEvent {
-        match self {
-            Anomaly::UntrustedExecutable { event, .. }
-            | Anomaly::SuspiciousParentChild { event, .. }
-            | Anomaly::DeepProcessTree { event, .. }
-            | Anomaly::UnusualPort { event, .. } => event,
-            Anomaly::EventStorm { .. } => {
-                panic!("EventStorm anomaly does not have a associated event")
+            AnomalyKind::MassFileDeletion {
+                process,
+                deleted,
+                directories,
+                time_window_seconds,
+                ..
+            } => {
+                format!(
+                    "Mass File Deletion: {process} deleted {deleted} files across {directories} directories in {time_window_seconds}s (possible ransomware)"
+                )
             }
-        }
-    }
-}
-
-const DEEP_NESTING_THRESHOLD: usize = 5;
-const UNUSUAL_PORT_THRESHOLD: u16 = 49152;
-const EVENT_STORM_THRESHOLD_COUNT: usize = 50;
-const EVENT_STORM_WINDOW_SECONDS: usize = 10;
-
-pub fn detect_anomalies(events: &[SysmonEvent]) -> Vec<Anomaly> {
-    let mut detector = AnomalyDetector::new();
-    detector.analyze_batch(events)
-}
-struct AnomalyDetector {
-    anomalies: Vec<Anomaly>,
-    /// Maps Parent PID to Vector of Child PID
-    process_chains: HashMap<u64, Vec<u64>>,
-    /// Maps PID to Depth
-    process_depth: HashMap<u64, usize>,
-    /// Maps EventID to Timestamps
-    event_counts: HashMap<u8, Vec<DateTime<Utc>>>,
-}
-impl AnomalyDetector {
-    fn new() -> Self {
-        Self {
-            anomalies: vec![],
-            process_chains: HashMap::new(),
-            process_depth: HashMap::new(),
-            event_counts: HashMap::new(),
-        }
-    }
-    fn analyze_batch(&mut self, events: &[SysmonEvent]) -> Vec<Anomaly> {
-        info!(
-            "Starting batch anomaly detection on {} events",
-            events.len()
-        );
-
-        let mut sorted_events = events.to_vec();
-        sorted_events.sort_by_key(|event| event.system().time_created.system_time.clone());
-        for event in &sorted_events {
-            if let Ok(parsed_time) = event.system().time_created.system_time.parse() {
-                self.event_counts
-                    .entry(event.system().event_id.event_id)
-                    .or_default()
-                    .push(parsed_time);
-            } else {
-                info!(
-                    "Failed to parse timestamp for event {}: '{}'",
-                    event.system().event_id.event_id,
-                    event.system().time_created.system_time
-                );
-                continue;
+            AnomalyKind::SuspiciousPipe {
+                pipe,
+                process,
+                reason,
+                ..
+            } => {
+                format!("Suspicious Named Pipe: {pipe} used by {process} ({reason})")
             }
-            match event {
-                SysmonEvent::ProcessCreate(event) => {
-                    if let Some(anomaly) = check_suspicious_parent_child(event) {
-                        self.anomalies.push(anomaly)
-                    }
-                    self.check_process_depth_batch(event);
-                }
-                SysmonEvent::OutboundNetwork(event) => {
-                    if let Some(anomaly) = check_unusual_port(event) {
-                        self.anomalies.push(anomaly);
-                    }
+            AnomalyKind::SuspiciousCommandLine {
+                process,
+                indicators,
+                score,
+                ..
+            } => {
+                format!(
+                    "Suspicious Command Line: {process} ({}, score {score})",
+                    indicators.join(", ")
+                )
+            }
+            AnomalyKind::Beaconing {
+                process,
+                destination,
+                count,
+                interval_seconds,
+                jitter,
+                ..
+            } => {
+                format!(
+                    "Possible Beaconing: {process} -> {destination} ({count} connections every {interval_seconds:.1}s, jitter {:.0}%)",
+                    jitter * 100.0
+                )
+            }
+            AnomalyKind::CredentialDumping {
+                process,
+                target,
+                granted_access,
+                ..
+            } => {
+                format!(
+                    "Possible Credential Dumping: {process} opened {target} with access {granted_access}"
+                )
+            }
+            AnomalyKind::WmiPersistence {
+                event,
+                consumer_class,
+                name,
+                destination,
+            } => {
+                let user = match event.as_ref() {
+                    SysmonEvent::WmiConsumer(e) => e.event_data.user.as_ref(),
+                    _ => None,
+                };
+                match user {
+                    Some(user) => format!(
+                        "WMI Persistence: {consumer_class} {name} created by {} runs {destination}",
+                        user.user
+                    ),
+                    None => format!(
+                        "WMI Persistence: {consumer_class} {name} created, runs {destination}"
+                    ),
                 }
-                _ => {}
             }
-        }
-        self.check_event_storms_batch();
-        info!(
-            "Finished batch anomaly detection on {} events",
-            events.len()
-        );
-        self.anomalies.clone()
-    }
-    fn check_process_depth_batch(&mut self, event: &ProcessCreateEvent) {
-        let data = &event.event_data;
-        let pid = data.process_id;
-        let parent_pid = data.parent_process_id;
-        let parent_depth = self.process_depth.get(&parent_pid).cloned().unwrap_or(0);
-        let current_depth = parent_depth + 1;
-        self.process_depth.insert(pid, current_depth);
-        self.process_chains.entry(parent_pid).or_default().push(pid);
-        if current_depth > DEEP_NESTING_THRESHOLD {
-            self.anomalies.push(Anomaly::DeepProcessTree {
-                event: SysmonEvent::ProcessCreate(event.clone()),
-                depth: current_depth,
-            });
-        }
-    }
-
-    fn check_event_storms_batch(&mut self) {
-        for (event_id, timestamp) in &self.event_counts {
-            if timestamp.len() < EVENT_STORM_THRESHOLD_COUNT {
-                continue;
+            AnomalyKind::SuspiciousDriver { driver, reason, .. } => {
+                format!("Suspicious Driver Load: {driver} ({reason})")
             }
-            for window in timestamp.windows(EVENT_STORM_WINDOW_SECONDS) {
-                let start_time = window[0];
-                let end_time = window[window.len() - 1];
-                let duration = end_time.signed_duration_since(start_time).num_seconds();
-                if duration <= EVENT_STORM_WINDOW_SECONDS as i64 {
-                    self.anomalies.push(Anomaly::EventStorm {
-                        event_id: *event_id,
-                        count: EVENT_STORM_THRESHOLD_COUNT,
-                        time_window_seconds: duration,
-                    });
-                    break;
+            AnomalyKind::RawDiskAccess {
+                process, device, ..
+            } => {
+                format!("Raw Disk Access: {process} read {device} directly")
+            }
+            AnomalyKind::SysmonTampering { action, .. } => format!("Sysmon Tampering: {action}"),
+            AnomalyKind::IocMatch {
+                process,
+                value,
+                indicator,
+                feed,
+                ..
+            } => {
+                if value == indicator {
+                    format!("Threat Intel Match: {process} -> {value} (feed {feed})")
+                } else {
+                    format!(
+                        "Threat Intel Match: {process} -> {value} matches {indicator} (feed {feed})"
+                    )
                 }
             }
-        }
-    }
-}
-// Individual Anomaly Checks
-/// Check for suspicious parent-child process relationships
-fn check_suspicious_parent_child(event: &ProcessCreateEvent) -> Option<Anomaly> {
-    let parent = &event.event_data.parent_image;
-    let child = &event.event_data.image;
-    let parent_name = parent
-        .image
-        .rsplit('\\')
-        .next()
-        .unwrap_or(parent.image.as_str());
-    let child_name = child.rsplit('\\').next().unwrap_or(child.image.as_str());
-    let parent_lower = parent_name.to_lowercase();
-    let child_lower = child_name.to_lowercase();
-
-    // Here come rules for parent child relationship
-    // Rule: svchost.exe should only be spawned by services.exe
-    if child_lower == "svchost.exe" && parent_lower != "services.exe" {
-        return Some(Anomaly::SuspiciousParentChild {
-            event: SysmonEvent::ProcessCreate(event.clone()),
-            parent: parent_name.to_string(),
-            child: child_name.to_string(),
-            reason: "svchost.exe is spawned by a non-service process".to_string(),
-        });
-    }
-    // Rule: Office apps spawning shells
-    let office_apps = ["winword.exe", "excel.exe", "powerpnt.exe"];
-    let shell_processes = ["powershell.exe", "cmd.exe", "wscript.exe", "cscript.exe"];
-
-    if office_apps.contains(&parent_lower.as_str())
-        && shell_processes.contains(&child_lower.as_str())
-    {
-        return Some(Anomaly::SuspiciousParentChild {
-            event: SysmonEvent::ProcessCreate(event.clone()),
-            parent: parent_name.to_string(),
-            child: child_name.to_string(),
-            reason: "Office application spawned a shell".to_string(),
-        });
-    }
-    None
-}
-/// Checks for unusual port usage in outbound network events.
-fn check_unusual_port(event: &NetworkEvent) -> Option<Anomaly> {
-    let data = &event.event_data;
-    if let (port, image, true) = (data.destination_port, &data.image, data.initiated) {
-        if port >= UNUSUAL_PORT_THRESHOLD {
-            let process = image.rsplit('\\').next().unwrap_or(image).to_string();
-            return Some(Anomaly::UnusualPort {
-                event: SysmonEvent::OutboundNetwork(event.clone()),
-                port,
+            AnomalyKind::UnknownBinary {
                 process,
+                sha256,
+                sensitive,
+                ..
+            } => {
+                let location = if *sensitive {
+                    " in a system directory"
+                } else {
+                    ""
+                };
+                format!("Unknown Binary{location}: {process} (SHA256 {sha256} not known good)")
+            }
+            AnomalyKind::ProcessTampering {
+                process,
+                tampering_type,
+                ..
+            } => format!("Process Tampering: {process} ({tampering_type})"),
+            AnomalyKind::ExecutableStream {
+                process,
+                target,
+                reason,
+                ..
+            } => format!("Executable Alternate Data Stream: {process} wrote {target} ({reason})"),
+            AnomalyKind::DownloadExecuted {
+                process, origin, ..
+            } => match &origin.host_url {
+                Some(url) => format!(
+                    "Downloaded File Executed: {process} ({} zone, from {url})",
+                    origin.zone
+                ),
+                None => format!("Downloaded File Executed: {process} ({} zone)", origin.zone),
+            },
+            AnomalyKind::ObfuscatedScriptBlock {
+                process,
+                indicators,
+                score,
+                ..
+            } => format!(
+                "Obfuscated PowerShell Script Block: {process} ({}, score {score})",
+                indicators.join(", ")
+            ),
+            AnomalyKind::ExternalExecutable {
+                process, source, ..
+            } => format!("External Executable: {process} runs from {source}"),
+            AnomalyKind::ParentSpoofing {
+                process,
+                parent,
+                reason,
+                ..
+            } => format!("Parent Process Spoofing: {process} claims parent {parent} ({reason})"),
+            AnomalyKind::SuspiciousFileDrop {
+                process,
+                target,
+                location,
+                ..
+            } => format!("Suspicious File Drop: {process} wrote {target} ({location})"),
+            AnomalyKind::ShortLivedProcess {
+                process,
+                parent,
+                lifetime_ms,
+                ..
+            } => format!(
+                "Short-Lived Process: {process} started by {parent} exited after {}",
+                lifetime::format_duration(Duration::milliseconds(*lifetime_ms))
+            ),
+            AnomalyKind::DroppedAndExecuted {
+                process,
+                dropper,
+                elapsed_ms,
+                ..
+            } => format!(
+                "Dropped Executable Started: {process} written by {dropper} ran {} later",
+                lifetime::format_duration(Duration::milliseconds(*elapsed_ms))
+            ),
+            AnomalyKind::Masquerading {
+                image, masquerade, ..
+            } => format!("Process Masquerading: {image} ({masquerade})"),
+            AnomalyKind::SuspiciousRegistryValue {
+                process,
+                key,
+                finding,
+                ..
+            } => format!("Suspicious Registry Value: {key} set by {process} ({finding})"),
+            AnomalyKind::SuspiciousInbound {
+                process,
+                port,
+                source,
+                reason,
+                ..
+            } => format!(
+                "Suspicious Inbound Connection: {source} to {process} on port {port} ({reason})"
+            ),
+            AnomalyKind::RareExecutable { image, rarity, .. } => {
+                format!("Rare Executable: {image} ({rarity})")
+            }
+            AnomalyKind::IntegrityEscalation {
+                process,
+                parent,
+                user,
+                from,
+                to,
+                ..
+            } => format!(
+                "Integrity Escalation: {parent} ({from}) started {process} ({to}) as {user}"
+            ),
+            AnomalyKind::LateralMovement {
+                event,
+                source,
+                process,
+                hosts,
+                ports,
+                time_window_seconds,
+                service,
+            } => {
+                let ports = ports
+                    .iter()
+                    .map(u16::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let fan_out = format!(
+                    "{process} on {source} connected to {hosts} internal hosts on ports {ports} \
+                     within {time_window_seconds}s"
+                );
+                match service {
+                    Some(service) => format!(
+                        "Lateral Movement: service {} ({}) created on {} after {fan_out}",
+                        service.name,
+                        service.image_path,
+                        event.system().computer.computer
+                    ),
+                    None => format!("Lateral Movement: {fan_out}"),
+                }
+            }
+            AnomalyKind::Custom { description, .. } => description.clone(),
+        }
+    }
+    /// Short identifier of the rule that produced this anomaly
+    pub fn rule_name(&self) -> &'static str {
+        match self {
+            AnomalyKind::UntrustedExecutable { .. } => "UntrustedExecutable",
+            AnomalyKind::SuspiciousParentChild { .. } => "SuspiciousParentChild",
+            AnomalyKind::DeepProcessTree { .. } => "DeepProcessTree",
+            AnomalyKind::UnusualPort { .. } => "UnusualPort",
+            AnomalyKind::EventStorm { .. } => "EventStorm",
+            AnomalyKind::MassFileDeletion { .. } => "MassFileDeletion",
+            AnomalyKind::SuspiciousPipe { .. } => "SuspiciousPipe",
+            AnomalyKind::SuspiciousCommandLine { .. } => "SuspiciousCommandLine",
+            AnomalyKind::Beaconing { .. } => "Beaconing",
+            AnomalyKind::CredentialDumping { .. } => "CredentialDumping",
+            AnomalyKind::IocMatch { .. } => "IocMatch",
+            AnomalyKind::WmiPersistence { .. } => "WmiPersistence",
+            AnomalyKind::SuspiciousDriver { .. } => "SuspiciousDriver",
+            AnomalyKind::RawDiskAccess { .. } => "RawDiskAccess",
+            AnomalyKind::SysmonTampering { .. } => "SysmonTampering",
+            AnomalyKind::UnknownBinary { .. } => "UnknownBinary",
+            AnomalyKind::ProcessTampering { .. } => "ProcessTampering",
+            AnomalyKind::ExecutableStream { .. } => "ExecutableStream",
+            AnomalyKind::DownloadExecuted { .. } => "DownloadExecuted",
+            AnomalyKind::ObfuscatedScriptBlock { .. } => "ObfuscatedScriptBlock",
+            AnomalyKind::ExternalExecutable { .. } => "ExternalExecutable",
+            AnomalyKind::ParentSpoofing { .. } => "ParentSpoofing",
+            AnomalyKind::SuspiciousFileDrop { .. } => "SuspiciousFileDrop",
+            AnomalyKind::ShortLivedProcess { .. } => "ShortLivedProcess",
+            AnomalyKind::DroppedAndExecuted { .. } => "DroppedAndExecuted",
+            AnomalyKind::Masquerading { .. } => "Masquerading",
+            AnomalyKind::SuspiciousRegistryValue { .. } => "SuspiciousRegistryValue",
+            AnomalyKind::SuspiciousInbound { .. } => "SuspiciousInbound",
+            AnomalyKind::RareExecutable { .. } => "RareExecutable",
+            AnomalyKind::IntegrityEscalation { .. } => "IntegrityEscalation",
+            AnomalyKind::LateralMovement { .. } => "LateralMovement",
+            AnomalyKind::Custom { rule, .. } => rule.name,
+        }
+    }
+    /// Stable ID of the rule that produced this anomaly (e.g. SLA-001)
+    pub fn rule_id(&self) -> &'static str {
+        if let AnomalyKind::Custom { rule, .. } = self {
+            return rule.id;
+        }
+        let name = self.rule_name();
+        RULE_IDS
+            .iter()
+            .find(|(_, rule)| *rule == name)
+            .map_or(name, |(id, _)| id)
+    }
+    /// MITRE ATT&CK techniques the finding may indicate
+    pub fn attack_techniques(&self) -> &'static [&'static str] {
+        match self {
+            AnomalyKind::UntrustedExecutable { .. } => &["T1204.002", "T1553.002"],
+            AnomalyKind::SuspiciousParentChild { .. } => &["T1059"],
+            AnomalyKind::DeepProcessTree { .. } => &[],
+            AnomalyKind::UnusualPort { .. } => &["T1571"],
+            AnomalyKind::EventStorm { .. } => &[],
+            AnomalyKind::MassFileDeletion { .. } => &["T1485", "T1486"],
+            AnomalyKind::SuspiciousPipe { .. } => &["T1559", "T1570"],
+            AnomalyKind::SuspiciousCommandLine { .. } => &["T1059", "T1027"],
+            AnomalyKind::Beaconing { .. } => &["T1071"],
+            AnomalyKind::CredentialDumping { .. } => &["T1003.001"],
+            AnomalyKind::IocMatch { .. } => &["T1071"],
+            AnomalyKind::WmiPersistence { .. } => &["T1546.003"],
+            AnomalyKind::SuspiciousDriver { .. } => &["T1014", "T1068"],
+            AnomalyKind::RawDiskAccess { .. } => &["T1006"],
+            AnomalyKind::SysmonTampering { .. } => &["T1562.001"],
+            AnomalyKind::UnknownBinary { .. } => &["T1036.005"],
+            AnomalyKind::ProcessTampering { .. } => &["T1055.012"],
+            AnomalyKind::ExecutableStream { .. } => &["T1564.004"],
+            AnomalyKind::DownloadExecuted { .. } => &["T1204.002"],
+            AnomalyKind::ObfuscatedScriptBlock { .. } => &["T1059.001", "T1027"],
+            AnomalyKind::ExternalExecutable {
+                source: ExecutableSource::NetworkShare { .. },
+                ..
+            } => &["T1021.002", "T1570"],
+            AnomalyKind::ExternalExecutable { .. } => &["T1091"],
+            AnomalyKind::ParentSpoofing { .. } => &["T1134.004"],
+            AnomalyKind::SuspiciousFileDrop { location, .. } => match location {
+                DropLocation::StartupFolder => &["T1547.001"],
+                DropLocation::ScheduledTasks => &["T1053.005"],
+                DropLocation::WebRoot => &["T1505.003"],
+            },
+            AnomalyKind::ShortLivedProcess { parent, .. }
+                if parent.eq_ignore_ascii_case("services.exe") =>
+            {
+                &["T1569.002"]
+            }
+            AnomalyKind::ShortLivedProcess { .. } => &["T1204.002"],
+            AnomalyKind::DroppedAndExecuted { .. } => &["T1105", "T1204.002"],
+            AnomalyKind::Masquerading { .. } => &["T1036.005"],
+            AnomalyKind::SuspiciousRegistryValue { finding, .. } => match finding {
+                RegistryFinding::EncodedRunValue => &["T1547.001", "T1027"],
+                RegistryFinding::ScriptComServer(_) => &["T1546.015"],
+                RegistryFinding::SecurityDisabled(setting) => setting.techniques,
+            },
+            AnomalyKind::SuspiciousInbound {
+                reason: InboundReason::UnusualListeningPort,
+                ..
+            } => &["T1571"],
+            AnomalyKind::SuspiciousInbound { port: 3389, .. } => &["T1021.001"],
+            AnomalyKind::SuspiciousInbound { port: 445, .. } => &["T1021.002"],
+            AnomalyKind::SuspiciousInbound { .. } => &["T1021"],
+            AnomalyKind::RareExecutable { .. } => &[],
+            AnomalyKind::IntegrityEscalation { .. } => &["T1548.002", "T1068"],
+            AnomalyKind::LateralMovement {
+                service: Some(_), ..
+            } => &["T1021.002", "T1021.006", "T1569.002"],
+            AnomalyKind::LateralMovement { .. } => &["T1021.002", "T1021.006"],
+            AnomalyKind::Custom { rule, .. } => rule.attack_techniques,
+        }
+    }
+    /// ATT&CK tactics of the techniques in [`AnomalyKind::attack_techniques`]
+    pub fn tactics(&self) -> &'static [Tactic] {
+        use Tactic::*;
+        match self {
+            AnomalyKind::UntrustedExecutable { .. } => &[Execution, DefenseEvasion],
+            AnomalyKind::SuspiciousParentChild { .. } => &[Execution],
+            AnomalyKind::DeepProcessTree { .. } => &[],
+            AnomalyKind::UnusualPort { .. } => &[CommandAndControl],
+            AnomalyKind::EventStorm { .. } => &[],
+            AnomalyKind::MassFileDeletion { .. } => &[Impact],
+            AnomalyKind::SuspiciousPipe { .. } => &[Execution, LateralMovement],
+            AnomalyKind::SuspiciousCommandLine { .. } => &[Execution, DefenseEvasion],
+            AnomalyKind::Beaconing { .. } => &[CommandAndControl],
+            AnomalyKind::CredentialDumping { .. } => &[CredentialAccess],
+            AnomalyKind::IocMatch { .. } => &[CommandAndControl],
+            AnomalyKind::WmiPersistence { .. } => &[Persistence, PrivilegeEscalation],
+            AnomalyKind::SuspiciousDriver { .. } => &[DefenseEvasion, PrivilegeEscalation],
+            AnomalyKind::RawDiskAccess { .. } => &[DefenseEvasion],
+            AnomalyKind::SysmonTampering { .. } => &[DefenseEvasion],
+            AnomalyKind::UnknownBinary { .. } => &[DefenseEvasion],
+            AnomalyKind::ProcessTampering { .. } => &[DefenseEvasion, PrivilegeEscalation],
+            AnomalyKind::ExecutableStream { .. } => &[DefenseEvasion],
+            AnomalyKind::DownloadExecuted { .. } => &[Execution],
+            AnomalyKind::ObfuscatedScriptBlock { .. } => &[Execution, DefenseEvasion],
+            AnomalyKind::ExternalExecutable {
+                source: ExecutableSource::NetworkShare { .. },
+                ..
+            } => &[LateralMovement],
+            AnomalyKind::ExternalExecutable { .. } => &[InitialAccess, LateralMovement],
+            AnomalyKind::ParentSpoofing { .. } => &[DefenseEvasion, PrivilegeEscalation],
+            AnomalyKind::SuspiciousFileDrop {
+                location: DropLocation::WebRoot,
+                ..
+            } => &[Persistence],
+            AnomalyKind::SuspiciousFileDrop { .. } => &[Persistence, PrivilegeEscalation],
+            AnomalyKind::ShortLivedProcess { .. } => &[Execution],
+            AnomalyKind::DroppedAndExecuted { .. } => &[CommandAndControl, Execution],
+            AnomalyKind::Masquerading { .. } => &[DefenseEvasion],
+            AnomalyKind::SuspiciousRegistryValue { finding, .. } => match finding {
+                RegistryFinding::EncodedRunValue => &[Persistence, DefenseEvasion],
+                RegistryFinding::ScriptComServer(_) => &[Persistence, PrivilegeEscalation],
+                RegistryFinding::SecurityDisabled(_) => &[DefenseEvasion],
+            },
+            AnomalyKind::SuspiciousInbound {
+                reason: InboundReason::UnusualListeningPort,
+                ..
+            } => &[CommandAndControl],
+            AnomalyKind::SuspiciousInbound { .. } => &[LateralMovement],
+            AnomalyKind::RareExecutable { .. } => &[],
+            AnomalyKind::IntegrityEscalation { .. } => &[PrivilegeEscalation, DefenseEvasion],
+            AnomalyKind::LateralMovement {
+                service: Some(_), ..
+            } => &[LateralMovement, Execution],
+            AnomalyKind::LateralMovement { .. } => &[LateralMovement],
+            AnomalyKind::Custom { rule, .. } => rule.tactics,
+        }
+    }
+    /// Key used to group identical findings (rule + process + reason)
+    pub fn dedup_key(&self) -> String {
+        let rule = self.rule_name();
+        match self {
+            AnomalyKind::UntrustedExecutable { event, reason } => {
+                format!("{rule}|{}|{reason}", process_name(event))
+            }
+            AnomalyKind::SuspiciousParentChild {
+                parent,
+                child,
+                reason,
+                ..
+            } => format!("{rule}|{parent}->{child}|{reason}").to_lowercase(),
+            AnomalyKind::DeepProcessTree { event, .. } => {
+                format!("{rule}|{}", process_name(event))
+            }
+            AnomalyKind::UnusualPort { port, process, .. } => {
+                format!("{rule}|{process}|{port}").to_lowercase()
+            }
+            AnomalyKind::EventStorm { event_id, .. } => format!("{rule}|{event_id}"),
+            AnomalyKind::MassFileDeletion { process, .. } => {
+                format!("{rule}|{process}").to_lowercase()
+            }
+            AnomalyKind::SuspiciousPipe {
+                process, reason, ..
+            } => format!("{rule}|{process}|{reason}").to_lowercase(),
+            AnomalyKind::SuspiciousCommandLine {
+                process,
+                indicators,
+                ..
+            } => format!("{rule}|{process}|{}", indicators.join(",")).to_lowercase(),
+            AnomalyKind::Beaconing {
+                process,
+                destination,
+                ..
+            } => format!("{rule}|{process}|{destination}").to_lowercase(),
+            AnomalyKind::CredentialDumping {
+                process,
+                granted_access,
+                ..
+            } => format!("{rule}|{process}|{granted_access}").to_lowercase(),
+            AnomalyKind::IocMatch {
+                process,
+                value,
+                feed,
+                ..
+            } => format!("{rule}|{feed}|{process}|{value}").to_lowercase(),
+            AnomalyKind::WmiPersistence {
+                name, destination, ..
+            } => format!("{rule}|{name}|{destination}").to_lowercase(),
+            AnomalyKind::SuspiciousDriver { driver, reason, .. } => {
+                format!("{rule}|{driver}|{reason}").to_lowercase()
+            }
+            AnomalyKind::RawDiskAccess {
+                process, device, ..
+            } => format!("{rule}|{process}|{device}").to_lowercase(),
+            AnomalyKind::SysmonTampering { event, action } => {
+                format!("{rule}|{}|{action}", event.system().computer.computer).to_lowercase()
+            }
+            AnomalyKind::UnknownBinary {
+                process, sha256, ..
+            } => format!("{rule}|{process}|{sha256}").to_lowercase(),
+            AnomalyKind::ProcessTampering {
+                process,
+                tampering_type,
+                ..
+            } => format!("{rule}|{process}|{tampering_type}").to_lowercase(),
+            AnomalyKind::ExecutableStream {
+                process, target, ..
+            } => format!("{rule}|{process}|{target}").to_lowercase(),
+            AnomalyKind::DownloadExecuted {
+                process, origin, ..
+            } => format!(
+                "{rule}|{process}|{}",
+                origin.host_url.as_deref().unwrap_or_default()
+            )
+            .to_lowercase(),
+            AnomalyKind::ObfuscatedScriptBlock {
+                process,
+                script_block_id,
+                ..
+            } => format!("{rule}|{process}|{script_block_id}").to_lowercase(),
+            AnomalyKind::ExternalExecutable {
+                process, source, ..
+            } => format!("{rule}|{process}|{source}").to_lowercase(),
+            AnomalyKind::ParentSpoofing {
+                process,
+                parent,
+                reason,
+                ..
+            } => format!("{rule}|{process}|{parent}|{reason}").to_lowercase(),
+            AnomalyKind::SuspiciousFileDrop {
+                process, target, ..
+            } => format!("{rule}|{process}|{target}").to_lowercase(),
+            AnomalyKind::ShortLivedProcess {
+                process, parent, ..
+            } => format!("{rule}|{process}|{parent}").to_lowercase(),
+            AnomalyKind::DroppedAndExecuted {
+                process, dropper, ..
+            } => format!("{rule}|{process}|{dropper}").to_lowercase(),
+            AnomalyKind::Masquerading { image, .. } => format!("{rule}|{image}").to_lowercase(),
+            AnomalyKind::SuspiciousRegistryValue {
+                process,
+                key,
+                finding,
+                ..
+            } => format!("{rule}|{process}|{key}|{finding}").to_lowercase(),
+            AnomalyKind::SuspiciousInbound {
+                process,
+                port,
+                source,
+                reason,
+                ..
+            } => format!("{rule}|{process}|{port}|{source}|{reason}").to_lowercase(),
+            AnomalyKind::RareExecutable { image, .. } => format!("{rule}|{image}").to_lowercase(),
+            AnomalyKind::IntegrityEscalation {
+                process,
+                parent,
+                user,
+                ..
+            } => format!("{rule}|{user}|{parent}->{process}").to_lowercase(),
+            AnomalyKind::LateralMovement {
+                source,
+                process,
+                service,
+                ..
+            } => match service {
+                Some(service) => format!("{rule}|{source}|{process}|{}", service.name),
+                None => format!("{rule}|{source}|{process}"),
+            }
+            .to_lowercase(),
+            AnomalyKind::Custom {
+                event, description, ..
+            } => {
+                let process = event.as_deref().map(process_name).unwrap_or_default();
+                format!("{rule}|{process}|{description}").to_lowercase()
+            }
+        }
+    }
+    /// Timestamp of the associated event, if any; the start of the burst for event storms
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            AnomalyKind::EventStorm { start, .. } => *start,
+            _ => self
+                .event()
+                .and_then(|event| event.system().time_created.time),
+        }
+    }
+    /// Time of the last event the finding covers
+    pub fn end_timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            AnomalyKind::EventStorm { end, .. } => *end,
+            _ => self.timestamp(),
+        }
+    }
+    /// Event that triggered the anomaly (aggregate anomalies such as EventStorm have none)
+    pub fn event(&self) -> Option<&SysmonEvent> {
+        match self {
+            AnomalyKind::UntrustedExecutable { event, .. }
+            | AnomalyKind::SuspiciousParentChild { event, .. }
+            | AnomalyKind::DeepProcessTree { event, .. }
+            | AnomalyKind::UnusualPort { event, .. }
+            | AnomalyKind::MassFileDeletion { event, .. }
+            | AnomalyKind::SuspiciousPipe { event, .. }
+            | AnomalyKind::SuspiciousCommandLine { event, .. }
+            | AnomalyKind::Beaconing { event, .. }
+            | AnomalyKind::CredentialDumping { event, .. }
+            | AnomalyKind::WmiPersistence { event, .. }
+            | AnomalyKind::SuspiciousDriver { event, .. }
+            | AnomalyKind::RawDiskAccess { event, .. }
+            | AnomalyKind::SysmonTampering { event, .. }
+            | AnomalyKind::IocMatch { event, .. }
+            | AnomalyKind::UnknownBinary { event, .. }
+            | AnomalyKind::ProcessTampering { event, .. }
+            | AnomalyKind::ExecutableStream { event, .. }
+            | AnomalyKind::DownloadExecuted { event, .. }
+            | AnomalyKind::ObfuscatedScriptBlock { event, .. }
+            | AnomalyKind::ExternalExecutable { event, .. }
+            | AnomalyKind::ParentSpoofing { event, .. }
+            | AnomalyKind::SuspiciousFileDrop { event, .. }
+            | AnomalyKind::ShortLivedProcess { event, .. }
+            | AnomalyKind::DroppedAndExecuted { event, .. }
+            | AnomalyKind::Masquerading { event, .. }
+            | AnomalyKind::SuspiciousRegistryValue { event, .. }
+            | AnomalyKind::SuspiciousInbound { event, .. }
+            | AnomalyKind::RareExecutable { event, .. }
+            | AnomalyKind::IntegrityEscalation { event, .. }
+            | AnomalyKind::LateralMovement { event, .. } => Some(event.as_ref()),
+            AnomalyKind::Custom { event, .. } => event.as_deref(),
+            AnomalyKind::EventStorm { .. } => None,
+        }
+    }
+}
+
+/// Finding of a rule with its severity and asset, resolved once against the detection config
+/// that produced it
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    kind: AnomalyKind,
+    severity: Severity,
+    asset: Option<Arc<Asset>>,
+}
+impl Anomaly {
+    /// Finding reported with the severity the config sets for its rule, if any, adjusted to the
+    /// criticality of its computer in the asset inventory
+    pub fn new(kind: AnomalyKind, config: &DetectionConfig) -> Self {
+        let mut severity = config
+            .severity_overrides
+            .get(&kind)
+            .unwrap_or_else(|| kind.default_severity());
+        let asset = config
+            .assets
+            .as_ref()
+            .zip(kind.event())
+            .and_then(|(assets, event)| assets.get(&event.system().computer.computer).cloned());
+        if let Some(criticality) = asset.as_ref().and_then(|asset| asset.criticality) {
+            severity = criticality.adjust(severity);
+        }
+        Self {
+            kind,
+            severity,
+            asset,
+        }
+    }
+    pub fn kind(&self) -> &AnomalyKind {
+        &self.kind
+    }
+    pub fn into_kind(self) -> AnomalyKind {
+        self.kind
+    }
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+    /// Inventory entry of the computer of the finding
+    pub fn asset(&self) -> Option<&Asset> {
+        self.asset.as_deref()
+    }
+}
+/// Finding with the severity its rule assigns
+impl From<AnomalyKind> for Anomaly {
+    fn from(kind: AnomalyKind) -> Self {
+        let severity = kind.default_severity();
+        Self {
+            kind,
+            severity,
+            asset: None,
+        }
+    }
+}
+impl std::ops::Deref for Anomaly {
+    type Target = AnomalyKind;
+    fn deref(&self) -> &AnomalyKind {
+        &self.kind
+    }
+}
+
+/// Group of identical anomalies reported once
+#[derive(Debug, Clone)]
+pub struct AggregatedAnomaly {
+    /// First occurrence of the anomaly
+    pub anomaly: Anomaly,
+    pub count: usize,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Group identical anomalies by rule, process and reason, keeping the order of first occurrence
+pub fn aggregate_anomalies(anomalies: &[Anomaly]) -> Vec<AggregatedAnomaly> {
+    let mut groups: Vec<AggregatedAnomaly> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for anomaly in anomalies {
+        let (timestamp, end) = (anomaly.timestamp(), anomaly.end_timestamp());
+        match index.get(&anomaly.dedup_key()) {
+            Some(&i) => {
+                let group = &mut groups[i];
+                group.count += 1;
+                if let Some(ts) = timestamp
+                    && group.first_seen.is_none_or(|first| ts < first)
+                {
+                    group.first_seen = Some(ts);
+                }
+                if let Some(ts) = end
+                    && group.last_seen.is_none_or(|last| ts > last)
+                {
+                    group.last_seen = Some(ts);
+                }
+            }
+            None => {
+                index.insert(anomaly.dedup_key(), groups.len());
+                groups.push(AggregatedAnomaly {
+                    anomaly: anomaly.clone(),
+                    count: 1,
+                    first_seen: timestamp,
+                    last_seen: end,
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// Anomalies found on one computer and the resulting risk score
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostRisk {
+    pub computer: String,
+    /// Distinct findings by severity, from Low to Critical
+    pub findings: [usize; 4],
+    /// All findings, including repeated ones
+    pub total: usize,
+    pub score: u32,
+}
+impl HostRisk {
+    /// Severity of the worst finding
+    pub fn max_severity(&self) -> Severity {
+        [
+            Severity::Critical,
+            Severity::High,
+            Severity::Medium,
+            Severity::Low,
+        ]
+        .into_iter()
+        .find(|&severity| self.findings[severity as usize] > 0)
+        .unwrap_or(Severity::Low)
+    }
+}
+/// Score of one distinct finding; repeats of the same finding don't add to the risk
+pub(crate) fn risk_weight(severity: Severity) -> u32 {
+    match severity {
+        Severity::Low => 1,
+        Severity::Medium => 3,
+        Severity::High => 10,
+        Severity::Critical => 25,
+    }
+}
+/// Group anomalies by the Computer of their event and rank the hosts by risk score, highest first.
+/// Aggregate findings without an event (EventStorm) are not attributed to any host.
+pub fn host_risk_summary(anomalies: &[Anomaly]) -> Vec<HostRisk> {
+    let mut hosts: Vec<HostRisk> = Vec::new();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    for anomaly in anomalies {
+        let Some(event) = anomaly.event() else {
+            continue;
+        };
+        let computer = &event.system().computer.computer;
+        let index = match hosts.iter().position(|host| host.computer == *computer) {
+            Some(index) => index,
+            None => {
+                hosts.push(HostRisk {
+                    computer: computer.clone(),
+                    findings: [0; 4],
+                    total: 0,
+                    score: 0,
+                });
+                hosts.len() - 1
+            }
+        };
+        let host = &mut hosts[index];
+        host.total += 1;
+        if seen.insert((computer.clone(), anomaly.dedup_key())) {
+            host.findings[anomaly.severity() as usize] += 1;
+            host.score += risk_weight(anomaly.severity());
+        }
+    }
+    hosts.sort_by(|a, b| b.score.cmp(&a.score).then(b.total.cmp(&a.total)));
+    hosts
+}
+
+fn process_name(event: &SysmonEvent) -> String {
+    let image = match event {
+        SysmonEvent::ProcessCreate(e) => &e.event_data.image,
+        SysmonEvent::ProcessTerminate(e) => &e.event_data.image,
+        SysmonEvent::FileCreate(e) => &e.event_data.image,
+        SysmonEvent::FileStreamCreate(e) => &e.event_data.image,
+        SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => &e.event_data.image,
+        SysmonEvent::FileDelete(e)
+        | SysmonEvent::FileBlock(e)
+        | SysmonEvent::FileExecutableDetected(e) => &e.event_data.image,
+        SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => &e.event_data.image,
+        SysmonEvent::RegistrySetValue(e) => &e.event_data.image,
+        SysmonEvent::DnsQuery(e) => &e.event_data.image,
+        SysmonEvent::ProcessAccess(e) => &e.event_data.image,
+        SysmonEvent::WmiFilter(e) | SysmonEvent::WmiConsumer(e) | SysmonEvent::WmiBinding(e) => {
+            &e.event_data.image
+        }
+        SysmonEvent::ServiceStateChange(e) | SysmonEvent::ConfigurationChange(e) => {
+            &e.event_data.image
+        }
+        SysmonEvent::DriverLoad(e) => &e.event_data.image,
+        SysmonEvent::RawAccessRead(e) => &e.event_data.image,
+        SysmonEvent::ClipboardChange(e) => &e.event_data.image,
+        SysmonEvent::ProcessTampering(e) => &e.event_data.image,
+        SysmonEvent::Logon(e) => &e.event_data.image,
+        SysmonEvent::ScriptBlock(e) => &e.event_data.image,
+    };
+    image.rsplit('\\').next().unwrap_or(image).to_lowercase()
+}
+
+const DEEP_NESTING_THRESHOLD: usize = 5;
+/// Access masks of Mimikatz (0x1010, 0x1410) and full access (0x1FFFFF) used by dumping tools
+const CREDENTIAL_DUMP_ACCESS_MASKS: &[u32] = &[0x1010, 0x1410, 0x1F_FFFF];
+/// System processes that open lsass.exe with such rights during normal operation
+const TRUSTED_LSASS_ACCESSORS: &[&str] = &[
+    r"c:\windows\system32\csrss.exe",
+    r"c:\windows\system32\lsm.exe",
+    r"c:\windows\system32\services.exe",
+    r"c:\windows\system32\smss.exe",
+    r"c:\windows\system32\svchost.exe",
+    r"c:\windows\system32\wbem\wmiprvse.exe",
+    r"c:\windows\system32\wininit.exe",
+];
+/// Directories of Windows Defender binaries, which scan lsass.exe memory and read volumes
+const DEFENDER_DIRECTORIES: &[&str] = &[
+    r"c:\programdata\microsoft\windows defender\platform\",
+    r"c:\program files\windows defender\",
+];
+/// Sysmon `Type` of WMI consumers that execute code, and their WMI classes
+const WMI_EXECUTING_CONSUMERS: &[(&str, &str)] = &[
+    ("Command Line", "CommandLineEventConsumer"),
+    ("Script", "ActiveScriptEventConsumer"),
+];
+/// Processes reading volumes directly for backups, defragmentation, indexing or disk checks,
+/// with the Windows Defender binaries
+const RAW_DISK_READERS: &[&str] = &[
+    "system",
+    r"c:\windows\system32\vssvc.exe",
+    r"c:\windows\system32\wbengine.exe",
+    r"c:\windows\system32\sdclt.exe",
+    r"c:\windows\system32\defrag.exe",
+    r"c:\windows\system32\chkdsk.exe",
+    r"c:\windows\system32\autochk.exe",
+    r"c:\windows\system32\svchost.exe",
+    r"c:\windows\system32\searchindexer.exe",
+];
+/// Default events of one ID within the storm window that make an event storm
+pub const DEFAULT_EVENT_STORM_THRESHOLD: usize = 50;
+/// Default time within which the threshold of events makes an event storm
+pub const DEFAULT_EVENT_STORM_WINDOW: &str = "10s";
+/// Processes named in event storm findings
+pub const EVENT_STORM_TOP_PROCESSES: usize = 3;
+const MASS_DELETION_THRESHOLD_COUNT: usize = 50;
+const MASS_DELETION_MIN_DIRECTORIES: usize = 5;
+const MASS_DELETION_WINDOW_SECONDS: i64 = 60;
+/// Pipe names (lowercase, `*` wildcard) used by lateral movement tools and C2 frameworks
+const SUSPICIOUS_PIPES: &[(&str, &str)] = &[
+    (r"\psexesvc*", "PsExec service pipe"),
+    (r"\paexec*", "PAExec service pipe"),
+    (r"\csexecsvc*", "CSExec service pipe"),
+    (r"\remcom*", "RemCom service pipe"),
+    (r"\msse-*-server", "Cobalt Strike default pipe"),
+    (r"\postex_*", "Cobalt Strike post-exploitation pipe"),
+    (r"\status_*", "Cobalt Strike SMB beacon pipe"),
+    (r"\msagent_*", "Cobalt Strike SMB beacon pipe"),
+];
+
+/// Path fragments (lowercase) of user-writable locations executables rarely run from
+const UNTRUSTED_LOCATIONS: &[(&str, &str)] = &[
+    (r"\appdata\local\temp\", "runs from a temporary directory"),
+    (r"\windows\temp\", "runs from a temporary directory"),
+    (r"\appdata\roaming\", "runs from AppData"),
+    (r"\downloads\", "runs from the Downloads folder"),
+    (r"\$recycle.bin\", "runs from the Recycle Bin"),
+];
+
+/// Directories where an unknown binary is more likely a planted or replaced system file
+const SENSITIVE_LOCATIONS: &[&str] = &[
+    r"\windows\system32\",
+    r"\windows\syswow64\",
+    r"\program files\",
+    r"\program files (x86)\",
+];
+
+const BEACON_MIN_INTERVAL_SECONDS: f64 = 1.0;
+
+pub fn detect_anomalies(events: &[SysmonEvent]) -> Vec<Anomaly> {
+    detect_anomalies_with_config(events, &DetectionConfig::default())
+}
+/// Detect anomalies using custom rule thresholds
+pub fn detect_anomalies_with_config(
+    events: &[SysmonEvent],
+    config: &DetectionConfig,
+) -> Vec<Anomaly> {
+    let mut detector = AnomalyDetector::new(config.clone());
+    detector.analyze_batch(events)
+}
+/// Detect anomalies and measure the evaluation time and findings of each rule
+pub fn detect_anomalies_profiled(
+    events: &[SysmonEvent],
+    config: &DetectionConfig,
+) -> (Vec<Anomaly>, RuleProfile) {
+    let mut detector = AnomalyDetector::new(config.clone());
+    detector.profile = Some(RuleProfile::default());
+    let anomalies = detector.analyze_batch(events);
+    (anomalies, detector.profile.unwrap_or_default())
+}
+/// Detect anomalies in events read one at a time (e.g. straight from a parser) without keeping
+/// them all in memory. Events are sorted within chunks of [`DETECTION_CHUNK_SIZE`], so the input
+/// only needs to be roughly in time order, as records of an EVTX file are.
+pub fn detect_anomalies_streaming(
+    events: impl IntoIterator<Item = SysmonEvent>,
+    config: &DetectionConfig,
+) -> Vec<Anomaly> {
+    let mut detector = AnomalyDetector::new(config.clone());
+    let mut chunk = Vec::with_capacity(DETECTION_CHUNK_SIZE);
+    let mut blocks = Vec::new();
+    let mut total = 0;
+    for event in events {
+        if matches!(event, SysmonEvent::ScriptBlock(_)) {
+            blocks.push(event);
+            continue;
+        }
+        chunk.push(event);
+        if chunk.len() == DETECTION_CHUNK_SIZE {
+            total += chunk.len();
+            detector.analyze_chunk(&timed_events(&chunk));
+            chunk.clear();
+        }
+    }
+    total += chunk.len() + blocks.len();
+    detector.analyze_chunk(&timed_events(&chunk));
+    detector.analyze_script_blocks(&timed_events(&blocks));
+    info!("Finished streaming anomaly detection on {} events", total);
+    detector.finish()
+}
+/// Anomaly detection fed with events while the log is still being parsed. Events are analyzed in
+/// time order once [`DETECTION_CHUNK_SIZE`] newer ones have arrived, so the findings are those of
+/// [`detect_anomalies_with_config`] on all events. An event older than one already analyzed makes
+/// [`IncrementalDetector::finish`] analyze everything again; script blocks, which may arrive last,
+/// are analyzed at the end in any case.
+pub struct IncrementalDetector {
+    detector: AnomalyDetector,
+    /// Time, position in the log and index in the events of those with a valid time
+    timed: Vec<(DateTime<Utc>, usize, usize)>,
+    /// Script blocks, analyzed once all other events were, as they may arrive late
+    blocks: Vec<(DateTime<Utc>, usize, usize)>,
+    /// Entries of `timed` already analyzed, the first ones once sorted
+    analyzed: usize,
+    /// An event arrived before one already analyzed
+    out_of_order: bool,
+    /// Events analyzed at once, [`DETECTION_CHUNK_SIZE`] outside of tests
+    chunk_size: usize,
+}
+impl IncrementalDetector {
+    /// Detector collecting a [`RuleProfile`] when `profile` is set
+    pub fn new(config: &DetectionConfig, profile: bool) -> Self {
+        let mut detector = AnomalyDetector::new(config.clone());
+        detector.profile = profile.then(RuleProfile::default);
+        Self {
+            detector,
+            timed: Vec::new(),
+            blocks: Vec::new(),
+            analyzed: 0,
+            out_of_order: false,
+            chunk_size: DETECTION_CHUNK_SIZE,
+        }
+    }
+    /// Take `events[index]` into account. `position` orders events of the same time as the log
+    /// does, it is usually `index` unless events are collected out of order. Analyzes the oldest
+    /// pending events once enough have arrived.
+    pub fn push(&mut self, events: &[SysmonEvent], index: usize, position: usize) {
+        let system = events[index].system();
+        let Some(time) = system.time_created.time else {
+            info!(
+                "Failed to parse timestamp for event {}: '{}'",
+                system.event_id.event_id, system.time_created.system_time
+            );
+            return;
+        };
+        if matches!(events[index], SysmonEvent::ScriptBlock(_)) {
+            self.blocks.push((time, position, index));
+            return;
+        }
+        let watermark = self.analyzed.checked_sub(1).map(|last| {
+            let (time, position, _) = self.timed[last];
+            (time, position)
+        });
+        if watermark.is_some_and(|watermark| (time, position) < watermark) {
+            self.out_of_order = true;
+        }
+        self.timed.push((time, position, index));
+        if !self.out_of_order && self.timed.len() - self.analyzed >= 2 * self.chunk_size {
+            self.timed[self.analyzed..].sort_unstable();
+            self.analyze_next(events);
+        }
+    }
+    /// Analyze the remaining events and report the findings and, when profiling, the rule profile
+    pub fn finish(mut self, events: &[SysmonEvent]) -> (Vec<Anomaly>, Option<RuleProfile>) {
+        if self.out_of_order {
+            info!("Events arrived out of time order, analyzing them again");
+            self.detector = AnomalyDetector {
+                profile: self
+                    .detector
+                    .profile
+                    .as_ref()
+                    .map(|_| RuleProfile::default()),
+                ..AnomalyDetector::new(self.detector.config.clone())
+            };
+            self.analyzed = 0;
+        }
+        self.timed[self.analyzed..].sort_unstable();
+        while self.analyzed < self.timed.len() {
+            self.analyze_next(events);
+        }
+        self.blocks.sort_unstable();
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|(time, _, index)| (*time, &events[*index]))
+            .collect();
+        self.detector.analyze_script_blocks(&blocks);
+        info!(
+            "Finished incremental anomaly detection on {} events",
+            self.timed.len() + self.blocks.len()
+        );
+        let anomalies = self.detector.finish();
+        (anomalies, self.detector.profile.take())
+    }
+    /// Analyze the next chunk of sorted pending events
+    fn analyze_next(&mut self, events: &[SysmonEvent]) {
+        let end = (self.analyzed + self.chunk_size).min(self.timed.len());
+        let chunk: Vec<_> = self.timed[self.analyzed..end]
+            .iter()
+            .map(|(time, _, index)| (*time, &events[*index]))
+            .collect();
+        self.detector.analyze_chunk(&chunk);
+        self.analyzed = end;
+    }
+}
+/// Events analyzed between two prunings of the detector state
+pub const DETECTION_CHUNK_SIZE: usize = 50_000;
+/// Process depths, connection series and processes of each host not updated for this long are
+/// forgotten
+const STATE_RETENTION_HOURS: i64 = 24;
+
+/// Events with their parsed creation time, sorted by time; events without a valid time are skipped
+fn timed_events(events: &[SysmonEvent]) -> Vec<(DateTime<Utc>, &SysmonEvent)> {
+    let mut timed: Vec<_> = events
+        .iter()
+        .filter_map(|event| {
+            let system = event.system();
+            match system.time_created.time {
+                Some(time) => Some((time, event)),
+                None => {
+                    info!(
+                        "Failed to parse timestamp for event {}: '{}'",
+                        system.event_id.event_id, system.time_created.system_time
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+    timed.sort_by_key(|(time, _)| *time);
+    timed
+}
+/// Outbound connections of one process to one destination
+struct ConnectionSeries {
+    first: Arc<SysmonEvent>,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    count: usize,
+    /// Latest connection times, at most `beacon_min_count`
+    recent: VecDeque<DateTime<Utc>>,
+    /// Mean interval and jitter of the first regular run of connections
+    regular: Option<(f64, f64)>,
+}
+/// Occurrences of one Event ID
+#[derive(Default)]
+struct StormState {
+    /// Latest occurrence times and process names, at most `event_storm_threshold`
+    recent: VecDeque<(DateTime<Utc>, String)>,
+    /// Burst in progress, while the latest occurrences fit in the storm window
+    burst: Option<StormBurst>,
+}
+/// Occurrences from the first time `event_storm_threshold` of them fit in the storm window until
+/// they no longer do
+struct StormBurst {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    events: usize,
+    /// Maps process name to its events
+    processes: HashMap<String, usize>,
+}
+/// Processes of one computer, to check the parents claimed by process creations
+struct HostProcesses {
+    /// Time since which the processes of the computer are known: its first event, later the
+    /// retention cutoff once older processes are forgotten
+    known_since: DateTime<Utc>,
+    /// Maps the GUID of each process with at least one event to the time of its latest one
+    seen: HashMap<uuid::Uuid, DateTime<Utc>>,
+    /// Maps ProcessGuid to the creation time and PID of the logged process creations
+    started: HashMap<uuid::Uuid, (DateTime<Utc>, u64)>,
+}
+struct AnomalyDetector {
+    config: DetectionConfig,
+    anomalies: Vec<AnomalyKind>,
+    /// Maps PID to depth and creation time
+    process_depth: HashMap<u64, (usize, DateTime<Utc>)>,
+    /// Maps EventID to its occurrences
+    event_storms: HashMap<u16, StormState>,
+    /// Maps ProcessGuid to recent deletions (timestamp, directory)
+    file_deletions: HashMap<uuid::Uuid, VecDeque<(DateTime<Utc>, String)>>,
+    /// Processes already reported for mass deletion
+    flagged_deleters: HashSet<uuid::Uuid>,
+    /// Maps (process, destination IP, port) to outbound connections
+    connections: HashMap<(String, String, u16), ConnectionSeries>,
+    /// Processes and hosts using each destination port
+    port_baseline: PortBaseline,
+    /// Maps (process, port), lowercased, to the time and event of the first connection to a port
+    /// the process is not expected to use
+    unexpected_ports: HashMap<(String, u16), (DateTime<Utc>, Arc<SysmonEvent>)>,
+    /// Processes and hosts accepting connections on each local port
+    listener_baseline: PortBaseline,
+    /// Maps (process, port), lowercased, to the time and event of the first connection accepted
+    /// on a port the process is not expected to serve, unless an inbound rule already reported it
+    unexpected_listeners: HashMap<(String, u16), (DateTime<Utc>, Arc<SysmonEvent>)>,
+    /// Maps computer to its processes
+    hosts: HashMap<String, HostProcesses>,
+    /// Process creations whose parent was not seen yet although its GUID says it started while the
+    /// log was recording, with the computer, parent GUID and time of the child
+    unseen_parents: Vec<(String, uuid::Uuid, DateTime<Utc>, Arc<SysmonEvent>)>,
+    /// Maps (computer, file path), lowercased, to downloads from remote zones
+    downloads: HashMap<(String, String), (DateTime<Utc>, MarkOfTheWeb)>,
+    /// Maps ProcessGuid to the creation time and event of children of Office applications and
+    /// services, until they exit or outlive the short-lived threshold
+    watched_children: HashMap<uuid::Uuid, (DateTime<Utc>, Arc<SysmonEvent>)>,
+    /// Executables written within the drop-execute window
+    dropped_executables: DroppedExecutables,
+    /// Maps (computer, ProcessGuid), the computer lowercased, to the creation time and integrity
+    /// level of the process
+    integrity_levels: HashMap<(String, uuid::Uuid), (DateTime<Utc>, Integrity)>,
+    /// Maps computer, lowercased, to the time of its latest consent prompt
+    consents: HashMap<String, DateTime<Utc>>,
+    /// Maps (computer, ProcessGuid), the computer lowercased, to the recent connections of the
+    /// process to lateral movement ports
+    lateral_connections: HashMap<(String, uuid::Uuid), VecDeque<LateralConnection>>,
+    /// Maps (computer, ProcessGuid) of the processes reported for a fan-out to their computer,
+    /// file name and connections, kept to correlate the services created on the hosts reached
+    fan_outs: HashMap<(String, uuid::Uuid), (String, String, FanOut)>,
+    /// Parts of script blocks split over several events, with the latest part logged
+    script_block_parts: ScriptBlockParts<Arc<SysmonEvent>>,
+    /// Rule statistics, only collected when profiling
+    profile: Option<RuleProfile>,
+    /// Instances of the registered custom detectors
+    custom: Vec<Box<dyn Detector>>,
+    /// Maps the key of each executable started to where it was seen and its first start, only
+    /// with a prevalence store
+    executables: HashMap<String, (Sightings, Arc<SysmonEvent>)>,
+}
+impl AnomalyDetector {
+    fn new(config: DetectionConfig) -> Self {
+        Self {
+            custom: config.detectors.instantiate(),
+            config,
+            anomalies: vec![],
+            process_depth: HashMap::new(),
+            event_storms: HashMap::new(),
+            file_deletions: HashMap::new(),
+            flagged_deleters: HashSet::new(),
+            connections: HashMap::new(),
+            port_baseline: PortBaseline::default(),
+            unexpected_ports: HashMap::new(),
+            listener_baseline: PortBaseline::default(),
+            unexpected_listeners: HashMap::new(),
+            hosts: HashMap::new(),
+            unseen_parents: Vec::new(),
+            downloads: HashMap::new(),
+            watched_children: HashMap::new(),
+            dropped_executables: DroppedExecutables::default(),
+            integrity_levels: HashMap::new(),
+            consents: HashMap::new(),
+            lateral_connections: HashMap::new(),
+            fan_outs: HashMap::new(),
+            script_block_parts: ScriptBlockParts::default(),
+            profile: None,
+            executables: HashMap::new(),
+        }
+    }
+    fn analyze_batch(&mut self, events: &[SysmonEvent]) -> Vec<Anomaly> {
+        info!(
+            "Starting batch anomaly detection on {} events",
+            events.len()
+        );
+        let (blocks, timed): (Vec<_>, Vec<_>) = timed_events(events)
+            .into_iter()
+            .partition(|(_, event)| matches!(event, SysmonEvent::ScriptBlock(_)));
+        for chunk in timed.chunks(DETECTION_CHUNK_SIZE) {
+            self.analyze_chunk(chunk);
+        }
+        self.analyze_script_blocks(&blocks);
+        info!(
+            "Finished batch anomaly detection on {} events",
+            events.len()
+        );
+        self.finish()
+    }
+    /// Analyze time-ordered script blocks once the other events were. They come from the
+    /// PowerShell log and only share state with each other, so analyzing them last gives the same
+    /// findings while `parse` holds them back until the processes that ran them are known.
+    fn analyze_script_blocks(&mut self, blocks: &[(DateTime<Utc>, &SysmonEvent)]) {
+        for (time, event) in blocks {
+            self.analyze_event(event, *time);
+        }
+    }
+    /// Analyze time-ordered events, then drop state that can no longer produce findings
+    fn analyze_chunk(&mut self, chunk: &[(DateTime<Utc>, &SysmonEvent)]) {
+        for (time, event) in chunk {
+            self.analyze_event(event, *time);
+        }
+        if let Some((watermark, _)) = chunk.last() {
+            self.prune(*watermark);
+        }
+    }
+    fn analyze_event(&mut self, event: &SysmonEvent, time: DateTime<Utc>) {
+        // Allowlisted processes are still known, so their children don't look spoofed
+        self.timed("ParentSpoofing", |detector| {
+            detector.track_process(event, time)
+        });
+        if self.config.allowlist.matches(event) {
+            return;
+        }
+        self.timed("EventStorm", |detector| detector.count_event(event, time));
+        let trigger = Trigger::new(event);
+        self.run("IocMatch", |config| check_ioc(&trigger, &config.ioc_feeds));
+        match event {
+            SysmonEvent::ProcessCreate(event) => {
+                self.run("SuspiciousParentChild", |config| {
+                    check_suspicious_parent_child(event, &trigger, &config.parent_child_rules)
+                });
+                self.run("SuspiciousCommandLine", |_| {
+                    check_command_line(event, &trigger)
+                });
+                self.run("UntrustedExecutable", |config| {
+                    check_untrusted_executable(event, &trigger, config.known_good.as_ref())
+                });
+                self.run("UnknownBinary", |config| {
+                    check_unknown_binary(event, &trigger, config.known_good.as_ref())
+                });
+                self.run("ExternalExecutable", |config| {
+                    check_external_executable(event, &trigger, &config.removable_drives)
+                });
+                self.run("Masquerading", |config| {
+                    check_masquerading(event, &trigger, config.known_good.as_ref())
+                });
+                self.timed("DeepProcessTree", |detector| {
+                    detector.check_process_depth_batch(event, &trigger, time)
+                });
+                self.run_stateful("ParentSpoofing", |detector| {
+                    detector.check_parent_spoofing_batch(event, &trigger, time)
+                });
+                self.run_stateful("DownloadExecuted", |detector| {
+                    detector.check_download_executed_batch(event, &trigger)
+                });
+                self.run_stateful("IntegrityEscalation", |detector| {
+                    detector.check_integrity_escalation_batch(event, &trigger, time)
+                });
+                self.timed("ShortLivedProcess", |detector| {
+                    detector.track_watched_child(event, &trigger, time)
+                });
+                self.run_stateful("DroppedAndExecuted", |detector| {
+                    detector.check_dropped_executed_batch(event, &trigger, time)
+                });
+                if self.config.prevalence.is_some() {
+                    self.timed("RareExecutable", |detector| {
+                        detector.track_executable(event, &trigger)
+                    });
+                }
+            }
+            SysmonEvent::FileExecutableDetected(_) => {
+                self.timed("DroppedAndExecuted", |detector| {
+                    detector.dropped_executables.record(time, trigger.shared())
+                });
+            }
+            SysmonEvent::ProcessTerminate(event) => {
+                self.run_stateful("ShortLivedProcess", |detector| {
+                    detector.check_short_lived_batch(event, time)
+                });
+            }
+            SysmonEvent::FileCreate(event) => {
+                self.run("SuspiciousFileDrop", |_| check_file_drop(event, &trigger));
+            }
+            SysmonEvent::FileStreamCreate(event) => {
+                self.run("ExecutableStream", |_| {
+                    check_executable_stream(event, &trigger)
+                });
+                self.timed("DownloadExecuted", |detector| {
+                    detector.track_download(event, time)
+                });
+            }
+            SysmonEvent::ScriptBlock(event) => {
+                self.run_stateful("ObfuscatedScriptBlock", |detector| {
+                    let text = detector
+                        .script_block_parts
+                        .add(event, || trigger.shared())?;
+                    check_script_block(event, &text, || trigger.shared())
+                });
+            }
+            SysmonEvent::OutboundNetwork(event) => {
+                self.timed("UnusualPort", |detector| {
+                    detector.track_port(event, &trigger, time)
+                });
+                self.timed("Beaconing", |detector| {
+                    detector.track_connection(event, &trigger, time)
+                });
+                self.run_stateful("LateralMovement", |detector| {
+                    detector.check_lateral_movement_batch(event, &trigger, time)
+                });
+            }
+            SysmonEvent::InboundNetwork(event) => {
+                self.run("SuspiciousInbound", |config| {
+                    check_inbound(event, &trigger, &config.inbound)
+                });
+                self.timed("SuspiciousInbound", |detector| {
+                    detector.track_listener(event, &trigger, time)
+                });
+            }
+            SysmonEvent::FileDelete(event) => {
+                self.timed("MassFileDeletion", |detector| {
+                    detector.check_mass_deletion_batch(event, &trigger, time)
+                });
+            }
+            SysmonEvent::PipeCreated(event) | SysmonEvent::PipeConnected(event) => {
+                self.run("SuspiciousPipe", |_| check_suspicious_pipe(event, &trigger));
+            }
+            SysmonEvent::RegistrySetValue(event) => {
+                self.run("SuspiciousRegistryValue", |_| {
+                    check_registry_value(event, &trigger)
+                });
+                self.run_stateful("LateralMovement", |detector| {
+                    detector.check_service_after_fan_out_batch(event, &trigger, time)
+                });
+            }
+            SysmonEvent::ProcessAccess(event) => {
+                self.run("CredentialDumping", |_| {
+                    check_credential_dumping(event, &trigger)
+                });
+            }
+            SysmonEvent::WmiConsumer(event) => {
+                self.run("WmiPersistence", |_| check_wmi_persistence(event, &trigger));
+            }
+            SysmonEvent::DriverLoad(event) => {
+                self.run("SuspiciousDriver", |_| {
+                    check_suspicious_driver(event, &trigger)
+                });
+            }
+            SysmonEvent::RawAccessRead(event) => {
+                self.run("RawDiskAccess", |_| check_raw_disk_access(event, &trigger));
+            }
+            SysmonEvent::ServiceStateChange(_) | SysmonEvent::ConfigurationChange(_) => {
+                self.run("SysmonTampering", |_| check_sysmon_tampering(&trigger));
+            }
+            SysmonEvent::ProcessTampering(event) => {
+                self.run("ProcessTampering", |_| {
+                    Some(check_process_tampering(event, &trigger))
+                });
+            }
+            _ => {}
+        }
+        self.run_custom(|detector| detector.on_event(event));
+    }
+    /// Evaluate every custom detector and keep their findings
+    fn run_custom(&mut self, mut check: impl FnMut(&mut dyn Detector) -> Vec<AnomalyKind>) {
+        let mut custom = std::mem::take(&mut self.custom);
+        for detector in &mut custom {
+            let anomalies = self.timed(detector.name(), |_| check(detector.as_mut()));
+            self.anomalies.extend(anomalies);
+        }
+        self.custom = custom;
+    }
+    /// Evaluate a rule that only looks at the current event and keep its finding
+    fn run(
+        &mut self,
+        rule: &'static str,
+        check: impl FnOnce(&DetectionConfig) -> Option<AnomalyKind>,
+    ) {
+        if let Some(anomaly) = self.timed(rule, |detector| check(&detector.config)) {
+            self.anomalies.push(anomaly);
+        }
+    }
+    /// Evaluate a rule that needs the detector state and keep its finding
+    fn run_stateful(
+        &mut self,
+        rule: &'static str,
+        check: impl FnOnce(&mut Self) -> Option<AnomalyKind>,
+    ) {
+        if let Some(anomaly) = self.timed(rule, check) {
+            self.anomalies.push(anomaly);
+        }
+    }
+    /// Evaluate part of a rule, recording the time it took when profiling; rules other than
+    /// [`DetectionConfig::only_rule`] are skipped
+    fn timed<T: Default>(&mut self, rule: &'static str, check: impl FnOnce(&mut Self) -> T) -> T {
+        if self.config.only_rule.is_some_and(|only| only != rule) {
+            return T::default();
+        }
+        if self.profile.is_none() {
+            return check(self);
+        }
+        let start = Instant::now();
+        let result = check(self);
+        let elapsed = start.elapsed();
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record(rule, elapsed);
+        }
+        result
+    }
+    /// Report findings that need the whole log (event storms, beaconing, unusual ports and
+    /// listeners, parents never seen, rare executables, custom detectors)
+    fn finish(&mut self) -> Vec<Anomaly> {
+        self.timed("EventStorm", Self::check_event_storms_batch);
+        self.timed("Beaconing", Self::check_beaconing_batch);
+        self.timed("UnusualPort", Self::check_unusual_ports_batch);
+        self.timed("SuspiciousInbound", Self::check_unusual_listeners_batch);
+        self.timed("ParentSpoofing", Self::check_unseen_parents_batch);
+        self.timed(
+            "ObfuscatedScriptBlock",
+            Self::check_incomplete_script_blocks_batch,
+        );
+        if let Some(path) = self.config.prevalence.clone() {
+            self.timed("RareExecutable", |detector| {
+                detector.check_prevalence_batch(&path)
             });
         }
+        self.run_custom(|detector| detector.finalize());
+        let mut anomalies: Vec<Anomaly> = std::mem::take(&mut self.anomalies)
+            .into_iter()
+            .map(|kind| Anomaly::new(kind, &self.config))
+            .collect();
+        anomalies.retain(|anomaly| {
+            let suppressed = self.config.is_suppressed(anomaly);
+            if let Some(profile) = self.profile.as_mut() {
+                profile.count_finding(anomaly, suppressed);
+            }
+            !suppressed
+        });
+        anomalies
+    }
+    /// Forget processes, deletions and connections older than the detection windows
+    fn prune(&mut self, watermark: DateTime<Utc>) {
+        let retention = watermark - Duration::hours(STATE_RETENTION_HOURS);
+        self.process_depth
+            .retain(|_, (_, created)| *created >= retention);
+        // A parent not logged within the retention after its child is not coming
+        let (expired, pending) = std::mem::take(&mut self.unseen_parents)
+            .into_iter()
+            .partition(|(_, _, time, _)| *time < retention);
+        self.unseen_parents = pending;
+        self.report_unseen_parents(expired);
+        for host in self.hosts.values_mut() {
+            host.known_since = host.known_since.max(retention);
+            host.seen.retain(|_, time| *time >= retention);
+            host.started.retain(|_, (created, _)| *created >= retention);
+        }
+        let deletion_window = watermark - Duration::seconds(MASS_DELETION_WINDOW_SECONDS);
+        self.file_deletions.retain(|_, deletions| {
+            deletions
+                .back()
+                .is_some_and(|(time, _)| *time >= deletion_window)
+        });
+        // Series already found regular are kept to report their final connection count
+        self.connections
+            .retain(|_, series| series.regular.is_some() || series.last_seen >= retention);
+        self.downloads.retain(|_, (time, _)| *time >= retention);
+        // A pair unexpected for the whole retention is judged on the baseline of that time
+        let (expired, recent) = std::mem::take(&mut self.unexpected_ports)
+            .into_iter()
+            .partition(|(_, (first, _))| *first < retention);
+        self.unexpected_ports = recent;
+        self.report_unusual_ports(expired);
+        self.port_baseline.retain_since(retention);
+        let (expired, recent) = std::mem::take(&mut self.unexpected_listeners)
+            .into_iter()
+            .partition(|(_, (first, _))| *first < retention);
+        self.unexpected_listeners = recent;
+        self.report_unusual_listeners(expired);
+        self.listener_baseline.retain_since(retention);
+        self.integrity_levels
+            .retain(|_, (created, _)| *created >= retention);
+        let consent_window = watermark - Duration::seconds(elevation::CONSENT_WINDOW_SECONDS);
+        self.consents.retain(|_, time| *time >= consent_window);
+        let fan_out_window = watermark - Duration::seconds(lateral::FAN_OUT_WINDOW_SECONDS);
+        self.lateral_connections.retain(|_, connections| {
+            connections
+                .back()
+                .is_some_and(|connection| connection.time >= fan_out_window)
+        });
+        let service_window = watermark - Duration::seconds(lateral::SERVICE_WINDOW_SECONDS);
+        self.fan_outs
+            .retain(|_, (_, _, fan_out)| fan_out.end >= service_window);
+        let short_lived = watermark - Duration::milliseconds(lifetime::SHORT_LIVED_MILLISECONDS);
+        self.watched_children
+            .retain(|_, (created, _)| *created >= short_lived);
+        self.dropped_executables
+            .retain_since(watermark - self.config.drop_execute_window);
+    }
+    /// Score script blocks whose missing parts never arrived on the parts logged
+    fn check_incomplete_script_blocks_batch(&mut self) {
+        let parts = std::mem::take(&mut self.script_block_parts);
+        for (text, event) in parts.into_incomplete() {
+            if let SysmonEvent::ScriptBlock(block) = event.as_ref()
+                && let Some(anomaly) = check_script_block(block, &text, || event.clone())
+            {
+                self.anomalies.push(anomaly);
+            }
+        }
+    }
+    /// Remember children of Office applications and services until they exit
+    fn track_watched_child(
+        &mut self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) {
+        let data = &event.event_data;
+        if lifetime::is_watched_child(&data.parent_image.image, &data.image.image) {
+            self.watched_children
+                .insert(data.process_guid.process_guid, (time, trigger.shared()));
+        }
+    }
+    /// Termination of a watched child soon after its creation
+    fn check_short_lived_batch(
+        &mut self,
+        event: &ProcessTerminateEvent,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let (created, creation) = self
+            .watched_children
+            .remove(&event.event_data.process_guid.process_guid)?;
+        short_lived_anomaly(creation, time - created)
+    }
+    /// Process started from an executable dropped within the window
+    fn check_dropped_executed_batch(
+        &self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let (dropped_at, dropped) = self
+            .dropped_executables
+            .find(&event.system.computer.computer, &event.event_data)?;
+        dropped_executed_anomaly(
+            event,
+            trigger,
+            dropped.clone(),
+            time - *dropped_at,
+            self.config.drop_execute_window,
+        )
+    }
+    /// Remember files downloaded from remote zones until they are executed
+    fn track_download(&mut self, event: &FileStreamEvent, time: DateTime<Utc>) {
+        if let Some((file, mark)) = streams::downloaded_file(&event.event_data) {
+            let key = (
+                event.system.computer.computer.to_lowercase(),
+                file.to_lowercase(),
+            );
+            self.downloads.insert(key, (time, mark));
+        }
+    }
+    /// Remember the processes seen on each computer and when they were created
+    fn track_process(&mut self, event: &SysmonEvent, time: DateTime<Utc>) {
+        // Script blocks come from the PowerShell log, which may cover another period
+        if matches!(event, SysmonEvent::ScriptBlock(_)) {
+            return;
+        }
+        let computer = &event.system().computer.computer;
+        if !self.hosts.contains_key(computer) {
+            self.hosts.insert(
+                computer.clone(),
+                HostProcesses {
+                    known_since: time,
+                    seen: HashMap::new(),
+                    started: HashMap::new(),
+                },
+            );
+        }
+        let Some(host) = self.hosts.get_mut(computer) else {
+            return;
+        };
+        let guid = event.process_guid();
+        if !guid.is_nil() {
+            host.seen.insert(guid, time);
+        }
+        if let SysmonEvent::ProcessCreate(e) = event {
+            host.started.insert(guid, (time, e.event_data.process_id));
+        }
+    }
+    /// Compare the claimed parent with its logged process creation. Parents not seen yet whose
+    /// GUID says they started during the log are checked again once the whole log was read.
+    fn check_parent_spoofing_batch(
+        &mut self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let data = &event.event_data;
+        let parent = data.parent_process_guid.process_guid;
+        let computer = &event.system.computer.computer;
+        let host = self.hosts.get(computer)?;
+        if let Some(&logged) = host.started.get(&parent) {
+            let reason = logged_parent_mismatch(event, logged, time)?;
+            return Some(parent_spoofing_anomaly(event, trigger.shared(), reason));
+        }
+        let started = spoofing::parent_creation_time(&data.process_guid.process_guid, &parent)?;
+        if started >= host.known_since && !host.seen.contains_key(&parent) {
+            self.unseen_parents
+                .push((computer.clone(), parent, time, trigger.shared()));
+        }
+        None
+    }
+    /// Remember the integrity level of the process and the consent prompts, then compare the
+    /// process with its parent
+    fn check_integrity_escalation_batch(
+        &mut self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let data = &event.event_data;
+        let computer = event.system.computer.computer.to_lowercase();
+        if elevation::is_consent(&data.image.image) {
+            self.consents.insert(computer.clone(), time);
+        }
+        if let Some(level) = Integrity::from_level(&data.integrity_level.integrity_level) {
+            self.integrity_levels.insert(
+                (computer.clone(), data.process_guid.process_guid),
+                (time, level),
+            );
+        }
+        let parent = data.parent_process_guid.process_guid;
+        let &(_, parent) = self.integrity_levels.get(&(computer.clone(), parent))?;
+        let consent = self.consents.get(&computer).copied();
+        integrity_escalation_anomaly(event, trigger, parent, consent, time)
+    }
+    /// Report children of parents created after them, or never seen although they started during
+    /// the log. A GUID time after the child only means the clock was set back since the parent
+    /// started (common on virtual machines), so it is not reported by itself.
+    fn check_unseen_parents_batch(&mut self) {
+        let pending = std::mem::take(&mut self.unseen_parents);
+        self.report_unseen_parents(pending);
+    }
+    /// Report the children among `pending` whose parent is still not logged, or was created after
+    /// them
+    fn report_unseen_parents(
+        &mut self,
+        pending: Vec<(String, uuid::Uuid, DateTime<Utc>, Arc<SysmonEvent>)>,
+    ) {
+        for (computer, parent, time, first) in pending {
+            let (Some(host), SysmonEvent::ProcessCreate(event)) =
+                (self.hosts.get(&computer), first.as_ref())
+            else {
+                continue;
+            };
+            let reason = match host.started.get(&parent) {
+                Some(&(started, _)) if started > time => SpoofingReason::ParentCreatedAfterChild,
+                _ if host.seen.contains_key(&parent) => continue,
+                _ if spoofing::parent_creation_time(
+                    &event.event_data.process_guid.process_guid,
+                    &parent,
+                )
+                .is_some_and(|started| started <= time) =>
+                {
+                    SpoofingReason::ParentNotInLog
+                }
+                _ => continue,
+            };
+            self.anomalies
+                .push(parent_spoofing_anomaly(event, first.clone(), reason));
+        }
+    }
+    fn check_download_executed_batch(
+        &self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+    ) -> Option<AnomalyKind> {
+        let key = (
+            event.system.computer.computer.to_lowercase(),
+            event.event_data.image.to_lowercase(),
+        );
+        let (_, mark) = self.downloads.get(&key)?;
+        Some(download_executed_anomaly(event, trigger, mark.clone()))
+    }
+    fn check_process_depth_batch(
+        &mut self,
+        event: &ProcessCreateEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) {
+        let data = &event.event_data;
+        let pid = data.process_id;
+        let parent_pid = data.parent_process_id;
+        let parent_depth = self
+            .process_depth
+            .get(&parent_pid)
+            .map_or(0, |(depth, _)| *depth);
+        let current_depth = parent_depth + 1;
+        self.process_depth.insert(pid, (current_depth, time));
+        if current_depth > DEEP_NESTING_THRESHOLD {
+            self.anomalies.push(AnomalyKind::DeepProcessTree {
+                event: trigger.shared(),
+                depth: current_depth,
+            });
+        }
+    }
+
+    fn check_mass_deletion_batch(
+        &mut self,
+        event: &FileDeleteEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) {
+        let guid = event.event_data.process_guid.process_guid;
+        if self.flagged_deleters.contains(&guid) {
+            return;
+        }
+        let window = self.file_deletions.entry(guid).or_default();
+        window.push_back((time, parent_directory(&event.event_data.target_filename)));
+        while let Some((oldest, _)) = window.front() {
+            if time.signed_duration_since(*oldest).num_seconds() > MASS_DELETION_WINDOW_SECONDS {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+        if let Some(anomaly) = mass_deletion_anomaly(event, trigger, window.iter()) {
+            self.flagged_deleters.insert(guid);
+            self.file_deletions.remove(&guid);
+            self.anomalies.push(anomaly);
+        }
+    }
+
+    /// Report a process once its recent connections to lateral movement ports reach enough hosts,
+    /// then keep adding its connections to correlate the services created on the hosts
+    fn check_lateral_movement_batch(
+        &mut self,
+        event: &NetworkEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let data = &event.event_data;
+        let connection = LateralConnection::new(data, time)?;
+        let source = &event.system.computer.computer;
+        let key = (source.to_lowercase(), data.process_guid.process_guid);
+        if let Some((_, _, fan_out)) = self.fan_outs.get_mut(&key) {
+            fan_out.add(&connection);
+            return None;
+        }
+        let window = self.lateral_connections.entry(key.clone()).or_default();
+        window.push_back(connection);
+        while window.front().is_some_and(|oldest| {
+            (time - oldest.time).num_seconds() > lateral::FAN_OUT_WINDOW_SECONDS
+        }) {
+            window.pop_front();
+        }
+        let fan_out = FanOut::of(window.iter())?;
+        self.lateral_connections.remove(&key);
+        let process = data.image.rsplit('\\').next().unwrap_or(&data.image);
+        let anomaly = lateral_movement_anomaly(trigger, source, process, &fan_out, None);
+        self.fan_outs
+            .insert(key, (source.clone(), process.to_string(), fan_out));
+        Some(anomaly)
+    }
+    /// Correlate a service created on a host with the earliest fan-out that reached it just before
+    fn check_service_after_fan_out_batch(
+        &mut self,
+        event: &RegistryEvent,
+        trigger: &Trigger,
+        time: DateTime<Utc>,
+    ) -> Option<AnomalyKind> {
+        let service = CreatedService::new(&event.event_data)?;
+        let computer = &event.system.computer.computer;
+        let (source, process, fan_out) = self
+            .fan_outs
+            .values()
+            .filter(|(_, _, fan_out)| fan_out.reached(computer) && fan_out.followed_by(time))
+            .min_by_key(|(_, _, fan_out)| fan_out.start)?;
+        Some(lateral_movement_anomaly(
+            trigger,
+            source,
+            process,
+            fan_out,
+            Some(service),
+        ))
+    }
+    /// Look for the first run of `beacon_min_count` regular connections of each series
+    fn track_connection(&mut self, event: &NetworkEvent, trigger: &Trigger, time: DateTime<Utc>) {
+        let min_count = self.config.beacon_min_count.max(3);
+        let data = &event.event_data;
+        let key = (
+            data.image
+                .rsplit('\\')
+                .next()
+                .unwrap_or(&data.image)
+                .to_lowercase(),
+            data.destination_ip.clone(),
+            data.destination_port,
+        );
+        let series = self
+            .connections
+            .entry(key)
+            .or_insert_with(|| ConnectionSeries {
+                first: trigger.shared(),
+                first_seen: time,
+                last_seen: time,
+                count: 0,
+                recent: VecDeque::with_capacity(min_count),
+                regular: None,
+            });
+        series.count += 1;
+        series.last_seen = time;
+        if series.regular.is_some() {
+            return;
+        }
+        if series.recent.len() == min_count {
+            series.recent.pop_front();
+        }
+        series.recent.push_back(time);
+        if series.recent.len() == min_count {
+            series.regular =
+                regular_interval(series.recent.make_contiguous(), self.config.beacon_jitter);
+            if series.regular.is_some() {
+                series.recent = VecDeque::new();
+            }
+        }
+    }
+
+    /// Add the connection to the port baseline and remember the first one of each unexpected
+    /// (process, port) pair
+    fn track_port(&mut self, event: &NetworkEvent, trigger: &Trigger, time: DateTime<Utc>) {
+        let data = &event.event_data;
+        let process = data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_lowercase();
+        self.port_baseline.record(
+            &process,
+            data.destination_port,
+            &event.system.computer.computer,
+            time,
+        );
+        if !ports::is_expected_port(
+            &self.config.port_profiles,
+            &data.image,
+            data.destination_port,
+        ) {
+            self.unexpected_ports
+                .entry((process, data.destination_port))
+                .or_insert_with(|| (time, trigger.shared()));
+        }
+    }
+
+    /// Report the unexpected pairs that stayed rare over the whole log
+    fn check_unusual_ports_batch(&mut self) {
+        let pairs = std::mem::take(&mut self.unexpected_ports);
+        self.report_unusual_ports(pairs);
+    }
+
+    /// Report the unexpected pairs among `pairs` that are rare in the port baseline
+    fn report_unusual_ports(
+        &mut self,
+        pairs: HashMap<(String, u16), (DateTime<Utc>, Arc<SysmonEvent>)>,
+    ) {
+        let mut pairs: Vec<_> = pairs
+            .into_iter()
+            .filter(|((process, port), _)| self.port_baseline.is_rare(process, *port))
+            .map(|(_, first)| first)
+            .collect();
+        // Keep output deterministic
+        pairs.sort_by_key(|(time, first)| (*time, first.system().event_record_id.event_record_id));
+        for (_, first) in pairs {
+            if let SysmonEvent::OutboundNetwork(event) = first.as_ref() {
+                let data = &event.event_data;
+                self.anomalies.push(AnomalyKind::UnusualPort {
+                    event: first.clone(),
+                    port: data.destination_port,
+                    process: data
+                        .image
+                        .rsplit('\\')
+                        .next()
+                        .unwrap_or(&data.image)
+                        .to_string(),
+                });
+            }
+        }
+    }
+
+    /// Add the accepted connection to the listener baseline and remember the first one of each
+    /// unexpected (process, local port) pair
+    fn track_listener(&mut self, event: &NetworkEvent, trigger: &Trigger, time: DateTime<Utc>) {
+        let data = &event.event_data;
+        if inbound::remote_peer(data).is_none() {
+            return;
+        }
+        let (_, port) = data.local();
+        let process = data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_lowercase();
+        let computer = &event.system.computer.computer;
+        self.listener_baseline
+            .record(&process, port, computer, time);
+        let inbound = &self.config.inbound;
+        if !inbound.is_expected_listener(&data.image, port)
+            && inbound.check(computer, data).is_none()
+        {
+            self.unexpected_listeners
+                .entry((process, port))
+                .or_insert_with(|| (time, trigger.shared()));
+        }
+    }
+
+    /// Report the unexpected listeners that stayed rare over the whole log
+    fn check_unusual_listeners_batch(&mut self) {
+        let pairs = std::mem::take(&mut self.unexpected_listeners);
+        self.report_unusual_listeners(pairs);
+    }
+
+    /// Report the unexpected listeners among `pairs` that are rare in the listener baseline
+    fn report_unusual_listeners(
+        &mut self,
+        pairs: HashMap<(String, u16), (DateTime<Utc>, Arc<SysmonEvent>)>,
+    ) {
+        let mut pairs: Vec<_> = pairs
+            .into_iter()
+            .filter(|((process, port), _)| self.listener_baseline.is_rare(process, *port))
+            .map(|(_, first)| first)
+            .collect();
+        // Keep output deterministic
+        pairs.sort_by_key(|(time, first)| (*time, first.system().event_record_id.event_record_id));
+        for (_, first) in pairs {
+            if let SysmonEvent::InboundNetwork(event) = first.as_ref() {
+                self.anomalies.push(inbound_anomaly(
+                    event,
+                    first.clone(),
+                    InboundReason::UnusualListeningPort,
+                ));
+            }
+        }
+    }
+
+    /// Remember where each executable started in the run
+    fn track_executable(&mut self, event: &ProcessCreateEvent, trigger: &Trigger) {
+        let (sightings, _) = self
+            .executables
+            .entry(prevalence::process_key(event))
+            .or_insert_with(|| {
+                (
+                    Sightings::new(&event.event_data.image.image),
+                    trigger.shared(),
+                )
+            });
+        sightings.add_host(&event.system.computer.computer);
+    }
+
+    /// Report the executables of the run that are rare in the prevalence store; the commands add
+    /// the run to the store afterwards
+    fn check_prevalence_batch(&mut self, path: &Path) {
+        let store = match PrevalenceStore::load(path) {
+            Ok(store) => store,
+            Err(e) => {
+                warn!("Skipping rare executables: {e:#}");
+                return;
+            }
+        };
+        let mut rare: Vec<_> = self
+            .executables
+            .iter()
+            .filter_map(|(key, (sightings, first))| {
+                let rarity = store.rarity(key, sightings)?;
+                Some((rarity, sightings, first))
+            })
+            .collect();
+        // Keep output deterministic
+        rare.sort_by_key(|(_, _, first)| {
+            (
+                first.system().time_created.time,
+                first.system().event_record_id.event_record_id,
+            )
+        });
+        for (rarity, sightings, first) in rare {
+            self.anomalies.push(AnomalyKind::RareExecutable {
+                event: first.clone(),
+                image: sightings.image.clone(),
+                rarity,
+            });
+        }
+    }
+
+    fn check_beaconing_batch(&mut self) {
+        let mut series: Vec<_> = self.connections.values().collect();
+        // Keep output deterministic
+        series.sort_by_key(|s| s.first_seen);
+        for s in series {
+            if let (Some((interval, jitter)), SysmonEvent::OutboundNetwork(first)) =
+                (s.regular, s.first.as_ref())
+            {
+                self.anomalies.push(beaconing_anomaly(
+                    first,
+                    s.first.clone(),
+                    s.count,
+                    interval,
+                    jitter,
+                ));
+            }
+        }
+    }
+
+    /// Count the event in the sliding window of its ID. A burst starts when the latest
+    /// `event_storm_threshold` occurrences fit in the window and is reported once they no longer do.
+    fn count_event(&mut self, event: &SysmonEvent, time: DateTime<Utc>) {
+        let threshold = self.config.event_storm_threshold.max(1);
+        let window = self.config.event_storm_window;
+        let event_id = event.system().event_id.event_id;
+        let storm = self.event_storms.entry(event_id).or_default();
+        let process = event.process_name();
+        if storm.recent.len() == threshold {
+            storm.recent.pop_front();
+        }
+        storm.recent.push_back((time, process.to_string()));
+        let dense = storm.recent.len() == threshold
+            && storm
+                .recent
+                .front()
+                .is_some_and(|(start, _)| time - *start <= window);
+        match &mut storm.burst {
+            Some(burst) if dense => {
+                burst.end = time;
+                burst.events += 1;
+                if !process.is_empty() {
+                    *burst.processes.entry(process.to_string()).or_default() += 1;
+                }
+            }
+            Some(_) => {
+                if let Some(burst) = storm.burst.take() {
+                    self.anomalies.push(storm_anomaly(event_id, burst));
+                }
+            }
+            None if dense => {
+                let mut burst = StormBurst {
+                    start: storm.recent.front().map_or(time, |(start, _)| *start),
+                    end: time,
+                    events: storm.recent.len(),
+                    processes: HashMap::new(),
+                };
+                for (_, process) in &storm.recent {
+                    if !process.is_empty() {
+                        *burst.processes.entry(process.clone()).or_default() += 1;
+                    }
+                }
+                storm.burst = Some(burst);
+            }
+            None => {}
+        }
+    }
+    /// Report the bursts still in progress at the end of the log
+    fn check_event_storms_batch(&mut self) {
+        let mut storms: Vec<_> = self
+            .event_storms
+            .iter_mut()
+            .filter_map(|(event_id, storm)| Some((*event_id, storm.burst.take()?)))
+            .collect();
+        storms.sort_by_key(|(event_id, _)| *event_id);
+        self.anomalies.extend(
+            storms
+                .into_iter()
+                .map(|(event_id, burst)| storm_anomaly(event_id, burst)),
+        );
+    }
+}
+fn storm_anomaly(event_id: u16, burst: StormBurst) -> AnomalyKind {
+    AnomalyKind::EventStorm {
+        event_id,
+        count: burst.events,
+        time_window_seconds: (burst.end - burst.start).num_seconds(),
+        start: Some(burst.start),
+        end: Some(burst.end),
+        top_processes: top_processes(burst.processes),
+    }
+}
+// Individual Anomaly Checks
+/// Check process creation against the parent/child rules, the first matching rule is reported
+fn check_suspicious_parent_child(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    rules: &[ParentChildRule],
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let parent = data.parent_image.image.as_str();
+    let child = data.image.image.as_str();
+    let rule = rules.iter().find(|rule| {
+        rule.matches(
+            parent,
+            &data.parent_command_line.command_line,
+            child,
+            &data.command_line.command_line,
+        )
+    })?;
+    let file_name = |image: &str| image.rsplit('\\').next().unwrap_or(image).to_string();
+    Some(AnomalyKind::SuspiciousParentChild {
+        event: trigger.shared(),
+        parent: file_name(parent),
+        child: file_name(child),
+        reason: rule.reason.clone(),
+        severity: rule.severity,
+    })
+}
+/// Score the command line for obfuscation and download cradles
+fn check_command_line(event: &ProcessCreateEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let result = command_line::score_command_line(&data.image, &data.command_line);
+    if result.indicators.is_empty() {
+        return None;
+    }
+    Some(AnomalyKind::SuspiciousCommandLine {
+        event: trigger.shared(),
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        indicators: result.indicators,
+        score: result.score,
+    })
+}
+/// Checks the image location and, when available, its signature
+fn check_untrusted_executable(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    known_good: Option<&KnownGoodHashes>,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    // A binary with a known-good hash is trusted wherever it runs from
+    if let Some(known_good) = known_good
+        && known_good::sha256_of(&data.hashes.hashes).is_some_and(|hash| known_good.contains(hash))
+    {
+        return None;
+    }
+    let image = data.image.to_lowercase();
+    let name = data.image.rsplit('\\').next().unwrap_or(&data.image);
+    if let Some(status) = data
+        .signature_status
+        .as_deref()
+        .filter(|status| !status.eq_ignore_ascii_case("valid"))
+        && data.signed == Some(true)
+    {
+        return Some(AnomalyKind::UntrustedExecutable {
+            event: trigger.shared(),
+            reason: format!("Invalid signature of {name} ({status})"),
+        });
+    }
+    // Anything after the drive letter colon is an alternate data stream
+    let location = if image.get(2..).is_some_and(|path| path.contains(':')) {
+        "runs from an alternate data stream"
+    } else {
+        UNTRUSTED_LOCATIONS
+            .iter()
+            .find(|(fragment, _)| image.contains(fragment))
+            .map(|(_, reason)| *reason)?
+    };
+    let signature_valid = data.signed == Some(true)
+        && data
+            .signature_status
+            .as_deref()
+            .is_none_or(|status| status.eq_ignore_ascii_case("valid"));
+    if signature_valid {
+        return None;
+    }
+    let reason = if data.signed == Some(false) {
+        format!("Unsigned executable {name} {location}")
+    } else {
+        format!("Executable {name} {location}")
+    };
+    Some(AnomalyKind::UntrustedExecutable {
+        event: trigger.shared(),
+        reason,
+    })
+}
+/// Executables named like well-known binaries, unless their hash is known good
+fn check_masquerading(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    known_good: Option<&KnownGoodHashes>,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let masquerade = masquerading::masquerade(&data.image.image)?;
+    if let Some(known_good) = known_good
+        && known_good::sha256_of(&data.hashes.hashes).is_some_and(|hash| known_good.contains(hash))
+    {
+        return None;
+    }
+    Some(AnomalyKind::Masquerading {
+        event: trigger.shared(),
+        image: data.image.image.clone(),
+        masquerade,
+    })
+}
+/// Executables started from a network share (copied there for lateral movement) or from a
+/// removable drive
+fn check_external_executable(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    removable_drives: &[char],
+) -> Option<AnomalyKind> {
+    let image = &event.event_data.image.image;
+    let source = external::executable_source(image, removable_drives)?;
+    Some(AnomalyKind::ExternalExecutable {
+        event: trigger.shared(),
+        process: image.rsplit('\\').next().unwrap_or(image).to_string(),
+        source,
+    })
+}
+/// Checks the first outbound connection of a process to a port it is not expected to use,
+/// against the baseline of the connections in the context
+fn check_unusual_port_live(
+    event: &NetworkEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+    config: &DetectionConfig,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let port = data.destination_port;
+    if ports::is_expected_port(&config.port_profiles, &data.image, port) {
+        return None;
+    }
+    let process = data.image.rsplit('\\').next().unwrap_or(&data.image);
+    let mut baseline = PortBaseline::default();
+    for e in context {
+        if let SysmonEvent::OutboundNetwork(e) = e {
+            if e.event_data.destination_port == port
+                && e.event_data.image.eq_ignore_ascii_case(&data.image)
+            {
+                return None;
+            }
+            let image = &e.event_data.image;
+            baseline.record(
+                image.rsplit('\\').next().unwrap_or(image),
+                e.event_data.destination_port,
+                &e.system.computer.computer,
+                e.system.time_created.time.unwrap_or_default(),
+            );
+        }
+    }
+    let system = &event.system;
+    baseline.record(
+        process,
+        port,
+        &system.computer.computer,
+        system.time_created.time.unwrap_or_default(),
+    );
+    baseline
+        .is_rare(process, port)
+        .then(|| AnomalyKind::UnusualPort {
+            event: trigger.shared(),
+            port,
+            process: process.to_string(),
+        })
+}
+/// Inbound connection reported by the inbound rules whatever the rest of the log
+fn check_inbound(
+    event: &NetworkEvent,
+    trigger: &Trigger,
+    rules: &InboundRules,
+) -> Option<AnomalyKind> {
+    let reason = rules.check(&event.system.computer.computer, &event.event_data)?;
+    Some(inbound_anomaly(event, trigger.shared(), reason))
+}
+/// Flags a connection accepted on a port the process is not expected to serve, unless the
+/// process accepted connections on it within the context window
+fn check_unusual_listener_live(
+    event: &NetworkEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+    config: &DetectionConfig,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let (_, port) = data.local();
+    if inbound::remote_peer(data).is_none()
+        || config.inbound.is_expected_listener(&data.image, port)
+    {
+        return None;
+    }
+    let process = data.image.rsplit('\\').next().unwrap_or(&data.image);
+    let mut baseline = PortBaseline::default();
+    for e in context {
+        if let SysmonEvent::InboundNetwork(e) = e {
+            let (_, local_port) = e.event_data.local();
+            if local_port == port && e.event_data.image.eq_ignore_ascii_case(&data.image) {
+                return None;
+            }
+            let image = &e.event_data.image;
+            baseline.record(
+                image.rsplit('\\').next().unwrap_or(image),
+                local_port,
+                &e.system.computer.computer,
+                e.system.time_created.time.unwrap_or_default(),
+            );
+        }
+    }
+    let system = &event.system;
+    baseline.record(
+        process,
+        port,
+        &system.computer.computer,
+        system.time_created.time.unwrap_or_default(),
+    );
+    baseline
+        .is_rare(process, port)
+        .then(|| inbound_anomaly(event, trigger.shared(), InboundReason::UnusualListeningPort))
+}
+fn inbound_anomaly(
+    event: &NetworkEvent,
+    shared: Arc<SysmonEvent>,
+    reason: InboundReason,
+) -> AnomalyKind {
+    let data = &event.event_data;
+    let (source, _) = data.remote();
+    let (_, port) = data.local();
+    AnomalyKind::SuspiciousInbound {
+        event: shared,
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        port,
+        source: source.to_string(),
+        reason,
+    }
+}
+/// Checks named pipes against names used by known offensive tooling
+fn check_suspicious_pipe(event: &PipeEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let pipe = data.pipe_name.to_lowercase();
+    let (_, reason) = SUSPICIOUS_PIPES
+        .iter()
+        .find(|(pattern, _)| wildcard_match(pattern, &pipe))?;
+    let image = &data.image.image;
+    Some(AnomalyKind::SuspiciousPipe {
+        event: trigger.shared(),
+        pipe: data.pipe_name.clone(),
+        process: image.rsplit('\\').next().unwrap_or(image).to_string(),
+        reason: reason.to_string(),
+    })
+}
+/// Registry values written with suspicious data: encoded autostart commands, script engines as
+/// COM servers and security features switched off
+fn check_registry_value(event: &RegistryEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let finding = registry::inspect(data)?;
+    let image = &data.image.image;
+    Some(AnomalyKind::SuspiciousRegistryValue {
+        event: trigger.shared(),
+        process: image.rsplit('\\').next().unwrap_or(image).to_string(),
+        key: data.target_object.clone(),
+        finding,
+    })
+}
+/// Flags handles to lsass.exe with access rights used to read its memory,
+/// unless opened by a Windows component that legitimately does so
+fn check_credential_dumping(event: &ProcessAccessEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let target = data
+        .target_image
+        .rsplit('\\')
+        .next()
+        .unwrap_or(&data.target_image);
+    if !target.eq_ignore_ascii_case("lsass.exe")
+        || !data
+            .granted_access_mask()
+            .is_some_and(|mask| CREDENTIAL_DUMP_ACCESS_MASKS.contains(&mask))
+    {
+        return None;
+    }
+    let image = data.image.to_lowercase();
+    if TRUSTED_LSASS_ACCESSORS.contains(&image.as_str())
+        || DEFENDER_DIRECTORIES
+            .iter()
+            .any(|directory| image.starts_with(directory))
+    {
+        return None;
+    }
+    Some(AnomalyKind::CredentialDumping {
+        event: trigger.shared(),
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        target: target.to_string(),
+        granted_access: data.granted_access.clone(),
+    })
+}
+/// Flags creation of WMI consumers that run commands or scripts, the executing half of a
+/// WMI event subscription used for persistence
+fn check_wmi_persistence(event: &WmiEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    if !data.is_created() {
+        return None;
+    }
+    let consumer_type = data.consumer_type.as_deref()?.trim();
+    let (_, consumer_class) = WMI_EXECUTING_CONSUMERS
+        .iter()
+        .find(|(kind, _)| kind.eq_ignore_ascii_case(consumer_type))?;
+    let unquote = |value: &Option<String>| {
+        value
+            .as_deref()
+            .unwrap_or("-")
+            .trim()
+            .trim_matches('"')
+            .to_string()
+    };
+    Some(AnomalyKind::WmiPersistence {
+        event: trigger.shared(),
+        consumer_class,
+        name: unquote(&data.name),
+        destination: unquote(&data.destination),
+    })
+}
+/// Flags drivers that are unsigned or whose signature is not valid (e.g. revoked certificates of
+/// vulnerable drivers brought along by the attacker)
+fn check_suspicious_driver(event: &DriverLoadEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let status = data.signature_status.as_deref().unwrap_or_default().trim();
+    let reason = if data.signed == Some(false) {
+        "unsigned".to_string()
+    } else if !status.is_empty() && !status.eq_ignore_ascii_case("valid") {
+        match &data.signature {
+            Some(signature) => format!("signature of {signature} is {status}"),
+            None => format!("signature is {status}"),
+        }
+    } else {
+        return None;
+    };
+    Some(AnomalyKind::SuspiciousDriver {
+        event: trigger.shared(),
+        driver: data.image_loaded.image.clone(),
+        revoked: status.eq_ignore_ascii_case("revoked"),
+        reason,
+    })
+}
+/// Reports binaries whose SHA256 is missing from the known-good set, with a higher severity in
+/// system and program directories. Nothing is reported without a set or a logged SHA256.
+fn check_unknown_binary(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    known_good: Option<&KnownGoodHashes>,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let sha256 = known_good::sha256_of(&data.hashes.hashes)?;
+    if known_good?.contains(sha256) {
+        return None;
+    }
+    let image = data.image.to_lowercase();
+    Some(AnomalyKind::UnknownBinary {
+        event: trigger.shared(),
+        process: data.image.image.clone(),
+        sha256: sha256.to_lowercase(),
+        sensitive: SENSITIVE_LOCATIONS
+            .iter()
+            .any(|location| image.contains(location)),
+    })
+}
+/// Flags direct reads of a volume by processes other than known backup and system tools,
+/// used to copy locked files such as NTDS.dit or the SAM hive
+fn check_raw_disk_access(event: &RawAccessReadEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let image = data.image.image.to_lowercase();
+    if RAW_DISK_READERS.contains(&image.as_str())
+        || DEFENDER_DIRECTORIES
+            .iter()
+            .any(|directory| image.starts_with(directory))
+    {
+        return None;
+    }
+    let process = data
+        .image
+        .image
+        .rsplit('\\')
+        .next()
+        .unwrap_or(&data.image.image)
+        .to_lowercase();
+    Some(AnomalyKind::RawDiskAccess {
+        event: trigger.shared(),
+        process,
+        device: data.device.clone(),
+    })
+}
+/// Flags the Sysmon service stopping and changes of its configuration, done by attackers to
+/// blind the monitoring before acting
+fn check_sysmon_tampering(trigger: &Trigger) -> Option<AnomalyKind> {
+    let action = match trigger.event {
+        SysmonEvent::ServiceStateChange(e) if e.event_data.is_stopped() => {
+            "service stopped".to_string()
+        }
+        SysmonEvent::ConfigurationChange(e) => match e.event_data.configuration.as_deref() {
+            Some(configuration) if !configuration.trim().is_empty() => {
+                format!("configuration changed to {}", configuration.trim())
+            }
+            _ => "configuration changed".to_string(),
+        },
+        _ => return None,
+    };
+    Some(AnomalyKind::SysmonTampering {
+        event: trigger.shared(),
+        action,
+    })
+}
+/// Every tampering event is reported: Sysmon only logs images replaced or locked while their
+/// process runs, which is how process hollowing and herpaderping look
+fn check_process_tampering(event: &ProcessTamperingEvent, trigger: &Trigger) -> AnomalyKind {
+    let data = &event.event_data;
+    AnomalyKind::ProcessTampering {
+        event: trigger.shared(),
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        tampering_type: data.tampering_type.clone(),
+    }
+}
+/// Child of an Office application or services that ran for less than the short-lived threshold
+fn short_lived_anomaly(creation: Arc<SysmonEvent>, lifetime: Duration) -> Option<AnomalyKind> {
+    let SysmonEvent::ProcessCreate(event) = creation.as_ref() else {
+        return None;
+    };
+    let data = &event.event_data;
+    if !lifetime::is_watched_child(&data.parent_image.image, &data.image.image)
+        || !lifetime::is_short_lived(lifetime)
+    {
+        return None;
+    }
+    let file_name = |image: &str| image.rsplit('\\').next().unwrap_or(image).to_string();
+    Some(AnomalyKind::ShortLivedProcess {
+        process: file_name(&data.image.image),
+        parent: file_name(&data.parent_image.image),
+        lifetime_ms: lifetime.num_milliseconds(),
+        event: creation,
+    })
+}
+/// Process started from a dropped executable no longer than `window` after the drop
+fn dropped_executed_anomaly(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    dropped: Arc<SysmonEvent>,
+    elapsed: Duration,
+    window: Duration,
+) -> Option<AnomalyKind> {
+    let SysmonEvent::FileExecutableDetected(drop) = dropped.as_ref() else {
+        return None;
+    };
+    if elapsed < Duration::zero() || elapsed > window {
+        return None;
+    }
+    let file_name = |image: &str| image.rsplit('\\').next().unwrap_or(image).to_string();
+    Some(AnomalyKind::DroppedAndExecuted {
+        event: trigger.shared(),
+        process: file_name(&event.event_data.image.image),
+        dropper: file_name(&drop.event_data.image.image),
+        elapsed_ms: elapsed.num_milliseconds(),
+        dropped,
+    })
+}
+/// Process started from an executable whose drop is logged in the context within the window
+fn check_dropped_executed_live(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+    window: Duration,
+) -> Option<AnomalyKind> {
+    let time = event.system.time_created.time?;
+    let computer = &event.system.computer.computer;
+    let dropped = context.iter().rev().find(|e| match e {
+        SysmonEvent::FileExecutableDetected(drop) => {
+            drop.system.computer.computer.eq_ignore_ascii_case(computer)
+                && dropped::is_started_from(&drop.event_data, &event.event_data)
+        }
+        _ => false,
+    })?;
+    let elapsed = time - dropped.system().time_created.time?;
+    dropped_executed_anomaly(event, trigger, Arc::new(dropped.clone()), elapsed, window)
+}
+/// Termination of a process whose creation, logged in the context, is a watched child
+fn check_short_lived_live(
+    event: &ProcessTerminateEvent,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let guid = event.event_data.process_guid.process_guid;
+    let creation = context.iter().rev().find(|e| match e {
+        SysmonEvent::ProcessCreate(e) => {
+            e.event_data.process_guid.process_guid == guid
+                && e.system.computer.computer == event.system.computer.computer
+        }
+        _ => false,
+    })?;
+    let lifetime = event.system.time_created.time? - creation.system().time_created.time?;
+    short_lived_anomaly(Arc::new(creation.clone()), lifetime)
+}
+/// Files written to Startup folders, task folders or web roots by other processes than installers:
+/// persistence and web shells that need no registry change
+fn check_file_drop(event: &FileCreateEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let location = file_drops::drop_location(&data.image.image, &data.target_filename)?;
+    Some(AnomalyKind::SuspiciousFileDrop {
+        event: trigger.shared(),
+        process: trigger.event.process_name().to_string(),
+        target: data.target_filename.clone(),
+        location,
+    })
+}
+/// Streams other than the Mark-of-the-Web holding a PE image or a script: a way to hide payloads
+/// from directory listings and file scanners
+fn check_executable_stream(event: &FileStreamEvent, trigger: &Trigger) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let reason = streams::executable_stream(data)?;
+    Some(AnomalyKind::ExecutableStream {
+        event: trigger.shared(),
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        target: data.target_filename.clone(),
+        reason,
+    })
+}
+/// Script blocks hiding what they run behind encoding and obfuscation. `text` is that of the
+/// whole block, reassembled from its parts, and `event` the part reported.
+fn check_script_block(
+    event: &ScriptBlockEvent,
+    text: &str,
+    shared: impl FnOnce() -> Arc<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let result = script_block::score_script_block(text);
+    if result.score < script_block::REPORT_SCORE {
+        return None;
+    }
+    let process = match data.image.rsplit('\\').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("PID {}", data.process_id),
+    };
+    Some(AnomalyKind::ObfuscatedScriptBlock {
+        event: shared(),
+        process,
+        script_block_id: data.script_block_id.clone(),
+        indicators: result.indicators,
+        score: result.score,
+    })
+}
+/// Inconsistency between a process creation and the logged creation (time, PID) of its parent
+fn logged_parent_mismatch(
+    event: &ProcessCreateEvent,
+    (started, pid): (DateTime<Utc>, u64),
+    time: DateTime<Utc>,
+) -> Option<SpoofingReason> {
+    if started > time {
+        Some(SpoofingReason::ParentCreatedAfterChild)
+    } else if pid != event.event_data.parent_process_id {
+        Some(SpoofingReason::ParentPidMismatch)
+    } else {
+        None
+    }
+}
+fn parent_spoofing_anomaly(
+    event: &ProcessCreateEvent,
+    shared: Arc<SysmonEvent>,
+    reason: SpoofingReason,
+) -> AnomalyKind {
+    let file_name = |image: &str| image.rsplit('\\').next().unwrap_or(image).to_string();
+    AnomalyKind::ParentSpoofing {
+        event: shared,
+        process: file_name(&event.event_data.image),
+        parent: file_name(&event.event_data.parent_image),
+        reason,
+    }
+}
+/// Process creation whose parent, logged in the context, was created after it or has another PID.
+/// Parents never seen are only reported in batch mode.
+fn check_parent_spoofing_live(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let parent = event.event_data.parent_process_guid.process_guid;
+    let time = event.system.time_created.time?;
+    let logged = context.iter().rev().find_map(|e| match e {
+        SysmonEvent::ProcessCreate(e)
+            if e.event_data.process_guid.process_guid == parent
+                && e.system.computer.computer == event.system.computer.computer =>
+        {
+            Some((e.system.time_created.time?, e.event_data.process_id))
+        }
+        _ => None,
+    });
+    let reason = logged_parent_mismatch(event, logged?, time)?;
+    Some(parent_spoofing_anomaly(event, trigger.shared(), reason))
+}
+/// Process created at High or System integrity by a parent at Medium or lower, unless a consent
+/// prompt started shortly before approved it
+fn integrity_escalation_anomaly(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    parent: Integrity,
+    consent: Option<DateTime<Utc>>,
+    time: DateTime<Utc>,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let level = Integrity::from_level(&data.integrity_level.integrity_level)?;
+    if !elevation::escalates(&data.parent_image.image, parent, level)
+        || consent.is_some_and(|consent| elevation::approved(consent, time))
+    {
+        return None;
     }
-    None
+    let file_name = |image: &str| image.rsplit('\\').next().unwrap_or(image).to_string();
+    Some(AnomalyKind::IntegrityEscalation {
+        event: trigger.shared(),
+        process: file_name(&data.image.image),
+        parent: file_name(&data.parent_image.image),
+        user: data.user.user.clone(),
+        from: parent,
+        to: level,
+    })
+}
+/// Process created at a higher integrity than its parent, logged in the context, without a
+/// consent prompt in the context approving it
+fn check_integrity_escalation_live(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let parent = event.event_data.parent_process_guid.process_guid;
+    let computer = &event.system.computer.computer;
+    let time = event.system.time_created.time?;
+    let same_computer =
+        |e: &ProcessCreateEvent| e.system.computer.computer.eq_ignore_ascii_case(computer);
+    let parent = context.iter().rev().find_map(|e| match e {
+        SysmonEvent::ProcessCreate(e)
+            if e.event_data.process_guid.process_guid == parent && same_computer(e) =>
+        {
+            Integrity::from_level(&e.event_data.integrity_level.integrity_level)
+        }
+        _ => None,
+    })?;
+    let consent = context.iter().rev().find_map(|e| match e {
+        SysmonEvent::ProcessCreate(e)
+            if elevation::is_consent(&e.event_data.image.image) && same_computer(e) =>
+        {
+            e.system.time_created.time
+        }
+        _ => None,
+    });
+    integrity_escalation_anomaly(event, trigger, parent, consent, time)
+}
+fn lateral_movement_anomaly(
+    trigger: &Trigger,
+    source: &str,
+    process: &str,
+    fan_out: &FanOut,
+    service: Option<CreatedService>,
+) -> AnomalyKind {
+    AnomalyKind::LateralMovement {
+        event: trigger.shared(),
+        source: source.to_string(),
+        process: process.to_string(),
+        hosts: fan_out.hosts(),
+        ports: fan_out.ports.iter().copied().collect(),
+        time_window_seconds: fan_out.seconds(),
+        service,
+    }
+}
+/// Connections of the processes of the context to lateral movement ports since `since`, by
+/// computer, lowercased, and ProcessGuid, with the first connection event of each process
+fn lateral_connections_live(
+    context: &VecDeque<SysmonEvent>,
+    since: DateTime<Utc>,
+) -> HashMap<(String, uuid::Uuid), (&NetworkEvent, Vec<LateralConnection>)> {
+    let mut processes: HashMap<_, (&NetworkEvent, Vec<LateralConnection>)> = HashMap::new();
+    for e in context {
+        let SysmonEvent::OutboundNetwork(e) = e else {
+            continue;
+        };
+        let Some(connection) = e
+            .system
+            .time_created
+            .time
+            .filter(|time| *time >= since)
+            .and_then(|time| LateralConnection::new(&e.event_data, time))
+        else {
+            continue;
+        };
+        let key = (
+            e.system.computer.computer.to_lowercase(),
+            e.event_data.process_guid.process_guid,
+        );
+        processes
+            .entry(key)
+            .or_insert_with(|| (e, Vec::new()))
+            .1
+            .push(connection);
+    }
+    processes
+}
+/// Flag a connection pushing its process over the fan-out threshold (for live analysis)
+fn check_lateral_movement_live(
+    event: &NetworkEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let time = event.system.time_created.time?;
+    let current = LateralConnection::new(&event.event_data, time)?;
+    let source = &event.system.computer.computer;
+    let key = (
+        source.to_lowercase(),
+        event.event_data.process_guid.process_guid,
+    );
+    let window_start = time - Duration::seconds(lateral::FAN_OUT_WINDOW_SECONDS);
+    let mut processes = lateral_connections_live(context, window_start);
+    let previous = processes
+        .remove(&key)
+        .map(|(_, connections)| connections)
+        .unwrap_or_default();
+    // Only alert when this connection makes the fan-out
+    if FanOut::of(&previous).is_some() {
+        return None;
+    }
+    let fan_out = FanOut::of(previous.iter().chain(std::iter::once(&current)))?;
+    let image = &event.event_data.image;
+    let process = image.rsplit('\\').next().unwrap_or(image);
+    Some(lateral_movement_anomaly(
+        trigger, source, process, &fan_out, None,
+    ))
+}
+/// Service created on a host that a fan-out of the context reached just before (for live
+/// analysis)
+fn check_service_after_fan_out_live(
+    event: &RegistryEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let service = CreatedService::new(&event.event_data)?;
+    let time = event.system.time_created.time?;
+    let computer = &event.system.computer.computer;
+    let since =
+        time - Duration::seconds(lateral::SERVICE_WINDOW_SECONDS + lateral::FAN_OUT_WINDOW_SECONDS);
+    let (first, fan_out) = lateral_connections_live(context, since)
+        .into_values()
+        .filter_map(|(first, connections)| Some((first, FanOut::first(&connections)?)))
+        .filter(|(_, fan_out)| fan_out.reached(computer) && fan_out.followed_by(time))
+        .min_by_key(|(_, fan_out)| fan_out.start)?;
+    let image = &first.event_data.image;
+    Some(lateral_movement_anomaly(
+        trigger,
+        &first.system.computer.computer,
+        image.rsplit('\\').next().unwrap_or(image),
+        &fan_out,
+        Some(service),
+    ))
+}
+fn download_executed_anomaly(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    origin: MarkOfTheWeb,
+) -> AnomalyKind {
+    let image = &event.event_data.image;
+    AnomalyKind::DownloadExecuted {
+        event: trigger.shared(),
+        process: image.rsplit('\\').next().unwrap_or(image).to_string(),
+        origin,
+    }
+}
+/// Process started from a file whose Mark-of-the-Web, seen in the context, says it was
+/// downloaded from the internet
+fn check_download_executed_live(
+    event: &ProcessCreateEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let image = &event.event_data.image.image;
+    let computer = &event.system.computer.computer;
+    let mark = context.iter().rev().find_map(|e| match e {
+        SysmonEvent::FileStreamCreate(stream)
+            if stream
+                .system
+                .computer
+                .computer
+                .eq_ignore_ascii_case(computer) =>
+        {
+            streams::downloaded_file(&stream.event_data)
+                .filter(|(file, _)| file.eq_ignore_ascii_case(image))
+                .map(|(_, mark)| mark)
+        }
+        _ => None,
+    })?;
+    Some(download_executed_anomaly(event, trigger, mark))
+}
+/// Checks remote addresses and queried domains against threat-intel feeds
+fn check_ioc(trigger: &Trigger, feeds: &[IocFeed]) -> Option<AnomalyKind> {
+    if feeds.is_empty() {
+        return None;
+    }
+    let event = trigger.event;
+    let (addresses, domains): (Vec<&str>, Vec<&str>) = match event {
+        SysmonEvent::OutboundNetwork(e) | SysmonEvent::InboundNetwork(e) => {
+            let data = &e.event_data;
+            // The remote side of an inbound connection is its source
+            let (ip, hostname) = if data.initiated {
+                (&data.destination_ip, &data.destination_hostname)
+            } else {
+                (&data.source_ip, &data.source_hostname)
+            };
+            (vec![ip.as_str()], hostname.as_deref().into_iter().collect())
+        }
+        SysmonEvent::DnsQuery(e) => (
+            e.event_data.resolved_addresses().collect(),
+            vec![e.event_data.query_name.as_str()],
+        ),
+        _ => return None,
+    };
+    feeds.iter().find_map(|feed| {
+        let (value, indicator) = domains
+            .iter()
+            .find_map(|domain| {
+                feed.match_domain(domain)
+                    .map(|indicator| (*domain, indicator))
+            })
+            .or_else(|| {
+                addresses
+                    .iter()
+                    .find_map(|ip| feed.match_ip(ip).map(|indicator| (*ip, indicator)))
+            })?;
+        Some(AnomalyKind::IocMatch {
+            event: trigger.shared(),
+            process: event.process_name().to_string(),
+            value: value.to_string(),
+            indicator: indicator.to_string(),
+            feed: feed.name.clone(),
+        })
+    })
+}
+/// Match `text` against a pattern where `*` matches any sequence of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+/// Mean interval and relative jitter of sorted timestamps, if they are regular enough
+fn regular_interval(times: &[DateTime<Utc>], max_jitter: f64) -> Option<(f64, f64)> {
+    let intervals: Vec<f64> = times
+        .windows(2)
+        .map(|pair| pair[1].signed_duration_since(pair[0]).num_milliseconds() as f64 / 1000.0)
+        .collect();
+    if intervals.is_empty() {
+        return None;
+    }
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    if mean < BEACON_MIN_INTERVAL_SECONDS {
+        return None;
+    }
+    let variance =
+        intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+    let jitter = variance.sqrt() / mean;
+    (jitter <= max_jitter).then_some((mean, jitter))
+}
+fn beaconing_anomaly(
+    event: &NetworkEvent,
+    shared: Arc<SysmonEvent>,
+    count: usize,
+    interval: f64,
+    jitter: f64,
+) -> AnomalyKind {
+    let data = &event.event_data;
+    AnomalyKind::Beaconing {
+        event: shared,
+        process: data
+            .image
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&data.image)
+            .to_string(),
+        destination: format!("{}:{}", data.destination_ip, data.destination_port),
+        count,
+        interval_seconds: interval,
+        jitter,
+    }
+}
+/// Stateful beaconing check using context buffer (for live analysis).
+/// Alerts once, when the connection count to a destination reaches the minimum.
+fn check_beaconing_live(
+    event: &NetworkEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+    config: &DetectionConfig,
+) -> Option<AnomalyKind> {
+    let data = &event.event_data;
+    let time = event.system().time_created.time?;
+    let mut times: Vec<DateTime<Utc>> = context
+        .iter()
+        .filter_map(|e| match e {
+            SysmonEvent::OutboundNetwork(e)
+                if e.event_data.image.eq_ignore_ascii_case(&data.image)
+                    && e.event_data.destination_ip == data.destination_ip
+                    && e.event_data.destination_port == data.destination_port =>
+            {
+                e.system().time_created.time
+            }
+            _ => None,
+        })
+        .collect();
+    times.push(time);
+    let min_count = config.beacon_min_count.max(3);
+    if times.len() != min_count {
+        return None;
+    }
+    times.sort();
+    let (interval, jitter) = regular_interval(&times, config.beacon_jitter)?;
+    Some(beaconing_anomaly(
+        event,
+        trigger.shared(),
+        times.len(),
+        interval,
+        jitter,
+    ))
+}
+/// Directory part of a Windows path (lowercase)
+fn parent_directory(path: &str) -> String {
+    path.rsplit_once('\\')
+        .map(|(dir, _)| dir)
+        .unwrap_or("")
+        .to_lowercase()
+}
+/// Deleted files, distinct directories and seconds between the first and last deletion, when
+/// there are enough deletions across enough directories to be a mass deletion
+fn mass_deletion_stats<'a>(
+    deletions: impl Iterator<Item = &'a (DateTime<Utc>, String)>,
+) -> Option<(usize, usize, i64)> {
+    let mut count = 0;
+    let mut first = None;
+    let mut last = None;
+    let mut directories = HashSet::new();
+    for (time, directory) in deletions {
+        count += 1;
+        first = Some(first.map_or(*time, |first: DateTime<Utc>| first.min(*time)));
+        last = Some(last.map_or(*time, |last: DateTime<Utc>| last.max(*time)));
+        directories.insert(directory.as_str());
+    }
+    if count < MASS_DELETION_THRESHOLD_COUNT || directories.len() < MASS_DELETION_MIN_DIRECTORIES {
+        return None;
+    }
+    let seconds = match (first, last) {
+        (Some(first), Some(last)) => last.signed_duration_since(first).num_seconds(),
+        _ => 0,
+    };
+    Some((count, directories.len(), seconds))
+}
+/// Flag a process deleting many files across many directories within the window
+fn mass_deletion_anomaly<'a>(
+    event: &FileDeleteEvent,
+    trigger: &Trigger,
+    deletions: impl Iterator<Item = &'a (DateTime<Utc>, String)>,
+) -> Option<AnomalyKind> {
+    let (deleted, directories, time_window_seconds) = mass_deletion_stats(deletions)?;
+    let image = &event.event_data.image;
+    Some(AnomalyKind::MassFileDeletion {
+        event: trigger.shared(),
+        process: image.rsplit('\\').next().unwrap_or(image).to_string(),
+        deleted,
+        directories,
+        time_window_seconds,
+    })
+}
+/// Stateful check for mass file deletion using context buffer (for live analysis)
+fn check_mass_deletion_live(
+    event: &FileDeleteEvent,
+    trigger: &Trigger,
+    context: &VecDeque<SysmonEvent>,
+) -> Option<AnomalyKind> {
+    let guid = event.event_data.process_guid.process_guid;
+    let time = event.system().time_created.time?;
+    let window_start = time - Duration::seconds(MASS_DELETION_WINDOW_SECONDS);
+    let mut previous = Vec::new();
+    for e in context.iter().rev() {
+        let SysmonEvent::FileDelete(e) = e else {
+            continue;
+        };
+        if e.event_data.process_guid.process_guid != guid {
+            continue;
+        }
+        let Some(e_time) = e.system().time_created.time else {
+            continue;
+        };
+        // Stop when the event is too old
+        if e_time < window_start {
+            break;
+        }
+        previous.push((e_time, parent_directory(&e.event_data.target_filename)));
+    }
+    // Only alert when this deletion pushes the process over the threshold
+    if mass_deletion_stats(previous.iter()).is_some() {
+        return None;
+    }
+    let current = (time, parent_directory(&event.event_data.target_filename));
+    mass_deletion_anomaly(
+        event,
+        trigger,
+        previous.iter().chain(std::iter::once(&current)),
+    )
 }
 /// Check process depth context buffer (for live analysis)
 fn check_process_depth(
     event: &ProcessCreateEvent,
+    trigger: &Trigger,
     context: &VecDeque<SysmonEvent>,
-) -> Option<Anomaly> {
+) -> Option<AnomalyKind> {
     let data = &event.event_data;
     let parent_pid = data.parent_process_id;
     let mut depth = 1;
@@ -327,8 +3947,8 @@ fn check_process_depth(
         }
     }
     if depth > DEEP_NESTING_THRESHOLD {
-        return Some(Anomaly::DeepProcessTree {
-            event: SysmonEvent::ProcessCreate(event.clone()),
+        return Some(AnomalyKind::DeepProcessTree {
+            event: trigger.shared(),
             depth,
         });
     }
@@ -338,32 +3958,1366 @@ fn check_process_depth(
 fn check_event_storm_live(
     event: &ProcessCreateEvent,
     context: &VecDeque<SysmonEvent>,
-) -> Option<Anomaly> {
+    config: &DetectionConfig,
+) -> Option<AnomalyKind> {
     let event_id = event.system().event_id.event_id;
-    let window_end_time =
-        match DateTime::parse_from_rfc3339(&event.system().time_created.system_time) {
-            Ok(dt) => dt.with_timezone(&Utc),
-            Err(_) => return None, // skip malformed time
-        };
-    let window_start_time = window_end_time - Duration::seconds(EVENT_STORM_WINDOW_SECONDS as i64);
+    // Skip malformed time
+    let window_end_time = event.system().time_created.time?;
+    let window_start_time = window_end_time - config.event_storm_window;
     let mut count = 0;
+    let mut start = window_end_time;
+    let mut processes: HashMap<String, usize> = HashMap::new();
     for e in context.iter().rev() {
-        let e_time = match DateTime::parse_from_rfc3339(&e.system().time_created.system_time) {
-            Ok(dt) => dt.with_timezone(&Utc),
-            Err(_) => continue, // skip invalid timestamps
+        let Some(e_time) = e.system().time_created.time else {
+            continue; // skip invalid timestamps
         };
         // Stop when the event is too old
         if e_time < window_start_time {
             break;
         }
         count += 1;
+        start = start.min(e_time);
+        if !e.process_name().is_empty() {
+            *processes.entry(e.process_name().to_string()).or_default() += 1;
+        }
     }
-    if count >= EVENT_STORM_THRESHOLD_COUNT {
-        return Some(Anomaly::EventStorm {
+    if count >= config.event_storm_threshold {
+        return Some(AnomalyKind::EventStorm {
             event_id,
             count,
-            time_window_seconds: EVENT_STORM_WINDOW_SECONDS as i64,
+            time_window_seconds: config.event_storm_window.num_seconds(),
+            start: Some(start),
+            end: Some(window_end_time),
+            top_processes: top_processes(processes),
         });
     }
     None
 }
+/// Process names with the most events, at most [`EVENT_STORM_TOP_PROCESSES`]
+fn top_processes(processes: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut top: Vec<_> = processes.into_iter().collect();
+    top.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    top.truncate(EVENT_STORM_TOP_PROCESSES);
+    top
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{
+        PARENT_GUID, PROCESS_GUID, process_event, sysmon_event, terminate_event,
+    };
+
+    fn file_delete_event(second: u32, path: &str) -> SysmonEvent {
+        sysmon_event(
+            23,
+            5,
+            &format!("2025-01-01T10:00:{second:02}.000Z"),
+            &format!(
+                r#"<Data Name="ProcessGuid">{{{PROCESS_GUID}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="User">TEST-PC\Administrator</Data>
+        <Data Name="Image">C:\Users\Public\locker.exe</Data>
+        <Data Name="TargetFilename">{path}</Data>
+        <Data Name="Hashes">SHA1=1234567890ABCDEF</Data>
+        <Data Name="IsExecutable">false</Data>
+        <Data Name="Archived">true</Data>"#
+            ),
+        )
+    }
+
+    fn pipe_event(event_id: u16, pipe: &str) -> SysmonEvent {
+        sysmon_event(
+            event_id,
+            1,
+            "2025-01-01T10:00:00.000Z",
+            &format!(
+                r#"<Data Name="ProcessGuid">{{{PROCESS_GUID}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="PipeName">{pipe}</Data>
+        <Data Name="Image">C:\Windows\System32\rundll32.exe</Data>"#
+            ),
+        )
+    }
+
+    fn connection_event(time: DateTime<Utc>, destination: &str) -> SysmonEvent {
+        sysmon_event(
+            3,
+            5,
+            &time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            &format!(
+                r#"<Data Name="ProcessGuid">{{{PROCESS_GUID}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="Image">C:\Users\Public\update.exe</Data>
+        <Data Name="Protocol">tcp</Data>
+        <Data Name="Initiated">true</Data>
+        <Data Name="SourceIsIpv6">false</Data>
+        <Data Name="SourceIp">10.0.0.5</Data>
+        <Data Name="SourcePort">50000</Data>
+        <Data Name="DestinationIsIpv6">false</Data>
+        <Data Name="DestinationIp">{destination}</Data>
+        <Data Name="DestinationPort">443</Data>"#
+            ),
+        )
+    }
+
+    #[test]
+    fn regular_connections_are_beaconing() {
+        let start: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let config = DetectionConfig::default();
+        // Every 60s with a second of jitter
+        let mut events: Vec<SysmonEvent> = (0..config.beacon_min_count as i64)
+            .map(|i| connection_event(start + Duration::seconds(i * 60 + i % 2), "203.0.113.10"))
+            .collect();
+        // Irregular traffic to another destination
+        events.extend(
+            [0, 5, 90, 100, 400, 410, 1000, 1300, 1301, 2000]
+                .into_iter()
+                .map(|s| connection_event(start + Duration::seconds(s), "198.51.100.7")),
+        );
+        let beacons: Vec<Anomaly> = detect_anomalies_with_config(&events, &config)
+            .into_iter()
+            .filter(|a| a.rule_name() == "Beaconing")
+            .collect();
+        assert_eq!(beacons.len(), 1);
+        assert!(beacons[0].description().contains("203.0.113.10:443"));
+
+        let strict = DetectionConfig {
+            beacon_jitter: 0.001,
+            ..config
+        };
+        assert!(
+            detect_anomalies_with_config(&events, &strict)
+                .iter()
+                .all(|a| a.rule_name() != "Beaconing")
+        );
+    }
+
+    #[test]
+    fn findings_share_the_triggering_event() {
+        let event = process_event(
+            "2025-01-01T10:00:00.000Z",
+            r"C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE",
+            r"C:\Users\Bob\AppData\Local\Temp\cmd.exe",
+        );
+        let anomalies = detect_anomalies(std::slice::from_ref(&event));
+        let events: Vec<&SysmonEvent> = anomalies
+            .iter()
+            .filter_map(|anomaly| anomaly.event())
+            .collect();
+        assert!(events.len() >= 2, "{anomalies:?}");
+        assert!(events.iter().all(|shared| std::ptr::eq(*shared, events[0])));
+    }
+
+    #[test]
+    fn streaming_matches_batch_detection() {
+        let start: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let config = DetectionConfig::default();
+        let mut events: Vec<SysmonEvent> = (0..20)
+            .map(|i| connection_event(start + Duration::seconds(i * 30), "203.0.113.10"))
+            .collect();
+        // Burst of connections for an event storm
+        events.extend(
+            (0..DEFAULT_EVENT_STORM_THRESHOLD as i64)
+                .map(|i| connection_event(start + Duration::milliseconds(i * 10), "192.0.2.1")),
+        );
+        events.reverse();
+        let describe = |anomalies: Vec<Anomaly>| -> Vec<String> {
+            anomalies
+                .iter()
+                .map(|anomaly| anomaly.description())
+                .collect()
+        };
+        let batch = describe(detect_anomalies_with_config(&events, &config));
+        assert!(batch.contains(
+            &"Event Storm: ID 3 (51 events in 0s, top processes: update.exe 51)".to_string()
+        ));
+        let storms = aggregate_anomalies(&detect_anomalies_with_config(&events, &config));
+        let storm = storms
+            .iter()
+            .find(|group| group.anomaly.rule_id() == "SLA-005")
+            .unwrap();
+        assert_eq!(storm.first_seen, Some(start));
+        assert_eq!(storm.last_seen, Some(start + Duration::milliseconds(490)));
+        assert!(
+            batch
+                .iter()
+                .any(|d| d.contains("20 connections every 30.0s"))
+        );
+        assert_eq!(describe(detect_anomalies_streaming(events, &config)), batch);
+    }
+
+    #[test]
+    fn incremental_matches_batch_detection() {
+        let start: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let config = DetectionConfig::default();
+        let mut events: Vec<SysmonEvent> = (0..20)
+            .map(|i| connection_event(start + Duration::seconds(i * 30), "203.0.113.10"))
+            .collect();
+        events.extend(
+            (0..DEFAULT_EVENT_STORM_THRESHOLD as i64)
+                .map(|i| connection_event(start + Duration::milliseconds(i * 10), "192.0.2.1")),
+        );
+        let describe = |anomalies: Vec<Anomaly>| -> Vec<String> {
+            anomalies
+                .iter()
+                .map(|anomaly| anomaly.description())
+                .collect()
+        };
+        let batch = describe(detect_anomalies_with_config(&events, &config));
+        let incremental = |events: &[SysmonEvent]| {
+            let mut detector = IncrementalDetector::new(&config, false);
+            detector.chunk_size = 4;
+            for index in 0..events.len() {
+                detector.push(events, index, index);
+            }
+            let out_of_order = detector.out_of_order;
+            (describe(detector.finish(events).0), out_of_order)
+        };
+        let mut sorted = events.clone();
+        sorted.sort_by_key(|event| event.system().time_created.time);
+        assert_eq!(incremental(&sorted), (batch.clone(), false));
+        // The burst arrives after later connections were analyzed
+        assert_eq!(incremental(&events), (batch, true));
+
+        // Script blocks held back until their processes are known do not restart the analysis
+        let payload = "I`E`X ([Convert]::FromBase64String($b))";
+        sorted.extend((0..2).map(|_| script_block_event("2025-01-01T10:00:05Z", payload)));
+        let batch = describe(detect_anomalies_with_config(&sorted, &config));
+        assert!(batch.iter().any(|finding| finding.contains("PowerShell")));
+        assert_eq!(incremental(&sorted), (batch, false));
+    }
+
+    #[test]
+    fn event_storm_per_contiguous_burst() {
+        let start: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let config = DetectionConfig {
+            event_storm_threshold: 5,
+            event_storm_window: Duration::seconds(2),
+            ..DetectionConfig::default()
+        };
+        let burst = |offset: i64, count: i64| -> Vec<SysmonEvent> {
+            (0..count)
+                .map(|i| {
+                    let time = start + Duration::seconds(offset) + Duration::milliseconds(i * 100);
+                    connection_event(time, "192.0.2.1")
+                })
+                .collect()
+        };
+        let mut events = burst(0, 8);
+        // Slower than the threshold, neither a storm nor part of one
+        events.extend(
+            (0..4).map(|i| connection_event(start + Duration::seconds(60 + i * 5), "192.0.2.1")),
+        );
+        events.extend(burst(600, 6));
+        let storms: Vec<String> = detect_anomalies_with_config(&events, &config)
+            .iter()
+            .filter(|anomaly| anomaly.rule_name() == "EventStorm")
+            .map(|anomaly| anomaly.description())
+            .collect();
+        assert_eq!(
+            storms,
+            [
+                "Event Storm: ID 3 (8 events in 0s, top processes: update.exe 8)",
+                "Event Storm: ID 3 (6 events in 0s, top processes: update.exe 6)",
+            ]
+        );
+        assert!(
+            !detect_anomalies(&events)
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "EventStorm")
+        );
+    }
+
+    #[test]
+    fn offensive_pipe_names() {
+        let events = [
+            pipe_event(17, r"\PSEXESVC"),
+            pipe_event(17, r"\MSSE-1234-server"),
+            pipe_event(18, r"\postex_ssh_a1b2"),
+            pipe_event(17, r"\MSSE-1234"),
+            pipe_event(17, r"\lsass"),
+        ];
+        let anomalies = detect_anomalies(&events);
+        assert_eq!(anomalies.len(), 3);
+        assert!(anomalies.iter().all(|a| a.rule_name() == "SuspiciousPipe"));
+        assert!(anomalies[2].event().unwrap().is_pipe_connected());
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("a*bc", "abc_"));
+        assert!(!wildcard_match("ab*b", "ab"));
+    }
+
+    #[test]
+    fn mass_deletion_across_directories() {
+        let events: Vec<SysmonEvent> = (0..MASS_DELETION_THRESHOLD_COUNT)
+            .map(|i| {
+                file_delete_event(
+                    (i % 60) as u32,
+                    &format!(r"C:\Users\Test\dir{}\file{i}.docx", i % 10),
+                )
+            })
+            .collect();
+        let anomalies: Vec<Anomaly> = detect_anomalies(&events)
+            .into_iter()
+            .filter(|a| a.rule_name() == "MassFileDeletion")
+            .collect();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].severity(), Severity::Critical);
+
+        // Same number of deletions in a single directory is not flagged
+        let events: Vec<SysmonEvent> = (0..MASS_DELETION_THRESHOLD_COUNT)
+            .map(|i| file_delete_event(0, &format!(r"C:\Temp\file{i}.tmp")))
+            .collect();
+        assert!(
+            detect_anomalies(&events)
+                .iter()
+                .all(|a| a.rule_name() != "MassFileDeletion")
+        );
+    }
+
+    #[test]
+    fn identical_anomalies_are_grouped() {
+        let events = [
+            process_event(
+                "2025-01-01T10:00:05.000Z",
+                r"C:\Office\WINWORD.EXE",
+                r"C:\Windows\System32\cmd.exe",
+            ),
+            process_event(
+                "2025-01-01T10:00:01.000Z",
+                r"C:\Office\winword.exe",
+                r"C:\Windows\System32\cmd.exe",
+            ),
+            process_event(
+                "2025-01-01T10:00:03.000Z",
+                r"C:\Office\excel.exe",
+                r"C:\Windows\System32\cmd.exe",
+            ),
+        ];
+        let anomalies = detect_anomalies(&events);
+        assert_eq!(anomalies.len(), 3);
+        let groups = aggregate_anomalies(&anomalies);
+        assert_eq!(groups.len(), 2);
+        let word = groups
+            .iter()
+            .find(|g| g.anomaly.description().to_lowercase().contains("winword"))
+            .unwrap();
+        assert_eq!(word.count, 2);
+        assert_eq!(word.first_seen, "2025-01-01T10:00:01Z".parse().ok());
+        assert_eq!(word.last_seen, "2025-01-01T10:00:05Z".parse().ok());
+    }
+
+    fn signed(mut event: SysmonEvent, status: &str) -> ProcessCreateEvent {
+        let SysmonEvent::ProcessCreate(ref mut process) = event else {
+            unreachable!()
+        };
+        process.event_data.signed = Some(true);
+        process.event_data.signature_status = Some(status.to_string());
+        process.clone()
+    }
+
+    #[test]
+    fn untrusted_executable_locations_and_signatures() {
+        let time = "2025-01-01T10:00:00.000Z";
+        let explorer = r"C:\Windows\explorer.exe";
+        let events = [
+            process_event(time, explorer, r"C:\Users\Bob\AppData\Local\Temp\a.exe"),
+            process_event(time, explorer, r"C:\Users\Bob\Downloads\doc.txt:b.exe"),
+            process_event(time, explorer, r"C:\Windows\System32\notepad.exe"),
+        ];
+        let reasons: Vec<String> = detect_anomalies(&events)
+            .iter()
+            .filter_map(|anomaly| match anomaly.kind() {
+                AnomalyKind::UntrustedExecutable { reason, .. } => Some(reason.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "Executable a.exe runs from a temporary directory",
+                "Executable doc.txt:b.exe runs from an alternate data stream",
+            ]
+        );
+
+        let check = |event: ProcessCreateEvent| {
+            let wrapped = SysmonEvent::ProcessCreate(event.clone());
+            check_untrusted_executable(&event, &Trigger::new(&wrapped), None)
+        };
+        assert!(check(signed(events[0].clone(), "Valid")).is_none());
+        let anomaly = check(signed(events[2].clone(), "Revoked")).unwrap();
+        assert_eq!(anomaly.default_severity(), Severity::High);
+        assert_eq!(
+            anomaly.description(),
+            "Untrusted Executable: Invalid signature of notepad.exe (Revoked)"
+        );
+    }
+
+    #[test]
+    fn executables_from_shares_and_removable_drives() {
+        let time = "2025-01-01T10:00:00.000Z";
+        let services = r"C:\Windows\System32\services.exe";
+        let events = [
+            process_event(time, services, r"\\FILESRV01\ADMIN$\PSEXESVC.exe"),
+            process_event(time, services, r"\\corp.local\NETLOGON\logon.bat"),
+            process_event(time, services, r"E:\autorun\setup.exe"),
+            process_event(time, services, r"C:\Windows\System32\svchost.exe"),
+        ];
+        let external = |config: &DetectionConfig| -> Vec<(String, Severity)> {
+            detect_anomalies_with_config(&events, config)
+                .iter()
+                .filter(|anomaly| anomaly.rule_id() == "SLA-021")
+                .map(|anomaly| (anomaly.description(), anomaly.severity()))
+                .collect()
+        };
+        assert_eq!(
+            external(&DetectionConfig::default()),
+            [
+                (
+                    r"External Executable: PSEXESVC.exe runs from network share \\FILESRV01\ADMIN$"
+                        .to_string(),
+                    Severity::High
+                ),
+                (
+                    r"External Executable: logon.bat runs from network share \\corp.local\NETLOGON"
+                        .to_string(),
+                    Severity::Medium
+                ),
+            ]
+        );
+        let config = DetectionConfig {
+            removable_drives: vec!['E'],
+            ..DetectionConfig::default()
+        };
+        let findings = external(&config);
+        assert_eq!(
+            findings[2],
+            (
+                "External Executable: setup.exe runs from removable drive E:".to_string(),
+                Severity::High
+            )
+        );
+    }
+
+    #[test]
+    fn spoofed_parents() {
+        // Sysmon GUID of a process of the test machine created at `time`
+        let guid = |time: &str, pid: u32| {
+            let seconds = time.parse::<DateTime<Utc>>().unwrap().timestamp();
+            uuid::Uuid::parse_str(&format!(
+                "365abb72-{:04x}-{:04x}-0000-0010{pid:08x}",
+                seconds & 0xffff,
+                seconds >> 16
+            ))
+            .unwrap()
+        };
+        let create = |time: &str, pid: u32, parent: (&str, u32), parent_pid: u32, image: &str| {
+            let mut event = process_event(time, r"C:\Windows\explorer.exe", image);
+            if let SysmonEvent::ProcessCreate(e) = &mut event {
+                e.event_data.process_guid.process_guid = guid(time, pid);
+                e.event_data.process_id = pid.into();
+                e.event_data.parent_process_guid.process_guid = guid(parent.0, parent.1);
+                e.event_data.parent_process_id = parent_pid.into();
+            }
+            event
+        };
+        let explorer = ("2025-01-01T09:00:00Z", 100);
+        let cmd = ("2025-01-01T10:00:05Z", 200);
+        let events = [
+            create(cmd.0, cmd.1, explorer, 100, "cmd.exe"),
+            create("2025-01-01T10:01:00Z", 301, cmd, 999, "a.exe"),
+            create(
+                "2025-01-01T10:02:00Z",
+                302,
+                ("2025-01-01T10:05:00Z", 400),
+                400,
+                "b.exe",
+            ),
+            create(
+                "2025-01-01T10:03:00Z",
+                303,
+                ("2025-01-01T10:00:30Z", 500),
+                500,
+                "c.exe",
+            ),
+            create("2025-01-01T10:04:00Z", 304, cmd, 200, "d.exe"),
+            create("2025-01-01T10:05:00Z", 400, explorer, 100, "e.exe"),
+        ];
+        let spoofed = |anomalies: Vec<Anomaly>| -> Vec<_> {
+            anomalies
+                .iter()
+                .filter_map(|anomaly| match anomaly.kind() {
+                    AnomalyKind::ParentSpoofing {
+                        process, reason, ..
+                    } => Some((process.clone(), *reason)),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            spoofed(detect_anomalies(&events)),
+            [
+                ("a.exe".to_string(), SpoofingReason::ParentPidMismatch),
+                ("b.exe".to_string(), SpoofingReason::ParentCreatedAfterChild),
+                ("c.exe".to_string(), SpoofingReason::ParentNotInLog),
+            ]
+        );
+
+        let context: VecDeque<_> = [&events[0], &events[5]].into_iter().cloned().collect();
+        let live = |event: &SysmonEvent| {
+            detect_anomalies_live(event, &context)
+                .iter()
+                .any(|anomaly| anomaly.rule_id() == "SLA-022")
+        };
+        assert!(live(&events[1]));
+        assert!(live(&events[2]));
+        assert!(!live(&events[3]));
+        assert!(!live(&events[4]));
+
+        // A day later the processes of the first chunk are forgotten, unseen parents reported,
+        // and a child of a parent started before the retention is not
+        let mut detector = AnomalyDetector::new(DetectionConfig::default());
+        let later = create("2025-01-02T12:00:00Z", 600, cmd, 200, "f.exe");
+        for chunk in [&events[..], std::slice::from_ref(&later)] {
+            detector.analyze_chunk(&timed_events(chunk));
+        }
+        assert!(detector.unseen_parents.is_empty());
+        let host = detector.hosts.values().next().unwrap();
+        assert_eq!((host.seen.len(), host.started.len()), (1, 1));
+        assert_eq!(
+            spoofed(detector.finish()),
+            spoofed(detect_anomalies(&events))
+        );
+    }
+
+    #[test]
+    fn rare_process_port_pairs() {
+        let start: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let connection = |minute: i64, computer: &str, image: &str, port: u16| {
+            let mut event = connection_event(start + Duration::minutes(minute), "203.0.113.7");
+            if let SysmonEvent::OutboundNetwork(e) = &mut event {
+                e.system.computer.computer = computer.to_string();
+                e.event_data.image.image = image.to_string();
+                e.event_data.destination_port = port;
+            }
+            event
+        };
+        let browser = r"C:\Program Files\Mozilla Firefox\firefox.exe";
+        let mut events = vec![
+            connection(0, "WS1", browser, 443),
+            connection(1, "WS1", browser, 8443),
+            connection(2, "WS1", browser, 4444),
+            connection(3, "WS1", r"C:\Windows\System32\svchost.exe", 49712),
+            connection(4, "WS1", r"C:\Users\Public\update.exe", 50123),
+            connection(5, "WS1", r"C:\Users\Public\update.exe", 50123),
+            connection(6, "WS1", r"C:\Program Files\Backup\backupagent.exe", 10000),
+        ];
+        // A proxy port used by many processes, and an agent port used on every host
+        for (minute, image) in ["a.exe", "b.exe", "c.exe"].iter().enumerate() {
+            events.push(connection(10 + minute as i64, "WS1", image, 3128));
+        }
+        for (minute, computer) in ["WS1", "WS2", "WS3"].iter().enumerate() {
+            events.push(connection(20 + minute as i64, computer, "agent.exe", 9000));
+        }
+        let unusual = |config: &DetectionConfig| -> Vec<String> {
+            detect_anomalies_with_config(&events, config)
+                .iter()
+                .filter(|anomaly| anomaly.rule_name() == "UnusualPort")
+                .map(|anomaly| anomaly.description())
+                .collect()
+        };
+        assert_eq!(
+            unusual(&DetectionConfig::default()),
+            [
+                "Unusual Network Port: 4444 used by firefox.exe",
+                "Unusual Network Port: 50123 used by update.exe",
+                "Unusual Network Port: 10000 used by backupagent.exe",
+            ]
+        );
+        let mut config = DetectionConfig::default();
+        config.port_profiles.extend(
+            config_file::ConfigFile::parse(
+                "port_allowlist:\n  - image: backupagent.exe\n    ports: [\"10000-10010\"]\n",
+                false,
+            )
+            .unwrap()
+            .port_allowlist,
+        );
+        assert_eq!(unusual(&config).len(), 2);
+
+        let context: VecDeque<_> = events[..4].iter().cloned().collect();
+        let live = |event: &SysmonEvent, context: &VecDeque<SysmonEvent>| {
+            detect_anomalies_live(event, context)
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "UnusualPort")
+        };
+        assert!(live(&events[4], &context));
+        let context: VecDeque<_> = events[..5].iter().cloned().collect();
+        assert!(!live(&events[5], &context));
+    }
+
+    #[test]
+    fn binaries_checked_against_known_good_hashes() {
+        let known = "a".repeat(64);
+        let unknown = "b".repeat(64);
+        let with_hash = |image: &str, sha256: &str| {
+            let mut event = process_event(
+                "2025-01-01T10:00:00.000Z",
+                r"C:\Windows\explorer.exe",
+                image,
+            );
+            if let SysmonEvent::ProcessCreate(ref mut process) = event {
+                process.event_data.hashes.hashes = format!("SHA1=01,SHA256={sha256}");
+            }
+            event
+        };
+        let events = [
+            with_hash(r"C:\Users\Bob\AppData\Local\Temp\setup.exe", &known),
+            with_hash(r"C:\Windows\System32\svchost.exe", &unknown),
+            with_hash(r"C:\Tools\putty.exe", &unknown),
+        ];
+        let config = DetectionConfig {
+            known_good: Some(KnownGoodHashes::parse(&known).unwrap()),
+            ..DetectionConfig::default()
+        };
+        let findings: Vec<(&str, Severity)> = detect_anomalies_with_config(&events, &config)
+            .iter()
+            .filter(|anomaly| {
+                matches!(anomaly.rule_name(), "UntrustedExecutable" | "UnknownBinary")
+            })
+            .map(|anomaly| (anomaly.rule_name(), anomaly.severity()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                ("UnknownBinary", Severity::High),
+                ("UnknownBinary", Severity::Low)
+            ]
+        );
+        // Without a hash set the temporary directory is still reported
+        assert!(
+            detect_anomalies(&events)
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "UntrustedExecutable")
+        );
+    }
+
+    fn dns_event(query: &str, results: &str) -> SysmonEvent {
+        sysmon_event(
+            22,
+            5,
+            "2025-01-01T10:00:00.000Z",
+            &format!(
+                r#"<Data Name="ProcessGuid">{{{PROCESS_GUID}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="QueryName">{query}</Data>
+        <Data Name="QueryStatus">0</Data>
+        <Data Name="QueryResults">{results}</Data>
+        <Data Name="Image">C:\Users\Public\update.exe</Data>"#
+            ),
+        )
+    }
+
+    #[test]
+    fn threat_intel_matches() {
+        let feed = IocFeed::parse(
+            "name: test-feed\nips: [203.0.113.0/24]\ndomains: [evil.example]",
+            "",
+            false,
+        )
+        .unwrap();
+        let config = DetectionConfig {
+            ioc_feeds: vec![feed],
+            ..DetectionConfig::default()
+        };
+        let time = "2025-01-01T10:00:00Z".parse().unwrap();
+        let events = [
+            connection_event(time, "203.0.113.9"),
+            connection_event(time, "192.0.2.1"),
+            dns_event(
+                "cdn.evil.example",
+                "type:  5 edge.example;::ffff:192.0.2.1;",
+            ),
+            dns_event("benign.example", "::ffff:203.0.113.10;"),
+        ];
+        let descriptions: Vec<String> = detect_anomalies_with_config(&events, &config)
+            .iter()
+            .filter(|anomaly| anomaly.rule_name() == "IocMatch")
+            .map(|anomaly| {
+                assert_eq!(anomaly.severity(), Severity::Critical);
+                anomaly.description()
+            })
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "Threat Intel Match: update.exe -> 203.0.113.9 matches 203.0.113.0/24 (feed test-feed)",
+                "Threat Intel Match: update.exe -> cdn.evil.example matches evil.example (feed test-feed)",
+                "Threat Intel Match: update.exe -> 203.0.113.10 matches 203.0.113.0/24 (feed test-feed)",
+            ]
+        );
+    }
+
+    #[test]
+    fn hosts_ranked_by_risk() {
+        let time = "2025-01-01T10:00:00Z".parse().unwrap();
+        let on_host = |computer: &str| {
+            let mut event = connection_event(time, "203.0.113.9");
+            if let SysmonEvent::OutboundNetwork(network) = &mut event {
+                network.system.computer.computer = computer.to_string();
+            }
+            AnomalyKind::UnusualPort {
+                event: Arc::new(event),
+                port: 50000,
+                process: "update.exe".to_string(),
+            }
+        };
+        let hosts = host_risk_summary(
+            &[
+                on_host("WKS-1"),
+                on_host("WKS-2"),
+                on_host("WKS-2"),
+                AnomalyKind::CredentialDumping {
+                    event: Arc::new(process_access_event(
+                        r"C:\Users\Public\procdump.exe",
+                        "0x1FFFFF",
+                    )),
+                    process: "procdump.exe".to_string(),
+                    target: "lsass.exe".to_string(),
+                    granted_access: "0x1FFFFF".to_string(),
+                },
+                AnomalyKind::EventStorm {
+                    event_id: 3,
+                    count: 100,
+                    time_window_seconds: 5,
+                    start: None,
+                    end: None,
+                    top_processes: Vec::new(),
+                },
+            ]
+            .map(Anomaly::from),
+        );
+        let ranking: Vec<_> = hosts
+            .iter()
+            .map(|host| (host.computer.as_str(), host.score, host.total))
+            .collect();
+        // Repeats of a finding count in the total only, EventStorm has no host
+        assert_eq!(
+            ranking,
+            [("TEST-PC", 25, 1), ("WKS-2", 3, 2), ("WKS-1", 3, 1)]
+        );
+        assert_eq!(hosts[0].max_severity(), Severity::Critical);
+    }
+
+    #[test]
+    fn scoped_suppressions() {
+        let feed = IocFeed::parse("ips: [203.0.113.0/24]", "feed", false).unwrap();
+        let time = "2025-01-01T10:00:00Z".parse().unwrap();
+        let events = [connection_event(time, "203.0.113.9")];
+        let ioc_matches = |suppressions: &str| {
+            let config = DetectionConfig {
+                ioc_feeds: vec![feed.clone()],
+                suppressions: config_file::ConfigFile::parse(suppressions, false)
+                    .unwrap()
+                    .suppressions,
+                ..DetectionConfig::default()
+            };
+            detect_anomalies_with_config(&events, &config)
+                .iter()
+                .filter(|anomaly| anomaly.rule_id() == "SLA-011")
+                .count()
+        };
+        assert_eq!(ioc_matches("suppressions: []"), 1);
+        assert_eq!(
+            ioc_matches("suppressions: [{rule: SLA-011, image: UPDATE.EXE, computer: test-pc}]"),
+            0
+        );
+        assert_eq!(
+            ioc_matches("suppressions: [{rule: SLA-011, image: backupagent.exe}]"),
+            1
+        );
+        assert_eq!(
+            ioc_matches("suppressions: [{rule: IocMatch, computer: OTHER-PC}]"),
+            1
+        );
+    }
+
+    fn process_access_event(source: &str, granted_access: &str) -> SysmonEvent {
+        sysmon_event(
+            10,
+            3,
+            "2025-01-01T10:00:00.000Z",
+            &format!(
+                r#"<Data Name="RuleName">-</Data>
+        <Data Name="SourceProcessGUID">{{{PROCESS_GUID}}}</Data>
+        <Data Name="SourceProcessId">4242</Data>
+        <Data Name="SourceThreadId">4243</Data>
+        <Data Name="SourceImage">{source}</Data>
+        <Data Name="TargetProcessGUID">{{{PARENT_GUID}}}</Data>
+        <Data Name="TargetProcessId">636</Data>
+        <Data Name="TargetImage">C:\Windows\system32\lsass.exe</Data>
+        <Data Name="GrantedAccess">{granted_access}</Data>
+        <Data Name="CallTrace">C:\Windows\SYSTEM32\ntdll.dll+9d4c4</Data>
+        <Data Name="SourceUser">TEST-PC\Administrator</Data>
+        <Data Name="TargetUser">NT AUTHORITY\SYSTEM</Data>"#
+            ),
+        )
+    }
+
+    #[test]
+    fn lsass_access_by_dumping_tools() {
+        let events = [
+            process_access_event(r"C:\Users\Public\procdump64.exe", "0x1FFFFF"),
+            process_access_event(r"C:\Windows\System32\rundll32.exe", "0x1410"),
+            process_access_event(r"C:\Windows\system32\svchost.exe", "0x1410"),
+            process_access_event(r"C:\Users\Public\procdump64.exe", "0x1000"),
+        ];
+        let descriptions: Vec<String> = detect_anomalies(&events)
+            .iter()
+            .map(|anomaly| {
+                assert_eq!(anomaly.severity(), Severity::Critical);
+                anomaly.description()
+            })
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "Possible Credential Dumping: procdump64.exe opened lsass.exe with access 0x1FFFFF",
+                "Possible Credential Dumping: rundll32.exe opened lsass.exe with access 0x1410",
+            ]
+        );
+    }
+
+    fn wmi_consumer_event(operation: &str, consumer_type: &str) -> SysmonEvent {
+        sysmon_event(
+            20,
+            3,
+            "2025-01-01T10:00:00.000Z",
+            &format!(
+                r#"<Data Name="RuleName">-</Data>
+        <Data Name="EventType">WmiConsumerEvent</Data>
+        <Data Name="Operation">{operation}</Data>
+        <Data Name="User">TEST-PC\Administrator</Data>
+        <Data Name="Name"> "Updater"</Data>
+        <Data Name="Type">{consumer_type}</Data>
+        <Data Name="Destination"> "powershell.exe -nop -enc SQBFAFgA"</Data>"#
+            ),
+        )
+    }
+
+    #[test]
+    fn wmi_event_consumer_persistence() {
+        let events = [
+            wmi_consumer_event("Created", "Command Line"),
+            wmi_consumer_event("Created", "Log File"),
+            wmi_consumer_event("Deleted", "Script"),
+        ];
+        assert!(matches!(events[0], SysmonEvent::WmiConsumer(_)));
+        let anomalies = detect_anomalies(&events);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].rule_id(), "SLA-012");
+        assert_eq!(
+            anomalies[0].description(),
+            r"WMI Persistence: CommandLineEventConsumer Updater created by TEST-PC\Administrator runs powershell.exe -nop -enc SQBFAFgA"
+        );
+        let context = VecDeque::new();
+        assert_eq!(detect_anomalies_live(&events[0], &context).len(), 1);
+    }
+
+    fn event_with_data(event_id: u16, data: &str) -> SysmonEvent {
+        sysmon_event(event_id, 3, "2025-01-01T10:00:00.000Z", data)
+    }
+
+    #[test]
+    fn driver_load_and_raw_disk_access() {
+        let driver = |signed: &str, status: &str| {
+            event_with_data(
+                6,
+                &format!(
+                    r#"<Data Name="ImageLoaded">C:\Windows\System32\drivers\RTCore64.sys</Data>
+        <Data Name="Hashes">SHA256=01AA278B07B58DC46C84BD0B1B5C8E9EE4E62EA0BF7A695862444AF32E87F1FD</Data>
+        <Data Name="Signed">{signed}</Data>
+        <Data Name="Signature">Micro-Star International CO., LTD.</Data>
+        <Data Name="SignatureStatus">{status}</Data>"#
+                ),
+            )
+        };
+        let raw_read = |image: &str| {
+            event_with_data(
+                9,
+                &format!(
+                    r#"<Data Name="ProcessGuid">{{11111111-2222-3333-4444-555555555555}}</Data>
+        <Data Name="ProcessId">4242</Data>
+        <Data Name="Image">{image}</Data>
+        <Data Name="Device">\Device\HarddiskVolume2</Data>"#
+                ),
+            )
+        };
+        let events = [
+            driver("true", "Valid"),
+            driver("true", "Revoked"),
+            driver("false", "Unavailable"),
+            raw_read(r"C:\Windows\System32\VSSVC.exe"),
+            raw_read(r"C:\Users\Bob\Downloads\ninjacopy.exe"),
+            raw_read(r"C:\ProgramData\Microsoft\Windows Defender\Platform\4.18.2\MsMpEng.exe"),
+            // A known name is not enough
+            raw_read(r"C:\Users\Bob\vssvc.exe"),
+        ];
+        assert!(matches!(events[0], SysmonEvent::DriverLoad(_)));
+        assert!(matches!(events[3], SysmonEvent::RawAccessRead(_)));
+        let findings: Vec<(&str, Severity, String)> = detect_anomalies(&events)
+            .iter()
+            .map(|a| (a.rule_id(), a.severity(), a.description()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    "SLA-013",
+                    Severity::Critical,
+                    r"Suspicious Driver Load: C:\Windows\System32\drivers\RTCore64.sys (signature of Micro-Star International CO., LTD. is Revoked)".to_string()
+                ),
+                (
+                    "SLA-013",
+                    Severity::High,
+                    r"Suspicious Driver Load: C:\Windows\System32\drivers\RTCore64.sys (unsigned)".to_string()
+                ),
+                (
+                    "SLA-014",
+                    Severity::High,
+                    r"Raw Disk Access: ninjacopy.exe read \Device\HarddiskVolume2 directly".to_string()
+                ),
+                (
+                    "SLA-014",
+                    Severity::High,
+                    r"Raw Disk Access: vssvc.exe read \Device\HarddiskVolume2 directly".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sysmon_service_tampering() {
+        let state = |state: &str| {
+            event_with_data(
+                4,
+                &format!(
+                    r#"<Data Name="State">{state}</Data>
+        <Data Name="Version">15.15</Data>
+        <Data Name="SchemaVersion">4.90</Data>"#
+                ),
+            )
+        };
+        let events = [
+            state("Started"),
+            state("Stopped"),
+            event_with_data(
+                16,
+                r#"<Data Name="Configuration">C:\Windows\Temp\empty.xml</Data>
+        <Data Name="ConfigurationFileHash">SHA256=E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855</Data>"#,
+            ),
+        ];
+        assert!(matches!(events[2], SysmonEvent::ConfigurationChange(_)));
+        let findings: Vec<(&str, Severity, String)> = detect_anomalies(&events)
+            .iter()
+            .map(|a| (a.rule_id(), a.severity(), a.description()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    "SLA-015",
+                    Severity::Critical,
+                    "Sysmon Tampering: service stopped".to_string()
+                ),
+                (
+                    "SLA-015",
+                    Severity::Critical,
+                    r"Sysmon Tampering: configuration changed to C:\Windows\Temp\empty.xml"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn process_tampering_and_clipboard_events() {
+        let process = r#"<Data Name="ProcessGuid">{11111111-2222-3333-4444-555555555555}</Data>
+        <Data Name="ProcessId">6228</Data>"#;
+        let events = [
+            event_with_data(
+                24,
+                &format!(
+                    r#"{process}
+        <Data Name="Image">C:\Windows\System32\rdpclip.exe</Data>
+        <Data Name="Session">2</Data>
+        <Data Name="ClientInfo">user: LAB\rsmith ip: 10.0.0.5 hostname: WKS-042</Data>
+        <Data Name="Hashes">SHA256=E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855</Data>
+        <Data Name="Archived">true</Data>
+        <Data Name="User">LAB\rsmith</Data>"#
+                ),
+            ),
+            event_with_data(
+                25,
+                &format!(
+                    r#"{process}
+        <Data Name="Image">C:\Users\rsmith\AppData\Local\Temp\setup.exe</Data>
+        <Data Name="Type">Image is replaced</Data>
+        <Data Name="User">LAB\rsmith</Data>"#
+                ),
+            ),
+        ];
+        let SysmonEvent::ClipboardChange(clipboard) = &events[0] else {
+            panic!("expected a clipboard event");
+        };
+        assert_eq!(clipboard.event_data.archived, Some(true));
+        let findings: Vec<(&str, Severity, String)> = detect_anomalies(&events)
+            .iter()
+            .map(|a| (a.rule_id(), a.severity(), a.description()))
+            .collect();
+        assert_eq!(
+            findings,
+            [(
+                "SLA-017",
+                Severity::High,
+                "Process Tampering: setup.exe (Image is replaced)".to_string()
+            )]
+        );
+    }
+
+    fn stream_event(target: &str, hash: &str, contents: &str) -> SysmonEvent {
+        event_with_data(
+            15,
+            &format!(
+                r#"<Data Name="ProcessGuid">{{11111111-2222-3333-4444-555555555555}}</Data>
+        <Data Name="ProcessId">6228</Data>
+        <Data Name="Image">C:\Program Files\Mozilla Firefox\firefox.exe</Data>
+        <Data Name="TargetFilename">{target}</Data>
+        <Data Name="CreationUtcTime">2025-01-01 09:59:59.000</Data>
+        <Data Name="Hash">{hash}</Data>
+        <Data Name="Contents">{contents}</Data>
+        <Data Name="User">TEST-PC\Bob</Data>"#
+            ),
+        )
+    }
+
+    #[test]
+    fn alternate_data_streams_and_downloads() {
+        let download = r"C:\Users\Bob\Downloads\invoice.exe";
+        let events = [
+            stream_event(
+                &format!("{download}:Zone.Identifier"),
+                "SHA256=AB,IMPHASH=00000000000000000000000000000000",
+                "[ZoneTransfer]  ZoneId=3  HostUrl=https://example.com/invoice.exe",
+            ),
+            stream_event(
+                r"C:\Users\Public\report.txt:payload",
+                "SHA256=CD,IMPHASH=F34D5F2D4577ED6D9CEEC516C1F5A744",
+                "-",
+            ),
+            stream_event(r"C:\Users\Public\notes.txt:todo.txt", "SHA256=EF", "todo"),
+            process_event("2025-01-01T10:05:00Z", r"C:\Windows\explorer.exe", download),
+            process_event(
+                "2025-01-01T10:06:00Z",
+                r"C:\Windows\explorer.exe",
+                r"C:\Users\Bob\Downloads\other.exe",
+            ),
+        ];
+        let SysmonEvent::FileStreamCreate(stream) = &events[0] else {
+            panic!("expected a stream event");
+        };
+        assert_eq!(
+            stream.event_data.stream(),
+            Some((download, "Zone.Identifier"))
+        );
+        let findings: Vec<(&str, String)> = detect_anomalies(&events)
+            .iter()
+            .filter(|a| ["SLA-018", "SLA-019"].contains(&a.rule_id()))
+            .map(|a| (a.rule_id(), a.description()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    "SLA-018",
+                    r"Executable Alternate Data Stream: firefox.exe wrote C:\Users\Public\report.txt:payload (PE image)".to_string()
+                ),
+                (
+                    "SLA-019",
+                    "Downloaded File Executed: invoice.exe (Internet zone, from https://example.com/invoice.exe)".to_string()
+                ),
+            ]
+        );
+
+        let context: VecDeque<SysmonEvent> = events[..3].iter().cloned().collect();
+        let live = detect_anomalies_live(&events[3], &context);
+        assert!(live.iter().any(|a| a.rule_id() == "SLA-019"));
+    }
+
+    fn script_block_event(time: &str, text: &str) -> SysmonEvent {
+        script_block_part(time, (1, 1), text)
+    }
+
+    fn script_block_part(time: &str, (number, total): (u32, u32), text: &str) -> SysmonEvent {
+        SysmonEvent::from_str(format!(
+                r#"
+    <Event>
+      <System>
+        <Provider Name="Microsoft-Windows-PowerShell" Guid="{{A0C1853B-5C40-4B15-8766-3CF1C58F985A}}" />
+        <EventID>4104</EventID>
+        <Version>1</Version>
+        <Level>5</Level>
+        <Task>2</Task>
+        <Opcode>15</Opcode>
+        <Keywords>0x0</Keywords>
+        <TimeCreated SystemTime="{time}"/>
+        <EventRecordID>7</EventRecordID>
+        <Execution ProcessID="1000" ThreadID="2000"/>
+        <Channel>Microsoft-Windows-PowerShell/Operational</Channel>
+        <Computer>TEST-PC</Computer>
+        <Security UserID="S-1-5-21-1000"/>
+      </System>
+      <EventData>
+        <Data Name="MessageNumber">{number}</Data>
+        <Data Name="MessageTotal">{total}</Data>
+        <Data Name="ScriptBlockText">{text}</Data>
+        <Data Name="ScriptBlockId">9c2a1a4e-0e6f-4f0a-9a37-1c7b0e1f4d2b</Data>
+        <Data Name="Path"></Data>
+      </EventData>
+    </Event>"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn obfuscated_script_blocks() {
+        let script_block = |text: &str| script_block_event("2025-01-01T10:00:05Z", text);
+        let payload = "$s=New-Object IO.MemoryStream(,[Convert]::FromBase64String($b)); \
+            I`E`X (New-Object IO.StreamReader(New-Object IO.Compression.GzipStream($s,0))).ReadToEnd()";
+        let mut events = vec![
+            process_event(
+                "2025-01-01T10:00:00Z",
+                r"C:\Windows\explorer.exe",
+                r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+            ),
+            script_block(payload),
+            script_block("Get-Service | Where-Object Status -eq Running"),
+        ];
+        let uncorrelated = detect_anomalies(&events);
+        assert_eq!(uncorrelated.len(), 1);
+        assert_eq!(
+            uncorrelated[0].description(),
+            "Obfuscated PowerShell Script Block: PID 1000 (IEX, FromBase64String, compressed payload, score 6)"
+        );
+
+        crate::powershell::correlate_script_blocks(&mut events);
+        let anomalies = detect_anomalies(&events);
+        assert_eq!(anomalies[0].rule_id(), "SLA-020");
+        assert_eq!(anomalies[0].severity(), Severity::Medium);
+        assert!(anomalies[0].description().contains(": powershell.exe ("));
+        let live = detect_anomalies_live(&events[1], &VecDeque::new());
+        assert_eq!(live.len(), 1);
+
+        // A block split over three events is scored once, on its whole text, when the last part
+        // arrives; a block missing a part is scored on the parts logged
+        let (start, end) = payload.split_at(60);
+        let parts = vec![
+            script_block_part("2025-01-01T10:00:07Z", (2, 3), end),
+            script_block_part("2025-01-01T10:00:06Z", (1, 3), start),
+            script_block_part("2025-01-01T10:00:08Z", (3, 3), "Write-Host done"),
+        ];
+        let anomalies = detect_anomalies(&parts);
+        assert_eq!(anomalies.len(), 1);
+        assert!(anomalies[0].description().contains("score 6"));
+        assert!(detect_anomalies(&parts[1..2]).is_empty());
+        assert_eq!(detect_anomalies(&parts[..2]).len(), 1);
+        let context: VecDeque<SysmonEvent> = parts[..2].iter().cloned().collect();
+        assert!(detect_anomalies_live(&parts[1], &VecDeque::new()).is_empty());
+        assert_eq!(detect_anomalies_live(&parts[2], &context).len(), 1);
+    }
+
+    #[test]
+    fn custom_parent_child_rules() {
+        let time = "2025-01-01T10:00:00Z";
+        let events = [
+            process_event(
+                time,
+                r"C:\Program Files\nodejs\node.exe",
+                r"C:\Windows\System32\cmd.exe",
+            ),
+            process_event(
+                time,
+                r"C:\Program Files\Microsoft Office\WINWORD.EXE",
+                r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+            ),
+        ];
+        let chains = |config: &DetectionConfig| -> Vec<(String, Severity)> {
+            detect_anomalies_with_config(&events, config)
+                .iter()
+                .filter(|anomaly| anomaly.rule_id() == "SLA-002")
+                .map(|anomaly| (anomaly.description(), anomaly.severity()))
+                .collect()
+        };
+        let defaults = chains(&DetectionConfig::default());
+        assert_eq!(defaults.len(), 1);
+        assert!(defaults[0].0.contains("Office application spawned a shell"));
+
+        let file = config_file::ConfigFile::parse(
+            "parent_child_rules:\n  - parent: node.exe\n    child: cmd.exe|powershell.exe\n    severity: critical\n    reason: Node.js server spawned a shell\n",
+            false,
+        )
+        .unwrap();
+        let mut config = DetectionConfig::default();
+        config.parent_child_rules.extend(file.parent_child_rules);
+        let custom = chains(&config);
+        assert_eq!(custom.len(), 2);
+        assert!(
+            custom.contains(&(
+                "Suspicious Process Chain: node.exe -> cmd.exe (Node.js server spawned a shell)"
+                    .to_string(),
+                Severity::Critical
+            ))
+        );
+    }
+
+    #[test]
+    fn persistence_and_webshell_file_drops() {
+        let file_create = |image: &str, target: &str| {
+            event_with_data(
+                11,
+                &format!(
+                    r#"<Data Name="ProcessGuid">{{11111111-2222-3333-4444-555555555555}}</Data>
+        <Data Name="ProcessId">4242</Data>
+        <Data Name="Image">{image}</Data>
+        <Data Name="TargetFilename">{target}</Data>
+        <Data Name="CreationUtcTime">2025-01-01 10:00:00.000</Data>"#
+                ),
+            )
+        };
+        let events = [
+            file_create(
+                r"c:\windows\system32\inetsrv\w3wp.exe",
+                r"C:\inetpub\wwwroot\aspnet_client\system_web\cmd.aspx",
+            ),
+            file_create(
+                r"C:\Windows\System32\svchost.exe",
+                r"C:\Windows\System32\Tasks\Microsoft\Windows\Defrag\ScheduledDefrag",
+            ),
+            file_create(
+                r"C:\Users\Bob\AppData\Local\Temp\stage.exe",
+                r"C:\ProgramData\Microsoft\Windows\Start Menu\Programs\StartUp\update.vbs",
+            ),
+        ];
+        let findings: Vec<(Severity, String)> = detect_anomalies(&events)
+            .iter()
+            .filter(|a| a.rule_id() == "SLA-023")
+            .map(|a| (a.severity(), a.description()))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                (
+                    Severity::High,
+                    r"Suspicious File Drop: w3wp.exe wrote C:\inetpub\wwwroot\aspnet_client\system_web\cmd.aspx (web root)".to_string()
+                ),
+                (
+                    Severity::Medium,
+                    r"Suspicious File Drop: stage.exe wrote C:\ProgramData\Microsoft\Windows\Start Menu\Programs\StartUp\update.vbs (Startup folder)".to_string()
+                ),
+            ]
+        );
+        let live = detect_anomalies_live(&events[0], &VecDeque::new());
+        assert_eq!(live[0].attack_techniques(), ["T1505.003"]);
+    }
+
+    #[test]
+    fn short_lived_children_of_office_and_services() {
+        let terminate =
+            |time: &str| terminate_event(time, PROCESS_GUID, r"C:\Windows\System32\cmd.exe");
+        let short_lived = |events: &[SysmonEvent]| -> Vec<String> {
+            detect_anomalies(events)
+                .iter()
+                .filter(|a| a.rule_id() == "SLA-024")
+                .map(|anomaly| anomaly.description())
+                .collect()
+        };
+        let word = r"C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE";
+        let cmd = r"C:\Windows\System32\cmd.exe";
+        let created = process_event("2025-01-01T10:00:00.000Z", word, cmd);
+        let events = [created.clone(), terminate("2025-01-01T10:00:00.800Z")];
+        assert_eq!(
+            short_lived(&events),
+            ["Short-Lived Process: cmd.exe started by WINWORD.EXE exited after 800 ms"]
+        );
+        assert!(short_lived(&[created.clone(), terminate("2025-01-01T10:05:00.000Z")]).is_empty());
+        let explorer = process_event("2025-01-01T10:00:00.000Z", r"C:\Windows\explorer.exe", cmd);
+        assert!(short_lived(&[explorer, terminate("2025-01-01T10:00:00.800Z")]).is_empty());
+
+        let context = VecDeque::from([created]);
+        let live = detect_anomalies_live(&terminate("2025-01-01T10:00:01.500Z"), &context);
+        assert_eq!(live.len(), 1);
+        assert!(live[0].event().is_some_and(|e| e.process_id() == 1000));
+        assert_eq!(live[0].attack_techniques(), ["T1204.002"]);
+    }
+
+    #[test]
+    fn dropped_executables_started_within_the_window() {
+        let dropped = SysmonEvent::from_str(
+            r#"<Event><System>
+        <Provider Name="Microsoft-Windows-Sysmon" Guid="{...}" />
+        <EventID>29</EventID><Version>5</Version><Level>4</Level><Task>29</Task><Opcode>0</Opcode>
+        <Keywords>0x8000000000000000</Keywords><TimeCreated SystemTime="2025-01-01T10:00:00.000Z"/>
+        <EventRecordID>41</EventRecordID><Execution ProcessID="1000" ThreadID="2000"/>
+        <Channel>Microsoft-Windows-Sysmon/Operational</Channel><Computer>TEST-PC</Computer>
+        <Security UserID="S-1-5-18"/></System>
+      <EventData>
+        <Data Name="RuleName">-</Data>
+        <Data Name="UtcTime">2025-01-01 10:00:00.000</Data>
+        <Data Name="ProcessGuid">{99999999-8888-7777-6666-555555555555}</Data>
+        <Data Name="ProcessId">4321</Data>
+        <Data Name="User">TEST-PC\Administrator</Data>
+        <Data Name="Image">C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE</Data>
+        <Data Name="TargetFilename">C:\Users\Public\payload.exe</Data>
+        <Data Name="Hashes">SHA1=00,SHA256=ABCDEF</Data>
+      </EventData></Event>"#,
+        )
+        .unwrap();
+        let explorer = r"C:\Windows\explorer.exe";
+        let dropped_and_executed = |events: &[SysmonEvent]| -> Vec<String> {
+            detect_anomalies(events)
+                .iter()
+                .filter(|a| a.rule_id() == "SLA-025")
+                .map(|anomaly| anomaly.description())
+                .collect()
+        };
+        let started = process_event(
+            "2025-01-01T10:00:30.000Z",
+            explorer,
+            r"C:\Users\Public\PAYLOAD.EXE",
+        );
+        assert_eq!(
+            dropped_and_executed(&[dropped.clone(), started.clone()]),
+            ["Dropped Executable Started: PAYLOAD.EXE written by WINWORD.EXE ran 30.0 s later"]
+        );
+        // Renamed copy, found by its hash
+        let mut renamed = process_event("2025-01-01T10:01:00.000Z", explorer, r"C:\Temp\svc.exe");
+        if let SysmonEvent::ProcessCreate(e) = &mut renamed {
+            e.event_data.hashes.hashes = "SHA256=abcdef".to_string();
+        }
+        assert_eq!(dropped_and_executed(&[dropped.clone(), renamed]).len(), 1);
+        let late = process_event(
+            "2025-01-01T10:30:00.000Z",
+            explorer,
+            r"C:\Users\Public\payload.exe",
+        );
+        assert!(dropped_and_executed(&[dropped.clone(), late]).is_empty());
+
+        let live = detect_anomalies_live(&started, &VecDeque::from([dropped]));
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].attack_techniques(), ["T1105", "T1204.002"]);
+        let AnomalyKind::DroppedAndExecuted { dropped, .. } = live[0].kind() else {
+            panic!("expected DroppedAndExecuted");
+        };
+        assert_eq!(dropped.system().event_id.event_id, 29);
+    }
+}
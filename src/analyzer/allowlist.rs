@@ -0,0 +1,174 @@
+//! Known-benign activity (security agents, backup and management tools) excluded from detection
+use super::ioc::Network;
+use super::known_good::sha256_of;
+use super::parent_child::glob_match;
+use crate::helpers::HasProcess;
+use crate::sysmon::Event as SysmonEvent;
+use crate::yaml_or_json::{from_yaml_or_json, load_yaml_or_json};
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Allowlist file layout, in YAML or JSON:
+///
+/// ```yaml
+/// images: ['C:\Program Files\Backup\agent.exe', MsMpEng.exe]
+/// hashes: [9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08]
+/// command_lines: ['*\inventory.ps1 -Nightly*']
+/// destination_ips: [10.0.0.5, 192.168.10.0/24]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AllowlistFile {
+    #[serde(default)]
+    images: Vec<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+    #[serde(default)]
+    command_lines: Vec<String>,
+    #[serde(default)]
+    destination_ips: Vec<String>,
+}
+
+/// Events that never produce findings: those of processes with an allowlisted image, hash or
+/// command line, and connections to allowlisted addresses
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Allowlist {
+    /// Lowercase full paths or file names
+    images: Vec<String>,
+    /// Lowercase SHA256 hashes
+    hashes: HashSet<String>,
+    /// Lowercase globs of the whole command line
+    command_lines: Vec<String>,
+    destination_ips: Vec<Network>,
+}
+
+impl Allowlist {
+    pub fn load(path: &Path) -> Result<Self> {
+        load_yaml_or_json(path, "allowlist", Self::parse)
+    }
+
+    pub fn parse(content: &str, is_json: bool) -> Result<Self> {
+        let file: AllowlistFile = from_yaml_or_json(content, is_json)?;
+        let mut hashes = HashSet::new();
+        for hash in &file.hashes {
+            let hash = hash.trim();
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!("'{hash}' is not a SHA256 hash");
+            }
+            hashes.insert(hash.to_ascii_lowercase());
+        }
+        let lowercase = |values: Vec<String>| -> Vec<String> {
+            values
+                .iter()
+                .map(|value| value.trim().to_lowercase())
+                .filter(|value| !value.is_empty())
+                .collect()
+        };
+        Ok(Self {
+            images: lowercase(file.images),
+            hashes,
+            command_lines: lowercase(file.command_lines),
+            destination_ips: file
+                .destination_ips
+                .iter()
+                .map(|ip| Network::parse(ip))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn extend(&mut self, other: Allowlist) {
+        self.images.extend(other.images);
+        self.hashes.extend(other.hashes);
+        self.command_lines.extend(other.command_lines);
+        self.destination_ips.extend(other.destination_ips);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+            && self.hashes.is_empty()
+            && self.command_lines.is_empty()
+            && self.destination_ips.is_empty()
+    }
+
+    /// Whether the event is left out of detection. Images are compared by full path, or by file
+    /// name for entries without a directory; hashes with the SHA256 of the process, driver or
+    /// file of the event.
+    pub fn matches(&self, event: &SysmonEvent) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let image = event.image().to_lowercase();
+        let name = image.rsplit('\\').next().unwrap_or(&image);
+        if self.images.iter().any(|allowed| {
+            if allowed.contains('\\') {
+                *allowed == image
+            } else {
+                allowed == name
+            }
+        }) {
+            return true;
+        }
+        let fields = event.fields();
+        if let Some(hash) = fields
+            .get("Hashes")
+            .or_else(|| fields.get("Hash"))
+            .and_then(sha256_of)
+            && self.hashes.contains(&hash.to_ascii_lowercase())
+        {
+            return true;
+        }
+        if let Some(command_line) = fields.get("CommandLine") {
+            let command_line = command_line.to_lowercase();
+            if self
+                .command_lines
+                .iter()
+                .any(|pattern| glob_match(pattern, &command_line))
+            {
+                return true;
+            }
+        }
+        fields
+            .get("DestinationIp")
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+            .is_some_and(|ip| {
+                self.destination_ips
+                    .iter()
+                    .any(|network| network.contains(ip))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{DetectionConfig, detect_anomalies_with_config};
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn allowlisted_events_produce_no_findings() {
+        let events = [json_process_event(json!({
+            "CommandLine": r"cmd.exe /c C:\Scripts\Backup.cmd /nightly",
+        }))];
+        assert!(!detect_anomalies_with_config(&events, &DetectionConfig::default()).is_empty());
+
+        let allowlisted = |yaml: &str| {
+            let config = DetectionConfig {
+                allowlist: Allowlist::parse(yaml, false).unwrap(),
+                ..DetectionConfig::default()
+            };
+            detect_anomalies_with_config(&events, &config).is_empty()
+        };
+        assert!(allowlisted("images: [CMD.EXE]"));
+        assert!(allowlisted(r"images: ['c:\windows\system32\cmd.exe']"));
+        assert!(!allowlisted(r"images: ['C:\Tools\cmd.exe']"));
+        assert!(allowlisted(r"command_lines: ['*\scripts\backup.cmd *']"));
+        assert!(!allowlisted(r"command_lines: ['*\scripts\backup.cmd']"));
+        assert!(!allowlisted("destination_ips: [10.0.0.0/8]"));
+        assert!(Allowlist::parse("hashes: [abc]", false).is_err());
+        assert!(Allowlist::parse("domains: [example.com]", false).is_err());
+    }
+}
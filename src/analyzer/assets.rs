@@ -0,0 +1,198 @@
+//! Asset inventory (CMDB export) giving the role, owner and criticality of computers. Findings
+//! on critical assets are raised a severity level, those on low-value assets lowered.
+use super::Severity;
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Accepted names of the column holding the computer name
+const COMPUTER_COLUMNS: &[&str] = &["computer", "hostname", "host", "name"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Criticality {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Display for Criticality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Criticality::Low => f.write_str("low"),
+            Criticality::Medium => f.write_str("medium"),
+            Criticality::High => f.write_str("high"),
+            Criticality::Critical => f.write_str("critical"),
+        }
+    }
+}
+
+impl Criticality {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Some(Criticality::Low),
+            "medium" => Some(Criticality::Medium),
+            "high" => Some(Criticality::High),
+            "critical" => Some(Criticality::Critical),
+            _ => None,
+        }
+    }
+
+    /// Severity of a finding on an asset of this criticality: critical assets raise it a level,
+    /// high ones to Medium at least, low ones lower it a level
+    pub fn adjust(self, severity: Severity) -> Severity {
+        match (self, severity) {
+            (Criticality::Critical, Severity::Low) => Severity::Medium,
+            (Criticality::Critical, Severity::Medium) => Severity::High,
+            (Criticality::Critical, _) => Severity::Critical,
+            (Criticality::High, Severity::Low) => Severity::Medium,
+            (Criticality::Low, Severity::Critical) => Severity::High,
+            (Criticality::Low, Severity::High) => Severity::Medium,
+            (Criticality::Low, _) => Severity::Low,
+            (_, severity) => severity,
+        }
+    }
+}
+
+/// Inventory entry of a computer; columns left empty are `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Asset {
+    pub role: Option<String>,
+    pub owner: Option<String>,
+    pub criticality: Option<Criticality>,
+}
+
+/// Assets by lowercase computer name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetInventory {
+    /// Shared by the findings on the computer
+    assets: HashMap<String, Arc<Asset>>,
+}
+
+impl AssetInventory {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read asset inventory {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid asset inventory {}", path.display()))
+    }
+
+    /// Parse a CSV file with a `computer` (or `hostname`, `host`, `name`) column and optional
+    /// `role`, `owner` and `criticality` (low, medium, high, critical) columns
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes());
+        let headers = reader.headers()?.clone();
+        let column = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+        };
+        let computer = column(COMPUTER_COLUMNS).ok_or_else(|| {
+            anyhow!("missing computer column, expected one of {COMPUTER_COLUMNS:?}")
+        })?;
+        let (role, owner, criticality) = (
+            column(&["role"]),
+            column(&["owner"]),
+            column(&["criticality"]),
+        );
+        let mut assets = HashMap::new();
+        for (index, record) in reader.records().enumerate() {
+            // The header is line 1
+            let line = index + 2;
+            let record = record.with_context(|| format!("line {line}"))?;
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|column| record.get(column))
+                    .filter(|value| !value.is_empty())
+            };
+            let Some(name) = field(Some(computer)) else {
+                continue;
+            };
+            let criticality = match field(criticality) {
+                Some(value) => match Criticality::parse(value) {
+                    Some(criticality) => Some(criticality),
+                    None => bail!("line {line}: unknown criticality '{value}'"),
+                },
+                None => None,
+            };
+            let asset = Asset {
+                role: field(role).map(str::to_string),
+                owner: field(owner).map(str::to_string),
+                criticality,
+            };
+            assets.insert(name.to_lowercase(), Arc::new(asset));
+        }
+        Ok(Self { assets })
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Asset of a computer, by its full name or its name without the domain
+    pub fn get(&self, computer: &str) -> Option<&Arc<Asset>> {
+        let computer = computer.to_lowercase();
+        self.assets.get(&computer).or_else(|| {
+            let (host, _) = computer.split_once('.')?;
+            self.assets.get(host)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{DetectionConfig, detect_anomalies, detect_anomalies_with_config};
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn inventory_and_severity() {
+        let inventory = AssetInventory::parse(
+            "Hostname,Role,Owner,Criticality\n\
+             DC01, Domain Controller ,IT Ops,critical\n\
+             KIOSK-3,Kiosk,,low\n\
+             ws-17,,Bob,\n\
+             ,orphan,,high\n",
+        )
+        .unwrap();
+        assert_eq!(inventory.len(), 3);
+        let dc = inventory.get("dc01.corp.example.com").unwrap();
+        assert_eq!(dc.role.as_deref(), Some("Domain Controller"));
+        assert_eq!(dc.criticality, Some(Criticality::Critical));
+        assert_eq!(inventory.get("WS-17").unwrap().role, None);
+        assert!(inventory.get("WS-18").is_none());
+        assert_eq!(
+            Criticality::Critical.adjust(Severity::Medium),
+            Severity::High
+        );
+        assert_eq!(Criticality::High.adjust(Severity::Low), Severity::Medium);
+        assert_eq!(Criticality::Low.adjust(Severity::High), Severity::Medium);
+        assert_eq!(Criticality::Medium.adjust(Severity::High), Severity::High);
+        assert!(AssetInventory::parse("ip,role\n10.0.0.1,dc\n").is_err());
+        assert!(AssetInventory::parse("computer,criticality\nDC01,urgent\n").is_err());
+
+        // Findings resolve the asset of their computer from the detection config
+        let events = [json_process_event(json!({
+            "Hostname": "DC01.corp.example.com",
+            "User": r"CORP\Bob",
+        }))];
+        let config = DetectionConfig {
+            assets: Some(Arc::new(inventory)),
+            ..DetectionConfig::default()
+        };
+        let anomaly = &detect_anomalies_with_config(&events, &config)[0];
+        assert_eq!(anomaly.default_severity(), Severity::High);
+        assert_eq!(anomaly.severity(), Severity::Critical);
+        assert_eq!(anomaly.asset().unwrap().owner.as_deref(), Some("IT Ops"));
+        assert!(detect_anomalies(&events)[0].asset().is_none());
+    }
+}
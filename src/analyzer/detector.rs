@@ -0,0 +1,244 @@
+//! Extension point for detections defined outside the analyzer, e.g. in downstream crates
+use super::attack::Tactic;
+use super::{Anomaly, AnomalyKind, DetectionConfig, Severity};
+use crate::sysmon::Event as SysmonEvent;
+use std::sync::Arc;
+
+/// Detection contributed without modifying the built-in rules. A new instance is created for
+/// each detection run, so state kept between events never leaks from one log into another.
+pub trait Detector: Send {
+    /// Name under which the evaluation time is profiled, usually that of its rule
+    fn name(&self) -> &'static str;
+    /// Findings triggered by one event, in time order for batch detection
+    fn on_event(&mut self, event: &SysmonEvent) -> Vec<AnomalyKind>;
+    /// Findings that need the whole log, reported once all events were seen. Not called while
+    /// watching or following, where the log never ends.
+    fn finalize(&mut self) -> Vec<AnomalyKind> {
+        Vec::new()
+    }
+    /// Rules whose findings the detector reports, so the detection config can refer to them
+    fn rules(&self) -> Vec<&'static CustomRule> {
+        Vec::new()
+    }
+}
+
+/// Description of a rule implemented by a [`Detector`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct CustomRule {
+    /// Stable ID, which should not collide with the built-in `SLA-` IDs
+    pub id: &'static str,
+    pub name: &'static str,
+    pub attack_techniques: &'static [&'static str],
+    pub tactics: &'static [Tactic],
+}
+
+impl CustomRule {
+    /// Finding of this rule triggered by an event
+    pub fn finding(
+        &'static self,
+        event: &SysmonEvent,
+        severity: Severity,
+        description: impl Into<String>,
+    ) -> AnomalyKind {
+        AnomalyKind::Custom {
+            rule: self,
+            event: Some(Arc::new(event.clone())),
+            severity,
+            description: description.into(),
+        }
+    }
+}
+
+type DetectorFactory = Arc<dyn Fn() -> Box<dyn Detector> + Send + Sync>;
+
+/// Custom detectors run alongside the built-in rules
+#[derive(Clone, Default)]
+pub struct DetectorRegistry {
+    factories: Vec<DetectorFactory>,
+}
+
+impl DetectorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a detector, created by `factory` for each detection run
+    pub fn register<D: Detector + 'static>(
+        &mut self,
+        factory: impl Fn() -> D + Send + Sync + 'static,
+    ) {
+        self.factories
+            .push(Arc::new(move || Box::new(factory()) as Box<dyn Detector>));
+    }
+
+    pub fn with<D: Detector + 'static>(
+        mut self,
+        factory: impl Fn() -> D + Send + Sync + 'static,
+    ) -> Self {
+        self.register(factory);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.factories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+
+    /// Fresh instances of all registered detectors
+    pub fn instantiate(&self) -> Vec<Box<dyn Detector>> {
+        self.factories.iter().map(|factory| factory()).collect()
+    }
+
+    /// Whether a registered detector reports the rule given by ID or name (case-insensitive)
+    pub fn has_rule(&self, rule: &str) -> bool {
+        self.instantiate().iter().any(|detector| {
+            detector.name().eq_ignore_ascii_case(rule)
+                || detector.rules().iter().any(|custom| {
+                    custom.id.eq_ignore_ascii_case(rule) || custom.name.eq_ignore_ascii_case(rule)
+                })
+        })
+    }
+}
+
+impl std::fmt::Debug for DetectorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DetectorRegistry")
+            .field("detectors", &self.factories.len())
+            .finish()
+    }
+}
+
+/// Registries are equal when they hold the same factories
+impl PartialEq for DetectorRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        self.factories.len() == other.factories.len()
+            && self
+                .factories
+                .iter()
+                .zip(&other.factories)
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// Run custom detectors on a live event, dropping suppressed findings
+pub fn detect_custom_live(
+    detectors: &mut [Box<dyn Detector>],
+    event: &SysmonEvent,
+    config: &DetectionConfig,
+) -> Vec<Anomaly> {
+    let anomalies = detectors
+        .iter_mut()
+        .flat_map(|detector| detector.on_event(event))
+        .collect();
+    config.findings(anomalies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::severity::SeverityOverrides;
+    use crate::analyzer::{detect_anomalies_profiled, detect_anomalies_with_config};
+    use crate::fixtures::json_event;
+    use serde_json::json;
+
+    static DNS_BURST: CustomRule = CustomRule {
+        id: "ACME-001",
+        name: "DnsBurst",
+        attack_techniques: &["T1071.004"],
+        tactics: &[Tactic::CommandAndControl],
+    };
+
+    /// Reports every query of example.com, and the number of queries once the log ends
+    #[derive(Default)]
+    struct DnsBurst {
+        queries: usize,
+    }
+
+    impl Detector for DnsBurst {
+        fn name(&self) -> &'static str {
+            DNS_BURST.name
+        }
+
+        fn on_event(&mut self, event: &SysmonEvent) -> Vec<AnomalyKind> {
+            match event {
+                SysmonEvent::DnsQuery(e) if e.event_data.query_name == "example.com" => {
+                    self.queries += 1;
+                    vec![DNS_BURST.finding(event, Severity::Low, "Query of example.com")]
+                }
+                _ => Vec::new(),
+            }
+        }
+
+        fn rules(&self) -> Vec<&'static CustomRule> {
+            vec![&DNS_BURST]
+        }
+
+        fn finalize(&mut self) -> Vec<AnomalyKind> {
+            vec![AnomalyKind::Custom {
+                rule: &DNS_BURST,
+                event: None,
+                severity: Severity::Medium,
+                description: format!("{} DNS queries", self.queries),
+            }]
+        }
+    }
+
+    fn query(record: u32) -> SysmonEvent {
+        json_event(
+            22,
+            json!({
+                "EventTime": format!("2025-01-01 10:00:0{record}"),
+                "RecordNumber": record,
+                "ProcessId": "4242",
+                "QueryName": "example.com",
+                "Image": r"C:\Windows\System32\cmd.exe",
+            }),
+        )
+    }
+
+    #[test]
+    fn registered_detectors_contribute_findings() {
+        let config = DetectionConfig {
+            detectors: DetectorRegistry::new().with(DnsBurst::default),
+            ..DetectionConfig::default()
+        };
+        let events = [query(1), query(2)];
+        let anomalies = detect_anomalies_with_config(&events, &config);
+        assert_eq!(anomalies.len(), 3);
+        assert_eq!(anomalies[0].rule_id(), "ACME-001");
+        assert_eq!(anomalies[0].rule_name(), "DnsBurst");
+        assert_eq!(anomalies[0].tactics(), [Tactic::CommandAndControl]);
+        assert_eq!(anomalies[2].description(), "2 DNS queries");
+        assert_eq!(anomalies[2].severity(), Severity::Medium);
+        // Each run starts from a fresh detector
+        let anomalies = detect_anomalies_with_config(&events[..1], &config);
+        assert_eq!(anomalies[1].description(), "1 DNS queries");
+
+        let (_, profile) = detect_anomalies_profiled(&events, &config);
+        let stats = profile.get("DnsBurst").unwrap();
+        assert_eq!((stats.evaluations, stats.hits), (3, 3));
+
+        let mut detectors = config.detectors.instantiate();
+        assert_eq!(
+            detect_custom_live(&mut detectors, &query(3), &config).len(),
+            1
+        );
+
+        // Severity overrides may refer to the rules of registered detectors
+        let config = DetectionConfig {
+            severity_overrides: SeverityOverrides::new([("acme-001".to_string(), Severity::High)]),
+            ..config
+        };
+        config.validate().unwrap();
+        let anomalies = detect_anomalies_with_config(&events, &config);
+        assert_eq!(anomalies[0].severity(), Severity::High);
+        let config = DetectionConfig {
+            detectors: DetectorRegistry::new(),
+            ..config
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Executables written to disk (FileExecutableDetected, 29) and processes later started from them
+use super::known_good::sha256_of;
+use crate::sysmon::{Event as SysmonEvent, FileDeleteEventData, ProcessCreateEventData};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Default time after a drop within which starting the file is reported
+pub const DEFAULT_DROP_EXECUTE_WINDOW: &str = "10m";
+
+/// Whether the process was started from the dropped file, by path or SHA256
+pub fn is_started_from(dropped: &FileDeleteEventData, process: &ProcessCreateEventData) -> bool {
+    if dropped
+        .target_filename
+        .eq_ignore_ascii_case(&process.image.image)
+    {
+        return true;
+    }
+    let dropped_hash = dropped.hashes.as_deref().and_then(sha256_of);
+    dropped_hash.is_some_and(|dropped_hash| {
+        sha256_of(&process.hashes).is_some_and(|hash| hash.eq_ignore_ascii_case(dropped_hash))
+    })
+}
+
+/// Executables dropped on each computer, by lowercase path and SHA256, with the time and event of
+/// their last drop
+#[derive(Debug, Default)]
+pub struct DroppedExecutables {
+    by_path: HashMap<(String, String), (DateTime<Utc>, Arc<SysmonEvent>)>,
+    by_hash: HashMap<(String, String), (DateTime<Utc>, Arc<SysmonEvent>)>,
+}
+
+impl DroppedExecutables {
+    /// Remember a FileExecutableDetected event
+    pub fn record(&mut self, time: DateTime<Utc>, event: Arc<SysmonEvent>) {
+        let SysmonEvent::FileExecutableDetected(dropped) = event.as_ref() else {
+            return;
+        };
+        let computer = dropped.system.computer.computer.to_lowercase();
+        let data = &dropped.event_data;
+        if let Some(hash) = data.hashes.as_deref().and_then(sha256_of) {
+            self.by_hash.insert(
+                (computer.clone(), hash.to_lowercase()),
+                (time, event.clone()),
+            );
+        }
+        self.by_path.insert(
+            (computer, data.target_filename.to_lowercase()),
+            (time, event),
+        );
+    }
+
+    /// Last drop of the file a process on `computer` was started from
+    pub fn find(
+        &self,
+        computer: &str,
+        process: &ProcessCreateEventData,
+    ) -> Option<&(DateTime<Utc>, Arc<SysmonEvent>)> {
+        let computer = computer.to_lowercase();
+        self.by_path
+            .get(&(computer.clone(), process.image.to_lowercase()))
+            .or_else(|| {
+                let hash = sha256_of(&process.hashes)?.to_lowercase();
+                self.by_hash.get(&(computer, hash))
+            })
+    }
+
+    /// Forget drops older than `oldest`
+    pub fn retain_since(&mut self, oldest: DateTime<Utc>) {
+        self.by_path.retain(|_, (time, _)| *time >= oldest);
+        self.by_hash.retain(|_, (time, _)| *time >= oldest);
+    }
+}
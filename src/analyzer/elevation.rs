@@ -0,0 +1,148 @@
+//! Integrity level escalation: a process running at High or System integrity started by one at
+//! Medium or lower, without the UAC consent prompt or a service start that grant elevation
+use crate::filters::Integrity;
+use chrono::{DateTime, Duration, Utc};
+
+/// A consent prompt started within this time before an elevated process approved its elevation
+pub const CONSENT_WINDOW_SECONDS: i64 = 120;
+/// UAC consent prompt, started by the Application Information service for each elevation
+const CONSENT: &str = "consent.exe";
+/// Parents whose children run elevated by design: the consent prompt and the Service Control
+/// Manager starting services
+const ELEVATION_BROKERS: &[&str] = &[CONSENT, "services.exe"];
+
+fn file_name(image: &str) -> &str {
+    image.rsplit('\\').next().unwrap_or(image)
+}
+
+/// Whether the process is the UAC consent prompt
+pub fn is_consent(image: &str) -> bool {
+    file_name(image).eq_ignore_ascii_case(CONSENT)
+}
+
+/// Whether a process at `level` started by one at `parent` escalated, unless the parent is an
+/// elevation broker
+pub fn escalates(parent_image: &str, parent: Integrity, level: Integrity) -> bool {
+    parent <= Integrity::Medium
+        && level >= Integrity::High
+        && !ELEVATION_BROKERS
+            .iter()
+            .any(|broker| file_name(parent_image).eq_ignore_ascii_case(broker))
+}
+
+/// Whether a consent prompt started at `consent` approved the elevation of a process started at
+/// `time`
+pub fn approved(consent: DateTime<Utc>, time: DateTime<Utc>) -> bool {
+    consent <= time && time - consent <= Duration::seconds(CONSENT_WINDOW_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{
+        AnomalyKind, detect_anomalies, detect_anomalies_live, detect_anomalies_with_config,
+    };
+    use crate::fixtures::json_process_event;
+    use crate::sysmon::Event as SysmonEvent;
+    use serde_json::json;
+    use std::collections::VecDeque;
+
+    fn spawn(
+        time: &str,
+        guid: u8,
+        parent: (u8, &str),
+        image: &str,
+        user: &str,
+        integrity: &str,
+    ) -> SysmonEvent {
+        json_process_event(json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "WS-01",
+            "RecordNumber": guid,
+            "ProcessGuid": format!("{{{guid:08X}-2222-3333-4444-555555555555}}"),
+            "ProcessId": guid.to_string(),
+            "Image": image,
+            "CommandLine": image,
+            "User": user,
+            "IntegrityLevel": integrity,
+            "ParentProcessGuid": format!("{{{:08X}-2222-3333-4444-555555555555}}", parent.0),
+            "ParentProcessId": parent.0.to_string(),
+            "ParentImage": parent.1,
+            "ParentCommandLine": parent.1,
+        }))
+    }
+
+    fn escalations(events: &[SysmonEvent]) -> Vec<String> {
+        detect_anomalies_with_config(events, &Default::default())
+            .iter()
+            .filter(|anomaly| matches!(anomaly.kind(), AnomalyKind::IntegrityEscalation { .. }))
+            .map(|anomaly| anomaly.description())
+            .collect()
+    }
+
+    #[test]
+    fn escalation_without_consent() {
+        let explorer = r"C:\Windows\explorer.exe";
+        let cmd = r"C:\Windows\System32\cmd.exe";
+        let fodhelper = r"C:\Windows\System32\fodhelper.exe";
+        let consent = r"C:\Windows\System32\consent.exe";
+        let svchost = r"C:\Windows\System32\svchost.exe";
+        let shell = spawn(
+            "10:00:00",
+            1,
+            (0, "userinit.exe"),
+            explorer,
+            "WS-01\\bob",
+            "Medium",
+        );
+        let medium = spawn("10:00:10", 2, (1, explorer), cmd, "WS-01\\bob", "Medium");
+        let bypass = spawn("10:00:20", 3, (2, cmd), fodhelper, "WS-01\\bob", "High");
+        let events = [shell.clone(), medium.clone(), bypass.clone()];
+        assert_eq!(
+            escalations(&events),
+            ["Integrity Escalation: cmd.exe (Medium) started fodhelper.exe (High) as WS-01\\bob"]
+        );
+        let anomalies = detect_anomalies(&events);
+        let finding = anomalies
+            .iter()
+            .find(|anomaly| anomaly.rule_id() == "SLA-030")
+            .unwrap();
+        assert_eq!(finding.attack_techniques(), ["T1548.002", "T1068"]);
+        let live = detect_anomalies_live(&bypass, &VecDeque::from([shell.clone(), medium.clone()]));
+        assert!(live.iter().any(|anomaly| anomaly.rule_id() == "SLA-030"));
+
+        // Run as administrator: the consent prompt shows up just before the elevated process
+        let prompt = spawn(
+            "10:00:15",
+            4,
+            (9, svchost),
+            consent,
+            "NT AUTHORITY\\SYSTEM",
+            "System",
+        );
+        let elevated = spawn("10:00:20", 5, (1, explorer), cmd, "WS-01\\bob", "High");
+        assert!(escalations(&[shell.clone(), prompt.clone(), elevated.clone()]).is_empty());
+        let live = detect_anomalies_live(&elevated, &VecDeque::from([shell.clone(), prompt]));
+        assert!(!live.iter().any(|anomaly| anomaly.rule_id() == "SLA-030"));
+
+        // SYSTEM shell from a user process, and children of elevated processes are not escalations
+        let system = spawn(
+            "10:05:00",
+            6,
+            (2, cmd),
+            cmd,
+            "NT AUTHORITY\\SYSTEM",
+            "System",
+        );
+        let child = spawn(
+            "10:05:01",
+            7,
+            (6, cmd),
+            r"C:\Windows\System32\whoami.exe",
+            "NT AUTHORITY\\SYSTEM",
+            "System",
+        );
+        let found = escalations(&[shell, medium, system, child]);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("(System) as NT AUTHORITY\\SYSTEM"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Executables started from network shares or removable drives rather than from local disks
+use std::fmt::Display;
+
+/// Shares of domain controllers that serve logon scripts and group policies to every client
+const DOMAIN_SHARES: &[&str] = &["netlogon", "sysvol"];
+
+/// Location outside the local fixed disks an executable was started from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutableSource {
+    /// UNC path, `\\host\share\...`
+    NetworkShare { host: String, share: String },
+    /// Drive letter configured as removable media
+    RemovableDrive(char),
+}
+
+impl ExecutableSource {
+    /// Logon script and group policy shares, where executables are expected
+    pub fn is_domain_share(&self) -> bool {
+        matches!(self, ExecutableSource::NetworkShare { share, .. }
+            if DOMAIN_SHARES.iter().any(|name| share.eq_ignore_ascii_case(name)))
+    }
+}
+
+impl Display for ExecutableSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutableSource::NetworkShare { host, share } => {
+                write!(f, r"network share \\{host}\{share}")
+            }
+            ExecutableSource::RemovableDrive(drive) => write!(f, "removable drive {drive}:"),
+        }
+    }
+}
+
+/// Source of an image path on a UNC path or one of the removable drive letters (uppercase).
+/// Win32 device paths (`\\?\C:\...`, `\\.\...`) are local.
+pub fn executable_source(image: &str, removable_drives: &[char]) -> Option<ExecutableSource> {
+    let unc = image
+        .get(..8)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(r"\\?\UNC\"));
+    let share_path = if unc {
+        Some(&image[8..])
+    } else {
+        image
+            .strip_prefix(r"\\")
+            .filter(|rest| !rest.starts_with(['?', '.']))
+    };
+    if let Some(path) = share_path {
+        let mut parts = path.split('\\');
+        let host = parts.next().filter(|host| !host.is_empty())?;
+        return Some(ExecutableSource::NetworkShare {
+            host: host.to_string(),
+            share: parts.next().unwrap_or_default().to_string(),
+        });
+    }
+    let mut chars = image.strip_prefix(r"\\?\").unwrap_or(image).chars();
+    let drive = chars.next()?.to_ascii_uppercase();
+    (chars.next() == Some(':') && removable_drives.contains(&drive))
+        .then_some(ExecutableSource::RemovableDrive(drive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_and_removable_drives() {
+        let share = |host: &str, share: &str| ExecutableSource::NetworkShare {
+            host: host.to_string(),
+            share: share.to_string(),
+        };
+        let removable = ['E', 'F'];
+        assert_eq!(
+            executable_source(r"\\fileserver\tools$\psexesvc.exe", &removable),
+            Some(share("fileserver", "tools$"))
+        );
+        assert_eq!(
+            executable_source(r"\\?\UNC\10.0.0.5\C$\Windows\a.exe", &removable),
+            Some(share("10.0.0.5", "C$"))
+        );
+        assert_eq!(
+            executable_source(r"e:\autorun\setup.exe", &removable),
+            Some(ExecutableSource::RemovableDrive('E'))
+        );
+        assert_eq!(
+            executable_source(r"\\?\C:\Windows\System32\cmd.exe", &removable),
+            None
+        );
+        assert_eq!(executable_source(r"\\.\pipe\x", &removable), None);
+        assert_eq!(executable_source(r"D:\setup.exe", &removable), None);
+        assert!(share("DC01", "NETLOGON").is_domain_share());
+        assert_eq!(
+            share("DC01", "SYSVOL").to_string(),
+            r"network share \\DC01\SYSVOL"
+        );
+    }
+}
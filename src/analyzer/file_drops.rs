@@ -0,0 +1,148 @@
+//! Files created where Windows runs or serves them without further setup: Startup folders,
+//! scheduled task folders and web server roots
+use std::fmt::Display;
+
+/// Folders whose files run at logon, for the user (`AppData\Roaming`) or everyone (`ProgramData`)
+const STARTUP_FOLDERS: &[&str] = &[r"\microsoft\windows\start menu\programs\startup\"];
+/// Task definitions, legacy `.job` files and the XML files of the Task Scheduler 2.0
+const TASK_FOLDERS: &[&str] = &[
+    r"\windows\tasks\",
+    r"\windows\system32\tasks\",
+    r"\windows\syswow64\tasks\",
+];
+/// Document roots of IIS, Exchange (OWA and ECP), XAMPP/WAMP, Apache, nginx and Tomcat
+const WEB_ROOTS: &[&str] = &[
+    r"\inetpub\",
+    r"\frontend\httpproxy\",
+    r"\clientaccess\",
+    r"\htdocs\",
+    r"\wamp\www\",
+    r"\wamp64\www\",
+    r"\nginx\html\",
+    r"\webapps\",
+];
+/// Extensions of pages a web server executes
+const SERVER_SCRIPT_EXTENSIONS: &[&str] = &[
+    "asp", "aspx", "ashx", "asmx", "asa", "cer", "cshtml", "jsp", "jspx", "php", "phtml", "cfm",
+    "war",
+];
+/// Processes that install software and Windows components, by full path
+const INSTALLERS: &[&str] = &[
+    r"c:\windows\system32\msiexec.exe",
+    r"c:\windows\syswow64\msiexec.exe",
+    r"c:\windows\servicing\trustedinstaller.exe",
+];
+/// Folder and name of the Windows Modules Installer worker, which runs from a versioned folder
+/// of the component store
+const MODULES_INSTALLER_WORKER: (&str, &str) = (r"c:\windows\winsxs\", "tiworker.exe");
+/// Host of the Task Scheduler service, which writes the files of tasks registered through its API
+const TASK_SCHEDULER: &str = r"c:\windows\system32\svchost.exe";
+/// Written by Explorer in every folder it customizes
+const FOLDER_SETTINGS: &str = "desktop.ini";
+
+/// Folder a file was dropped in that persists or exposes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropLocation {
+    StartupFolder,
+    ScheduledTasks,
+    WebRoot,
+}
+
+impl Display for DropLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DropLocation::StartupFolder => "Startup folder",
+            DropLocation::ScheduledTasks => "scheduled tasks folder",
+            DropLocation::WebRoot => "web root",
+        })
+    }
+}
+
+/// Location of a file created by `image` that persists or exposes it, `None` for other files
+/// and for files written by installers. Only server-side scripts count in web roots, and task
+/// files created by the Task Scheduler itself are expected.
+pub fn drop_location(image: &str, target: &str) -> Option<DropLocation> {
+    let image = image.to_ascii_lowercase();
+    let (store, worker) = MODULES_INSTALLER_WORKER;
+    if INSTALLERS.contains(&image.as_str())
+        || (image.starts_with(store) && image.ends_with(&format!("\\{worker}")))
+    {
+        return None;
+    }
+    let target = target.to_ascii_lowercase();
+    let file = target.rsplit('\\').next().unwrap_or(&target);
+    let under = |folders: &[&str]| folders.iter().any(|folder| target.contains(folder));
+    if under(STARTUP_FOLDERS) {
+        return (file != FOLDER_SETTINGS).then_some(DropLocation::StartupFolder);
+    }
+    if under(TASK_FOLDERS) {
+        return (image != TASK_SCHEDULER).then_some(DropLocation::ScheduledTasks);
+    }
+    let extension = file.rsplit_once('.')?.1;
+    (under(WEB_ROOTS) && SERVER_SCRIPT_EXTENSIONS.contains(&extension))
+        .then_some(DropLocation::WebRoot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persistence_and_webshell_locations() {
+        let startup =
+            r"C:\Users\bob\AppData\Roaming\Microsoft\Windows\Start Menu\Programs\Startup\";
+        let cmd = r"C:\Windows\System32\cmd.exe";
+        assert_eq!(
+            drop_location(cmd, &format!("{startup}update.lnk")),
+            Some(DropLocation::StartupFolder)
+        );
+        assert_eq!(drop_location(cmd, &format!("{startup}desktop.ini")), None);
+        assert_eq!(
+            drop_location(
+                r"C:\Windows\System32\msiexec.exe",
+                &format!("{startup}app.lnk")
+            ),
+            None
+        );
+        assert_eq!(
+            drop_location(cmd, r"C:\Windows\System32\Tasks\Updater"),
+            Some(DropLocation::ScheduledTasks)
+        );
+        assert_eq!(
+            drop_location(
+                r"C:\Windows\System32\svchost.exe",
+                r"C:\Windows\System32\Tasks\Updater"
+            ),
+            None
+        );
+        // Same names elsewhere
+        assert_eq!(
+            drop_location(
+                r"C:\Users\bob\svchost.exe",
+                r"C:\Windows\System32\Tasks\Updater"
+            ),
+            Some(DropLocation::ScheduledTasks)
+        );
+        assert_eq!(
+            drop_location(r"C:\Temp\msiexec.exe", &format!("{startup}app.lnk")),
+            Some(DropLocation::StartupFolder)
+        );
+        assert_eq!(
+            drop_location(
+                r"C:\Windows\WinSxS\amd64_microsoft-windows-servicingstack_31bf3856ad364e35_10.0.19041.1_none_1\TiWorker.exe",
+                r"C:\Windows\System32\Tasks\Microsoft\Windows\Servicing\StartComponentCleanup"
+            ),
+            None
+        );
+        let w3wp = r"c:\windows\system32\inetsrv\w3wp.exe";
+        assert_eq!(
+            drop_location(w3wp, r"C:\inetpub\wwwroot\aspnet_client\shell.aspx"),
+            Some(DropLocation::WebRoot)
+        );
+        assert_eq!(
+            drop_location(w3wp, r"C:\inetpub\logs\LogFiles\W3SVC1\u_ex250101.log"),
+            None
+        );
+        assert_eq!(drop_location(cmd, r"C:\Users\bob\Desktop\shell.aspx"), None);
+    }
+}
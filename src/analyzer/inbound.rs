@@ -0,0 +1,267 @@
+//! Inbound connections (network events not initiated by the logging machine): services accepting
+//! connections on ports they are not expected to serve, connections to workstations, and remote
+//! administration from outside the admin subnets
+use super::ioc::Network;
+use super::parent_child::Pattern;
+use super::ports::{self, PortProfile};
+use crate::sysmon::NetworkEventData;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+/// Services shipped with the analyzer and the local ports they accept connections on, in the
+/// config file format. Windows services get the dynamic port range, where RPC endpoints listen.
+const DEFAULT_LISTENERS: &str = r#"
+- image: system|svchost.exe|lsass.exe|services.exe|wininit.exe|spoolsv.exe|dns.exe|dfsrs.exe|ntfrs.exe
+  ports: [53, 88, 135, 139, 389, 445, 464, 636, 3268, 3269, 3389, 5985, 5986, "49152-65535"]
+- image: w3wp.exe|inetinfo.exe|httpd.exe|nginx.exe
+  ports: [80, 443, 8080, 8443]
+- image: sqlservr.exe|sqlbrowser.exe
+  ports: [1433, 1434]
+- image: sshd.exe
+  ports: [22]
+"#;
+/// Name resolution, discovery and DHCP traffic every Windows machine receives
+const DISCOVERY_PORTS: &[u16] = &[68, 137, 138, 1900, 5353, 5355];
+/// Local ports of remote administration checked against the admin subnets: RDP and SMB
+const DEFAULT_ADMIN_PORTS: &[u16] = &[3389, 445];
+
+/// `inbound` section of the detection config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InboundSection {
+    /// Computer names of workstations, which should not accept connections from other machines
+    pub workstations: Option<Pattern>,
+    /// Networks remote administration is expected from
+    #[serde(default)]
+    pub admin_subnets: Vec<Network>,
+    /// Local ports reported when reached from outside the admin subnets, RDP and SMB by default
+    pub admin_ports: Option<Vec<u16>>,
+    /// Local ports expected in addition to the built-in services
+    #[serde(default)]
+    pub listening_allowlist: Vec<PortProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InboundRules {
+    /// Built-in services followed by the config file allowlist
+    pub listeners: Vec<PortProfile>,
+    pub workstations: Option<Pattern>,
+    /// Remote administration is only checked when there are admin subnets
+    pub admin_subnets: Vec<Network>,
+    pub admin_ports: Vec<u16>,
+}
+
+impl Default for InboundRules {
+    fn default() -> Self {
+        Self {
+            listeners: serde_norway::from_str(DEFAULT_LISTENERS)
+                .expect("built-in listening ports are valid"),
+            workstations: None,
+            admin_subnets: Vec::new(),
+            admin_ports: DEFAULT_ADMIN_PORTS.to_vec(),
+        }
+    }
+}
+
+impl From<InboundSection> for InboundRules {
+    fn from(section: InboundSection) -> Self {
+        let mut rules = Self::default();
+        rules.listeners.extend(section.listening_allowlist);
+        rules.workstations = section.workstations;
+        rules.admin_subnets = section.admin_subnets;
+        if let Some(admin_ports) = section.admin_ports {
+            rules.admin_ports = admin_ports;
+        }
+        rules
+    }
+}
+
+impl InboundRules {
+    /// Whether `image` is expected to accept connections on the local `port`
+    pub fn is_expected_listener(&self, image: &str, port: u16) -> bool {
+        DISCOVERY_PORTS.contains(&port) || ports::is_expected_port(&self.listeners, image, port)
+    }
+
+    /// Reason to report an inbound connection to `computer` whatever the history of the log.
+    /// Connections from the machine itself are never reported.
+    pub fn check(&self, computer: &str, data: &NetworkEventData) -> Option<InboundReason> {
+        let remote = remote_peer(data)?;
+        let (_, port) = data.local();
+        let from_admin_subnet = self
+            .admin_subnets
+            .iter()
+            .any(|network| network.contains(remote));
+        if from_admin_subnet {
+            return None;
+        }
+        if !self.admin_subnets.is_empty() && self.admin_ports.contains(&port) {
+            return Some(InboundReason::RemoteAdministration);
+        }
+        let is_workstation = self
+            .workstations
+            .as_ref()
+            .is_some_and(|workstations| workstations.matches_text(computer));
+        (is_workstation && !DISCOVERY_PORTS.contains(&port)).then_some(InboundReason::Workstation)
+    }
+}
+
+/// Address of the other machine, unless the connection comes from the machine itself or its
+/// link (loopback, link-local, own address)
+pub fn remote_peer(data: &NetworkEventData) -> Option<IpAddr> {
+    let (remote, _) = data.remote();
+    let (local, _) = data.local();
+    let remote: IpAddr = remote.trim().parse().ok()?;
+    let is_link_local = match remote {
+        IpAddr::V4(ip) => ip.is_link_local(),
+        IpAddr::V6(ip) => ip.segments()[0] & 0xffc0 == 0xfe80,
+    };
+    let is_local = local
+        .trim()
+        .parse::<IpAddr>()
+        .is_ok_and(|local| local == remote);
+    (!remote.is_loopback() && !is_link_local && !is_local).then_some(remote)
+}
+
+/// Why an inbound connection is suspicious
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InboundReason {
+    /// Remote administration port reached from outside the admin subnets
+    RemoteAdministration,
+    /// Workstations don't serve other machines
+    Workstation,
+    /// Connection accepted on a port the process is not expected to serve, rare in the log
+    UnusualListeningPort,
+}
+
+impl Display for InboundReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InboundReason::RemoteAdministration => f.write_str("from outside the admin subnets"),
+            InboundReason::Workstation => f.write_str("on a workstation"),
+            InboundReason::UnusualListeningPort => f.write_str("on an unusual port"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{
+        AnomalyDetector, AnomalyKind, DetectionConfig, detect_anomalies_live_with_config,
+        detect_anomalies_with_config, timed_events,
+    };
+    use crate::jsonl;
+    use std::collections::VecDeque;
+
+    fn inbound(computer: &str, image: &str, source: &str, port: u16) -> crate::sysmon::Event {
+        let line = serde_json::json!({
+            "EventTime": "2025-01-01 10:00:00",
+            "Hostname": computer,
+            "EventID": 3,
+            "RecordNumber": 17,
+            "UtcTime": "2025-01-01 10:00:00.000",
+            "ProcessGuid": "{11111111-2222-3333-4444-555555555555}",
+            "ProcessId": "42",
+            "Image": image,
+            "Protocol": "tcp",
+            "Initiated": "false",
+            "SourceIsIpv6": "false",
+            "SourceIp": source,
+            "SourcePort": "50123",
+            "DestinationIsIpv6": "false",
+            "DestinationIp": "10.0.5.20",
+            "DestinationPort": port.to_string(),
+        });
+        jsonl::parse_json_event(&line.to_string()).unwrap()
+    }
+
+    fn reasons(events: &[crate::sysmon::Event], config: &DetectionConfig) -> Vec<InboundReason> {
+        detect_anomalies_with_config(events, config)
+            .iter()
+            .filter_map(|anomaly| match anomaly.kind() {
+                AnomalyKind::SuspiciousInbound { reason, .. } => Some(*reason),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inbound_rules() {
+        let section: InboundSection = serde_norway::from_str(
+            "{workstations: 'WS-*', admin_subnets: [10.10.0.0/24], listening_allowlist: [{image: agent.exe, ports: [9000]}]}",
+        )
+        .unwrap();
+        let config = DetectionConfig {
+            inbound: InboundRules::from(section),
+            ..DetectionConfig::default()
+        };
+        let svchost = r"C:\Windows\System32\svchost.exe";
+
+        let rdp = inbound("SRV-01", svchost, "10.0.9.7", 3389);
+        assert_eq!(
+            reasons(std::slice::from_ref(&rdp), &config),
+            [InboundReason::RemoteAdministration]
+        );
+        let admin = inbound("WS-17", "System", "10.10.0.4", 445);
+        assert!(reasons(&[admin], &config).is_empty());
+        let workstation = inbound("WS-17", svchost, "10.0.9.7", 135);
+        assert_eq!(
+            reasons(&[workstation], &config),
+            [InboundReason::Workstation]
+        );
+        assert!(reasons(&[inbound("WS-17", svchost, "127.0.0.1", 135)], &config).is_empty());
+        assert!(reasons(&[inbound("WS-17", svchost, "10.0.9.7", 5353)], &config).is_empty());
+
+        let backdoor = inbound("SRV-01", r"C:\Users\Public\svc.exe", "10.0.9.7", 4444);
+        assert_eq!(
+            reasons(std::slice::from_ref(&backdoor), &config),
+            [InboundReason::UnusualListeningPort]
+        );
+        let live = detect_anomalies_live_with_config(&backdoor, &VecDeque::new(), &config);
+        assert_eq!(live.len(), 1);
+        // Outbound rules don't look at inbound connections
+        assert!(
+            !live
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "UnusualPort")
+        );
+        let agent = inbound(
+            "SRV-01",
+            r"C:\Program Files\Agent\agent.exe",
+            "10.0.9.7",
+            9000,
+        );
+        assert!(reasons(&[agent], &config).is_empty());
+        assert!(reasons(&[rdp], &DetectionConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn unusual_listeners_settled_after_retention() {
+        let backdoor = inbound("SRV-01", r"C:\Users\Public\svc.exe", "10.0.9.7", 4444);
+        let mut later = inbound(
+            "SRV-01",
+            r"C:\Windows\System32\svchost.exe",
+            "10.0.9.7",
+            5353,
+        );
+        if let crate::sysmon::Event::InboundNetwork(e) = &mut later {
+            e.system.time_created.time = "2025-01-02T12:00:00Z".parse().ok();
+        }
+        let mut detector = AnomalyDetector::new(DetectionConfig::default());
+        for event in [&backdoor, &later] {
+            detector.analyze_chunk(&timed_events(std::slice::from_ref(event)));
+        }
+        // Reported once it left the retention, rather than kept until the end of the log
+        assert!(detector.unexpected_listeners.is_empty());
+        let reasons: Vec<_> = detector
+            .finish()
+            .iter()
+            .filter_map(|anomaly| match anomaly.kind() {
+                AnomalyKind::SuspiciousInbound { reason, .. } => Some(*reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons, [InboundReason::UnusualListeningPort]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Network indicators of compromise (IPs, CIDRs and domains) loaded from threat-intel feeds
+use crate::yaml_or_json::{from_yaml_or_json, load_yaml_or_json};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Feed file layout, in YAML or JSON:
+///
+/// ```yaml
+/// name: abuse-ch
+/// ips: [203.0.113.7, 198.51.100.0/24]
+/// domains: [evil.example]
+/// ```
+#[derive(Debug, Deserialize)]
+struct FeedFile {
+    name: Option<String>,
+    #[serde(default)]
+    ips: Vec<String>,
+    #[serde(default)]
+    domains: Vec<String>,
+}
+
+/// Single address or CIDR block
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Network {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl TryFrom<String> for Network {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        Self::parse(&s)
+    }
+}
+
+impl Network {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let address: IpAddr = address
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("invalid IP address '{s}'"))?;
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&prefix| prefix <= max_prefix)
+                .ok_or_else(|| anyhow!("invalid CIDR prefix in '{s}'"))?,
+            None => max_prefix,
+        };
+        Ok(Self { address, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Indicators of one threat-intel feed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IocFeed {
+    pub name: String,
+    /// Original indicator text and its parsed network
+    networks: Vec<(String, Network)>,
+    /// Lowercase domains, matching themselves and their subdomains
+    domains: Vec<String>,
+}
+
+impl IocFeed {
+    /// Load a YAML or JSON feed, named after the file unless it sets `name`
+    pub fn load(path: &Path) -> Result<Self> {
+        let default_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        load_yaml_or_json(path, "IOC file", |content, is_json| {
+            Self::parse(content, &default_name, is_json)
+        })
+    }
+
+    pub fn parse(content: &str, default_name: &str, is_json: bool) -> Result<Self> {
+        let file: FeedFile = from_yaml_or_json(content, is_json)?;
+        let networks = file
+            .ips
+            .into_iter()
+            .map(|ip| Network::parse(&ip).map(|network| (ip, network)))
+            .collect::<Result<_>>()?;
+        let domains = file
+            .domains
+            .iter()
+            .map(|domain| domain.trim().trim_end_matches('.').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        Ok(Self {
+            name: file.name.unwrap_or_else(|| default_name.to_string()),
+            networks,
+            domains,
+        })
+    }
+
+    /// Indicator matching the address, if any
+    pub fn match_ip(&self, ip: &str) -> Option<&str> {
+        let ip: IpAddr = ip.trim().parse().ok()?;
+        self.networks
+            .iter()
+            .find(|(_, network)| network.contains(ip))
+            .map(|(indicator, _)| indicator.as_str())
+    }
+
+    /// Indicator matching the domain or one of its parent domains, if any
+    pub fn match_domain(&self, domain: &str) -> Option<&str> {
+        let domain = domain.trim().trim_end_matches('.').to_lowercase();
+        self.domains
+            .iter()
+            .find(|indicator| {
+                domain == **indicator
+                    || domain
+                        .strip_suffix(indicator.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            })
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_and_json_feeds() {
+        let yaml = "ips:\n  - 203.0.113.7\n  - 198.51.100.0/24\n  - 2001:db8::/32\ndomains:\n  - Evil.Example.\n";
+        let feed = IocFeed::parse(yaml, "feed", false).unwrap();
+        assert_eq!(feed.name, "feed");
+        assert_eq!(feed.match_ip("203.0.113.7"), Some("203.0.113.7"));
+        assert_eq!(feed.match_ip("198.51.100.200"), Some("198.51.100.0/24"));
+        assert_eq!(feed.match_ip("2001:db8::1"), Some("2001:db8::/32"));
+        assert_eq!(feed.match_ip("198.51.101.1"), None);
+        assert_eq!(feed.match_domain("cdn.evil.example"), Some("evil.example"));
+        assert_eq!(feed.match_domain("notevil.example"), None);
+
+        let json = r#"{"name": "abuse", "ips": ["0.0.0.0/0"]}"#;
+        let feed = IocFeed::parse(json, "ignored", true).unwrap();
+        assert_eq!(feed.name, "abuse");
+        assert_eq!(feed.match_ip("8.8.8.8"), Some("0.0.0.0/0"));
+
+        assert!(IocFeed::parse("ips: [10.0.0.0/33]", "bad", false).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Known-good SHA256 hashes of binaries, from an NSRL export or a user-provided list
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Column holding the SHA256 in NSRL RDS and similar CSV exports
+const SHA256_COLUMNS: &[&str] = &["sha256", "sha-256"];
+
+/// SHA256 hashes of trusted binaries; NSRL sets hold millions, so they are kept as bytes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KnownGoodHashes {
+    hashes: HashSet<[u8; 32]>,
+}
+
+impl KnownGoodHashes {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read hash set {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid hash set {}", path.display()))
+    }
+
+    /// Parse a CSV file with a `sha256` (or `SHA-256`) column in its header, or a list with one
+    /// hash per line, optionally followed by the file name as written by `sha256sum`.
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .comment(Some(b'#'))
+            .from_reader(content.as_bytes());
+        let column = reader.headers()?.iter().position(|header| {
+            SHA256_COLUMNS
+                .iter()
+                .any(|name| header.eq_ignore_ascii_case(name))
+        });
+        let Some(column) = column else {
+            return Self::parse_list(content);
+        };
+        let mut hashes = HashSet::new();
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            let value = record
+                .get(column)
+                .ok_or_else(|| anyhow!("line {line}: missing SHA256 column"))?;
+            // NSRL leaves the column empty for files it only knows by other hashes
+            if value.is_empty() {
+                continue;
+            }
+            let hash = decode_sha256(value)
+                .ok_or_else(|| anyhow!("line {line}: '{value}' is not a SHA256 hash"))?;
+            hashes.insert(hash);
+        }
+        Ok(Self { hashes })
+    }
+
+    /// Parse a list of hashes, each at the start of its line
+    fn parse_list(content: &str) -> Result<Self> {
+        let mut hashes = HashSet::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let value = line.split_whitespace().next().unwrap_or_default();
+            let Some(hash) = decode_sha256(value) else {
+                bail!("line {}: '{value}' is not a SHA256 hash", index + 1);
+            };
+            hashes.insert(hash);
+        }
+        Ok(Self { hashes })
+    }
+
+    pub fn extend(&mut self, other: KnownGoodHashes) {
+        self.hashes.extend(other.hashes);
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn contains(&self, sha256: &str) -> bool {
+        decode_sha256(sha256).is_some_and(|hash| self.hashes.contains(&hash))
+    }
+}
+
+/// Bytes of a SHA256 written in hexadecimal, in either case
+fn decode_sha256(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut hash = [0; 32];
+    for (byte, digits) in hash.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// SHA256 of a Sysmon hashes field (e.g. `SHA1=...,SHA256=...`), if it was logged
+pub fn sha256_of(hashes: &str) -> Option<&str> {
+    hashes
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sha256"))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nsrl_csv_and_hash_lists() {
+        let known = "A".repeat(64);
+        let csv = format!(
+            "\"sha256\",\"sha1\",\"file_name\"\n\"{known}\",\"01\",\"notepad.exe\"\n\"\",\"02\",\"old.dll\"\n"
+        );
+        let set = KnownGoodHashes::parse(&csv).unwrap();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&known.to_lowercase()));
+
+        let list = format!(
+            "# trusted tools\n{}  tool.exe\n\n{}\n",
+            "b".repeat(64),
+            "c".repeat(64)
+        );
+        assert_eq!(KnownGoodHashes::parse(&list).unwrap().len(), 2);
+        assert!(KnownGoodHashes::parse("1234 tool.exe").is_err());
+        let quoted = format!("file_name,SHA-256\n\"tool, v2.exe\",{known}\nbad.exe,xyz\n");
+        let error = KnownGoodHashes::parse(&quoted).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: 'xyz' is not a SHA256 hash");
+
+        assert_eq!(
+            sha256_of("SHA1=01,MD5=02,SHA256=ABCD,IMPHASH=03"),
+            Some("ABCD")
+        );
+        assert_eq!(sha256_of("SHA1=01"), None);
+    }
+}
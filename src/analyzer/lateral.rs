@@ -0,0 +1,283 @@
+//! Lateral movement: one process connecting to the SMB, RPC or WinRM ports of many internal hosts
+//! within a short time (fan-out), and services created on those hosts shortly after, as PsExec,
+//! smbexec and similar tools do
+use crate::sysmon::{NetworkEventData, RegistryEventData};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashSet};
+use std::net::IpAddr;
+
+/// SMB and admin shares, RPC endpoint mapper and WinRM
+pub const LATERAL_PORTS: &[u16] = &[445, 135, 5985];
+/// Distinct internal hosts one process must reach to make a fan-out
+pub const FAN_OUT_MIN_HOSTS: usize = 5;
+/// Time within which the connections of a fan-out are made
+pub const FAN_OUT_WINDOW_SECONDS: i64 = 300;
+/// A service created on a reached host within this time after the last connection of the fan-out
+/// is correlated with it
+pub const SERVICE_WINDOW_SECONDS: i64 = 600;
+
+/// Connection of a process to a lateral movement port of another internal host
+#[derive(Debug, Clone)]
+pub struct LateralConnection {
+    pub time: DateTime<Utc>,
+    pub destination: IpAddr,
+    pub port: u16,
+    /// Destination host name resolved by Sysmon, lowercased
+    pub hostname: Option<String>,
+}
+
+impl LateralConnection {
+    /// Outbound connection to a lateral movement port of a private address other than the
+    /// machine's own, `None` for other connections
+    pub fn new(data: &NetworkEventData, time: DateTime<Utc>) -> Option<Self> {
+        if !data.initiated || !LATERAL_PORTS.contains(&data.destination_port) {
+            return None;
+        }
+        let destination: IpAddr = data.destination_ip.trim().parse().ok()?;
+        let internal = match destination {
+            IpAddr::V4(ip) => ip.is_private(),
+            IpAddr::V6(ip) => ip.is_unique_local(),
+        };
+        let own = data
+            .source_ip
+            .trim()
+            .parse::<IpAddr>()
+            .is_ok_and(|source| source == destination);
+        if !internal || own {
+            return None;
+        }
+        let hostname = data
+            .destination_hostname
+            .as_deref()
+            .map(str::trim)
+            .filter(|hostname| !hostname.is_empty() && *hostname != "-")
+            .map(str::to_lowercase);
+        Some(Self {
+            time,
+            destination,
+            port: data.destination_port,
+            hostname,
+        })
+    }
+}
+
+/// Connections reaching enough hosts to be a fan-out
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanOut {
+    destinations: HashSet<IpAddr>,
+    /// Ports connected to, in ascending order
+    pub ports: BTreeSet<u16>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Host names of the destinations, lowercased
+    pub hostnames: BTreeSet<String>,
+}
+
+impl FanOut {
+    /// Connections of one process within [`FAN_OUT_WINDOW_SECONDS`], `None` if they reach fewer
+    /// than [`FAN_OUT_MIN_HOSTS`] hosts
+    pub fn of<'a>(connections: impl IntoIterator<Item = &'a LateralConnection>) -> Option<Self> {
+        let mut connections = connections.into_iter();
+        let first = connections.next()?;
+        let mut fan_out = Self {
+            destinations: HashSet::new(),
+            ports: BTreeSet::new(),
+            start: first.time,
+            end: first.time,
+            hostnames: BTreeSet::new(),
+        };
+        fan_out.add(first);
+        for connection in connections {
+            fan_out.add(connection);
+        }
+        (fan_out.hosts() >= FAN_OUT_MIN_HOSTS).then_some(fan_out)
+    }
+
+    /// Fan-out of the connections of one process, in time order, from the first time those within
+    /// [`FAN_OUT_WINDOW_SECONDS`] reach enough hosts, with the later connections added
+    pub fn first(connections: &[LateralConnection]) -> Option<Self> {
+        let mut start = 0;
+        for (end, connection) in connections.iter().enumerate() {
+            while (connection.time - connections[start].time).num_seconds() > FAN_OUT_WINDOW_SECONDS
+            {
+                start += 1;
+            }
+            if let Some(mut fan_out) = Self::of(&connections[start..=end]) {
+                for later in &connections[end + 1..] {
+                    fan_out.add(later);
+                }
+                return Some(fan_out);
+            }
+        }
+        None
+    }
+
+    /// Count a later connection of the process
+    pub fn add(&mut self, connection: &LateralConnection) {
+        self.destinations.insert(connection.destination);
+        self.ports.insert(connection.port);
+        self.hostnames.extend(connection.hostname.clone());
+        self.start = self.start.min(connection.time);
+        self.end = self.end.max(connection.time);
+    }
+
+    pub fn hosts(&self) -> usize {
+        self.destinations.len()
+    }
+
+    /// Whether a destination of the fan-out is the computer, comparing host names without their
+    /// domain
+    pub fn reached(&self, computer: &str) -> bool {
+        let host = |name: &str| name.split('.').next().unwrap_or(name).to_lowercase();
+        let computer = host(computer);
+        self.hostnames.iter().any(|name| host(name) == computer)
+    }
+
+    /// Whether a service created at `time` follows the fan-out closely enough to belong to it
+    pub fn followed_by(&self, time: DateTime<Utc>) -> bool {
+        time >= self.start && (time - self.end).num_seconds() <= SERVICE_WINDOW_SECONDS
+    }
+
+    pub fn seconds(&self) -> i64 {
+        (self.end - self.start).num_seconds()
+    }
+}
+
+/// Service installed by writing its ImagePath under the Services key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedService {
+    pub name: String,
+    pub image_path: String,
+}
+
+impl CreatedService {
+    /// Service whose ImagePath a registry SetValue writes, `None` for other values
+    pub fn new(data: &RegistryEventData) -> Option<Self> {
+        let segments: Vec<&str> = data.target_object.split('\\').collect();
+        let [hive, system, control_set, services, name, value] = segments.as_slice() else {
+            return None;
+        };
+        let is = |segment: &str, expected: &str| segment.eq_ignore_ascii_case(expected);
+        let control_set = control_set.to_lowercase();
+        let is_service = (is(hive, "HKLM") || is(hive, "HKEY_LOCAL_MACHINE"))
+            && is(system, "System")
+            && (control_set == "currentcontrolset" || control_set.starts_with("controlset"))
+            && is(services, "Services")
+            && is(value, "ImagePath");
+        is_service.then(|| Self {
+            name: name.to_string(),
+            image_path: data.details.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::{Anomaly, Severity, detect_anomalies, detect_anomalies_live};
+    use crate::jsonl::parse_json_event;
+    use crate::sysmon::Event as SysmonEvent;
+    use std::collections::VecDeque;
+
+    fn connect(time: &str, destination: u8, port: u16) -> SysmonEvent {
+        let line = serde_json::json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "WS-01.corp.local",
+            "EventID": 3,
+            "RecordNumber": destination,
+            "UtcTime": format!("2025-01-01 {time}.000"),
+            "ProcessGuid": "{11111111-2222-3333-4444-555555555555}",
+            "ProcessId": "42",
+            "Image": r"C:\Tools\PsExec64.exe",
+            "Protocol": "tcp",
+            "Initiated": "true",
+            "SourceIsIpv6": "false",
+            "SourceIp": "10.0.0.5",
+            "SourcePort": "50123",
+            "DestinationIsIpv6": "false",
+            "DestinationIp": format!("10.0.1.{destination}"),
+            "DestinationHostname": format!("SRV-{destination:02}.corp.local"),
+            "DestinationPort": port.to_string(),
+        });
+        parse_json_event(&line.to_string()).unwrap()
+    }
+
+    fn service(time: &str, computer: &str) -> SysmonEvent {
+        let line = serde_json::json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": computer,
+            "EventID": 13,
+            "RecordNumber": 90,
+            "EventType": "SetValue",
+            "UtcTime": format!("2025-01-01 {time}.000"),
+            "ProcessGuid": "{99999999-2222-3333-4444-555555555555}",
+            "ProcessId": "640",
+            "Image": r"C:\Windows\System32\services.exe",
+            "TargetObject": r"HKLM\System\CurrentControlSet\Services\PSEXESVC\ImagePath",
+            "Details": r"%SystemRoot%\PSEXESVC.exe",
+        });
+        parse_json_event(&line.to_string()).unwrap()
+    }
+
+    fn lateral(anomalies: &[Anomaly]) -> Vec<String> {
+        anomalies
+            .iter()
+            .filter(|anomaly| anomaly.rule_id() == "SLA-031")
+            .map(|anomaly| anomaly.description())
+            .collect()
+    }
+
+    #[test]
+    fn fan_out_and_service_creation() {
+        let mut events: Vec<SysmonEvent> = (1..=5)
+            .map(|host| connect(&format!("10:00:{:02}", host * 10), host, 445))
+            .collect();
+        events.push(connect("10:00:55", 3, 135));
+        events.push(service("10:01:30", "SRV-03"));
+        // Too late, and on a host the process never connected to
+        events.push(service("10:20:00", "SRV-04"));
+        events.push(service("10:01:40", "SRV-09"));
+        let findings = detect_anomalies(&events);
+        assert_eq!(
+            lateral(&findings),
+            [
+                "Lateral Movement: PsExec64.exe on WS-01.corp.local connected to 5 internal hosts \
+                 on ports 445 within 40s",
+                "Lateral Movement: service PSEXESVC (%SystemRoot%\\PSEXESVC.exe) created on SRV-03 \
+                 after PsExec64.exe on WS-01.corp.local connected to 5 internal hosts on ports \
+                 135, 445 within 45s",
+            ]
+        );
+        let correlated = findings
+            .iter()
+            .filter(|anomaly| anomaly.rule_id() == "SLA-031")
+            .nth(1)
+            .unwrap();
+        assert_eq!(correlated.severity(), Severity::Critical);
+        assert!(correlated.attack_techniques().contains(&"T1569.002"));
+
+        // Four hosts are not a fan-out, a connection to the fifth one is, once
+        let context: VecDeque<SysmonEvent> = events[..4].iter().cloned().collect();
+        assert!(lateral(&detect_anomalies_live(&events[5], &context)).is_empty());
+        assert_eq!(
+            lateral(&detect_anomalies_live(&events[4], &context)).len(),
+            1
+        );
+        let context: VecDeque<SysmonEvent> = events[..5].iter().cloned().collect();
+        assert!(lateral(&detect_anomalies_live(&events[5], &context)).is_empty());
+        let context: VecDeque<SysmonEvent> = events[..6].iter().cloned().collect();
+        assert_eq!(
+            lateral(&detect_anomalies_live(&events[6], &context)),
+            [lateral(&findings)[1].clone()]
+        );
+        assert!(lateral(&detect_anomalies_live(&events[8], &context)).is_empty());
+
+        // Five hosts over eight minutes are no fan-out, neither in batch nor in live analysis
+        let mut slow: Vec<SysmonEvent> = (1..=5)
+            .map(|host| connect(&format!("10:{:02}:00", host * 2), host, 445))
+            .collect();
+        slow.push(service("10:11:00", "SRV-03"));
+        assert!(lateral(&detect_anomalies(&slow)).is_empty());
+        let context: VecDeque<SysmonEvent> = slow[..5].iter().cloned().collect();
+        assert!(lateral(&detect_anomalies_live(&slow[5], &context)).is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! Process lifetimes, from the creation (1) and termination (5) events of each process GUID
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Processes ending within this time of their creation are short-lived
+pub const SHORT_LIVED_MILLISECONDS: i64 = 5000;
+/// Office applications, whose children run macros and exploit payloads, and the Service Control
+/// Manager, whose children run commands of services created remotely (PsExec, smbexec)
+const WATCHED_PARENTS: &[&str] = &[
+    "winword.exe",
+    "excel.exe",
+    "powerpnt.exe",
+    "outlook.exe",
+    "msaccess.exe",
+    "mspub.exe",
+    "onenote.exe",
+    "visio.exe",
+    "services.exe",
+];
+/// Helpers these parents start and that exit on their own: error reporting and print spooling
+const EXPECTED_CHILDREN: &[&str] = &["werfault.exe", "dw20.exe", "splwow64.exe"];
+
+/// Creation and termination times of a process, each `None` when not in the log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Lifetime {
+    pub started: Option<DateTime<Utc>>,
+    pub ended: Option<DateTime<Utc>>,
+}
+
+impl Lifetime {
+    /// Time between the creation and termination, when both were logged
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.ended? - self.started?)
+    }
+}
+
+/// Lifetime of every process created or terminated in the events
+pub fn process_lifetimes(events: &[SysmonEvent]) -> HashMap<Uuid, Lifetime> {
+    let mut lifetimes: HashMap<Uuid, Lifetime> = HashMap::new();
+    for event in events {
+        match event {
+            SysmonEvent::ProcessCreate(e) => {
+                lifetimes
+                    .entry(e.event_data.process_guid.process_guid)
+                    .or_default()
+                    .started = e.system.time_created.time;
+            }
+            SysmonEvent::ProcessTerminate(e) => {
+                lifetimes
+                    .entry(e.event_data.process_guid.process_guid)
+                    .or_default()
+                    .ended = e.system.time_created.time;
+            }
+            _ => {}
+        }
+    }
+    lifetimes
+}
+
+/// Whether a process created by `parent` is checked for ending quickly
+pub fn is_watched_child(parent: &str, image: &str) -> bool {
+    let name = |path: &str| {
+        path.rsplit('\\')
+            .next()
+            .unwrap_or(path)
+            .to_ascii_lowercase()
+    };
+    WATCHED_PARENTS.contains(&name(parent).as_str())
+        && !EXPECTED_CHILDREN.contains(&name(image).as_str())
+}
+
+/// Whether a process that ran for `duration` is short-lived
+pub fn is_short_lived(duration: Duration) -> bool {
+    duration >= Duration::zero() && duration.num_milliseconds() < SHORT_LIVED_MILLISECONDS
+}
+
+/// Duration for display: `350 ms`, `4.2 s`, `3 min 5 s`, `2 h 10 min` or `3 d 4 h`
+pub fn format_duration(duration: Duration) -> String {
+    let ms = duration.num_milliseconds().max(0);
+    let s = ms / 1000;
+    match s {
+        0 => format!("{ms} ms"),
+        1..60 => format!("{:.1} s", ms as f64 / 1000.0),
+        60..3600 => format!("{} min {} s", s / 60, s % 60),
+        3600..86400 => format!("{} h {} min", s / 3600, s % 3600 / 60),
+        _ => format!("{} d {} h", s / 86400, s % 86400 / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watched_children_and_durations() {
+        let word = r"C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE";
+        assert!(is_watched_child(word, r"C:\Windows\System32\cmd.exe"));
+        assert!(!is_watched_child(word, r"C:\Windows\System32\WerFault.exe"));
+        assert!(is_watched_child(
+            r"C:\Windows\System32\services.exe",
+            r"C:\Windows\BSDSrBvX.exe"
+        ));
+        assert!(!is_watched_child(
+            r"C:\Windows\explorer.exe",
+            r"C:\Windows\System32\cmd.exe"
+        ));
+        assert!(is_short_lived(Duration::milliseconds(800)));
+        assert!(!is_short_lived(Duration::seconds(30)));
+        assert!(!is_short_lived(Duration::seconds(-1)));
+        assert_eq!(format_duration(Duration::milliseconds(350)), "350 ms");
+        assert_eq!(format_duration(Duration::milliseconds(4250)), "4.2 s");
+        assert_eq!(format_duration(Duration::seconds(185)), "3 min 5 s");
+        assert_eq!(format_duration(Duration::seconds(7800)), "2 h 10 min");
+        assert_eq!(format_duration(Duration::hours(76)), "3 d 4 h");
+    }
+}
@@ -0,0 +1,193 @@
+//! Executables posing as well-known binaries: a name a typo away from one (scvhost.exe,
+//! lsasss.exe, chr0me.exe) or the exact name of a Windows binary outside its directories
+use std::fmt::Display;
+
+const SYSTEM32: &[&str] = &[r"\windows\system32\"];
+const SYSTEM_DIRECTORIES: &[&str] = &[r"\windows\system32\", r"\windows\syswow64\"];
+/// Windows binaries and the directories (lowercase, without the drive) they run from
+const SYSTEM_BINARIES: &[(&str, &[&str])] = &[
+    ("svchost.exe", SYSTEM_DIRECTORIES),
+    ("lsass.exe", SYSTEM32),
+    ("csrss.exe", SYSTEM32),
+    ("smss.exe", SYSTEM32),
+    ("wininit.exe", SYSTEM32),
+    ("winlogon.exe", SYSTEM32),
+    ("services.exe", SYSTEM32),
+    ("lsaiso.exe", SYSTEM32),
+    ("spoolsv.exe", SYSTEM32),
+    ("taskhost.exe", SYSTEM32),
+    ("taskhostw.exe", SYSTEM32),
+    ("sihost.exe", SYSTEM32),
+    ("fontdrvhost.exe", SYSTEM32),
+    ("dwm.exe", SYSTEM32),
+    ("ctfmon.exe", SYSTEM_DIRECTORIES),
+    ("conhost.exe", SYSTEM32),
+    ("dllhost.exe", SYSTEM_DIRECTORIES),
+    ("rundll32.exe", SYSTEM_DIRECTORIES),
+    ("regsvr32.exe", SYSTEM_DIRECTORIES),
+    ("runtimebroker.exe", SYSTEM32),
+    ("searchindexer.exe", SYSTEM32),
+    (
+        "wmiprvse.exe",
+        &[r"\windows\system32\wbem\", r"\windows\syswow64\wbem\"],
+    ),
+    ("explorer.exe", &[r"\windows\", r"\windows\syswow64\"]),
+];
+/// Applications whose names are imitated but that may be installed anywhere
+const APPLICATION_BINARIES: &[&str] = &[
+    "chrome.exe",
+    "firefox.exe",
+    "msedge.exe",
+    "iexplore.exe",
+    "outlook.exe",
+    "winword.exe",
+    "excel.exe",
+    "onedrive.exe",
+    "teams.exe",
+];
+/// Component store and servicing folders holding copies of system binaries during updates
+const SERVICING_DIRECTORIES: &[&str] = &[r"\windows\winsxs\", r"\windows\servicing\"];
+/// Names shorter than this (without `.exe`) are not compared, as one edit turns them into
+/// unrelated tools
+const MIN_COMPARED_LENGTH: usize = 5;
+/// Names of this length and longer (without `.exe`) may differ by two edits instead of one
+const TWO_EDITS_LENGTH: usize = 8;
+
+/// How an executable imitates a well-known binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Masquerade {
+    /// Name a typo away from the binary
+    LookAlike(&'static str),
+    /// Name of a Windows binary, run from another directory
+    WrongDirectory,
+}
+
+impl Display for Masquerade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Masquerade::LookAlike(name) => write!(f, "looks like {name}"),
+            Masquerade::WrongDirectory => f.write_str("outside its Windows directory"),
+        }
+    }
+}
+
+/// How the executable at `image` imitates a well-known binary, `None` for the binaries
+/// themselves and for unrelated names
+pub fn masquerade(image: &str) -> Option<Masquerade> {
+    let image = image.to_ascii_lowercase();
+    let (directory, name) = image.rsplit_once('\\')?;
+    if let Some((_, directories)) = SYSTEM_BINARIES.iter().find(|(known, _)| *known == name) {
+        // Compare the directory without its drive letter; other path forms are not judged
+        if directory.as_bytes().get(1) != Some(&b':') {
+            return None;
+        }
+        let directory = format!("{}\\", &directory[2..]);
+        let expected = directories.contains(&directory.as_str())
+            || SERVICING_DIRECTORIES
+                .iter()
+                .any(|servicing| directory.starts_with(servicing));
+        return (!expected).then_some(Masquerade::WrongDirectory);
+    }
+    if APPLICATION_BINARIES.contains(&name) {
+        return None;
+    }
+    let stem = name.strip_suffix(".exe")?;
+    // Look-alikes often carry an architecture suffix (svhost64.exe)
+    let unsuffixed = stem
+        .strip_suffix("64")
+        .or_else(|| stem.strip_suffix("32"))
+        .unwrap_or(stem);
+    SYSTEM_BINARIES
+        .iter()
+        .map(|(known, _)| *known)
+        .chain(APPLICATION_BINARIES.iter().copied())
+        .find(|known| {
+            let known_stem = known.trim_end_matches(".exe");
+            let allowed = if known_stem.len() >= TWO_EDITS_LENGTH {
+                2
+            } else {
+                1
+            };
+            known_stem.len() >= MIN_COMPARED_LENGTH
+                && [stem, unsuffixed]
+                    .iter()
+                    .any(|name| edit_distance(name, known_stem) <= allowed)
+        })
+        .map(Masquerade::LookAlike)
+}
+
+/// Edits (insertions, deletions, substitutions and swaps of adjacent characters) between two
+/// names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows of the distances for the two previous prefixes of `a` and the current one
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_alikes_and_wrong_directories() {
+        assert_eq!(
+            masquerade(r"C:\Windows\scvhost.exe"),
+            Some(Masquerade::LookAlike("svchost.exe"))
+        );
+        assert_eq!(
+            masquerade(r"C:\Windows\System32\lsasss.exe"),
+            Some(Masquerade::LookAlike("lsass.exe"))
+        );
+        assert_eq!(
+            masquerade(r"C:\Users\Bob\AppData\Local\chr0me.exe"),
+            Some(Masquerade::LookAlike("chrome.exe"))
+        );
+        assert_eq!(
+            masquerade(r"C:\ProgramData\svhost64.exe"),
+            Some(Masquerade::LookAlike("svchost.exe"))
+        );
+        assert_eq!(
+            masquerade(r"C:\Users\Public\svchost.exe"),
+            Some(Masquerade::WrongDirectory)
+        );
+        assert_eq!(
+            masquerade(r"C:\Users\Public\Windows\System32\svchost.exe"),
+            Some(Masquerade::WrongDirectory)
+        );
+        for legit in [
+            r"C:\Windows\System32\svchost.exe",
+            r"D:\Windows\SysWOW64\svchost.exe",
+            r"C:\Windows\System32\taskhostw.exe",
+            r"C:\Windows\System32\LsaIso.exe",
+            r"C:\Windows\explorer.exe",
+            r"C:\Windows\WinSxS\amd64_microsoft-windows-explorer_31bf3856ad364e35_10.0.19041.1_none\explorer.exe",
+            r"C:\Users\Bob\AppData\Local\Google\Chrome\Application\chrome.exe",
+            r"C:\Windows\System32\cmd.exe",
+            r"C:\Windows\System32\sc.exe",
+            r"C:\Tools\notepad++.exe",
+        ] {
+            assert_eq!(masquerade(legit), None, "{legit}");
+        }
+        assert_eq!(edit_distance("scvhost", "svchost"), 1);
+        assert_eq!(edit_distance("explroer", "explorer"), 1);
+        assert_eq!(edit_distance("lsass", "lsaiso"), 2);
+    }
+}
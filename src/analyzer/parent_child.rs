@@ -0,0 +1,254 @@
+//! Declarative parent/child process rules, the built-in ones and those of the detection config file
+use super::Severity;
+use crate::filters::path_glob::PathGlob;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// Rules shipped with the analyzer, in the config file format. Shells, reconnaissance tools and
+/// LOLBins started by internet-facing servers are the usual sign of a webshell; Java only counts
+/// when it runs an application server, as desktop Java applications start shells as they please.
+const DEFAULT_RULES: &str = r#"
+- parent: "!services.exe"
+  child: svchost.exe
+  severity: high
+  reason: svchost.exe is spawned by a non-service process
+- parent: winword.exe|excel.exe|powerpnt.exe
+  child: powershell.exe|cmd.exe|wscript.exe|cscript.exe
+  severity: high
+  reason: Office application spawned a shell
+- parent: w3wp.exe|httpd.exe|nginx.exe|php-cgi.exe|tomcat*.exe|sqlservr.exe|UMWorkerProcess.exe
+  child: &server_children "cmd.exe|powershell.exe|pwsh.exe|wscript.exe|cscript.exe|mshta.exe\
+    |rundll32.exe|regsvr32.exe|certutil.exe|bitsadmin.exe|msbuild.exe|wmic.exe|curl.exe\
+    |whoami.exe|net.exe|net1.exe|nltest.exe|systeminfo.exe|ipconfig.exe|tasklist.exe|quser.exe\
+    |hostname.exe|bash.exe|sh.exe"
+  severity: high
+  reason: Server process spawned a shell or LOLBin (possible webshell)
+- parent: java.exe|javaw.exe
+  parent_command_line: "*catalina*|*tomcat*|*jboss*|*wildfly*|*weblogic*|*websphere*|*jetty*"
+  child: *server_children
+  severity: high
+  reason: Java application server spawned a shell or LOLBin (possible webshell)
+"#;
+
+/// Case-insensitive glob (`*` and `?`) with `|` separated alternatives, negated by a leading `!`.
+/// Patterns containing a `\` match the full image path, others only the file name. Alternatives
+/// with `**` or a `%TEMP%` style variable are [`PathGlob`]s matching the full path.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern {
+    negated: bool,
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Alternative {
+    Glob(String),
+    Path(PathGlob),
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = anyhow::Error;
+
+    fn try_from(pattern: String) -> Result<Self> {
+        let (negated, rest) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern.as_str()),
+        };
+        let alternatives = rest
+            .split('|')
+            .map(|alternative| {
+                let alternative = alternative.trim().to_lowercase();
+                if alternative.is_empty() {
+                    Err(anyhow!("empty alternative in pattern '{pattern}'"))
+                } else if PathGlob::is_path_glob(&alternative) {
+                    Ok(Alternative::Path(PathGlob::parse(&alternative)?))
+                } else {
+                    Ok(Alternative::Glob(alternative))
+                }
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            negated,
+            alternatives,
+        })
+    }
+}
+
+impl Pattern {
+    pub(crate) fn matches_image(&self, image: &str) -> bool {
+        let image = image.to_lowercase();
+        let name = image.rsplit('\\').next().unwrap_or(&image);
+        self.matches_with(|alternative| match alternative {
+            Alternative::Glob(glob) if glob.contains('\\') => glob_match(glob, &image),
+            Alternative::Glob(glob) => glob_match(glob, name),
+            Alternative::Path(glob) => glob.matches(&image),
+        })
+    }
+
+    pub(crate) fn matches_text(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.matches_with(|alternative| match alternative {
+            Alternative::Glob(glob) => glob_match(glob, &text),
+            Alternative::Path(glob) => glob.matches(&text),
+        })
+    }
+
+    fn matches_with(&self, matches: impl Fn(&Alternative) -> bool) -> bool {
+        self.alternatives.iter().any(matches) != self.negated
+    }
+}
+
+/// Glob match of the whole text, `*` matches any run of characters and `?` a single one
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently stands for
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Process creation by a matching parent, reported as a suspicious parent/child chain
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParentChildRule {
+    pub parent: Pattern,
+    /// Condition on the parent command line, e.g. `*catalina*`
+    pub parent_command_line: Option<Pattern>,
+    pub child: Pattern,
+    /// Condition on the child command line, e.g. `*-enc*`
+    pub command_line: Option<Pattern>,
+    pub severity: Severity,
+    pub reason: String,
+}
+
+impl ParentChildRule {
+    /// Rules checked unless the config replaces them
+    pub fn defaults() -> Vec<Self> {
+        serde_norway::from_str(DEFAULT_RULES).expect("built-in parent/child rules are valid")
+    }
+
+    pub fn matches(
+        &self,
+        parent_image: &str,
+        parent_command_line: &str,
+        image: &str,
+        command_line: &str,
+    ) -> bool {
+        self.parent.matches_image(parent_image)
+            && self
+                .parent_command_line
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches_text(parent_command_line))
+            && self.child.matches_image(image)
+            && self
+                .command_line
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches_text(command_line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_and_default_rules() {
+        assert!(glob_match("w3wp*.exe", "w3wp.exe"));
+        assert!(glob_match("*-enc*", "powershell -nop -enc aqbfafga"));
+        assert!(glob_match("cmd.ex?", "cmd.exe"));
+        assert!(!glob_match("cmd.exe", "cmd.exe.bak"));
+
+        let rules = ParentChildRule::defaults();
+        assert_eq!(rules.len(), 4);
+        let svchost = &rules[0];
+        assert!(svchost.matches(
+            r"C:\Temp\evil.exe",
+            "",
+            r"C:\Windows\System32\svchost.exe",
+            ""
+        ));
+        assert!(!svchost.matches(
+            r"C:\Windows\System32\SERVICES.EXE",
+            "",
+            r"C:\Windows\System32\svchost.exe",
+            ""
+        ));
+
+        let rule: ParentChildRule = serde_norway::from_str(
+            "parent: C:\\Windows\\*\\w3wp.exe\nchild: cmd.exe\ncommand_line: '*whoami*'\nseverity: critical\nreason: Web shell\n",
+        )
+        .unwrap();
+        assert!(rule.matches(
+            r"c:\windows\system32\inetsrv\w3wp.exe",
+            "w3wp.exe",
+            r"C:\Windows\System32\cmd.exe",
+            "cmd.exe /c WHOAMI"
+        ));
+        assert!(!rule.matches(
+            r"D:\w3wp.exe",
+            "w3wp.exe",
+            r"C:\Windows\System32\cmd.exe",
+            "cmd /c whoami"
+        ));
+        assert!(
+            serde_norway::from_str::<ParentChildRule>(
+                "parent: a|\nchild: b\nseverity: low\nreason: x\n"
+            )
+            .is_err()
+        );
+
+        let dropped = Pattern::try_from(r"%APPDATA%\**\*.exe|rundll32.exe".to_string()).unwrap();
+        assert!(dropped.matches_image(r"C:\Users\bob\AppData\Roaming\x\y\upd.exe"));
+        assert!(dropped.matches_image(r"C:\Windows\System32\rundll32.exe"));
+        assert!(!dropped.matches_image(r"C:\Program Files\App\upd.exe"));
+        let percent = Pattern::try_from("*50%*".to_string()).unwrap();
+        assert!(percent.matches_text("throttle --cpu 50%"));
+    }
+
+    #[test]
+    fn shells_started_by_servers() {
+        let rules = ParentChildRule::defaults();
+        let reported = |parent: &str, parent_command_line: &str, child: &str| {
+            rules
+                .iter()
+                .any(|rule| rule.matches(parent, parent_command_line, child, ""))
+        };
+        let w3wp = r"C:\Windows\System32\inetsrv\w3wp.exe";
+        let cmd = r"C:\Windows\System32\cmd.exe";
+        assert!(reported(w3wp, "w3wp.exe -ap DefaultAppPool", cmd));
+        assert!(reported(
+            w3wp,
+            "w3wp.exe",
+            r"C:\Windows\System32\whoami.exe"
+        ));
+        // ASP.NET compiling a page is expected
+        let csc = r"C:\Windows\Microsoft.NET\Framework64\v4.0.30319\csc.exe";
+        assert!(!reported(w3wp, "w3wp.exe", csc));
+
+        let java = r"C:\Program Files\Java\bin\java.exe";
+        let catalina = "java.exe -Dcatalina.home=C:\\Tomcat org.apache.catalina.startup.Bootstrap";
+        assert!(reported(java, catalina, cmd));
+        assert!(!reported(java, "java.exe -jar ide.jar", cmd));
+    }
+}
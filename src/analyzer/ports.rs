@@ -0,0 +1,151 @@
+//! Expected destination ports of processes, the built-in profiles and the allowlist of the
+//! detection config file, and the port usage baseline of a log
+use super::parent_child::Pattern;
+use crate::filters::network::PortRange;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// Profiles shipped with the analyzer, in the config file format. RPC clients (`svchost.exe`,
+/// `lsass.exe`, ...) get the dynamic port range, which domain controllers hand out for RPC.
+const DEFAULT_PROFILES: &str = r#"
+- image: "*"
+  ports: [53, 80, 443]
+- image: chrome.exe|msedge.exe|firefox.exe|iexplore.exe|brave.exe|opera.exe|vivaldi.exe
+  ports: [8080, 8443]
+- image: outlook.exe|thunderbird.exe
+  ports: [25, 110, 143, 465, 587, 993, 995]
+- image: svchost.exe|lsass.exe|services.exe|system|dfsrs.exe|ntfrs.exe|dns.exe
+  ports: [88, 123, 135, 137, 138, 139, 389, 445, 464, 636, 3268, 3269, "49152-65535"]
+- image: teams.exe|ms-teams.exe|zoom.exe|webex.exe|skype.exe
+  ports: ["3478-3481", "8801-8810", "50000-59999"]
+- image: mstsc.exe
+  ports: [3389]
+- image: ssh.exe|putty.exe|winscp.exe
+  ports: [22]
+"#;
+
+/// A port used by this many processes is a service of the environment (proxy, internal API)
+const COMMON_PORT_PROCESSES: usize = 3;
+/// A process using a port on this many hosts does so by design
+const COMMON_PAIR_COMPUTERS: usize = 3;
+
+/// Destination ports expected from the processes with a matching image
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortProfile {
+    pub image: Pattern,
+    pub ports: Vec<PortRange>,
+}
+
+impl PortProfile {
+    /// Profiles used unless the config adds more
+    pub fn defaults() -> Vec<Self> {
+        serde_norway::from_str(DEFAULT_PROFILES).expect("built-in port profiles are valid")
+    }
+}
+
+/// Whether one of the profiles expects connections of `image` to `port`
+pub fn is_expected_port(profiles: &[PortProfile], image: &str, port: u16) -> bool {
+    profiles.iter().any(|profile| {
+        profile.ports.iter().any(|range| range.contains(port)) && profile.image.matches_image(image)
+    })
+}
+
+/// Processes and hosts seen connecting to each destination port
+#[derive(Debug, Default)]
+pub struct PortBaseline {
+    /// Maps port to the process names using it and the time they last did
+    processes: HashMap<u16, HashMap<String, DateTime<Utc>>>,
+    /// Maps (process name, port) to the computers it was seen on
+    computers: HashMap<(String, u16), HashSet<String>>,
+}
+
+impl PortBaseline {
+    pub fn record(&mut self, process: &str, port: u16, computer: &str, time: DateTime<Utc>) {
+        let process = process.to_lowercase();
+        self.processes
+            .entry(port)
+            .or_default()
+            .insert(process.clone(), time);
+        self.computers
+            .entry((process, port))
+            .or_default()
+            .insert(computer.to_lowercase());
+    }
+
+    /// Forget the processes that have not used their port since `time`
+    pub fn retain_since(&mut self, time: DateTime<Utc>) {
+        self.processes.retain(|port, processes| {
+            processes.retain(|process, last| {
+                let recent = *last >= time;
+                if !recent {
+                    self.computers.remove(&(process.clone(), *port));
+                }
+                recent
+            });
+            !processes.is_empty()
+        });
+    }
+
+    /// A pair is rare unless many processes use the port or the process uses it on many hosts
+    pub fn is_rare(&self, process: &str, port: u16) -> bool {
+        let process = process.to_lowercase();
+        self.processes
+            .get(&port)
+            .is_none_or(|processes| processes.len() < COMMON_PORT_PROCESSES)
+            && self
+                .computers
+                .get(&(process, port))
+                .is_none_or(|computers| computers.len() < COMMON_PAIR_COMPUTERS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn profiles_and_baseline() {
+        let mut profiles = PortProfile::defaults();
+        let browser = r"C:\Program Files\Google\Chrome\Application\chrome.exe";
+        assert!(is_expected_port(&profiles, browser, 443));
+        assert!(is_expected_port(&profiles, browser, 8443));
+        assert!(!is_expected_port(&profiles, browser, 4444));
+        assert!(is_expected_port(&profiles, "svchost.exe", 49700));
+        assert!(!is_expected_port(
+            &profiles,
+            r"C:\Users\Public\a.exe",
+            49700
+        ));
+
+        let allowlist: Vec<PortProfile> =
+            serde_norway::from_str("- image: backupagent.exe\n  ports: [10000, \"10080-10090\"]\n")
+                .unwrap();
+        profiles.extend(allowlist);
+        assert!(is_expected_port(&profiles, "BackupAgent.exe", 10085));
+        assert!(!is_expected_port(&profiles, "BackupAgent.exe", 10091));
+        assert!(
+            serde_norway::from_str::<Vec<PortProfile>>("- image: a.exe\n  ports: [\"9-1\"]")
+                .is_err()
+        );
+
+        let mut baseline = PortBaseline::default();
+        let time = |hour: u32| Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap();
+        baseline.record("implant.exe", 4444, "WS1", time(10));
+        for (hour, process) in [(8, "a.exe"), (10, "b.exe"), (10, "c.exe")] {
+            baseline.record(process, 3128, "WS1", time(hour));
+        }
+        for computer in ["WS1", "WS2", "WS3"] {
+            baseline.record("agent.exe", 9000, computer, time(10));
+        }
+        assert!(baseline.is_rare("implant.exe", 4444));
+        assert!(!baseline.is_rare("a.exe", 3128));
+        assert!(!baseline.is_rare("Agent.exe", 9000));
+        // Once a.exe is forgotten, two processes use the port
+        baseline.retain_since(time(9));
+        assert!(baseline.is_rare("b.exe", 3128));
+        assert!(!baseline.is_rare("Agent.exe", 9000));
+    }
+}
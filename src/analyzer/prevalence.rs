@@ -0,0 +1,320 @@
+//! Prevalence of executables across detection runs, kept in a file the commands update after each
+//! run, so that executables never seen before, or only seen a few times on a single host, can be
+//! reported. `stats --dns` keeps the domains it saw in the same file, to report those never
+//! queried before.
+use super::known_good;
+use crate::sysmon::{Event as SysmonEvent, ProcessCreateEvent};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tracing::warn;
+
+/// Format version of the store, bumped when its layout changes
+pub const PREVALENCE_VERSION: u32 = 1;
+/// Runs recorded before findings are reported, as every executable is new to an empty store
+pub const MIN_BASELINE_RUNS: u32 = 3;
+/// An executable seen in at most this many earlier runs, always on the same host, is rare
+const RARE_RUNS: u32 = 2;
+/// Hosts remembered per executable, more than needed to tell rare from common
+const MAX_HOSTS: usize = 10;
+
+/// Runs and hosts an executable was seen in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sightings {
+    /// Full path of the executable when it was first seen
+    pub image: String,
+    pub runs: u32,
+    /// Lowercase computer names, at most [`MAX_HOSTS`]
+    pub hosts: BTreeSet<String>,
+}
+
+impl Sightings {
+    pub fn new(image: &str) -> Self {
+        Self {
+            image: image.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn add_host(&mut self, computer: &str) {
+        if self.hosts.len() < MAX_HOSTS {
+            self.hosts.insert(computer.to_lowercase());
+        }
+    }
+}
+
+/// Executables of past runs, by [`executable_key`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrevalenceStore {
+    version: u32,
+    /// Detection runs recorded
+    pub runs: u32,
+    executables: BTreeMap<String, Sightings>,
+    /// `stats --dns` runs recorded
+    #[serde(default)]
+    pub domain_runs: u32,
+    /// Runs each lowercase domain was queried in
+    #[serde(default)]
+    domains: BTreeMap<String, u32>,
+}
+
+impl Default for PrevalenceStore {
+    fn default() -> Self {
+        Self {
+            version: PREVALENCE_VERSION,
+            runs: 0,
+            executables: BTreeMap::new(),
+            domain_runs: 0,
+            domains: BTreeMap::new(),
+        }
+    }
+}
+
+/// Key of an executable: its SHA256 when logged, so renamed copies count as one, else its path
+pub fn executable_key(image: &str, sha256: Option<&str>) -> String {
+    match sha256 {
+        Some(sha256) => format!("sha256:{}", sha256.to_lowercase()),
+        None => format!("image:{}", image.to_lowercase()),
+    }
+}
+
+/// Key of the executable a process starts
+pub fn process_key(event: &ProcessCreateEvent) -> String {
+    let data = &event.event_data;
+    executable_key(
+        &data.image.image,
+        known_good::sha256_of(&data.hashes.hashes),
+    )
+}
+
+/// Executables started in the events and the hosts they ran on, by [`executable_key`]
+pub fn executables(events: &[SysmonEvent]) -> BTreeMap<String, Sightings> {
+    let mut executables = BTreeMap::new();
+    for event in events {
+        if let SysmonEvent::ProcessCreate(event) = event {
+            executables
+                .entry(process_key(event))
+                .or_insert_with(|| Sightings::new(&event.event_data.image.image))
+                .add_host(&event.system.computer.computer);
+        }
+    }
+    executables
+}
+
+/// Add a detection run over the events to the store, if any. Detection only reads the store, so
+/// the commands record their runs once it is done; a failure is only logged, as the findings of
+/// the run stand.
+pub fn record_run(path: Option<&Path>, events: &[SysmonEvent]) {
+    let Some(path) = path else {
+        return;
+    };
+    let run = executables(events);
+    if let Err(e) = PrevalenceStore::update(path, |store| store.record_run(&run)) {
+        warn!("Failed to update the prevalence store: {e:#}");
+    }
+}
+
+impl PrevalenceStore {
+    /// Store saved in a file, empty when the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        let store: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Malformed prevalence store {}", path.display()))?;
+        if store.version != PREVALENCE_VERSION {
+            bail!(
+                "Unsupported prevalence store version {} in {} (expected {PREVALENCE_VERSION})",
+                store.version,
+                path.display()
+            );
+        }
+        Ok(store)
+    }
+
+    /// Write the store to a new file next to it and move it in place, so an interrupted run
+    /// leaves the previous store intact
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut temp = tempfile::NamedTempFile::new_in(dir)
+            .with_context(|| format!("Failed to create a file in {}", dir.display()))?;
+        temp.write_all(serde_json::to_string(self)?.as_bytes())
+            .with_context(|| format!("Failed to write {}", temp.path().display()))?;
+        temp.persist(path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Load the store, change it and save it, holding a lock on `<file>.lock` all along so that
+    /// runs ending at the same time do not overwrite each other's changes
+    pub fn update(path: &Path, change: impl FnOnce(&mut Self)) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock = File::create(&lock_path)
+            .with_context(|| format!("Failed to create {}", Path::new(&lock_path).display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", Path::new(&lock_path).display()))?;
+        let mut store = Self::load(path)?;
+        change(&mut store);
+        store.save(path)?;
+        Ok(store)
+    }
+
+    /// How rare the executable seen in this run is, `None` if it is common or the store has too
+    /// few runs to tell
+    pub fn rarity(&self, key: &str, run: &Sightings) -> Option<Rarity> {
+        if self.runs < MIN_BASELINE_RUNS {
+            return None;
+        }
+        let Some(past) = self.executables.get(key) else {
+            return Some(Rarity::FirstSeen);
+        };
+        let single_host = past.hosts.union(&run.hosts).nth(1).is_none();
+        (past.runs <= RARE_RUNS && single_host).then_some(Rarity::Rare { runs: past.runs })
+    }
+
+    /// Domains never queried in earlier runs, `None` while the store has too few runs to tell
+    pub fn new_domains<'a>(&self, domains: impl Iterator<Item = &'a str>) -> Option<Vec<&'a str>> {
+        (self.domain_runs >= MIN_BASELINE_RUNS).then(|| {
+            domains
+                .filter(|domain| !self.domains.contains_key(*domain))
+                .collect()
+        })
+    }
+
+    /// Count a `stats --dns` run and the domains it saw
+    pub fn record_domains<'a>(&mut self, domains: impl Iterator<Item = &'a str>) {
+        self.domain_runs += 1;
+        for domain in domains {
+            *self.domains.entry(domain.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Count a run and the executables it saw
+    pub fn record_run(&mut self, run: &BTreeMap<String, Sightings>) {
+        self.runs += 1;
+        for (key, sightings) in run {
+            let entry = self
+                .executables
+                .entry(key.clone())
+                .or_insert_with(|| Sightings::new(&sightings.image));
+            entry.runs += 1;
+            for host in &sightings.hosts {
+                entry.add_host(host);
+            }
+        }
+    }
+}
+
+/// How rare an executable is across runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rarity {
+    FirstSeen,
+    /// Seen in this many earlier runs, on the same host
+    Rare {
+        runs: u32,
+    },
+}
+
+impl Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rarity::FirstSeen => f.write_str("first seen"),
+            Rarity::Rare { runs: 1 } => f.write_str("seen in 1 earlier run"),
+            Rarity::Rare { runs } => write!(f, "seen in {runs} earlier runs"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{AnomalyKind, DetectionConfig, detect_anomalies_with_config};
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    fn process(computer: &str, image: &str, sha256: &str) -> crate::sysmon::Event {
+        json_process_event(json!({
+            "Hostname": computer,
+            "Image": image,
+            "CommandLine": image,
+            "User": format!(r"{computer}\Bob"),
+            "Hashes": format!("SHA256={sha256}"),
+            "ParentImage": r"C:\Windows\explorer.exe",
+            "ParentCommandLine": "explorer.exe",
+        }))
+    }
+
+    /// Findings of a run, recorded in the store afterwards as the commands do
+    fn rare(events: &[crate::sysmon::Event], config: &DetectionConfig) -> Vec<(String, Rarity)> {
+        let anomalies = detect_anomalies_with_config(events, config);
+        record_run(config.prevalence.as_deref(), events);
+        anomalies
+            .into_iter()
+            .filter_map(|anomaly| match anomaly.into_kind() {
+                AnomalyKind::RareExecutable { image, rarity, .. } => Some((image, rarity)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_seen_and_rare_across_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prevalence.json");
+        let config = DetectionConfig {
+            prevalence: Some(path.clone()),
+            ..DetectionConfig::default()
+        };
+        let word = r"C:\Program Files\Microsoft Office\WINWORD.EXE";
+        let common = [
+            process("WS1", word, "AA"),
+            process("WS2", word, "AA"),
+            process("WS1", r"C:\Tools\report.exe", "BB"),
+        ];
+        // Nothing is reported while the baseline builds up
+        for _ in 0..MIN_BASELINE_RUNS {
+            assert!(rare(&common, &config).is_empty());
+        }
+        let store = PrevalenceStore::load(&path).unwrap();
+        assert_eq!(store.runs, MIN_BASELINE_RUNS);
+        // Detection alone leaves the store as it is
+        detect_anomalies_with_config(&common, &config);
+        assert_eq!(PrevalenceStore::load(&path).unwrap(), store);
+
+        let dropper = r"C:\Users\Bob\AppData\Local\Temp\upd.exe";
+        let run = [
+            process("WS1", word, "AA"),
+            process("WS1", dropper, "CC"),
+            // Renamed copy of a known binary
+            process("WS2", r"C:\Temp\r.exe", "bb"),
+        ];
+        assert_eq!(
+            rare(&run, &config),
+            [(dropper.to_string(), Rarity::FirstSeen)]
+        );
+        assert_eq!(
+            rare(&run, &config),
+            [(dropper.to_string(), Rarity::Rare { runs: 1 })]
+        );
+        rare(&run, &config);
+        // Seen in three earlier runs now
+        assert!(rare(&run, &config).is_empty());
+        assert_eq!(
+            PrevalenceStore::load(&path).unwrap().runs,
+            MIN_BASELINE_RUNS + 4
+        );
+
+        std::fs::write(&path, r#"{"version":99,"runs":0,"executables":{}}"#).unwrap();
+        assert!(PrevalenceStore::load(&path).is_err());
+    }
+}
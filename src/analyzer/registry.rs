@@ -0,0 +1,255 @@
+//! Registry values whose content is suspicious (SetValue, 13): encoded blobs in autostart keys,
+//! script engines registered as COM servers and security features switched off
+use crate::sysmon::{RegistryEventData, RegistryValue};
+use std::fmt::Display;
+
+/// Autostart keys (lowercase) run at logon, including their 32-bit views
+const RUN_KEYS: &[&str] = &[
+    r"\currentversion\run\",
+    r"\currentversion\runonce\",
+    r"\currentversion\runonceex\",
+    r"\currentversion\policies\explorer\run\",
+];
+/// Base64 or hex tokens of this length and longer are considered encoded payloads
+const MIN_BLOB_LENGTH: usize = 40;
+/// Subkeys of a CLSID naming the code that serves the class
+const COM_SERVER_KEYS: &[&str] = &["inprocserver32", "localserver32", "scriptleturl"];
+/// Script hosts and engines a COM server should not point to
+const SCRIPT_ENGINES: &[&str] = &[
+    "scrobj.dll",
+    "jscript.dll",
+    "jscript9.dll",
+    "vbscript.dll",
+    "mshta.exe",
+    "wscript.exe",
+    "cscript.exe",
+    "powershell.exe",
+    "pwsh.exe",
+];
+
+/// Security feature turned off by writing a DWORD
+#[derive(Debug, PartialEq, Eq)]
+pub struct SecuritySetting {
+    /// Value name as Windows spells it
+    pub name: &'static str,
+    /// End of the value path, lowercase
+    key: &'static str,
+    /// Data that turns the feature off
+    disabled: u32,
+    pub techniques: &'static [&'static str],
+}
+
+const SECURITY_SETTINGS: &[SecuritySetting] = &[
+    SecuritySetting {
+        name: "DisableAntiSpyware",
+        key: r"\windows defender\disableantispyware",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "DisableAntiVirus",
+        key: r"\windows defender\disableantivirus",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "DisableRealtimeMonitoring",
+        key: r"\real-time protection\disablerealtimemonitoring",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "DisableBehaviorMonitoring",
+        key: r"\real-time protection\disablebehaviormonitoring",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "DisableIOAVProtection",
+        key: r"\real-time protection\disableioavprotection",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "DisableScriptScanning",
+        key: r"\real-time protection\disablescriptscanning",
+        disabled: 1,
+        techniques: &["T1562.001"],
+    },
+    // Start type 4 disables the Defender service
+    SecuritySetting {
+        name: "WinDefend Start",
+        key: r"\services\windefend\start",
+        disabled: 4,
+        techniques: &["T1562.001"],
+    },
+    SecuritySetting {
+        name: "EnableLUA",
+        key: r"\currentversion\policies\system\enablelua",
+        disabled: 0,
+        techniques: &["T1548.002"],
+    },
+    SecuritySetting {
+        name: "ConsentPromptBehaviorAdmin",
+        key: r"\currentversion\policies\system\consentpromptbehavioradmin",
+        disabled: 0,
+        techniques: &["T1548.002"],
+    },
+    // Domain, private (standard) and public profiles, local and by policy
+    SecuritySetting {
+        name: "EnableFirewall",
+        key: r"profile\enablefirewall",
+        disabled: 0,
+        techniques: &["T1562.004"],
+    },
+];
+
+/// What makes a registry value suspicious
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryFinding {
+    /// Base64 or hex blob in an autostart value, a payload rather than a command
+    EncodedRunValue,
+    /// COM server of a class pointing at a script engine, as in scriptlet hijacks
+    ScriptComServer(&'static str),
+    SecurityDisabled(&'static SecuritySetting),
+}
+
+impl Display for RegistryFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryFinding::EncodedRunValue => f.write_str("encoded data in autostart value"),
+            RegistryFinding::ScriptComServer(engine) => write!(f, "COM server runs {engine}"),
+            RegistryFinding::SecurityDisabled(setting) => {
+                write!(f, "{} set to {}", setting.name, setting.disabled)
+            }
+        }
+    }
+}
+
+/// What is suspicious about the value written, if anything
+pub fn inspect(data: &RegistryEventData) -> Option<RegistryFinding> {
+    let key = data.target_object.to_lowercase();
+    match data.value() {
+        RegistryValue::Dword(value) => SECURITY_SETTINGS
+            .iter()
+            .find(|setting| key.ends_with(setting.key) && value == setting.disabled)
+            .map(RegistryFinding::SecurityDisabled),
+        RegistryValue::String(value) => {
+            if RUN_KEYS.iter().any(|run| key.contains(run)) && has_encoded_blob(&value) {
+                return Some(RegistryFinding::EncodedRunValue);
+            }
+            let (_, class) = key.split_once(r"\clsid\")?;
+            let server = class.split('\\').nth(1)?;
+            if !COM_SERVER_KEYS.contains(&server) {
+                return None;
+            }
+            if server == "scriptleturl" {
+                return Some(RegistryFinding::ScriptComServer("scrobj.dll"));
+            }
+            let value = value.to_lowercase();
+            SCRIPT_ENGINES
+                .iter()
+                .find(|engine| value.contains(*engine))
+                .map(|engine| RegistryFinding::ScriptComServer(engine))
+        }
+        RegistryValue::Qword(_) | RegistryValue::Binary | RegistryValue::Empty => None,
+    }
+}
+
+/// Whether the string holds a long base64 token (mixing cases and digits) or hex token
+fn has_encoded_blob(value: &str) -> bool {
+    value
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=')))
+        .filter(|token| token.len() >= MIN_BLOB_LENGTH)
+        .any(|token| {
+            let unpadded = token.trim_end_matches('=');
+            let is_hex = token.len() % 2 == 0 && token.chars().all(|c| c.is_ascii_hexdigit());
+            let is_base64 = token.len() % 4 == 0
+                && token.len() - unpadded.len() <= 2
+                && !unpadded.contains('=')
+                && unpadded.chars().any(|c| c.is_ascii_uppercase())
+                && unpadded.chars().any(|c| c.is_ascii_lowercase())
+                && unpadded.chars().any(|c| c.is_ascii_digit());
+            is_hex || is_base64
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonl;
+    use crate::sysmon::Event as SysmonEvent;
+
+    fn set_value(target_object: &str, details: &str) -> Option<RegistryFinding> {
+        let line = serde_json::json!({
+            "EventTime": "2025-01-01 10:00:00",
+            "Hostname": "WS1",
+            "EventID": 13,
+            "RecordNumber": 17,
+            "EventType": "SetValue",
+            "UtcTime": "2025-01-01 10:00:00.000",
+            "ProcessGuid": "{11111111-2222-3333-4444-555555555555}",
+            "ProcessId": "42",
+            "Image": r"C:\Windows\System32\reg.exe",
+            "TargetObject": target_object,
+            "Details": details,
+        });
+        let SysmonEvent::RegistrySetValue(event) =
+            jsonl::parse_json_event(&line.to_string()).unwrap()
+        else {
+            panic!("not a registry event");
+        };
+        inspect(&event.event_data)
+    }
+
+    #[test]
+    fn suspicious_value_contents() {
+        let run = r"HKU\S-1-5-21-1-2-3-1001\SOFTWARE\Microsoft\Windows\CurrentVersion\Run\Updater";
+        assert_eq!(
+            set_value(
+                run,
+                "powershell.exe -w hidden -enc SQBFAFgAIAAoAE4AZQB3AC0ATwBiAGoAZQBjAHQAIABOAGUAdAAuAFcAZQBiAA=="
+            ),
+            Some(RegistryFinding::EncodedRunValue)
+        );
+        assert_eq!(
+            set_value(
+                run,
+                "rundll32.exe 4d5a90000300000004000000ffff0000b800000000000000,Start"
+            ),
+            Some(RegistryFinding::EncodedRunValue)
+        );
+        assert_eq!(
+            set_value(
+                run,
+                r#""C:\Users\Bob\AppData\Local\Microsoft\OneDrive\OneDrive.exe" /background"#
+            ),
+            None
+        );
+        assert_eq!(
+            set_value(
+                r"HKU\S-1-5-21-1-2-3-1001_Classes\CLSID\{0A29FF9E-7F9C-4437-8B11-F424491E3931}\InprocServer32\(Default)",
+                r"C:\Windows\System32\scrobj.dll"
+            ),
+            Some(RegistryFinding::ScriptComServer("scrobj.dll"))
+        );
+        assert_eq!(
+            set_value(
+                r"HKLM\SOFTWARE\Classes\CLSID\{0A29FF9E-7F9C-4437-8B11-F424491E3931}\InprocServer32\(Default)",
+                r"C:\Program Files\Vendor\shellext.dll"
+            ),
+            None
+        );
+        let defender = r"HKLM\SOFTWARE\Policies\Microsoft\Windows Defender\DisableAntiSpyware";
+        let Some(RegistryFinding::SecurityDisabled(setting)) =
+            set_value(defender, "DWORD (0x00000001)")
+        else {
+            panic!("DisableAntiSpyware not reported");
+        };
+        assert_eq!(setting.name, "DisableAntiSpyware");
+        assert_eq!(set_value(defender, "DWORD (0x00000000)"), None);
+        let uac = r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\System\EnableLUA";
+        assert!(set_value(uac, "DWORD (0x00000000)").is_some());
+        assert_eq!(set_value(uac, "DWORD (0x00000001)"), None);
+    }
+}
@@ -0,0 +1,98 @@
+//! Evaluation time and hit counts of the detection rules, to find slow or noisy rules
+use super::Anomaly;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Cost and findings of one rule over a detection run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    /// Times the rule looked at an event, or at the whole log for aggregate rules
+    pub evaluations: u64,
+    pub time: Duration,
+    /// Findings reported by the rule
+    pub hits: usize,
+    /// Findings dropped by suppressions
+    pub suppressed: usize,
+}
+
+/// Statistics of every rule evaluated during a detection run, by rule name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleProfile {
+    rules: BTreeMap<&'static str, RuleStats>,
+}
+
+impl RuleProfile {
+    pub(super) fn record(&mut self, rule: &'static str, elapsed: Duration) {
+        let stats = self.rules.entry(rule).or_default();
+        stats.evaluations += 1;
+        stats.time += elapsed;
+    }
+
+    pub(super) fn count_finding(&mut self, anomaly: &Anomaly, suppressed: bool) {
+        let stats = self.rules.entry(anomaly.rule_name()).or_default();
+        if suppressed {
+            stats.suppressed += 1;
+        } else {
+            stats.hits += 1;
+        }
+    }
+
+    pub fn get(&self, rule: &str) -> Option<&RuleStats> {
+        self.rules.get(rule)
+    }
+
+    /// Rules ordered by total evaluation time, slowest first
+    pub fn by_time(&self) -> Vec<(&'static str, RuleStats)> {
+        let mut rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|(rule, stats)| (*rule, *stats))
+            .collect();
+        rules.sort_by(|(a, a_stats), (b, b_stats)| b_stats.time.cmp(&a_stats.time).then(a.cmp(b)));
+        rules
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.rules.values().map(|stats| stats.time).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::suppression::Suppression;
+    use crate::analyzer::{DetectionConfig, detect_anomalies_profiled};
+    use crate::fixtures::json_event;
+    use serde_json::json;
+
+    #[test]
+    fn rules_are_timed_and_hits_counted() {
+        let tampering = |record: u32| {
+            json_event(
+                25,
+                json!({
+                    "EventTime": format!("2025-01-01 10:00:0{record}"),
+                    "RecordNumber": record,
+                    "Type": "Image is replaced",
+                }),
+            )
+        };
+        let events = [tampering(1), tampering(2)];
+        let (anomalies, profile) = detect_anomalies_profiled(&events, &DetectionConfig::default());
+        assert_eq!(anomalies.len(), 2);
+        let stats = profile.get("ProcessTampering").unwrap();
+        assert_eq!((stats.evaluations, stats.hits, stats.suppressed), (2, 2, 0));
+        // Evaluated for every event even without findings
+        assert_eq!(profile.get("IocMatch").unwrap().evaluations, 2);
+        assert!(profile.get("SuspiciousPipe").is_none());
+        assert_eq!(profile.by_time().len(), 8);
+
+        let config = DetectionConfig {
+            suppressions: vec![Suppression::rule("SLA-017").unwrap()],
+            ..DetectionConfig::default()
+        };
+        let (anomalies, profile) = detect_anomalies_profiled(&events, &config);
+        assert!(anomalies.is_empty());
+        let stats = profile.get("ProcessTampering").unwrap();
+        assert_eq!((stats.hits, stats.suppressed), (0, 2));
+    }
+}
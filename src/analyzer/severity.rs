@@ -0,0 +1,98 @@
+//! Severities set per rule by the detection config, e.g. to downgrade a noisy rule or upgrade
+//! one that matters more on servers
+use super::detector::DetectorRegistry;
+use super::{AnomalyKind, Severity, rule_name_of};
+use anyhow::{Result, bail};
+
+/// Severity of the findings of rules, given by ID or name (case-insensitive)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeverityOverrides(Vec<(String, Severity)>);
+
+impl SeverityOverrides {
+    pub fn new(overrides: impl IntoIterator<Item = (String, Severity)>) -> Self {
+        Self(overrides.into_iter().collect())
+    }
+
+    /// Severity set for the rule of the anomaly, `None` to keep its own
+    pub fn get(&self, anomaly: &AnomalyKind) -> Option<Severity> {
+        self.0
+            .iter()
+            .find(|(rule, _)| {
+                rule.eq_ignore_ascii_case(anomaly.rule_id())
+                    || rule.eq_ignore_ascii_case(anomaly.rule_name())
+            })
+            .map(|(_, severity)| *severity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Fail on a rule that is neither built in nor reported by one of the detectors
+    pub fn validate(&self, detectors: &DetectorRegistry) -> Result<()> {
+        for (rule, _) in &self.0 {
+            if rule_name_of(rule).is_none() && !detectors.has_rule(rule) {
+                bail!("Unknown rule '{rule}' in severity overrides");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::config_file::ConfigFile;
+    use crate::analyzer::{DetectionConfig, detect_anomalies, detect_anomalies_with_config};
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn overrides_and_minimum_severity() {
+        let event = json_process_event(json!({}));
+        let anomalies = detect_anomalies(std::slice::from_ref(&event));
+        let anomaly = &anomalies[0];
+        assert_eq!(anomaly.rule_id(), "SLA-002");
+        assert_eq!(anomaly.default_severity(), Severity::High);
+
+        let file = ConfigFile::parse(
+            "severity_overrides:\n  suspiciousparentchild: low\n  SLA-003: critical\n",
+            false,
+        )
+        .unwrap();
+        let overrides = SeverityOverrides::new(file.severity_overrides);
+        assert_eq!(overrides.get(anomaly), Some(Severity::Low));
+        let overrides = SeverityOverrides::new([("SLA-003".to_string(), Severity::Critical)]);
+        assert_eq!(overrides.get(anomaly), None);
+        let file = ConfigFile::parse("severity_overrides:\n  NoSuchRule: low\n", false).unwrap();
+        let config = DetectionConfig {
+            severity_overrides: SeverityOverrides::new(file.severity_overrides),
+            ..DetectionConfig::default()
+        };
+        assert!(config.validate().is_err());
+        let config = DetectionConfig {
+            severity_overrides: overrides,
+            ..DetectionConfig::default()
+        };
+        config.validate().unwrap();
+        let anomalies = detect_anomalies_with_config(std::slice::from_ref(&event), &config);
+        assert_eq!(anomalies[0].severity(), Severity::High);
+        let config = DetectionConfig {
+            severity_overrides: SeverityOverrides::new([("SLA-002".to_string(), Severity::Low)]),
+            ..DetectionConfig::default()
+        };
+        let anomalies = detect_anomalies_with_config(&[event], &config);
+        assert_eq!(anomalies[0].severity(), Severity::Low);
+
+        let config = DetectionConfig {
+            min_severity: Some(Severity::Critical),
+            ..DetectionConfig::default()
+        };
+        assert!(config.is_suppressed(anomaly));
+        let config = DetectionConfig {
+            min_severity: Some(Severity::High),
+            ..DetectionConfig::default()
+        };
+        assert!(!config.is_suppressed(anomaly));
+    }
+}
@@ -0,0 +1,57 @@
+//! Consistency of the parent recorded in process creations, which PPID spoofing
+//! (`PROC_THREAD_ATTRIBUTE_PARENT_PROCESS`) or forged events break
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+use uuid::Uuid;
+
+/// Parent of a process creation that cannot be its real creator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpoofingReason {
+    /// The parent started after the child
+    ParentCreatedAfterChild,
+    /// The parent GUID belongs to a process with another PID than the child's parent PID
+    ParentPidMismatch,
+    /// The parent started while the log was recording, yet no event of it was logged
+    ParentNotInLog,
+}
+
+impl Display for SpoofingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SpoofingReason::ParentCreatedAfterChild => "parent created after the child",
+            SpoofingReason::ParentPidMismatch => "parent GUID does not match the parent PID",
+            SpoofingReason::ParentNotInLog => "parent GUID never seen in the log",
+        })
+    }
+}
+
+/// Creation time Sysmon encodes in a process GUID, in whole seconds: the first group identifies
+/// the machine, the second and third are the low and high words of the Unix time
+/// (`{MMMMMMMM-LLLL-HHHH-...}`). The time is only trusted when `parent` has the machine part of
+/// `child`, as GUIDs of other sources (converted logs, test data) follow no such layout.
+pub fn parent_creation_time(child: &Uuid, parent: &Uuid) -> Option<DateTime<Utc>> {
+    let (machine, low, high, _) = parent.as_fields();
+    if parent.is_nil() || machine != child.as_fields().0 {
+        return None;
+    }
+    DateTime::from_timestamp((u32::from(high) << 16 | u32::from(low)).into(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creation_time_of_guid() {
+        // cmd.exe started by msxsl.exe, started at 2019-05-23 17:26:08.716
+        let child = Uuid::parse_str("{365abb72-d7b1-5ce6-0000-0010b1c86d00}").unwrap();
+        let parent = Uuid::parse_str("{365abb72-d7b0-5ce6-0000-001077c56d00}").unwrap();
+        assert_eq!(
+            parent_creation_time(&child, &parent).map(|time| time.to_rfc3339()),
+            Some("2019-05-23T17:26:08+00:00".to_string())
+        );
+        let other = Uuid::parse_str("{11111111-2222-3333-4444-555555555555}").unwrap();
+        assert_eq!(parent_creation_time(&other, &parent), None);
+        assert_eq!(parent_creation_time(&child, &Uuid::nil()), None);
+    }
+}
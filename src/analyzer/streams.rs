@@ -0,0 +1,131 @@
+//! Alternate data streams (event 15): the Mark-of-the-Web of downloads and executable content
+//! hidden in streams
+use crate::sysmon::FileStreamEventData;
+use std::fmt::Display;
+
+/// Stream in which browsers and mail clients record where a file came from
+pub const ZONE_IDENTIFIER: &str = "Zone.Identifier";
+
+/// Stream name extensions of content Windows runs directly
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "bat", "cmd", "com", "cpl", "dll", "exe", "hta", "js", "jse", "msi", "ps1", "scr", "vbe",
+    "vbs", "wsf",
+];
+
+/// URL security zone of a Mark-of-the-Web
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Zone {
+    LocalMachine,
+    Intranet,
+    Trusted,
+    Internet,
+    Untrusted,
+}
+
+impl Zone {
+    fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Zone::LocalMachine,
+            1 => Zone::Intranet,
+            2 => Zone::Trusted,
+            3 => Zone::Internet,
+            4 => Zone::Untrusted,
+            _ => return None,
+        })
+    }
+
+    /// The file came from outside the organization
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Zone::Internet | Zone::Untrusted)
+    }
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Zone::LocalMachine => "Local Machine",
+            Zone::Intranet => "Intranet",
+            Zone::Trusted => "Trusted",
+            Zone::Internet => "Internet",
+            Zone::Untrusted => "Untrusted",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Origin of a file recorded in its `Zone.Identifier` stream
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MarkOfTheWeb {
+    pub zone: Zone,
+    /// URL the file was downloaded from, when the browser recorded it
+    pub host_url: Option<String>,
+}
+
+/// Parse the contents of a `Zone.Identifier` stream. Sysmon logs its lines separated by spaces
+/// or line breaks, e.g. `[ZoneTransfer]  ZoneId=3  HostUrl=https://example.com/a.exe`.
+pub fn parse_zone_identifier(contents: &str) -> Option<MarkOfTheWeb> {
+    let value = |key: &str| {
+        contents
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix(key)?.strip_prefix('='))
+    };
+    let zone = Zone::from_id(value("ZoneId")?.parse().ok()?)?;
+    Some(MarkOfTheWeb {
+        zone,
+        host_url: value("HostUrl").map(str::to_string),
+    })
+}
+
+/// File downloaded from a remote zone and its origin, for a `Zone.Identifier` stream event
+pub fn downloaded_file(data: &FileStreamEventData) -> Option<(&str, MarkOfTheWeb)> {
+    let (file, stream) = data.stream()?;
+    if !stream.eq_ignore_ascii_case(ZONE_IDENTIFIER) {
+        return None;
+    }
+    let mark = parse_zone_identifier(data.contents.as_deref()?)?;
+    mark.zone.is_remote().then_some((file, mark))
+}
+
+/// Why the stream holds executable content, `None` for ordinary streams
+pub fn executable_stream(data: &FileStreamEventData) -> Option<&'static str> {
+    let (_, stream) = data.stream()?;
+    if stream.eq_ignore_ascii_case(ZONE_IDENTIFIER) {
+        return None;
+    }
+    // Sysmon logs an import hash of zeros for content that is not a PE image
+    let imphash = data.hash.as_ref().and_then(|hash| {
+        hash.hashes
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("imphash"))
+            .map(|(_, value)| value.trim())
+    });
+    if imphash.is_some_and(|imphash| !imphash.is_empty() && imphash.bytes().any(|b| b != b'0')) {
+        return Some("PE image");
+    }
+    let extension = stream.rsplit_once('.')?.1.to_ascii_lowercase();
+    EXECUTABLE_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some("executable extension")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_identifier_contents() {
+        let mark = parse_zone_identifier(
+            "[ZoneTransfer]\r\nZoneId=3\r\nReferrerUrl=https://example.com/\r\nHostUrl=https://example.com/a.exe\r\n",
+        )
+        .unwrap();
+        assert_eq!(mark.zone, Zone::Internet);
+        assert_eq!(mark.host_url.as_deref(), Some("https://example.com/a.exe"));
+        assert_eq!(
+            parse_zone_identifier("[ZoneTransfer]  ZoneId=1").map(|mark| mark.zone),
+            Some(Zone::Intranet)
+        );
+        assert!(parse_zone_identifier("[ZoneTransfer]  ZoneId=9").is_none());
+        assert!(parse_zone_identifier("-").is_none());
+    }
+}
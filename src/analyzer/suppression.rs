@@ -0,0 +1,101 @@
+//! Suppression of known false positives, by rule and optionally by process, user or computer
+use super::{AnomalyKind, rule_name_of};
+use crate::filters::EventFilter;
+use crate::helpers::HasSystem;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// Findings of one rule to drop; all conditions that are set must match the triggering event
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    /// Rule ID (e.g. SLA-003) or name
+    pub rule: String,
+    /// Process image, full path or file name (case-insensitive)
+    pub image: Option<String>,
+    /// Account, `DOMAIN\user` or just `user` (case-insensitive)
+    pub user: Option<String>,
+    /// Computer the event was logged on (case-insensitive)
+    pub computer: Option<String>,
+}
+
+impl Suppression {
+    /// Suppress every finding of the rule, given by ID or name
+    pub fn rule(rule: &str) -> Result<Self> {
+        let suppression = Self {
+            rule: rule.to_string(),
+            image: None,
+            user: None,
+            computer: None,
+        };
+        suppression.validate()?;
+        Ok(suppression)
+    }
+
+    pub(super) fn validate(&self) -> Result<()> {
+        rule_name_of(&self.rule)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("Unknown rule '{}' in suppression", self.rule))
+    }
+
+    pub fn matches(&self, anomaly: &AnomalyKind) -> bool {
+        if rule_name_of(&self.rule) != Some(anomaly.rule_name()) {
+            return false;
+        }
+        if self.image.is_none() && self.user.is_none() && self.computer.is_none() {
+            return true;
+        }
+        // Scoped suppressions need the event to check their conditions against
+        let Some(event) = anomaly.event() else {
+            return false;
+        };
+        if let Some(computer) = &self.computer
+            && !event
+                .system()
+                .computer
+                .computer
+                .eq_ignore_ascii_case(computer)
+        {
+            return false;
+        }
+        EventFilter::new()
+            .with_image(self.image.clone())
+            .with_user(self.user.clone())
+            .matches(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::config_file::ConfigFile;
+
+    #[test]
+    fn config_file_suppressions() {
+        let yaml =
+            "suppressions:\n  - rule: SLA-003\n    image: backupagent.exe\n  - rule: eventstorm\n";
+        let suppressions = ConfigFile::parse(yaml, false).unwrap().suppressions;
+        assert_eq!(suppressions.len(), 2);
+        assert_eq!(suppressions[0].image.as_deref(), Some("backupagent.exe"));
+
+        let storm = AnomalyKind::EventStorm {
+            event_id: 1,
+            count: 100,
+            time_window_seconds: 5,
+            start: None,
+            end: None,
+            top_processes: Vec::new(),
+        };
+        assert!(suppressions[1].matches(&storm));
+        assert!(!suppressions[0].matches(&storm));
+
+        assert!(ConfigFile::parse("suppressions:\n  - rule: SLA-999\n", false).is_err());
+        assert!(
+            ConfigFile::parse(
+                r#"{"suppressions": [{"rule": "SLA-001", "path": "x"}]}"#,
+                true
+            )
+            .is_err()
+        );
+    }
+}
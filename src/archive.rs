@@ -0,0 +1,306 @@
+//! Compressed inputs: gzip files (`.evtx.gz`, `.jsonl.gz`) and ZIP archives of logs, as triage
+//! packages usually arrive. They are extracted to a temporary directory removed once parsed.
+use crate::parser::InputFormat;
+use anyhow::{Context, Result, bail};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tracing::{info, warn};
+use zip::ZipArchive;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZIP_EMPTY_MAGIC: &[u8] = b"PK\x05\x06";
+/// Total size of the files extracted from the inputs of one run, so that a decompression bomb
+/// fails instead of filling the disk
+pub const MAX_EXTRACTED_BYTES: u64 = 64 << 30;
+
+/// Input files ready to parse, compressed ones replaced by their extracted contents
+pub struct ExpandedInputs {
+    pub paths: Vec<PathBuf>,
+    /// Holds the extracted files until the inputs are dropped
+    _dir: Option<TempDir>,
+}
+
+/// Kind of compression of a file, from its first bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zip,
+}
+
+fn detect(path: &Path) -> Result<Compression> {
+    let mut magic = [0u8; 4];
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open: {}", path.display()))?;
+    let read = file.read(&mut magic)?;
+    let magic = &magic[..read];
+    Ok(if magic.starts_with(GZIP_MAGIC) {
+        Compression::Gzip
+    } else if magic.starts_with(ZIP_MAGIC) || magic.starts_with(ZIP_EMPTY_MAGIC) {
+        Compression::Zip
+    } else {
+        Compression::None
+    })
+}
+
+/// Whether the file is a gzip file or a ZIP archive
+pub fn is_compressed(path: &Path) -> Result<bool> {
+    Ok(detect(path)? != Compression::None)
+}
+
+/// Log files of the format, by extension once a `.gz` suffix is removed
+pub(crate) fn is_log_file(name: &str, format: InputFormat) -> bool {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    match format {
+        InputFormat::Evtx => name.ends_with(".evtx"),
+        InputFormat::Jsonl => [".json", ".jsonl", ".ndjson"]
+            .iter()
+            .any(|extension| name.ends_with(extension)),
+        InputFormat::Csv => name.ends_with(".csv"),
+    }
+}
+
+/// Replace gzip files and ZIP archives by the log files of the format they contain. Other files
+/// are passed through, so plain inputs cost nothing but a look at their first bytes.
+pub fn expand_inputs(paths: &[PathBuf], format: InputFormat) -> Result<ExpandedInputs> {
+    expand_inputs_within(paths, format, MAX_EXTRACTED_BYTES)
+}
+
+/// [`expand_inputs`] extracting at most `limit` bytes in total
+fn expand_inputs_within(
+    paths: &[PathBuf],
+    format: InputFormat,
+    limit: u64,
+) -> Result<ExpandedInputs> {
+    let mut dir: Option<TempDir> = None;
+    let mut expanded = Vec::with_capacity(paths.len());
+    let mut budget = limit;
+    for (index, path) in paths.iter().enumerate() {
+        let compression = detect(path)?;
+        if compression == Compression::None {
+            expanded.push(path.clone());
+            continue;
+        }
+        if dir.is_none() {
+            dir = Some(
+                TempDir::with_prefix("sla-").context("Failed to create a temporary directory")?,
+            );
+        }
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let target = dir.as_ref().unwrap().path().join(format!("{index}-{name}"));
+        std::fs::create_dir(&target)?;
+        let extracted = match compression {
+            Compression::Gzip => {
+                let out = target.join(name.strip_suffix(".gz").unwrap_or(&name));
+                let file = File::open(path)?;
+                copy_to_file(MultiGzDecoder::new(BufReader::new(file)), &out, &mut budget)
+                    .with_context(|| format!("Failed to decompress {}", path.display()))?;
+                vec![out]
+            }
+            Compression::Zip => extract_zip(path, &target, format, &mut budget)
+                .with_context(|| format!("Failed to extract {}", path.display()))?,
+            Compression::None => unreachable!(),
+        };
+        if extracted.is_empty() {
+            warn!("No {format:?} logs found in {}", path.display());
+        } else {
+            info!(
+                "Extracted {} files from {}",
+                extracted.len(),
+                path.display()
+            );
+        }
+        expanded.extend(extracted);
+    }
+    Ok(ExpandedInputs {
+        paths: expanded,
+        _dir: dir,
+    })
+}
+
+/// Write the contents of `reader` to a new file, failing once they exceed the `budget` of bytes
+/// left, which is reduced by the bytes written
+fn copy_to_file(reader: impl Read, path: &Path, budget: &mut u64) -> Result<u64> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let written = io::copy(&mut reader.take(budget.saturating_add(1)), &mut out)?;
+    if written > *budget {
+        bail!("Extracted files exceed the size limit, extract the input first");
+    }
+    out.flush()?;
+    *budget -= written;
+    Ok(written)
+}
+
+/// Extract the log files of a ZIP archive into `target`, whatever folder they are in
+fn extract_zip(
+    path: &Path,
+    target: &Path,
+    format: InputFormat,
+    budget: &mut u64,
+) -> Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut extracted = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index)?;
+        let name = entry.name().to_string();
+        if entry.is_dir() || !is_log_file(&name, format) {
+            continue;
+        }
+        if entry.encrypted() {
+            warn!("Skipping encrypted {name} in {}", path.display());
+            continue;
+        }
+        drop(entry);
+        // Folders are flattened, the index keeps files of the same name apart
+        let base = name.rsplit(['/', '\\']).next().unwrap_or(&name);
+        let out = target.join(format!("{index}-{base}"));
+        // Reading the entry to its end checks its CRC
+        copy_to_file(archive.by_index(index)?, &out, budget)
+            .with_context(|| format!("Failed to extract {name}"))?;
+        let out = match base.to_ascii_lowercase().ends_with(".gz") {
+            true => {
+                let decompressed = out.with_extension("");
+                let compressed = File::open(&out)?;
+                // The compressed copy is removed once decompressed
+                *budget += compressed.metadata()?.len();
+                copy_to_file(
+                    MultiGzDecoder::new(BufReader::new(compressed)),
+                    &decompressed,
+                    budget,
+                )
+                .with_context(|| format!("Failed to decompress {name}"))?;
+                std::fs::remove_file(&out)?;
+                decompressed
+            }
+            false => out,
+        };
+        extracted.push(out);
+    }
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use flate2::write::GzEncoder;
+
+    fn event_line(record: u32) -> String {
+        format!(
+            r#"{{"EventTime":"2025-01-01 10:00:00","Hostname":"TEST-PC","EventID":22,"RecordNumber":{record},"UtcTime":"2025-01-01 10:00:00.000","ProcessGuid":"{{11111111-2222-3333-4444-555555555555}}","ProcessId":"42","QueryName":"example.com","QueryStatus":"0","QueryResults":"-","Image":"C:\\Windows\\System32\\svchost.exe"}}"#
+        )
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// ZIP archive of (name, contents, deflated) entries
+    fn zip(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, contents, deflated) in entries {
+            let method = match deflated {
+                true => zip::CompressionMethod::Deflated,
+                false => zip::CompressionMethod::Stored,
+            };
+            let options = zip::write::SimpleFileOptions::default().compression_method(method);
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(contents).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn compressed_inputs_are_extracted() {
+        let dir = TempDir::with_prefix("sla-archive-test-").unwrap();
+        let plain = dir.path().join("plain.jsonl");
+        std::fs::write(&plain, event_line(1)).unwrap();
+        let gz = dir.path().join("host1.jsonl.gz");
+        std::fs::write(&gz, gzip(event_line(2).as_bytes())).unwrap();
+        let lines = format!("{}\n{}\n", event_line(3), event_line(4));
+        let package = dir.path().join("triage.zip");
+        std::fs::write(
+            &package,
+            zip(&[
+                ("logs/", b"", false),
+                ("logs/host2.jsonl", lines.as_bytes(), true),
+                ("logs/host3/events.json", event_line(5).as_bytes(), false),
+                (
+                    "logs/host4.jsonl.gz",
+                    &gzip(event_line(6).as_bytes()),
+                    false,
+                ),
+                ("README.txt", b"collected by IR", true),
+            ]),
+        )
+        .unwrap();
+
+        let inputs = vec![plain.clone(), gz, package.clone()];
+        let expanded = expand_inputs(&inputs, InputFormat::Jsonl).unwrap();
+        assert_eq!(expanded.paths.len(), 5);
+        assert_eq!(expanded.paths[0], plain);
+        let names: Vec<String> = expanded.paths[1..]
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "host1.jsonl",
+                "1-host2.jsonl",
+                "2-events.json",
+                "3-host4.jsonl"
+            ]
+        );
+        let temporary = expanded.paths[1].clone();
+        drop(expanded);
+        assert!(!temporary.exists());
+
+        let report = parser::parse_files(&inputs, InputFormat::Jsonl, false).unwrap();
+        let mut records: Vec<u32> = report
+            .events
+            .iter()
+            .map(|event| {
+                crate::helpers::HasSystem::system(event)
+                    .event_record_id
+                    .event_record_id
+            })
+            .collect();
+        records.sort();
+        assert_eq!(records, [1, 2, 3, 4, 5, 6]);
+
+        let mut corrupted = std::fs::read(&package).unwrap();
+        let position = corrupted.windows(5).position(|w| w == b"Event").unwrap();
+        corrupted[position] = b'X';
+        std::fs::write(&package, corrupted).unwrap();
+        assert!(expand_inputs(&[package], InputFormat::Jsonl).is_err());
+
+        // Highly compressed contents fail once they exceed the limit
+        let bomb = dir.path().join("bomb.zip");
+        let zeros = vec![b'0'; 1 << 20];
+        std::fs::write(
+            &bomb,
+            zip(&[("a.jsonl", &zeros, true), ("b.jsonl", &zeros, true)]),
+        )
+        .unwrap();
+        assert!(std::fs::metadata(&bomb).unwrap().len() < 1 << 16);
+        assert!(
+            expand_inputs_within(std::slice::from_ref(&bomb), InputFormat::Jsonl, 3 << 19).is_err()
+        );
+        assert!(expand_inputs_within(&[bomb], InputFormat::Jsonl, 2 << 20).is_ok());
+    }
+}
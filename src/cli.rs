@@ -1,9 +1,67 @@
+use crate::analyzer::allowlist::Allowlist;
+use crate::analyzer::assets::AssetInventory;
+use crate::analyzer::config_file::ConfigFile;
+use crate::analyzer::dropped::DEFAULT_DROP_EXECUTE_WINDOW;
+use crate::analyzer::ioc::IocFeed;
+use crate::analyzer::ioc::Network;
+use crate::analyzer::known_good::KnownGoodHashes;
+use crate::analyzer::parent_child::ParentChildRule;
+use crate::analyzer::ports::PortProfile;
+use crate::analyzer::prevalence::PrevalenceStore;
+use crate::analyzer::severity::SeverityOverrides;
+use crate::analyzer::suppression::Suppression;
+use crate::analyzer::{
+    DEFAULT_EVENT_STORM_THRESHOLD, DEFAULT_EVENT_STORM_WINDOW, DetectionConfig, Severity,
+};
+#[cfg(windows)]
+use crate::commands::collect::execute_collect;
+use crate::commands::completions::execute_completions;
+use crate::commands::daemon::execute_daemon;
+use crate::commands::diff::execute_diff;
+use crate::commands::export::execute_export;
+use crate::commands::index::execute_index;
+use crate::commands::manpage::execute_manpage;
+use crate::commands::netsummary::execute_netsummary;
 use crate::commands::parse::execute_parse;
+use crate::commands::profile::execute_profile;
+use crate::commands::rules::execute_rules;
+use crate::commands::serve::execute_serve;
+use crate::commands::sessions::execute_sessions;
+use crate::commands::show::execute_show;
+use crate::commands::stats::execute_stats;
+use crate::commands::triage::execute_triage;
+use crate::commands::tui::execute_tui;
 #[cfg(windows)]
 use crate::commands::watch::execute_watch;
-use chrono::{DateTime, Utc};
-use clap::{Args, Parser, Subcommand};
+use crate::daemon::Schedule;
+use crate::display::{
+    ColorChoice, OutputFormat, TimeZoneChoice, parse_time_zone, set_color_choice, set_time_zone,
+};
+use crate::event_detail::EventSelector;
+use crate::export::{ExportFormat, ExportTarget};
+use crate::filters::network::{
+    Direction, NetworkFilter, PortRange, Protocol, parse_network, parse_port_range,
+};
+use crate::filters::path_glob::{PathGlob, parse_path_glob};
+use crate::filters::query::{Query, parse_query};
+use crate::filters::{
+    EventFilter, EventIdSpec, Integrity, SearchMode, parse_event_id_spec, parse_logon_id,
+    parse_timestamp,
+};
+#[cfg(windows)]
+use crate::forward::{ForwardTarget, parse_forward_target};
+use crate::netsummary::NetSortOrder;
+use crate::parser::InputFormat;
+use crate::profile::ProcessSelector;
+use crate::template::EventTemplate;
+use crate::triage::{TriageStatus, parse_finding_id};
+use chrono::{DateTime, Duration, Utc};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use clap_complete::Shell;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Parser)]
 #[command(name = "Sysmon Log Analyzer")]
@@ -13,6 +71,18 @@ use std::path::PathBuf;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Colorize output; `auto` disables colors when stdout is not a terminal or NO_COLOR is set
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Show timestamps in the local time zone instead of UTC
+    #[arg(long, global = true, conflicts_with = "tz")]
+    pub local_time: bool,
+
+    /// Show timestamps in this time zone: `utc`, `local` or an offset such as +02:00
+    #[arg(long, global = true, value_name = "ZONE", value_parser = parse_time_zone)]
+    pub tz: Option<TimeZoneChoice>,
 }
 
 #[derive(Subcommand)]
@@ -20,66 +90,1201 @@ pub enum Commands {
     /// Parse .evtx file
     Parse(ParseCommand),
 
+    /// Forward parsed events to Splunk HEC or Elasticsearch
+    Export(ExportCommand),
+
+    /// Inspect and test detection rules
+    Rules(RulesCommand),
+
+    /// Browse parsed events and anomalies interactively
+    Tui(TuiCommand),
+
+    /// Summarize everything a process did across the log
+    Profile(ProfileCommand),
+
+    /// Group events into logon sessions, or show the activity of one session
+    Sessions(SessionsCommand),
+
+    /// Compare two captures and report artifacts present in only one of them
+    Diff(DiffCommand),
+
+    /// Record triage verdicts of findings in a triage file, or list them
+    Triage(TriageCommand),
+
+    /// Print every field of a single event, untruncated
+    Show(ShowCommand),
+
+    /// Summarize outbound connections by process and destination
+    Netsummary(NetsummaryCommand),
+
+    /// Top-N reports: the busiest network talkers, or the DNS queries with --dns
+    Stats(StatsCommand),
+
+    /// Periodically analyze the log files dropped in a directory, writing a JSON report for each
+    Daemon(DaemonCommand),
+
+    /// Write a sidecar index of EVTX files, so that filtered parses only read matching chunks
+    Index(IndexCommand),
+
+    /// Serve an HTTP API to upload logs, run detection and fetch the reports
+    Serve(ServeCommand),
+
+    /// Print a shell completion script
+    Completions(CompletionsCommand),
+
+    /// Write man pages of the command and its subcommands
+    Manpage(ManpageCommand),
+
     /// Real-time monitoring of the live Sysmon channel (Windows only)
     #[cfg(windows)]
     Watch(WatchCommand),
+
+    /// Read events from the Sysmon channel of this or a remote computer (Windows only)
+    #[cfg(windows)]
+    Collect(CollectCommand),
 }
 
 #[derive(Args)]
 pub struct ParseCommand {
+    /// Path to .evtx (or --format jsonl/csv) files, records present in more than one file are reported once
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub file_paths: Vec<PathBuf>,
+
+    /// Input file format
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub format: InputFormat,
+
+    /// YAML or JSON column mapping of CSV or JSON Lines exports of other tools (EvtxECmd, Chainsaw, ...)
+    #[arg(long, value_name = "FILE", conflicts_with = "follow")]
+    pub mapping: Option<PathBuf>,
+
+    /// Don't show the parsing progress bar
+    #[arg(long, short)]
+    pub quiet: bool,
+
+    /// Keep reading records appended to the file (e.g. by a collector) until Ctrl+C
+    #[arg(long, conflicts_with_all = ["no_dedup", "profile_rules", "output", "fail_on", "graph", "export", "context"])]
+    pub follow: bool,
+
+    /// Read damaged or carved EVTX files chunk by chunk, skipping corrupted chunks and
+    /// reporting how many records were recovered and lost
+    #[arg(long, conflicts_with = "follow")]
+    pub recover: bool,
+
+    /// Write records that could be read but not parsed as Sysmon events to this file as XML
+    #[arg(long, value_name = "FILE", requires = "recover")]
+    pub dump_unparsed: Option<PathBuf>,
+
+    /// Parse only one record out of N (`1/N`) for a quick first pass over very noisy logs
+    #[arg(long, value_name = "1/N", value_parser = parse_sample_rate, conflicts_with_all = ["follow", "recover"])]
+    pub sample: Option<u32>,
+
+    /// Stop reading once N events were parsed
+    #[arg(long, value_name = "N", conflicts_with_all = ["follow", "recover"])]
+    pub max_events: Option<usize>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Include events after this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub after: Option<DateTime<Utc>>,
+
+    /// Include events before this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub before: Option<DateTime<Utc>>,
+
+    /// Enable anomaly detection
+    #[arg(long, short)]
+    pub detect: bool,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+
+    /// Report every anomaly instead of grouping identical findings
+    #[arg(long, requires = "detect")]
+    pub no_dedup: bool,
+
+    /// List findings on their own instead of grouping related ones into incidents
+    #[arg(long, requires = "detect")]
+    pub no_incidents: bool,
+
+    /// Anomalies of a host logged within this time of the first one belong to the same incident
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = crate::incident::DEFAULT_INCIDENT_WINDOW)]
+    pub incident_window: Duration,
+
+    /// Report the evaluation time and hit count of each detection rule
+    #[arg(long, requires = "detect")]
+    pub profile_rules: bool,
+
+    /// Output format, `json` prints only the anomaly report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Print each filtered event as one line shaped by this format string instead of the tables,
+    /// e.g. "{time} {event_id} {image} {cmdline}" (placeholders: time, event_id, event, computer,
+    /// record_id, pid, guid, image, process, cmdline, user, parent, details or any event data
+    /// field such as {TargetFilename})
+    #[arg(long, value_name = "FORMAT", value_parser = EventTemplate::parse, conflicts_with = "output")]
+    pub format_string: Option<EventTemplate>,
+
+    /// Exit with code 2 when an anomaly of at least this severity is detected
+    #[arg(long, value_enum, requires = "detect")]
+    pub fail_on: Option<Severity>,
+
+    /// Include the N events logged before and after each anomaly on the same computer in the JSON
+    /// report, with --output json
+    #[arg(long, value_name = "N", requires = "detect")]
+    pub context: Option<usize>,
+
+    /// Write the process tree as a Graphviz DOT file (anomalous processes are highlighted with --detect)
+    #[arg(long, value_name = "FILE")]
+    pub graph: Option<PathBuf>,
+
+    /// Export observables of detected anomalies as threat intel, the anomalies as a SARIF log, or
+    /// the events as a Timesketch timeline (requires --export-file, and --detect but for timesketch)
+    #[arg(long, value_enum, requires = "export_file")]
+    pub export: Option<ExportFormat>,
+
+    /// Destination of the export
+    #[arg(long, value_name = "FILE", requires = "export")]
+    pub export_file: Option<PathBuf>,
+
+    /// Annotate findings with their verdicts recorded by the `triage` command in this file
+    #[arg(long, value_name = "FILE", requires = "detect")]
+    pub triage: Option<PathBuf>,
+
+    /// Leave out findings triaged as false or true positives
+    #[arg(long, requires = "triage")]
+    pub hide_triaged: bool,
+
+    /// Read the whole files even when they have an up-to-date index
+    #[arg(long)]
+    pub no_index: bool,
+}
+
+#[derive(Args)]
+pub struct IndexCommand {
+    /// Path to .evtx files to index
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub file_paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportCommand {
     /// Path to .evtx file
     #[arg(value_name = "FILE")]
     pub file_path: PathBuf,
 
-    /// Display events whose Event ID is in the provided list (e.g. 1,2,7)
-    #[arg(long, value_delimiter = ',')]
-    pub event_id: Option<Vec<u8>>,
+    /// Destination type
+    #[arg(long, value_enum)]
+    pub target: ExportTarget,
 
-    /// Search by substring in key fields
+    /// Collector endpoint URL (e.g. https://splunk:8088/services/collector/event)
     #[arg(long)]
-    pub search: Option<String>,
+    pub url: String,
+
+    /// Splunk HEC token or Elasticsearch API key
+    #[arg(long, env = "SYSMON_EXPORT_TOKEN")]
+    pub token: Option<String>,
 
-    /// Include events after this time (format: YYYY-MM-DD HH:MM:SS)
+    /// Destination index
     #[arg(long)]
+    pub index: Option<String>,
+
+    /// Splunk sourcetype
+    #[arg(long, default_value = "sysmon")]
+    pub sourcetype: String,
+
+    /// Number of documents sent per request
+    #[arg(long, default_value_t = 500)]
+    pub batch_size: usize,
+
+    /// Number of retries for failed requests
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Include events after this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
     pub after: Option<DateTime<Utc>>,
 
-    /// Include events before this time (format: YYYY-MM-DD HH:MM:SS)
+    /// Include events before this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub before: Option<DateTime<Utc>>,
+
+    /// Also run anomaly detection and export the anomalies
+    #[arg(long, short)]
+    pub detect: bool,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+}
+
+#[derive(Args)]
+pub struct TuiCommand {
+    /// Path to .evtx file
+    #[arg(value_name = "FILE")]
+    pub file_path: PathBuf,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Include events after this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub after: Option<DateTime<Utc>>,
+
+    /// Include events before this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub before: Option<DateTime<Utc>>,
+
+    /// Enable anomaly detection (fills the anomalies tab)
+    #[arg(long, short)]
+    pub detect: bool,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("process").required(true).args(["pid", "guid", "image"])))]
+pub struct ProfileCommand {
+    /// Path to .evtx file
+    #[arg(value_name = "FILE")]
+    pub file_path: PathBuf,
+
+    /// Process ID
+    #[arg(long)]
+    pub pid: Option<u64>,
+
+    /// Process GUID
+    #[arg(long)]
+    pub guid: Option<Uuid>,
+
+    /// Process image (full path or file name)
+    #[arg(long)]
+    pub image: Option<String>,
+
+    /// Include the activity of all descendant processes
     #[arg(long)]
+    pub children: bool,
+
+    /// Include events after this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub after: Option<DateTime<Utc>>,
+
+    /// Include events before this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl ProfileCommand {
+    pub fn selector(&self) -> ProcessSelector {
+        match (self.pid, self.guid, &self.image) {
+            (Some(pid), _, _) => ProcessSelector::Pid(pid),
+            (_, Some(guid), _) => ProcessSelector::Guid(guid),
+            (_, _, image) => ProcessSelector::Image(image.clone().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SessionsCommand {
+    /// Path to .evtx file (Sysmon or Security)
+    #[arg(value_name = "FILE")]
+    pub file_path: PathBuf,
+
+    /// Show the processes and anomalies of the session with this logon ID (hex 0x3e7 or decimal)
+    #[arg(long, value_parser = parse_logon_id)]
+    pub session: Option<u64>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Include events after this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
+    pub after: Option<DateTime<Utc>>,
+
+    /// Include events before this time (YYYY-MM-DD HH:MM:SS in UTC, or RFC 3339 with an offset)
+    #[arg(long, value_parser = parse_timestamp)]
     pub before: Option<DateTime<Utc>>,
 
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+}
+
+#[derive(Args)]
+pub struct DiffCommand {
+    /// First capture, e.g. before the incident
+    #[arg(value_name = "FIRST")]
+    pub first: PathBuf,
+
+    /// Second capture, e.g. after the incident or from another host
+    #[arg(value_name = "SECOND")]
+    pub second: PathBuf,
+
+    /// Input file format of both captures
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub format: InputFormat,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(Args)]
+pub struct TriageCommand {
+    /// Triage file, created with the first verdict
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+
+    /// Finding IDs, as shown by `parse --detect` and in the `finding_id` field of JSON reports.
+    /// Without IDs the recorded verdicts are listed.
+    #[arg(value_name = "ID", value_parser = parse_finding_id)]
+    pub ids: Vec<String>,
+
+    /// Verdict to record
+    #[arg(long, value_enum, requires = "ids", conflicts_with = "clear")]
+    pub status: Option<TriageStatus>,
+
+    /// Note saved with the verdict, e.g. a ticket number
+    #[arg(long, requires = "status")]
+    pub note: Option<String>,
+
+    /// Forget the verdicts of the findings
+    #[arg(long, requires = "ids")]
+    pub clear: bool,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("event").required(true).args(["record_id", "index"])))]
+pub struct ShowCommand {
+    /// Path to .evtx (or --format jsonl) files
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub file_paths: Vec<PathBuf>,
+
+    /// Input file format
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub format: InputFormat,
+
+    /// Event record ID (EventRecordID), as in the `record_id` field of JSON output
+    #[arg(long)]
+    pub record_id: Option<u32>,
+
+    /// Position of the event among the parsed events, starting at 1
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub index: Option<u64>,
+
+    /// Only show the event of this computer, when record IDs of several computers overlap
+    #[arg(long)]
+    pub computer: Option<String>,
+
+    /// Print the fields as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl ShowCommand {
+    pub fn selector(&self) -> EventSelector {
+        match (self.record_id, self.index) {
+            (Some(id), _) => EventSelector::RecordId(id),
+            (_, index) => EventSelector::Index(index.unwrap_or(1) as usize),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct CompletionsCommand {
+    /// Shell to complete in, e.g. `completions bash > /etc/bash_completion.d/security_log_analyser`
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManpageCommand {
+    /// Write a page per subcommand to this directory instead of printing the main page
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct NetsummaryCommand {
+    /// Path to .evtx (or --format jsonl) files
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub file_paths: Vec<PathBuf>,
+
+    /// Input file format
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub format: InputFormat,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Order of the processes
+    #[arg(long, value_enum, default_value_t = NetSortOrder::Connections)]
+    pub sort: NetSortOrder,
+
+    /// Number of processes shown
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+
+    /// Number of destinations shown per process, the busiest first
+    #[arg(long, default_value_t = 10)]
+    pub destinations: usize,
+}
+
+#[derive(Args)]
+pub struct StatsCommand {
+    /// Path to .evtx (or --format jsonl) files
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub file_paths: Vec<PathBuf>,
+
+    /// Input file format
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub format: InputFormat,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// Report DNS queries: top domains, domains queried by many processes and processes whose
+    /// queries fail (NXDOMAIN), instead of network talkers
+    #[arg(long)]
+    pub dns: bool,
+
+    /// Prevalence store file (see `parse --prevalence`); with --dns, reports the domains never
+    /// queried in earlier runs and adds those of this run
+    #[arg(long, value_name = "FILE", requires = "dns")]
+    pub prevalence: Option<PathBuf>,
+
+    /// Number of entries shown per list
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args)]
+pub struct DaemonCommand {
+    /// Directory scanned for new .evtx and .jsonl/.json files (and .csv with --mapping), which
+    /// may be gzip-compressed or in ZIP archives
+    #[arg(long, value_name = "DIR")]
+    pub input: PathBuf,
+
+    /// Directory the JSON reports are written to, one per analyzed file
+    #[arg(long, value_name = "DIR")]
+    pub output: PathBuf,
+
+    /// Directory analyzed files are moved to [default: INPUT/processed]
+    #[arg(long, value_name = "DIR")]
+    pub processed: Option<PathBuf>,
+
+    /// Directory files that could not be analyzed are moved to, next to a file with the error
+    /// [default: INPUT/failed]
+    #[arg(long, value_name = "DIR")]
+    pub failed: Option<PathBuf>,
+
+    /// Time between two scans of the input directory
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "5m")]
+    pub interval: Duration,
+
+    /// Scan at the times matching a cron pattern in local time instead, e.g. "0 */2 * * *" or
+    /// "30 1 * * mon-fri"
+    #[arg(long, value_name = "CRON", value_parser = parse_schedule, conflicts_with = "interval")]
+    pub schedule: Option<Schedule>,
+
+    /// Files modified more recently than this may still be written and are left for the next scan
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub settle: Duration,
+
+    /// Scan the input directory once and exit
+    #[arg(long)]
+    pub once: bool,
+
+    /// Format of the logs in ZIP archives
+    #[arg(long, value_enum, default_value_t = InputFormat::Evtx)]
+    pub archive_format: InputFormat,
+
+    /// YAML or JSON column mapping of CSV or JSON Lines exports of other tools (EvtxECmd, Chainsaw, ...)
+    #[arg(long, value_name = "FILE")]
+    pub mapping: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+
+    /// Anomalies of a host logged within this time of the first one belong to the same incident
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = crate::incident::DEFAULT_INCIDENT_WINDOW)]
+    pub incident_window: Duration,
+}
+
+#[derive(Args)]
+pub struct ServeCommand {
+    /// Address and port to listen on; use 0.0.0.0 to accept other machines, with a --token
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: String,
+
+    /// Bearer token required by every endpoint but /health
+    #[arg(long, env = "SLA_API_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// Largest accepted upload, in megabytes
+    #[arg(long, value_name = "MB", default_value_t = 512)]
+    pub max_upload: u64,
+
+    /// Uploads analyzed at the same time
+    #[arg(long, default_value_t = 4)]
+    pub threads: usize,
+
+    /// YAML or JSON column mapping of CSV or JSON Lines exports of other tools (EvtxECmd, Chainsaw, ...)
+    #[arg(long, value_name = "FILE")]
+    pub mapping: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+
+    /// Anomalies of a host logged within this time of the first one belong to the same incident
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = crate::incident::DEFAULT_INCIDENT_WINDOW)]
+    pub incident_window: Duration,
+}
+
+#[cfg(windows)]
+#[derive(Args)]
+pub struct WatchCommand {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
     /// Enable anomaly detection
     #[arg(long, short)]
     pub detect: bool,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+
+    /// Replay events from the last duration before going live (e.g. 30m, 2h, 1d)
+    #[arg(long, value_parser = parse_duration)]
+    pub since: Option<Duration>,
+
+    /// Maximum number of recent events kept as context for anomaly detection
+    #[arg(long, default_value_t = crate::live_monitor::DEFAULT_BUFFER_SIZE)]
+    pub buffer_size: usize,
+
+    /// Only keep context events from this time window (e.g. 10m), in addition to --buffer-size
+    #[arg(long, value_parser = parse_duration)]
+    pub context_window: Option<Duration>,
+
+    /// Save the last processed event to this file and resume after it on the next run
+    #[arg(long, value_name = "FILE")]
+    pub bookmark: Option<PathBuf>,
+
+    /// Save the detection context to this file every minute and on exit, and restore it on the
+    /// next run so stateful rules keep their history across restarts
+    #[arg(long, value_name = "FILE", requires = "detect")]
+    pub state: Option<PathBuf>,
+
+    /// Forward matching events and anomalies to a syslog collector as RFC 5424 messages:
+    /// syslog://host[:port] (UDP), syslog+tcp://host[:port] or syslog+tls://host[:port]; or, in
+    /// builds with the kafka feature, to a Kafka topic as JSON: kafka://broker[:port][,...]/topic
+    #[arg(long, value_name = "URL", value_parser = parse_forward_target)]
+    pub forward: Option<ForwardTarget>,
+
+    /// PEM file of the certificate authorities trusted for a syslog+tls:// collector instead of
+    /// the public ones, e.g. an internal CA
+    #[arg(long, value_name = "PEM", requires = "forward")]
+    pub syslog_ca: Option<PathBuf>,
+
+    /// Only forward anomalies, not every matching event
+    #[arg(long, requires_all = ["forward", "detect"])]
+    pub forward_anomalies_only: bool,
+
+    /// Ring the terminal bell and show a desktop notification for High and Critical anomalies
+    #[arg(long, requires = "detect")]
+    pub notify: bool,
+
+    /// Don't print matching events, only anomalies and the statistics line
+    #[arg(long, short)]
+    pub quiet: bool,
+
+    /// Only print the first of every N matching events (all of them are still analyzed)
+    #[arg(long, value_name = "N", conflicts_with = "quiet", value_parser = clap::value_parser!(u64).range(1..))]
+    pub print_every: Option<u64>,
+
+    /// Time between two statistics lines (events per second, anomalies so far), 0s to disable
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = crate::live_output::DEFAULT_STATS_INTERVAL)]
+    pub stats_interval: Duration,
+
+    #[command(flatten)]
+    pub remote: RemoteArgs,
+
+    /// On exit, write the session summary, its anomalies and the buffered events to this JSON file
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Apply changes of the --config file while monitoring: its rules, suppressions, severity
+    /// overrides and filter, without restarting the subscription or clearing the context
+    #[arg(long, requires = "config")]
+    pub reload: bool,
 }
 
 #[cfg(windows)]
 #[derive(Args)]
-pub struct WatchCommand {
-    /// Display events whose Event ID is in the provided list (e.g. 1,2,7)
-    #[arg(long, value_delimiter = ',')]
-    pub event_id: Option<Vec<u8>>,
+pub struct CollectCommand {
+    #[command(flatten)]
+    pub filter: FilterArgs,
 
-    /// Search by substring in key fields
-    #[arg(long)]
-    pub search: Option<String>,
+    /// Only collect events from the last duration (e.g. 30m, 2h, 1d)
+    #[arg(long, value_parser = parse_duration)]
+    pub since: Option<Duration>,
 
     /// Enable anomaly detection
     #[arg(long, short)]
     pub detect: bool,
+
+    #[command(flatten)]
+    pub detection: DetectionArgs,
+
+    #[command(flatten)]
+    pub remote: RemoteArgs,
 }
 
-pub fn execute(config: Config) -> anyhow::Result<()> {
+/// Connection to the event log of another computer, over the Event Log remoting protocol
+#[cfg(windows)]
+#[derive(Args)]
+pub struct RemoteArgs {
+    /// Read the Sysmon channel of this computer instead of the local one
+    #[arg(long, value_name = "HOST")]
+    pub remote: Option<String>,
+
+    /// Account used to connect to the remote computer (default: current user)
+    #[arg(long, value_name = "USER", requires = "remote")]
+    pub remote_user: Option<String>,
+
+    /// Domain of --remote-user
+    #[arg(long, value_name = "DOMAIN", requires = "remote_user")]
+    pub remote_domain: Option<String>,
+
+    /// Password of --remote-user (default: prompted for)
+    #[arg(
+        long,
+        env = "SYSMON_REMOTE_PASSWORD",
+        hide_env_values = true,
+        requires = "remote_user"
+    )]
+    pub remote_password: Option<String>,
+}
+
+#[cfg(windows)]
+impl RemoteArgs {
+    /// Remote computer to read from, prompting for the password of --remote-user when it is not
+    /// given
+    pub fn into_remote(self) -> anyhow::Result<Option<crate::live_monitor::RemoteHost>> {
+        let Some(host) = self.remote else {
+            return Ok(None);
+        };
+        let password = match (&self.remote_user, self.remote_password) {
+            (Some(user), None) => Some(rpassword::prompt_password(format!(
+                "Password of {user} on {host}: "
+            ))?),
+            (_, password) => password,
+        };
+        Ok(Some(crate::live_monitor::RemoteHost {
+            host,
+            user: self.remote_user,
+            domain: self.remote_domain,
+            password,
+        }))
+    }
+}
+
+/// Event selection options shared by all commands
+#[derive(Args)]
+pub struct FilterArgs {
+    /// Display events whose Event ID is in the provided list. Accepts IDs, ranges,
+    /// aliases (process, network, dns, registry, file, pipe, wmi, logon, powershell) and exclusions
+    /// (e.g. 1-10,!3,dns)
+    #[arg(long, value_delimiter = ',', value_parser = parse_event_id_spec)]
+    pub event_id: Option<Vec<EventIdSpec>>,
+
+    /// Search by substring in the computer name and any event data field (can be repeated)
+    #[arg(long)]
+    pub search: Vec<String>,
+
+    /// How multiple search terms are combined
+    #[arg(long, value_enum, default_value_t = SearchMode::Any)]
+    pub search_mode: SearchMode,
+
+    /// Drop events containing this substring in any field searched by --search (can be repeated)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Match search and exclude terms case-sensitively
+    #[arg(long)]
+    pub case_sensitive: bool,
+
+    /// Display events of this process image (full path or file name)
+    #[arg(long)]
+    pub image: Option<String>,
+
+    /// Display events whose image or target file matches this path glob: * and ? within a folder,
+    /// ** across folders and %TEMP%, %APPDATA%... for the usual folders of these variables, e.g.
+    /// "C:\Users\*\AppData\**\*.exe" (can be repeated)
+    #[arg(long, value_name = "GLOB", value_parser = parse_path_glob)]
+    pub path_glob: Vec<PathGlob>,
+
+    /// Display events logged by these computers, names or globs such as WS-*.corp.local
+    /// (can be repeated or comma separated)
+    #[arg(long, value_delimiter = ',', value_name = "NAME|GLOB")]
+    pub computer: Vec<String>,
+
+    /// Display events of these processes, by ProcessGuid (can be repeated or comma separated)
+    #[arg(long, value_delimiter = ',', value_name = "GUID")]
+    pub process_guid: Vec<Uuid>,
+
+    /// Display events of this account (DOMAIN\user or user)
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Display process events of this logon session (hex 0x3e7 or decimal)
+    #[arg(long, value_parser = parse_logon_id)]
+    pub logon_id: Option<u64>,
+
+    /// Display process events running at this integrity level
+    #[arg(long, value_enum)]
+    pub integrity: Option<Integrity>,
+
+    /// Display network connections to these addresses or CIDR blocks (e.g. 10.0.0.0/8,203.0.113.7)
+    #[arg(long, value_delimiter = ',', value_name = "IP|CIDR", value_parser = parse_network)]
+    pub dest_ip: Vec<Network>,
+
+    /// Display network connections to these ports or port ranges (e.g. 443,8000-8999)
+    #[arg(long, value_delimiter = ',', value_name = "PORT|RANGE", value_parser = parse_port_range)]
+    pub dest_port: Vec<PortRange>,
+
+    /// Display network connections of this transport protocol
+    #[arg(long, value_enum)]
+    pub protocol: Option<Protocol>,
+
+    /// Display inbound (accepted) or outbound (initiated) network connections
+    #[arg(long, value_enum)]
+    pub direction: Option<Direction>,
+
+    /// Display events matching an expression, e.g.
+    /// "event_id == 3 and image endswith 'powershell.exe' and dest_port > 1024"
+    #[arg(long = "where", value_name = "EXPR", value_parser = parse_query)]
+    pub query: Option<Query>,
+}
+
+impl FilterArgs {
+    pub fn into_filter(self) -> EventFilter {
+        EventFilter::new()
+            .with_event_id_specs(self.event_id)
+            .with_search_terms(self.search)
+            .with_search_mode(self.search_mode)
+            .with_exclude_terms(self.exclude)
+            .with_case_sensitive(self.case_sensitive)
+            .with_image(self.image)
+            .with_path_globs(self.path_glob)
+            .with_computers(self.computer)
+            .with_process_guids(self.process_guid)
+            .with_user(self.user)
+            .with_logon_id(self.logon_id)
+            .with_integrity(self.integrity)
+            .with_network(NetworkFilter {
+                dest_ips: self.dest_ip,
+                dest_ports: self.dest_port,
+                protocol: self.protocol,
+                direction: self.direction,
+            })
+            .with_query(self.query)
+    }
+}
+
+/// Thresholds of the detection rules
+#[derive(Args)]
+pub struct DetectionArgs {
+    /// Connections to the same destination needed to report beaconing
+    #[arg(long, default_value_t = DetectionConfig::default().beacon_min_count)]
+    pub beacon_min_count: usize,
+
+    /// Maximum deviation of beacon intervals relative to their mean (0.1 = 10%)
+    #[arg(long, default_value_t = DetectionConfig::default().beacon_jitter)]
+    pub beacon_jitter: f64,
+
+    /// Report processes started from an executable written less than this before (Sysmon event 29)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = DEFAULT_DROP_EXECUTE_WINDOW)]
+    pub drop_execute_window: Duration,
+
+    /// Events of one Event ID within --storm-window that make an event storm
+    #[arg(
+        long,
+        default_value_t = DEFAULT_EVENT_STORM_THRESHOLD,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub storm_threshold: usize,
+
+    /// Time within which --storm-threshold events of one ID make an event storm
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = DEFAULT_EVENT_STORM_WINDOW)]
+    pub storm_window: Duration,
+
+    /// YAML or JSON threat-intel feed with `ips` (addresses or CIDRs) and `domains` lists (can be repeated)
+    #[arg(long = "ioc-file", value_name = "FILE")]
+    pub ioc_files: Vec<PathBuf>,
+
+    /// SHA256 hashes of trusted binaries, as an NSRL CSV export or one hash per line (can be repeated)
+    #[arg(long = "known-good", value_name = "FILE")]
+    pub known_good_files: Vec<PathBuf>,
+
+    /// YAML or JSON file of `images`, `hashes`, `command_lines` and `destination_ips` whose events
+    /// never produce findings, e.g. security and backup agents (can be repeated)
+    #[arg(long = "allowlist", value_name = "FILE")]
+    pub allowlist_files: Vec<PathBuf>,
+
+    /// CSV asset inventory with `computer`, `role`, `owner` and `criticality` columns; findings
+    /// show the role of their computer and are raised or lowered by its criticality
+    #[arg(long, value_name = "CSV")]
+    pub assets: Option<PathBuf>,
+
+    /// JSON file counting the runs and hosts each executable was seen in, created if missing;
+    /// every detection run adds its executables and reports new and rare ones
+    #[arg(long, value_name = "FILE")]
+    pub prevalence: Option<PathBuf>,
+
+    /// Drop all findings of these rules, by ID or name (e.g. SLA-003,UnusualPort)
+    #[arg(long, value_delimiter = ',', value_name = "RULE")]
+    pub suppress: Vec<String>,
+
+    /// YAML or JSON detection config file with `suppressions`, `parent_child_rules`,
+    /// `port_allowlist`, `inbound` and `severity_overrides` sections
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Drop findings below this severity, after the overrides of the config file
+    #[arg(long, value_enum)]
+    pub min_severity: Option<Severity>,
+
+    /// Drive letters of removable media (e.g. E,F); executables started from them are reported
+    /// like those started from network shares
+    #[arg(long, value_delimiter = ',', value_name = "DRIVE", value_parser = parse_drive_letter)]
+    pub removable_drives: Vec<char>,
+}
+
+impl DetectionArgs {
+    pub fn into_config(self) -> anyhow::Result<DetectionConfig> {
+        let (config, filter) = self.load()?;
+        if filter.is_some() {
+            tracing::warn!("The filter of the config file is only used by watch, ignoring it");
+        }
+        Ok(config)
+    }
+
+    /// Detection config and the `filter` expression of the config file. Loads every file again,
+    /// so `watch --reload` calls it when the config file changes, and changes nothing else: the
+    /// caller applies the result only once all files loaded.
+    pub fn load(&self) -> anyhow::Result<(DetectionConfig, Option<Query>)> {
+        let ioc_feeds = self
+            .ioc_files
+            .iter()
+            .map(|path| IocFeed::load(path))
+            .collect::<anyhow::Result<_>>()?;
+        let mut known_good: Option<KnownGoodHashes> = None;
+        for path in &self.known_good_files {
+            known_good
+                .get_or_insert_default()
+                .extend(KnownGoodHashes::load(path)?);
+        }
+        let mut allowlist = Allowlist::default();
+        for path in &self.allowlist_files {
+            allowlist.extend(Allowlist::load(path)?);
+        }
+        let mut file = match &self.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        for rule in &self.suppress {
+            file.suppressions.push(Suppression::rule(rule)?);
+        }
+        let mut parent_child_rules = ParentChildRule::defaults();
+        parent_child_rules.append(&mut file.parent_child_rules);
+        let mut port_profiles = PortProfile::defaults();
+        port_profiles.append(&mut file.port_allowlist);
+        let assets = self
+            .assets
+            .as_deref()
+            .map(AssetInventory::load)
+            .transpose()?
+            .map(Arc::new);
+        if let Some(path) = &self.prevalence {
+            // Fail now rather than when the run finishes
+            PrevalenceStore::load(path)?;
+        }
+        let config = DetectionConfig {
+            beacon_min_count: self.beacon_min_count,
+            beacon_jitter: self.beacon_jitter,
+            drop_execute_window: self.drop_execute_window,
+            event_storm_threshold: self.storm_threshold,
+            event_storm_window: self.storm_window,
+            ioc_feeds,
+            suppressions: file.suppressions,
+            parent_child_rules,
+            known_good,
+            removable_drives: self.removable_drives.clone(),
+            port_profiles,
+            min_severity: self.min_severity,
+            allowlist,
+            inbound: file.inbound.into(),
+            prevalence: self.prevalence.clone(),
+            severity_overrides: SeverityOverrides::new(file.severity_overrides),
+            assets,
+            ..DetectionConfig::default()
+        };
+        config.validate()?;
+        Ok((config, file.filter))
+    }
+}
+
+#[derive(Args)]
+pub struct RulesCommand {
+    #[command(subcommand)]
+    pub action: RulesAction,
+}
+
+#[derive(Subcommand)]
+pub enum RulesAction {
+    /// List available detection rules
+    List,
+
+    /// Run a single rule against a log file and report the events it matches
+    Test {
+        /// Rule ID or name (see `rules list`)
+        rule: String,
+
+        /// Sample events: an .evtx file, or JSON lines if named .json, .jsonl or .ndjson
+        #[arg(long, value_name = "FILE")]
+        events: PathBuf,
+
+        /// Rule settings and config file to test, whose errors are reported before the rule runs
+        #[command(flatten)]
+        detection: Box<DetectionArgs>,
+    },
+}
+
+/// Cron pattern of five fields (minute, hour, day of month, month, day of week)
+pub fn parse_schedule(s: &str) -> Result<Schedule, String> {
+    let cron = s
+        .parse()
+        .map_err(|e: croner::errors::CronError| e.to_string())?;
+    Ok(Schedule::Cron(Box::new(cron)))
+}
+
+/// Run the selected command, the exit code is only non-zero for `parse --fail-on` findings
+pub fn execute(config: Config) -> anyhow::Result<ExitCode> {
+    set_color_choice(config.color);
+    set_time_zone(config.time_zone);
     match config.command {
-        Commands::Parse(cmd) => execute_parse(cmd),
+        Commands::Parse(cmd) => return execute_parse(cmd),
+        Commands::Export(cmd) => execute_export(cmd),
+        Commands::Rules(cmd) => execute_rules(cmd),
+        Commands::Tui(cmd) => execute_tui(cmd),
+        Commands::Profile(cmd) => execute_profile(cmd),
+        Commands::Sessions(cmd) => execute_sessions(cmd),
+        Commands::Diff(cmd) => execute_diff(cmd),
+        Commands::Triage(cmd) => execute_triage(cmd),
+        Commands::Show(cmd) => execute_show(cmd),
+        Commands::Netsummary(cmd) => execute_netsummary(cmd),
+        Commands::Stats(cmd) => execute_stats(cmd),
+        Commands::Daemon(cmd) => execute_daemon(cmd),
+        Commands::Index(cmd) => execute_index(cmd),
+        Commands::Serve(cmd) => execute_serve(cmd),
+        Commands::Completions(cmd) => execute_completions(cmd),
+        Commands::Manpage(cmd) => execute_manpage(cmd),
         #[cfg(windows)]
         Commands::Watch(cmd) => execute_watch(cmd),
-    }
+        #[cfg(windows)]
+        Commands::Collect(cmd) => execute_collect(cmd),
+    }?;
+    Ok(ExitCode::SUCCESS)
 }
 pub fn parse_args() -> Config {
+    let cli = Cli::parse();
     Config {
-        command: Cli::parse().command,
+        command: cli.command,
+        color: cli.color,
+        time_zone: match cli.tz {
+            Some(zone) => zone,
+            None if cli.local_time => TimeZoneChoice::Local,
+            None => TimeZoneChoice::Utc,
+        },
     }
 }
 pub struct Config {
     pub command: Commands,
+    pub color: ColorChoice,
+    pub time_zone: TimeZoneChoice,
+}
+/// Parse a drive letter such as `E` or `e:` into its uppercase letter
+pub fn parse_drive_letter(s: &str) -> Result<char, String> {
+    let mut chars = s.trim().trim_end_matches([':', '\\']).chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Ok(letter.to_ascii_uppercase()),
+        _ => Err(format!(
+            "invalid drive letter '{s}' (expected e.g. E or E:)"
+        )),
+    }
+}
+/// Parse a duration such as `90s`, `30m`, `2h` or `1d`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{s}' (expected s, m, h or d)"))?;
+    let (value, unit) = s.split_at(split);
+    let value: i64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration value in '{s}'"))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(value),
+        "m" => Duration::try_minutes(value),
+        "h" => Duration::try_hours(value),
+        "d" => Duration::try_days(value),
+        _ => {
+            return Err(format!(
+                "unknown duration unit '{unit}' (expected s, m, h or d)"
+            ));
+        }
+    };
+    duration.ok_or_else(|| format!("duration '{s}' is too long"))
+}
+/// Parse a sampling rate such as `1/10` (or just `10`), one record out of N is kept
+pub fn parse_sample_rate(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let every = match s.split_once('/') {
+        Some((one, every)) if one.trim() == "1" => every.trim(),
+        Some(_) => return Err(format!("invalid sampling rate '{s}' (expected 1/N)")),
+        None => s,
+    };
+    match every.parse::<u32>() {
+        Ok(every) if every > 0 => Ok(every),
+        _ => Err(format!("invalid sampling rate '{s}' (expected 1/N)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+    }
+
+    #[test]
+    fn sample_rate() {
+        assert_eq!(parse_sample_rate("1/10"), Ok(10));
+        assert_eq!(parse_sample_rate("4"), Ok(4));
+        assert!(parse_sample_rate("2/10").is_err());
+        assert!(parse_sample_rate("1/0").is_err());
+        assert!(parse_sample_rate("1/x").is_err());
+    }
+
+    #[test]
+    fn command_definition() {
+        use clap::CommandFactory;
+        // Duplicate argument names between flattened option groups only panic when parsed
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn detection_config_loads_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.yaml");
+        std::fs::write(&config, "severity_overrides:\n  UnusualPort: low\n").unwrap();
+        let load = |args: &[&str]| {
+            let mut argv = vec![
+                "sla",
+                "parse",
+                "a.evtx",
+                "--config",
+                config.to_str().unwrap(),
+            ];
+            argv.extend(args);
+            let Commands::Parse(cmd) = Cli::try_parse_from(argv).unwrap().command else {
+                panic!("expected parse command");
+            };
+            cmd.detection.load()
+        };
+        let missing = dir.path().join("assets.csv");
+        assert!(load(&["--assets", missing.to_str().unwrap()]).is_err());
+        assert!(DetectionConfig::default().severity_overrides.is_empty());
+        let (detection, _) = load(&[]).unwrap();
+        assert!(!detection.severity_overrides.is_empty());
+    }
+
+    #[test]
+    fn drive_letters() {
+        assert_eq!(parse_drive_letter("e:"), Ok('E'));
+        assert_eq!(parse_drive_letter(r"F:\"), Ok('F'));
+        assert!(parse_drive_letter("EF").is_err());
+        assert!(parse_drive_letter("1").is_err());
+    }
+
+    #[test]
+    fn storm_threshold_is_positive() {
+        let parse = |threshold| {
+            Cli::try_parse_from(["sla", "parse", "a.evtx", "--storm-threshold", threshold])
+        };
+        assert!(parse("0").is_err());
+        assert!(parse("5").is_ok());
+    }
+
+    #[test]
+    fn parse_duration_invalid() {
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("9223372036854775807d").is_err());
+    }
+
+    #[test]
+    fn event_id_list() {
+        let cli =
+            Cli::try_parse_from(["sla", "parse", "a.evtx", "--event-id", "1-3,!2,dns"]).unwrap();
+        let Commands::Parse(cmd) = cli.command else {
+            panic!("expected parse command");
+        };
+        let filter = cmd.filter.into_filter();
+        assert_eq!(filter.get_event_ids(), Some(&vec![1, 2, 3, 22]));
+        assert_eq!(filter.get_excluded_event_ids(), [2]);
+    }
+
+    #[test]
+    fn color_choice_is_global() {
+        let cli = Cli::try_parse_from(["sla", "parse", "a.evtx", "--color", "never"]).unwrap();
+        assert_eq!(cli.color, ColorChoice::Never);
+        let cli = Cli::try_parse_from(["sla", "parse", "a.evtx"]).unwrap();
+        assert_eq!(cli.color, ColorChoice::Auto);
+    }
+
+    #[test]
+    fn time_zone_options() {
+        use chrono::FixedOffset;
+        let cli = Cli::try_parse_from(["sla", "parse", "a.evtx", "--tz", "+05:30"]).unwrap();
+        assert_eq!(
+            cli.tz,
+            Some(TimeZoneChoice::Offset(
+                FixedOffset::east_opt(5 * 3600 + 1800).unwrap()
+            ))
+        );
+        let cli = Cli::try_parse_from(["sla", "parse", "a.evtx", "--local-time"]).unwrap();
+        assert!(cli.local_time);
+        assert!(Cli::try_parse_from(["sla", "parse", "a.evtx", "--tz", "Europe/Kyiv"]).is_err());
+        assert!(
+            Cli::try_parse_from(["sla", "parse", "a.evtx", "--local-time", "--tz", "utc"]).is_err()
+        );
+    }
 }
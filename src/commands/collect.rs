@@ -0,0 +1,47 @@
+#![cfg(windows)]
+use crate::analyzer::prevalence;
+use crate::cli::CollectCommand;
+use crate::{analyzer, display, live_monitor};
+use anyhow::Result;
+use colored::Colorize;
+use tracing::info;
+
+pub(crate) fn execute_collect(cmd: CollectCommand) -> Result<()> {
+    let CollectCommand {
+        filter,
+        since,
+        detect,
+        detection,
+        remote,
+    } = cmd;
+    let remote = remote.into_remote()?;
+    println!("{}", "Security Log Analyzer - Collect".bright_cyan().bold());
+    let host = remote.as_ref().map_or("localhost", |remote| &remote.host);
+    println!("Collecting Sysmon events from {}\n", host.bright_yellow());
+
+    let filter = filter.into_filter();
+    let events = live_monitor::collect_events(&filter, since, remote.as_ref())?;
+    println!(
+        "Total events found: {}",
+        events.len().to_string().bright_green()
+    );
+    let anomalies = if detect {
+        info!("Running anomaly detection");
+        let config = detection.into_config()?;
+        let anomalies = analyzer::detect_anomalies_with_config(&events, &config);
+        prevalence::record_run(config.prevalence.as_deref(), &events);
+        anomalies
+    } else {
+        Vec::new()
+    };
+    if !anomalies.is_empty() {
+        println!("Anomalies detected:");
+        display::display_aggregated_anomalies(&analyzer::aggregate_anomalies(&anomalies), None);
+    }
+    display::display_events(&events);
+    if !anomalies.is_empty() {
+        println!();
+        display::display_host_risk(&analyzer::host_risk_summary(&anomalies));
+    }
+    Ok(())
+}
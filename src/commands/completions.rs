@@ -0,0 +1,32 @@
+use crate::cli::{Cli, CompletionsCommand};
+use clap::CommandFactory;
+
+/// Name the completions and man pages refer to the program by, that of the installed binary
+pub const BIN_NAME: &str = env!("CARGO_PKG_NAME");
+
+pub fn execute_completions(cmd: CompletionsCommand) -> anyhow::Result<()> {
+    let mut command = Cli::command().name(BIN_NAME);
+    clap_complete::generate(cmd.shell, &mut command, BIN_NAME, &mut std::io::stdout());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap_complete::Shell;
+
+    #[test]
+    fn completions_cover_subcommands_and_filters() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            Shell::Bash,
+            &mut Cli::command().name(BIN_NAME),
+            BIN_NAME,
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("security_log_analyser__subcmd__parse"));
+        assert!(script.contains("--path-glob"));
+        assert!(script.contains("timesketch"));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::cli::DaemonCommand;
+use crate::daemon::{self, DaemonConfig, Schedule};
+use crate::mapping::FieldMapping;
+use anyhow::{Result, bail};
+use colored::*;
+
+pub fn execute_daemon(cmd: DaemonCommand) -> Result<()> {
+    let schedule = match cmd.schedule {
+        Some(schedule) => schedule,
+        None => {
+            let interval = cmd.interval.to_std()?;
+            if interval.is_zero() {
+                bail!("--interval must be longer than zero");
+            }
+            Schedule::Every(interval)
+        }
+    };
+    let config = DaemonConfig {
+        processed: cmd.processed.unwrap_or_else(|| cmd.input.join("processed")),
+        failed: cmd.failed.unwrap_or_else(|| cmd.input.join("failed")),
+        input: cmd.input,
+        output: cmd.output,
+        schedule,
+        settle: cmd.settle.to_std()?,
+        archive_format: cmd.archive_format,
+        mapping: cmd
+            .mapping
+            .map(|path| FieldMapping::load(&path))
+            .transpose()?,
+        filter: cmd.filter.into_filter(),
+        detection: cmd.detection.into_config()?,
+        incident_window: cmd.incident_window,
+    };
+    if !cmd.once {
+        println!(
+            "{}",
+            "=== Security Log Analyzer - Daemon ==="
+                .bright_cyan()
+                .bold()
+        );
+        let when = match &config.schedule {
+            Schedule::Every(interval) => format!("every {}s", interval.as_secs()),
+            Schedule::Cron(cron) => format!("at '{}'", cron.pattern),
+        };
+        println!(
+            "Watching {} {}, reports in {}",
+            config.input.to_string_lossy().bright_yellow(),
+            when,
+            config.output.to_string_lossy().bright_yellow()
+        );
+        println!("Press {} to exit\n", "Ctrl+C".bright_red());
+    }
+    daemon::run(config, cmd.once)
+}
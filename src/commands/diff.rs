@@ -0,0 +1,26 @@
+use crate::cli::DiffCommand;
+use crate::{diff, display, parser};
+use anyhow::Result;
+use colored::*;
+
+pub fn execute_diff(cmd: DiffCommand) -> Result<()> {
+    let DiffCommand {
+        first,
+        second,
+        format,
+        filter,
+    } = cmd;
+    println!(
+        "Comparing {} with {}\n",
+        first.to_string_lossy().bright_yellow(),
+        second.to_string_lossy().bright_yellow()
+    );
+    let filter = filter.into_filter();
+    let first_events =
+        filter.apply(&parser::parse_files(std::slice::from_ref(&first), format, false)?.events);
+    let second_events =
+        filter.apply(&parser::parse_files(std::slice::from_ref(&second), format, false)?.events);
+    let diff = diff::diff_logs(&first_events, &second_events);
+    display::display_diff(&diff, &first.to_string_lossy(), &second.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::analyzer::prevalence;
+use crate::cli::ExportCommand;
+use crate::export::{ExportConfig, Exporter, anomaly_to_json, event_to_json};
+use crate::{analyzer, parser};
+use anyhow::Result;
+use colored::*;
+use serde_json::Value;
+use tracing::info;
+
+pub fn execute_export(cmd: ExportCommand) -> Result<()> {
+    let ExportCommand {
+        file_path,
+        target,
+        url,
+        token,
+        index,
+        sourcetype,
+        batch_size,
+        retries,
+        filter,
+        after,
+        before,
+        detect,
+        detection,
+    } = cmd;
+    println!("{}", "Security Log Analyzer - Export".bright_cyan().bold());
+    println!(
+        "Exporting file: {}\n",
+        file_path.to_string_lossy().bright_yellow()
+    );
+    let events = parser::parse_evtx_file(&file_path)?;
+    let filters = filter.into_filter().with_time_range(after, before);
+    let filtered_events = filters.apply(&events);
+
+    let mut documents: Vec<Value> = filtered_events.iter().map(event_to_json).collect();
+    if detect {
+        info!("Running anomaly detection");
+        let config = detection.into_config()?;
+        let anomalies = analyzer::detect_anomalies_with_config(&filtered_events, &config);
+        prevalence::record_run(config.prevalence.as_deref(), &filtered_events);
+        documents.extend(anomalies.iter().map(anomaly_to_json));
+    }
+
+    let exporter = Exporter::new(ExportConfig {
+        target,
+        url,
+        token,
+        index,
+        sourcetype,
+        batch_size,
+        retries,
+    });
+    let sent = exporter.export(&documents)?;
+    println!(
+        "Exported {} documents to {:?}",
+        sent.to_string().bright_green(),
+        target
+    );
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use crate::cli::IndexCommand;
+use crate::index;
+use anyhow::Result;
+use colored::*;
+
+pub fn execute_index(cmd: IndexCommand) -> Result<()> {
+    for path in &cmd.file_paths {
+        let built = index::build_index(path)?;
+        println!(
+            "Indexed {}: {} events in {} chunks -> {}",
+            path.to_string_lossy().bright_yellow(),
+            built.events.to_string().bright_green(),
+            built.chunks.len(),
+            index::index_path(path).to_string_lossy()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::cli::{Cli, ManpageCommand};
+use crate::commands::completions::BIN_NAME;
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_mangen::Man;
+
+pub fn execute_manpage(cmd: ManpageCommand) -> Result<()> {
+    let command = Cli::command().name(BIN_NAME);
+    match cmd.out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            clap_mangen::generate_to(command, &dir)
+                .with_context(|| format!("Failed to write man pages to {}", dir.display()))?;
+            println!("Man pages written to {}", dir.display());
+        }
+        None => Man::new(command).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_per_subcommand() {
+        let dir = tempfile::tempdir().unwrap();
+        execute_manpage(ManpageCommand {
+            out_dir: Some(dir.path().to_path_buf()),
+        })
+        .unwrap();
+        let main = std::fs::read_to_string(dir.path().join("security_log_analyser.1")).unwrap();
+        assert!(main.contains("security_log_analyser\\-parse(1)"));
+        let parse =
+            std::fs::read_to_string(dir.path().join("security_log_analyser-parse.1")).unwrap();
+        assert!(parse.contains("path\\-glob"));
+    }
+}
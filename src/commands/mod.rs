@@ -1,2 +1,18 @@
+pub mod collect;
+pub mod completions;
+pub mod daemon;
+pub mod diff;
+pub mod export;
+pub mod index;
+pub mod manpage;
+pub mod netsummary;
 pub mod parse;
+pub mod profile;
+pub mod rules;
+pub mod serve;
+pub mod sessions;
+pub mod show;
+pub mod stats;
+pub mod triage;
+pub mod tui;
 pub mod watch;
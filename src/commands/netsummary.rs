@@ -0,0 +1,11 @@
+use crate::cli::NetsummaryCommand;
+use crate::{display, netsummary, parser};
+use anyhow::Result;
+
+pub fn execute_netsummary(cmd: NetsummaryCommand) -> Result<()> {
+    let report = parser::parse_files(&cmd.file_paths, cmd.format, false)?;
+    let events = cmd.filter.into_filter().apply(&report.events);
+    let summaries = netsummary::summarize_connections(&events, cmd.sort);
+    display::display_net_summary(&summaries, cmd.top, cmd.destinations);
+    Ok(())
+}
@@ -1,51 +1,452 @@
+use crate::analyzer::attack::tactic_summary;
+use crate::analyzer::prevalence;
+use crate::analyzer::{AggregatedAnomaly, Anomaly};
 use crate::cli::ParseCommand;
-use crate::{analyzer, display, filters, parser};
-use anyhow::Result;
+use crate::display::OutputFormat;
+use crate::export::ExportFormat;
+use crate::follow::{self, FollowConfig};
+use crate::incident::correlate_incidents;
+use crate::intel::IntelFormat;
+use crate::mapping::FieldMapping;
+use crate::pipeline::{PipelineConfig, Source, run_pipeline};
+use crate::triage::{TriageFile, finding_id};
+use crate::{analyzer, archive, display, export, graph, index, intel, parser, sarif, timesketch};
+use anyhow::{Context, Result, anyhow, bail};
 use colored::*;
+use std::path::Path;
+use std::process::ExitCode;
 use tracing::info;
 
-pub fn execute_parse(cmd: ParseCommand) -> Result<()> {
+/// Exit code of `parse --fail-on` when a matching anomaly is found (1 is used for errors)
+const ANOMALIES_FOUND_EXIT_CODE: u8 = 2;
+
+pub fn execute_parse(cmd: ParseCommand) -> Result<ExitCode> {
     let ParseCommand {
-        file_path,
-        event_id,
-        search,
+        file_paths,
+        format: input_format,
+        mapping,
+        quiet,
+        follow,
+        recover,
+        dump_unparsed,
+        sample,
+        max_events,
+        filter,
         detect,
+        detection,
+        no_dedup,
+        no_incidents,
+        incident_window,
+        profile_rules,
+        output,
+        format_string,
+        fail_on,
+        context,
+        graph,
+        export,
+        export_file,
         after,
         before,
+        triage,
+        hide_triaged,
+        no_index,
     } = cmd;
-    println!("{}", "Security Log Analyzer".bright_cyan().bold());
-    println!(
-        "Analyzing file: {}\n",
-        file_path.to_string_lossy().bright_yellow()
-    );
-    let events = parser::parse_evtx_file(&file_path)?;
-    let filters = filters::EventFilter::new()
-        .with_event_ids(event_id)
-        .with_search_term(search)
-        .with_time_range(after, before);
-    let filtered_events = filters.apply(&events);
-    println!(
-        "Total events found: {} (filtered {})",
-        events.len().to_string().bright_green(),
-        filtered_events.len().to_string().bright_red()
-    );
-    let _anomalies = if detect {
+    if follow {
+        let [path] =
+            <[_; 1]>::try_from(file_paths).map_err(|_| anyhow!("--follow takes a single file"))?;
+        if archive::is_compressed(&path)? {
+            bail!("--follow needs an uncompressed file");
+        }
+        println!(
+            "{}",
+            "=== Security Log Analyzer - Follow ==="
+                .bright_cyan()
+                .bold()
+        );
+        println!("Following file: {}", path.to_string_lossy().bright_yellow());
+        println!("Press {} to exit\n", "Ctrl+C".bright_red());
+        follow::follow_file(FollowConfig {
+            path,
+            format: input_format,
+            filter: filter.into_filter().with_time_range(after, before),
+            detect,
+            detection: detection.into_config()?,
+        })?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(format) = export
+        && format.needs_detection()
+        && !detect
+    {
+        bail!("--export stix, misp and sarif need --detect");
+    }
+    if context.is_some() && output != OutputFormat::Json {
+        bail!("--context only applies to the JSON report, add --output json");
+    }
+    if recover && input_format != parser::InputFormat::Evtx {
+        bail!("--recover only applies to EVTX files");
+    }
+    // A format string shapes the output for scripts, so it is printed alone
+    let text = output == OutputFormat::Text && format_string.is_none();
+    if text {
+        println!("{}", "Security Log Analyzer".bright_cyan().bold());
+        for file_path in &file_paths {
+            println!(
+                "Analyzing file: {}",
+                file_path.to_string_lossy().bright_yellow()
+            );
+        }
+        println!();
+    }
+    let sampling = parser::Sampling {
+        every: sample.unwrap_or(1),
+        max_events,
+    };
+    let filters = filter.into_filter().with_time_range(after, before);
+    // Context needs the events around the findings, which the index may leave out
+    let indexed = if no_index
+        || recover
+        || input_format != parser::InputFormat::Evtx
+        || mapping.is_some()
+        || sampling.is_active()
+        || context.is_some()
+    {
+        None
+    } else {
+        index::parse_indexed(&file_paths, &filters)?
+    };
+    let total_events = indexed.as_ref().map(|parsed| parsed.total_events);
+    let mapping = mapping.map(|path| FieldMapping::load(&path)).transpose()?;
+    let source = if let Some(parsed) = indexed {
+        if text {
+            println!(
+                "Read {} of {} chunks using the index",
+                parsed.chunks_read.to_string().bright_green(),
+                parsed.total_chunks
+            );
+        }
+        Source::Parsed(parsed.report)
+    } else if recover {
+        Source::Parsed(parser::parse_evtx_files_recovering(
+            &file_paths,
+            !quiet,
+            dump_unparsed.as_deref(),
+        )?)
+    } else {
+        Source::Files {
+            paths: &file_paths,
+            format: input_format,
+            mapping: mapping.as_ref(),
+            show_progress: !quiet,
+            sampling,
+        }
+    };
+    let detection = if detect {
         info!("Running anomaly detection");
-        let detected = analyzer::detect_anomalies(&filtered_events);
-        if !detected.is_empty() {
-            println!("Anomalies detected:");
-            for anomaly in &detected {
+        Some((detection.into_config()?, profile_rules))
+    } else {
+        None
+    };
+    let prevalence_store = detection
+        .as_ref()
+        .and_then(|(config, _)| config.prevalence.clone());
+    let output_run = run_pipeline(
+        source,
+        PipelineConfig {
+            filter: &filters,
+            detection,
+            keep_all: context.is_some(),
+        },
+        |event| {
+            // Lines of a format string are printed as the events are read
+            if let Some(template) = &format_string
+                && output == OutputFormat::Text
+            {
+                println!("{}", template.render(event));
+            }
+        },
+    )?;
+    let report = output_run.report;
+    if text && sampling.is_active() {
+        let mut note = Vec::new();
+        if sampling.every > 1 {
+            note.push(format!(
+                "sampled 1/{} of the records ({} left out)",
+                sampling.every, report.sampled_out
+            ));
+        }
+        if report.truncated {
+            note.push(format!(
+                "stopped after {} events",
+                sampling.max_events.unwrap_or_default()
+            ));
+        }
+        if !note.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "Partial analysis: {}. Counts and rate-based detections (event storms, beaconing, mass deletion) are not representative of the whole log",
+                    note.join(", ")
+                )
+                .bright_yellow()
+            );
+        }
+    }
+    if text && let Some(stats) = report.recovery {
+        let lost = if stats.corrupt_chunks > 0 || stats.lost_records > 0 {
+            format!(
+                "{} damaged chunks skipped, {} records lost",
+                stats.corrupt_chunks, stats.lost_records
+            )
+            .bright_red()
+        } else {
+            "no damage found".bright_green()
+        };
+        println!(
+            "Recovered {} records from {} chunks: {}",
+            stats.recovered_records.to_string().bright_green(),
+            stats.chunks,
+            lost
+        );
+        if let Some(path) = &dump_unparsed {
+            println!(
+                "{} records that are not Sysmon events written to {}",
+                report.skipped_records,
+                path.to_string_lossy().bright_yellow()
+            );
+        }
+    }
+    if text && report.duplicates > 0 {
+        println!(
+            "Dropped {} duplicate records found in more than one file",
+            report.duplicates.to_string().bright_yellow()
+        );
+    }
+    let recovery = report.recovery;
+    let (sampled_out, truncated) = (report.sampled_out, report.truncated);
+    let left_out = report.events;
+    let total_events = total_events.unwrap_or(output_run.total_events);
+    let filtered_events = output_run.filtered_events;
+    prevalence::record_run(prevalence_store.as_deref(), &filtered_events);
+    if text {
+        println!(
+            "Total events found: {} (filtered {})",
+            total_events.to_string().bright_green(),
+            filtered_events.len().to_string().bright_red()
+        );
+    }
+    let (anomalies, rule_profile) = (output_run.anomalies, output_run.rule_profile);
+    let triage = triage.map(|path| TriageFile::load(&path)).transpose()?;
+    let mut anomalies = anomalies;
+    if hide_triaged && let Some(triage) = &triage {
+        let before = anomalies.len();
+        anomalies.retain(|anomaly| !triage.is_closed(anomaly));
+        let hidden = before - anomalies.len();
+        if text && hidden > 0 {
+            println!(
+                "Hid {} findings already triaged as false or true positives",
+                hidden.to_string().bright_yellow()
+            );
+        }
+    }
+    let groups = group_anomalies(&anomalies, no_dedup);
+    let incidents =
+        (!no_incidents).then(|| correlate_incidents(&filtered_events, &anomalies, incident_window));
+    if text && !anomalies.is_empty() {
+        println!("Anomalies detected:");
+        if let Some(incidents) = incidents.as_ref().filter(|_| !no_dedup) {
+            display::display_incidents(incidents, triage.as_ref());
+        } else if no_dedup {
+            for anomaly in &anomalies {
                 println!(
-                    "{}: {}",
+                    "{} [{}]: {}{} {}",
                     anomaly.severity().to_string().bright_red(),
-                    anomaly.description()
+                    anomaly.rule_id(),
+                    anomaly.description(),
+                    display::asset_role(anomaly),
+                    format!("#{}", finding_id(anomaly)).bright_black()
                 );
             }
+        } else {
+            display::display_aggregated_anomalies(&groups, triage.as_ref());
+        }
+    }
+    if let Some(graph_path) = graph {
+        let dot = graph::process_graph_dot(&filtered_events, &anomalies);
+        std::fs::write(&graph_path, dot)
+            .with_context(|| format!("Failed to write graph to {}", graph_path.display()))?;
+        info!("Process graph written to {}", graph_path.display());
+        if text {
+            println!(
+                "Process graph written to {}",
+                graph_path.to_string_lossy().bright_yellow()
+            );
+        }
+    }
+    if let (Some(format), Some(export_path)) = (export, export_file) {
+        let what = match format {
+            ExportFormat::Timesketch => {
+                let rows = timesketch::write_timeline(&export_path, &filtered_events, &anomalies)?;
+                format!("Timesketch timeline of {rows} rows")
+            }
+            ExportFormat::Stix => write_export(
+                &export_path,
+                &intel::export_anomalies(&anomalies, IntelFormat::Stix),
+                "Threat intel",
+            )?,
+            ExportFormat::Misp => write_export(
+                &export_path,
+                &intel::export_anomalies(&anomalies, IntelFormat::Misp),
+                "Threat intel",
+            )?,
+            ExportFormat::Sarif => {
+                let files: Vec<String> = file_paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect();
+                write_export(
+                    &export_path,
+                    &sarif::sarif_log(&anomalies, &files),
+                    "SARIF log",
+                )?
+            }
+        };
+        info!("{what} written to {}", export_path.display());
+        if text {
+            println!(
+                "{what} written to {}",
+                export_path.to_string_lossy().bright_yellow()
+            );
+        }
+    }
+    match output {
+        // Already printed while parsing
+        OutputFormat::Text if format_string.is_some() => {}
+        OutputFormat::Text => {
+            display::display_events(&filtered_events);
+            if !anomalies.is_empty() {
+                println!();
+                display::display_host_risk(&analyzer::host_risk_summary(&anomalies));
+                println!();
+                display::display_tactic_summary(&tactic_summary(&groups));
+            }
+            if let Some(profile) = &rule_profile {
+                println!();
+                display::display_rule_profile(profile);
+            }
+        }
+        OutputFormat::Json => {
+            let files: Vec<String> = file_paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            let context = context.map(|size| {
+                export::EventContext::new(filtered_events.iter().chain(&left_out), size)
+            });
+            let mut report = export::anomaly_report(
+                &files,
+                total_events,
+                filtered_events.len(),
+                &groups,
+                context.as_ref(),
+            );
+            if let Some(triage) = &triage
+                && let Some(entries) = report["anomalies"].as_array_mut()
+            {
+                for (entry, group) in entries.iter_mut().zip(&groups) {
+                    entry["triage"] =
+                        triage
+                            .get(&group.anomaly)
+                            .map_or(serde_json::Value::Null, |verdict| {
+                                serde_json::json!({
+                                    "status": verdict.status,
+                                    "note": verdict.note,
+                                    "updated": verdict.updated,
+                                })
+                            });
+                }
+            }
+            if let Some(incidents) = &incidents {
+                report["incidents"] = export::incidents_to_json(incidents);
+            }
+            if let Some(stats) = &recovery {
+                report["recovery"] = serde_json::json!({
+                    "chunks": stats.chunks,
+                    "corrupt_chunks": stats.corrupt_chunks,
+                    "recovered_records": stats.recovered_records,
+                    "lost_records": stats.lost_records,
+                });
+            }
+            if sampling.is_active() {
+                report["sampling"] = serde_json::json!({
+                    "every": sampling.every,
+                    "max_events": sampling.max_events,
+                    "records_skipped": sampled_out,
+                    "truncated": truncated,
+                });
+            }
+            if let Some(profile) = &rule_profile {
+                report["rule_profile"] = export::rule_profile_to_json(profile);
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
         }
-        detected
+    }
+    let failed = fail_on.is_some_and(|threshold| {
+        anomalies
+            .iter()
+            .any(|anomaly| anomaly.severity() >= threshold)
+    });
+    Ok(if failed {
+        ExitCode::from(ANOMALIES_FOUND_EXIT_CODE)
     } else {
-        Vec::new()
-    };
-    display::display_events(&filtered_events);
-    Ok(())
+        ExitCode::SUCCESS
+    })
+}
+
+/// Write a JSON export to its file, returning what it is for the summary line
+fn write_export(path: &Path, document: &serde_json::Value, what: &str) -> Result<String> {
+    std::fs::write(path, serde_json::to_string_pretty(document)?)
+        .with_context(|| format!("Failed to write export to {}", path.display()))?;
+    Ok(what.to_string())
+}
+
+/// Anomalies grouped for reporting, or one group per anomaly with `--no-dedup`
+fn group_anomalies(anomalies: &[Anomaly], no_dedup: bool) -> Vec<AggregatedAnomaly> {
+    if !no_dedup {
+        return analyzer::aggregate_anomalies(anomalies);
+    }
+    anomalies
+        .iter()
+        .map(|anomaly| AggregatedAnomaly {
+            anomaly: anomaly.clone(),
+            count: 1,
+            first_seen: anomaly.timestamp(),
+            last_seen: anomaly.end_timestamp(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::AnomalyKind;
+
+    #[test]
+    fn no_dedup_event_storm_last_seen_is_end_of_burst() {
+        let start = "2025-01-01T10:00:00Z".parse().unwrap();
+        let end = "2025-01-01T10:00:04Z".parse().unwrap();
+        let storm: Anomaly = AnomalyKind::EventStorm {
+            event_id: 22,
+            count: 5,
+            time_window_seconds: 10,
+            start: Some(start),
+            end: Some(end),
+            top_processes: vec![("chrome.exe".to_string(), 5)],
+        }
+        .into();
+        let groups = group_anomalies(&[storm], true);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].first_seen, Some(start));
+        assert_eq!(groups[0].last_seen, Some(end));
+    }
 }
@@ -0,0 +1,21 @@
+use crate::cli::ProfileCommand;
+use crate::filters::EventFilter;
+use crate::{display, parser, profile};
+use anyhow::Result;
+use colored::*;
+
+pub fn execute_profile(cmd: ProfileCommand) -> Result<()> {
+    let selector = cmd.selector();
+    println!(
+        "Profiling {} in {}\n",
+        selector.to_string().bright_cyan(),
+        cmd.file_path.to_string_lossy().bright_yellow()
+    );
+    let events = parser::parse_evtx_file(&cmd.file_path)?;
+    let filtered_events = EventFilter::new()
+        .with_time_range(cmd.after, cmd.before)
+        .apply(&events);
+    let profile = profile::build_profile(&filtered_events, &selector, cmd.children);
+    display::display_profile(&profile);
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use crate::analyzer::{self, Anomaly, DetectionConfig, RULE_IDS};
+use crate::cli::{DetectionArgs, RulesAction, RulesCommand};
+use crate::helpers::HasSystem;
+use crate::parser::InputFormat;
+use crate::{archive, display, parser};
+use anyhow::{Context, Result, anyhow};
+use colored::*;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub fn execute_rules(cmd: RulesCommand) -> Result<()> {
+    match cmd.action {
+        RulesAction::List => {
+            for (id, name) in RULE_IDS {
+                println!("{id}  {name}");
+            }
+            Ok(())
+        }
+        RulesAction::Test {
+            rule,
+            events,
+            detection,
+        } => test_rule(&rule, &events, *detection),
+    }
+}
+
+/// Outcome of `rules test`
+struct RuleTest {
+    rule: &'static str,
+    events: usize,
+    matches: Vec<Anomaly>,
+    elapsed: Duration,
+}
+
+/// Evaluate the rule alone over the sample events, with the settings and config file given
+fn run_rule(rule: &str, events_path: &Path, detection: DetectionArgs) -> Result<RuleTest> {
+    let rule = analyzer::rule_name_of(rule).ok_or_else(|| {
+        anyhow!(
+            "Unknown rule '{rule}'. Available rules: {}",
+            analyzer::RULE_NAMES.join(", ")
+        )
+    })?;
+    let config = DetectionConfig {
+        only_rule: Some(rule),
+        ..detection
+            .into_config()
+            .context("Failed to load the rules")?
+    };
+    let format = if archive::is_log_file(&events_path.to_string_lossy(), InputFormat::Jsonl) {
+        InputFormat::Jsonl
+    } else {
+        InputFormat::Evtx
+    };
+    let events = parser::parse_files(&[events_path.to_path_buf()], format, false)?.events;
+
+    let started = Instant::now();
+    let matches = analyzer::detect_anomalies_with_config(&events, &config);
+    Ok(RuleTest {
+        rule,
+        events: events.len(),
+        matches,
+        elapsed: started.elapsed(),
+    })
+}
+
+fn test_rule(rule: &str, events_path: &Path, detection: DetectionArgs) -> Result<()> {
+    let test = run_rule(rule, events_path, detection)?;
+    println!(
+        "{} {}",
+        "Testing rule".bright_cyan().bold(),
+        test.rule.bright_yellow()
+    );
+    for anomaly in &test.matches {
+        match anomaly.event() {
+            Some(event) => println!(
+                "  [{}] record {} at {}: {}",
+                anomaly.severity(),
+                event.system().event_record_id.event_record_id,
+                display::format_timestamp(&event.system().time_created),
+                anomaly.description()
+            ),
+            None => println!("  [{}] {}", anomaly.severity(), anomaly.description()),
+        }
+    }
+    println!(
+        "{} matches in {} events (evaluated in {:.2?})",
+        test.matches.len().to_string().bright_green(),
+        test.events,
+        test.elapsed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use crate::fixtures::json_process_line;
+    use clap::Parser;
+    use serde_json::json;
+
+    fn run(args: &[&str]) -> Result<RuleTest> {
+        let argv = [&["sla", "rules", "test"], args].concat();
+        let Commands::Rules(RulesCommand {
+            action:
+                RulesAction::Test {
+                    rule,
+                    events,
+                    detection,
+                },
+        }) = Cli::try_parse_from(argv).unwrap().command
+        else {
+            panic!("expected rules test command");
+        };
+        run_rule(&rule, &events, *detection)
+    }
+
+    #[test]
+    fn rule_tested_alone_on_json_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = dir.path().join("events.jsonl");
+        // Word starting an encoded PowerShell command matches two rules
+        let line = json_process_line(json!({
+            "Image": r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+            "CommandLine": "powershell.exe -enc SQBFAFgA",
+        }));
+        std::fs::write(&events, line).unwrap();
+        let events = events.to_str().unwrap();
+
+        let test = run(&["SLA-008", "--events", events]).unwrap();
+        assert_eq!(test.rule, "SuspiciousCommandLine");
+        assert_eq!(test.events, 1);
+        let rules: Vec<_> = test
+            .matches
+            .iter()
+            .map(|anomaly| anomaly.rule_name())
+            .collect();
+        assert_eq!(rules, ["SuspiciousCommandLine"]);
+
+        assert!(run(&["NoSuchRule", "--events", events]).is_err());
+        let config = dir.path().join("rules.yaml");
+        std::fs::write(
+            &config,
+            "parent_child_rules:\n  - parent: winword.exe\n    kid: x\n",
+        )
+        .unwrap();
+        // Rule syntax errors are reported rather than running without the rule
+        let config = config.to_str().unwrap();
+        let Err(error) = run(&["SLA-002", "--events", events, "--config", config]) else {
+            panic!("expected an invalid rule");
+        };
+        assert!(format!("{error:#}").contains("unknown field `kid`"));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::cli::ServeCommand;
+use crate::mapping::FieldMapping;
+use crate::server::{ApiServer, ServerConfig};
+use anyhow::{Result, bail};
+use colored::*;
+use std::sync::Arc;
+
+pub fn execute_serve(cmd: ServeCommand) -> Result<()> {
+    if cmd.threads == 0 {
+        bail!("--threads must be at least 1");
+    }
+    let server = Arc::new(ApiServer::bind(ServerConfig {
+        listen: cmd.listen,
+        token: cmd.token,
+        max_upload: cmd.max_upload * 1024 * 1024,
+        threads: cmd.threads,
+        mapping: cmd
+            .mapping
+            .map(|path| FieldMapping::load(&path))
+            .transpose()?,
+        filter: cmd.filter.into_filter(),
+        detection: cmd.detection.into_config()?,
+        incident_window: cmd.incident_window,
+    })?);
+    let stopping = server.clone();
+    ctrlc::set_handler(move || {
+        println!(
+            "\n{}",
+            "Received stop signal... shutting down after the current requests.".bright_yellow()
+        );
+        stopping.stop();
+    })?;
+    println!(
+        "{}",
+        "=== Security Log Analyzer - API ===".bright_cyan().bold()
+    );
+    if let Some(addr) = server.local_addr() {
+        println!("Listening on {}", format!("http://{addr}").bright_yellow());
+    }
+    println!("Press {} to exit\n", "Ctrl+C".bright_red());
+    server.serve();
+    Ok(())
+}
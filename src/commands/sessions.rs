@@ -0,0 +1,45 @@
+use crate::analyzer::prevalence;
+use crate::cli::SessionsCommand;
+use crate::{analyzer, display, parser, session};
+use anyhow::{Result, anyhow};
+use colored::*;
+
+pub fn execute_sessions(cmd: SessionsCommand) -> Result<()> {
+    let SessionsCommand {
+        file_path,
+        session,
+        filter,
+        after,
+        before,
+        detection,
+    } = cmd;
+    println!(
+        "Reconstructing logon sessions in {}\n",
+        file_path.to_string_lossy().bright_yellow()
+    );
+    let events = parser::parse_evtx_file(&file_path)?;
+    let filtered_events = filter
+        .into_filter()
+        .with_time_range(after, before)
+        .apply(&events);
+    let config = detection.into_config()?;
+    let anomalies = analyzer::detect_anomalies_with_config(&filtered_events, &config);
+    prevalence::record_run(config.prevalence.as_deref(), &filtered_events);
+    let report = session::build_sessions(&filtered_events, &anomalies);
+    let Some(logon_id) = session else {
+        display::display_sessions(&report);
+        return Ok(());
+    };
+    // Sessions of other computers are already filtered out by --computer
+    match report.find(logon_id, None)[..] {
+        [] => Err(anyhow!("No session with logon ID {logon_id:#x}")),
+        [session] => {
+            display::display_session(session);
+            Ok(())
+        }
+        ref sessions => Err(anyhow!(
+            "Logon ID {logon_id:#x} was used on {} computers, select one with --computer",
+            sessions.len()
+        )),
+    }
+}
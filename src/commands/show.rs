@@ -0,0 +1,30 @@
+use crate::cli::ShowCommand;
+use crate::event_detail::{EventDetail, select_events};
+use crate::{display, parser};
+use anyhow::{Result, bail};
+use serde_json::Value;
+
+pub fn execute_show(cmd: ShowCommand) -> Result<()> {
+    let selector = cmd.selector();
+    let report = parser::parse_files(&cmd.file_paths, cmd.format, false)?;
+    let selected = select_events(&report.events, selector, cmd.computer.as_deref());
+    if selected.is_empty() {
+        bail!("No event matches {selector}");
+    }
+    let details: Vec<_> = selected
+        .into_iter()
+        .map(|(index, event)| EventDetail::new(index, event))
+        .collect();
+    if cmd.json {
+        let json: Vec<Value> = details.iter().map(EventDetail::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    for (i, detail) in details.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        display::display_event_detail(detail);
+    }
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use crate::analyzer::prevalence::PrevalenceStore;
+use crate::cli::StatsCommand;
+use crate::stats::{DnsReport, top_talkers};
+use crate::{display, parser};
+use anyhow::Result;
+
+pub fn execute_stats(cmd: StatsCommand) -> Result<()> {
+    let report = parser::parse_files(&cmd.file_paths, cmd.format, false)?;
+    let events = cmd.filter.into_filter().apply(&report.events);
+    if !cmd.dns {
+        display::display_talkers(&top_talkers(&events), cmd.top);
+        return Ok(());
+    }
+    let mut dns = DnsReport::new(&events);
+    let mut baseline = None;
+    if let Some(path) = &cmd.prevalence {
+        let store = PrevalenceStore::update(path, |store| dns.check_prevalence(store))?;
+        baseline = Some(store.domain_runs);
+    }
+    display::display_dns_report(&dns, cmd.top, baseline);
+    Ok(())
+}
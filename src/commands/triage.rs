@@ -0,0 +1,40 @@
+use crate::cli::TriageCommand;
+use crate::display;
+use crate::triage::TriageFile;
+use anyhow::{Result, bail};
+use colored::*;
+
+pub fn execute_triage(cmd: TriageCommand) -> Result<()> {
+    let TriageCommand {
+        file,
+        ids,
+        status,
+        note,
+        clear,
+    } = cmd;
+    let mut triage = TriageFile::load(&file)?;
+    if ids.is_empty() {
+        display::display_triage(&triage);
+        return Ok(());
+    }
+    if clear {
+        for id in &ids {
+            if !triage.remove(id) {
+                println!("{}", format!("No verdict recorded for {id}").yellow());
+            }
+        }
+    } else if let Some(status) = status {
+        let now = chrono::Utc::now();
+        for id in &ids {
+            triage.set(id, status, note.clone(), now);
+        }
+        println!(
+            "Marked {} findings as {}",
+            ids.len().to_string().bright_green(),
+            status.to_string().bright_cyan()
+        );
+    } else {
+        bail!("Give a verdict with --status, or --clear to forget one");
+    }
+    triage.save(&file)
+}
@@ -0,0 +1,31 @@
+use crate::analyzer::prevalence;
+use crate::cli::TuiCommand;
+use crate::{analyzer, parser, tui};
+use anyhow::Result;
+use tracing::info;
+
+pub fn execute_tui(cmd: TuiCommand) -> Result<()> {
+    let TuiCommand {
+        file_path,
+        filter,
+        after,
+        before,
+        detect,
+        detection,
+    } = cmd;
+    let events = parser::parse_evtx_file(&file_path)?;
+    let filtered_events = filter
+        .into_filter()
+        .with_time_range(after, before)
+        .apply(&events);
+    let anomalies = if detect {
+        info!("Running anomaly detection");
+        let config = detection.into_config()?;
+        let detected = analyzer::detect_anomalies_with_config(&filtered_events, &config);
+        prevalence::record_run(config.prevalence.as_deref(), &filtered_events);
+        analyzer::aggregate_anomalies(&detected)
+    } else {
+        Vec::new()
+    };
+    tui::run(filtered_events, anomalies)
+}
@@ -1,29 +1,94 @@
 #![cfg(windows)]
 use crate::cli::WatchCommand;
-use crate::sysmon::Event as SysmonEvent;
-use crate::{filters, live_monitor};
-use anyhow::Result;
+use crate::config_reload::ConfigReload;
+use crate::forward::Forwarder;
+use crate::live_monitor::{self, MonitorConfig};
+use crate::live_output::EventPrinting;
+use crate::{display, export};
+use anyhow::{Context, Result};
 use colored::Colorize;
 
 #[cfg(windows)]
 pub(crate) fn execute_watch(cmd: WatchCommand) -> Result<()> {
     let WatchCommand {
-        event_id,
-        search,
+        filter,
         detect,
+        detection,
+        since,
+        buffer_size,
+        context_window,
+        bookmark,
+        state,
+        forward,
+        syslog_ca,
+        forward_anomalies_only,
+        notify,
+        quiet,
+        print_every,
+        stats_interval,
+        remote,
+        report,
+        reload,
     } = cmd;
+    let forward = match (forward, syslog_ca) {
+        (Some(forward), Some(ca)) => Some(forward.with_syslog_ca(ca)?),
+        (forward, _) => forward,
+    };
+    let forwarder = forward.map(Forwarder::connect).transpose()?;
+    // The config file filter comes on top of the command line filters
+    let base_filter = filter.into_filter();
+    let (config, config_filter) = detection.load()?;
+    let filter = base_filter.clone().and_query(config_filter);
+    let reload = match (&detection.config, reload) {
+        (Some(path), true) => Some(ConfigReload::new(path.clone(), move || {
+            let (config, config_filter) = detection.load()?;
+            Ok((base_filter.clone().and_query(config_filter), config))
+        })),
+        _ => None,
+    };
+    let remote = remote.into_remote()?;
     println!(
         "{}",
         "=== Security Log Analyzer - Live Monitor ==="
             .bright_cyan()
             .bold()
     );
-    println!("Monitoring Sysmon events in real-time...\n");
+    match &remote {
+        Some(remote) => println!(
+            "Monitoring Sysmon events of {} in real-time...\n",
+            remote.host.bright_yellow()
+        ),
+        None => println!("Monitoring Sysmon events in real-time...\n"),
+    }
     println!("Press {} to exit\n", "Ctrl+C".bright_red());
 
-    let filter = filters::EventFilter::new()
-        .with_event_ids(event_id)
-        .with_search_term(search);
-    let _captured_events: Vec<SysmonEvent> = live_monitor::start_monitoring(filter, detect)?;
+    let outcome = live_monitor::start_monitoring(MonitorConfig {
+        filter,
+        detect,
+        detection: config,
+        since,
+        buffer_size,
+        context_window,
+        bookmark,
+        state,
+        forwarder,
+        forward_events: !forward_anomalies_only,
+        notify,
+        printing: EventPrinting::new(quiet, print_every),
+        stats_interval: stats_interval.to_std().ok(),
+        remote,
+        reload,
+    })?;
+    println!();
+    display::display_session_summary(&outcome.summary);
+    if let Some(path) = report {
+        let document = export::session_report(&outcome.summary, &outcome.events);
+        std::fs::write(&path, serde_json::to_string_pretty(&document)?)
+            .with_context(|| format!("Failed to write session report to {}", path.display()))?;
+        println!(
+            "Session report written to {}",
+            path.to_string_lossy().bright_yellow()
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,109 @@
+//! Reload of the settings of live monitoring when their config file changes, so filters and
+//! rules can be edited without restarting the monitor and losing its subscription and context
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Time between two checks of the config file
+pub const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Modification time and size of a file, `None` while it is missing
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Watches a config file and loads the settings built from it again when it changes
+pub struct ConfigReload<T> {
+    path: PathBuf,
+    load: Box<dyn FnMut() -> Result<T>>,
+    stamp: Option<(SystemTime, u64)>,
+    checked: Option<Instant>,
+}
+
+impl<T> ConfigReload<T> {
+    /// Watch `path`, whose current content the settings in use were loaded from
+    pub fn new(path: PathBuf, load: impl FnMut() -> Result<T> + 'static) -> Self {
+        Self {
+            stamp: stamp(&path),
+            path,
+            load: Box::new(load),
+            checked: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// New settings if the file changed since the last check, at most every
+    /// [`RELOAD_CHECK_INTERVAL`]. Settings that fail to load are reported and skipped, the
+    /// previous ones stay in use until the file is fixed.
+    pub fn poll(&mut self, now: Instant) -> Option<T> {
+        if self
+            .checked
+            .is_some_and(|checked| now.duration_since(checked) < RELOAD_CHECK_INTERVAL)
+        {
+            return None;
+        }
+        self.checked = Some(now);
+        let stamp = stamp(&self.path);
+        // A missing file is usually an editor replacing it, wait for the new one
+        if stamp.is_none() || stamp == self.stamp {
+            return None;
+        }
+        self.stamp = stamp;
+        debug!("Config file {} changed", self.path.display());
+        match (self.load)() {
+            Ok(settings) => {
+                info!("Reloaded config file {}", self.path.display());
+                Some(settings)
+            }
+            Err(e) => {
+                warn!(
+                    "Keeping the previous settings, failed to reload {}: {:#}",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::config_file::ConfigFile;
+
+    #[test]
+    fn reload_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "filter: event_id == 1\n").unwrap();
+        let source = path.clone();
+        let mut reload = ConfigReload::new(path.clone(), move || {
+            Ok(ConfigFile::load(&source)?.suppressions.len())
+        });
+        let start = Instant::now();
+        assert_eq!(reload.poll(start), None);
+
+        let suppressions = "suppressions:\n  - rule: SLA-003\n  - rule: UnusualPort\n";
+        fs::write(&path, suppressions).unwrap();
+        // Not checked again before the interval
+        assert_eq!(reload.poll(start), None);
+        let later = start + RELOAD_CHECK_INTERVAL;
+        assert_eq!(reload.poll(later), Some(2));
+        assert_eq!(reload.poll(later + RELOAD_CHECK_INTERVAL), None);
+
+        // Invalid content keeps the previous settings, fixing it applies the new ones
+        fs::write(&path, "filter: event_id ==\n").unwrap();
+        let later = later + RELOAD_CHECK_INTERVAL * 2;
+        assert_eq!(reload.poll(later), None);
+        fs::write(&path, "suppressions: [{rule: SLA-003}]\n").unwrap();
+        assert_eq!(reload.poll(later + RELOAD_CHECK_INTERVAL), Some(1));
+        assert_eq!(reload.path(), path);
+    }
+}
@@ -0,0 +1,400 @@
+//! Daemon mode: periodically analyze the log files dropped in a directory, write a JSON report
+//! for each and move them out of the way, for unattended pipelines without an external scheduler
+use crate::analyzer::{self, DetectionConfig, prevalence};
+use crate::archive;
+use crate::export;
+use crate::filters::EventFilter;
+use crate::incident::correlate_incidents;
+use crate::mapping::FieldMapping;
+use crate::parser::{self, InputFormat, Sampling};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Local};
+use colored::Colorize;
+use croner::Cron;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+pub struct DaemonConfig {
+    /// Directory scanned for new log files
+    pub input: PathBuf,
+    /// Directory the JSON reports are written to
+    pub output: PathBuf,
+    /// Directory analyzed files are moved to
+    pub processed: PathBuf,
+    /// Directory files that could not be analyzed are moved to, with the error
+    pub failed: PathBuf,
+    /// When the input directory is scanned
+    pub schedule: Schedule,
+    /// Files modified more recently than this may still be written and wait for the next scan
+    pub settle: Duration,
+    /// Format of the logs in ZIP archives; other files are recognized by their extension
+    pub archive_format: InputFormat,
+    /// Column mapping of CSV files (which are skipped without one) and JSON exports of other tools
+    pub mapping: Option<FieldMapping>,
+    pub filter: EventFilter,
+    pub detection: DetectionConfig,
+    pub incident_window: chrono::Duration,
+}
+
+/// When the input directory is scanned
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// At a fixed time after the start of the previous scan
+    Every(Duration),
+    /// At the times matching a cron pattern, in local time
+    Cron(Box<Cron>),
+}
+
+impl Schedule {
+    /// Time of the scan after one started at `time`
+    pub fn next_scan(&self, time: DateTime<Local>) -> Result<DateTime<Local>> {
+        match self {
+            Schedule::Every(interval) => Ok(time + *interval),
+            Schedule::Cron(cron) => cron
+                .find_next_occurrence(&time, false)
+                .map_err(|e| anyhow!("No next scan for '{}': {e}", cron.pattern)),
+        }
+    }
+}
+
+/// Result of the analysis of one file
+#[derive(Debug)]
+pub struct FileOutcome {
+    pub file: PathBuf,
+    pub result: Result<AnalyzedFile>,
+}
+
+#[derive(Debug)]
+pub struct AnalyzedFile {
+    pub report: PathBuf,
+    pub events: usize,
+    pub anomalies: usize,
+    pub incidents: usize,
+}
+
+/// Scan the input directory every interval until Ctrl+C, or once when `once` is set
+pub fn run(config: DaemonConfig, once: bool) -> Result<()> {
+    for dir in [&config.output, &config.processed, &config.failed] {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    }
+    let running = Arc::new(AtomicBool::new(true));
+    if !once {
+        let r = running.clone();
+        ctrlc::set_handler(move || {
+            println!(
+                "\n{}",
+                "Received stop signal... shutting down after the current file.".bright_yellow()
+            );
+            r.store(false, Ordering::SeqCst);
+        })?;
+    }
+    while running.load(Ordering::SeqCst) {
+        let started = Local::now();
+        // The input directory may be unreachable for a moment, e.g. on a network share
+        match scan(&config, &running) {
+            Ok(outcomes) => outcomes.iter().for_each(print_outcome),
+            Err(e) => warn!("Failed to scan {}: {:#}", config.input.display(), e),
+        }
+        if once {
+            break;
+        }
+        let next_scan = config.schedule.next_scan(started)?;
+        while running.load(Ordering::SeqCst) {
+            let Ok(remaining) = (next_scan - Local::now()).to_std() else {
+                break;
+            };
+            std::thread::sleep(remaining.min(Duration::from_secs(1)));
+        }
+    }
+    Ok(())
+}
+
+/// Analyze the log files of the input directory that are no longer written, oldest first, and
+/// move each to the processed or failed directory. A file that cannot be moved is left for the
+/// next scan.
+pub fn scan(config: &DaemonConfig, running: &AtomicBool) -> Result<Vec<FileOutcome>> {
+    let mut outcomes = Vec::new();
+    for (file, format) in pending_files(config)? {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        info!("Analyzing {}", file.display());
+        let result = analyze_file(config, &file, format);
+        let target = match &result {
+            Ok(_) => &config.processed,
+            Err(e) => {
+                warn!("Failed to analyze {}: {:#}", file.display(), e);
+                &config.failed
+            }
+        };
+        let name = file_name(&file);
+        let moved = unique_path(target, &name, "");
+        if let Err(e) = move_file(&file, &moved) {
+            warn!("{:#}", e);
+            continue;
+        }
+        if let Err(e) = &result {
+            let error_file = moved.with_file_name(format!("{}.error.txt", file_name(&moved)));
+            if let Err(write_error) = fs::write(&error_file, format!("{e:#}\n")) {
+                warn!("Failed to write {}: {}", error_file.display(), write_error);
+            }
+        }
+        outcomes.push(FileOutcome { file, result });
+    }
+    Ok(outcomes)
+}
+
+/// Files of the input directory in a supported format, not modified for `settle`, oldest first
+fn pending_files(config: &DaemonConfig) -> Result<Vec<(PathBuf, InputFormat)>> {
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    let entries = fs::read_dir(&config.input)
+        .with_context(|| format!("Failed to read directory {}", config.input.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !metadata.is_file() || name.starts_with('.') {
+            continue;
+        }
+        let Some(format) = file_format(&name, config.archive_format, config.mapping.is_some())
+        else {
+            continue;
+        };
+        let modified = metadata.modified()?;
+        if now.duration_since(modified).unwrap_or_default() < config.settle {
+            continue;
+        }
+        files.push((modified, entry.path(), format));
+    }
+    files.sort_by(|(a, a_path, _), (b, b_path, _)| a.cmp(b).then(a_path.cmp(b_path)));
+    Ok(files
+        .into_iter()
+        .map(|(_, path, format)| (path, format))
+        .collect())
+}
+
+/// Format of a file by its extension (`.gz` files by the extension before it); CSV files need a
+/// column mapping
+fn file_format(name: &str, archive_format: InputFormat, has_mapping: bool) -> Option<InputFormat> {
+    if name.to_ascii_lowercase().ends_with(".zip") {
+        return Some(archive_format);
+    }
+    [InputFormat::Evtx, InputFormat::Jsonl, InputFormat::Csv]
+        .into_iter()
+        .filter(|format| *format != InputFormat::Csv || has_mapping)
+        .find(|format| archive::is_log_file(name, *format))
+}
+
+fn analyze_file(config: &DaemonConfig, file: &Path, format: InputFormat) -> Result<AnalyzedFile> {
+    let report = file_report(
+        file,
+        &file.to_string_lossy(),
+        format,
+        config.mapping.as_ref(),
+        &config.filter,
+        &config.detection,
+        config.incident_window,
+    )?;
+    let path = unique_path(&config.output, &file_name(file), ".json");
+    fs::write(&path, serde_json::to_string_pretty(&report.json)?)
+        .with_context(|| format!("Failed to write report {}", path.display()))?;
+    Ok(AnalyzedFile {
+        report: path,
+        events: report.events,
+        anomalies: report.anomalies,
+        incidents: report.incidents,
+    })
+}
+
+/// Anomaly report of a log file with its incidents, and its counts
+pub struct FileReport {
+    pub json: Value,
+    /// Events left by the filter
+    pub events: usize,
+    /// Findings after grouping identical ones
+    pub anomalies: usize,
+    pub incidents: usize,
+}
+
+/// Parse a log file, run detection on the events the filter keeps and build the anomaly report
+/// with incidents, listing the file as `name`
+pub fn file_report(
+    file: &Path,
+    name: &str,
+    format: InputFormat,
+    mapping: Option<&FieldMapping>,
+    filter: &EventFilter,
+    detection: &DetectionConfig,
+    incident_window: chrono::Duration,
+) -> Result<FileReport> {
+    let parsed = parser::parse_files_sampled(
+        &[file.to_path_buf()],
+        format,
+        mapping,
+        false,
+        Sampling::default(),
+    )?;
+    let events = filter.apply(&parsed.events);
+    let anomalies = analyzer::detect_anomalies_with_config(&events, detection);
+    prevalence::record_run(detection.prevalence.as_deref(), &events);
+    let groups = analyzer::aggregate_anomalies(&anomalies);
+    let incidents = correlate_incidents(&events, &anomalies, incident_window);
+    let mut json = export::anomaly_report(
+        &[name.to_string()],
+        parsed.events.len(),
+        events.len(),
+        &groups,
+        None,
+    );
+    json["incidents"] = export::incidents_to_json(&incidents);
+    Ok(FileReport {
+        json,
+        events: events.len(),
+        anomalies: groups.len(),
+        incidents: incidents.len(),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `dir/name{suffix}`, or `dir/name.N{suffix}` with the first free N when it exists
+fn unique_path(dir: &Path, name: &str, suffix: &str) -> PathBuf {
+    let path = dir.join(format!("{name}{suffix}"));
+    if !path.exists() {
+        return path;
+    }
+    (1..)
+        .map(|n| dir.join(format!("{name}.{n}{suffix}")))
+        .find(|path| !path.exists())
+        .expect("a free file name")
+}
+
+/// Rename, or copy and delete when the target is on another file system
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .and_then(|_| fs::remove_file(from))
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+fn print_outcome(outcome: &FileOutcome) {
+    let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let file = outcome.file.to_string_lossy();
+    match &outcome.result {
+        Ok(analyzed) => println!(
+            "[{}] {}: {} events, {} findings in {} incidents -> {}",
+            time,
+            file.bright_yellow(),
+            analyzed.events,
+            if analyzed.anomalies > 0 {
+                analyzed.anomalies.to_string().bright_red()
+            } else {
+                analyzed.anomalies.to_string().bright_green()
+            },
+            analyzed.incidents,
+            analyzed.report.to_string_lossy()
+        ),
+        Err(e) => println!(
+            "[{}] {}: {} {:#}",
+            time,
+            file.bright_yellow(),
+            "failed:".bright_red(),
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_line;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn scan_writes_reports_and_moves_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("incoming");
+        fs::create_dir(&input).unwrap();
+        let line = json_process_line(json!({}));
+        fs::write(input.join("ws1.jsonl"), format!("{line}\n")).unwrap();
+        fs::write(input.join("broken.evtx"), b"not an event log").unwrap();
+        fs::write(input.join("notes.txt"), b"ignored").unwrap();
+        fs::write(input.join("export.csv"), b"ignored without a mapping").unwrap();
+        let config = |settle| DaemonConfig {
+            processed: input.join("processed"),
+            failed: input.join("failed"),
+            output: dir.path().join("reports"),
+            input: input.clone(),
+            schedule: Schedule::Every(Duration::from_secs(60)),
+            settle,
+            archive_format: InputFormat::Evtx,
+            mapping: None,
+            filter: EventFilter::new(),
+            detection: DetectionConfig::default(),
+            incident_window: chrono::Duration::minutes(2),
+        };
+        run(config(Duration::ZERO), true).unwrap();
+
+        let report: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("reports/ws1.jsonl.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(report["total_events"], 1);
+        assert_eq!(report["anomalies"][0]["rule_id"], "SLA-002");
+        assert_eq!(report["incidents"].as_array().unwrap().len(), 1);
+        assert!(input.join("processed/ws1.jsonl").exists());
+        assert!(input.join("failed/broken.evtx").exists());
+        assert!(input.join("failed/broken.evtx.error.txt").exists());
+        assert!(input.join("notes.txt").exists());
+        assert!(input.join("export.csv").exists());
+        assert!(!input.join("ws1.jsonl").exists());
+
+        // A file with the same name dropped again gets a report of its own
+        fs::write(input.join("ws1.jsonl"), format!("{line}\n")).unwrap();
+        let running = AtomicBool::new(true);
+        assert!(
+            scan(&config(Duration::from_secs(3600)), &running)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(scan(&config(Duration::ZERO), &running).unwrap().len(), 1);
+        assert!(dir.path().join("reports/ws1.jsonl.1.json").exists());
+        assert!(input.join("processed/ws1.jsonl.1").exists());
+
+        // A file that cannot be moved stays for the next scan, the others are still analyzed
+        fs::write(input.join("ws1.jsonl"), format!("{line}\n")).unwrap();
+        fs::write(input.join("broken.evtx"), b"not an event log").unwrap();
+        let blocked = DaemonConfig {
+            processed: dir.path().join("reports/ws1.jsonl.json"),
+            ..config(Duration::ZERO)
+        };
+        let outcomes = scan(&blocked, &running).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].result.is_err());
+        assert!(input.join("ws1.jsonl").exists());
+        assert!(input.join("failed/broken.evtx.1").exists());
+
+        let schedule = crate::cli::parse_schedule("30 */2 * * *").unwrap();
+        let time = Local.with_ymd_and_hms(2025, 1, 1, 10, 30, 0).unwrap();
+        assert_eq!(
+            schedule.next_scan(time).unwrap(),
+            Local.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap()
+        );
+        assert!(crate::cli::parse_schedule("every hour").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Detection context of live monitoring saved to disk, so the stateful rules (event storms,
+//! process depth, beaconing, mass deletion) keep their history across restarts
+use crate::jsonl::event_to_xml;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Format version of the state file, bumped when its layout changes
+pub const STATE_VERSION: u32 = 1;
+/// Time between two saves of the state while monitoring
+pub const STATE_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    /// Context events, oldest first, as event XML
+    events: Vec<String>,
+}
+
+/// Serialize the detection context
+pub fn state_to_json<'a>(events: impl IntoIterator<Item = &'a SysmonEvent>) -> Result<String> {
+    let state = StateFile {
+        version: STATE_VERSION,
+        events: events.into_iter().map(event_to_xml).collect(),
+    };
+    Ok(serde_json::to_string(&state)?)
+}
+
+/// Detection context saved by [`state_to_json`]; events that no longer parse are skipped
+pub fn state_from_json(json: &str) -> Result<Vec<SysmonEvent>> {
+    let state: StateFile = serde_json::from_str(json).context("Malformed detection state")?;
+    if state.version != STATE_VERSION {
+        bail!(
+            "Unsupported detection state version {} (expected {STATE_VERSION})",
+            state.version
+        );
+    }
+    let mut events = Vec::with_capacity(state.events.len());
+    for xml in &state.events {
+        match SysmonEvent::from_str(xml) {
+            Ok(event) => events.push(event),
+            Err(e) => warn!("Skipping event of the detection state: {:#}", e),
+        }
+    }
+    Ok(events)
+}
+
+/// Detection context saved in a file, empty when the file does not exist yet
+pub fn load_state(path: &Path) -> Result<Vec<SysmonEvent>> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+    };
+    let events = state_from_json(&json).with_context(|| format!("in {}", path.display()))?;
+    info!(
+        "Restored {} context events from {}",
+        events.len(),
+        path.display()
+    );
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies_live;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+    use std::collections::VecDeque;
+
+    fn process(time: &str, pid: u32, parent_pid: u32) -> SysmonEvent {
+        json_process_event(json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "TEST-PC",
+            "RecordNumber": pid,
+            "ProcessGuid": format!("{{11111111-2222-3333-4444-55555555{pid:04}}}"),
+            "ProcessId": pid.to_string(),
+            "CommandLine": "cmd.exe",
+            "ParentProcessGuid": format!("{{99999999-8888-7777-6666-55555555{parent_pid:04}}}"),
+            "ParentProcessId": parent_pid.to_string(),
+            "ParentImage": r"C:\Windows\System32\cmd.exe",
+            "ParentCommandLine": "cmd.exe",
+        }))
+    }
+
+    #[test]
+    fn context_survives_a_restart() {
+        // A chain of nested shells, deep enough to be reported once the last one starts
+        let chain: Vec<_> = (1..=6)
+            .map(|pid| process(&format!("10:00:0{pid}"), pid, pid - 1))
+            .collect();
+        let json = state_to_json(&chain).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        assert!(load_state(&path).unwrap().is_empty());
+        std::fs::write(&path, &json).unwrap();
+        let restored: VecDeque<_> = load_state(&path).unwrap().into();
+
+        assert_eq!(restored.len(), chain.len());
+        assert_eq!(
+            format!("{:?}", restored.back().unwrap()),
+            format!("{:?}", chain.last().unwrap())
+        );
+        let next = process("10:00:07", 7, 6);
+        let with_state = detect_anomalies_live(&next, &restored);
+        let without_state = detect_anomalies_live(&next, &VecDeque::new());
+        assert!(
+            with_state
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "DeepProcessTree")
+        );
+        assert!(
+            !without_state
+                .iter()
+                .any(|anomaly| anomaly.rule_name() == "DeepProcessTree")
+        );
+
+        assert!(state_from_json(r#"{"version":99,"events":[]}"#).is_err());
+    }
+}
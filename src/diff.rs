@@ -0,0 +1,207 @@
+//! Comparison of two captures: artifacts seen in one log but not in the other
+use crate::helpers::HasProcess;
+use crate::sysmon::Event as SysmonEvent;
+use std::collections::BTreeMap;
+
+/// Artifacts compared case-insensitively, keeping the spelling of their first occurrence
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactSet(BTreeMap<String, String>);
+
+impl ArtifactSet {
+    fn insert(&mut self, artifact: &str) {
+        if artifact.is_empty() {
+            return;
+        }
+        self.0
+            .entry(artifact.to_lowercase())
+            .or_insert_with(|| artifact.to_string());
+    }
+
+    fn difference(&self, other: &ArtifactSet) -> ArtifactSet {
+        ArtifactSet(
+            self.0
+                .iter()
+                .filter(|(key, _)| !other.0.contains_key(*key))
+                .map(|(key, artifact)| (key.clone(), artifact.clone()))
+                .collect(),
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.values().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// What a capture shows running and talking on the hosts it covers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    /// Images of processes that were started or generated events
+    pub processes: ArtifactSet,
+    /// Images started by the service control manager (services.exe)
+    pub services: ArtifactSet,
+    /// Outbound connections as `host:port`, the host name when Sysmon resolved it
+    pub network_destinations: ArtifactSet,
+    pub dns_queries: ArtifactSet,
+    /// Registry values written (event 13), as the key followed by the value name
+    pub registry_keys: ArtifactSet,
+}
+
+impl Inventory {
+    pub fn from_events(events: &[SysmonEvent]) -> Self {
+        let mut inventory = Inventory::default();
+        for event in events {
+            inventory.processes.insert(event.image());
+            match event {
+                SysmonEvent::ProcessCreate(e) => {
+                    let data = &e.event_data;
+                    let parent = &data.parent_image.image;
+                    let parent_name = parent.rsplit('\\').next().unwrap_or(parent);
+                    if parent_name.eq_ignore_ascii_case("services.exe") {
+                        inventory.services.insert(&data.image.image);
+                    }
+                }
+                SysmonEvent::OutboundNetwork(e) => {
+                    let data = &e.event_data;
+                    let host = match &data.destination_hostname {
+                        Some(host) if !host.is_empty() && host != "-" => host,
+                        _ => &data.destination_ip,
+                    };
+                    inventory
+                        .network_destinations
+                        .insert(&format!("{host}:{}", data.destination_port));
+                }
+                SysmonEvent::DnsQuery(e) => inventory.dns_queries.insert(&e.event_data.query_name),
+                SysmonEvent::RegistrySetValue(e) => {
+                    inventory.registry_keys.insert(&e.event_data.target_object)
+                }
+                _ => {}
+            }
+        }
+        inventory
+    }
+
+    /// Artifacts of this inventory missing from the other
+    fn difference(&self, other: &Inventory) -> Inventory {
+        Inventory {
+            processes: self.processes.difference(&other.processes),
+            services: self.services.difference(&other.services),
+            network_destinations: self
+                .network_destinations
+                .difference(&other.network_destinations),
+            dns_queries: self.dns_queries.difference(&other.dns_queries),
+            registry_keys: self.registry_keys.difference(&other.registry_keys),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+            && self.services.is_empty()
+            && self.network_destinations.is_empty()
+            && self.dns_queries.is_empty()
+            && self.registry_keys.is_empty()
+    }
+}
+
+/// Artifacts present in only one of two captures
+#[derive(Debug, Clone, Default)]
+pub struct LogDiff {
+    pub only_in_first: Inventory,
+    pub only_in_second: Inventory,
+}
+
+pub fn diff_logs(first: &[SysmonEvent], second: &[SysmonEvent]) -> LogDiff {
+    let first = Inventory::from_events(first);
+    let second = Inventory::from_events(second);
+    LogDiff {
+        only_in_first: first.difference(&second),
+        only_in_second: second.difference(&first),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{json_event, json_process_event};
+    use serde_json::json;
+
+    fn process(image: &str, parent: &str) -> SysmonEvent {
+        json_process_event(json!({
+            "Hostname": "TEST-PC",
+            "Image": image,
+            "CommandLine": image,
+            "ParentImage": parent,
+            "ParentCommandLine": parent,
+        }))
+    }
+
+    #[test]
+    fn artifacts_only_in_one_capture() {
+        let services = r"C:\Windows\System32\services.exe";
+        let before = [
+            process(r"C:\Windows\System32\svchost.exe", services),
+            process(
+                r"C:\Windows\System32\notepad.exe",
+                r"C:\Windows\explorer.exe",
+            ),
+        ];
+        let after = [
+            process(r"C:\WINDOWS\system32\svchost.exe", services),
+            process(r"C:\ProgramData\updater.exe", services),
+            json_event(
+                13,
+                json!({
+                    "RecordNumber": 3,
+                    "EventType": "SetValue",
+                    "Image": r"C:\ProgramData\updater.exe",
+                    "TargetObject": r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Run\Updater",
+                    "Details": r"C:\ProgramData\updater.exe",
+                }),
+            ),
+            json_event(
+                22,
+                json!({
+                    "RecordNumber": 2,
+                    "QueryName": "c2.example.net",
+                    "Image": r"C:\ProgramData\updater.exe",
+                }),
+            ),
+        ];
+        let diff = diff_logs(&before, &after);
+        assert!(
+            diff.only_in_first
+                .processes
+                .iter()
+                .eq([r"C:\Windows\System32\notepad.exe"])
+        );
+        assert!(diff.only_in_first.services.is_empty());
+        assert!(
+            diff.only_in_second
+                .services
+                .iter()
+                .eq([r"C:\ProgramData\updater.exe"])
+        );
+        assert!(
+            diff.only_in_second
+                .dns_queries
+                .iter()
+                .eq(["c2.example.net"])
+        );
+        assert!(
+            diff.only_in_second
+                .registry_keys
+                .iter()
+                .eq([r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Run\Updater"])
+        );
+        assert!(diff.only_in_first.registry_keys.is_empty());
+        // Paths differing only in case are the same process
+        assert_eq!(diff.only_in_second.processes.len(), 1);
+    }
+}
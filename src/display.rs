@@ -1,11 +1,142 @@
-use crate::analyzer::{Anomaly, Severity};
+use crate::analyzer::attack::TacticSummary;
+use crate::analyzer::lifetime;
+use crate::analyzer::prevalence::MIN_BASELINE_RUNS;
+use crate::analyzer::rule_stats::RuleProfile;
+use crate::analyzer::{AggregatedAnomaly, Anomaly, HostRisk, Severity, rule_id_of};
+use crate::diff::{ArtifactSet, Inventory, LogDiff};
+use crate::event_detail::EventDetail;
 use crate::helpers::HasSystem;
-use crate::sysmon::Event as SysmonEvent;
+use crate::incident::Incident;
+use crate::live_output::SessionSummary;
+use crate::netsummary::ProcessNetSummary;
+use crate::profile::ProcessProfile;
+use crate::session::{Session, SessionReport};
+use crate::stats::{DnsReport, DomainStats, Talkers};
+use crate::sysmon::{Event as SysmonEvent, TimeCreated, format_event_time};
+use crate::triage::{TriageFile, finding_id};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use clap::ValueEnum;
 use colored::{Color, ColoredString, Colorize};
 use prettytable::{Cell, Row, Table};
+use std::sync::RwLock;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 const EVENTS_DISPLAYED: usize = 100;
 
+/// When to colorize terminal output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Colorize when stdout is a terminal and NO_COLOR is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Apply the color choice to all subsequent output
+pub fn set_color_choice(choice: ColorChoice) {
+    match choice {
+        // colored already checks NO_COLOR, CLICOLOR and whether stdout is a terminal
+        ColorChoice::Auto => colored::control::unset_override(),
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+}
+
+/// Time zone of displayed timestamps; machine-readable output always stays in UTC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeZoneChoice {
+    /// Timestamps as written in the log
+    #[default]
+    Utc,
+    /// Time zone of this computer
+    Local,
+    Offset(FixedOffset),
+}
+
+static TIME_ZONE: RwLock<TimeZoneChoice> = RwLock::new(TimeZoneChoice::Utc);
+
+/// Show all subsequent timestamps in this time zone
+pub fn set_time_zone(zone: TimeZoneChoice) {
+    *TIME_ZONE.write().unwrap_or_else(|e| e.into_inner()) = zone;
+}
+
+/// Parse `utc`, `local` or a UTC offset such as `+02:00` or `-0530`
+pub fn parse_time_zone(s: &str) -> Result<TimeZoneChoice, String> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+        return Ok(TimeZoneChoice::Utc);
+    }
+    if s.eq_ignore_ascii_case("local") {
+        return Ok(TimeZoneChoice::Local);
+    }
+    let invalid =
+        || format!("invalid time zone '{s}' (expected utc, local or an offset such as +02:00)");
+    let (sign, offset) = match s.split_at_checked(1) {
+        Some(("+", offset)) => (1, offset),
+        Some(("-", offset)) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    let digits = offset.replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let hours: i32 = digits[..2].parse().map_err(|_| invalid())?;
+    let minutes: i32 = digits[2..].parse().map_err(|_| invalid())?;
+    if minutes >= 60 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .map(TimeZoneChoice::Offset)
+        .ok_or_else(invalid)
+}
+
+/// Event time in the selected time zone, as written in the log when it is not a valid timestamp
+pub fn format_timestamp(time: &TimeCreated) -> String {
+    match time.time {
+        Some(time) => format_time(time),
+        None => time.system_time.clone(),
+    }
+}
+
+/// Time in the selected time zone, UTC times in RFC 3339
+pub fn format_time(time: DateTime<Utc>) -> String {
+    let zone = *TIME_ZONE.read().unwrap_or_else(|e| e.into_inner());
+    let format = "%Y-%m-%d %H:%M:%S%.3f %:z";
+    match zone {
+        TimeZoneChoice::Utc => format_event_time(time),
+        TimeZoneChoice::Local => time.with_timezone(&Local).format(format).to_string(),
+        TimeZoneChoice::Offset(offset) => time.with_timezone(&offset).format(format).to_string(),
+    }
+}
+
+fn format_optional_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(|| "-".to_string(), format_time)
+}
+
+/// Print a table, styled only when colored output is enabled
+fn print_table(table: &Table) {
+    let printed = if colored::control::SHOULD_COLORIZE.should_colorize() {
+        table.print_tty(true)
+    } else {
+        table.print(&mut std::io::stdout())
+    };
+    if let Err(e) = printed {
+        tracing::warn!("Failed to print table: {}", e);
+    }
+}
+
+/// Output of the `parse` command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored tables for terminals
+    #[default]
+    Text,
+    /// Anomaly report following schemas/anomaly-report.schema.json
+    Json,
+}
+
 pub fn display_events(events: &[SysmonEvent]) {
     if events.is_empty() {
         println!("{}", "No events to found".yellow());
@@ -14,6 +145,7 @@ pub fn display_events(events: &[SysmonEvent]) {
     let mut table = Table::new();
     table.add_row(Row::new(vec![
         Cell::new("Timestamp").style_spec("Fb"),
+        Cell::new("Computer").style_spec("Fb"),
         Cell::new("ID").style_spec("Fb"),
         Cell::new("Event Type").style_spec("Fb"),
         Cell::new("Process").style_spec("Fb"),
@@ -22,7 +154,7 @@ pub fn display_events(events: &[SysmonEvent]) {
     for event in events.iter().take(EVENTS_DISPLAYED) {
         add_event_row(&mut table, event);
     }
-    table.printstd();
+    print_table(&table);
     if events.len() > EVENTS_DISPLAYED {
         println!(
             "\n{} Showing first {} events out of {}",
@@ -39,7 +171,8 @@ fn add_event_row(table: &mut Table, event: &SysmonEvent) {
     let details = format_event_details(event);
     let event_type = event.name();
     table.add_row(Row::new(vec![
-        Cell::new(&event.system().time_created.system_time),
+        Cell::new(&format_timestamp(&event.system().time_created)),
+        Cell::new(&event.system().computer.computer),
         Cell::new(&event.system().event_id.event_id.to_string()),
         Cell::new(event_type),
         Cell::new(&process_name.color(color)),
@@ -51,27 +184,32 @@ pub fn display_anomalies(anomalies: &[Anomaly]) {
     println!("{}", "🔍 Detected Anomalies:".bright_red().bold());
     println!("{}", "─".repeat(80).bright_black());
     for (i, anomaly) in anomalies.iter().enumerate() {
-        if let Anomaly::EventStorm { .. } = anomaly {
-            println!(
-                "\n{} [{}] {}",
-                format!("{}.", i + 1).bright_white(),
-                severity_color(anomaly.severity()),
-                anomaly.description().bright_white().bold()
-            );
-            continue;
-        }
-        let event = anomaly.event();
         println!(
             "\n{} [{}] {}",
             format!("{}.", i + 1).bright_white(),
             severity_color(anomaly.severity()),
             anomaly.description().bright_white().bold()
         );
+        let Some(event) = anomaly.event() else {
+            continue;
+        };
         println!(
             "   {} {}",
             "Time:".bright_black(),
-            event.system().time_created.system_time
+            format_timestamp(&event.system().time_created)
         );
+        println!(
+            "   {} {}",
+            "Record:".bright_black(),
+            event.system().event_record_id.event_record_id
+        );
+        if let Some(asset) = anomaly.asset() {
+            let role = asset.role.as_deref().unwrap_or("-");
+            match &asset.owner {
+                Some(owner) => println!("   {} {role} ({owner})", "Asset:".bright_black()),
+                None => println!("   {} {role}", "Asset:".bright_black()),
+            }
+        }
         let (_, process_name) = get_process_and_color(event);
         println!(
             "   {} {}",
@@ -97,17 +235,718 @@ pub fn display_anomalies(anomalies: &[Anomaly]) {
         anomalies.len().to_string().bright_red().bold()
     );
 }
+/// Display grouped anomalies with occurrence count, time span and finding ID, and their
+/// verdict when a triage file is given
+pub fn display_aggregated_anomalies(groups: &[AggregatedAnomaly], triage: Option<&TriageFile>) {
+    for group in groups {
+        print_aggregated_anomaly(group, triage, "");
+    }
+}
+fn print_aggregated_anomaly(group: &AggregatedAnomaly, triage: Option<&TriageFile>, indent: &str) {
+    let anomaly = &group.anomaly;
+    print!(
+        "{indent}{} [{}]: {}{}",
+        anomaly.severity().to_string().bright_red(),
+        anomaly.rule_id(),
+        anomaly.description(),
+        asset_role(anomaly)
+    );
+    if group.count > 1 {
+        print!(" {}", format!("(x{})", group.count).bright_yellow());
+    }
+    if let Some(entry) = triage.and_then(|triage| triage.get(anomaly)) {
+        print!(" {}", format!("({})", entry.status).bright_cyan());
+    }
+    print!(" {}", format!("#{}", finding_id(anomaly)).bright_black());
+    match (group.first_seen, group.last_seen) {
+        (Some(first), Some(last)) if first != last => println!(
+            " {}",
+            format!("[{} .. {}]", format_time(first), format_time(last)).bright_black()
+        ),
+        (Some(first), _) => println!(" {}", format!("[{}]", format_time(first)).bright_black()),
+        _ => println!(),
+    }
+}
+/// Display incidents, highest score first, with their findings
+pub fn display_incidents(incidents: &[Incident], triage: Option<&TriageFile>) {
+    for incident in incidents {
+        print!(
+            "{} {}",
+            format!("Incident {}", incident.id).bright_cyan().bold(),
+            severity_color(incident.severity())
+        );
+        if let Some(computer) = &incident.computer {
+            print!(" on {}", computer.bright_yellow());
+        }
+        print!(
+            ": score {}, findings: {}",
+            incident.score.to_string().bright_white(),
+            incident.anomaly_count()
+        );
+        match (incident.first_seen, incident.last_seen) {
+            (Some(first), Some(last)) if first != last => println!(
+                " {}",
+                format!("[{} .. {}]", format_time(first), format_time(last)).bright_black()
+            ),
+            (Some(first), _) => println!(" {}", format!("[{}]", format_time(first)).bright_black()),
+            _ => println!(),
+        }
+        if !incident.processes.is_empty() {
+            println!(
+                "  {} {}",
+                "Processes:".bright_black(),
+                incident.processes.join(", ")
+            );
+        }
+        for group in &incident.findings {
+            print_aggregated_anomaly(group, triage, "  ");
+        }
+    }
+}
+/// Display the busiest processes with their busiest destinations
+pub fn display_net_summary(summaries: &[ProcessNetSummary], top: usize, destinations: usize) {
+    if summaries.is_empty() {
+        println!("{}", "No outbound connections found".yellow());
+        return;
+    }
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Process").style_spec("Fb"),
+        Cell::new("Destination").style_spec("Fb"),
+        Cell::new("Protocol").style_spec("Fb"),
+        Cell::new("Connections").style_spec("Fb"),
+        Cell::new("First seen").style_spec("Fb"),
+        Cell::new("Last seen").style_spec("Fb"),
+    ]));
+    for summary in summaries.iter().take(top) {
+        let process = format!(
+            "{}\n{} connections, {} destinations",
+            summary.image,
+            summary.connections,
+            summary.destinations.len()
+        );
+        for (i, destination) in summary.destinations.iter().take(destinations).enumerate() {
+            let address = match &destination.hostname {
+                Some(host) => format!("{}:{} ({host})", destination.ip, destination.port),
+                None => format!("{}:{}", destination.ip, destination.port),
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(if i == 0 { &process } else { "" }),
+                Cell::new(&address),
+                Cell::new(&destination.protocol),
+                Cell::new(&destination.connections.to_string()),
+                Cell::new(&format_optional_time(destination.first_seen)),
+                Cell::new(&format_optional_time(destination.last_seen)),
+            ]));
+        }
+        if summary.destinations.len() > destinations {
+            table.add_row(Row::new(vec![
+                Cell::new(""),
+                Cell::new(&format!(
+                    "... {} more",
+                    summary.destinations.len() - destinations
+                )),
+            ]));
+        }
+    }
+    print_table(&table);
+    if summaries.len() > top {
+        println!(
+            "\n{} Showing {} processes out of {}",
+            "\u{2139}".bright_blue(),
+            top,
+            summaries.len()
+        );
+    }
+}
+/// Display the processes and destinations with the most outbound connections
+pub fn display_talkers(talkers: &Talkers, top: usize) {
+    if talkers.processes.is_empty() {
+        println!("{}", "No outbound connections found".yellow());
+        return;
+    }
+    for (title, name, talkers, peers) in [
+        (
+            "Top processes",
+            "Process",
+            &talkers.processes,
+            "Destinations",
+        ),
+        (
+            "Top destinations",
+            "Destination",
+            &talkers.destinations,
+            "Processes",
+        ),
+    ] {
+        println!("{}", title.bright_cyan().bold());
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new(name).style_spec("Fb"),
+            Cell::new("Connections").style_spec("Fb"),
+            Cell::new(peers).style_spec("Fb"),
+        ]));
+        for talker in talkers.iter().take(top) {
+            table.add_row(Row::new(vec![
+                Cell::new(&talker.name),
+                Cell::new(&talker.connections.to_string()),
+                Cell::new(&talker.peers.to_string()),
+            ]));
+        }
+        print_table(&table);
+    }
+}
+/// Display the DNS report; `baseline` is the number of runs of the prevalence store, if any
+pub fn display_dns_report(report: &DnsReport, top: usize, baseline: Option<u32>) {
+    if report.queries == 0 {
+        println!("{}", "No DNS queries found".yellow());
+        return;
+    }
+    println!(
+        "{} queries of {} domains by {} processes\n",
+        report.queries.to_string().bright_white().bold(),
+        report.domains.len(),
+        report.processes.len()
+    );
+    let domain_table = |title: &str, domains: Vec<&DomainStats>| {
+        if domains.is_empty() {
+            return;
+        }
+        println!("{}", title.bright_cyan().bold());
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Domain").style_spec("Fb"),
+            Cell::new("Queries").style_spec("Fb"),
+            Cell::new("Processes").style_spec("Fb"),
+            Cell::new("NXDOMAIN").style_spec("Fb"),
+            Cell::new("First seen").style_spec("Fb"),
+        ]));
+        for stats in domains {
+            table.add_row(Row::new(vec![
+                Cell::new(&stats.domain),
+                Cell::new(&stats.queries.to_string()),
+                Cell::new(&stats.processes.to_string()),
+                Cell::new(&stats.nxdomain.to_string()),
+                Cell::new(&format_optional_time(stats.first_seen)),
+            ]));
+        }
+        print_table(&table);
+    };
+    domain_table("Top queried domains", report.top_queried(top));
+    domain_table(
+        "Domains queried by the most processes",
+        report.top_by_processes(top),
+    );
+    let failing = report.top_nxdomain(top);
+    if !failing.is_empty() {
+        println!(
+            "{}",
+            "Processes with the most NXDOMAIN answers"
+                .bright_cyan()
+                .bold()
+        );
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Process").style_spec("Fb"),
+            Cell::new("NXDOMAIN").style_spec("Fb"),
+            Cell::new("Queries").style_spec("Fb"),
+            Cell::new("Domains").style_spec("Fb"),
+        ]));
+        for stats in failing {
+            table.add_row(Row::new(vec![
+                Cell::new(&stats.image),
+                Cell::new(&stats.nxdomain.to_string()),
+                Cell::new(&stats.queries.to_string()),
+                Cell::new(&stats.domains.to_string()),
+            ]));
+        }
+        print_table(&table);
+    }
+    match (&report.new_domains, baseline) {
+        (Some(new), _) if new.is_empty() => {
+            println!("{}", "No domains new to the prevalence store".green());
+        }
+        (Some(new), _) => {
+            let domains: Vec<_> = report
+                .domains
+                .iter()
+                .filter(|stats| new.contains(&stats.domain))
+                .take(top)
+                .collect();
+            let title = format!("{} domains never queried in earlier runs", new.len());
+            domain_table(&title, domains);
+        }
+        (None, Some(runs)) => println!(
+            "\n{} Prevalence store has {runs} of {MIN_BASELINE_RUNS} runs, new domains are reported once it has enough",
+            "\u{2139}".bright_blue()
+        ),
+        (None, None) => {}
+    }
+}
+/// Display every field of an event, hashes one per line
+pub fn display_event_detail(detail: &EventDetail) {
+    println!(
+        "{} {}",
+        format!("#{}", detail.index).bright_white(),
+        detail.event_name.bright_cyan().bold()
+    );
+    let width = detail
+        .system
+        .iter()
+        .map(|(name, _)| name.width())
+        .chain(detail.data.iter().map(|(name, _)| name.width()))
+        .chain(detail.hashes.iter().map(|(name, _)| name.width()))
+        .max()
+        .unwrap_or_default()
+        + 1;
+    let section = |title: &str, fields: &mut dyn Iterator<Item = (&str, &str)>| {
+        println!("{}", title.bright_cyan());
+        for (name, value) in fields {
+            println!(
+                "  {} {value}",
+                pad(&format!("{name}:"), width).bright_black()
+            );
+        }
+    };
+    section(
+        "System:",
+        &mut detail
+            .system
+            .iter()
+            .map(|(name, value)| (*name, value.as_str())),
+    );
+    section(
+        "Event data:",
+        &mut detail
+            .data
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+    if !detail.hashes.is_empty() {
+        section(
+            "Hashes:",
+            &mut detail
+                .hashes
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+    }
+}
+/// Display the verdicts of a triage file, most recent first
+pub fn display_triage(triage: &TriageFile) {
+    if triage.entries.is_empty() {
+        println!("{}", "No triaged findings".yellow());
+        return;
+    }
+    let mut entries: Vec<_> = triage.entries.iter().collect();
+    entries.sort_by(|(a_id, a), (b_id, b)| b.updated.cmp(&a.updated).then(a_id.cmp(b_id)));
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Finding").style_spec("Fb"),
+        Cell::new("Status").style_spec("Fb"),
+        Cell::new("Updated").style_spec("Fb"),
+        Cell::new("Note").style_spec("Fb"),
+    ]));
+    for (id, entry) in entries {
+        table.add_row(Row::new(vec![
+            Cell::new(id),
+            Cell::new(&entry.status.to_string()),
+            Cell::new(&entry.updated),
+            Cell::new(entry.note.as_deref().unwrap_or_default()),
+        ]));
+    }
+    print_table(&table);
+}
+/// Display findings by ATT&CK tactic with the processes that triggered most of them
+pub fn display_tactic_summary(tactics: &[TacticSummary]) {
+    if tactics.is_empty() {
+        return;
+    }
+    println!("{}", "Findings by ATT&CK tactic:".bright_cyan().bold());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Tactic").style_spec("Fb"),
+        Cell::new("ID").style_spec("Fb"),
+        Cell::new("Findings").style_spec("Fb"),
+        Cell::new("Top processes").style_spec("Fb"),
+    ]));
+    for summary in tactics {
+        let processes = summary
+            .top_processes
+            .iter()
+            .map(|(process, count)| format!("{process} ({count})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.add_row(Row::new(vec![
+            Cell::new(&summary.tactic.to_string()),
+            Cell::new(summary.tactic.id()),
+            Cell::new(&summary.count.to_string()),
+            Cell::new(&processes),
+        ]));
+    }
+    print_table(&table);
+}
+/// Display the evaluation time and findings of each rule, slowest first
+pub fn display_rule_profile(profile: &RuleProfile) {
+    println!("{}", "Rule performance:".bright_cyan().bold());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("ID").style_spec("Fb"),
+        Cell::new("Rule").style_spec("Fb"),
+        Cell::new("Evaluations").style_spec("Fb"),
+        Cell::new("Time (ms)").style_spec("Fb"),
+        Cell::new("Avg (µs)").style_spec("Fb"),
+        Cell::new("Hits").style_spec("Fb"),
+        Cell::new("Suppressed").style_spec("Fb"),
+    ]));
+    for (rule, stats) in profile.by_time() {
+        let average = stats.time.as_secs_f64() * 1e6 / stats.evaluations.max(1) as f64;
+        table.add_row(Row::new(vec![
+            Cell::new(rule_id_of(rule).unwrap_or_default()),
+            Cell::new(rule),
+            Cell::new(&stats.evaluations.to_string()),
+            Cell::new(&format!("{:.3}", stats.time.as_secs_f64() * 1e3)),
+            Cell::new(&format!("{average:.2}")),
+            Cell::new(&stats.hits.to_string()),
+            Cell::new(&stats.suppressed.to_string()),
+        ]));
+    }
+    print_table(&table);
+    println!(
+        "Total rule time: {:.3} ms",
+        profile.total_time().as_secs_f64() * 1e3
+    );
+}
+/// Display hosts ranked by risk score, so the worst machine is looked at first
+pub fn display_host_risk(hosts: &[HostRisk]) {
+    if hosts.is_empty() {
+        return;
+    }
+    println!("{}", "Risk by host:".bright_cyan().bold());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Computer").style_spec("Fb"),
+        Cell::new("Score").style_spec("Fb"),
+        Cell::new("Worst").style_spec("Fb"),
+        Cell::new("Critical").style_spec("Fb"),
+        Cell::new("High").style_spec("Fb"),
+        Cell::new("Medium").style_spec("Fb"),
+        Cell::new("Low").style_spec("Fb"),
+        Cell::new("Total").style_spec("Fb"),
+    ]));
+    for host in hosts {
+        let count = |severity: Severity| Cell::new(&host.findings[severity as usize].to_string());
+        table.add_row(Row::new(vec![
+            Cell::new(&host.computer),
+            Cell::new(&host.score.to_string()),
+            Cell::new(&severity_color(host.max_severity())),
+            count(Severity::Critical),
+            count(Severity::High),
+            count(Severity::Medium),
+            count(Severity::Low),
+            Cell::new(&host.total.to_string()),
+        ]));
+    }
+    print_table(&table);
+}
+/// Display what a monitoring session saw: its duration, events by ID, anomalies by severity and
+/// the processes with the most events
+pub fn display_session_summary(summary: &SessionSummary) {
+    println!("{}", "Session summary:".bright_cyan().bold());
+    println!(
+        "Monitored for {} ({} to {}): {} events, {} anomalies",
+        lifetime::format_duration(summary.duration()),
+        format_time(summary.started),
+        format_time(summary.stopped),
+        summary.total_events().to_string().bright_green(),
+        summary.anomalies.len().to_string().bright_red()
+    );
+    if !summary.anomalies.is_empty() {
+        let counts = summary.anomalies_by_severity();
+        let severities: Vec<String> = Severity::value_variants()
+            .iter()
+            .rev()
+            .map(|severity| {
+                format!(
+                    "{} {}",
+                    severity_color(*severity),
+                    counts[*severity as usize]
+                )
+            })
+            .collect();
+        println!("Anomalies by severity: {}", severities.join(", "));
+    }
+    if summary.total_events() == 0 {
+        return;
+    }
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("ID").style_spec("Fb"),
+        Cell::new("Event Type").style_spec("Fb"),
+        Cell::new("Events").style_spec("Fb"),
+    ]));
+    for (id, name, count) in summary.events_by_id() {
+        table.add_row(Row::new(vec![
+            Cell::new(&id.to_string()),
+            Cell::new(name),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    print_table(&table);
+    let processes = summary.top_processes();
+    if processes.is_empty() {
+        return;
+    }
+    println!("{}", "Top processes:".bright_cyan().bold());
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Process").style_spec("Fb"),
+        Cell::new("Events").style_spec("Fb"),
+    ]));
+    for (process, count) in processes {
+        table.add_row(Row::new(vec![
+            Cell::new(process),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    print_table(&table);
+}
 /// Display anomalies for live mode (more compact)
 pub fn display_anomalies_live(anomalies: &[Anomaly]) {
     for anomaly in anomalies {
         println!(
-            "{} [{}] {}",
+            "{} [{}] [{}] {}{}",
             "\u{26A0}".bright_red().bold(),
             severity_color(anomaly.severity()),
-            anomaly.description().bright_yellow().bold()
+            anomaly.rule_id(),
+            anomaly.description().bright_yellow().bold(),
+            asset_role(anomaly)
         );
     }
 }
+/// Display the aggregated activity of a process
+pub fn display_profile(profile: &ProcessProfile) {
+    if profile.is_empty() {
+        println!("{}", "No events found for the process".yellow());
+        return;
+    }
+    println!("{}", "Processes:".bright_cyan().bold());
+    for process in &profile.processes {
+        println!(
+            "  {} {} {}",
+            format!("PID {}", process.pid).bright_white(),
+            process.image.bright_cyan(),
+            format!("{{{}}}", process.guid).bright_black()
+        );
+        if let Some(started) = process.started {
+            println!(
+                "     {} {}",
+                "Started:".bright_black(),
+                format_time(started)
+            );
+        }
+        if let Some(ended) = process.ended {
+            let lifetime = process
+                .lifetime()
+                .map(|lifetime| format!(" (ran {})", lifetime::format_duration(lifetime)))
+                .unwrap_or_default();
+            println!(
+                "     {} {}{}",
+                "Ended:".bright_black(),
+                format_time(ended),
+                lifetime.bright_yellow()
+            );
+        }
+        if let Some(parent) = &process.parent_image {
+            println!(
+                "     {} {}",
+                "Parent:".bright_black(),
+                parent.bright_magenta()
+            );
+        }
+        if let Some(cmd) = &process.command_line {
+            println!("     {} {}", "Command:".bright_black(), truncate(cmd, 70));
+        }
+    }
+    if let (Some(first), Some(last)) = (profile.first_seen, profile.last_seen) {
+        println!(
+            "  {} {} events [{} .. {}]",
+            "Activity:".bright_black(),
+            profile.event_count,
+            format_time(first),
+            format_time(last)
+        );
+    }
+
+    if !profile.children.is_empty() {
+        println!("\n{}", "Child processes:".bright_cyan().bold());
+        for child in &profile.children {
+            println!(
+                "  [{}] {} {} {}",
+                format_optional_time(child.timestamp).bright_black(),
+                format!("PID {}", child.pid).bright_white(),
+                child.image.bright_green(),
+                truncate(&child.command_line, 60)
+            );
+        }
+    }
+    if !profile.network_destinations.is_empty() {
+        println!("\n{}", "Network destinations:".bright_cyan().bold());
+        for (destination, count) in &profile.network_destinations {
+            println!("  {destination} {}", format!("(x{count})").bright_yellow());
+        }
+    }
+    print_profile_section("Files created:", &profile.files_created);
+    print_profile_section("Files deleted:", &profile.files_deleted);
+    print_profile_section("Named pipes:", &profile.pipes);
+    print_profile_section("DNS queries:", &profile.dns_queries);
+    if !profile.registry_values.is_empty() {
+        println!("\n{}", "Registry values set:".bright_cyan().bold());
+        for (key, details) in &profile.registry_values {
+            println!("  {key} = {}", truncate(details, 60));
+        }
+    }
+}
+
+fn print_profile_section<'a>(title: &str, values: impl IntoIterator<Item = &'a String>) {
+    let mut values = values.into_iter().peekable();
+    if values.peek().is_none() {
+        return;
+    }
+    println!("\n{}", title.bright_cyan().bold());
+    for value in values {
+        println!("  {value}");
+    }
+}
+
+/// Display one row per logon session
+pub fn display_sessions(report: &SessionReport) {
+    if report.sessions.is_empty() {
+        println!("{}", "No logon sessions found".yellow());
+        return;
+    }
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Logon ID").style_spec("Fb"),
+        Cell::new("Computer").style_spec("Fb"),
+        Cell::new("User").style_spec("Fb"),
+        Cell::new("Start").style_spec("Fb"),
+        Cell::new("End").style_spec("Fb"),
+        Cell::new("Events").style_spec("Fb"),
+        Cell::new("Processes").style_spec("Fb"),
+        Cell::new("Anomalies").style_spec("Fb"),
+    ]));
+    for session in &report.sessions {
+        let anomalies = match session
+            .anomalies
+            .iter()
+            .map(|group| group.anomaly.severity())
+            .max()
+        {
+            Some(worst) => format!("{} ({})", session.anomalies.len(), severity_color(worst)),
+            None => "0".to_string(),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{:#x}", session.logon_id)),
+            Cell::new(&session.computer),
+            Cell::new(session.user.as_deref().unwrap_or("-")),
+            Cell::new(&format_optional_time(session.start)),
+            Cell::new(&format_optional_time(session.end)),
+            Cell::new(&session.event_count.to_string()),
+            Cell::new(&session.processes.len().to_string()),
+            Cell::new(&anomalies),
+        ]));
+    }
+    print_table(&table);
+    if report.unattributed > 0 {
+        println!(
+            "\n{} {} events could not be attributed to a session (no process creation or logon in the log)",
+            "\u{2139}".bright_blue(),
+            report.unattributed
+        );
+    }
+}
+
+/// Display the processes and anomalies of one logon session
+pub fn display_session(session: &Session) {
+    println!(
+        "{} {} on {} {}",
+        "Session".bright_cyan().bold(),
+        format!("{:#x}", session.logon_id).bright_white(),
+        session.computer.bright_yellow(),
+        session.user.as_deref().unwrap_or("-").bright_cyan()
+    );
+    if let Some(logon_type) = session.logon_type {
+        print!("  {} type {logon_type}", "Logon:".bright_black());
+        match &session.source_ip {
+            Some(ip) => println!(" from {ip}"),
+            None => println!(),
+        }
+    }
+    if let (Some(start), Some(end)) = (session.start, session.end) {
+        println!(
+            "  {} {} events [{} .. {}]",
+            "Activity:".bright_black(),
+            session.event_count,
+            format_time(start),
+            format_time(end)
+        );
+    }
+    if !session.processes.is_empty() {
+        println!("\n{}", "Processes:".bright_cyan().bold());
+        for process in &session.processes {
+            println!(
+                "  [{}] {} {} {}",
+                format_optional_time(process.timestamp).bright_black(),
+                format!("PID {}", process.pid).bright_white(),
+                process.image.bright_green(),
+                truncate(&process.command_line, 60)
+            );
+        }
+    }
+    if !session.anomalies.is_empty() {
+        println!("\n{}", "Anomalies:".bright_red().bold());
+        display_aggregated_anomalies(&session.anomalies, None);
+    }
+}
+
+/// Display the artifacts found in only one of two captures
+pub fn display_diff(diff: &LogDiff, first: &str, second: &str) {
+    if diff.only_in_first.is_empty() && diff.only_in_second.is_empty() {
+        println!("{}", "No differences found".green());
+        return;
+    }
+    print_inventory(&format!("Only in {first}:"), &diff.only_in_first);
+    print_inventory(&format!("Only in {second}:"), &diff.only_in_second);
+}
+
+fn print_inventory(title: &str, inventory: &Inventory) {
+    if inventory.is_empty() {
+        return;
+    }
+    println!("{}", title.bright_cyan().bold());
+    print_artifacts("Processes", &inventory.processes);
+    print_artifacts("Services", &inventory.services);
+    print_artifacts("Network destinations", &inventory.network_destinations);
+    print_artifacts("DNS queries", &inventory.dns_queries);
+    print_artifacts("Registry keys", &inventory.registry_keys);
+    println!();
+}
+
+fn print_artifacts(title: &str, artifacts: &ArtifactSet) {
+    if artifacts.is_empty() {
+        return;
+    }
+    println!(
+        "  {} {}",
+        title.bright_white().bold(),
+        format!("({})", artifacts.len()).bright_black()
+    );
+    for artifact in artifacts.iter() {
+        println!("    {artifact}");
+    }
+}
+
 /// Format a single event for compact live monitoring
 pub fn print_compact_event(event: &SysmonEvent, count: usize) {
     let (color, process_name) = get_process_and_color(event);
@@ -115,7 +954,7 @@ pub fn print_compact_event(event: &SysmonEvent, count: usize) {
 
     print!(
         "[{}] {} {} {} {} ",
-        event.system().time_created.system_time.bright_black(),
+        format_timestamp(&event.system().time_created).bright_black(),
         format!("#{count}").dimmed(),
         format!("ID:{}", event.system().event_id.event_id).bright_yellow(),
         process_name.color(color),
@@ -124,6 +963,14 @@ pub fn print_compact_event(event: &SysmonEvent, count: usize) {
 
     println!("{}", truncate(&details, 80));
 }
+/// Role of the computer of the finding in the asset inventory, as ` [role]`, empty if unknown
+pub fn asset_role(anomaly: &Anomaly) -> String {
+    anomaly
+        .asset()
+        .and_then(|asset| asset.role.as_deref())
+        .map(|role| format!(" {}", format!("[{role}]").bright_cyan()))
+        .unwrap_or_default()
+}
 /// Get a colored string for severity
 fn severity_color(severity: Severity) -> ColoredString {
     match severity {
@@ -134,12 +981,36 @@ fn severity_color(severity: Severity) -> ColoredString {
     }
 }
 /// Get the primary process name and risk color
-fn get_process_and_color(event: &SysmonEvent) -> (Color, String) {
+pub(crate) fn get_process_and_color(event: &SysmonEvent) -> (Color, String) {
     let image = match &event {
         SysmonEvent::ProcessCreate(event) => &event.event_data.image,
         SysmonEvent::InboundNetwork(event) => &event.event_data.image,
         SysmonEvent::OutboundNetwork(event) => &event.event_data.image,
         SysmonEvent::FileCreate(event) => &event.event_data.image,
+        SysmonEvent::FileStreamCreate(event) => &event.event_data.image,
+        SysmonEvent::FileDelete(event)
+        | SysmonEvent::FileBlock(event)
+        | SysmonEvent::FileExecutableDetected(event) => &event.event_data.image,
+        SysmonEvent::PipeCreated(event) | SysmonEvent::PipeConnected(event) => {
+            &event.event_data.image
+        }
+        SysmonEvent::RegistrySetValue(event) => &event.event_data.image,
+        SysmonEvent::DnsQuery(event) => &event.event_data.image,
+        SysmonEvent::ProcessAccess(event) => &event.event_data.image,
+        SysmonEvent::ServiceStateChange(event) | SysmonEvent::ConfigurationChange(event) => {
+            &event.event_data.image
+        }
+        // Drivers are loaded by the kernel, show the driver instead of an empty image
+        SysmonEvent::DriverLoad(event) => &event.event_data.image_loaded,
+        SysmonEvent::RawAccessRead(event) => &event.event_data.image,
+        SysmonEvent::ClipboardChange(event) => &event.event_data.image,
+        SysmonEvent::ProcessTampering(event) => &event.event_data.image,
+        SysmonEvent::ProcessTerminate(event) => &event.event_data.image,
+        SysmonEvent::WmiFilter(event)
+        | SysmonEvent::WmiConsumer(event)
+        | SysmonEvent::WmiBinding(event) => &event.event_data.image,
+        SysmonEvent::Logon(event) => &event.event_data.image,
+        SysmonEvent::ScriptBlock(event) => &event.event_data.image,
     };
     let process_name = image
         .rsplit('\\')
@@ -156,9 +1027,9 @@ fn get_process_and_color(event: &SysmonEvent) -> (Color, String) {
         "bash.exe",
         "zsh.exe",
     ];
-    let color = if shell.contains(&lower_name.as_str()) {
+    let color = if shell.contains(&lower_name.as_str()) || event.is_process_tampering() {
         Color::Red // High risk
-    } else if event.system().event_id.event_id == 3 {
+    } else if event.is_inbound_network() || event.is_outbound_network() {
         Color::Blue // Network event (Event ID 3)
     } else if lower_name == "svchost.exe" {
         Color::Yellow // Suspicious
@@ -181,6 +1052,144 @@ pub fn format_event_details(event: &SysmonEvent) -> String {
         SysmonEvent::FileCreate(event) => {
             format!("File: {}", event.event_data.target_filename)
         }
+        SysmonEvent::FileStreamCreate(event) => {
+            format!("Stream: {}", event.event_data.target_filename)
+        }
+        SysmonEvent::FileDelete(event) => {
+            format!("Deleted: {}", event.event_data.target_filename)
+        }
+        SysmonEvent::FileBlock(event) => {
+            format!("Blocked: {}", event.event_data.target_filename)
+        }
+        SysmonEvent::FileExecutableDetected(event) => {
+            format!("Executable written: {}", event.event_data.target_filename)
+        }
+        SysmonEvent::PipeCreated(event) => {
+            format!("Pipe created: {}", event.event_data.pipe_name)
+        }
+        SysmonEvent::PipeConnected(event) => {
+            format!("Pipe connected: {}", event.event_data.pipe_name)
+        }
+        SysmonEvent::RegistrySetValue(event) => {
+            let data = &event.event_data;
+            format!("Registry: {} = {}", data.target_object, data.details)
+        }
+        SysmonEvent::DnsQuery(event) => {
+            let data = &event.event_data;
+            let addresses: Vec<&str> = data.resolved_addresses().collect();
+            if addresses.is_empty() {
+                format!("DNS: {}", data.query_name)
+            } else {
+                format!("DNS: {} -> {}", data.query_name, addresses.join(", "))
+            }
+        }
+        SysmonEvent::ProcessAccess(event) => {
+            let data = &event.event_data;
+            format!(
+                "Access: {} ({})",
+                data.target_image
+                    .rsplit('\\')
+                    .next()
+                    .unwrap_or(&data.target_image),
+                data.granted_access
+            )
+        }
+        SysmonEvent::ServiceStateChange(event) => {
+            let data = &event.event_data;
+            format!(
+                "Sysmon {} (version {})",
+                data.state.as_deref().unwrap_or("-").to_lowercase(),
+                data.version.as_deref().unwrap_or("-")
+            )
+        }
+        SysmonEvent::ConfigurationChange(event) => {
+            let data = &event.event_data;
+            format!(
+                "Sysmon config changed: {} {}",
+                data.configuration.as_deref().unwrap_or("-"),
+                data.configuration_file_hash.as_deref().unwrap_or_default()
+            )
+        }
+        SysmonEvent::DriverLoad(event) => {
+            let data = &event.event_data;
+            let signature = match (data.signed, &data.signature) {
+                (Some(false), _) => "unsigned".to_string(),
+                (_, Some(signature)) => format!(
+                    "{signature}, {}",
+                    data.signature_status.as_deref().unwrap_or("-")
+                ),
+                (_, None) => data.signature_status.as_deref().unwrap_or("-").to_string(),
+            };
+            format!("Driver: {} ({signature})", data.image_loaded.image)
+        }
+        SysmonEvent::RawAccessRead(event) => {
+            format!("Raw read: {}", event.event_data.device)
+        }
+        SysmonEvent::ClipboardChange(event) => {
+            let data = &event.event_data;
+            format!(
+                "Clipboard: session {} {}",
+                data.session.as_deref().unwrap_or("-"),
+                data.client_info.as_deref().unwrap_or_default()
+            )
+        }
+        SysmonEvent::ProcessTampering(event) => {
+            format!("Tampering: {}", event.event_data.tampering_type)
+        }
+        SysmonEvent::ProcessTerminate(event) => {
+            format!("Terminated: PID {}", event.event_data.process_id)
+        }
+        SysmonEvent::WmiFilter(event) => {
+            let data = &event.event_data;
+            format!(
+                "WMI filter {}: {} {}",
+                data.operation.to_lowercase(),
+                data.name.as_deref().unwrap_or("-"),
+                data.query.as_deref().unwrap_or_default()
+            )
+        }
+        SysmonEvent::WmiConsumer(event) => {
+            let data = &event.event_data;
+            format!(
+                "WMI {} consumer {}: {} {}",
+                data.consumer_type.as_deref().unwrap_or("-"),
+                data.operation.to_lowercase(),
+                data.name.as_deref().unwrap_or("-"),
+                data.destination.as_deref().unwrap_or_default()
+            )
+        }
+        SysmonEvent::WmiBinding(event) => {
+            let data = &event.event_data;
+            format!(
+                "WMI binding {}: {} -> {}",
+                data.operation.to_lowercase(),
+                data.filter.as_deref().unwrap_or("-"),
+                data.consumer.as_deref().unwrap_or("-")
+            )
+        }
+        SysmonEvent::Logon(event) => {
+            let data = &event.event_data;
+            match &data.ip_address {
+                Some(ip) => format!(
+                    "Logon type {}: {} from {ip}",
+                    data.logon_type, data.target_user.user
+                ),
+                None => format!("Logon type {}: {}", data.logon_type, data.target_user.user),
+            }
+        }
+        SysmonEvent::ScriptBlock(event) => {
+            let data = &event.event_data;
+            // Script blocks span many lines, keep the table to one line per event
+            let text = data.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if data.message_total > 1 {
+                format!(
+                    "Script block {}/{}: {text}",
+                    data.message_number, data.message_total
+                )
+            } else {
+                format!("Script block: {text}")
+            }
+        }
     }
 }
 fn get_command_line(event: &SysmonEvent) -> Option<String> {
@@ -197,11 +1206,49 @@ fn get_parent_image(event: &SysmonEvent) -> Option<String> {
         _ => None,
     }
 }
-/// Truncate string to max length
-fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+/// Truncate a string to a display width in terminal columns. Cuts between grapheme clusters, so
+/// accented and combined characters stay whole, and wide (CJK) characters count as two columns.
+pub(crate) fn truncate(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+    let budget = max_width.saturating_sub(3);
+    let (mut width, mut end) = (0, 0);
+    for (index, grapheme) in s.grapheme_indices(true) {
+        width += grapheme.width();
+        if width > budget {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    format!("{}...", &s[..end])
+}
+/// Pad a string with spaces to a display width, as `{:<width$}` counts characters, not columns
+pub(crate) fn pad(s: &str, width: usize) -> String {
+    format!("{s}{}", " ".repeat(width.saturating_sub(s.width())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_and_pad_by_display_width() {
+        assert_eq!(truncate("cmd.exe /c dir", 20), "cmd.exe /c dir");
+        assert_eq!(truncate("cmd.exe /c dir", 10), "cmd.exe...");
+        // Multi-byte characters are never split
+        assert_eq!(
+            truncate("powershell -c Écrire-Hôte", 17),
+            "powershell -c ..."
+        );
+        assert_eq!(truncate("Écrire-Hôte café", 10), "Écrire-...");
+        // Combining accent stays with its letter
+        assert_eq!(truncate("cafe\u{301} noir", 7), "cafe\u{301}...");
+        // CJK characters take two columns
+        assert_eq!(truncate("C:\\用户\\文档\\报告.exe", 12), "C:\\用户\\...");
+        assert_eq!(truncate("用户", 4), "用户");
+        assert_eq!(truncate("用户名", 4), "...");
+        assert_eq!(pad("报告.exe", 10), "报告.exe  ");
+        assert_eq!(pad("svchost.exe", 4), "svchost.exe");
     }
 }
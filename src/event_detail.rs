@@ -0,0 +1,191 @@
+//! Every field of single events, for the `show` command
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use serde_json::{Map, Value, json};
+use std::fmt;
+
+/// Event data fields holding a comma separated `ALGORITHM=value` list
+const HASH_FIELDS: &[&str] = &["Hashes", "Hash"];
+
+/// Which events to show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSelector {
+    /// `<EventRecordID>` of the event, may match one event per file or computer
+    RecordId(u32),
+    /// Position of the event among the parsed events, starting at 1
+    Index(usize),
+}
+
+impl fmt::Display for EventSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventSelector::RecordId(id) => write!(f, "record {id}"),
+            EventSelector::Index(index) => write!(f, "event #{index}"),
+        }
+    }
+}
+
+/// Selected events with their position (starting at 1), optionally only those of a computer
+pub fn select_events<'a>(
+    events: &'a [SysmonEvent],
+    selector: EventSelector,
+    computer: Option<&str>,
+) -> Vec<(usize, &'a SysmonEvent)> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| (index + 1, event))
+        .filter(|(index, event)| match selector {
+            EventSelector::RecordId(id) => event.system().event_record_id.event_record_id == id,
+            EventSelector::Index(wanted) => *index == wanted,
+        })
+        .filter(|(_, event)| {
+            computer.is_none_or(|computer| {
+                event
+                    .system()
+                    .computer
+                    .computer
+                    .eq_ignore_ascii_case(computer)
+            })
+        })
+        .collect()
+}
+
+/// All fields of an event, untruncated and in log order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventDetail {
+    pub index: usize,
+    pub event_name: String,
+    /// `<System>` metadata
+    pub system: Vec<(&'static str, String)>,
+    /// `<EventData>` fields, hash lists excluded
+    pub data: Vec<(String, String)>,
+    /// Hash lists split into algorithm and value
+    pub hashes: Vec<(String, String)>,
+}
+
+impl EventDetail {
+    pub fn new(index: usize, event: &SysmonEvent) -> Self {
+        let system = event.system();
+        let mut data = Vec::new();
+        let mut hashes = Vec::new();
+        for (name, value) in event.fields().iter() {
+            if HASH_FIELDS.contains(&name) {
+                hashes.extend(split_hashes(value));
+            } else {
+                data.push((name.to_string(), value.to_string()));
+            }
+        }
+        Self {
+            index,
+            event_name: event.name().to_string(),
+            system: vec![
+                ("Provider", system.provider.provider_name.clone()),
+                ("Channel", system.channel.value.clone()),
+                ("Event ID", system.event_id.event_id.to_string()),
+                (
+                    "Record ID",
+                    system.event_record_id.event_record_id.to_string(),
+                ),
+                ("Time Created", system.time_created.to_rfc3339()),
+                ("Computer", system.computer.computer.clone()),
+                ("User ID", system.security.security.clone()),
+                ("Version", system.version.version.clone()),
+                ("Level", system.level.level.clone()),
+                ("Task", system.task.task.clone()),
+                ("Opcode", system.opcode.opcode.clone()),
+                ("Keywords", system.keywords.keywords.clone()),
+                ("Process ID", system.execution.process_id.clone()),
+                ("Thread ID", system.execution.thread_id.clone()),
+            ],
+            data,
+            hashes,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "index": self.index,
+            "event_type": self.event_name,
+            "system": fields_to_json(self.system.iter().map(|(name, value)| (*name, value))),
+            "data": fields_to_json(self.data.iter().map(|(name, value)| (name.as_str(), value))),
+            "hashes": fields_to_json(self.hashes.iter().map(|(name, value)| (name.as_str(), value))),
+        })
+    }
+}
+
+fn fields_to_json<'a>(fields: impl Iterator<Item = (&'a str, &'a String)>) -> Value {
+    Value::Object(
+        fields
+            .map(|(name, value)| (name.to_string(), Value::String(value.clone())))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+/// `SHA1=AB,MD5=CD` into its algorithms and values, the whole list when it has no `=`
+fn split_hashes(hashes: &str) -> Vec<(String, String)> {
+    hashes
+        .split(',')
+        .map(str::trim)
+        .filter(|hash| !hash.is_empty())
+        .map(|hash| match hash.split_once('=') {
+            Some((algorithm, value)) => (algorithm.trim().to_uppercase(), value.trim().to_string()),
+            None => ("Hash".to_string(), hash.to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    fn process(record: u32, computer: &str, command_line: &str) -> SysmonEvent {
+        json_process_event(json!({
+            "Hostname": computer,
+            "RecordNumber": record,
+            "CommandLine": command_line,
+            "Hashes": "SHA1=AB12,MD5=CD34,SHA256=EF56,IMPHASH=0011",
+        }))
+    }
+
+    #[test]
+    fn full_event_details() {
+        let long = format!("cmd.exe /c echo {}", "x".repeat(300));
+        let events = [
+            process(7, "WS1", "cmd.exe"),
+            process(8, "WS1", &long),
+            process(8, "WS2.corp.local", "cmd.exe /c whoami"),
+        ];
+        let by_record = select_events(&events, EventSelector::RecordId(8), None);
+        assert_eq!(
+            by_record
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            [2, 3]
+        );
+        let on_computer = select_events(&events, EventSelector::RecordId(8), Some("ws1"));
+        assert_eq!(on_computer.len(), 1);
+        assert!(select_events(&events, EventSelector::Index(4), None).is_empty());
+
+        let (index, event) = select_events(&events, EventSelector::Index(2), None)[0];
+        let detail = EventDetail::new(index, event);
+        assert!(detail.data.contains(&("CommandLine".to_string(), long)));
+        assert!(detail.system.contains(&("Record ID", "8".to_string())));
+        assert_eq!(
+            detail.hashes,
+            [
+                ("SHA1".to_string(), "AB12".to_string()),
+                ("MD5".to_string(), "CD34".to_string()),
+                ("SHA256".to_string(), "EF56".to_string()),
+                ("IMPHASH".to_string(), "0011".to_string()),
+            ]
+        );
+        assert!(detail.data.iter().all(|(name, _)| name != "Hashes"));
+        let json = detail.to_json();
+        assert_eq!(json["hashes"]["SHA256"], "EF56");
+        assert_eq!(json["system"]["Computer"], "WS1");
+    }
+}
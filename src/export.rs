@@ -0,0 +1,814 @@
+use crate::analyzer::attack::tactic_summary;
+use crate::analyzer::rule_stats::RuleProfile;
+use crate::analyzer::{AggregatedAnomaly, Anomaly, AnomalyKind, Severity, rule_id_of};
+use crate::helpers::{HasProcess, HasSystem};
+use crate::incident::Incident;
+use crate::live_output::SessionSummary;
+use crate::sysmon::{Event as SysmonEvent, format_event_time};
+use crate::triage::finding_id;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// JSON Schema of the document built by [`anomaly_report`]
+pub const ANOMALY_REPORT_SCHEMA: &str = include_str!("../schemas/anomaly-report.schema.json");
+/// Bumped whenever a field of the anomaly report changes meaning or is removed
+pub const ANOMALY_REPORT_SCHEMA_VERSION: u32 = 2;
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 30;
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Supported forwarding destinations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportTarget {
+    /// Splunk HTTP Event Collector
+    Splunk,
+    /// Elasticsearch bulk API
+    Elastic,
+}
+
+/// Output formats of `parse --export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// STIX 2.1 bundle of indicators
+    Stix,
+    /// MISP event with attributes
+    Misp,
+    /// SARIF 2.1.0 log with one result per anomaly
+    Sarif,
+    /// Timesketch JSON Lines timeline of the events, and of the anomalies with --detect
+    Timesketch,
+}
+
+impl ExportFormat {
+    /// Whether the export is made of the detected anomalies only
+    pub fn needs_detection(self) -> bool {
+        self != ExportFormat::Timesketch
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    pub target: ExportTarget,
+    /// Full endpoint URL (e.g. https://splunk:8088/services/collector/event or http://es:9200/_bulk)
+    pub url: String,
+    /// Splunk HEC token or Elasticsearch API key
+    pub token: Option<String>,
+    pub index: Option<String>,
+    pub sourcetype: String,
+    pub batch_size: usize,
+    pub retries: u32,
+}
+
+/// Ships events and anomalies to a remote collector in batches
+pub struct Exporter {
+    config: ExportConfig,
+    agent: ureq::Agent,
+}
+
+impl Exporter {
+    pub fn new(config: ExportConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+            .build();
+        Self { config, agent }
+    }
+    /// Export documents, returns the number of documents sent
+    pub fn export(&self, documents: &[Value]) -> Result<usize> {
+        let batch_size = self.config.batch_size.max(1);
+        let mut sent = 0;
+        for (i, batch) in documents.chunks(batch_size).enumerate() {
+            let payload = match self.config.target {
+                ExportTarget::Splunk => build_splunk_payload(
+                    batch,
+                    &self.config.sourcetype,
+                    self.config.index.as_deref(),
+                ),
+                ExportTarget::Elastic => build_elastic_bulk_payload(
+                    batch,
+                    self.config.index.as_deref().unwrap_or("sysmon"),
+                ),
+            };
+            self.send_with_retry(&payload)?;
+            sent += batch.len();
+            info!("Exported batch {} ({} documents)", i + 1, batch.len());
+        }
+        Ok(sent)
+    }
+    fn send_with_retry(&self, payload: &str) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.send(payload) {
+                Ok(()) => return Ok(()),
+                Err(SendError::Fatal(e)) => return Err(e),
+                Err(SendError::Retryable(e)) if attempt < self.config.retries => {
+                    let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt);
+                    warn!("Export request failed ({e}), retrying in {delay}ms");
+                    thread::sleep(Duration::from_millis(delay));
+                    attempt += 1;
+                }
+                Err(SendError::Retryable(e)) => {
+                    return Err(e.context(format!("Export failed after {} retries", attempt)));
+                }
+            }
+        }
+    }
+    fn send(&self, payload: &str) -> std::result::Result<(), SendError> {
+        let (content_type, auth) = match self.config.target {
+            ExportTarget::Splunk => (
+                "application/json",
+                self.config.token.as_ref().map(|t| format!("Splunk {t}")),
+            ),
+            ExportTarget::Elastic => (
+                "application/x-ndjson",
+                self.config.token.as_ref().map(|t| format!("ApiKey {t}")),
+            ),
+        };
+        let mut request = self
+            .agent
+            .post(&self.config.url)
+            .set("Content-Type", content_type);
+        if let Some(auth) = auth {
+            request = request.set("Authorization", &auth);
+        }
+        match request.send_string(payload) {
+            Ok(response) => {
+                if self.config.target == ExportTarget::Elastic {
+                    check_elastic_response(response)
+                } else {
+                    Ok(())
+                }
+            }
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                let error = anyhow!("Collector returned HTTP {code}: {body}");
+                if code == 429 || code >= 500 {
+                    Err(SendError::Retryable(error))
+                } else {
+                    Err(SendError::Fatal(error))
+                }
+            }
+            Err(e) => Err(SendError::Retryable(anyhow!("Transport error: {e}"))),
+        }
+    }
+}
+
+enum SendError {
+    Retryable(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+/// Bulk API answers 200 even if individual documents were rejected
+fn check_elastic_response(response: ureq::Response) -> std::result::Result<(), SendError> {
+    let body: Value = response
+        .into_json()
+        .map_err(|e| SendError::Retryable(anyhow!("Invalid bulk response: {e}")))?;
+    if body.get("errors").and_then(Value::as_bool).unwrap_or(false) {
+        return Err(SendError::Fatal(anyhow!(
+            "Elasticsearch rejected some documents in the batch"
+        )));
+    }
+    Ok(())
+}
+
+/// Build a Splunk HEC payload (concatenated event objects)
+pub fn build_splunk_payload(documents: &[Value], sourcetype: &str, index: Option<&str>) -> String {
+    documents
+        .iter()
+        .map(|doc| {
+            let mut envelope = json!({
+                "sourcetype": sourcetype,
+                "event": doc,
+            });
+            if let Some(time) = doc
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .and_then(|ts| ts.parse::<DateTime<Utc>>().ok())
+            {
+                envelope["time"] = json!(time.timestamp_millis() as f64 / 1000.0);
+            }
+            if let Some(host) = doc.get("computer").filter(|h| !h.is_null()) {
+                envelope["host"] = host.clone();
+            }
+            if let Some(index) = index {
+                envelope["index"] = json!(index);
+            }
+            envelope.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Build an Elasticsearch bulk payload (NDJSON action/document pairs)
+pub fn build_elastic_bulk_payload(documents: &[Value], index: &str) -> String {
+    let mut payload = String::new();
+    for doc in documents {
+        payload.push_str(&json!({ "index": { "_index": index } }).to_string());
+        payload.push('\n');
+        payload.push_str(&doc.to_string());
+        payload.push('\n');
+    }
+    payload
+}
+
+/// Convert a Sysmon event into a flat JSON document
+pub fn event_to_json(event: &SysmonEvent) -> Value {
+    let system = event.system();
+    let mut doc = json!({
+        "kind": "event",
+        "timestamp": system.time_created.to_rfc3339(),
+        "event_id": system.event_id.event_id,
+        "event_type": event.name(),
+        "record_id": system.event_record_id.event_record_id,
+        "computer": system.computer.computer,
+    });
+    let fields = match event {
+        SysmonEvent::ProcessCreate(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "command_line": data.command_line.command_line,
+                "current_directory": data.current_directory.current_directory,
+                "user": data.user.user,
+                "logon_id": data.logon_id.logon_id,
+                "integrity_level": data.integrity_level.integrity_level,
+                "hashes": data.hashes.hashes,
+                "parent_process_guid": data.parent_process_guid.process_guid.to_string(),
+                "parent_process_id": data.parent_process_id,
+                "parent_image": data.parent_image.image,
+                "parent_command_line": data.parent_command_line.command_line,
+                "signed": data.signed,
+                "signature": data.signature,
+                "signature_status": data.signature_status,
+            })
+        }
+        SysmonEvent::FileCreate(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "target_filename": data.target_filename,
+                "creation_utc_time": data.creation_utc_time.utc_time,
+            })
+        }
+        SysmonEvent::FileStreamCreate(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "target_filename": data.target_filename,
+                "hash": data.hash.as_ref().map(|h| h.hashes.clone()),
+                "contents": data.contents,
+            })
+        }
+        SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "protocol": data.protocol,
+                "initiated": data.initiated,
+                "source_ip": data.source_ip,
+                "source_hostname": data.source_hostname,
+                "source_port": data.source_port,
+                "destination_ip": data.destination_ip,
+                "destination_hostname": data.destination_hostname,
+                "destination_port": data.destination_port,
+                "destination_port_name": data.destination_port_name,
+            })
+        }
+        SysmonEvent::FileDelete(e)
+        | SysmonEvent::FileBlock(e)
+        | SysmonEvent::FileExecutableDetected(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "target_filename": data.target_filename,
+                "hashes": data.hashes.as_ref().map(|h| h.hashes.clone()),
+                "is_executable": data.is_executable,
+                "archived": data.archived,
+            })
+        }
+        SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "pipe_name": data.pipe_name,
+            })
+        }
+        SysmonEvent::RegistrySetValue(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "target_object": data.target_object,
+                "details": data.details,
+            })
+        }
+        SysmonEvent::DnsQuery(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "query_name": data.query_name,
+                "query_status": data.query_status,
+                "query_results": data.query_results,
+            })
+        }
+        SysmonEvent::ProcessAccess(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "target_process_guid": data.target_process_guid.process_guid.to_string(),
+                "target_process_id": data.target_process_id,
+                "target_image": data.target_image.image,
+                "target_user": data.target_user.as_ref().map(|u| u.user.clone()),
+                "granted_access": data.granted_access,
+                "call_trace": data.call_trace,
+            })
+        }
+        SysmonEvent::ServiceStateChange(e) | SysmonEvent::ConfigurationChange(e) => {
+            let data = &e.event_data;
+            json!({
+                "state": data.state,
+                "version": data.version,
+                "schema_version": data.schema_version,
+                "configuration": data.configuration,
+                "configuration_file_hash": data.configuration_file_hash,
+            })
+        }
+        SysmonEvent::DriverLoad(e) => {
+            let data = &e.event_data;
+            json!({
+                "image_loaded": data.image_loaded.image,
+                "hashes": data.hashes.as_ref().map(|h| h.hashes.clone()),
+                "signed": data.signed,
+                "signature": data.signature,
+                "signature_status": data.signature_status,
+            })
+        }
+        SysmonEvent::RawAccessRead(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "device": data.device,
+            })
+        }
+        SysmonEvent::ClipboardChange(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "session": data.session,
+                "client_info": data.client_info,
+                "hashes": data.hashes.as_ref().map(|h| h.hashes.clone()),
+                "archived": data.archived,
+            })
+        }
+        SysmonEvent::ProcessTerminate(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+            })
+        }
+        SysmonEvent::ProcessTampering(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "type": data.tampering_type,
+            })
+        }
+        SysmonEvent::WmiFilter(e) | SysmonEvent::WmiConsumer(e) | SysmonEvent::WmiBinding(e) => {
+            let data = &e.event_data;
+            json!({
+                "user": data.user.as_ref().map(|u| u.user.clone()),
+                "wmi_event_type": data.event_type,
+                "operation": data.operation,
+                "event_namespace": data.event_namespace,
+                "name": data.name,
+                "query": data.query,
+                "consumer_type": data.consumer_type,
+                "destination": data.destination,
+                "consumer": data.consumer,
+                "filter": data.filter,
+            })
+        }
+        SysmonEvent::Logon(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "user": data.target_user.user,
+                "user_sid": data.target_user_sid,
+                "logon_id": data.target_logon_id.logon_id,
+                "logon_type": data.logon_type,
+                "logon_process": data.logon_process,
+                "authentication_package": data.authentication_package,
+                "workstation": data.workstation,
+                "source_ip": data.ip_address,
+                "source_port": data.ip_port,
+            })
+        }
+        SysmonEvent::ScriptBlock(e) => {
+            let data = &e.event_data;
+            json!({
+                "process_guid": data.process_guid.process_guid.to_string(),
+                "process_id": data.process_id,
+                "image": data.image.image,
+                "script_block_id": data.script_block_id,
+                "message_number": data.message_number,
+                "message_total": data.message_total,
+                "path": data.path,
+                "text": data.text,
+            })
+        }
+    };
+    if let (Some(doc), Value::Object(fields)) = (doc.as_object_mut(), fields) {
+        doc.extend(fields);
+    }
+    doc
+}
+
+/// Convert an anomaly into a JSON document
+pub fn anomaly_to_json(anomaly: &Anomaly) -> Value {
+    let event = anomaly.event();
+    json!({
+        "kind": "anomaly",
+        "timestamp": event.map(|e| e.system().time_created.to_rfc3339()),
+        "computer": event.map(|e| e.system().computer.computer.clone()),
+        "rule_id": anomaly.rule_id(),
+        "rule_name": anomaly.rule_name(),
+        "severity": anomaly.severity().to_string(),
+        "description": anomaly.description(),
+        "asset": asset_json(anomaly),
+        "event": event.map(event_to_json),
+    })
+}
+
+/// Inventory entry of the computer of the anomaly, null if it is not in the asset inventory
+fn asset_json(anomaly: &Anomaly) -> Value {
+    anomaly.asset().map_or(Value::Null, |asset| {
+        json!({
+            "role": asset.role,
+            "owner": asset.owner,
+            "criticality": asset.criticality.map(|criticality| criticality.to_string()),
+        })
+    })
+}
+
+/// Events of each computer in time order, to include the events around anomalies in reports
+pub struct EventContext<'a> {
+    size: usize,
+    hosts: HashMap<&'a str, Vec<&'a SysmonEvent>>,
+}
+
+impl<'a> EventContext<'a> {
+    /// Context of `size` events before and after each anomaly
+    pub fn new(events: impl IntoIterator<Item = &'a SysmonEvent>, size: usize) -> Self {
+        let mut hosts: HashMap<&str, Vec<&SysmonEvent>> = HashMap::new();
+        for event in events {
+            hosts
+                .entry(event.system().computer.computer.as_str())
+                .or_default()
+                .push(event);
+        }
+        for timeline in hosts.values_mut() {
+            timeline.sort_by_key(|event| Self::timeline_key(event));
+        }
+        Self { size, hosts }
+    }
+
+    /// Order of the events in a timeline
+    fn timeline_key(event: &SysmonEvent) -> (Option<DateTime<Utc>>, u32) {
+        let system = event.system();
+        (
+            system.time_created.time,
+            system.event_record_id.event_record_id,
+        )
+    }
+
+    /// Events logged on the same computer just before and after `event`, `None` if it is not known
+    fn around(&self, event: &SysmonEvent) -> Option<(&[&'a SysmonEvent], &[&'a SysmonEvent])> {
+        let system = event.system();
+        let timeline = self.hosts.get(system.computer.computer.as_str())?;
+        // Events of other logs may share the time and record ID
+        let key = Self::timeline_key(event);
+        let start = timeline.partition_point(|candidate| Self::timeline_key(candidate) < key);
+        let index = start
+            + timeline[start..]
+                .iter()
+                .take_while(|candidate| Self::timeline_key(candidate) == key)
+                .position(|candidate| {
+                    candidate.system().event_id.event_id == system.event_id.event_id
+                })?;
+        let before = &timeline[index.saturating_sub(self.size)..index];
+        let after = &timeline[index + 1..(index + 1 + self.size).min(timeline.len())];
+        Some((before, after))
+    }
+
+    fn to_json(&self, event: &SysmonEvent) -> Value {
+        let Some((before, after)) = self.around(event) else {
+            return Value::Null;
+        };
+        let documents = |events: &[&SysmonEvent]| -> Vec<Value> {
+            events.iter().map(|event| event_to_json(event)).collect()
+        };
+        json!({
+            "before": documents(before),
+            "after": documents(after),
+        })
+    }
+}
+
+/// Machine-readable report of detected anomalies, see [`ANOMALY_REPORT_SCHEMA`]
+pub fn anomaly_report(
+    files: &[String],
+    total_events: usize,
+    filtered_events: usize,
+    anomalies: &[AggregatedAnomaly],
+    context: Option<&EventContext>,
+) -> Value {
+    json!({
+        "schema_version": ANOMALY_REPORT_SCHEMA_VERSION,
+        "files": files,
+        "total_events": total_events,
+        "filtered_events": filtered_events,
+        "anomalies": anomalies.iter().map(|group| {
+            let anomaly = &group.anomaly;
+            let mut entry = json!({
+                "finding_id": finding_id(anomaly),
+                "rule_id": anomaly.rule_id(),
+                "rule_name": anomaly.rule_name(),
+                "severity": anomaly.severity().to_string(),
+                "attack": anomaly.attack_techniques(),
+                "description": anomaly.description(),
+                "count": group.count,
+                "first_seen": group.first_seen.map(format_event_time),
+                "last_seen": group.last_seen.map(format_event_time),
+                "event": anomaly.event().map(event_reference),
+            });
+            if let AnomalyKind::EventStorm { top_processes, .. } = anomaly.kind() {
+                entry["top_processes"] = top_processes.iter().map(|(process, count)| json!({
+                    "process": process,
+                    "count": count,
+                })).collect();
+            }
+            if let AnomalyKind::DroppedAndExecuted { dropped, .. } = anomaly.kind() {
+                entry["dropped_event"] = event_reference(dropped);
+            }
+            let asset = asset_json(anomaly);
+            if !asset.is_null() {
+                entry["asset"] = asset;
+            }
+            if let Some(context) = context {
+                entry["context"] = anomaly
+                    .event()
+                    .map_or(Value::Null, |event| context.to_json(event));
+            }
+            entry
+        }).collect::<Vec<_>>(),
+        "tactics": tactic_summary(anomalies).iter().map(|summary| json!({
+            "tactic_id": summary.tactic.id(),
+            "tactic": summary.tactic.to_string(),
+            "count": summary.count,
+            "top_processes": summary.top_processes.iter().map(|(process, count)| json!({
+                "process": process,
+                "count": count,
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// Report of a monitoring session: its summary, the anomalies it found and the events still
+/// buffered as detection context when it stopped
+pub fn session_report(summary: &SessionSummary, events: &[SysmonEvent]) -> Value {
+    let counts = summary.anomalies_by_severity();
+    json!({
+        "started": summary.started.to_rfc3339(),
+        "stopped": summary.stopped.to_rfc3339(),
+        "duration_seconds": summary.duration().num_seconds(),
+        "total_events": summary.total_events(),
+        "events_by_id": summary.events_by_id().map(|(id, name, count)| json!({
+            "event_id": id,
+            "event_type": name,
+            "count": count,
+        })).collect::<Vec<_>>(),
+        "anomalies_by_severity": Severity::value_variants().iter().rev().map(|severity| {
+            (severity.to_string(), json!(counts[*severity as usize]))
+        }).collect::<serde_json::Map<_, _>>(),
+        "top_processes": summary.top_processes().iter().map(|(process, count)| json!({
+            "process": process,
+            "count": count,
+        })).collect::<Vec<_>>(),
+        "anomalies": summary.anomalies.iter().map(anomaly_to_json).collect::<Vec<_>>(),
+        "events": events.iter().map(event_to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Rule statistics of `--profile-rules`, slowest rule first
+pub fn rule_profile_to_json(profile: &RuleProfile) -> Value {
+    profile
+        .by_time()
+        .into_iter()
+        .map(|(rule, stats)| {
+            json!({
+                "rule_id": rule_id_of(rule),
+                "rule_name": rule,
+                "evaluations": stats.evaluations,
+                "time_ms": stats.time.as_secs_f64() * 1e3,
+                "hits": stats.hits,
+                "suppressed": stats.suppressed,
+            })
+        })
+        .collect()
+}
+
+/// Incidents with the finding IDs of their findings, as listed in the `anomalies` of the report
+pub fn incidents_to_json(incidents: &[Incident]) -> Value {
+    incidents
+        .iter()
+        .map(|incident| {
+            json!({
+                "incident_id": incident.id,
+                "computer": incident.computer,
+                "score": incident.score,
+                "severity": incident.severity().to_string(),
+                "count": incident.anomaly_count(),
+                "first_seen": incident.first_seen.map(format_event_time),
+                "last_seen": incident.last_seen.map(format_event_time),
+                "processes": incident.processes,
+                "findings": incident
+                    .findings
+                    .iter()
+                    .map(|group| finding_id(&group.anomaly))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+/// Fields identifying an event in the original log
+fn event_reference(event: &SysmonEvent) -> Value {
+    let system = event.system();
+    json!({
+        "event_id": system.event_id.event_id,
+        "record_id": system.event_record_id.event_record_id,
+        "computer": system.computer.computer,
+        "timestamp": system.time_created.to_rfc3339(),
+        "process_guid": event.process_guid().to_string(),
+        "process_id": event.process_id(),
+        "image": event.image(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_documents() -> Vec<Value> {
+        vec![
+            json!({"timestamp": "2025-01-01T10:00:00.000Z", "computer": "TEST-PC", "event_id": 1}),
+            json!({"timestamp": "2025-01-01T10:00:01.500Z", "computer": "TEST-PC", "event_id": 3}),
+        ]
+    }
+
+    #[test]
+    fn splunk_payload_wraps_each_document() {
+        let payload = build_splunk_payload(&sample_documents(), "sysmon", Some("main"));
+        let lines: Vec<Value> = payload
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["sourcetype"], "sysmon");
+        assert_eq!(lines[0]["index"], "main");
+        assert_eq!(lines[0]["host"], "TEST-PC");
+        assert_eq!(lines[1]["time"], 1735725601.5);
+        assert_eq!(lines[1]["event"]["event_id"], 3);
+    }
+
+    #[test]
+    fn elastic_payload_has_action_lines() {
+        let payload = build_elastic_bulk_payload(&sample_documents(), "sysmon-2025");
+        let lines: Vec<&str> = payload.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(payload.ends_with('\n'));
+        let action: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(action["index"]["_index"], "sysmon-2025");
+    }
+
+    #[test]
+    fn anomaly_report_has_schema_fields() {
+        let anomalies = crate::analyzer::aggregate_anomalies(&[AnomalyKind::EventStorm {
+            event_id: 3,
+            count: 500,
+            time_window_seconds: 60,
+            start: Some("2025-01-01T10:00:00Z".parse().unwrap()),
+            end: Some("2025-01-01T10:01:00Z".parse().unwrap()),
+            top_processes: vec![
+                ("svchost.exe".to_string(), 400),
+                ("lsass.exe".to_string(), 100),
+            ],
+        }
+        .into()]);
+        let report = anomaly_report(&["Sysmon.evtx".to_string()], 600, 550, &anomalies, None);
+        let schema: Value = serde_json::from_str(ANOMALY_REPORT_SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            ANOMALY_REPORT_SCHEMA_VERSION
+        );
+        let required = |schema: &Value| -> Vec<String> {
+            schema["required"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|field| field.as_str().unwrap().to_string())
+                .collect()
+        };
+        for field in required(&schema) {
+            assert!(report.get(&field).is_some(), "missing {field}");
+        }
+        let anomaly = &report["anomalies"][0];
+        for field in required(&schema["properties"]["anomalies"]["items"]) {
+            assert!(anomaly.get(&field).is_some(), "missing anomalies[].{field}");
+        }
+        assert_eq!(anomaly["rule_id"], "SLA-005");
+        assert_eq!(anomaly["rule_name"], "EventStorm");
+        assert_eq!(anomaly["severity"], "High");
+        assert_eq!(anomaly["event"], Value::Null);
+        assert_eq!(anomaly["first_seen"], "2025-01-01T10:00:00Z");
+        assert_eq!(anomaly["last_seen"], "2025-01-01T10:01:00Z");
+        assert_eq!(anomaly["top_processes"][0]["process"], "svchost.exe");
+        assert_eq!(anomaly["top_processes"][1]["count"], 100);
+    }
+
+    #[test]
+    fn anomaly_context_per_host() {
+        let record = |id: u32, host: &str| {
+            let line = format!(
+                r#"{{"EventTime":"2025-01-01 10:00:{id:02}","Hostname":"{host}","EventID":22,"RecordNumber":{id},"UtcTime":"2025-01-01 10:00:00.000","ProcessGuid":"{{11111111-2222-3333-4444-555555555555}}","ProcessId":"4242","QueryName":"example.com","Image":"C:\\Windows\\System32\\cmd.exe"}}"#
+            );
+            crate::jsonl::parse_json_event(&line).unwrap()
+        };
+        // Out of order and interleaved with another host
+        let events: Vec<SysmonEvent> = [5, 1, 4, 2, 3]
+            .into_iter()
+            .map(|id| record(id, "TEST-PC"))
+            .chain([record(6, "OTHER-PC")])
+            .collect();
+        let anomalies = crate::analyzer::aggregate_anomalies(&[AnomalyKind::UntrustedExecutable {
+            event: std::sync::Arc::new(record(2, "TEST-PC")),
+            reason: "test".to_string(),
+        }
+        .into()]);
+        let context = EventContext::new(&events, 2);
+        let report = anomaly_report(&[], 6, 6, &anomalies, Some(&context));
+        let record_ids = |events: &Value| -> Vec<u64> {
+            events
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|event| event["record_id"].as_u64().unwrap())
+                .collect()
+        };
+        let context = &report["anomalies"][0]["context"];
+        assert_eq!(record_ids(&context["before"]), [1]);
+        assert_eq!(record_ids(&context["after"]), [3, 4]);
+    }
+}
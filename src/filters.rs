@@ -1,23 +1,250 @@
-use crate::helpers::HasSystem;
-use crate::sysmon::Event as SysmonEvent;
-use chrono::{DateTime, Utc};
+pub mod network;
+pub mod path_glob;
+pub mod query;
+
+use crate::analyzer::parent_child::glob_match;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::{Event as SysmonEvent, User};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use network::NetworkFilter;
+use path_glob::PathGlob;
+use query::Query;
+use std::fmt::{self, Display, Formatter};
+use uuid::Uuid;
+
+/// How multiple search terms are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SearchMode {
+    /// Event must match at least one term
+    #[default]
+    Any,
+    /// Event must match every term
+    All,
+}
+
+/// Account an event was logged for, the target account of logons
+pub(crate) fn event_user(event: &SysmonEvent) -> Option<&User> {
+    match event {
+        SysmonEvent::ProcessCreate(e) => Some(&e.event_data.user),
+        SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => {
+            e.event_data.user.as_ref()
+        }
+        SysmonEvent::FileDelete(e)
+        | SysmonEvent::FileBlock(e)
+        | SysmonEvent::FileExecutableDetected(e) => e.event_data.user.as_ref(),
+        SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => e.event_data.user.as_ref(),
+        SysmonEvent::RegistrySetValue(e) => e.event_data.user.as_ref(),
+        SysmonEvent::DnsQuery(e) => e.event_data.user.as_ref(),
+        SysmonEvent::ProcessAccess(e) => e.event_data.user.as_ref(),
+        SysmonEvent::RawAccessRead(e) => e.event_data.user.as_ref(),
+        SysmonEvent::ClipboardChange(e) => e.event_data.user.as_ref(),
+        SysmonEvent::FileStreamCreate(e) => e.event_data.user.as_ref(),
+        SysmonEvent::ProcessTampering(e) => e.event_data.user.as_ref(),
+        SysmonEvent::ProcessTerminate(e) => e.event_data.user.as_ref(),
+        SysmonEvent::WmiFilter(e) | SysmonEvent::WmiConsumer(e) | SysmonEvent::WmiBinding(e) => {
+            e.event_data.user.as_ref()
+        }
+        SysmonEvent::Logon(e) => Some(&e.event_data.target_user),
+        SysmonEvent::FileCreate(_)
+        | SysmonEvent::ScriptBlock(_)
+        | SysmonEvent::ServiceStateChange(_)
+        | SysmonEvent::ConfigurationChange(_)
+        | SysmonEvent::DriverLoad(_) => None,
+    }
+}
+
+/// Mandatory integrity level of a process, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Integrity {
+    Untrusted,
+    Low,
+    Medium,
+    High,
+    System,
+}
+
+impl Integrity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Integrity::Untrusted => "Untrusted",
+            Integrity::Low => "Low",
+            Integrity::Medium => "Medium",
+            Integrity::High => "High",
+            Integrity::System => "System",
+        }
+    }
+    /// Level of an `IntegrityLevel` value such as `High`, `None` when unknown
+    pub fn from_level(level: &str) -> Option<Self> {
+        Self::from_str(level.trim(), true).ok()
+    }
+}
+
+impl Display for Integrity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse a time given as RFC 3339 (`2025-01-01T10:00:00+02:00`), `YYYY-MM-DD HH:MM[:SS[.fff]]`
+/// or `YYYY-MM-DD`; times without an offset are UTC
+pub fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = s.parse::<DateTime<Utc>>() {
+        return Ok(time);
+    }
+    const FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ];
+    FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|time| time.and_utc())
+        .ok_or_else(|| {
+            format!("invalid time '{s}' (expected e.g. 2025-01-01 10:00:00 or RFC 3339)")
+        })
+}
+
+/// Parse a logon ID given in hex (`0x3e7`) or decimal (`999`)
+pub fn parse_logon_id(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid logon ID '{s}'"))
+}
+
+/// One element of an Event ID list: a single ID, a range (`1-10`) or an alias (`network`),
+/// optionally negated with `!`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventIdSpec {
+    pub ids: Vec<u16>,
+    pub exclude: bool,
+}
+
+/// Named groups of Sysmon Event IDs
+const EVENT_ID_ALIASES: &[(&str, &[u16])] = &[
+    ("process", &[1, 5, 8, 10, 25, 4688]),
+    ("network", &[3, 5156]),
+    ("dns", &[22]),
+    ("registry", &[12, 13, 14]),
+    ("file", &[2, 11, 15, 23, 26, 27, 28, 29]),
+    ("pipe", &[17, 18]),
+    ("wmi", &[19, 20, 21]),
+    ("logon", &[4624]),
+    ("powershell", &[4104]),
+];
+
+/// Parse an Event ID list element such as `3`, `1-10`, `!22` or `registry`
+pub fn parse_event_id_spec(s: &str) -> Result<EventIdSpec, String> {
+    let s = s.trim();
+    let (exclude, value) = match s.strip_prefix('!') {
+        Some(value) => (true, value.trim()),
+        None => (false, s),
+    };
+    let parse_id = |id: &str| {
+        id.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid event ID '{id}'"))
+    };
+    let ids = if let Some((_, ids)) = EVENT_ID_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(value))
+    {
+        ids.to_vec()
+    } else if let Some((start, end)) = value.split_once('-') {
+        let (start, end) = (parse_id(start)?, parse_id(end)?);
+        if start > end {
+            return Err(format!("invalid event ID range '{value}'"));
+        }
+        (start..=end).collect()
+    } else {
+        vec![parse_id(value)?]
+    };
+    Ok(EventIdSpec { ids, exclude })
+}
+
+/// XPath alternatives for a sorted list of Event IDs, collapsing consecutive IDs into ranges
+fn xpath_id_conditions(ids: &[u16]) -> String {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for &id in ids {
+        match ranges.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(id) => *end = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("EventID={start}")
+            } else {
+                format!("(EventID>={start} and EventID<={end})")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct EventFilter {
-    event_ids: Option<Vec<u8>>,
+    event_ids: Option<Vec<u16>>,
+    excluded_event_ids: Vec<u16>,
     after: Option<DateTime<Utc>>,
     before: Option<DateTime<Utc>>,
-    search_term: Option<String>,
+    search_terms: Vec<String>,
+    search_mode: SearchMode,
+    exclude_terms: Vec<String>,
+    case_sensitive: bool,
+    user: Option<String>,
+    logon_id: Option<u64>,
+    integrity: Option<Integrity>,
+    image: Option<String>,
+    path_globs: Vec<PathGlob>,
+    computers: Vec<String>,
+    process_guids: Vec<Uuid>,
+    network: NetworkFilter,
+    query: Option<Query>,
 }
 
 impl EventFilter {
     pub fn new() -> Self {
         Self::default()
     }
-    pub fn with_event_ids(mut self, ids: Option<Vec<u8>>) -> Self {
+    pub fn with_event_ids(mut self, ids: Option<Vec<u16>>) -> Self {
         self.event_ids = ids;
         self
     }
+    /// Combine ID, range and alias specs; excluded IDs win over included ones
+    pub fn with_event_id_specs(mut self, specs: Option<Vec<EventIdSpec>>) -> Self {
+        let mut included: Vec<u16> = Vec::new();
+        let mut excluded: Vec<u16> = Vec::new();
+        for spec in specs.into_iter().flatten() {
+            if spec.exclude {
+                excluded.extend(spec.ids);
+            } else {
+                included.extend(spec.ids);
+            }
+        }
+        for ids in [&mut included, &mut excluded] {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        self.event_ids = (!included.is_empty()).then_some(included);
+        self.excluded_event_ids = excluded;
+        self
+    }
     pub fn with_time_range(
         mut self,
         after: Option<DateTime<Utc>>,
@@ -27,78 +254,323 @@ impl EventFilter {
         self.before = before;
         self
     }
-    pub fn with_search_term(mut self, term: Option<String>) -> Self {
-        self.search_term = term.map(|s| s.to_lowercase());
+    pub fn with_search_term(self, term: Option<String>) -> Self {
+        self.with_search_terms(term.into_iter().collect())
+    }
+    pub fn with_search_terms(mut self, terms: Vec<String>) -> Self {
+        self.search_terms = terms;
+        self
+    }
+    pub fn with_search_mode(mut self, mode: SearchMode) -> Self {
+        self.search_mode = mode;
+        self
+    }
+    pub fn with_exclude_terms(mut self, terms: Vec<String>) -> Self {
+        self.exclude_terms = terms;
+        self
+    }
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+    /// Keep events of this account (`DOMAIN\user` or just `user`, case-insensitive)
+    pub fn with_user(mut self, user: Option<String>) -> Self {
+        self.user = user;
+        self
+    }
+    /// Keep events of this process image (full path or file name, case-insensitive)
+    pub fn with_image(mut self, image: Option<String>) -> Self {
+        self.image = image;
+        self
+    }
+    /// Keep events whose process image or, for file events, target file matches any of these globs
+    pub fn with_path_globs(mut self, globs: Vec<PathGlob>) -> Self {
+        self.path_globs = globs;
+        self
+    }
+    /// Keep events logged by a computer matching any of these names or globs (`*` and `?`,
+    /// case-insensitive). A name without a domain also matches the fully qualified name.
+    pub fn with_computers(mut self, computers: Vec<String>) -> Self {
+        self.computers = computers
+            .into_iter()
+            .map(|computer| computer.trim().to_lowercase())
+            .collect();
+        self
+    }
+    /// Keep events generated by one of these processes
+    pub fn with_process_guids(mut self, guids: Vec<Uuid>) -> Self {
+        self.process_guids = guids;
+        self
+    }
+    /// Keep only events matching a `--where` expression
+    pub fn with_query(mut self, query: Option<Query>) -> Self {
+        self.query = query;
         self
     }
-    pub fn get_event_ids(&self) -> Option<&Vec<u8>> {
+    /// Require `query` in addition to the expression set by [`Self::with_query`]
+    pub fn and_query(mut self, query: Option<Query>) -> Self {
+        self.query = match (self.query.take(), query) {
+            (Some(current), Some(query)) => Some(current.and(query)),
+            (current, query) => current.or(query),
+        };
+        self
+    }
+    /// Keep network connections matching destination, protocol and direction criteria
+    pub fn with_network(mut self, network: NetworkFilter) -> Self {
+        self.network = network;
+        self
+    }
+    pub fn with_logon_id(mut self, logon_id: Option<u64>) -> Self {
+        self.logon_id = logon_id;
+        self
+    }
+    pub fn with_integrity(mut self, integrity: Option<Integrity>) -> Self {
+        self.integrity = integrity;
+        self
+    }
+    pub fn get_event_ids(&self) -> Option<&Vec<u16>> {
         self.event_ids.as_ref()
     }
+    pub fn get_excluded_event_ids(&self) -> &[u16] {
+        &self.excluded_event_ids
+    }
+    pub fn get_time_range(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        (self.after, self.before)
+    }
+    pub fn get_process_guids(&self) -> &[Uuid] {
+        &self.process_guids
+    }
+    /// Check the event ID, creation time and computer, the `System` values that can be read
+    /// before the rest of an event is rendered and parsed
+    pub fn matches_system(
+        &self,
+        event_id: u16,
+        time: Option<DateTime<Utc>>,
+        computer: &str,
+    ) -> bool {
+        if self.excluded_event_ids.contains(&event_id) {
+            return false;
+        }
+        if let Some(ids) = &self.event_ids
+            && !ids.contains(&event_id)
+        {
+            return false;
+        }
+        // Events without a valid time can't be placed in the range
+        if let Some(after) = self.after
+            && time.is_none_or(|time| time < after)
+        {
+            return false;
+        }
+        if let Some(before) = self.before
+            && time.is_none_or(|time| time > before)
+        {
+            return false;
+        }
+        self.computers.is_empty() || self.matches_computer(computer)
+    }
     pub fn matches(&self, event: &SysmonEvent) -> bool {
-        if let Some(ref ids) = self.event_ids {
-            if !ids.contains(&event.system().event_id.event_id) {
-                return false;
-            }
+        let system = event.system();
+        if !self.matches_system(
+            system.event_id.event_id,
+            system.time_created.time,
+            &system.computer.computer,
+        ) {
+            return false;
         }
-        if let Some(after) = self.after {
-            if event.system().time_created.system_time < after.to_string() {
-                return false;
-            }
+        if !self.process_guids.is_empty() && !self.process_guids.contains(&event.process_guid()) {
+            return false;
         }
-        if let Some(before) = self.before {
-            if event.system().time_created.system_time > before.to_string() {
-                return false;
-            }
+        if !self.matches_identity(event) {
+            return false;
+        }
+        if !self.path_globs.is_empty() && !self.matches_path(event) {
+            return false;
+        }
+        if !self.network.is_empty() && !self.network.matches(event) {
+            return false;
+        }
+        if let Some(query) = &self.query
+            && !query.matches(event)
+        {
+            return false;
+        }
+
+        // Exclusion filter
+        if self
+            .exclude_terms
+            .iter()
+            .any(|term| self.search_matches(event, term))
+        {
+            return false;
         }
 
         // Search term filter
-        if let Some(ref search) = self.search_term {
-            if !self.search_matches(event, search) {
+        if !self.search_terms.is_empty() {
+            let matched = match self.search_mode {
+                SearchMode::Any => self
+                    .search_terms
+                    .iter()
+                    .any(|term| self.search_matches(event, term)),
+                SearchMode::All => self
+                    .search_terms
+                    .iter()
+                    .all(|term| self.search_matches(event, term)),
+            };
+            if !matched {
                 return false;
             }
         }
 
         true
     }
+    /// Check whether the term occurs in the computer name or any event data field
     pub fn search_matches(&self, event: &SysmonEvent, search: &str) -> bool {
-        if event
-            .system()
-            .computer
-            .computer
-            .to_lowercase()
-            .contains(search)
-        {
-            return true;
-        }
-        let check = |s: &str| s.to_lowercase().contains(search);
-
-        match event {
-            SysmonEvent::ProcessCreate(proc) => {
-                let data = &proc.event_data;
-                check(&data.image.image)
-                    || check(&data.command_line.command_line)
-                    || check(&data.user.user)
-                    || check(&data.parent_image.image)
+        let search = if self.case_sensitive {
+            search.to_string()
+        } else {
+            search.to_lowercase()
+        };
+        let check = |s: &str| {
+            if self.case_sensitive {
+                s.contains(&search)
+            } else {
+                s.to_lowercase().contains(&search)
             }
+        };
+        check(&event.system().computer.computer)
+            || event.fields().iter().any(|(_, value)| check(value))
+    }
+    fn matches_path(&self, event: &SysmonEvent) -> bool {
+        let target = match event {
+            SysmonEvent::FileCreate(e) => Some(&e.event_data.target_filename),
+            SysmonEvent::FileStreamCreate(e) => Some(&e.event_data.target_filename),
+            SysmonEvent::FileDelete(e)
+            | SysmonEvent::FileBlock(e)
+            | SysmonEvent::FileExecutableDetected(e) => Some(&e.event_data.target_filename),
+            _ => None,
+        };
+        [Some(event.image()), target.map(String::as_str)]
+            .into_iter()
+            .flatten()
+            .any(|path| self.path_globs.iter().any(|glob| glob.matches(path)))
+    }
+    fn matches_computer(&self, computer: &str) -> bool {
+        let computer = computer.to_lowercase();
+        let host = computer.split('.').next().unwrap_or(&computer);
+        self.computers
+            .iter()
+            .any(|pattern| glob_match(pattern, &computer) || glob_match(pattern, host))
+    }
+    /// Build an XPath query selecting a superset of the events this filter matches,
+    /// so the Event Log service drops the rest before they are rendered and parsed.
+    /// `since` limits the query to events created within that duration from now.
+    pub fn to_xpath(&self, since: Option<Duration>) -> String {
+        let mut system = Vec::new();
+        if let Some(ids) = self.event_ids.as_deref().filter(|ids| !ids.is_empty()) {
+            system.push(format!("({})", xpath_id_conditions(ids)));
+        }
+        if !self.excluded_event_ids.is_empty() {
+            system.push(format!(
+                "not({})",
+                xpath_id_conditions(&self.excluded_event_ids)
+            ));
+        }
+        if let Some(after) = self.after {
+            system.push(format!(
+                "TimeCreated[@SystemTime>='{}']",
+                after.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ));
+        }
+        if let Some(before) = self.before {
+            system.push(format!(
+                "TimeCreated[@SystemTime<='{}']",
+                before.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ));
+        }
+        if let Some(since) = since {
+            system.push(format!(
+                "TimeCreated[timediff(@SystemTime) <= {}]",
+                since.num_milliseconds()
+            ));
+        }
 
-            SysmonEvent::FileCreate(file) => {
-                let data = &file.event_data;
-                check(&data.image.image) || check(&data.target_filename)
+        // Computer, image and user are left to `matches`: XPath compares values case-sensitively,
+        // while host names, Windows paths and account names are not
+        if system.is_empty() {
+            "*".to_string()
+        } else {
+            format!("*[System[{}]]", system.join(" and "))
+        }
+    }
+    /// Check user, logon ID and integrity level criteria.
+    /// Events that do not carry a requested field never match.
+    fn matches_identity(&self, event: &SysmonEvent) -> bool {
+        if let Some(ref wanted) = self.image {
+            let image = match event {
+                SysmonEvent::ProcessCreate(e) => &e.event_data.image.image,
+                SysmonEvent::FileCreate(e) => &e.event_data.image.image,
+                SysmonEvent::FileStreamCreate(e) => &e.event_data.image.image,
+                SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => {
+                    &e.event_data.image.image
+                }
+                SysmonEvent::FileDelete(e)
+                | SysmonEvent::FileBlock(e)
+                | SysmonEvent::FileExecutableDetected(e) => &e.event_data.image.image,
+                SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => {
+                    &e.event_data.image.image
+                }
+                SysmonEvent::RegistrySetValue(e) => &e.event_data.image.image,
+                SysmonEvent::DnsQuery(e) => &e.event_data.image.image,
+                SysmonEvent::ProcessAccess(e) => &e.event_data.image.image,
+                SysmonEvent::ServiceStateChange(e) | SysmonEvent::ConfigurationChange(e) => {
+                    &e.event_data.image.image
+                }
+                SysmonEvent::DriverLoad(e) => &e.event_data.image.image,
+                SysmonEvent::RawAccessRead(e) => &e.event_data.image.image,
+                SysmonEvent::ClipboardChange(e) => &e.event_data.image.image,
+                SysmonEvent::ProcessTampering(e) => &e.event_data.image.image,
+                SysmonEvent::ProcessTerminate(e) => &e.event_data.image.image,
+                SysmonEvent::WmiFilter(e)
+                | SysmonEvent::WmiConsumer(e)
+                | SysmonEvent::WmiBinding(e) => &e.event_data.image.image,
+                SysmonEvent::Logon(e) => &e.event_data.image.image,
+                SysmonEvent::ScriptBlock(e) => &e.event_data.image.image,
+            };
+            let file_name = image.rsplit('\\').next().unwrap_or(image);
+            if !image.eq_ignore_ascii_case(wanted) && !file_name.eq_ignore_ascii_case(wanted) {
+                return false;
             }
-
-            SysmonEvent::InboundNetwork(net) | SysmonEvent::OutboundNetwork(net) => {
-                let data = &net.event_data;
-                check(&data.image)
-                    || check(&data.destination_ip)
-                    || check(
-                        &data
-                            .user
-                            .as_ref()
-                            .map(|s| s.user.to_owned())
-                            .unwrap_or_else(|| "".to_string()),
-                    )
+        }
+        if let Some(ref wanted) = self.user {
+            let user = event_user(event);
+            let Some(user) = user else {
+                return false;
+            };
+            let account = user.user.rsplit('\\').next().unwrap_or(&user.user);
+            if !user.user.eq_ignore_ascii_case(wanted) && !account.eq_ignore_ascii_case(wanted) {
+                return false;
             }
         }
+        if self.logon_id.is_none() && self.integrity.is_none() {
+            return true;
+        }
+        // Logon events carry the session they created, which correlates them with its processes
+        let (logon_id, integrity_level) = match event {
+            SysmonEvent::ProcessCreate(e) => (
+                &e.event_data.logon_id,
+                Some(&e.event_data.integrity_level.integrity_level),
+            ),
+            SysmonEvent::Logon(e) => (&e.event_data.target_logon_id, None),
+            _ => return false,
+        };
+        let logon_matches = self
+            .logon_id
+            .is_none_or(|id| parse_logon_id(&logon_id.logon_id) == Ok(id));
+        let integrity_matches = self.integrity.is_none_or(|level| {
+            integrity_level.is_some_and(|integrity| integrity.eq_ignore_ascii_case(level.as_str()))
+        });
+        logon_matches && integrity_matches
     }
     pub fn apply(&self, events: &[SysmonEvent]) -> Vec<SysmonEvent> {
         events
@@ -108,3 +580,265 @@ impl EventFilter {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROCESS_CREATE: &str = r#"
+    <Event>
+      <System>
+        <Provider Name="Microsoft-Windows-Sysmon" Guid="{...}" />
+        <EventID>1</EventID>
+        <Version>5</Version>
+        <Level>4</Level>
+        <Task>1</Task>
+        <Opcode>0</Opcode>
+        <Keywords>0x8000000000000000</Keywords>
+        <TimeCreated SystemTime="2025-01-01T10:00:00.000Z"/>
+        <EventRecordID>42</EventRecordID>
+        <Execution ProcessID="1000" ThreadID="2000"/>
+        <Channel>Microsoft-Windows-Sysmon/Operational</Channel>
+        <Computer>TEST-PC</Computer>
+        <Security UserID="S-1-5-18"/>
+      </System>
+      <EventData>
+        <Data Name="UtcTime">2025-01-01 10:00:00.000</Data>
+        <Data Name="ProcessGuid">{11111111-2222-3333-4444-555555555555}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="Image">C:\Windows\System32\cmd.exe</Data>
+        <Data Name="CommandLine">cmd.exe /c whoami</Data>
+        <Data Name="CurrentDirectory">C:\Users\Test</Data>
+        <Data Name="User">TEST-PC\Administrator</Data>
+        <Data Name="LogonGuid">{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}</Data>
+        <Data Name="LogonId">0x3e7</Data>
+        <Data Name="TerminalSessionId">1</Data>
+        <Data Name="IntegrityLevel">High</Data>
+        <Data Name="Hashes">SHA1=1234567890ABCDEF</Data>
+        <Data Name="ParentProcessGuid">{99999999-8888-7777-6666-555555555555}</Data>
+        <Data Name="ParentProcessId">4321</Data>
+        <Data Name="ParentImage">C:\Windows\explorer.exe</Data>
+        <Data Name="ParentCommandLine">explorer.exe</Data>
+      </EventData>
+    </Event>
+    "#;
+
+    fn event() -> SysmonEvent {
+        SysmonEvent::from_str(PROCESS_CREATE).unwrap()
+    }
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn search_any_and_all() {
+        let any = EventFilter::new().with_search_terms(terms(&["whoami", "powershell"]));
+        assert!(any.matches(&event()));
+        let all = any.clone().with_search_mode(SearchMode::All);
+        assert!(!all.matches(&event()));
+        let all = all.with_search_terms(terms(&["whoami", "explorer"]));
+        assert!(all.matches(&event()));
+    }
+
+    #[test]
+    fn search_covers_every_data_field() {
+        let event = event();
+        assert_eq!(event.fields().get("LogonId"), Some("0x3e7"));
+        // Hashes, GUIDs and the working directory are only reachable through the generic fields
+        for term in ["1234567890abcdef", "AAAAAAAA-BBBB", r"C:\Users\Test"] {
+            let filter = EventFilter::new().with_search_terms(terms(&[term]));
+            assert!(filter.matches(&event), "{term}");
+        }
+        let filter = EventFilter::new().with_search_terms(terms(&["SHA256"]));
+        assert!(!filter.matches(&event));
+    }
+
+    #[test]
+    fn exclude_drops_matching_events() {
+        let filter = EventFilter::new().with_exclude_terms(terms(&["EXPLORER.EXE"]));
+        assert!(!filter.matches(&event()));
+        let filter = EventFilter::new().with_exclude_terms(terms(&["notepad"]));
+        assert!(filter.matches(&event()));
+    }
+
+    #[test]
+    fn case_sensitive_search() {
+        let filter = EventFilter::new()
+            .with_search_terms(terms(&["CMD.EXE"]))
+            .with_case_sensitive(true);
+        assert!(!filter.matches(&event()));
+        let filter = filter.with_case_sensitive(false);
+        assert!(filter.matches(&event()));
+    }
+
+    #[test]
+    fn event_id_ranges_aliases_and_exclusions() {
+        let specs: Vec<EventIdSpec> = ["1-10", "!3", "registry"]
+            .iter()
+            .map(|s| parse_event_id_spec(s).unwrap())
+            .collect();
+        let filter = EventFilter::new().with_event_id_specs(Some(specs));
+        assert_eq!(
+            filter.get_event_ids(),
+            Some(&vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 13, 14])
+        );
+        assert_eq!(filter.get_excluded_event_ids(), [3]);
+        assert!(filter.matches(&event()));
+
+        let filter = EventFilter::new()
+            .with_event_id_specs(Some(vec![parse_event_id_spec("!process").unwrap()]));
+        assert_eq!(filter.get_event_ids(), None);
+        assert!(!filter.matches(&event()));
+
+        assert!(parse_event_id_spec("10-1").is_err());
+        assert!(parse_event_id_spec("70000").is_err());
+        assert!(parse_event_id_spec("bogus").is_err());
+    }
+
+    #[test]
+    fn xpath_query() {
+        assert_eq!(EventFilter::new().to_xpath(None), "*");
+        let filter = EventFilter::new()
+            .with_event_id_specs(Some(vec![
+                parse_event_id_spec("1").unwrap(),
+                parse_event_id_spec("3-5").unwrap(),
+                parse_event_id_spec("!4").unwrap(),
+            ]))
+            .with_user(Some(r"NT AUTHORITY\SYSTEM".to_string()))
+            .with_image(Some(r"C:\Windows\System32\cmd.exe".to_string()));
+        assert_eq!(
+            filter.to_xpath(Some(Duration::minutes(5))),
+            "*[System[(EventID=1 or (EventID>=3 and EventID<=5)) and not(EventID=4) \
+             and TimeCreated[timediff(@SystemTime) <= 300000]]]"
+        );
+        let after = "2025-01-01T10:00:00Z".parse().unwrap();
+        let filter = EventFilter::new().with_time_range(Some(after), None);
+        assert_eq!(
+            filter.to_xpath(None),
+            "*[System[TimeCreated[@SystemTime>='2025-01-01T10:00:00.000Z']]]"
+        );
+    }
+
+    #[test]
+    fn timestamp_formats_and_time_range() {
+        let expected: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        for input in [
+            "2025-01-01 10:00:00",
+            "2025-01-01T10:00:00",
+            "2025-01-01 10:00:00.000",
+            "2025-01-01 10:00",
+            "2025-01-01T12:00:00+02:00",
+        ] {
+            assert_eq!(parse_timestamp(input), Ok(expected), "{input}");
+        }
+        assert_eq!(
+            parse_timestamp("2025-01-01"),
+            Ok("2025-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert!(parse_timestamp("01/01/2025").is_err());
+
+        let filter = EventFilter::new().with_time_range(
+            parse_timestamp("2025-01-01 09:59").ok(),
+            parse_timestamp("2025-01-01 10:00:00").ok(),
+        );
+        assert!(filter.matches(&event()));
+        let filter =
+            EventFilter::new().with_time_range(parse_timestamp("2025-01-01 10:00:01").ok(), None);
+        assert!(!filter.matches(&event()));
+    }
+
+    #[test]
+    fn image_filter() {
+        let filter = EventFilter::new().with_image(Some("CMD.EXE".to_string()));
+        assert!(filter.matches(&event()));
+        let filter = filter.with_image(Some(r"C:\Temp\cmd.exe".to_string()));
+        assert!(!filter.matches(&event()));
+    }
+
+    #[test]
+    fn path_glob_filter() {
+        let line = serde_json::json!({
+            "EventTime": "2025-01-01 10:00:00",
+            "Hostname": "TEST-PC",
+            "EventID": 11,
+            "RecordNumber": 43,
+            "UtcTime": "2025-01-01 10:00:00.000",
+            "ProcessGuid": "{11111111-2222-3333-4444-555555555555}",
+            "ProcessId": "1000",
+            "Image": r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+            "TargetFilename": r"C:\Users\Test\AppData\Local\Temp\stage\payload.exe",
+            "CreationUtcTime": "2025-01-01 10:00:00.000",
+        });
+        let dropped = crate::jsonl::parse_json_event(&line.to_string()).unwrap();
+        let globs = |globs: &[&str]| {
+            globs
+                .iter()
+                .map(|glob| path_glob::parse_path_glob(glob).unwrap())
+                .collect()
+        };
+        let filter = EventFilter::new().with_path_globs(globs(&[r"%TEMP%\**\*.exe"]));
+        assert!(filter.matches(&dropped));
+        assert!(!filter.matches(&event()));
+        let filter = EventFilter::new().with_path_globs(globs(&[
+            r"C:\Users\*\AppData\**\*.exe",
+            r"%WINDIR%\*\cmd.exe",
+        ]));
+        assert!(filter.matches(&dropped));
+        assert!(filter.matches(&event()));
+        let filter = EventFilter::new().with_path_globs(globs(&[r"C:\Users\*\*.exe"]));
+        assert!(!filter.matches(&dropped));
+    }
+
+    #[test]
+    fn computer_filter() {
+        let forwarded =
+            SysmonEvent::from_str(PROCESS_CREATE.replace("TEST-PC<", "WS01.corp.example<"))
+                .unwrap();
+        let filter = EventFilter::new().with_computers(terms(&["test-pc"]));
+        assert!(filter.matches(&event()));
+        assert!(!filter.matches(&forwarded));
+        let filter = EventFilter::new().with_computers(terms(&["ws01"]));
+        assert!(filter.matches(&forwarded));
+        let filter = EventFilter::new().with_computers(terms(&["WS*.corp.example", "db?"]));
+        assert!(filter.matches(&forwarded));
+        assert!(!filter.matches(&event()));
+        assert_eq!(filter.to_xpath(None), "*");
+        let filter = EventFilter::new().with_computers(terms(&["WS01.corp.example"]));
+        assert!(filter.matches(&forwarded));
+        assert_eq!(filter.to_xpath(None), "*");
+    }
+
+    #[test]
+    fn system_prefilter() {
+        let time = "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().ok();
+        let filter = EventFilter::new()
+            .with_event_ids(Some(vec![1, 3]))
+            .with_computers(terms(&["ws*"]));
+        assert!(filter.matches_system(1, time, "WS01.corp.example"));
+        assert!(!filter.matches_system(11, time, "WS01.corp.example"));
+        assert!(!filter.matches_system(1, time, "TEST-PC"));
+        let after = "2025-01-02T00:00:00Z".parse::<DateTime<Utc>>().ok();
+        let filter = EventFilter::new().with_time_range(after, None);
+        assert!(!filter.matches_system(1, time, "TEST-PC"));
+        assert!(!filter.matches_system(1, None, "TEST-PC"));
+        assert!(filter.matches_system(1, after, "TEST-PC"));
+    }
+
+    #[test]
+    fn user_logon_and_integrity() {
+        let filter = EventFilter::new().with_user(Some("administrator".to_string()));
+        assert!(filter.matches(&event()));
+        let filter = filter.with_user(Some(r"OTHER-PC\Administrator".to_string()));
+        assert!(!filter.matches(&event()));
+
+        let filter = EventFilter::new()
+            .with_logon_id(Some(parse_logon_id("999").unwrap()))
+            .with_integrity(Some(Integrity::High));
+        assert!(filter.matches(&event()));
+        let filter = filter.with_integrity(Some(Integrity::System));
+        assert!(!filter.matches(&event()));
+        assert!(parse_logon_id("0x3E7") == Ok(999));
+        assert!(parse_logon_id("logon").is_err());
+    }
+}
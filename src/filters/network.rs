@@ -0,0 +1,200 @@
+//! Criteria on network connections: destination address and port, protocol and direction
+use crate::analyzer::ioc::Network;
+use crate::sysmon::{Event as SysmonEvent, NetworkEventData};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// Transport protocol of a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Direction of a connection as seen from the logging computer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// Connections accepted from another host
+    In,
+    /// Connections initiated by the logging computer
+    Out,
+}
+
+/// Single port or inclusive range of ports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "PortSpec")]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+/// Port range of a config file: a port number or a string [`parse_port_range`] accepts
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PortSpec {
+    Port(u16),
+    Range(String),
+}
+
+impl TryFrom<PortSpec> for PortRange {
+    type Error = String;
+
+    fn try_from(spec: PortSpec) -> Result<Self, String> {
+        match spec {
+            PortSpec::Port(port) => Ok(Self {
+                start: port,
+                end: port,
+            }),
+            PortSpec::Range(range) => parse_port_range(&range),
+        }
+    }
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+/// Parse a port (`443`) or an inclusive port range (`8000-8999`)
+pub fn parse_port_range(s: &str) -> Result<PortRange, String> {
+    let s = s.trim();
+    let parse_port = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid port '{port}'"))
+    };
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+        None => {
+            let port = parse_port(s)?;
+            (port, port)
+        }
+    };
+    if start > end {
+        return Err(format!("invalid port range '{s}'"));
+    }
+    Ok(PortRange { start, end })
+}
+
+/// Parse an IP address (`10.0.0.5`) or a CIDR block (`10.0.0.0/8`, `fd00::/8`)
+pub fn parse_network(s: &str) -> Result<Network, String> {
+    Network::parse(s).map_err(|e| e.to_string())
+}
+
+/// Network criteria of a filter. Each list matches when the connection matches any of its
+/// elements; events other than network connections never match a non-empty filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkFilter {
+    pub dest_ips: Vec<Network>,
+    pub dest_ports: Vec<PortRange>,
+    pub protocol: Option<Protocol>,
+    pub direction: Option<Direction>,
+}
+
+impl NetworkFilter {
+    pub fn is_empty(&self) -> bool {
+        self.dest_ips.is_empty()
+            && self.dest_ports.is_empty()
+            && self.protocol.is_none()
+            && self.direction.is_none()
+    }
+
+    pub fn matches(&self, event: &SysmonEvent) -> bool {
+        let (data, direction) = match event {
+            SysmonEvent::OutboundNetwork(e) => (&e.event_data, Direction::Out),
+            SysmonEvent::InboundNetwork(e) => (&e.event_data, Direction::In),
+            _ => return false,
+        };
+        self.matches_connection(data, direction)
+    }
+
+    /// The destination is the side that accepted the connection, as Sysmon reports it
+    fn matches_connection(&self, data: &NetworkEventData, direction: Direction) -> bool {
+        if self.direction.is_some_and(|wanted| wanted != direction) {
+            return false;
+        }
+        if let Some(protocol) = self.protocol {
+            let name = match protocol {
+                Protocol::Tcp => "tcp",
+                Protocol::Udp => "udp",
+            };
+            if !data.protocol.eq_ignore_ascii_case(name) {
+                return false;
+            }
+        }
+        if !self.dest_ports.is_empty()
+            && !self
+                .dest_ports
+                .iter()
+                .any(|range| range.contains(data.destination_port))
+        {
+            return false;
+        }
+        if !self.dest_ips.is_empty() {
+            let Ok(ip) = data.destination_ip.parse::<IpAddr>() else {
+                return false;
+            };
+            if !self.dest_ips.iter().any(|network| network.contains(ip)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_event;
+    use serde_json::json;
+
+    fn connection(initiated: bool, protocol: &str, ip: &str, port: u16) -> SysmonEvent {
+        json_event(
+            3,
+            json!({
+                "Protocol": protocol,
+                "Initiated": initiated.to_string(),
+                "SourceIsIpv6": "false",
+                "SourceIp": "192.168.1.10",
+                "SourcePort": "50000",
+                "DestinationIsIpv6": "false",
+                "DestinationIp": ip,
+                "DestinationPort": port.to_string(),
+            }),
+        )
+    }
+
+    #[test]
+    fn destination_protocol_and_direction() {
+        let filter = NetworkFilter {
+            dest_ips: vec![
+                parse_network("10.0.0.0/8").unwrap(),
+                parse_network("203.0.113.7").unwrap(),
+            ],
+            dest_ports: vec![
+                parse_port_range("443").unwrap(),
+                parse_port_range("8000-8999").unwrap(),
+            ],
+            protocol: Some(Protocol::Tcp),
+            direction: Some(Direction::Out),
+        };
+        assert!(filter.matches(&connection(true, "tcp", "10.1.2.3", 443)));
+        assert!(filter.matches(&connection(true, "tcp", "203.0.113.7", 8080)));
+        assert!(!filter.matches(&connection(true, "tcp", "203.0.113.8", 443)));
+        assert!(!filter.matches(&connection(true, "tcp", "10.1.2.3", 9000)));
+        assert!(!filter.matches(&connection(true, "udp", "10.1.2.3", 443)));
+        assert!(!filter.matches(&connection(false, "tcp", "10.1.2.3", 443)));
+
+        let inbound = NetworkFilter {
+            direction: Some(Direction::In),
+            ..NetworkFilter::default()
+        };
+        assert!(inbound.matches(&connection(false, "udp", "192.168.1.10", 53)));
+
+        assert!(parse_port_range("9000-8000").is_err());
+        assert!(parse_port_range("70000").is_err());
+        assert!(parse_network("10.0.0.0/33").is_err());
+    }
+}
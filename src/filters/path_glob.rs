@@ -0,0 +1,173 @@
+//! Path globs of `--path-glob` and of detection rule patterns: `*` and `?` within one folder or
+//! file name, `**` across any number of folders, and `%TEMP%` style environment variables
+//! expanded to the folders they usually point to
+use crate::analyzer::parent_child::glob_match;
+use anyhow::{Result, anyhow, bail};
+
+/// Segment standing for any number of folders, including none
+const ANY_DEPTH: &str = "**";
+
+/// Folders a user profile can be in: regular accounts, SYSTEM and service accounts
+const PROFILES: &[&str] = &[
+    r"?:\users\*",
+    r"?:\windows\system32\config\systemprofile",
+    r"?:\windows\serviceprofiles\*",
+];
+
+/// Folders an environment variable usually points to, `None` for unknown variables
+fn expand_variable(name: &str) -> Option<Vec<String>> {
+    let profile = |suffix: &str| -> Vec<String> {
+        PROFILES
+            .iter()
+            .map(|profile| format!("{profile}{suffix}"))
+            .collect()
+    };
+    let folder = |path: &str| vec![path.to_string()];
+    Some(match name.to_lowercase().as_str() {
+        "userprofile" => profile(""),
+        "appdata" => profile(r"\appdata\roaming"),
+        "localappdata" => profile(r"\appdata\local"),
+        "temp" | "tmp" => {
+            let mut folders = profile(r"\appdata\local\temp");
+            folders.push(r"?:\windows\temp".to_string());
+            folders
+        }
+        "public" => folder(r"?:\users\public"),
+        "programdata" | "allusersprofile" => folder(r"?:\programdata"),
+        "programfiles" | "programw6432" => folder(r"?:\program files"),
+        "programfiles(x86)" => folder(r"?:\program files (x86)"),
+        "windir" | "systemroot" => folder(r"?:\windows"),
+        "systemdrive" | "homedrive" => folder("?:"),
+        _ => return None,
+    })
+}
+
+/// Case-insensitive glob matching a whole Windows path, e.g. `C:\Users\*\AppData\**\*.exe` or
+/// `%TEMP%\*.ps1`. `/` and `\` both separate folders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathGlob {
+    /// Segments of every expansion of the environment variables
+    alternatives: Vec<Vec<String>>,
+}
+
+impl PathGlob {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let pieces: Vec<&str> = pattern.trim().split('%').collect();
+        if pieces.len().is_multiple_of(2) {
+            bail!("unmatched % in path glob '{pattern}'");
+        }
+        // Pieces at odd positions are variable names
+        let mut expansions = vec![String::new()];
+        for (i, piece) in pieces.iter().enumerate() {
+            let folders = if !i.is_multiple_of(2) {
+                expand_variable(piece)
+                    .ok_or_else(|| anyhow!("unknown variable %{piece}% in path glob '{pattern}'"))?
+            } else {
+                vec![piece.to_lowercase()]
+            };
+            expansions = expansions
+                .iter()
+                .flat_map(|prefix| {
+                    folders
+                        .iter()
+                        .map(move |folder| format!("{prefix}{folder}"))
+                })
+                .collect();
+        }
+        if expansions
+            .iter()
+            .all(|expansion| segments(expansion).is_empty())
+        {
+            bail!("empty path glob '{pattern}'");
+        }
+        Ok(Self {
+            alternatives: expansions
+                .iter()
+                .map(|expansion| {
+                    segments(expansion)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+
+    /// Whether a pattern alternative is a path glob rather than a plain glob: it spans folders
+    /// with `**` or refers to a known environment variable
+    pub fn is_path_glob(pattern: &str) -> bool {
+        let pieces: Vec<&str> = pattern.split('%').collect();
+        pattern.contains(ANY_DEPTH)
+            || (1..pieces.len().saturating_sub(1))
+                .step_by(2)
+                .any(|i| expand_variable(pieces[i]).is_some())
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = path.to_lowercase();
+        let path = segments(&path);
+        self.alternatives
+            .iter()
+            .any(|pattern| match_segments(pattern, &path))
+    }
+}
+
+/// Folder and file names of a path; repeated separators and those of UNC paths are ignored
+fn segments(path: &str) -> Vec<&str> {
+    path.split(['\\', '/'])
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((segment, rest)) if segment == ANY_DEPTH => {
+            (0..=path.len()).any(|skipped| match_segments(rest, &path[skipped..]))
+        }
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, tail)| glob_match(segment, name) && match_segments(rest, tail)),
+    }
+}
+
+/// Parse a `--path-glob` value
+pub fn parse_path_glob(s: &str) -> Result<PathGlob, String> {
+    PathGlob::parse(s).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_globs_and_variables() {
+        let glob = PathGlob::parse(r"C:\Users\*\AppData\**\*.exe").unwrap();
+        assert!(glob.matches(r"C:\Users\bob\AppData\Local\Temp\x\payload.EXE"));
+        assert!(glob.matches(r"c:/users/bob/appdata/run.exe"));
+        assert!(!glob.matches(r"C:\Users\bob\Desktop\AppData\run.exe"));
+        assert!(!glob.matches(r"C:\Users\bob\AppData\Local\notes.txt"));
+        // `*` stays within a folder
+        assert!(
+            !PathGlob::parse(r"C:\*\run.exe")
+                .unwrap()
+                .matches(r"C:\a\b\run.exe")
+        );
+
+        let temp = PathGlob::parse(r"%TEMP%\*.ps1").unwrap();
+        assert!(temp.matches(r"C:\Users\alice\AppData\Local\Temp\stage.ps1"));
+        assert!(temp.matches(r"C:\Windows\Temp\stage.ps1"));
+        assert!(temp.matches(r"C:\Windows\System32\config\systemprofile\AppData\Local\Temp\a.ps1"));
+        assert!(!temp.matches(r"C:\Users\alice\AppData\Local\Temp\sub\stage.ps1"));
+        let roaming = PathGlob::parse(r"%appdata%\**\*.dll").unwrap();
+        assert!(roaming.matches(r"D:\Users\alice\AppData\Roaming\Vendor\x.dll"));
+        assert!(!roaming.matches(r"C:\Users\alice\AppData\Local\Vendor\x.dll"));
+
+        assert!(PathGlob::parse(r"%NOPE%\*.exe").is_err());
+        assert!(PathGlob::parse(r"%TEMP\*.exe").is_err());
+        assert!(PathGlob::is_path_glob(r"%LocalAppData%\*.exe"));
+        assert!(PathGlob::is_path_glob(r"c:\**\x.exe"));
+        assert!(!PathGlob::is_path_glob("*50%*"));
+        assert!(!PathGlob::is_path_glob(r"c:\windows\*\w3wp.exe"));
+    }
+}
@@ -0,0 +1,419 @@
+//! `--where` expressions: field comparisons combined with `and`, `or`, `not` and parentheses, e.g.
+//! `event_id == 1 and image endswith 'powershell.exe' and dest_port > 1024`
+use super::event_user;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::{Event as SysmonEvent, NetworkEventData};
+use std::borrow::Cow;
+
+/// Expression compiled once per run and evaluated against every event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Expr);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Vec<Literal>),
+}
+
+/// Field an expression refers to. Common fields have aliases that work across event types,
+/// any other name is looked up in the event data (`TargetFilename` or `target_filename`).
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    EventId,
+    RecordId,
+    Computer,
+    Image,
+    ProcessName,
+    ProcessId,
+    User,
+    SourceIp,
+    SourcePort,
+    DestinationIp,
+    DestinationPort,
+    Protocol,
+    /// Event data field name, lowercase without underscores
+    Data(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+    In,
+}
+
+/// Value to compare with, lowercased and parsed as a number up front
+#[derive(Debug, Clone, PartialEq)]
+struct Literal {
+    text: String,
+    number: Option<i64>,
+}
+
+impl Literal {
+    fn new(text: &str) -> Self {
+        Self {
+            text: text.to_lowercase(),
+            number: parse_number(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+/// Parse a `--where` expression
+pub fn parse_query(s: &str) -> Result<Query, String> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let expr = parser.or()?;
+    match parser.next() {
+        None => Ok(Query(expr)),
+        Some(token) => Err(format!("Unexpected {} after expression", describe(&token))),
+    }
+}
+
+impl Query {
+    pub fn matches(&self, event: &SysmonEvent) -> bool {
+        self.0.matches(event)
+    }
+    /// Expression matching the events both expressions match
+    pub fn and(self, other: Query) -> Query {
+        Query(Expr::And(Box::new(self.0), Box::new(other.0)))
+    }
+}
+
+impl Expr {
+    fn matches(&self, event: &SysmonEvent) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(event) && right.matches(event),
+            Expr::Or(left, right) => left.matches(event) || right.matches(event),
+            Expr::Not(expr) => !expr.matches(event),
+            // Events without the field never match a comparison
+            Expr::Compare(field, op, values) => field
+                .value(event)
+                .is_some_and(|value| compare(&value, *op, values)),
+        }
+    }
+}
+
+fn compare(value: &str, op: Op, literals: &[Literal]) -> bool {
+    let text = value.to_lowercase();
+    let number = parse_number(value);
+    let equals = |literal: &Literal| match (number, literal.number) {
+        (Some(number), Some(expected)) => number == expected,
+        _ => text == literal.text,
+    };
+    let Some(literal) = literals.first() else {
+        return false;
+    };
+    let ordering = || match (number, literal.number) {
+        (Some(number), Some(expected)) => number.cmp(&expected),
+        _ => text.as_str().cmp(literal.text.as_str()),
+    };
+    match op {
+        Op::Eq => equals(literal),
+        Op::Ne => !equals(literal),
+        Op::Lt => ordering().is_lt(),
+        Op::Le => ordering().is_le(),
+        Op::Gt => ordering().is_gt(),
+        Op::Ge => ordering().is_ge(),
+        Op::Contains => text.contains(&literal.text),
+        Op::StartsWith => text.starts_with(&literal.text),
+        Op::EndsWith => text.ends_with(&literal.text),
+        Op::In => literals.iter().any(equals),
+    }
+}
+
+/// Decimal or `0x` hexadecimal integer
+fn parse_number(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+impl Field {
+    fn new(name: &str) -> Self {
+        let normalized: String = name
+            .chars()
+            .filter(|c| *c != '_')
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "eventid" | "id" => Field::EventId,
+            "recordid" => Field::RecordId,
+            "computer" | "host" => Field::Computer,
+            "image" => Field::Image,
+            "processname" => Field::ProcessName,
+            "processid" | "pid" => Field::ProcessId,
+            "user" => Field::User,
+            "srcip" | "sourceip" => Field::SourceIp,
+            "srcport" | "sourceport" => Field::SourcePort,
+            "destip" | "destinationip" => Field::DestinationIp,
+            "destport" | "destinationport" => Field::DestinationPort,
+            "protocol" => Field::Protocol,
+            _ => Field::Data(normalized),
+        }
+    }
+
+    fn value<'a>(&self, event: &'a SysmonEvent) -> Option<Cow<'a, str>> {
+        let network = || match event {
+            SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => Some(&e.event_data),
+            _ => None,
+        };
+        let network_field = |field: fn(&'a NetworkEventData) -> Cow<'a, str>| network().map(field);
+        match self {
+            Field::EventId => Some(event.system().event_id.event_id.to_string().into()),
+            Field::RecordId => Some(
+                event
+                    .system()
+                    .event_record_id
+                    .event_record_id
+                    .to_string()
+                    .into(),
+            ),
+            Field::Computer => Some(event.system().computer.computer.as_str().into()),
+            Field::Image => Some(event.image().into()),
+            Field::ProcessName => Some(event.process_name().into()),
+            Field::ProcessId => Some(event.process_id().to_string().into()),
+            Field::User => event_user(event).map(|user| user.user.as_str().into()),
+            Field::SourceIp => network_field(|data| data.source_ip.as_str().into()),
+            Field::SourcePort => network_field(|data| data.source_port.to_string().into()),
+            Field::DestinationIp => network_field(|data| data.destination_ip.as_str().into()),
+            Field::DestinationPort => {
+                network_field(|data| data.destination_port.to_string().into())
+            }
+            Field::Protocol => network_field(|data| data.protocol.as_str().into()),
+            Field::Data(name) => event
+                .fields()
+                .iter()
+                // Sysmon names have no underscores, comparing them case-insensitively is enough
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.into()),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            // Strings have no escapes, so Windows paths can be written as they are
+            chars.next();
+            let text: String = chars
+                .by_ref()
+                .map(|(_, c)| c)
+                .take_while(|&ch| ch != c)
+                .collect();
+            if !s[start + 1..].contains(c) {
+                return Err(format!("Unterminated string at position {start}"));
+            }
+            tokens.push(Token::Text(text));
+        } else if let Some(symbol) = ["==", "!=", "<=", ">=", "<", ">", "(", ")", ","]
+            .into_iter()
+            .find(|symbol| s[start..].starts_with(symbol))
+        {
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        } else if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            return Err(format!("Unexpected character '{c}' at position {start}"));
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{word}'"),
+        Token::Text(text) => format!("string '{text}'"),
+        Token::Symbol(symbol) => format!("'{symbol}'"),
+    }
+}
+
+/// Recursive descent parser; `not` binds tighter than `and`, which binds tighter than `or`
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consume the next token if it is the keyword (case-insensitive)
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.tokens.get(self.position),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &'static str) -> bool {
+        let found = self.tokens.get(self.position) == Some(&Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.symbol("(") {
+            let expr = self.or()?;
+            if !self.symbol(")") {
+                return Err("Missing ')'".to_string());
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let field = match self.next() {
+            Some(Token::Word(word)) => Field::new(&word),
+            Some(token) => {
+                return Err(format!("Expected a field name, found {}", describe(&token)));
+            }
+            None => return Err("Expected a field name at the end of the expression".to_string()),
+        };
+        let op = match self.next() {
+            Some(Token::Symbol("==")) => Op::Eq,
+            Some(Token::Symbol("!=")) => Op::Ne,
+            Some(Token::Symbol("<")) => Op::Lt,
+            Some(Token::Symbol("<=")) => Op::Le,
+            Some(Token::Symbol(">")) => Op::Gt,
+            Some(Token::Symbol(">=")) => Op::Ge,
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "contains" => Op::Contains,
+                "startswith" => Op::StartsWith,
+                "endswith" => Op::EndsWith,
+                "in" => Op::In,
+                _ => return Err(format!("Unknown operator '{word}'")),
+            },
+            Some(token) => return Err(format!("Expected an operator, found {}", describe(&token))),
+            None => return Err("Expected an operator at the end of the expression".to_string()),
+        };
+        if op != Op::In {
+            return Ok(Expr::Compare(field, op, vec![self.literal()?]));
+        }
+        if !self.symbol("(") {
+            return Err("Expected '(' after 'in'".to_string());
+        }
+        let mut values = vec![self.literal()?];
+        while self.symbol(",") {
+            values.push(self.literal()?);
+        }
+        if !self.symbol(")") {
+            return Err("Missing ')' after 'in' list".to_string());
+        }
+        Ok(Expr::Compare(field, op, values))
+    }
+
+    fn literal(&mut self) -> Result<Literal, String> {
+        match self.next() {
+            Some(Token::Text(text) | Token::Word(text)) => Ok(Literal::new(&text)),
+            Some(token) => Err(format!("Expected a value, found {}", describe(&token))),
+            None => Err("Expected a value at the end of the expression".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonl::parse_json_event;
+
+    #[test]
+    fn where_expressions() {
+        let connection = parse_json_event(
+            r#"{"EventTime":"2025-01-01 10:00:00","Hostname":"TEST-PC","EventID":3,"RecordNumber":7,"UtcTime":"2025-01-01 10:00:00.000","ProcessGuid":"{11111111-2222-3333-4444-555555555555}","ProcessId":"4242","Image":"C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe","User":"TEST-PC\\Bob","Protocol":"tcp","Initiated":"true","SourceIsIpv6":"false","SourceIp":"10.0.0.5","SourcePort":"50123","DestinationIsIpv6":"false","DestinationIp":"203.0.113.10","DestinationPort":"8443"}"#,
+        )
+        .unwrap();
+        let matches = |expr: &str| parse_query(expr).unwrap().matches(&connection);
+
+        assert!(matches(
+            "event_id == 3 and image endswith 'POWERSHELL.EXE' and dest_port > 1024"
+        ));
+        assert!(matches(
+            "dest_port in (443, 8443) and not user == 'TEST-PC\\Alice'"
+        ));
+        assert!(matches(
+            r"process_name == powershell.exe or Image startswith 'C:\Temp'"
+        ));
+        // Any event data field, by its name or in snake case
+        assert!(matches(
+            "source_is_ipv6 == false and DestinationIsIpv6 != true"
+        ));
+        assert!(!matches(
+            "(event_id == 1 or event_id == 22) and pid == 4242"
+        ));
+        // Events without the field never match, even with !=
+        assert!(!matches("target_filename != 'x'"));
+
+        for invalid in [
+            "event_id ==",
+            "event_id like 1",
+            "(event_id == 1",
+            "image == 'unterminated",
+            "dest_port in 443",
+            "event_id == 1 event_id == 2",
+        ] {
+            assert!(parse_query(invalid).is_err(), "{invalid}");
+        }
+    }
+}
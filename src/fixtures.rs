@@ -0,0 +1,212 @@
+//! Sysmon events in the XML of the Windows event log and in the NXLog JSON Lines format, built by
+//! the tests of several modules
+use crate::jsonl::parse_json_event;
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+/// GUID of the process of an event unless the test sets its own
+pub const PROCESS_GUID: &str = "11111111-2222-3333-4444-555555555555";
+/// GUID of the parent of a created process unless the test sets its own
+pub const PARENT_GUID: &str = "99999999-8888-7777-6666-555555555555";
+
+/// Event of the Sysmon channel logged by TEST-PC at `time` (RFC 3339), `data` holding the
+/// `<Data>` elements following UtcTime
+pub fn sysmon_event(event_id: u16, version: u8, time: &str, data: &str) -> SysmonEvent {
+    let utc_time = time
+        .parse::<DateTime<Utc>>()
+        .expect("test event time is RFC 3339")
+        .format("%Y-%m-%d %H:%M:%S%.3f");
+    let xml = format!(
+        r#"
+    <Event>
+      <System>
+        <Provider Name="Microsoft-Windows-Sysmon" Guid="{{...}}" />
+        <EventID>{event_id}</EventID>
+        <Version>{version}</Version>
+        <Level>4</Level>
+        <Task>{event_id}</Task>
+        <Opcode>0</Opcode>
+        <Keywords>0x8000000000000000</Keywords>
+        <TimeCreated SystemTime="{time}"/>
+        <EventRecordID>42</EventRecordID>
+        <Execution ProcessID="1000" ThreadID="2000"/>
+        <Channel>Microsoft-Windows-Sysmon/Operational</Channel>
+        <Computer>TEST-PC</Computer>
+        <Security UserID="S-1-5-18"/>
+      </System>
+      <EventData>
+        <Data Name="UtcTime">{utc_time}</Data>
+        {data}
+      </EventData>
+    </Event>
+    "#
+    );
+    SysmonEvent::from_str(xml).unwrap()
+}
+
+/// Process creation (event 1) by TEST-PC\Administrator, with the fields tests vary
+pub struct ProcessCreate<'a> {
+    pub time: &'a str,
+    pub guid: &'a str,
+    pub parent_guid: &'a str,
+    pub parent: &'a str,
+    pub image: &'a str,
+    pub hashes: &'a str,
+}
+
+impl Default for ProcessCreate<'_> {
+    fn default() -> Self {
+        Self {
+            time: "2025-01-01T10:00:00.000Z",
+            guid: PROCESS_GUID,
+            parent_guid: PARENT_GUID,
+            parent: r"C:\Windows\explorer.exe",
+            image: r"C:\Windows\System32\cmd.exe",
+            hashes: "SHA1=1234567890ABCDEF",
+        }
+    }
+}
+
+impl ProcessCreate<'_> {
+    pub fn event(&self) -> SysmonEvent {
+        let Self {
+            time,
+            guid,
+            parent_guid,
+            parent,
+            image,
+            hashes,
+        } = self;
+        sysmon_event(
+            1,
+            5,
+            time,
+            &format!(
+                r#"<Data Name="ProcessGuid">{{{guid}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="Image">{image}</Data>
+        <Data Name="CommandLine">{image}</Data>
+        <Data Name="CurrentDirectory">C:\Users\Test</Data>
+        <Data Name="User">TEST-PC\Administrator</Data>
+        <Data Name="LogonGuid">{{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}}</Data>
+        <Data Name="LogonId">0x3e7</Data>
+        <Data Name="TerminalSessionId">1</Data>
+        <Data Name="IntegrityLevel">High</Data>
+        <Data Name="Hashes">{hashes}</Data>
+        <Data Name="ParentProcessGuid">{{{parent_guid}}}</Data>
+        <Data Name="ParentProcessId">4321</Data>
+        <Data Name="ParentImage">{parent}</Data>
+        <Data Name="ParentCommandLine">{parent}</Data>"#
+            ),
+        )
+    }
+}
+
+/// Creation of `image` by `parent` at `time`, with the default GUIDs
+pub fn process_event(time: &str, parent: &str, image: &str) -> SysmonEvent {
+    ProcessCreate {
+        time,
+        parent,
+        image,
+        ..ProcessCreate::default()
+    }
+    .event()
+}
+
+/// File creation (event 11) of `target` by the process
+pub fn file_event(time: &str, guid: &str, image: &str, target: &str) -> SysmonEvent {
+    sysmon_event(
+        11,
+        2,
+        time,
+        &format!(
+            r#"<Data Name="ProcessGuid">{{{guid}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="Image">{image}</Data>
+        <Data Name="TargetFilename">{target}</Data>
+        <Data Name="CreationUtcTime">2025-01-01 10:00:00.000</Data>"#
+        ),
+    )
+}
+
+/// Termination (event 5) of the process
+pub fn terminate_event(time: &str, guid: &str, image: &str) -> SysmonEvent {
+    sysmon_event(
+        5,
+        3,
+        time,
+        &format!(
+            r#"<Data Name="RuleName">-</Data>
+        <Data Name="ProcessGuid">{{{guid}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="Image">{image}</Data>"#
+        ),
+    )
+}
+
+/// `fields` replacing or added to those of `line`; UtcTime follows an EventTime set without it
+fn json_line(mut line: Value, fields: Value) -> String {
+    let Value::Object(fields) = fields else {
+        panic!("test event fields are a JSON object");
+    };
+    if let Some(time) = fields.get("EventTime").and_then(Value::as_str)
+        && !fields.contains_key("UtcTime")
+    {
+        line["UtcTime"] = json!(format!("{time}.000"));
+    }
+    for (name, value) in fields {
+        line[name] = value;
+    }
+    line.to_string()
+}
+
+/// Event in the NXLog JSON Lines format: `fields` over the header of an event of svchost.exe
+/// logged by TEST-PC at 2025-01-01 10:00:00
+pub fn json_event(event_id: u16, fields: Value) -> SysmonEvent {
+    let header = json!({
+        "EventTime": "2025-01-01 10:00:00",
+        "Hostname": "TEST-PC",
+        "EventID": event_id,
+        "RecordNumber": 1,
+        "UtcTime": "2025-01-01 10:00:00.000",
+        "ProcessGuid": format!("{{{PROCESS_GUID}}}"),
+        "ProcessId": "100",
+        "Image": r"C:\Windows\System32\svchost.exe",
+        "User": r"TEST-PC\Bob",
+    });
+    parse_json_event(&json_line(header, fields)).unwrap()
+}
+
+/// Line of a process creation in the NXLog JSON Lines format: `fields` over `cmd.exe /c whoami`
+/// started by Word on WS1
+pub fn json_process_line(fields: Value) -> String {
+    let line = json!({
+        "EventTime": "2025-01-01 10:00:00",
+        "Hostname": "WS1",
+        "EventID": 1,
+        "RecordNumber": 17,
+        "UtcTime": "2025-01-01 10:00:00.000",
+        "ProcessGuid": format!("{{{PROCESS_GUID}}}"),
+        "ProcessId": "42",
+        "Image": r"C:\Windows\System32\cmd.exe",
+        "CommandLine": "cmd.exe /c whoami",
+        "CurrentDirectory": "C:\\",
+        "User": r"WS1\Bob",
+        "LogonGuid": "{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}",
+        "LogonId": "0x1234",
+        "TerminalSessionId": "1",
+        "IntegrityLevel": "Medium",
+        "Hashes": "SHA256=00",
+        "ParentProcessGuid": format!("{{{PARENT_GUID}}}"),
+        "ParentProcessId": "7",
+        "ParentImage": r"C:\Program Files\Microsoft Office\WINWORD.EXE",
+        "ParentCommandLine": "WINWORD.EXE",
+    });
+    json_line(line, fields)
+}
+
+/// Process creation of [`json_process_line`]
+pub fn json_process_event(fields: Value) -> SysmonEvent {
+    parse_json_event(&json_process_line(fields)).unwrap()
+}
@@ -0,0 +1,330 @@
+//! Follow mode: tail an EVTX or JSON Lines file that is still being written, like `watch` does
+//! for the live channel but without the Windows Event Log API
+use crate::analyzer::{self, DetectionConfig, detector};
+use crate::display;
+use crate::filters::EventFilter;
+use crate::jsonl;
+use crate::parser::{self, InputFormat};
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
+use evtx::{EvtxParser, ParserSettings};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Time between two checks of the file for new records
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Recent events kept as context for anomaly detection, the default of `watch`
+const CONTEXT_SIZE: usize = 1000;
+
+pub struct FollowConfig {
+    pub path: PathBuf,
+    pub format: InputFormat,
+    pub filter: EventFilter,
+    pub detect: bool,
+    pub detection: DetectionConfig,
+}
+
+/// Position in the followed file
+enum Position {
+    /// Byte offset after the last complete line
+    Jsonl(u64),
+    Evtx(EvtxPosition),
+}
+
+/// Last record read from an EVTX file
+#[derive(Debug, Default)]
+struct EvtxPosition {
+    /// Highest record ID seen, `None` while the file has no records
+    last_record_id: Option<u64>,
+    /// Chunk holding that record, read again as the collector may add records to it
+    chunk: u64,
+}
+
+/// Reader returning the records appended to a file since the previous read
+pub(crate) struct FileTail {
+    path: PathBuf,
+    position: Position,
+}
+
+impl FileTail {
+    /// Start at the end of the file, only records appended later are returned
+    pub(crate) fn open(path: PathBuf, format: InputFormat) -> Result<Self> {
+        let mut tail = Self {
+            position: match format {
+                InputFormat::Evtx => Position::Evtx(EvtxPosition::default()),
+                InputFormat::Jsonl => Position::Jsonl(0),
+                InputFormat::Csv => bail!("--follow doesn't support CSV files"),
+            },
+            path,
+        };
+        let skipped = tail.read_new()?.len();
+        info!(
+            "Following {}, skipped {} existing events",
+            tail.path.display(),
+            skipped
+        );
+        Ok(tail)
+    }
+
+    /// Records appended since the last call; records that are not Sysmon events are skipped
+    pub(crate) fn read_new(&mut self) -> Result<Vec<SysmonEvent>> {
+        match &mut self.position {
+            Position::Jsonl(offset) => read_jsonl(&self.path, offset),
+            Position::Evtx(position) => read_evtx(&self.path, position),
+        }
+    }
+}
+
+/// Read the complete lines after `offset`, a line still being written is left for the next read
+fn read_jsonl(path: &PathBuf, offset: &mut u64) -> Result<Vec<SysmonEvent>> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open: {}", path.display()))?;
+    let length = file.metadata()?.len();
+    if length < *offset {
+        info!(
+            "{} was truncated, reading it from the start",
+            path.display()
+        );
+        *offset = 0;
+    }
+    file.seek(SeekFrom::Start(*offset))?;
+    let mut appended = Vec::new();
+    file.read_to_end(&mut appended)?;
+    let Some(end) = appended.iter().rposition(|&byte| byte == b'\n') else {
+        return Ok(Vec::new());
+    };
+    *offset += end as u64 + 1;
+    let events = String::from_utf8_lossy(&appended[..end])
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match jsonl::parse_json_event(line) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                debug!("Failed to parse JSON record as Sysmon event: {}", e);
+                None
+            }
+        })
+        .collect();
+    Ok(events)
+}
+
+/// Read the records with a higher ID than the last one seen. Only the chunk that held it and the
+/// chunks after it are parsed; once a full circular log starts again at its first chunk, reading
+/// goes on from there.
+fn read_evtx(path: &PathBuf, position: &mut EvtxPosition) -> Result<Vec<SysmonEvent>> {
+    let settings = ParserSettings::default().num_threads(0);
+    let mut parser = EvtxParser::from_path(path)
+        .with_context(|| format!("Failed to open: {}", path.display()))?
+        .with_configuration(settings.clone());
+    let settings = Arc::new(settings);
+    let mut events = Vec::new();
+    let start = position.chunk;
+    if !read_chunks(&mut parser, &settings, start, position, &mut events) && start > 0 {
+        read_chunks(&mut parser, &settings, 0, position, &mut events);
+    }
+    Ok(events)
+}
+
+/// Parse the chunks from `start` on that hold records newer than `position`, until one that
+/// does not, and return whether any did. The chunk being written is only complete once the
+/// collector flushes it, so the one of the last record is parsed whatever its header says.
+fn read_chunks(
+    parser: &mut EvtxParser<File>,
+    settings: &Arc<ParserSettings>,
+    start: u64,
+    position: &mut EvtxPosition,
+    events: &mut Vec<SysmonEvent>,
+) -> bool {
+    let last_record_id = position.last_record_id;
+    let is_new = |record_id: u64| last_record_id.is_none_or(|last| record_id > last);
+    let mut found = false;
+    let mut next = start;
+    while let Some((chunk, number)) = parser.find_next_chunk(next) {
+        next = number + 1;
+        let mut chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                debug!("Skipping unreadable EVTX chunk {}: {}", number, e);
+                continue;
+            }
+        };
+        if number != position.chunk && !is_new(chunk.header.last_event_record_id) {
+            // Later chunks are empty or hold the oldest records of a circular log
+            break;
+        }
+        let mut records = match chunk.parse(settings.clone()) {
+            Ok(records) => records,
+            Err(e) => {
+                debug!("Skipping unreadable EVTX chunk {}: {}", number, e);
+                continue;
+            }
+        };
+        for record in records.iter() {
+            let record = match record {
+                Ok(record) if is_new(record.event_record_id) => record.into_xml(),
+                Ok(_) => continue,
+                Err(e) => Err(e),
+            };
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    debug!("Skipping unreadable EVTX record: {}", e);
+                    continue;
+                }
+            };
+            found = true;
+            position.chunk = number;
+            position.last_record_id = position.last_record_id.max(Some(record.event_record_id));
+            match parser::parse_xml_event(&record.data) {
+                Ok(event) => events.push(event),
+                Err(e) => debug!("Failed to parse record as Sysmon event: {}", e),
+            }
+        }
+    }
+    found
+}
+
+/// Follow the file until Ctrl+C, printing new events and the anomalies they trigger
+pub fn follow_file(config: FollowConfig) -> Result<()> {
+    let FollowConfig {
+        path,
+        format,
+        filter,
+        detect,
+        detection,
+    } = config;
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        println!(
+            "\n{}",
+            "Received stop signal... shutting down.".bright_yellow()
+        );
+        r.store(false, Ordering::SeqCst);
+    })?;
+    let mut tail = FileTail::open(path, format)?;
+    let mut context: VecDeque<SysmonEvent> = VecDeque::with_capacity(CONTEXT_SIZE);
+    let mut custom_detectors = detection.detectors.instantiate();
+    let mut event_count = 0;
+    while running.load(Ordering::SeqCst) {
+        let events = match tail.read_new() {
+            Ok(events) => events,
+            Err(e) => {
+                // The file may be rotated or locked by the collector for a moment
+                warn!("Failed to read followed file: {:#}", e);
+                Vec::new()
+            }
+        };
+        for event in events.into_iter().filter(|event| filter.matches(event)) {
+            event_count += 1;
+            display::print_compact_event(&event, event_count);
+            if detect {
+                let mut anomalies =
+                    analyzer::detect_anomalies_live_with_config(&event, &context, &detection);
+                anomalies.extend(detector::detect_custom_live(
+                    &mut custom_detectors,
+                    &event,
+                    &detection,
+                ));
+                if !anomalies.is_empty() {
+                    display::display_anomalies_live(&anomalies);
+                }
+            }
+            if context.len() >= CONTEXT_SIZE {
+                context.pop_front();
+            }
+            context.push_back(event);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    info!("Stopped following after {} events", event_count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::HasSystem;
+    use std::io::Write;
+
+    #[test]
+    fn jsonl_tail_returns_appended_lines() {
+        let record = |id: u32| {
+            format!(
+                r#"{{"EventTime":"2025-01-01 10:00:00","Hostname":"TEST-PC","EventID":22,"RecordNumber":{id},"UtcTime":"2025-01-01 10:00:00.000","ProcessGuid":"{{11111111-2222-3333-4444-555555555555}}","ProcessId":"4242","QueryName":"example.com","Image":"C:\\Windows\\System32\\cmd.exe"}}"#
+            )
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("follow.jsonl");
+        std::fs::write(&path, format!("{}\n", record(1))).unwrap();
+        let mut tail = FileTail::open(path.clone(), InputFormat::Jsonl).unwrap();
+        assert!(tail.read_new().unwrap().is_empty());
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        let second = record(2);
+        let (head, rest) = second.split_at(20);
+        write!(file, "{}\n{head}", record(2)).unwrap();
+        let record_ids = |events: Vec<SysmonEvent>| -> Vec<_> {
+            events
+                .iter()
+                .map(|event| event.system().event_record_id.event_record_id)
+                .collect()
+        };
+        assert_eq!(record_ids(tail.read_new().unwrap()), [2]);
+        // The second line is only returned once it is complete
+        assert!(tail.read_new().unwrap().is_empty());
+        writeln!(file, "{rest}").unwrap();
+        assert_eq!(record_ids(tail.read_new().unwrap()), [2]);
+
+        std::fs::write(&path, format!("{}\n", record(3))).unwrap();
+        assert_eq!(record_ids(tail.read_new().unwrap()), [3]);
+    }
+
+    #[test]
+    fn evtx_tail_parses_chunks_from_the_last_record() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_files/Execution/rogue_msi_url_1040_1042.evtx");
+        let mut tail = FileTail::open(path, InputFormat::Evtx).unwrap();
+        let Position::Evtx(position) = &mut tail.position else {
+            panic!("expected an EVTX position");
+        };
+        let (last_record_id, chunk) = (position.last_record_id.unwrap(), position.chunk);
+        assert!(chunk > 0, "{position:?}");
+        assert!(tail.read_new().unwrap().is_empty());
+
+        // Records after the position are read again from its chunk on, and only once
+        let Position::Evtx(position) = &mut tail.position else {
+            unreachable!();
+        };
+        position.last_record_id = Some(last_record_id - 1);
+        assert!(tail.read_new().unwrap().is_empty());
+        let Position::Evtx(position) = &tail.position else {
+            unreachable!();
+        };
+        assert_eq!(
+            (position.last_record_id, position.chunk),
+            (Some(last_record_id), chunk)
+        );
+
+        // A position past the end of the file, as in a circular log that started again at its
+        // first chunk, goes on from that chunk
+        let sysmon = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_files/Execution/Exec_sysmon_meterpreter_reversetcp_msipackage.evtx");
+        let mut tail = FileTail::open(sysmon, InputFormat::Evtx).unwrap();
+        tail.position = Position::Evtx(EvtxPosition {
+            last_record_id: Some(0),
+            chunk: 40,
+        });
+        assert!(!tail.read_new().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+//! Destinations of `watch --forward`: a syslog collector, or a Kafka topic in builds with the
+//! `kafka` feature
+use crate::analyzer::Anomaly;
+#[cfg(feature = "kafka")]
+use crate::kafka::{KafkaForwarder, KafkaTarget, parse_kafka_target};
+use crate::syslog::{SyslogForwarder, SyslogTarget, Transport, parse_syslog_target};
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Result, bail};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardTarget {
+    Syslog(SyslogTarget),
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaTarget),
+}
+
+impl fmt::Display for ForwardTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForwardTarget::Syslog(target) => target.fmt(f),
+            #[cfg(feature = "kafka")]
+            ForwardTarget::Kafka(target) => target.fmt(f),
+        }
+    }
+}
+
+/// Parse a `syslog://`, `syslog+tcp://`, `syslog+tls://` or `kafka://` URL
+pub fn parse_forward_target(s: &str) -> Result<ForwardTarget, String> {
+    let is_kafka = s
+        .get(..8)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("kafka://"));
+    if !is_kafka {
+        return parse_syslog_target(s).map(ForwardTarget::Syslog);
+    }
+    #[cfg(feature = "kafka")]
+    return parse_kafka_target(&format!("kafka://{}", &s[8..])).map(ForwardTarget::Kafka);
+    #[cfg(not(feature = "kafka"))]
+    Err("Forwarding to Kafka needs a build with the `kafka` feature".to_string())
+}
+
+impl ForwardTarget {
+    /// Trust the certificate authorities of a PEM file for a `syslog+tls://` collector
+    pub fn with_syslog_ca(self, ca: PathBuf) -> Result<Self> {
+        match self {
+            ForwardTarget::Syslog(target) if target.transport == Transport::Tls => {
+                Ok(ForwardTarget::Syslog(SyslogTarget {
+                    ca: Some(ca),
+                    ..target
+                }))
+            }
+            _ => bail!("--syslog-ca only applies to syslog+tls:// targets"),
+        }
+    }
+}
+
+/// Sends events and anomalies to a [`ForwardTarget`]
+pub enum Forwarder {
+    Syslog(SyslogForwarder),
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaForwarder),
+}
+
+impl Forwarder {
+    pub fn connect(target: ForwardTarget) -> Result<Self> {
+        match target {
+            ForwardTarget::Syslog(target) => SyslogForwarder::connect(target).map(Self::Syslog),
+            #[cfg(feature = "kafka")]
+            ForwardTarget::Kafka(target) => KafkaForwarder::connect(target).map(Self::Kafka),
+        }
+    }
+
+    pub fn send_event(&mut self, event: &SysmonEvent) -> Result<()> {
+        match self {
+            Forwarder::Syslog(forwarder) => forwarder.send_event(event),
+            #[cfg(feature = "kafka")]
+            Forwarder::Kafka(forwarder) => forwarder.send_event(event),
+        }
+    }
+
+    pub fn send_anomaly(&mut self, anomaly: &Anomaly) -> Result<()> {
+        match self {
+            Forwarder::Syslog(forwarder) => forwarder.send_anomaly(anomaly),
+            #[cfg(feature = "kafka")]
+            Forwarder::Kafka(forwarder) => forwarder.send_anomaly(anomaly),
+        }
+    }
+}
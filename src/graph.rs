@@ -0,0 +1,162 @@
+use crate::analyzer::lifetime::{self, format_duration};
+use crate::analyzer::{Anomaly, Severity};
+use crate::helpers::HasProcess;
+use crate::sysmon::Event as SysmonEvent;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use uuid::Uuid;
+
+struct Node {
+    label: String,
+    /// Process seen only as a parent, its creation is not in the log
+    placeholder: bool,
+}
+
+/// Render process parent/child relationships as a Graphviz DOT graph.
+/// Processes that triggered anomalies are filled with a color matching the highest severity, and
+/// processes whose termination is logged show how long they ran.
+pub fn process_graph_dot(events: &[SysmonEvent], anomalies: &[Anomaly]) -> String {
+    let lifetimes = lifetime::process_lifetimes(events);
+    let mut nodes: BTreeMap<Uuid, Node> = BTreeMap::new();
+    let mut edges: Vec<(Uuid, Uuid)> = Vec::new();
+    for event in events {
+        let SysmonEvent::ProcessCreate(e) = event else {
+            continue;
+        };
+        let data = &e.event_data;
+        let guid = data.process_guid.process_guid;
+        let parent_guid = data.parent_process_guid.process_guid;
+        let mut label = format!("{}\nPID {}", basename(&data.image), data.process_id);
+        if let Some(duration) = lifetimes.get(&guid).and_then(|l| l.duration()) {
+            label.push_str(&format!("\nran {}", format_duration(duration)));
+        }
+        nodes.insert(
+            guid,
+            Node {
+                label,
+                placeholder: false,
+            },
+        );
+        nodes.entry(parent_guid).or_insert_with(|| Node {
+            label: format!(
+                "{}\nPID {}",
+                basename(&data.parent_image),
+                data.parent_process_id
+            ),
+            placeholder: true,
+        });
+        edges.push((parent_guid, guid));
+    }
+
+    let mut flagged: HashMap<Uuid, Severity> = HashMap::new();
+    for anomaly in anomalies {
+        let Some(event) = anomaly.event() else {
+            continue;
+        };
+        let (guid, image, pid) = (event.process_guid(), event.image(), event.process_id());
+        nodes.entry(guid).or_insert_with(|| Node {
+            label: format!("{}\nPID {pid}", basename(image)),
+            placeholder: false,
+        });
+        let severity = flagged.entry(guid).or_insert(anomaly.severity());
+        *severity = (*severity).max(anomaly.severity());
+    }
+
+    let mut dot = String::from("digraph process_tree {\n");
+    dot.push_str("    rankdir=LR;\n");
+    dot.push_str("    node [shape=box, fontname=\"Helvetica\"];\n");
+    for (guid, node) in &nodes {
+        let mut attributes = vec![format!("label=\"{}\"", escape(&node.label))];
+        let mut style = Vec::new();
+        if node.placeholder {
+            style.push("dashed");
+        }
+        if flagged.contains_key(guid) {
+            style.push("filled");
+        }
+        if !style.is_empty() {
+            attributes.push(format!("style=\"{}\"", style.join(",")));
+        }
+        if let Some(severity) = flagged.get(guid) {
+            attributes.push(format!("fillcolor=\"{}\"", severity_fill(*severity)));
+        }
+        let _ = writeln!(dot, "    \"{guid}\" [{}];", attributes.join(", "));
+    }
+    edges.sort();
+    edges.dedup();
+    for (parent, child) in edges {
+        let _ = writeln!(dot, "    \"{parent}\" -> \"{child}\";");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn basename(image: &str) -> &str {
+    image.rsplit('\\').next().unwrap_or(image)
+}
+
+fn severity_fill(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "#ff4d4d",
+        Severity::High => "#ff944d",
+        Severity::Medium => "#ffd24d",
+        Severity::Low => "#9ecbff",
+    }
+}
+
+/// Escape a string for use inside a quoted DOT attribute
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::ProcessCreate;
+
+    fn process_event(guid: &str, parent_guid: &str, parent: &str, image: &str) -> SysmonEvent {
+        ProcessCreate {
+            guid,
+            parent_guid,
+            parent,
+            image,
+            ..ProcessCreate::default()
+        }
+        .event()
+    }
+
+    #[test]
+    fn graph_marks_anomalous_processes() {
+        let word = "11111111-0000-0000-0000-000000000001";
+        let cmd = "11111111-0000-0000-0000-000000000002";
+        let explorer = "11111111-0000-0000-0000-000000000003";
+        let events = [
+            process_event(
+                word,
+                explorer,
+                r"C:\Windows\explorer.exe",
+                r"C:\Office\WINWORD.EXE",
+            ),
+            process_event(
+                cmd,
+                word,
+                r"C:\Office\WINWORD.EXE",
+                r"C:\Windows\System32\cmd.exe",
+            ),
+        ];
+        let dot = process_graph_dot(&events, &detect_anomalies(&events));
+        assert!(dot.starts_with("digraph process_tree {"));
+        assert!(dot.contains(&format!("\"{explorer}\" -> \"{word}\";")));
+        assert!(dot.contains(&format!("\"{word}\" -> \"{cmd}\";")));
+        assert!(dot.contains(&format!(
+            "\"{cmd}\" [label=\"cmd.exe\\nPID 1000\", style=\"filled\", fillcolor=\"#ff944d\"];"
+        )));
+        assert!(dot.contains(&format!(
+            "\"{explorer}\" [label=\"explorer.exe\\nPID 4321\", style=\"dashed\"];"
+        )));
+    }
+}
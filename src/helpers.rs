@@ -1,6 +1,13 @@
+use crate::helpers::__seal_has_process::Sealed as ProcessSealed;
 use crate::helpers::__seal_has_system::Sealed;
-use crate::sysmon::{Event, FileCreateEvent, NetworkEvent, ProcessCreateEvent, System};
+use crate::sysmon::{
+    ClipboardEvent, DnsEvent, DriverLoadEvent, Event, FileCreateEvent, FileDeleteEvent,
+    FileStreamEvent, LogonEvent, NetworkEvent, PipeEvent, ProcessAccessEvent, ProcessCreateEvent,
+    ProcessTamperingEvent, ProcessTerminateEvent, RawAccessReadEvent, RegistryEvent,
+    ScriptBlockEvent, SysmonServiceEvent, System, WmiEvent,
+};
 use sealed::sealed;
+use uuid::Uuid;
 #[sealed]
 pub trait HasSystem {
     fn system(&self) -> &System;
@@ -40,6 +47,10 @@ fn event_name(system: &System) -> &'static str {
         28 => "FileBlockShredding",
         29 => "FileExecutableDetected",
         255 => "Error",
+        4104 => "ScriptBlock",
+        4624 => "Logon",
+        4688 => "SecurityProcessCreate",
+        5156 => "SecurityNetworkConnect",
         _ => "Unknown",
     }
 }
@@ -61,14 +72,224 @@ impl HasSystem for NetworkEvent {
         &self.system
     }
 }
+impl Sealed for FileDeleteEvent {}
+impl HasSystem for FileDeleteEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for PipeEvent {}
+impl HasSystem for PipeEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for RegistryEvent {}
+impl HasSystem for RegistryEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for DnsEvent {}
+impl HasSystem for DnsEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for ProcessAccessEvent {}
+impl HasSystem for ProcessAccessEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for SysmonServiceEvent {}
+impl HasSystem for SysmonServiceEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for FileStreamEvent {}
+impl HasSystem for FileStreamEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for DriverLoadEvent {}
+impl HasSystem for DriverLoadEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for RawAccessReadEvent {}
+impl HasSystem for RawAccessReadEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for ClipboardEvent {}
+impl HasSystem for ClipboardEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for ProcessTerminateEvent {}
+impl HasSystem for ProcessTerminateEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for ProcessTamperingEvent {}
+impl HasSystem for ProcessTamperingEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for WmiEvent {}
+impl HasSystem for WmiEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for LogonEvent {}
+impl HasSystem for LogonEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
+impl Sealed for ScriptBlockEvent {}
+impl HasSystem for ScriptBlockEvent {
+    fn system(&self) -> &System {
+        &self.system
+    }
+}
 impl Sealed for Event {}
 impl HasSystem for Event {
     fn system(&self) -> &System {
         match self {
             Event::ProcessCreate(e) => e.system(),
             Event::FileCreate(e) => e.system(),
+            Event::FileStreamCreate(e) => e.system(),
             Event::InboundNetwork(e) => e.system(),
             Event::OutboundNetwork(e) => e.system(),
+            Event::FileDelete(e) | Event::FileBlock(e) | Event::FileExecutableDetected(e) => {
+                e.system()
+            }
+            Event::PipeCreated(e) | Event::PipeConnected(e) => e.system(),
+            Event::RegistrySetValue(e) => e.system(),
+            Event::DnsQuery(e) => e.system(),
+            Event::ProcessAccess(e) => e.system(),
+            Event::ServiceStateChange(e) | Event::ConfigurationChange(e) => e.system(),
+            Event::DriverLoad(e) => e.system(),
+            Event::RawAccessRead(e) => e.system(),
+            Event::ClipboardChange(e) => e.system(),
+            Event::ProcessTerminate(e) => e.system(),
+            Event::ProcessTampering(e) => e.system(),
+            Event::WmiFilter(e) | Event::WmiConsumer(e) | Event::WmiBinding(e) => e.system(),
+            Event::Logon(e) => e.system(),
+            Event::ScriptBlock(e) => e.system(),
         }
     }
 }
+/// Identity of the process that generated an event
+#[sealed]
+pub trait HasProcess {
+    fn process_guid(&self) -> Uuid;
+    fn process_id(&self) -> u64;
+    /// Full path of the process image
+    fn image(&self) -> &str;
+    /// File name of the process image
+    fn process_name(&self) -> &str {
+        let image = self.image();
+        image.rsplit('\\').next().unwrap_or(image)
+    }
+}
+macro_rules! process_field {
+    ($event:expr, |$data:ident| $field:expr) => {
+        match $event {
+            Event::ProcessCreate(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::FileCreate(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::FileStreamCreate(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::InboundNetwork(e) | Event::OutboundNetwork(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::FileDelete(e) | Event::FileBlock(e) | Event::FileExecutableDetected(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::PipeCreated(e) | Event::PipeConnected(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::RegistrySetValue(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::DnsQuery(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ProcessAccess(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ServiceStateChange(e) | Event::ConfigurationChange(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::DriverLoad(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::RawAccessRead(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ClipboardChange(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ProcessTerminate(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ProcessTampering(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::WmiFilter(e) | Event::WmiConsumer(e) | Event::WmiBinding(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::Logon(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+            Event::ScriptBlock(e) => {
+                let $data = &e.event_data;
+                $field
+            }
+        }
+    };
+}
+impl ProcessSealed for Event {}
+impl HasProcess for Event {
+    fn process_guid(&self) -> Uuid {
+        process_field!(self, |data| data.process_guid.process_guid)
+    }
+    fn process_id(&self) -> u64 {
+        process_field!(self, |data| data.process_id)
+    }
+    fn image(&self) -> &str {
+        process_field!(self, |data| data.image.image.as_str())
+    }
+}
@@ -0,0 +1,335 @@
+//! Correlation of related anomalies into incidents, so an attack chain is reported as one case
+//! rather than as unrelated findings
+use crate::analyzer::{AggregatedAnomaly, Anomaly, Severity, aggregate_anomalies, risk_weight};
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Default gap between two anomalies on the same host below which they belong to one incident
+pub const DEFAULT_INCIDENT_WINDOW: &str = "2m";
+/// Ancestors of a process searched for anomalies of the same chain
+const MAX_CHAIN_DEPTH: usize = 16;
+/// Score added for every ATT&CK tactic of an incident with several findings beyond the first, as
+/// findings that span several stages of an attack are less likely to be noise
+const TACTIC_BONUS: u32 = 5;
+
+/// Related anomalies of one host
+#[derive(Debug, Clone)]
+pub struct Incident {
+    /// Position in the ranking, starting at 1
+    pub id: usize,
+    /// `None` for aggregate findings without an event (EventStorm)
+    pub computer: Option<String>,
+    /// Distinct findings, in order of first occurrence
+    pub findings: Vec<AggregatedAnomaly>,
+    /// Process names involved, in order of first anomaly
+    pub processes: Vec<String>,
+    pub score: u32,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+impl Incident {
+    /// Severity of the worst finding
+    pub fn severity(&self) -> Severity {
+        self.findings
+            .iter()
+            .map(|group| group.anomaly.severity())
+            .max()
+            .unwrap_or(Severity::Low)
+    }
+
+    /// All anomalies, including repeated ones
+    pub fn anomaly_count(&self) -> usize {
+        self.findings.iter().map(|group| group.count).sum()
+    }
+}
+
+/// Disjoint sets of anomaly indices
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.0[i] != i {
+            self.0[i] = self.0[self.0[i]];
+            i = self.0[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.0[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+/// Group anomalies into incidents: anomalies of the same process, of a process and one of its
+/// ancestors, or logged on the same host within `window` of the first of them are related. Parent chains
+/// come from the process creations among `events`. Incidents are ranked by score, highest first.
+pub fn correlate_incidents(
+    events: &[SysmonEvent],
+    anomalies: &[Anomaly],
+    window: Duration,
+) -> Vec<Incident> {
+    let mut parents: HashMap<(&str, Uuid), Uuid> = HashMap::new();
+    for event in events {
+        if let SysmonEvent::ProcessCreate(e) = event {
+            parents.insert(
+                (
+                    e.system.computer.computer.as_str(),
+                    e.event_data.process_guid.process_guid,
+                ),
+                e.event_data.parent_process_guid.process_guid,
+            );
+        }
+    }
+
+    let mut sets = UnionFind((0..anomalies.len()).collect());
+    let mut by_process: HashMap<(&str, Uuid), usize> = HashMap::new();
+    for (i, anomaly) in anomalies.iter().enumerate() {
+        let Some(event) = anomaly.event() else {
+            continue;
+        };
+        let guid = event.process_guid();
+        if guid.is_nil() {
+            continue;
+        }
+        let computer = event.system().computer.computer.as_str();
+        match by_process.get(&(computer, guid)) {
+            Some(&first) => sets.union(first, i),
+            None => {
+                by_process.insert((computer, guid), i);
+            }
+        }
+    }
+    for (&(computer, guid), &i) in &by_process {
+        let mut seen = HashSet::from([guid]);
+        let mut current = guid;
+        for _ in 0..MAX_CHAIN_DEPTH {
+            let Some(&parent) = parents.get(&(computer, current)) else {
+                break;
+            };
+            if !seen.insert(parent) {
+                break;
+            }
+            if let Some(&ancestor) = by_process.get(&(computer, parent)) {
+                sets.union(ancestor, i);
+            }
+            current = parent;
+        }
+    }
+
+    let mut timeline: Vec<(&str, DateTime<Utc>, usize)> = anomalies
+        .iter()
+        .enumerate()
+        .filter_map(|(i, anomaly)| {
+            let event = anomaly.event()?;
+            Some((
+                event.system().computer.computer.as_str(),
+                anomaly.timestamp()?,
+                i,
+            ))
+        })
+        .collect();
+    timeline.sort();
+    // Windows start at their first finding, so a steady trickle of findings does not chain
+    // into one incident spanning the whole log
+    let mut start: Option<(&str, DateTime<Utc>, usize)> = None;
+    for (computer, time, i) in timeline {
+        match start {
+            Some((start_computer, start_time, first))
+                if start_computer == computer && time - start_time <= window =>
+            {
+                sets.union(first, i);
+            }
+            _ => start = Some((computer, time, i)),
+        }
+    }
+
+    let mut members: Vec<Vec<Anomaly>> = Vec::new();
+    let mut set_index: HashMap<usize, usize> = HashMap::new();
+    for (i, anomaly) in anomalies.iter().enumerate() {
+        let set = sets.find(i);
+        let index = *set_index.entry(set).or_insert_with(|| {
+            members.push(Vec::new());
+            members.len() - 1
+        });
+        members[index].push(anomaly.clone());
+    }
+    let mut incidents: Vec<Incident> = members.iter().map(|set| incident(set)).collect();
+    incidents.sort_by_key(|incident| {
+        (
+            std::cmp::Reverse(incident.score),
+            incident.first_seen.is_none(),
+            incident.first_seen,
+        )
+    });
+    for (i, incident) in incidents.iter_mut().enumerate() {
+        incident.id = i + 1;
+    }
+    incidents
+}
+
+fn incident(anomalies: &[Anomaly]) -> Incident {
+    let findings = aggregate_anomalies(anomalies);
+    let mut processes: Vec<String> = Vec::new();
+    for anomaly in anomalies {
+        if let Some(event) = anomaly.event() {
+            let image = event.image();
+            let name = image.rsplit('\\').next().unwrap_or(image).to_lowercase();
+            if !name.is_empty() && !processes.contains(&name) {
+                processes.push(name);
+            }
+        }
+    }
+    let mut score = findings
+        .iter()
+        .map(|group| risk_weight(group.anomaly.severity()))
+        .sum::<u32>();
+    if findings.len() > 1 {
+        let tactics: HashSet<_> = anomalies
+            .iter()
+            .flat_map(|anomaly| anomaly.tactics())
+            .collect();
+        score += TACTIC_BONUS * tactics.len().saturating_sub(1) as u32;
+    }
+    Incident {
+        id: 0,
+        computer: anomalies
+            .iter()
+            .find_map(|anomaly| anomaly.event())
+            .map(|event| event.system().computer.computer.clone()),
+        processes,
+        score,
+        first_seen: findings.iter().filter_map(|group| group.first_seen).min(),
+        last_seen: findings.iter().filter_map(|group| group.last_seen).max(),
+        findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    fn process(
+        time: &str,
+        computer: &str,
+        guid: u32,
+        parent_guid: u32,
+        parent_image: &str,
+        image: &str,
+        command_line: &str,
+    ) -> SysmonEvent {
+        json_process_event(json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": computer,
+            "RecordNumber": guid,
+            "ProcessGuid": format!("{{{guid:08X}-2222-3333-4444-555555555555}}"),
+            "ProcessId": guid.to_string(),
+            "Image": image,
+            "CommandLine": command_line,
+            "User": format!(r"{computer}\Bob"),
+            "ParentProcessGuid": format!("{{{parent_guid:08X}-2222-3333-4444-555555555555}}"),
+            "ParentProcessId": parent_guid.to_string(),
+            "ParentImage": parent_image,
+            "ParentCommandLine": "explorer.exe",
+        }))
+    }
+
+    #[test]
+    fn phishing_chain_is_one_incident() {
+        let encoded = "powershell.exe -NoP -W Hidden -Enc SQBFAFgAIAAoAE4AZQB3AC0ATwBiAGoAZQBjAHQAIABOAGUAdAAuAFcAZQBiAEMAbABpAGUAbgB0ACkA";
+        let events = vec![
+            process(
+                "09:00:00",
+                "WS1",
+                1,
+                0,
+                r"C:\Windows\explorer.exe",
+                r"C:\Program Files\Microsoft Office\WINWORD.EXE",
+                "WINWORD.EXE invoice.docm",
+            ),
+            process(
+                "09:00:05",
+                "WS1",
+                2,
+                1,
+                r"C:\Program Files\Microsoft Office\WINWORD.EXE",
+                r"C:\Windows\System32\cmd.exe",
+                "cmd.exe /c start",
+            ),
+            process(
+                "09:00:06",
+                "WS1",
+                3,
+                2,
+                r"C:\Windows\System32\cmd.exe",
+                r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+                encoded,
+            ),
+            process(
+                "09:30:00",
+                "WS1",
+                4,
+                3,
+                r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+                r"C:\Users\Bob\AppData\Local\Temp\update.exe",
+                "update.exe",
+            ),
+            process(
+                "09:00:06",
+                "WS2",
+                5,
+                9,
+                r"C:\Windows\explorer.exe",
+                r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+                encoded,
+            ),
+        ];
+        let anomalies = detect_anomalies(&events);
+        let incidents = correlate_incidents(&events, &anomalies, Duration::minutes(2));
+        let ws1: Vec<_> = incidents
+            .iter()
+            .filter(|incident| incident.computer.as_deref() == Some("WS1"))
+            .collect();
+        assert_eq!(ws1.len(), 1, "{incidents:#?}");
+        let chain = ws1[0];
+        assert!(chain.findings.len() >= 3, "{chain:#?}");
+        assert!(chain.processes.contains(&"update.exe".to_string()));
+        assert_eq!(chain.id, 1);
+        assert!(
+            incidents
+                .iter()
+                .any(|incident| incident.computer.as_deref() == Some("WS2"))
+        );
+        assert!(chain.score > incidents.last().unwrap().score);
+
+        // Unrelated findings 90 seconds apart are grouped by windows from the first one
+        // rather than chained together
+        let trickle: Vec<_> = ["10:00:00", "10:01:30", "10:03:00", "10:04:30"]
+            .into_iter()
+            .zip(10..)
+            .map(|(time, guid)| {
+                process(
+                    time,
+                    "WS3",
+                    guid,
+                    guid + 100,
+                    r"C:\Program Files\Microsoft Office\WINWORD.EXE",
+                    r"C:\Windows\System32\cmd.exe",
+                    "cmd.exe /c start",
+                )
+            })
+            .collect();
+        let anomalies = detect_anomalies(&trickle);
+        let incidents = correlate_incidents(&trickle, &anomalies, Duration::minutes(2));
+        assert_eq!(incidents.len(), 2, "{incidents:#?}");
+    }
+}
@@ -0,0 +1,376 @@
+//! Sidecar indexes of EVTX files: the record IDs, time range, Event IDs and process GUIDs of every
+//! chunk, so that filtered parses of the same file only read the chunks that can hold a match
+use crate::filters::EventFilter;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::parser::{self, ParseReport, parse_xml_event};
+use crate::{archive, powershell};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use evtx::{EvtxChunkData, EvtxParser, ParserSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Bumped when the layout changes, older indexes are rebuilt
+const INDEX_VERSION: u32 = 1;
+const FILE_HEADER_SIZE: u64 = 4096;
+const CHUNK_SIZE: usize = 65536;
+
+/// Index of one EVTX file, stored as JSON next to it (`<file>.idx`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvtxIndex {
+    pub version: u32,
+    /// Size and modification time of the indexed file; the index is stale once they change
+    pub file_size: u64,
+    pub file_modified: DateTime<Utc>,
+    /// Sysmon events in the file
+    pub events: usize,
+    pub chunks: Vec<ChunkEntry>,
+}
+
+/// What a chunk of the file holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub number: u64,
+    pub first_record_id: u64,
+    pub last_record_id: u64,
+    /// Time range of the Sysmon events of the chunk, `None` without any timed event
+    pub first_time: Option<DateTime<Utc>>,
+    pub last_time: Option<DateTime<Utc>>,
+    pub event_ids: Vec<u16>,
+    pub process_guids: Vec<Uuid>,
+}
+
+/// Events of the chunks selected with the indexes, and the size of the whole files
+#[derive(Debug)]
+pub struct IndexedParse {
+    pub report: ParseReport,
+    /// Sysmon events in the files, as counted when indexing
+    pub total_events: usize,
+    pub chunks_read: usize,
+    pub total_chunks: usize,
+}
+
+/// Path of the sidecar index of an EVTX file
+pub fn index_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".idx");
+    PathBuf::from(name)
+}
+
+/// Index every chunk of an uncompressed EVTX file and write the sidecar index
+pub fn build_index(path: &Path) -> Result<EvtxIndex> {
+    if archive::is_compressed(path)? {
+        bail!(
+            "{} is compressed, only plain EVTX files can be indexed",
+            path.display()
+        );
+    }
+    let (file_size, file_modified) = file_stamp(path)?;
+    let settings = Arc::new(ParserSettings::default());
+    let mut parser = EvtxParser::from_path(path)
+        .with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?
+        .with_configuration((*settings).clone());
+    let mut index = EvtxIndex {
+        version: INDEX_VERSION,
+        file_size,
+        file_modified,
+        events: 0,
+        chunks: Vec::new(),
+    };
+    let mut next = 0;
+    while let Some((chunk, number)) = parser.find_next_chunk(next) {
+        next = number + 1;
+        let mut chunk = chunk.with_context(|| {
+            format!("Chunk {number} is damaged, parse the file with --recover instead")
+        })?;
+        let mut entry = ChunkEntry {
+            number,
+            first_record_id: chunk.header.first_event_record_id,
+            last_record_id: chunk.header.last_event_record_id,
+            first_time: None,
+            last_time: None,
+            event_ids: Vec::new(),
+            process_guids: Vec::new(),
+        };
+        let (mut event_ids, mut process_guids) = (BTreeSet::new(), BTreeSet::new());
+        let mut records = chunk.parse(settings.clone()).with_context(|| {
+            format!("Chunk {number} is damaged, parse the file with --recover instead")
+        })?;
+        for record in records.iter() {
+            let Ok(record) = record else {
+                continue;
+            };
+            let Ok(record) = record.into_xml() else {
+                continue;
+            };
+            let Ok(event) = parse_xml_event(&record.data) else {
+                continue;
+            };
+            index.events += 1;
+            event_ids.insert(event.system().event_id.event_id);
+            let guid = event.process_guid();
+            if !guid.is_nil() {
+                process_guids.insert(guid);
+            }
+            if let Some(time) = event.system().time_created.time {
+                entry.first_time = Some(entry.first_time.map_or(time, |first| first.min(time)));
+                entry.last_time = Some(entry.last_time.map_or(time, |last| last.max(time)));
+            }
+        }
+        entry.event_ids = event_ids.into_iter().collect();
+        entry.process_guids = process_guids.into_iter().collect();
+        index.chunks.push(entry);
+    }
+    let sidecar = index_path(path);
+    std::fs::write(&sidecar, serde_json::to_string(&index)?)
+        .with_context(|| format!("Failed to write index {}", sidecar.display()))?;
+    Ok(index)
+}
+
+/// Sidecar index of a file, `None` when there is none or the file changed since it was built
+pub fn load_index(path: &Path) -> Result<Option<EvtxIndex>> {
+    let sidecar = index_path(path);
+    let Ok(content) = std::fs::read_to_string(&sidecar) else {
+        return Ok(None);
+    };
+    let index: EvtxIndex = match serde_json::from_str(&content) {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("Ignoring unreadable index {}: {}", sidecar.display(), e);
+            return Ok(None);
+        }
+    };
+    if index.version != INDEX_VERSION || (index.file_size, index.file_modified) != file_stamp(path)?
+    {
+        warn!(
+            "Ignoring stale index {}, run the index command again",
+            sidecar.display()
+        );
+        return Ok(None);
+    }
+    Ok(Some(index))
+}
+
+impl EvtxIndex {
+    /// Chunks that may hold events matching the Event ID, time and process GUID criteria of the
+    /// filter; the other criteria are left to the filter itself
+    pub fn matching_chunks(&self, filter: &EventFilter) -> Vec<&ChunkEntry> {
+        let excluded = filter.get_excluded_event_ids();
+        let (after, before) = filter.get_time_range();
+        let guids = filter.get_process_guids();
+        self.chunks
+            .iter()
+            .filter(|chunk| {
+                filter
+                    .get_event_ids()
+                    .is_none_or(|ids| chunk.event_ids.iter().any(|id| ids.contains(id)))
+                    && chunk.event_ids.iter().any(|id| !excluded.contains(id))
+                    && after.is_none_or(|after| chunk.last_time.is_some_and(|last| last >= after))
+                    && before
+                        .is_none_or(|before| chunk.first_time.is_some_and(|first| first <= before))
+                    && (guids.is_empty()
+                        || chunk.process_guids.iter().any(|guid| guids.contains(guid)))
+            })
+            .collect()
+    }
+}
+
+/// Parse only the chunks of the files that may hold events matching the filter. `None` when the
+/// filter has no indexed criteria or one of the files has no up-to-date index.
+pub fn parse_indexed(paths: &[PathBuf], filter: &EventFilter) -> Result<Option<IndexedParse>> {
+    let (after, before) = filter.get_time_range();
+    if filter.get_event_ids().is_none()
+        && filter.get_excluded_event_ids().is_empty()
+        && after.is_none()
+        && before.is_none()
+        && filter.get_process_guids().is_empty()
+    {
+        return Ok(None);
+    }
+    let mut indexes = Vec::new();
+    for path in paths {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        match load_index(path)? {
+            Some(index) => indexes.push(index),
+            None => return Ok(None),
+        }
+    }
+    let mut parsed = IndexedParse {
+        report: ParseReport::default(),
+        total_events: 0,
+        chunks_read: 0,
+        total_chunks: 0,
+    };
+    for (path, index) in paths.iter().zip(&indexes) {
+        let chunks = index.matching_chunks(filter);
+        debug!(
+            "Reading {} of {} chunks of {}",
+            chunks.len(),
+            index.chunks.len(),
+            path.display()
+        );
+        let report = read_chunks(path, &chunks)?;
+        parsed.report.events.extend(report.events);
+        parsed.report.total_records += report.total_records;
+        parsed.report.read_errors += report.read_errors;
+        parsed.report.skipped_records += report.skipped_records;
+        parsed.total_events += index.events;
+        parsed.chunks_read += chunks.len();
+        parsed.total_chunks += index.chunks.len();
+    }
+    if paths.len() > 1 {
+        parsed.report.duplicates = parser::remove_duplicates(&mut parsed.report.events);
+    }
+    powershell::correlate_script_blocks(&mut parsed.report.events);
+    info!(
+        "Read {} of {} chunks using the indexes",
+        parsed.chunks_read, parsed.total_chunks
+    );
+    Ok(Some(parsed))
+}
+
+/// Read the records of the given chunks, seeking directly to each of them
+fn read_chunks(path: &Path, chunks: &[&ChunkEntry]) -> Result<ParseReport> {
+    let settings = Arc::new(ParserSettings::default());
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?;
+    let mut report = ParseReport::default();
+    for chunk in chunks {
+        let mut data = vec![0; CHUNK_SIZE];
+        file.seek(SeekFrom::Start(
+            FILE_HEADER_SIZE + chunk.number * CHUNK_SIZE as u64,
+        ))?;
+        file.read_exact(&mut data)
+            .with_context(|| format!("Failed to read chunk {}", chunk.number))?;
+        let mut data = EvtxChunkData::new(data, false)
+            .with_context(|| format!("Failed to parse chunk {}", chunk.number))?;
+        let mut records = data
+            .parse(settings.clone())
+            .with_context(|| format!("Failed to parse chunk {}", chunk.number))?;
+        for record in records.iter() {
+            report.total_records += 1;
+            let xml = match record {
+                Ok(record) => record.into_xml(),
+                Err(e) => Err(e),
+            };
+            match xml {
+                Ok(record) => match parse_xml_event(&record.data) {
+                    Ok(event) => report.events.push(event),
+                    Err(e) => {
+                        report.skipped_records += 1;
+                        debug!("Failed to parse record as Sysmon event: {}", e)
+                    }
+                },
+                Err(e) => {
+                    report.read_errors += 1;
+                    warn!("Error reading EVTX record: {}", e)
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn file_stamp(path: &Path) -> Result<(u64, DateTime<Utc>)> {
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+    Ok((metadata.len(), metadata.modified()?.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexed_parse_reads_matching_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("meterpreter.evtx");
+        std::fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("test_files/Execution/Exec_sysmon_meterpreter_reversetcp_msipackage.evtx"),
+            &path,
+        )
+        .unwrap();
+        let filter = EventFilter::new().with_event_ids(Some(vec![3]));
+        assert!(
+            parse_indexed(std::slice::from_ref(&path), &filter)
+                .unwrap()
+                .is_none()
+        );
+
+        let index = build_index(&path).unwrap();
+        assert_eq!(load_index(&path).unwrap().as_ref(), Some(&index));
+        let full = parser::parse_evtx_file(&path).unwrap();
+        assert_eq!(index.events, full.len());
+        let parsed = parse_indexed(std::slice::from_ref(&path), &filter)
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.total_events, full.len());
+        assert_eq!(
+            filter.apply(&parsed.report.events).len(),
+            filter.apply(&full).len()
+        );
+        assert!(
+            parse_indexed(
+                std::slice::from_ref(&path),
+                &EventFilter::new().with_search_term(Some("cmd".to_string()))
+            )
+            .unwrap()
+            .is_none()
+        );
+
+        // Chunks without any selected Event ID, time or process are not read
+        let chunk = &index.chunks[0];
+        let guid = chunk.process_guids[0];
+        let last = chunk.last_time.unwrap();
+        for (filter, matches) in [
+            (EventFilter::new().with_event_ids(Some(vec![9999])), false),
+            (EventFilter::new().with_time_range(Some(last), None), true),
+            (
+                EventFilter::new().with_time_range(Some(last + chrono::Duration::seconds(1)), None),
+                false,
+            ),
+            (EventFilter::new().with_process_guids(vec![guid]), true),
+            (
+                EventFilter::new().with_process_guids(vec![Uuid::nil()]),
+                false,
+            ),
+        ] {
+            assert_eq!(index.matching_chunks(&filter).len(), usize::from(matches));
+        }
+
+        // Appending to the file makes the index stale
+        let mut data = std::fs::read(&path).unwrap();
+        data.extend([0; 16]);
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(load_index(&path).unwrap(), None);
+
+        // An Application log of several chunks
+        let path = dir.path().join("application.evtx");
+        std::fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("test_files/Execution/rogue_msi_url_1040_1042.evtx"),
+            &path,
+        )
+        .unwrap();
+        let index = build_index(&path).unwrap();
+        assert!(index.chunks.len() > 1);
+        let first = &index.chunks[0];
+        let second = &index.chunks[1];
+        assert_eq!(second.number, 1);
+        assert!(second.first_record_id > first.last_record_id);
+        let report = read_chunks(&path, &[second]).unwrap();
+        assert_eq!(
+            report.total_records as u64,
+            second.last_record_id - second.first_record_id + 1
+        );
+    }
+}
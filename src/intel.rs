@@ -0,0 +1,389 @@
+//! Conversion of detected anomalies into threat-intel formats (STIX 2.1 and MISP)
+use crate::analyzer::{Anomaly, Severity};
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Threat-intel formats of the observables of detected anomalies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntelFormat {
+    /// STIX 2.1 bundle of indicators
+    Stix,
+    /// MISP event with attributes
+    Misp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Imphash,
+}
+
+impl HashAlgorithm {
+    fn from_sysmon(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "MD5" => Some(HashAlgorithm::Md5),
+            "SHA1" => Some(HashAlgorithm::Sha1),
+            "SHA256" => Some(HashAlgorithm::Sha256),
+            "IMPHASH" => Some(HashAlgorithm::Imphash),
+            _ => None,
+        }
+    }
+    fn stix_name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA-1",
+            HashAlgorithm::Sha256 => "SHA-256",
+            // Not in the STIX hash vocabulary, custom names are allowed
+            HashAlgorithm::Imphash => "IMPHASH",
+        }
+    }
+    fn misp_type(self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Imphash => "imphash",
+        }
+    }
+}
+
+/// Observable value extracted from the event of an anomaly
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Observable {
+    Ip(IpAddr),
+    Domain(String),
+    Hash(HashAlgorithm, String),
+    FilePath(String),
+}
+
+impl Observable {
+    /// STIX patterning expression matching the observable
+    pub fn stix_pattern(&self) -> String {
+        match self {
+            Observable::Ip(IpAddr::V4(ip)) => format!("[ipv4-addr:value = '{ip}']"),
+            Observable::Ip(IpAddr::V6(ip)) => format!("[ipv6-addr:value = '{ip}']"),
+            Observable::Domain(domain) => {
+                format!("[domain-name:value = '{}']", escape_pattern(domain))
+            }
+            Observable::Hash(algorithm, value) => format!(
+                "[file:hashes.'{}' = '{}']",
+                algorithm.stix_name(),
+                escape_pattern(value)
+            ),
+            Observable::FilePath(path) => match path.rsplit_once('\\') {
+                Some((directory, name)) => format!(
+                    "[file:parent_directory_ref.path = '{}' AND file:name = '{}']",
+                    escape_pattern(directory),
+                    escape_pattern(name)
+                ),
+                None => format!("[file:name = '{}']", escape_pattern(path)),
+            },
+        }
+    }
+
+    /// MISP attribute type and category
+    fn misp_type(&self) -> (&'static str, &'static str) {
+        match self {
+            Observable::Ip(_) => ("ip-dst", "Network activity"),
+            Observable::Domain(_) => ("domain", "Network activity"),
+            Observable::Hash(algorithm, _) => (algorithm.misp_type(), "Payload delivery"),
+            Observable::FilePath(_) => ("filename", "Artifacts dropped"),
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Observable::Ip(ip) => ip.to_string(),
+            Observable::Domain(value)
+            | Observable::Hash(_, value)
+            | Observable::FilePath(value) => value.clone(),
+        }
+    }
+}
+
+/// Quotes and backslashes must be escaped inside STIX pattern strings
+fn escape_pattern(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// Parse a Sysmon hashes field (e.g. `SHA1=...,MD5=...,SHA256=...`)
+fn parse_hashes(hashes: &str) -> Vec<Observable> {
+    hashes
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(_, value)| !value.trim().is_empty())
+        .filter_map(|(name, value)| {
+            HashAlgorithm::from_sysmon(name.trim())
+                .map(|algorithm| Observable::Hash(algorithm, value.trim().to_lowercase()))
+        })
+        .collect()
+}
+
+/// Hashes, addresses, domains and file paths of the anomaly's event
+pub fn observables(anomaly: &Anomaly) -> Vec<Observable> {
+    let Some(event) = anomaly.event() else {
+        return Vec::new();
+    };
+    let mut observables = Vec::new();
+    match event {
+        SysmonEvent::ProcessCreate(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+            observables.extend(parse_hashes(&e.event_data.hashes.hashes));
+        }
+        SysmonEvent::FileCreate(e) => {
+            observables.push(Observable::FilePath(e.event_data.target_filename.clone()));
+        }
+        SysmonEvent::FileStreamCreate(e) => {
+            observables.push(Observable::FilePath(e.event_data.target_filename.clone()));
+            if let Some(hashes) = &e.event_data.hash {
+                observables.extend(parse_hashes(&hashes.hashes));
+            }
+        }
+        SysmonEvent::FileDelete(e)
+        | SysmonEvent::FileBlock(e)
+        | SysmonEvent::FileExecutableDetected(e) => {
+            observables.push(Observable::FilePath(e.event_data.target_filename.clone()));
+            if let Some(hashes) = &e.event_data.hashes {
+                observables.extend(parse_hashes(&hashes.hashes));
+            }
+        }
+        SysmonEvent::InboundNetwork(e) | SysmonEvent::OutboundNetwork(e) => {
+            let data = &e.event_data;
+            // The remote side of an inbound connection is its source
+            let (ip, hostname) = if data.initiated {
+                (&data.destination_ip, &data.destination_hostname)
+            } else {
+                (&data.source_ip, &data.source_hostname)
+            };
+            if let Ok(ip) = ip.parse() {
+                observables.push(Observable::Ip(ip));
+            }
+            if let Some(hostname) = hostname.as_deref().filter(|h| !h.is_empty() && *h != "-") {
+                observables.push(Observable::Domain(hostname.to_string()));
+            }
+            observables.push(Observable::FilePath(data.image.image.clone()));
+        }
+        SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::RegistrySetValue(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::ProcessAccess(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::DnsQuery(e) => {
+            let data = &e.event_data;
+            observables.push(Observable::Domain(data.query_name.clone()));
+            observables.extend(
+                data.resolved_addresses()
+                    .filter_map(|address| address.parse().ok())
+                    .map(Observable::Ip),
+            );
+        }
+        SysmonEvent::DriverLoad(e) => {
+            observables.push(Observable::FilePath(
+                e.event_data.image_loaded.image.clone(),
+            ));
+            if let Some(hashes) = &e.event_data.hashes {
+                observables.extend(parse_hashes(&hashes.hashes));
+            }
+        }
+        SysmonEvent::RawAccessRead(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::ProcessTampering(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::ClipboardChange(e) => {
+            observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+        }
+        SysmonEvent::ServiceStateChange(_)
+        | SysmonEvent::ProcessTerminate(_)
+        | SysmonEvent::ConfigurationChange(_)
+        | SysmonEvent::WmiFilter(_)
+        | SysmonEvent::WmiConsumer(_)
+        | SysmonEvent::WmiBinding(_) => {}
+        SysmonEvent::Logon(e) => {
+            if let Some(ip) = e
+                .event_data
+                .ip_address
+                .as_deref()
+                .and_then(|ip| ip.parse().ok())
+            {
+                observables.push(Observable::Ip(ip));
+            }
+        }
+        SysmonEvent::ScriptBlock(e) => {
+            if !e.event_data.image.is_empty() {
+                observables.push(Observable::FilePath(e.event_data.image.image.clone()));
+            }
+        }
+    }
+    observables
+}
+
+fn stix_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn event_time(anomaly: &Anomaly) -> Option<DateTime<Utc>> {
+    anomaly
+        .event()
+        .and_then(|event| event.system().time_created.time)
+}
+
+/// Build a STIX 2.1 bundle with one indicator per distinct observable
+pub fn stix_bundle(anomalies: &[Anomaly]) -> Value {
+    let now = stix_timestamp(Utc::now());
+    let mut seen = HashSet::new();
+    let mut objects = Vec::new();
+    for anomaly in anomalies {
+        let valid_from = event_time(anomaly)
+            .map(stix_timestamp)
+            .unwrap_or_else(|| now.clone());
+        for observable in observables(anomaly) {
+            let pattern = observable.stix_pattern();
+            if !seen.insert(pattern.clone()) {
+                continue;
+            }
+            objects.push(json!({
+                "type": "indicator",
+                "spec_version": "2.1",
+                "id": format!("indicator--{}", Uuid::new_v4()),
+                "created": now,
+                "modified": now,
+                "name": anomaly.rule_name(),
+                "description": anomaly.description(),
+                "indicator_types": ["anomalous-activity"],
+                "pattern": pattern,
+                "pattern_type": "stix",
+                "valid_from": valid_from,
+                "labels": [anomaly.severity().to_string().to_lowercase()],
+            }));
+        }
+    }
+    json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", Uuid::new_v4()),
+        "objects": objects,
+    })
+}
+
+/// Build a MISP event with one attribute per distinct observable
+pub fn misp_event(anomalies: &[Anomaly]) -> Value {
+    let mut seen = HashSet::new();
+    let mut attributes = Vec::new();
+    for anomaly in anomalies {
+        for observable in observables(anomaly) {
+            if !seen.insert(observable.clone()) {
+                continue;
+            }
+            let (attribute_type, category) = observable.misp_type();
+            let mut attribute = json!({
+                "uuid": Uuid::new_v4().to_string(),
+                "type": attribute_type,
+                "category": category,
+                "value": observable.value(),
+                "to_ids": !matches!(observable, Observable::FilePath(_)),
+                "comment": anomaly.description(),
+            });
+            if let Some(time) = event_time(anomaly) {
+                attribute["timestamp"] = json!(time.timestamp().to_string());
+            }
+            attributes.push(attribute);
+        }
+    }
+    // MISP threat levels: 1 high, 2 medium, 3 low, 4 undefined
+    let threat_level = match anomalies.iter().map(Anomaly::severity).max() {
+        Some(Severity::Critical | Severity::High) => "1",
+        Some(Severity::Medium) => "2",
+        Some(Severity::Low) => "3",
+        None => "4",
+    };
+    json!({
+        "Event": {
+            "uuid": Uuid::new_v4().to_string(),
+            "info": format!("Sysmon log analyzer findings ({} anomalies)", anomalies.len()),
+            "date": Utc::now().format("%Y-%m-%d").to_string(),
+            "threat_level_id": threat_level,
+            "analysis": "0",
+            "distribution": "0",
+            "Attribute": attributes,
+        }
+    })
+}
+
+/// Render anomalies in the requested format
+pub fn export_anomalies(anomalies: &[Anomaly], format: IntelFormat) -> Value {
+    match format {
+        IntelFormat::Stix => stix_bundle(anomalies),
+        IntelFormat::Misp => misp_event(anomalies),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::ProcessCreate;
+
+    fn process_event(parent: &str, image: &str) -> SysmonEvent {
+        ProcessCreate {
+            time: "2025-01-01T10:00:00.1234567Z",
+            guid: "11111111-0000-0000-0000-000000000002",
+            parent_guid: "11111111-0000-0000-0000-000000000001",
+            parent,
+            image,
+            hashes: "SHA1=ABCDEF0123,MD5=0123ABCDEF,IMPHASH=",
+        }
+        .event()
+    }
+
+    #[test]
+    fn stix_and_misp_observables() {
+        let events = [process_event(
+            r"C:\Office\WINWORD.EXE",
+            r"C:\Windows\System32\cmd.exe",
+        )];
+        let anomalies = detect_anomalies(&events);
+        assert!(!anomalies.is_empty());
+
+        let bundle = stix_bundle(&anomalies);
+        assert_eq!(bundle["type"], "bundle");
+        let patterns: Vec<&str> = bundle["objects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|object| object["pattern"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            patterns,
+            [
+                r"[file:parent_directory_ref.path = 'C:\\Windows\\System32' AND file:name = 'cmd.exe']",
+                "[file:hashes.'SHA-1' = 'abcdef0123']",
+                "[file:hashes.'MD5' = '0123abcdef']",
+            ]
+        );
+        assert_eq!(
+            bundle["objects"][0]["valid_from"],
+            "2025-01-01T10:00:00.123Z"
+        );
+
+        let event = misp_event(&anomalies);
+        let attributes = event["Event"]["Attribute"].as_array().unwrap();
+        let types: Vec<&str> = attributes
+            .iter()
+            .map(|a| a["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["filename", "sha1", "md5"]);
+        assert_eq!(event["Event"]["threat_level_id"], "1");
+    }
+}
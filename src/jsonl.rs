@@ -0,0 +1,309 @@
+//! Mapping of Winlogbeat (ECS) and nxlog JSON Lines exports into the Sysmon event model
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+/// Top-level nxlog (`im_msvistalog`) fields that describe the record rather than its event data
+const NXLOG_METADATA_FIELDS: &[&str] = &[
+    "AccountName",
+    "AccountType",
+    "ActivityID",
+    "Category",
+    "Channel",
+    "Domain",
+    "EventID",
+    "EventReceivedTime",
+    "EventTime",
+    "EventType",
+    "ExecutionProcessID",
+    "ExecutionThreadID",
+    "Hostname",
+    "Keywords",
+    "Message",
+    "Opcode",
+    "OpcodeValue",
+    "ProcessID",
+    "ProviderGuid",
+    "RecordNumber",
+    "RelatedActivityID",
+    "Severity",
+    "SeverityValue",
+    "SourceModuleName",
+    "SourceModuleType",
+    "SourceName",
+    "Task",
+    "ThreadID",
+    "UserID",
+    "Version",
+];
+
+/// Record fields shared by both export layouts, in the shape of the EVTX `<System>` element
+#[derive(Debug, Default)]
+pub(crate) struct Record {
+    pub(crate) event_id: String,
+    pub(crate) provider_name: String,
+    pub(crate) provider_guid: String,
+    pub(crate) version: String,
+    pub(crate) level: String,
+    pub(crate) task: String,
+    pub(crate) time_created: String,
+    pub(crate) record_id: String,
+    pub(crate) process_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) channel: String,
+    pub(crate) computer: String,
+    pub(crate) user_id: String,
+    pub(crate) event_data: Vec<(String, String)>,
+}
+
+/// Parse one JSON line of a Winlogbeat or nxlog export
+pub fn parse_json_event(line: &str) -> Result<SysmonEvent> {
+    let value: Value =
+        serde_json::from_str(line).map_err(|e| anyhow!("Malformed JSON record: {e}"))?;
+    let object = value
+        .as_object()
+        .ok_or_else(|| anyhow!("JSON record is not an object"))?;
+    let record = if let Some(winlog) = object.get("winlog").and_then(Value::as_object) {
+        winlogbeat_record(object, winlog)?
+    } else if object.contains_key("EventID") {
+        nxlog_record(object)?
+    } else {
+        return Err(anyhow!(
+            "Unknown JSON layout, expected Winlogbeat or nxlog fields"
+        ));
+    };
+    SysmonEvent::from_str(record.to_xml())
+}
+
+/// Winlogbeat keeps record metadata and event data under `winlog`, the time in `@timestamp`
+fn winlogbeat_record(object: &Map<String, Value>, winlog: &Map<String, Value>) -> Result<Record> {
+    let field = |path: &[&str]| lookup(winlog, path).unwrap_or_default();
+    let timestamp = object
+        .get("@timestamp")
+        .and_then(scalar)
+        .ok_or_else(|| anyhow!("No field: @timestamp"))?;
+    let event_data = winlog
+        .get("event_data")
+        .and_then(Value::as_object)
+        .map(event_data)
+        .unwrap_or_default();
+    Ok(Record {
+        event_id: lookup(winlog, &["event_id"])
+            .or_else(|| lookup(object, &["event", "code"]))
+            .ok_or_else(|| anyhow!("No field: winlog.event_id"))?,
+        provider_name: field(&["provider_name"]),
+        provider_guid: field(&["provider_guid"]),
+        version: field(&["version"]),
+        level: lookup(object, &["log", "level"]).unwrap_or_default(),
+        task: field(&["task"]),
+        time_created: normalize_time(&timestamp)?,
+        record_id: field(&["record_id"]),
+        process_id: field(&["process", "pid"]),
+        thread_id: field(&["process", "thread", "id"]),
+        channel: field(&["channel"]),
+        computer: field(&["computer_name"]),
+        user_id: field(&["user", "identifier"]),
+        event_data,
+    })
+}
+
+/// nxlog flattens event data next to its own metadata fields
+fn nxlog_record(object: &Map<String, Value>) -> Result<Record> {
+    let field = |name: &str| object.get(name).and_then(scalar).unwrap_or_default();
+    let time = object
+        .get("EventTime")
+        .and_then(scalar)
+        .ok_or_else(|| anyhow!("No field: EventTime"))?;
+    let event_data = object
+        .iter()
+        .filter(|(name, _)| !NXLOG_METADATA_FIELDS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.clone(), scalar(value)?)))
+        .collect();
+    Ok(Record {
+        event_id: field("EventID"),
+        provider_name: field("SourceName"),
+        provider_guid: field("ProviderGuid"),
+        version: field("Version"),
+        level: field("SeverityValue"),
+        task: field("Task"),
+        time_created: normalize_time(&time)?,
+        record_id: field("RecordNumber"),
+        process_id: object
+            .get("ExecutionProcessID")
+            .or_else(|| object.get("ProcessID"))
+            .and_then(scalar)
+            .unwrap_or_default(),
+        thread_id: object
+            .get("ExecutionThreadID")
+            .or_else(|| object.get("ThreadID"))
+            .and_then(scalar)
+            .unwrap_or_default(),
+        channel: field("Channel"),
+        computer: field("Hostname"),
+        user_id: field("UserID"),
+        event_data,
+    })
+}
+
+impl Record {
+    fn from_event(event: &SysmonEvent) -> Self {
+        let system = event.system();
+        Self {
+            event_id: system.event_id.event_id.to_string(),
+            provider_name: system.provider.provider_name.clone(),
+            provider_guid: system.provider.provider_guid.clone(),
+            version: system.version.version.clone(),
+            level: system.level.level.clone(),
+            task: system.task.task.clone(),
+            time_created: system.time_created.system_time.clone(),
+            record_id: system.event_record_id.event_record_id.to_string(),
+            process_id: system.execution.process_id.clone(),
+            thread_id: system.execution.thread_id.clone(),
+            channel: system.channel.value.clone(),
+            computer: system.computer.computer.clone(),
+            user_id: system.security.security.clone(),
+            event_data: event
+                .fields()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Render the record as EVTX XML so it goes through the regular event parsing
+    pub(crate) fn to_xml(&self) -> String {
+        let data: String = self
+            .event_data
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    r#"<Data Name="{}">{}</Data>"#,
+                    escape_xml(name),
+                    escape_xml(value)
+                )
+            })
+            .collect();
+        format!(
+            concat!(
+                "<Event><System>",
+                r#"<Provider Name="{}" Guid="{}"/>"#,
+                "<EventID>{}</EventID><Version>{}</Version><Level>{}</Level><Task>{}</Task>",
+                "<Opcode>0</Opcode><Keywords>0x8000000000000000</Keywords>",
+                r#"<TimeCreated SystemTime="{}"/><EventRecordID>{}</EventRecordID>"#,
+                r#"<Execution ProcessID="{}" ThreadID="{}"/>"#,
+                "<Channel>{}</Channel><Computer>{}</Computer>",
+                r#"<Security UserID="{}"/>"#,
+                "</System><EventData>{}</EventData></Event>"
+            ),
+            escape_xml(&self.provider_name),
+            escape_xml(&self.provider_guid),
+            escape_xml(&self.event_id),
+            escape_xml(or_default(&self.version, "0")),
+            escape_xml(or_default(&self.level, "4")),
+            escape_xml(or_default(&self.task, "0")),
+            escape_xml(&self.time_created),
+            escape_xml(or_default(&self.record_id, "0")),
+            escape_xml(&self.process_id),
+            escape_xml(&self.thread_id),
+            escape_xml(or_default(&self.channel, "-")),
+            escape_xml(or_default(&self.computer, "-")),
+            escape_xml(&self.user_id),
+            data
+        )
+    }
+}
+
+/// XML of an event rebuilt from its `<System>` element and fields, which parses back into the
+/// same event. Used to store events, as the event model itself is not serializable.
+pub fn event_to_xml(event: &SysmonEvent) -> String {
+    Record::from_event(event).to_xml()
+}
+
+fn event_data(data: &Map<String, Value>) -> Vec<(String, String)> {
+    data.iter()
+        .filter_map(|(name, value)| Some((name.clone(), scalar(value)?)))
+        .collect()
+}
+
+fn lookup(object: &Map<String, Value>, path: &[&str]) -> Option<String> {
+    let (last, parents) = path.split_last()?;
+    let mut object = object;
+    for name in parents {
+        object = object.get(*name)?.as_object()?;
+    }
+    object.get(*last).and_then(scalar)
+}
+
+/// String form of a JSON string, number or boolean
+pub(crate) fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// EVTX style `SystemTime` from an RFC 3339 timestamp or an nxlog `YYYY-MM-DD HH:MM:SS` time (taken as UTC)
+pub(crate) fn normalize_time(time: &str) -> Result<String> {
+    let time = DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f").map(|time| time.and_utc())
+        })
+        .map_err(|e| anyhow!("Invalid timestamp '{time}': {e}"))?;
+    Ok(time.to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+/// Elements of `<System>` can't be empty, exports may omit the fields they come from
+fn or_default<'a>(value: &'a str, default: &'a str) -> &'a str {
+    if value.is_empty() { default } else { value }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn winlogbeat_and_nxlog_records() {
+        let winlogbeat = r#"{"@timestamp":"2025-01-01T10:00:00.123Z","event":{"code":"1"},"winlog":{"event_id":1,"provider_name":"Microsoft-Windows-Sysmon","provider_guid":"{5770385F-C22A-43E0-BF4C-06F5698FFBD9}","record_id":42,"computer_name":"TEST-PC","channel":"Microsoft-Windows-Sysmon/Operational","version":5,"task":"Process Create (rule: ProcessCreate)","process":{"pid":1000,"thread":{"id":2000}},"user":{"identifier":"S-1-5-18"},"event_data":{"UtcTime":"2025-01-01 10:00:00.123","ProcessGuid":"{11111111-2222-3333-4444-555555555555}","ProcessId":"4242","Image":"C:\\Windows\\System32\\cmd.exe","CommandLine":"cmd.exe /c \"echo <a&b>\"","CurrentDirectory":"C:\\","User":"TEST-PC\\Administrator","LogonGuid":"{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}","LogonId":"0x3e7","TerminalSessionId":"1","IntegrityLevel":"System","Hashes":"SHA1=1234","ParentProcessGuid":"{99999999-8888-7777-6666-555555555555}","ParentProcessId":"4321","ParentImage":"C:\\Windows\\explorer.exe","ParentCommandLine":"explorer.exe"}}}"#;
+        let event = parse_json_event(winlogbeat).unwrap();
+        assert_eq!(event.system().computer.computer, "TEST-PC");
+        assert_eq!(event.system().event_record_id.event_record_id, 42);
+        assert_eq!(
+            event.system().time_created.system_time,
+            "2025-01-01T10:00:00.123000Z"
+        );
+        match event {
+            SysmonEvent::ProcessCreate(ev) => {
+                assert_eq!(ev.event_data.process_id, 4242);
+                assert_eq!(
+                    ev.event_data.command_line.command_line,
+                    "cmd.exe /c \"echo <a&b>\""
+                );
+            }
+            _ => panic!("Expected ProcessCreate event"),
+        }
+
+        let nxlog = r#"{"EventTime":"2025-01-01 10:00:00","Hostname":"TEST-PC","EventID":22,"SourceName":"Microsoft-Windows-Sysmon","RecordNumber":43,"ProcessID":1000,"ThreadID":2000,"Channel":"Microsoft-Windows-Sysmon/Operational","UserID":"S-1-5-18","UtcTime":"2025-01-01 10:00:00.000","ProcessGuid":"{11111111-2222-3333-4444-555555555555}","ProcessId":"4242","QueryName":"example.com","QueryStatus":"0","QueryResults":"::ffff:93.184.216.34;","Image":"C:\\Windows\\System32\\cmd.exe"}"#;
+        match parse_json_event(nxlog).unwrap() {
+            SysmonEvent::DnsQuery(ev) => {
+                assert_eq!(ev.system.execution.process_id, "1000");
+                assert_eq!(ev.event_data.query_name, "example.com");
+                assert!(ev.event_data.resolved_addresses().eq(["93.184.216.34"]));
+            }
+            _ => panic!("Expected DnsQuery event"),
+        }
+
+        assert!(parse_json_event(r#"{"message":"not an event"}"#).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Forwarding of events and anomalies to a Kafka topic (`kafka` feature). Messages are the JSON
+//! of the export schema, keyed by computer so the events of a host stay in one partition and in
+//! order; librdkafka batches them in the background.
+use crate::analyzer::Anomaly;
+use crate::export::{anomaly_to_json, event_to_json};
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Context, Result, anyhow};
+use rdkafka::ClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::KafkaError;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer};
+use rdkafka::types::RDKafkaErrorCode;
+use std::fmt;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DEFAULT_PORT: u16 = 9092;
+/// Time messages wait for more to fill a batch
+const LINGER_MS: &str = "200";
+/// Messages sent to a partition in one request at most
+const BATCH_MESSAGES: &str = "1000";
+/// Time a message may wait for its delivery before it is reported as lost
+const MESSAGE_TIMEOUT_MS: &str = "30000";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time given to queued messages to be delivered when forwarding stops
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts to queue a message while the local queue is full
+const QUEUE_FULL_RETRIES: u32 = 50;
+const QUEUE_FULL_DELAY: Duration = Duration::from_millis(100);
+
+/// Topic given as `kafka://broker[:port][,broker[:port]...]/topic`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaTarget {
+    /// `host:port` of the bootstrap brokers
+    pub brokers: Vec<String>,
+    pub topic: String,
+}
+
+impl fmt::Display for KafkaTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kafka://{}/{}", self.brokers.join(","), self.topic)
+    }
+}
+
+/// Parse a topic URL; broker ports default to 9092
+pub fn parse_kafka_target(s: &str) -> Result<KafkaTarget, String> {
+    let address = s
+        .strip_prefix("kafka://")
+        .ok_or_else(|| format!("Expected kafka://broker[:port]/topic, got '{s}'"))?;
+    let (brokers, topic) = address
+        .split_once('/')
+        .ok_or_else(|| format!("Missing topic in '{s}', expected kafka://broker[:port]/topic"))?;
+    // Kafka topic names: at most 249 ASCII letters, digits, '.', '_' and '-'
+    if topic.is_empty()
+        || topic.len() > 249
+        || !topic
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Err(format!("Invalid topic name '{topic}'"));
+    }
+    let brokers = brokers
+        .split(',')
+        .map(|broker| {
+            if broker.is_empty() {
+                return Err(format!("Missing broker in '{s}'"));
+            }
+            // IPv6 addresses are written in brackets, e.g. kafka://[::1]:9092/sysmon
+            let has_port = match broker.rsplit_once(']') {
+                Some((_, rest)) => rest.starts_with(':'),
+                None => broker.contains(':'),
+            };
+            if !has_port {
+                return Ok(format!("{broker}:{DEFAULT_PORT}"));
+            }
+            let (_, port) = broker.rsplit_once(':').unwrap_or_default();
+            port.parse::<u16>()
+                .map_err(|_| format!("Invalid port '{port}'"))?;
+            Ok(broker.to_string())
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(KafkaTarget {
+        brokers,
+        topic: topic.to_string(),
+    })
+}
+
+/// Logs the messages librdkafka gave up delivering
+struct DeliveryLogger;
+
+impl ClientContext for DeliveryLogger {}
+
+impl ProducerContext for DeliveryLogger {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        if let Err((e, _)) = result {
+            warn!("Failed to deliver message to Kafka: {e}");
+        }
+    }
+}
+
+/// Sends events and anomalies to a Kafka topic, with a `type` header of `event` or `anomaly`
+pub struct KafkaForwarder {
+    target: KafkaTarget,
+    producer: ThreadedProducer<DeliveryLogger>,
+}
+
+impl KafkaForwarder {
+    pub fn connect(target: KafkaTarget) -> Result<Self> {
+        let producer: ThreadedProducer<DeliveryLogger> = ClientConfig::new()
+            .set("bootstrap.servers", target.brokers.join(","))
+            .set("linger.ms", LINGER_MS)
+            .set("batch.num.messages", BATCH_MESSAGES)
+            .set("message.timeout.ms", MESSAGE_TIMEOUT_MS)
+            .create_with_context(DeliveryLogger)
+            .context("Failed to create Kafka producer")?;
+        // Fail now rather than on the first message when the brokers can't be reached
+        producer
+            .client()
+            .fetch_metadata(Some(&target.topic), CONNECT_TIMEOUT)
+            .with_context(|| format!("Failed to reach {target}"))?;
+        info!("Forwarding to {}", target);
+        Ok(Self { target, producer })
+    }
+
+    /// Forward an event as its JSON, keyed by its computer
+    pub fn send_event(&mut self, event: &SysmonEvent) -> Result<()> {
+        let payload = event_to_json(event).to_string();
+        self.send(&event.system().computer.computer, &payload, "event")
+    }
+
+    /// Forward an anomaly as its JSON, keyed by the computer of its event
+    pub fn send_anomaly(&mut self, anomaly: &Anomaly) -> Result<()> {
+        let host = anomaly
+            .event()
+            .map_or("", |event| &event.system().computer.computer);
+        let payload = anomaly_to_json(anomaly).to_string();
+        self.send(host, &payload, "anomaly")
+    }
+
+    /// Queue a message, waiting for room while the local queue is full
+    fn send(&self, key: &str, payload: &str, kind: &str) -> Result<()> {
+        let headers = OwnedHeaders::new().insert(Header {
+            key: "type",
+            value: Some(kind),
+        });
+        let mut record = BaseRecord::to(&self.target.topic)
+            .key(key)
+            .payload(payload)
+            .headers(headers);
+        for _ in 0..QUEUE_FULL_RETRIES {
+            match self.producer.send(record) {
+                Ok(()) => return Ok(()),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    record = returned;
+                    std::thread::sleep(QUEUE_FULL_DELAY);
+                }
+                Err((e, _)) => return Err(anyhow!("Failed to send to {}: {e}", self.target)),
+            }
+        }
+        Err(anyhow!("Failed to send to {}: queue full", self.target))
+    }
+}
+
+impl Drop for KafkaForwarder {
+    /// Deliver the queued messages, which the producer would otherwise drop
+    fn drop(&mut self) {
+        if let Err(e) = self.producer.flush(FLUSH_TIMEOUT) {
+            warn!("Failed to deliver queued messages to {}: {e}", self.target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_topic_urls() {
+        let target = parse_kafka_target("kafka://broker/sysmon-events").unwrap();
+        assert_eq!(target.brokers, ["broker:9092"]);
+        assert_eq!(target.topic, "sysmon-events");
+        let target = parse_kafka_target("kafka://k1:9093,[::1],10.0.0.5/soc.sysmon_v1").unwrap();
+        assert_eq!(target.brokers, ["k1:9093", "[::1]:9092", "10.0.0.5:9092"]);
+        assert_eq!(
+            target.to_string(),
+            "kafka://k1:9093,[::1]:9092,10.0.0.5:9092/soc.sysmon_v1"
+        );
+        assert!(parse_kafka_target("kafka://broker").is_err());
+        assert!(parse_kafka_target("kafka://broker/").is_err());
+        assert!(parse_kafka_target("kafka:///topic").is_err());
+        assert!(parse_kafka_target("kafka://broker:99999/topic").is_err());
+        assert!(parse_kafka_target("kafka://broker/bad topic").is_err());
+    }
+}
@@ -1,11 +1,53 @@
+//! Parsing, filtering and anomaly detection for Windows Sysmon logs.
+//!
+//! The [`prelude`] module re-exports the types needed to use the analyzer as a library.
+//! Library functions do not print to stdout; diagnostics are reported through `tracing`.
 pub mod analyzer;
+pub mod archive;
 pub mod cli;
 pub mod commands;
+pub mod config_reload;
+pub mod daemon;
+pub mod detection_state;
+pub mod diff;
 pub mod display;
+pub mod event_detail;
+pub mod export;
 pub mod filters;
-mod helpers;
+#[cfg(test)]
+mod fixtures;
+mod follow;
+pub mod forward;
+pub mod graph;
+pub mod helpers;
+pub mod incident;
+pub mod index;
+pub mod intel;
+pub mod jsonl;
+#[cfg(feature = "kafka")]
+pub mod kafka;
 #[cfg(windows)]
 mod live_monitor;
+pub mod live_output;
+pub mod mapping;
+pub mod netsummary;
+pub mod notify;
 pub mod parser;
-mod sysmon;
+pub mod pipeline;
+pub mod powershell;
+pub mod prelude;
+pub mod profile;
+pub mod sarif;
+pub mod security;
+pub mod server;
+pub mod session;
+pub mod stats;
+pub mod syslog;
+pub mod sysmon;
+pub mod sysmon_schema;
 pub mod telemetry;
+pub mod template;
+pub mod timesketch;
+pub mod triage;
+pub mod tui;
+pub mod yaml_or_json;
@@ -1,22 +1,151 @@
+use crate::analyzer::DetectionConfig;
+use crate::analyzer::detector::{self, Detector};
+use crate::config_reload::ConfigReload;
+use crate::detection_state::{self, STATE_SAVE_INTERVAL};
 use crate::filters::EventFilter;
+use crate::forward::Forwarder;
+use crate::helpers::HasSystem;
+use crate::live_output::{EventPrinting, SessionSummary, StatsTicker};
 use crate::sysmon::Event as SysmonEvent;
-use crate::{analyzer, display, parser};
+use crate::{analyzer, display, notify, parser, sysmon};
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
 use colored::Colorize;
 use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::{debug, error, info, warn};
 use windows::Win32::System::Threading::{CreateEventW, ResetEvent, WaitForSingleObject};
 use windows::{
     Win32::{Foundation::*, System::EventLog::*},
     core::*,
 };
-const BUFFER_SIZE: usize = 1000;
+pub(crate) const DEFAULT_BUFFER_SIZE: usize = 1000;
+const SYSMON_CHANNEL: PCWSTR = w!("Microsoft-Windows-Sysmon/Operational");
 
-pub fn start_monitoring(filter: EventFilter, detect: bool) -> Result<Vec<SysmonEvent>> {
+/// Computer whose Sysmon channel is read over the Event Log remoting protocol (RPC)
+pub struct RemoteHost {
+    pub host: String,
+    /// Account used to connect, the current user when not set
+    pub user: Option<String>,
+    pub domain: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Connection to the event log service of a remote computer
+struct Session(EVT_HANDLE);
+impl Session {
+    unsafe fn open(remote: &RemoteHost) -> Result<Self> {
+        let mut server = to_wide(&remote.host);
+        let mut user = remote.user.as_deref().map(to_wide);
+        let mut domain = remote.domain.as_deref().map(to_wide);
+        let mut password = remote.password.as_deref().map(to_wide);
+        let as_pwstr = |value: &mut Option<Vec<u16>>| {
+            value
+                .as_mut()
+                .map_or(PWSTR::null(), |v| PWSTR(v.as_mut_ptr()))
+        };
+        let login = EVT_RPC_LOGIN {
+            Server: PWSTR(server.as_mut_ptr()),
+            User: as_pwstr(&mut user),
+            Domain: as_pwstr(&mut domain),
+            Password: as_pwstr(&mut password),
+            Flags: EvtRpcLoginAuthNegotiate.0,
+        };
+        let handle = unsafe {
+            EvtOpenSession(
+                EvtRpcLogin,
+                &login as *const EVT_RPC_LOGIN as *const _,
+                None,
+                None,
+            )
+        };
+        // Don't leave the password in memory longer than needed
+        if let Some(password) = password.as_mut() {
+            password.fill(0);
+        }
+        let handle = handle
+            .map_err(|e| anyhow!("Failed to open event log session on {}: {e}", remote.host))?;
+        info!("Connected to the event log of {}", remote.host);
+        Ok(Self(handle))
+    }
+}
+impl Drop for Session {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = EvtClose(self.0);
+        }
+    }
+}
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Options of a live monitoring session
+pub struct MonitorConfig {
+    pub filter: EventFilter,
+    pub detect: bool,
+    pub detection: DetectionConfig,
+    /// Replay events from this far back before subscribing
+    pub since: Option<Duration>,
+    /// Maximum number of events kept as detection context
+    pub buffer_size: usize,
+    /// Drop context events older than this, relative to the newest event
+    pub context_window: Option<Duration>,
+    /// File used to persist the position in the channel between runs
+    pub bookmark: Option<PathBuf>,
+    /// File used to persist the detection context between runs
+    pub state: Option<PathBuf>,
+    /// Collector or topic receiving anomalies, and matching events with `forward_events`
+    pub forwarder: Option<Forwarder>,
+    pub forward_events: bool,
+    /// Ring the bell and show a desktop notification for High and Critical anomalies
+    pub notify: bool,
+    /// Which matching events are printed
+    pub printing: EventPrinting,
+    /// Time between two statistics lines, `None` to disable them
+    pub stats_interval: Option<std::time::Duration>,
+    /// Monitor this computer instead of the local one
+    pub remote: Option<RemoteHost>,
+    /// Config file whose changes replace the filter and the detection config while monitoring
+    pub reload: Option<ConfigReload<(EventFilter, DetectionConfig)>>,
+}
+
+/// Outcome of a monitoring session
+pub struct MonitorOutcome {
+    /// Detection context buffered when the session stopped
+    pub events: Vec<SysmonEvent>,
+    pub summary: SessionSummary,
+}
+
+pub fn start_monitoring(config: MonitorConfig) -> Result<MonitorOutcome> {
+    let MonitorConfig {
+        filter,
+        detect,
+        detection,
+        since,
+        buffer_size,
+        context_window,
+        bookmark,
+        state,
+        forwarder,
+        forward_events,
+        notify,
+        printing,
+        stats_interval,
+        remote,
+        reload,
+    } = config;
+    let buffer_size = buffer_size.max(1);
     info!("Starting live monitoring");
-    verify_sysmon_channel()?;
+    let session = match &remote {
+        Some(remote) => Some(unsafe { Session::open(remote)? }),
+        None => None,
+    };
+    verify_sysmon_channel(session.as_ref())?;
     // Set up Ctrl+C handler
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
@@ -27,31 +156,131 @@ pub fn start_monitoring(filter: EventFilter, detect: bool) -> Result<Vec<SysmonE
         );
         r.store(false, Ordering::SeqCst);
     })?;
-    let events_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(BUFFER_SIZE)));
-    let sub_result =
-        unsafe { subscribe_to_events(filter, detect, running.clone(), events_buffer.clone()) };
+    let bookmark = match bookmark {
+        Some(path) => Some(unsafe { Bookmark::open(path)? }),
+        None => None,
+    };
+    let resume = bookmark.as_ref().is_some_and(|b| b.resumed);
+    let mut context = VecDeque::with_capacity(buffer_size);
+    if let Some(path) = &state {
+        let restored = detection_state::load_state(path)?;
+        let skip = restored.len().saturating_sub(buffer_size);
+        context.extend(restored.into_iter().skip(skip));
+    }
+    let events_buffer = Arc::new(Mutex::new(context));
+    let mut pipeline = EventPipeline {
+        session: session.as_ref().map(|session| session.0),
+        filter,
+        renderer: unsafe { EventRenderer::new()? },
+        detect,
+        custom_detectors: detection.detectors.instantiate(),
+        detection,
+        events_buffer: events_buffer.clone(),
+        buffer_size,
+        context_window,
+        bookmark,
+        state: state.map(StateFile::new),
+        forwarder,
+        forward_events,
+        notify,
+        printing,
+        ticker: StatsTicker::new(stats_interval, Instant::now()),
+        event_count: 0,
+        anomaly_count: 0,
+        summary: SessionSummary::new(Utc::now()),
+        reload,
+    };
+    if resume && since.is_some() {
+        warn!("Resuming from bookmark, ignoring --since");
+    }
+    let mut start = SubscriptionStart::Future;
+    if let Some(since) = since.filter(|_| !resume) {
+        match unsafe { backfill_events(&mut pipeline, since, &running) } {
+            Ok(Some(position)) => start = SubscriptionStart::AfterBookmark(position),
+            Ok(None) => start = SubscriptionStart::Since(since),
+            Err(e) => {
+                error!("Backfill of recent events failed: {}", e);
+                return Err(e);
+            }
+        }
+    }
+    let sub_result = unsafe { subscribe_to_events(&mut pipeline, running.clone(), start) };
     if let Err(e) = sub_result {
         error!("Error subscribing to events failed: {}", e);
         return Err(e);
     }
     info!("Monitoring stopped.");
+    pipeline.save_state(true);
+    let EventPipeline {
+        mut summary,
+        events_buffer: pipeline_buffer,
+        ..
+    } = pipeline;
+    summary.stop(Utc::now());
+    drop(pipeline_buffer);
     let final_buffer = Arc::try_unwrap(events_buffer)
         .map_err(|_| anyhow::anyhow!("Failed to unwrap events buffer"))?
         .into_inner()?;
-    Ok(final_buffer.into_iter().collect())
+    Ok(MonitorOutcome {
+        events: final_buffer.into_iter().collect(),
+        summary,
+    })
+}
+/// Read the events of the Sysmon channel matching the filter, only those of the last `since` if set
+pub fn collect_events(
+    filter: &EventFilter,
+    since: Option<Duration>,
+    remote: Option<&RemoteHost>,
+) -> Result<Vec<SysmonEvent>> {
+    let session = match remote {
+        Some(remote) => Some(unsafe { Session::open(remote)? }),
+        None => None,
+    };
+    verify_sysmon_channel(session.as_ref())?;
+    let query = filter.to_xpath(since);
+    debug!("Collection XPath query: {}", query);
+    let mut events = Vec::new();
+    unsafe {
+        let result_set = EvtQuery(
+            session.as_ref().map(|session| session.0),
+            SYSMON_CHANNEL,
+            &HSTRING::from(&query),
+            EvtQueryChannelPath.0 | EvtQueryForwardDirection.0,
+        )?;
+        let running = AtomicBool::new(true);
+        let mut renderer = EventRenderer::new()?;
+        let result = for_each_event(
+            result_set,
+            &running,
+            |event_handle| match process_event_handle(event_handle, filter, &mut renderer) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to parse event: {}", e),
+            },
+        );
+        let _ = EvtClose(result_set);
+        result?;
+    }
+    info!("Collected {} events", events.len());
+    Ok(events)
 }
-fn verify_sysmon_channel() -> Result<()> {
-    let channel = w!("Microsoft-Windows-Sysmon/Operational");
+fn verify_sysmon_channel(session: Option<&Session>) -> Result<()> {
     unsafe {
-        let handle = EvtOpenChannelConfig(None, channel, 0);
+        let handle = EvtOpenChannelConfig(session.map(|session| session.0), SYSMON_CHANNEL, 0);
         if let Err(e) = handle {
+            let reasons = if session.is_some() {
+                "1. Sysmon is not installed on the remote computer.\n\
+                2. The Remote Event Log Management firewall rules are not enabled.\n\
+                3. The account is not allowed to read the remote event log."
+            } else {
+                "1. Sysmon is not installed.\n\
+                2. Sysmon service is not running.\n\
+                3. Application was not run as administrator."
+            };
             return Err(anyhow!(
                 "Sysmon channel not found or inaccessible!\n\
                 Error: {e}\n\
-                Possible reasons:\n\
-                1. Sysmon is not installed.\n\
-                2. Sysmon service is not running.\n\
-                3. Application was not run as administrator."
+                Possible reasons:\n{reasons}"
             ));
         }
         let _ = EvtClose(handle?);
@@ -59,15 +288,355 @@ fn verify_sysmon_channel() -> Result<()> {
     println!("{}", "Sysmon channel verified.".bright_green());
     Ok(())
 }
-unsafe fn subscribe_to_events(
+/// Filter, display and analyze events coming from either the backfill query or the subscription
+struct EventPipeline {
+    /// Remote event log session, `None` for the local computer
+    session: Option<EVT_HANDLE>,
     filter: EventFilter,
+    renderer: EventRenderer,
     detect: bool,
-    running: Arc<AtomicBool>,
+    detection: DetectionConfig,
+    /// Instances of the registered custom detectors, kept for the whole session
+    custom_detectors: Vec<Box<dyn Detector>>,
     events_buffer: Arc<Mutex<VecDeque<SysmonEvent>>>,
+    buffer_size: usize,
+    context_window: Option<Duration>,
+    bookmark: Option<Bookmark>,
+    state: Option<StateFile>,
+    forwarder: Option<Forwarder>,
+    forward_events: bool,
+    notify: bool,
+    printing: EventPrinting,
+    ticker: StatsTicker,
+    event_count: usize,
+    anomaly_count: usize,
+    summary: SessionSummary,
+    reload: Option<ConfigReload<(EventFilter, DetectionConfig)>>,
+}
+impl EventPipeline {
+    /// Save the bookmark and the detection state after a batch of events
+    fn save_progress(&mut self) {
+        if let Some(bookmark) = self.bookmark.as_mut()
+            && let Err(e) = unsafe { bookmark.save() }
+        {
+            warn!("Failed to save bookmark: {}", e);
+        }
+        self.save_state(false);
+    }
+    unsafe fn handle(&mut self, event_handle: EVT_HANDLE) {
+        if let Some(bookmark) = self.bookmark.as_mut() {
+            unsafe { bookmark.update(event_handle) };
+        }
+        match unsafe { process_event_handle(event_handle, &self.filter, &mut self.renderer) } {
+            Ok(Some(event)) => {
+                self.event_count += 1;
+                self.summary.record_event(&event);
+                if self.printing.should_print(self.event_count) {
+                    display::print_compact_event(&event, self.event_count);
+                }
+                if self.forward_events
+                    && let Some(forwarder) = self.forwarder.as_mut()
+                    && let Err(e) = forwarder.send_event(&event)
+                {
+                    warn!("Failed to forward event: {:#}", e);
+                }
+                let mut buffer = self.events_buffer.lock().unwrap();
+                if self.detect {
+                    let mut anomalies = analyzer::detect_anomalies_live_with_config(
+                        &event,
+                        &buffer,
+                        &self.detection,
+                    );
+                    anomalies.extend(detector::detect_custom_live(
+                        &mut self.custom_detectors,
+                        &event,
+                        &self.detection,
+                    ));
+                    self.anomaly_count += anomalies.len();
+                    self.summary.record_anomalies(&anomalies);
+                    if !anomalies.is_empty() {
+                        display::display_anomalies_live(&anomalies);
+                        if self.notify {
+                            notify::notify(&anomalies);
+                        }
+                    }
+                    if let Some(forwarder) = self.forwarder.as_mut() {
+                        for anomaly in &anomalies {
+                            if let Err(e) = forwarder.send_anomaly(anomaly) {
+                                warn!("Failed to forward anomaly: {:#}", e);
+                            }
+                        }
+                    }
+                }
+                // If Buffer is full, keep it at max size
+                while buffer.len() >= self.buffer_size {
+                    buffer.pop_front();
+                }
+                if let Some(window) = self.context_window {
+                    prune_expired(&mut buffer, &event, window);
+                }
+                buffer.push_back(event);
+                drop(buffer);
+                self.print_stats();
+            }
+            Ok(None) => {
+                // Ignore: Event was filtered out
+            }
+            Err(e) => {
+                warn!("Failed to parse event: {}", e);
+            }
+        }
+    }
+    /// Print the statistics line when it is due
+    fn print_stats(&mut self) {
+        if let Some(line) = self
+            .ticker
+            .tick(Instant::now(), self.event_count, self.anomaly_count)
+        {
+            println!("{}", line.bright_black());
+        }
+    }
+    /// Swap in the filter and detection config of a changed config file, between two events so
+    /// each event sees either the previous settings or the new ones. The custom detectors and
+    /// the context buffer are kept, the subscription still selects events with the XPath query
+    /// of the command line filters, which a config file filter does not change.
+    fn reload_config(&mut self) {
+        let Some(reload) = self.reload.as_mut() else {
+            return;
+        };
+        if let Some((filter, detection)) = reload.poll(Instant::now()) {
+            self.filter = filter;
+            self.detection = detection;
+            println!(
+                "{}",
+                format!("Applied changes of {}", reload.path().display()).bright_green()
+            );
+        }
+    }
+    /// Write the detection context to the state file, at most every [`STATE_SAVE_INTERVAL`]
+    /// unless forced
+    fn save_state(&mut self, force: bool) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        if !force && state.saved.elapsed() < STATE_SAVE_INTERVAL {
+            return;
+        }
+        let buffer = self.events_buffer.lock().unwrap();
+        let result = detection_state::state_to_json(buffer.iter())
+            .and_then(|json| write_atomically(&state.path, &json));
+        match result {
+            Ok(()) => debug!("Saved {} context events", buffer.len()),
+            Err(e) => warn!("Failed to save detection state: {:#}", e),
+        }
+        state.saved = Instant::now();
+    }
+}
+/// Detection context persisted to disk so monitoring can resume with its history after a restart
+struct StateFile {
+    path: PathBuf,
+    saved: Instant,
+}
+impl StateFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            saved: Instant::now(),
+        }
+    }
+}
+/// Position in the Sysmon channel persisted to disk so monitoring can resume after a restart
+struct Bookmark {
+    handle: EVT_HANDLE,
+    path: PathBuf,
+    /// A saved bookmark was loaded from disk
+    resumed: bool,
+    dirty: bool,
+}
+impl Bookmark {
+    unsafe fn open(path: PathBuf) -> Result<Self> {
+        let saved = fs::read_to_string(&path)
+            .ok()
+            .filter(|xml| !xml.trim().is_empty());
+        let handle = unsafe {
+            match &saved {
+                Some(xml) => EvtCreateBookmark(&HSTRING::from(xml.as_str()))
+                    .map_err(|e| anyhow!("Invalid bookmark in {}: {e}", path.display()))?,
+                None => EvtCreateBookmark(PCWSTR::null())?,
+            }
+        };
+        if saved.is_some() {
+            info!("Resuming from bookmark {}", path.display());
+        }
+        Ok(Self {
+            handle,
+            path,
+            resumed: saved.is_some(),
+            dirty: false,
+        })
+    }
+    unsafe fn update(&mut self, event_handle: EVT_HANDLE) {
+        match unsafe { EvtUpdateBookmark(self.handle, event_handle) } {
+            Ok(()) => self.dirty = true,
+            Err(e) => warn!("Failed to update bookmark: {}", e),
+        }
+    }
+    /// Write the bookmark to disk if it changed since the last save
+    unsafe fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let xml = unsafe { render_xml(self.handle, EvtRenderBookmark.0)? };
+        write_atomically(&self.path, &xml)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+impl Drop for Bookmark {
+    fn drop(&mut self) {
+        unsafe {
+            if let Err(e) = self.save() {
+                warn!("Failed to save bookmark: {}", e);
+            }
+            let _ = EvtClose(self.handle);
+        }
+    }
+}
+/// Replace the file contents without leaving a truncated file behind on crash
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+/// Drop buffered events that fall outside the time window ending at the newest event
+fn prune_expired(buffer: &mut VecDeque<SysmonEvent>, newest: &SysmonEvent, window: Duration) {
+    let Some(newest_time) = newest.system().time_created.time else {
+        return;
+    };
+    while let Some(oldest) = buffer.front() {
+        match oldest.system().time_created.time {
+            Some(time) if newest_time.signed_duration_since(time) > window => {
+                buffer.pop_front();
+            }
+            _ => break,
+        }
+    }
+}
+/// Read all currently available events from a query or subscription handle
+unsafe fn drain_events(
+    result_set: EVT_HANDLE,
+    pipeline: &mut EventPipeline,
+    running: &AtomicBool,
 ) -> Result<()> {
     unsafe {
-        let channel_path = w!("Microsoft-Windows-Sysmon/Operational");
-        let query = build_xpath_query(&filter);
+        for_each_event(result_set, running, |event_handle| {
+            pipeline.handle(event_handle)
+        })?;
+        pipeline.save_progress();
+        Ok(())
+    }
+}
+/// Call `f` with every event handle currently available, closing the handles afterwards
+unsafe fn for_each_event(
+    result_set: EVT_HANDLE,
+    running: &AtomicBool,
+    mut f: impl FnMut(EVT_HANDLE),
+) -> Result<()> {
+    unsafe {
+        while running.load(Ordering::SeqCst) {
+            let mut events: [isize; 16] = [EVT_HANDLE::default().0; 16];
+            let mut returned = 0u32;
+            let result = EvtNext(result_set, &mut events, 0, 0, &mut returned);
+            if let Err(e) = result {
+                if e.code() == ERROR_NO_MORE_ITEMS.to_hresult() {
+                    break;
+                }
+                error!("EvtNext failed: {}", e);
+                break;
+            }
+            if returned == 0 {
+                break;
+            }
+            for &event_handle in &events[..returned as usize] {
+                f(EVT_HANDLE(event_handle));
+                let _ = EvtClose(EVT_HANDLE(event_handle));
+            }
+        }
+        Ok(())
+    }
+}
+/// Run events already stored in the channel through the pipeline before going live. Returns a
+/// bookmark on the last of them, after which the subscription starts so that events written
+/// meanwhile are not missed, `None` if there were none.
+unsafe fn backfill_events(
+    pipeline: &mut EventPipeline,
+    since: Duration,
+    running: &AtomicBool,
+) -> Result<Option<EVT_HANDLE>> {
+    unsafe {
+        let query = pipeline.filter.to_xpath(Some(since));
+        let query_wide = HSTRING::from(&query);
+        debug!("Backfill XPath query: {}", query);
+        println!(
+            "{}",
+            format!(
+                "Replaying events from the last {}...\n",
+                format_since(since)
+            )
+            .bright_green()
+        );
+        let result_set = EvtQuery(
+            pipeline.session,
+            SYSMON_CHANNEL,
+            &query_wide,
+            EvtQueryChannelPath.0 | EvtQueryForwardDirection.0,
+        )?;
+        let before = pipeline.event_count;
+        let position = match EvtCreateBookmark(PCWSTR::null()) {
+            Ok(position) => position,
+            Err(e) => {
+                let _ = EvtClose(result_set);
+                return Err(e.into());
+            }
+        };
+        let mut read_any = false;
+        let result = for_each_event(result_set, running, |event_handle| {
+            match EvtUpdateBookmark(position, event_handle) {
+                Ok(()) => read_any = true,
+                Err(e) => warn!("Failed to update backfill position: {}", e),
+            }
+            pipeline.handle(event_handle)
+        });
+        pipeline.save_progress();
+        let _ = EvtClose(result_set);
+        info!("Backfilled {} events", pipeline.event_count - before);
+        if result.is_err() || !read_any {
+            let _ = EvtClose(position);
+        }
+        result.map(|()| read_any.then_some(position))
+    }
+}
+/// Where the subscription starts reading the channel, unless a saved bookmark is resumed
+enum SubscriptionStart {
+    /// Events written from now on
+    Future,
+    /// Events after the last backfilled one, a bookmark closed once subscribed
+    AfterBookmark(EVT_HANDLE),
+    /// Events of the backfill window, when the backfill found none: any written since then
+    Since(Duration),
+}
+unsafe fn subscribe_to_events(
+    pipeline: &mut EventPipeline,
+    running: Arc<AtomicBool>,
+    start: SubscriptionStart,
+) -> Result<()> {
+    unsafe {
+        let since = match start {
+            SubscriptionStart::Since(since) => Some(since),
+            _ => None,
+        };
+        let query = pipeline.filter.to_xpath(since);
         let query_wide = HSTRING::from(&query);
         debug!("XPath query: {}", query);
         println!(
@@ -75,152 +644,229 @@ unsafe fn subscribe_to_events(
             "Subscription active. Waiting for events...\n".bright_green()
         );
         let signal_event = CreateEventW(None, true, false, None)?;
+        let (bookmark, flags) = match (&pipeline.bookmark, &start) {
+            (Some(bookmark), _) if bookmark.resumed => (
+                Some(bookmark.handle),
+                EvtSubscribeStartAfterBookmark.0 | EvtSubscribeStrict.0,
+            ),
+            (_, SubscriptionStart::AfterBookmark(position)) => {
+                (Some(*position), EvtSubscribeStartAfterBookmark.0)
+            }
+            (_, SubscriptionStart::Since(_)) => (None, EvtSubscribeStartAtOldestRecord.0),
+            (_, SubscriptionStart::Future) => (None, EvtSubscribeToFutureEvents.0),
+        };
         let subscription = EvtSubscribe(
-            None,
+            pipeline.session,
             Some(signal_event),
-            channel_path,
+            SYSMON_CHANNEL,
             &query_wide,
+            bookmark,
             None,
             None,
-            None,
-            EvtSubscribeToFutureEvents.0,
-        )?;
-        let mut event_count = 0;
+            flags,
+        );
+        if let SubscriptionStart::AfterBookmark(position) = start {
+            let _ = EvtClose(position);
+        }
+        let subscription = subscription?;
 
         while running.load(Ordering::SeqCst) {
             let wait_result = WaitForSingleObject(signal_event, 1000); // 1 second timeout
             if wait_result == WAIT_OBJECT_0 {
                 ResetEvent(signal_event)?;
-                loop {
-                    let mut events: [isize; 16] = [EVT_HANDLE::default().0; 16];
-                    let mut returned = 0u32;
-                    let result = EvtNext(subscription, &mut events, 0, 0, &mut returned);
-                    if let Err(e) = result {
-                        if e.code() == ERROR_NO_MORE_ITEMS.to_hresult() {
-                            break;
-                        }
-                        error!("EvtNext failed: {}", e);
-                        break;
-                    }
-                    if returned == 0 {
-                        break;
-                    }
-                    for i in 0..returned as isize {
-                        let event_handle = events[i as usize];
-                        match process_event_handle(EVT_HANDLE(i), &filter) {
-                            Ok(Some(event)) => {
-                                event_count += 1;
-                                display::print_compact_event(&event, event_count);
-                                let mut buffer = events_buffer.lock().unwrap();
-                                if detect {
-                                    let anomalies =
-                                        analyzer::detect_anomalies_live(&event, &buffer);
-                                    if !anomalies.is_empty() {
-                                        display::display_anomalies_live(&anomalies);
-                                    }
-                                }
-                                // If Buffer is full, keep it at max size
-                                if buffer.len() == BUFFER_SIZE {
-                                    buffer.pop_front();
-                                }
-                                buffer.push_back(event);
-                            }
-                            Ok(None) => {
-                                // Ignore: Event was filtered out
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse event: {}", e);
-                            }
-                        }
-                        let _ = EvtClose(EVT_HANDLE(event_handle));
-                    }
-                }
+                drain_events(subscription, pipeline, &running)?;
             } else if wait_result == WAIT_TIMEOUT {
-                continue;
+                // Keep the statistics line going while the channel is idle
+                pipeline.print_stats();
             }
+            pipeline.reload_config();
         }
         let _ = EvtClose(subscription);
         let _ = CloseHandle(signal_event);
 
-        info!("Processed {} events", event_count);
-        println!(
-            "\n{}",
-            format!("Processed {event_count} events:").bright_green()
-        );
+        info!("Processed {} events", pipeline.event_count);
         Ok(())
     }
 }
 
-/// Process a single event handle from the subscription
+/// Process a single event handle from the subscription. Events that the ID, time and computer
+/// filters reject are dropped after rendering only their `System` values, before their XML is
+/// rendered and parsed.
 unsafe fn process_event_handle(
     event_handle: EVT_HANDLE,
     filter: &EventFilter,
+    renderer: &mut EventRenderer,
 ) -> Result<Option<SysmonEvent>> {
-    unsafe {
-        let event_xml = render_event_xml(event_handle)?;
-        match parser::parse_xml_event(&event_xml) {
-            Ok(event) => {
-                if filter.matches(&event) {
-                    Ok(Some(event))
-                } else {
-                    Ok(None)
-                }
-            }
-            Err(e) => {
-                debug!("Failed to deserialize event: {}", e);
-                Err(e)
+    match unsafe { renderer.system_values(event_handle) } {
+        Ok(system)
+            if !sysmon::SUPPORTED_EVENT_IDS.contains(&system.event_id)
+                || !filter.matches_system(system.event_id, system.time, &system.computer) =>
+        {
+            return Ok(None);
+        }
+        Ok(_) => {}
+        Err(e) => debug!("Failed to render event system values: {}", e),
+    }
+    let event_xml = unsafe { renderer.xml(event_handle)? };
+    match parser::parse_xml_event(&event_xml) {
+        Ok(event) => {
+            if filter.matches(&event) {
+                Ok(Some(event))
+            } else {
+                Ok(None)
             }
         }
+        Err(e) => {
+            debug!("Failed to deserialize event: {}", e);
+            Err(e)
+        }
     }
 }
 
-/// Render the event data to an XML string
-unsafe fn render_event_xml(event_handle: EVT_HANDLE) -> Result<String> {
-    unsafe {
-        let mut buffer_size = 0u32;
-        let mut buffer_used = 0u32;
-        let mut property_count = 0u32;
-        let _ = EvtRender(
-            None,
-            event_handle,
-            EvtRenderEventXml.0,
-            0,
-            None,
-            &mut buffer_size,
-            &mut property_count,
-        );
+/// `System` values of an event used to filter it before rendering its XML
+struct SystemValues {
+    event_id: u16,
+    time: Option<DateTime<Utc>>,
+    computer: String,
+}
 
-        let mut str_buffer = vec![0u16; (buffer_size / 2) as usize + 1];
+/// Renders events with a render context and buffers that are reused for every event
+struct EventRenderer {
+    /// Context rendering the `System` values of an event
+    system_context: EVT_HANDLE,
+    /// Array of `EVT_VARIANT` followed by the strings they point to, kept 8-byte aligned
+    values: Vec<u64>,
+    xml: Vec<u16>,
+}
+
+impl EventRenderer {
+    unsafe fn new() -> Result<Self> {
+        let system_context = unsafe { EvtCreateRenderContext(None, EvtRenderContextSystem.0)? };
+        Ok(EventRenderer {
+            system_context,
+            values: Vec::new(),
+            xml: Vec::new(),
+        })
+    }
+
+    unsafe fn system_values(&mut self, handle: EVT_HANDLE) -> Result<SystemValues> {
+        let (_, count) = unsafe {
+            render_into(
+                Some(self.system_context),
+                handle,
+                EvtRenderEventValues.0,
+                &mut self.values,
+            )?
+        };
+        // The buffer holds `count` variants and was filled by EvtRender
+        let values = unsafe {
+            std::slice::from_raw_parts(self.values.as_ptr() as *const EVT_VARIANT, count as usize)
+        };
+        let value = |id: EVT_SYSTEM_PROPERTY_ID| {
+            values
+                .get(id.0 as usize)
+                .filter(|v| v.Type != EvtVarTypeNull.0 as u32)
+        };
+        let event_id = value(EvtSystemEventID)
+            .map(|v| unsafe { v.Anonymous.UInt16Val })
+            .unwrap_or_default();
+        let time = value(EvtSystemTimeCreated)
+            .and_then(|v| filetime_to_utc(unsafe { v.Anonymous.FileTimeVal }));
+        let computer = match value(EvtSystemComputer) {
+            Some(v) => unsafe { v.Anonymous.StringVal.to_string()? },
+            None => String::new(),
+        };
+        Ok(SystemValues {
+            event_id,
+            time,
+            computer,
+        })
+    }
+
+    unsafe fn xml(&mut self, handle: EVT_HANDLE) -> Result<String> {
+        let (used, _) = unsafe { render_into(None, handle, EvtRenderEventXml.0, &mut self.xml)? };
+        utf16_until_null(&self.xml[..used as usize / 2])
+    }
+}
+
+impl Drop for EventRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = EvtClose(self.system_context);
+        }
+    }
+}
+
+/// Render an event or bookmark into `buffer`, growing it when too small. Returns the bytes
+/// used and the number of rendered values.
+unsafe fn render_into<T: Copy + Default>(
+    context: Option<EVT_HANDLE>,
+    handle: EVT_HANDLE,
+    flags: u32,
+    buffer: &mut Vec<T>,
+) -> Result<(u32, u32)> {
+    let unit = std::mem::size_of::<T>();
+    let mut buffer_used = 0u32;
+    let mut property_count = 0u32;
+    let result = unsafe {
         EvtRender(
-            None,
-            event_handle,
-            EvtRenderEventXml.0,
-            buffer_size,
-            Some(str_buffer.as_mut_ptr() as *mut _),
+            context,
+            handle,
+            flags,
+            (buffer.len() * unit) as u32,
+            Some(buffer.as_mut_ptr() as *mut _),
             &mut buffer_used,
             &mut property_count,
-        )?;
-        // Null terminate the string
-        str_buffer[buffer_used as usize / 2] = 0;
-        let pcwstr = PCWSTR(str_buffer.as_ptr());
-        let xml_string = pcwstr.to_string()?;
-        Ok(xml_string)
+        )
+    };
+    match result {
+        Ok(()) => return Ok((buffer_used, property_count)),
+        Err(e) if e.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult() => {}
+        Err(e) => return Err(e.into()),
     }
+    buffer.resize((buffer_used as usize).div_ceil(unit), T::default());
+    unsafe {
+        EvtRender(
+            context,
+            handle,
+            flags,
+            (buffer.len() * unit) as u32,
+            Some(buffer.as_mut_ptr() as *mut _),
+            &mut buffer_used,
+            &mut property_count,
+        )?
+    };
+    Ok((buffer_used, property_count))
 }
-/// Build the XPath query to pre-filter events at the API level
-fn build_xpath_query(filter: &EventFilter) -> String {
-    let mut condition = Vec::new();
-    if let Some(ids) = filter.get_event_ids() {
-        if ids.is_empty() {
-            let id_conditions: Vec<String> = ids.iter().map(|id| format!("EventID={id}")).collect();
-            condition.push(id_conditions.join(" or "));
-        }
-    }
 
-    // Here we are building the query for the event filter
-    if condition.is_empty() {
-        "*".to_string()
+/// Render an event or bookmark handle to an XML string
+unsafe fn render_xml(handle: EVT_HANDLE, flags: u32) -> Result<String> {
+    let mut buffer = Vec::new();
+    let (used, _) = unsafe { render_into(None, handle, flags, &mut buffer)? };
+    utf16_until_null(&buffer[..used as usize / 2])
+}
+
+/// Text of a rendered UTF-16 string, up to its null terminator
+fn utf16_until_null(text: &[u16]) -> Result<String> {
+    let end = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+    Ok(String::from_utf16(&text[..end])?)
+}
+
+/// Time of a `FILETIME`, in 100-nanosecond intervals since 1601-01-01
+fn filetime_to_utc(filetime: u64) -> Option<DateTime<Utc>> {
+    const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+    let micros = filetime.checked_sub(UNIX_EPOCH_INTERVALS)? / 10;
+    DateTime::from_timestamp_micros(micros as i64)
+}
+/// Human-readable form of the backfill window
+fn format_since(since: Duration) -> String {
+    if since.num_seconds() % 86400 == 0 {
+        format!("{}d", since.num_days())
+    } else if since.num_seconds() % 3600 == 0 {
+        format!("{}h", since.num_hours())
+    } else if since.num_seconds() % 60 == 0 {
+        format!("{}m", since.num_minutes())
     } else {
-        format!("*[System[{}]", condition.join("  and "))
+        format!("{}s", since.num_seconds())
     }
 }
@@ -0,0 +1,237 @@
+//! Rate limiting of the events printed by the live monitor and its periodic statistics line, so
+//! the console stays usable during event storms, and the summary of a session when it stops
+use crate::analyzer::Anomaly;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Default time between two statistics lines
+pub const DEFAULT_STATS_INTERVAL: &str = "10s";
+/// Processes listed in the session summary
+pub const SUMMARY_TOP_PROCESSES: usize = 10;
+
+/// Which of the matching events are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPrinting {
+    All,
+    /// The first of every N events
+    Every(u64),
+    /// Only anomalies are printed
+    Quiet,
+}
+
+impl EventPrinting {
+    pub fn new(quiet: bool, print_every: Option<u64>) -> Self {
+        match (quiet, print_every) {
+            (true, _) => EventPrinting::Quiet,
+            (false, Some(every)) if every > 1 => EventPrinting::Every(every),
+            _ => EventPrinting::All,
+        }
+    }
+
+    /// Whether the `count`th matching event (starting at 1) is printed
+    pub fn should_print(&self, count: usize) -> bool {
+        match self {
+            EventPrinting::All => true,
+            EventPrinting::Every(every) => (count.saturating_sub(1) as u64).is_multiple_of(*every),
+            EventPrinting::Quiet => false,
+        }
+    }
+}
+
+/// One-line summary of the monitor's progress at a fixed interval
+#[derive(Debug)]
+pub struct StatsTicker {
+    /// `None` disables the summary
+    interval: Option<Duration>,
+    started: Instant,
+    last_tick: Instant,
+    events_at_last_tick: usize,
+}
+
+impl StatsTicker {
+    pub fn new(interval: Option<Duration>, now: Instant) -> Self {
+        Self {
+            interval: interval.filter(|interval| !interval.is_zero()),
+            started: now,
+            last_tick: now,
+            events_at_last_tick: 0,
+        }
+    }
+
+    /// Summary line once the interval has elapsed since the previous one. The event rate is the
+    /// one since the previous line.
+    pub fn tick(&mut self, now: Instant, events: usize, anomalies: usize) -> Option<String> {
+        let interval = self.interval?;
+        let elapsed = now.duration_since(self.last_tick);
+        if elapsed < interval {
+            return None;
+        }
+        let rate = events.saturating_sub(self.events_at_last_tick) as f64 / elapsed.as_secs_f64();
+        self.last_tick = now;
+        self.events_at_last_tick = events;
+        let uptime = now.duration_since(self.started).as_secs();
+        Some(format!(
+            "[stats] {:02}:{:02}:{:02} up, {events} events ({rate:.1}/s), {anomalies} anomalies",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60
+        ))
+    }
+}
+
+/// Events and anomalies seen by a monitoring session, reported when it stops
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub started: DateTime<Utc>,
+    pub stopped: DateTime<Utc>,
+    /// Maps Event ID to the event type and the matching events
+    events_by_id: BTreeMap<u16, (String, usize)>,
+    /// Maps process name to its matching events
+    processes: HashMap<String, usize>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl SessionSummary {
+    pub fn new(started: DateTime<Utc>) -> Self {
+        Self {
+            started,
+            stopped: started,
+            events_by_id: BTreeMap::new(),
+            processes: HashMap::new(),
+            anomalies: Vec::new(),
+        }
+    }
+
+    pub fn record_event(&mut self, event: &SysmonEvent) {
+        let id = event.system().event_id.event_id;
+        self.events_by_id
+            .entry(id)
+            .or_insert_with(|| (event.name().to_string(), 0))
+            .1 += 1;
+        let process = event.process_name();
+        if !process.is_empty() {
+            *self.processes.entry(process.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn record_anomalies(&mut self, anomalies: &[Anomaly]) {
+        self.anomalies.extend_from_slice(anomalies);
+    }
+
+    pub fn stop(&mut self, at: DateTime<Utc>) {
+        self.stopped = at;
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        self.stopped - self.started
+    }
+
+    pub fn total_events(&self) -> usize {
+        self.events_by_id.values().map(|(_, count)| count).sum()
+    }
+
+    /// Event type and count of each Event ID, by ID
+    pub fn events_by_id(&self) -> impl Iterator<Item = (u16, &str, usize)> {
+        self.events_by_id
+            .iter()
+            .map(|(id, (name, count))| (*id, name.as_str(), *count))
+    }
+
+    /// Anomalies of each severity, indexed by `Severity as usize`
+    pub fn anomalies_by_severity(&self) -> [usize; 4] {
+        let mut counts = [0; 4];
+        for anomaly in &self.anomalies {
+            counts[anomaly.severity() as usize] += 1;
+        }
+        counts
+    }
+
+    /// Processes with the most events, at most [`SUMMARY_TOP_PROCESSES`]
+    pub fn top_processes(&self) -> Vec<(&str, usize)> {
+        let mut processes: Vec<_> = self
+            .processes
+            .iter()
+            .map(|(process, count)| (process.as_str(), *count))
+            .collect();
+        processes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        processes.truncate(SUMMARY_TOP_PROCESSES);
+        processes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn printing_and_ticker() {
+        let every = EventPrinting::new(false, Some(100));
+        let printed: Vec<usize> = (1..=250).filter(|&n| every.should_print(n)).collect();
+        assert_eq!(printed, [1, 101, 201]);
+        assert!(!EventPrinting::new(true, None).should_print(1));
+        assert_eq!(EventPrinting::new(false, Some(1)), EventPrinting::All);
+
+        let start = Instant::now();
+        let mut ticker = StatsTicker::new(Some(Duration::from_secs(10)), start);
+        assert_eq!(ticker.tick(start + Duration::from_secs(5), 40, 0), None);
+        assert_eq!(
+            ticker
+                .tick(start + Duration::from_secs(10), 2500, 3)
+                .as_deref(),
+            Some("[stats] 00:00:10 up, 2500 events (250.0/s), 3 anomalies")
+        );
+        assert_eq!(
+            ticker
+                .tick(start + Duration::from_secs(3725), 2500, 3)
+                .as_deref(),
+            Some("[stats] 01:02:05 up, 2500 events (0.0/s), 3 anomalies")
+        );
+        let mut disabled = StatsTicker::new(Some(Duration::ZERO), start);
+        assert_eq!(disabled.tick(start + Duration::from_secs(60), 1, 0), None);
+    }
+
+    #[test]
+    fn session_summary_and_report() {
+        let process = |image: &str, command_line: &str| {
+            json_process_event(json!({ "Image": image, "CommandLine": command_line }))
+        };
+        let started: DateTime<Utc> = "2025-01-01T10:00:00Z".parse().unwrap();
+        let mut summary = SessionSummary::new(started);
+        let events = [
+            process(r"C:\Windows\System32\cmd.exe", "cmd.exe /c whoami"),
+            process(r"C:\Windows\System32\cmd.exe", "cmd.exe /c dir"),
+            process(r"C:\Windows\notepad.exe", "notepad.exe"),
+        ];
+        for event in &events {
+            summary.record_event(event);
+        }
+        summary.record_anomalies(&crate::analyzer::detect_anomalies(&events[..1]));
+        summary.stop(started + chrono::Duration::minutes(5));
+        assert_eq!(summary.total_events(), 3);
+        assert_eq!(
+            summary.events_by_id().collect::<Vec<_>>(),
+            [(1, "ProcessCreate", 3)]
+        );
+        assert_eq!(
+            summary.top_processes(),
+            [("cmd.exe", 2), ("notepad.exe", 1)]
+        );
+        let high = summary.anomalies_by_severity()[crate::analyzer::Severity::High as usize];
+        assert!(high >= 1);
+
+        let report = crate::export::session_report(&summary, &events[2..]);
+        assert_eq!(report["duration_seconds"], 300);
+        assert_eq!(report["anomalies_by_severity"]["High"], high);
+        assert_eq!(report["top_processes"][0]["process"], "cmd.exe");
+        assert_eq!(report["events"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            report["anomalies"].as_array().unwrap().len(),
+            summary.anomalies.len()
+        );
+    }
+}
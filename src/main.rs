@@ -1,7 +1,8 @@
 use anyhow::Result;
 use security_log_analyser::{cli, telemetry};
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     telemetry::init_tracing();
     tracing::info!("Staring Sysmon Log Analyzer");
     let config = cli::parse_args();
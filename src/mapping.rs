@@ -0,0 +1,317 @@
+//! Column mappings loading CSV and JSON Lines exports of other tools (EvtxECmd, Chainsaw, custom
+//! pipelines) into the Sysmon event model
+use crate::jsonl::{Record, normalize_time, scalar};
+use crate::sysmon::Event as SysmonEvent;
+use crate::yaml_or_json::{from_yaml_or_json, load_yaml_or_json};
+use anyhow::{Result, anyhow, bail};
+use csv::StringRecord;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Columns of an export holding the fields of the events, in YAML or JSON:
+///
+/// ```yaml
+/// event_id: EventId
+/// time: TimeCreated
+/// computer: Computer
+/// record_id: EventRecordId
+/// payload: Payload
+/// fields:
+///   Image: ExecutableInfo
+/// ```
+///
+/// Columns of JSON records are top-level keys or dotted paths such as `Event.System.EventID`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMapping {
+    pub event_id: String,
+    /// RFC 3339 or `YYYY-MM-DD HH:MM:SS[.fff]` time, taken as UTC
+    pub time: String,
+    pub computer: Option<String>,
+    pub record_id: Option<String>,
+    pub provider: Option<String>,
+    pub channel: Option<String>,
+    pub process_id: Option<String>,
+    pub thread_id: Option<String>,
+    pub user_id: Option<String>,
+    /// Event data as JSON: an object of field names and values, or the `EventData.Data` list of
+    /// `@Name`/`#text` pairs written by EvtxECmd
+    pub payload: Option<String>,
+    /// Event data fields read from their own columns, by Sysmon field name. They take precedence
+    /// over the payload.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Also use every column that is not mapped as an event data field of the same name
+    #[serde(default)]
+    pub other_columns: bool,
+}
+
+/// Values of one exported record
+enum Row<'a> {
+    Csv {
+        headers: &'a StringRecord,
+        record: &'a StringRecord,
+    },
+    Json(&'a Map<String, Value>),
+}
+
+impl Row<'_> {
+    fn get(&self, column: &str) -> Option<Value> {
+        match self {
+            Row::Csv { headers, record } => headers
+                .iter()
+                .position(|header| header == column)
+                .and_then(|index| record.get(index))
+                .map(|value| Value::String(value.to_string())),
+            Row::Json(object) => object.get(column).cloned().or_else(|| {
+                let mut path = column.split('.');
+                let mut value = object.get(path.next()?)?;
+                for name in path {
+                    value = value.get(name)?;
+                }
+                Some(value.clone())
+            }),
+        }
+    }
+
+    /// Columns with a scalar value
+    fn columns(&self) -> Vec<(String, String)> {
+        match self {
+            Row::Csv { headers, record } => headers
+                .iter()
+                .zip(record.iter())
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            Row::Json(object) => object
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), scalar(value)?)))
+                .collect(),
+        }
+    }
+}
+
+impl FieldMapping {
+    /// Load a YAML or JSON mapping file (JSON if the extension is `.json`)
+    pub fn load(path: &Path) -> Result<Self> {
+        load_yaml_or_json(path, "mapping file", from_yaml_or_json)
+    }
+
+    pub fn parse(content: &str, is_json: bool) -> Result<Self> {
+        from_yaml_or_json(content, is_json)
+    }
+
+    /// Fail early when the event ID or time column is missing from the CSV header
+    pub fn check_headers(&self, headers: &StringRecord) -> Result<()> {
+        for column in [&self.event_id, &self.time] {
+            if !headers.iter().any(|header| header == column) {
+                bail!("No column '{column}' in the CSV header");
+            }
+        }
+        Ok(())
+    }
+
+    /// Event of a CSV record
+    pub fn csv_event(&self, headers: &StringRecord, record: &StringRecord) -> Result<SysmonEvent> {
+        SysmonEvent::from_str(self.record(&Row::Csv { headers, record })?.to_xml())
+    }
+
+    /// Event of a JSON line
+    pub fn json_event(&self, line: &str) -> Result<SysmonEvent> {
+        let value: Value =
+            serde_json::from_str(line).map_err(|e| anyhow!("Malformed JSON record: {e}"))?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| anyhow!("JSON record is not an object"))?;
+        SysmonEvent::from_str(self.record(&Row::Json(object))?.to_xml())
+    }
+
+    fn record(&self, row: &Row) -> Result<Record> {
+        let text = |column: Option<&String>| {
+            column
+                .and_then(|column| row.get(column))
+                .and_then(|value| scalar(&value))
+                .unwrap_or_default()
+        };
+        let required = |column: &String| {
+            Some(text(Some(column)))
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| anyhow!("No value in column: {column}"))
+        };
+        let mut event_data = match self.payload.as_ref().and_then(|column| row.get(column)) {
+            Some(payload) => payload_fields(&payload)?,
+            None => Vec::new(),
+        };
+        for (field, column) in &self.fields {
+            if let Some(value) = row.get(column).and_then(|value| scalar(&value)) {
+                event_data.retain(|(name, _)| name != field);
+                event_data.push((field.clone(), value));
+            }
+        }
+        if self.other_columns {
+            for (name, value) in row.columns() {
+                if !self.is_mapped(&name) && !event_data.iter().any(|(field, _)| *field == name) {
+                    event_data.push((name, value));
+                }
+            }
+        }
+        Ok(Record {
+            event_id: required(&self.event_id)?,
+            provider_name: text(self.provider.as_ref()),
+            time_created: normalize_time(&required(&self.time)?)?,
+            record_id: text(self.record_id.as_ref()),
+            process_id: text(self.process_id.as_ref()),
+            thread_id: text(self.thread_id.as_ref()),
+            channel: text(self.channel.as_ref()),
+            computer: text(self.computer.as_ref()),
+            user_id: text(self.user_id.as_ref()),
+            event_data,
+            ..Record::default()
+        })
+    }
+
+    fn is_mapped(&self, column: &str) -> bool {
+        [
+            Some(&self.event_id),
+            Some(&self.time),
+            self.computer.as_ref(),
+            self.record_id.as_ref(),
+            self.provider.as_ref(),
+            self.channel.as_ref(),
+            self.process_id.as_ref(),
+            self.thread_id.as_ref(),
+            self.user_id.as_ref(),
+            self.payload.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.fields.values())
+        .any(|mapped| mapped == column)
+    }
+}
+
+/// Fields of an event data payload, given as a JSON object or as JSON text
+fn payload_fields(payload: &Value) -> Result<Vec<(String, String)>> {
+    let payload = match payload {
+        Value::String(text) if text.trim().is_empty() => return Ok(Vec::new()),
+        Value::String(text) => {
+            serde_json::from_str(text).map_err(|e| anyhow!("Malformed JSON payload: {e}"))?
+        }
+        value => value.clone(),
+    };
+    let data = payload.get("EventData").unwrap_or(&payload);
+    Ok(match data.get("Data") {
+        Some(Value::Array(items)) => items.iter().filter_map(named_value).collect(),
+        Some(item @ Value::Object(_)) => named_value(item).into_iter().collect(),
+        _ => data
+            .as_object()
+            .ok_or_else(|| anyhow!("Event data payload is not a JSON object"))?
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), scalar(value)?)))
+            .collect(),
+    })
+}
+
+/// `{"@Name": "Image", "#text": "C:\\..."}` element of an XML to JSON conversion
+fn named_value(item: &Value) -> Option<(String, String)> {
+    Some((
+        item.get("@Name")?.as_str()?.to_string(),
+        item.get("#text").and_then(scalar).unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::HasSystem;
+
+    #[test]
+    fn evtxecmd_csv_and_chainsaw_json() {
+        let mapping =
+            FieldMapping::parse(include_str!("../mappings/evtxecmd.yaml"), false).unwrap();
+        let data = [
+            ("UtcTime", "2019-04-30 22:52:27.588"),
+            ("ProcessGuid", "{365ABB72-D15B-5CC8-0000-0010E2A21600}"),
+            ("ProcessId", "3788"),
+            ("Image", r"C:\Windows\System32\whoami.exe"),
+            ("CommandLine", "whoami"),
+            ("CurrentDirectory", r"C:\Users\IEUser\"),
+            ("User", r"IEWIN7\IEUser"),
+            ("LogonGuid", "{365ABB72-CFFE-5CC8-0000-002004EF0100}"),
+            ("LogonId", "0x1ef04"),
+            ("TerminalSessionId", "1"),
+            ("IntegrityLevel", "High"),
+            ("Hashes", "SHA1=1234"),
+            (
+                "ParentProcessGuid",
+                "{365ABB72-D14F-5CC8-0000-0010B29B1600}",
+            ),
+            ("ParentProcessId", "3600"),
+            ("ParentImage", r"C:\Windows\System32\cmd.exe"),
+            ("ParentCommandLine", "cmd.exe"),
+        ];
+        let payload = serde_json::json!({"EventData": {"Data": data
+            .iter()
+            .map(|(name, value)| serde_json::json!({"@Name": name, "#text": value}))
+            .collect::<Vec<_>>()}});
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record([
+                "RecordNumber",
+                "EventRecordId",
+                "TimeCreated",
+                "EventId",
+                "Provider",
+                "Channel",
+                "Computer",
+                "UserId",
+                "MapDescription",
+                "Payload",
+            ])
+            .unwrap();
+        writer
+            .write_record([
+                "7",
+                "1042",
+                "2019-04-30 22:52:27.5889760",
+                "1",
+                "Microsoft-Windows-Sysmon",
+                "Microsoft-Windows-Sysmon/Operational",
+                "IEWIN7",
+                "S-1-5-18",
+                "Process creation",
+                &payload.to_string(),
+            ])
+            .unwrap();
+        let csv = writer.into_inner().unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        let headers = reader.headers().unwrap().clone();
+        mapping.check_headers(&headers).unwrap();
+        let record = reader.records().next().unwrap().unwrap();
+        let event = mapping.csv_event(&headers, &record).unwrap();
+        assert_eq!(event.system().event_record_id.event_record_id, 1042);
+        assert_eq!(event.system().computer.computer, "IEWIN7");
+        let SysmonEvent::ProcessCreate(process) = &event else {
+            panic!("Expected ProcessCreate event, got {event:?}");
+        };
+        assert_eq!(process.event_data.command_line.command_line, "whoami");
+        assert_eq!(process.event_data.process_id, 3788);
+
+        let mapping =
+            FieldMapping::parse(include_str!("../mappings/chainsaw.yaml"), false).unwrap();
+        let line = r#"{"group":"Sysmon","kind":"individual","document":{"kind":"evtx","data":{"Event":{"System":{"EventID":22,"EventRecordID":77,"Computer":"WS1","TimeCreated_attributes":{"SystemTime":"2025-01-01T10:00:00.123456Z"},"Provider_attributes":{"Name":"Microsoft-Windows-Sysmon"}},"EventData":{"UtcTime":"2025-01-01 10:00:00.123","ProcessGuid":"{11111111-2222-3333-4444-555555555555}","ProcessId":4242,"QueryName":"evil.example","QueryStatus":"0","QueryResults":"::ffff:203.0.113.7;","Image":"C:\\Windows\\System32\\cmd.exe"}}}}}"#;
+        let SysmonEvent::DnsQuery(query) = mapping.json_event(line).unwrap() else {
+            panic!("Expected DnsQuery event");
+        };
+        assert_eq!(query.event_data.query_name, "evil.example");
+        assert_eq!(query.system.computer.computer, "WS1");
+
+        let missing = FieldMapping {
+            event_id: "EventID".to_string(),
+            time: "Time".to_string(),
+            ..FieldMapping::default()
+        };
+        assert!(missing.check_headers(&headers).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! Outbound connections grouped by process and destination, to spot processes talking to
+//! unusual or many destinations
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Order of the processes in the summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NetSortOrder {
+    /// Most connections first
+    #[default]
+    Connections,
+    /// Most distinct destinations first
+    Destinations,
+}
+
+/// Connections of a process to one destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestinationSummary {
+    pub ip: String,
+    pub port: u16,
+    pub protocol: String,
+    /// Name Sysmon resolved for the destination, if any
+    pub hostname: Option<String>,
+    pub connections: usize,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Outbound connections of all processes with the same image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessNetSummary {
+    pub image: String,
+    pub connections: usize,
+    /// Destinations, most connections first
+    pub destinations: Vec<DestinationSummary>,
+}
+
+/// Summarize the outbound connections (event 3 initiated by the process) of the events
+pub fn summarize_connections(
+    events: &[SysmonEvent],
+    order: NetSortOrder,
+) -> Vec<ProcessNetSummary> {
+    let mut processes: Vec<ProcessNetSummary> = Vec::new();
+    let mut process_index: HashMap<String, usize> = HashMap::new();
+    let mut destination_index: HashMap<(usize, String, u16, String), usize> = HashMap::new();
+    for event in events {
+        let SysmonEvent::OutboundNetwork(e) = event else {
+            continue;
+        };
+        let data = &e.event_data;
+        let process = *process_index
+            .entry(data.image.image.to_lowercase())
+            .or_insert_with(|| {
+                processes.push(ProcessNetSummary {
+                    image: data.image.image.clone(),
+                    connections: 0,
+                    destinations: Vec::new(),
+                });
+                processes.len() - 1
+            });
+        let summary = &mut processes[process];
+        summary.connections += 1;
+        let key = (
+            process,
+            data.destination_ip.clone(),
+            data.destination_port,
+            data.protocol.to_lowercase(),
+        );
+        let destination = *destination_index.entry(key).or_insert_with(|| {
+            summary.destinations.push(DestinationSummary {
+                ip: data.destination_ip.clone(),
+                port: data.destination_port,
+                protocol: data.protocol.to_lowercase(),
+                hostname: None,
+                connections: 0,
+                first_seen: None,
+                last_seen: None,
+            });
+            summary.destinations.len() - 1
+        });
+        let destination_summary = &mut summary.destinations[destination];
+        destination_summary.connections += 1;
+        if destination_summary.hostname.is_none() {
+            destination_summary.hostname = data
+                .destination_hostname
+                .clone()
+                .filter(|host| !host.is_empty() && host != "-");
+        }
+        if let Some(time) = e.system.time_created.time {
+            let first = destination_summary
+                .first_seen
+                .map_or(time, |first| first.min(time));
+            let last = destination_summary
+                .last_seen
+                .map_or(time, |last| last.max(time));
+            destination_summary.first_seen = Some(first);
+            destination_summary.last_seen = Some(last);
+        }
+    }
+    for summary in &mut processes {
+        summary
+            .destinations
+            .sort_by_key(|destination| Reverse(destination.connections));
+    }
+    match order {
+        NetSortOrder::Connections => processes.sort_by_key(|summary| Reverse(summary.connections)),
+        NetSortOrder::Destinations => processes
+            .sort_by_key(|summary| Reverse((summary.destinations.len(), summary.connections))),
+    }
+    processes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonl::parse_json_event;
+
+    fn connection(time: &str, image: &str, ip: &str, port: u16) -> SysmonEvent {
+        let image = image.replace('\\', "\\\\");
+        parse_json_event(&format!(r#"{{"EventTime":"2025-01-01 {time}","Hostname":"TEST-PC","EventID":3,"RecordNumber":1,"UtcTime":"2025-01-01 {time}.000","ProcessGuid":"{{11111111-2222-3333-4444-555555555555}}","ProcessId":"42","Image":"{image}","User":"TEST-PC\\Bob","Protocol":"tcp","Initiated":"true","SourceIsIpv6":"false","SourceIp":"10.0.0.5","SourceHostname":"-","SourcePort":"50000","SourcePortName":"-","DestinationIsIpv6":"false","DestinationIp":"{ip}","DestinationHostname":"-","DestinationPort":"{port}","DestinationPortName":"-"}}"#)).unwrap()
+    }
+
+    #[test]
+    fn connections_by_process_and_destination() {
+        let browser = r"C:\Program Files\Mozilla Firefox\firefox.exe";
+        let implant = r"C:\Users\Public\svc.exe";
+        let events = [
+            connection("10:00:00", browser, "93.184.216.34", 443),
+            connection("10:05:00", browser, "93.184.216.34", 443),
+            connection("10:01:00", &implant.to_uppercase(), "203.0.113.7", 8443),
+            connection("10:02:00", implant, "10.0.0.1", 445),
+            connection("10:03:00", implant, "10.0.0.2", 445),
+            connection("10:04:00", browser, "93.184.216.34", 443),
+        ];
+        let by_count = summarize_connections(&events, NetSortOrder::Connections);
+        assert_eq!(by_count.len(), 2);
+        assert_eq!(
+            (by_count[0].image.as_str(), by_count[0].connections),
+            (browser, 3)
+        );
+        let destination = &by_count[0].destinations[0];
+        assert_eq!((destination.port, destination.connections), (443, 3));
+        assert_eq!(
+            destination.first_seen.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-01-01T10:00:00+00:00")
+        );
+        assert_eq!(
+            destination.last_seen.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-01-01T10:05:00+00:00")
+        );
+
+        let by_destinations = summarize_connections(&events, NetSortOrder::Destinations);
+        assert_eq!(by_destinations[0].image, implant.to_uppercase());
+        assert_eq!(by_destinations[0].destinations.len(), 3);
+    }
+}
@@ -0,0 +1,103 @@
+//! Alerts for severe anomalies found while watching: a terminal bell, and on Windows a desktop
+//! toast notification
+use crate::analyzer::{Anomaly, Severity};
+use crate::helpers::HasSystem;
+use std::io::Write;
+
+/// Anomalies at or above this severity raise a notification
+pub const NOTIFY_SEVERITY: Severity = Severity::High;
+
+/// Title and text of the notification for an anomaly, `None` when it is not severe enough
+pub fn notification(anomaly: &Anomaly) -> Option<(String, String)> {
+    if anomaly.severity() < NOTIFY_SEVERITY {
+        return None;
+    }
+    let title = match anomaly.event() {
+        Some(event) => format!(
+            "{} anomaly on {}",
+            anomaly.severity(),
+            event.system().computer.computer
+        ),
+        None => format!("{} anomaly", anomaly.severity()),
+    };
+    Some((
+        title,
+        format!("[{}] {}", anomaly.rule_id(), anomaly.description()),
+    ))
+}
+
+/// Ring the terminal bell and show a notification for each severe anomaly
+pub fn notify(anomalies: &[Anomaly]) {
+    let notifications: Vec<_> = anomalies.iter().filter_map(notification).collect();
+    if notifications.is_empty() {
+        return;
+    }
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+    #[cfg(windows)]
+    for (title, text) in &notifications {
+        if let Err(e) = toast::show(title, text) {
+            tracing::warn!("Failed to show notification: {:#}", e);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod toast {
+    use anyhow::Result;
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+    use windows::core::HSTRING;
+
+    /// Unpackaged applications have no AppUserModelID of their own; toasts are shown under the
+    /// ID of PowerShell, which is registered on every Windows installation
+    const APP_ID: &str =
+        r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+    pub(super) fn show(title: &str, text: &str) -> Result<()> {
+        let xml = format!(
+            r#"<toast><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text></binding></visual></toast>"#,
+            escape(title),
+            escape(text)
+        );
+        let document = XmlDocument::new()?;
+        document.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&document)?;
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?
+            .Show(&toast)?;
+        Ok(())
+    }
+
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::json_event;
+    use serde_json::json;
+
+    #[test]
+    fn only_severe_anomalies_notify() {
+        let tampering = json_event(25, json!({ "Type": "Image is replaced" }));
+        let anomalies = detect_anomalies(&[tampering]);
+        let (title, text) = anomalies
+            .iter()
+            .find_map(notification)
+            .expect("tampering is high severity");
+        assert_eq!(title, "High anomaly on TEST-PC");
+        assert!(text.starts_with("[SLA-017] "), "{text}");
+        assert!(
+            anomalies
+                .iter()
+                .filter(|anomaly| anomaly.severity() < Severity::High)
+                .all(|anomaly| notification(anomaly).is_none())
+        );
+    }
+}
@@ -1,35 +1,635 @@
+use crate::archive;
+use crate::helpers::HasSystem;
+use crate::jsonl;
+use crate::mapping::FieldMapping;
+use crate::powershell;
 use crate::sysmon::Event as SysmonEvent;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
 use evtx::{EvtxParser, ParserSettings};
-use std::path::Path;
-use tracing::{info, warn};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Outcome of parsing an EVTX file
+#[derive(Debug, Clone, Default)]
+pub struct ParseReport {
+    /// Successfully parsed Sysmon events
+    pub events: Vec<SysmonEvent>,
+    /// Records read from the file
+    pub total_records: usize,
+    /// Records that could not be read from the file
+    pub read_errors: usize,
+    /// Records that are not supported Sysmon events
+    pub skipped_records: usize,
+    /// Records dropped because another file already contained them
+    pub duplicates: usize,
+    /// Damage found in EVTX files parsed in recovery mode
+    pub recovery: Option<RecoveryStats>,
+    /// Records read but left out by sampling
+    pub sampled_out: usize,
+    /// Reading stopped at the event limit before the end of the files
+    pub truncated: bool,
+}
+
+/// Subset of the records to parse, for a quick first pass over very noisy logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    /// Keep one record out of this many, 1 keeps them all
+    pub every: u32,
+    /// Stop reading once this many events were parsed
+    pub max_events: Option<usize>,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            every: 1,
+            max_events: None,
+        }
+    }
+}
+
+impl Sampling {
+    /// Only part of the records may be parsed
+    pub fn is_active(&self) -> bool {
+        self.every > 1 || self.max_events.is_some()
+    }
+}
+
+/// Receiver of events as they are parsed
+pub type EventSink<'a> = dyn FnMut(SysmonEvent) + 'a;
+
+/// Sampling state shared by all files of a run, so the event limit applies to their total
+struct Sampler {
+    sampling: Sampling,
+    seen: u64,
+    kept: usize,
+}
+
+impl Sampler {
+    fn new(sampling: Sampling) -> Self {
+        Self {
+            sampling,
+            seen: 0,
+            kept: 0,
+        }
+    }
+
+    /// The record just read is kept, the first of every `every` records is
+    fn keep(&mut self) -> bool {
+        let keep = self
+            .seen
+            .is_multiple_of(u64::from(self.sampling.every.max(1)));
+        self.seen += 1;
+        keep
+    }
+
+    fn full(&self) -> bool {
+        self.sampling.max_events.is_some_and(|max| self.kept >= max)
+    }
+}
+
+/// Chunks and records of damaged EVTX files that could and could not be read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecoveryStats {
+    /// Non-empty chunks found in the files
+    pub chunks: usize,
+    /// Chunks skipped because their header, string or template tables are damaged
+    pub corrupt_chunks: usize,
+    /// Records read from the files, whether or not they are Sysmon events
+    pub recovered_records: u64,
+    /// Records announced by chunk headers that could not be read. Records of chunks whose
+    /// header is unreadable are unknown and not counted.
+    pub lost_records: u64,
+}
+
+impl RecoveryStats {
+    fn add(&mut self, other: &RecoveryStats) {
+        self.chunks += other.chunks;
+        self.corrupt_chunks += other.corrupt_chunks;
+        self.recovered_records += other.recovered_records;
+        self.lost_records += other.lost_records;
+    }
+}
+
+/// Layout of the input files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Windows event log files
+    #[default]
+    Evtx,
+    /// Winlogbeat (ECS) or nxlog JSON exports, one event per line
+    Jsonl,
+    /// CSV exports of other tools, read with a column mapping (`--mapping`)
+    Csv,
+}
+
+/// Parse all Sysmon events from an EVTX file, or from the EVTX files of a gzip or ZIP archive
 pub fn parse_evtx_file(path: &Path) -> Result<Vec<SysmonEvent>> {
+    Ok(parse_evtx_files(&[path.to_path_buf()])?.events)
+}
+
+/// Parse an EVTX file and return the events together with parsing statistics
+pub fn parse_evtx_file_with_report(path: &Path) -> Result<ParseReport> {
+    parse_evtx_file_with_progress(path, &ProgressBar::hidden())
+}
+
+/// Parse an EVTX file, advancing `progress` by one for every record read.
+/// The length of the bar is set from the record count announced in the file header.
+pub fn parse_evtx_file_with_progress(path: &Path, progress: &ProgressBar) -> Result<ParseReport> {
+    let mut events = Vec::new();
+    let mut report = parse_evtx_file_sampled(
+        path,
+        progress,
+        &mut Sampler::new(Sampling::default()),
+        &mut |event| events.push(event),
+    )?;
+    report.events = events;
+    Ok(report)
+}
+
+fn parse_evtx_file_sampled(
+    path: &Path,
+    progress: &ProgressBar,
+    sampler: &mut Sampler,
+    sink: &mut EventSink,
+) -> Result<ParseReport> {
+    let next_record_id = header_next_record_id(path);
     let mut parser = EvtxParser::from_path(path)
         .with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?
         .with_configuration(ParserSettings::default().num_threads(0));
-    let mut events = Vec::new();
+    let kept_before = sampler.kept;
+    let mut report = ParseReport::default();
 
     for record in parser.records() {
+        if sampler.full() {
+            report.truncated = true;
+            break;
+        }
+        report.total_records += 1;
+        progress.inc(1);
+        if let (Ok(record), Some(next_record_id)) = (&record, next_record_id)
+            && progress.length().is_none()
+        {
+            progress.set_length(next_record_id.saturating_sub(record.event_record_id));
+        }
+        if !sampler.keep() {
+            report.sampled_out += 1;
+            continue;
+        }
         match record {
             Ok(record) => match parse_xml_event(&record.data) {
                 Ok(event) => {
-                    events.push(event);
+                    sampler.kept += 1;
+                    sink(event);
+                }
+                Err(e) => {
+                    report.skipped_records += 1;
+                    debug!("Failed to parse record as Sysmon event: {}", e)
                 }
-                Err(e) => warn!("Failed to parse record as Sysmon event: {}", e),
             },
-            Err(e) => warn!("Error reading EVTX record: {}", e),
+            Err(e) => {
+                report.read_errors += 1;
+                warn!("Error reading EVTX record: {}", e)
+            }
+        }
+    }
+    progress.finish_and_clear();
+    log_report(path, &report, sampler.kept - kept_before);
+    Ok(report)
+}
+
+/// Parse a possibly damaged EVTX file chunk by chunk, skipping chunks that can't be read and
+/// counting the records lost with them. Records that are read but are not valid Sysmon events are
+/// written to `unparsed` as XML, preceded by a comment with the file, record ID and error.
+pub fn parse_evtx_file_recovering(
+    path: &Path,
+    progress: &ProgressBar,
+    mut unparsed: Option<&mut dyn Write>,
+) -> Result<ParseReport> {
+    let settings = Arc::new(ParserSettings::default());
+    let mut parser = EvtxParser::from_path(path)
+        .with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?
+        .with_configuration((*settings).clone());
+    let mut report = ParseReport::default();
+    let mut stats = RecoveryStats::default();
+
+    for chunk in parser.chunks() {
+        stats.chunks += 1;
+        let mut chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                stats.corrupt_chunks += 1;
+                warn!("Skipping unreadable EVTX chunk: {}", e);
+                continue;
+            }
+        };
+        let header = &chunk.header;
+        let expected =
+            (header.last_event_record_id + 1).saturating_sub(header.first_event_record_id);
+        let mut records = match chunk.parse(settings.clone()) {
+            Ok(records) => records,
+            Err(e) => {
+                stats.corrupt_chunks += 1;
+                stats.lost_records += expected;
+                warn!("Skipping damaged EVTX chunk ({} records): {}", expected, e);
+                continue;
+            }
+        };
+        let mut read = 0;
+        for record in records.iter() {
+            report.total_records += 1;
+            progress.inc(1);
+            let xml = match record {
+                Ok(record) => record.into_xml(),
+                Err(e) => Err(e),
+            };
+            let record = match xml {
+                Ok(record) => record,
+                Err(e) => {
+                    report.read_errors += 1;
+                    warn!("Error reading EVTX record: {}", e);
+                    continue;
+                }
+            };
+            read += 1;
+            match parse_xml_event(&record.data) {
+                Ok(event) => report.events.push(event),
+                Err(e) => {
+                    report.skipped_records += 1;
+                    debug!("Failed to parse record as Sysmon event: {}", e);
+                    if let Some(out) = unparsed.as_mut() {
+                        write_unparsed(&mut **out, path, record.event_record_id, &e, &record.data)?;
+                    }
+                }
+            }
+        }
+        stats.recovered_records += read;
+        stats.lost_records += expected.saturating_sub(read);
+    }
+    progress.finish_and_clear();
+    log_report(path, &report, report.events.len());
+    info!(
+        "Recovered {} records from {} chunks of {} ({} damaged chunks, {} records lost)",
+        stats.recovered_records,
+        stats.chunks,
+        path.to_string_lossy(),
+        stats.corrupt_chunks,
+        stats.lost_records
+    );
+    report.recovery = Some(stats);
+    Ok(report)
+}
+
+fn write_unparsed(
+    out: &mut dyn Write,
+    path: &Path,
+    record_id: u64,
+    error: &anyhow::Error,
+    xml: &str,
+) -> Result<()> {
+    // "--" is not allowed in XML comments
+    let comment = format!(
+        "file: {} record: {record_id} error: {error}",
+        path.to_string_lossy()
+    )
+    .replace("--", "- -");
+    writeln!(out, "<!-- {comment} -->\n{}", xml.trim()).context("Failed to write unparsed record")
+}
+
+/// Parse a Winlogbeat or nxlog JSON Lines export, advancing `progress` by one for every line.
+/// Blank lines are ignored, lines that are not Sysmon events are counted as skipped.
+pub fn parse_jsonl_file_with_progress(path: &Path, progress: &ProgressBar) -> Result<ParseReport> {
+    let mut events = Vec::new();
+    let mut report = parse_jsonl_file_sampled(
+        path,
+        None,
+        progress,
+        &mut Sampler::new(Sampling::default()),
+        &mut |event| events.push(event),
+    )?;
+    report.events = events;
+    Ok(report)
+}
+
+fn parse_jsonl_file_sampled(
+    path: &Path,
+    mapping: Option<&FieldMapping>,
+    progress: &ProgressBar,
+    sampler: &mut Sampler,
+    sink: &mut EventSink,
+) -> Result<ParseReport> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?;
+    let kept_before = sampler.kept;
+    let mut report = ParseReport::default();
+
+    for line in BufReader::new(file).lines() {
+        if sampler.full() {
+            report.truncated = true;
+            break;
+        }
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => line,
+            Err(e) => {
+                report.total_records += 1;
+                report.read_errors += 1;
+                warn!("Error reading JSON line: {}", e);
+                continue;
+            }
+        };
+        report.total_records += 1;
+        progress.inc(1);
+        if !sampler.keep() {
+            report.sampled_out += 1;
+            continue;
+        }
+        let event = match mapping {
+            Some(mapping) => mapping.json_event(&line),
+            None => jsonl::parse_json_event(&line),
+        };
+        match event {
+            Ok(event) => {
+                sampler.kept += 1;
+                sink(event);
+            }
+            Err(e) => {
+                report.skipped_records += 1;
+                debug!("Failed to parse JSON record as Sysmon event: {}", e)
+            }
         }
     }
-    if events.is_empty() {
+    progress.finish_and_clear();
+    log_report(path, &report, sampler.kept - kept_before);
+    Ok(report)
+}
+
+fn parse_csv_file_sampled(
+    path: &Path,
+    mapping: &FieldMapping,
+    progress: &ProgressBar,
+    sampler: &mut Sampler,
+    sink: &mut EventSink,
+) -> Result<ParseReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to open: {}", path.to_string_lossy()))?;
+    let headers = reader
+        .headers()
+        .with_context(|| format!("Failed to read the CSV header of {}", path.display()))?
+        .clone();
+    mapping
+        .check_headers(&headers)
+        .with_context(|| format!("Mapping doesn't fit {}", path.display()))?;
+    let kept_before = sampler.kept;
+    let mut report = ParseReport::default();
+
+    for record in reader.records() {
+        if sampler.full() {
+            report.truncated = true;
+            break;
+        }
+        report.total_records += 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                report.read_errors += 1;
+                warn!("Error reading CSV record: {}", e);
+                continue;
+            }
+        };
+        progress.inc(1);
+        if !sampler.keep() {
+            report.sampled_out += 1;
+            continue;
+        }
+        match mapping.csv_event(&headers, &record) {
+            Ok(event) => {
+                sampler.kept += 1;
+                sink(event);
+            }
+            Err(e) => {
+                report.skipped_records += 1;
+                debug!("Failed to parse CSV record as Sysmon event: {}", e)
+            }
+        }
+    }
+    progress.finish_and_clear();
+    log_report(path, &report, sampler.kept - kept_before);
+    Ok(report)
+}
+
+fn log_report(path: &Path, report: &ParseReport, events: usize) {
+    if events == 0 {
         warn!("No Sysmon events found in file: {}", path.to_string_lossy());
     } else {
         info!(
-            "Parsed {} valid Sysmon events from {}",
-            events.len(),
-            path.to_string_lossy()
+            "Parsed {} valid Sysmon events from {} ({} skipped, {} unreadable)",
+            events,
+            path.to_string_lossy(),
+            report.skipped_records,
+            report.read_errors
+        );
+    }
+}
+/// Parse several EVTX files into one event list, dropping records present in more than one file
+pub fn parse_evtx_files(paths: &[PathBuf]) -> Result<ParseReport> {
+    parse_files(paths, InputFormat::Evtx, false)
+}
+
+/// Like [`parse_evtx_files`] for files of the given format, drawing a progress bar with an ETA
+/// on stderr for each file when `show_progress` is set and stderr is a terminal
+pub fn parse_files(
+    paths: &[PathBuf],
+    format: InputFormat,
+    show_progress: bool,
+) -> Result<ParseReport> {
+    parse_files_sampled(paths, format, None, show_progress, Sampling::default())
+}
+
+/// Like [`parse_files`], parsing only the records selected by `sampling`. Records left out are
+/// counted in [`ParseReport::sampled_out`]. CSV files and, when given, JSON lines are read with
+/// the column `mapping`.
+pub fn parse_files_sampled(
+    paths: &[PathBuf],
+    format: InputFormat,
+    mapping: Option<&FieldMapping>,
+    show_progress: bool,
+    sampling: Sampling,
+) -> Result<ParseReport> {
+    let mut events = Vec::new();
+    let mut report = parse_files_streaming(
+        paths,
+        format,
+        mapping,
+        show_progress,
+        sampling,
+        &mut |event| events.push(event),
+    )?;
+    powershell::correlate_script_blocks(&mut events);
+    report.events = events;
+    Ok(report)
+}
+
+/// Like [`parse_files_sampled`], handing each event to `sink` as soon as it is parsed instead of
+/// collecting them in the report. Records present in more than one file are dropped on the way;
+/// script blocks are not attributed to their process (see
+/// [`powershell::ScriptBlockCorrelation`]).
+pub fn parse_files_streaming(
+    paths: &[PathBuf],
+    format: InputFormat,
+    mapping: Option<&FieldMapping>,
+    show_progress: bool,
+    sampling: Sampling,
+    sink: &mut EventSink,
+) -> Result<ParseReport> {
+    let inputs = archive::expand_inputs(paths, format)?;
+    let paths = &inputs.paths;
+    let mut sampler = Sampler::new(sampling);
+    let mut report = ParseReport::default();
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    let mut sink = |event: SysmonEvent| {
+        if paths.len() > 1 && !seen.insert(record_key(&event)) {
+            duplicates += 1;
+        } else {
+            sink(event);
+        }
+    };
+    for path in paths {
+        if sampler.full() {
+            report.truncated = true;
+            break;
+        }
+        let progress = if show_progress {
+            progress_bar(path)
+        } else {
+            ProgressBar::hidden()
+        };
+        let file_report = match format {
+            InputFormat::Evtx => parse_evtx_file_sampled(path, &progress, &mut sampler, &mut sink)?,
+            InputFormat::Jsonl => {
+                parse_jsonl_file_sampled(path, mapping, &progress, &mut sampler, &mut sink)?
+            }
+            InputFormat::Csv => {
+                let Some(mapping) = mapping else {
+                    bail!("CSV input needs a column mapping (--mapping FILE)");
+                };
+                parse_csv_file_sampled(path, mapping, &progress, &mut sampler, &mut sink)?
+            }
+        };
+        report.total_records += file_report.total_records;
+        report.read_errors += file_report.read_errors;
+        report.skipped_records += file_report.skipped_records;
+        report.sampled_out += file_report.sampled_out;
+        report.truncated |= file_report.truncated;
+    }
+    report.duplicates = duplicates;
+    if report.duplicates > 0 {
+        info!(
+            "Dropped {} duplicate records found in more than one file",
+            report.duplicates
         );
     }
-    Ok(events)
+    Ok(report)
+}
+/// Parse EVTX files in recovery mode (see [`parse_evtx_file_recovering`]), dropping records
+/// present in more than one file. Records that aren't Sysmon events are written to `unparsed_path`.
+pub fn parse_evtx_files_recovering(
+    paths: &[PathBuf],
+    show_progress: bool,
+    unparsed_path: Option<&Path>,
+) -> Result<ParseReport> {
+    let inputs = archive::expand_inputs(paths, InputFormat::Evtx)?;
+    let paths = &inputs.paths;
+    let mut unparsed = unparsed_path
+        .map(|path| {
+            File::create(path)
+                .map(BufWriter::new)
+                .with_context(|| format!("Failed to create {}", path.display()))
+        })
+        .transpose()?;
+    let mut report = ParseReport {
+        recovery: Some(RecoveryStats::default()),
+        ..ParseReport::default()
+    };
+    for path in paths {
+        let progress = if show_progress {
+            progress_bar(path)
+        } else {
+            ProgressBar::hidden()
+        };
+        let file_report = parse_evtx_file_recovering(
+            path,
+            &progress,
+            unparsed.as_mut().map(|out| out as &mut dyn Write),
+        )?;
+        report.events.extend(file_report.events);
+        report.total_records += file_report.total_records;
+        report.read_errors += file_report.read_errors;
+        report.skipped_records += file_report.skipped_records;
+        if let (Some(total), Some(stats)) = (report.recovery.as_mut(), file_report.recovery) {
+            total.add(&stats);
+        }
+    }
+    if let Some(out) = unparsed.as_mut() {
+        out.flush().context("Failed to write unparsed records")?;
+    }
+    if paths.len() > 1 {
+        report.duplicates = remove_duplicates(&mut report.events);
+    }
+    powershell::correlate_script_blocks(&mut report.events);
+    Ok(report)
+}
+/// Progress bar counting records, showing a spinner until the expected count is known
+fn progress_bar(path: &Path) -> ProgressBar {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    ProgressBar::no_length()
+        .with_style(
+            ProgressStyle::with_template(
+                "{spinner} {prefix} [{elapsed_precise}] {wide_bar} {human_pos}/{human_len} records ({per_sec}, ETA {eta})",
+            )
+            .expect("valid progress template"),
+        )
+        .with_prefix(name)
+}
+
+/// Next record identifier from the EVTX file header, `None` if the header can't be read
+fn header_next_record_id(path: &Path) -> Option<u64> {
+    let mut header = [0u8; 32];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[..8] != b"ElfFile\0" {
+        return None;
+    }
+    Some(u64::from_le_bytes(header[24..32].try_into().ok()?))
+}
+/// Keep the first occurrence of each (Computer, EventRecordID, Provider) record,
+/// returns the number of removed duplicates
+pub fn remove_duplicates(events: &mut Vec<SysmonEvent>) -> usize {
+    let before = events.len();
+    let mut seen = HashSet::with_capacity(events.len());
+    events.retain(|event| seen.insert(record_key(event)));
+    before - events.len()
+}
+
+/// Computer, EventRecordID and provider, identifying a record across files
+fn record_key(event: &SysmonEvent) -> (String, u32, String) {
+    let system = event.system();
+    (
+        system.computer.computer.clone(),
+        system.event_record_id.event_record_id,
+        system.provider.provider_name.clone(),
+    )
 }
 /// Parse Sysmon XML event
 pub fn parse_xml_event(xml: &str) -> anyhow::Result<SysmonEvent> {
@@ -112,6 +712,105 @@ mod tests {
         }
     }
     #[test]
+    fn duplicates_across_files_are_dropped() {
+        let event = parse_xml_event(get_test_xml()).unwrap();
+        let other_host = parse_xml_event(&get_test_xml().replace("TEST-PC<", "OTHER-PC<")).unwrap();
+        let mut events = vec![event.clone(), other_host, event];
+        assert_eq!(remove_duplicates(&mut events), 1);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].system().computer.computer, "OTHER-PC");
+    }
+    #[test]
+    fn sampling_and_event_limit() {
+        let lines: String = (1..=10)
+            .map(|record| format!("{{\"EventTime\":\"2025-01-01 10:00:00\",\"Hostname\":\"TEST-PC\",\"EventID\":22,\"RecordNumber\":{record},\"UtcTime\":\"2025-01-01 10:00:00.000\",\"ProcessGuid\":\"{{11111111-2222-3333-4444-555555555555}}\",\"ProcessId\":\"4242\",\"QueryName\":\"example.com\",\"Image\":\"C:\\\\Windows\\\\System32\\\\cmd.exe\"}}\n"))
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let paths = [dir.path().join("a.jsonl"), dir.path().join("b.jsonl")];
+        std::fs::write(&paths[0], &lines).unwrap();
+        std::fs::write(&paths[1], lines.replace("TEST-PC", "OTHER-PC")).unwrap();
+        let parse = |every, max_events| {
+            parse_files_sampled(
+                &paths,
+                InputFormat::Jsonl,
+                None,
+                false,
+                Sampling { every, max_events },
+            )
+            .unwrap()
+        };
+
+        let report = parse(3, None);
+        let records: Vec<_> = report
+            .events
+            .iter()
+            .map(|event| event.system().event_record_id.event_record_id)
+            .collect();
+        assert_eq!(records, [1, 4, 7, 10, 3, 6, 9]);
+        assert_eq!((report.total_records, report.sampled_out), (20, 13));
+        assert!(!report.truncated);
+
+        // The limit applies to all files together
+        let report = parse(1, Some(12));
+        assert_eq!(report.events.len(), 12);
+        assert!(report.truncated);
+        let report = parse(1, Some(20));
+        assert_eq!(report.events.len(), 20);
+        assert!(!report.truncated);
+    }
+    #[test]
+    fn record_count_from_file_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.evtx");
+        let mut header = b"ElfFile\0".to_vec();
+        header.extend(0u64.to_le_bytes());
+        header.extend(3u64.to_le_bytes());
+        header.extend(1501u64.to_le_bytes());
+        std::fs::write(&path, &header).unwrap();
+        assert_eq!(header_next_record_id(&path), Some(1501));
+        std::fs::write(&path, &header[8..]).unwrap();
+        assert_eq!(header_next_record_id(&path), None);
+    }
+    #[test]
+    fn damaged_chunks_are_skipped_in_recovery_mode() {
+        // An Application log with several chunks of MsiInstaller events
+        let original = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_files/Execution/rogue_msi_url_1040_1042.evtx");
+        let mut data = std::fs::read(&original).unwrap();
+        let intact = parse_evtx_files_recovering(std::slice::from_ref(&original), false, None)
+            .unwrap()
+            .recovery
+            .unwrap();
+        assert_eq!((intact.corrupt_chunks, intact.lost_records), (0, 0));
+
+        // Damage the string table offsets of the second chunk
+        const FILE_HEADER_SIZE: usize = 4096;
+        const CHUNK_SIZE: usize = 65536;
+        let strings = FILE_HEADER_SIZE + CHUNK_SIZE + 128;
+        data[strings..strings + 256].fill(0xff);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("damaged.evtx");
+        let unparsed_path = dir.path().join("unparsed.xml");
+        std::fs::write(&path, &data).unwrap();
+        let report =
+            parse_evtx_files_recovering(std::slice::from_ref(&path), false, Some(&unparsed_path))
+                .unwrap();
+        let stats = report.recovery.unwrap();
+        assert_eq!(stats.chunks, intact.chunks);
+        assert_eq!(stats.corrupt_chunks, 1);
+        assert!(stats.lost_records > 0);
+        assert_eq!(
+            stats.recovered_records + stats.lost_records,
+            intact.recovered_records
+        );
+        // None of the records are Sysmon events, so all of them are dumped
+        let unparsed = std::fs::read_to_string(&unparsed_path).unwrap();
+        assert_eq!(
+            unparsed.matches("<!-- file: ").count() as u64,
+            stats.recovered_records
+        );
+    }
+    #[test]
     fn test_parse_xml_event_invalid() {
         // Missing closing tag, malformed XML
         let bad_xml = "<System><EventID>1";
@@ -0,0 +1,208 @@
+//! Parsing, filtering and anomaly detection of `parse` as stages on their own threads, connected
+//! by channels, so detection and output overlap with reading the files
+use crate::analyzer::rule_stats::RuleProfile;
+use crate::analyzer::{Anomaly, DetectionConfig, IncrementalDetector};
+use crate::filters::EventFilter;
+use crate::mapping::FieldMapping;
+use crate::parser::{self, InputFormat, ParseReport, Sampling};
+use crate::powershell::ScriptBlockCorrelation;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+use std::thread;
+
+/// Events sent through a channel at once
+const BATCH_SIZE: usize = 1024;
+/// Batches waiting in a channel before the stage sending them blocks
+const CHANNEL_BATCHES: usize = 16;
+
+/// Where the events of a run come from
+pub enum Source<'a> {
+    /// Files parsed by the first stage
+    Files {
+        paths: &'a [PathBuf],
+        format: InputFormat,
+        mapping: Option<&'a FieldMapping>,
+        show_progress: bool,
+        sampling: Sampling,
+    },
+    /// Events already parsed (through the index or in recovery mode), script blocks attributed
+    Parsed(ParseReport),
+}
+
+pub struct PipelineConfig<'a> {
+    pub filter: &'a EventFilter,
+    /// Detect anomalies with this config, collecting a rule profile when the flag is set
+    pub detection: Option<(DetectionConfig, bool)>,
+    /// Keep the events left out by the filter in the report, e.g. for context around findings
+    pub keep_all: bool,
+}
+
+/// Outcome of a run
+pub struct PipelineOutput {
+    /// Parsing statistics, with the events left out by the filter when they were kept
+    pub report: ParseReport,
+    /// Events parsed, before filtering
+    pub total_events: usize,
+    /// Events matching the filter, in log order
+    pub filtered_events: Vec<SysmonEvent>,
+    pub anomalies: Vec<Anomaly>,
+    pub rule_profile: Option<RuleProfile>,
+}
+
+/// Parse, filter and analyze the events, calling `on_event` on the calling thread for each event
+/// matching the filter as soon as it is known. Script blocks of PowerShell logs come last, once
+/// every process they may belong to has been read.
+pub fn run_pipeline(
+    source: Source,
+    config: PipelineConfig,
+    mut on_event: impl FnMut(&SysmonEvent),
+) -> Result<PipelineOutput> {
+    let correlate = matches!(source, Source::Files { .. });
+    let (parsed_tx, parsed_rx) = sync_channel(CHANNEL_BATCHES);
+    let (filtered_tx, filtered_rx) = sync_channel(CHANNEL_BATCHES);
+    thread::scope(|scope| {
+        let parser = scope.spawn(move || parse_stage(source, parsed_tx));
+        let filter = config.filter;
+        let keep_all = config.keep_all;
+        let filter =
+            scope.spawn(move || filter_stage(parsed_rx, filtered_tx, filter, correlate, keep_all));
+
+        let mut detector = config
+            .detection
+            .as_ref()
+            .map(|(detection, profile)| IncrementalDetector::new(detection, *profile));
+        let mut events = Vec::new();
+        let mut positions = Vec::new();
+        for batch in filtered_rx {
+            for (position, event) in batch {
+                on_event(&event);
+                events.push(event);
+                positions.push(position);
+                if let Some(detector) = detector.as_mut() {
+                    detector.push(&events, events.len() - 1, position);
+                }
+            }
+        }
+        let (total_events, left_out) = filter
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e));
+        let mut report = parser
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))?;
+        report.events = left_out;
+
+        let (anomalies, rule_profile) = detector
+            .map(|detector| detector.finish(&events))
+            .unwrap_or_default();
+        // Script blocks held back by the filter stage go back to their place in the log
+        if !positions.is_sorted() {
+            let mut positioned: Vec<_> = positions.into_iter().zip(events).collect();
+            positioned.sort_unstable_by_key(|(position, _)| *position);
+            events = positioned.into_iter().map(|(_, event)| event).collect();
+        }
+        Ok(PipelineOutput {
+            report,
+            total_events,
+            filtered_events: events,
+            anomalies,
+            rule_profile,
+        })
+    })
+}
+
+/// Send the events in batches, then return the parsing statistics
+fn parse_stage(source: Source, tx: SyncSender<Vec<SysmonEvent>>) -> Result<ParseReport> {
+    // A send only fails when a later stage stopped, which surfaces when it is joined
+    match source {
+        Source::Files {
+            paths,
+            format,
+            mapping,
+            show_progress,
+            sampling,
+        } => {
+            let mut batch = Vec::with_capacity(BATCH_SIZE);
+            let report = parser::parse_files_streaming(
+                paths,
+                format,
+                mapping,
+                show_progress,
+                sampling,
+                &mut |event| {
+                    batch.push(event);
+                    if batch.len() == BATCH_SIZE {
+                        let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                        let _ = tx.send(full);
+                    }
+                },
+            )?;
+            if !batch.is_empty() {
+                let _ = tx.send(batch);
+            }
+            Ok(report)
+        }
+        Source::Parsed(mut report) => {
+            let mut events = std::mem::take(&mut report.events).into_iter();
+            loop {
+                let batch: Vec<_> = events.by_ref().take(BATCH_SIZE).collect();
+                if batch.is_empty() || tx.send(batch).is_err() {
+                    break;
+                }
+            }
+            Ok(report)
+        }
+    }
+}
+
+/// Attribute script blocks to their process and forward the events matching the filter with
+/// their position in the log. Returns the number of events and, when `keep_all` is set, the
+/// events left out by the filter.
+fn filter_stage(
+    rx: Receiver<Vec<SysmonEvent>>,
+    tx: SyncSender<Vec<(usize, SysmonEvent)>>,
+    filter: &EventFilter,
+    correlate: bool,
+    keep_all: bool,
+) -> (usize, Vec<SysmonEvent>) {
+    let mut correlation = ScriptBlockCorrelation::default();
+    let mut left_out = Vec::new();
+    // Script blocks and their position, until every process creation has been read
+    let mut blocks = Vec::new();
+    let mut position = 0;
+    for batch in rx {
+        let mut passed = Vec::with_capacity(batch.len());
+        for event in batch {
+            if correlate && matches!(event, SysmonEvent::ScriptBlock(_)) {
+                blocks.push((position, event));
+            } else {
+                if correlate {
+                    correlation.record(&event);
+                }
+                if filter.matches(&event) {
+                    passed.push((position, event));
+                } else if keep_all {
+                    left_out.push(event);
+                }
+            }
+            position += 1;
+        }
+        if !passed.is_empty() && tx.send(passed).is_err() {
+            return (position, left_out);
+        }
+    }
+    let mut passed = Vec::new();
+    for (block_position, mut event) in blocks {
+        correlation.attribute(&mut event);
+        if filter.matches(&event) {
+            passed.push((block_position, event));
+        } else if keep_all {
+            left_out.push(event);
+        }
+    }
+    if !passed.is_empty() {
+        let _ = tx.send(passed);
+    }
+    (position, left_out)
+}
@@ -0,0 +1,224 @@
+//! Mapping of PowerShell script block logging (Microsoft-Windows-PowerShell/Operational 4104)
+//! into the event model, and correlation of script blocks with their PowerShell process
+use crate::security;
+use crate::sysmon::{Event, Image, ProcessGuid, ScriptBlockEvent, ScriptBlockEventData};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+/// PowerShell 4104 (script block logged)
+pub fn script_block(xml: &str) -> Result<ScriptBlockEvent> {
+    let (system, mut m, fields) = security::parse(xml)?;
+    let process_id: u64 = system
+        .execution
+        .process_id
+        .parse()
+        .map_err(|e| anyhow!("Invalid Execution ProcessID: {e}"))?;
+    let part = |value: Option<String>| value.and_then(|n| n.parse().ok()).unwrap_or(1);
+    let event_data = ScriptBlockEventData {
+        utc_time: security::utc_time(&system),
+        process_guid: security::process_guid(process_id),
+        process_id,
+        image: Image {
+            image: String::new(),
+        },
+        script_block_id: m.required("ScriptBlockId")?,
+        message_number: part(m.take("MessageNumber")),
+        message_total: part(m.take("MessageTotal")),
+        text: m.required("ScriptBlockText")?,
+        path: m.take("Path"),
+    };
+    Ok(ScriptBlockEvent {
+        system,
+        event_data,
+        fields,
+    })
+}
+
+/// Parts of script blocks split over several events, kept until the last one arrives
+#[derive(Debug)]
+pub struct ScriptBlockParts<T> {
+    /// Texts by message number and value of the latest part, by lowercase computer and script
+    /// block ID
+    blocks: HashMap<(String, String), (BTreeMap<u32, String>, T)>,
+}
+
+impl<T> Default for ScriptBlockParts<T> {
+    fn default() -> Self {
+        Self {
+            blocks: HashMap::new(),
+        }
+    }
+}
+
+impl<T> ScriptBlockParts<T> {
+    /// Add a part, keeping `value` of the latest one. Returns the text of the whole block once
+    /// this part completes it, `None` while parts are missing. Blocks logged in a single event
+    /// are returned as they are.
+    pub fn add(&mut self, event: &ScriptBlockEvent, value: impl FnOnce() -> T) -> Option<String> {
+        let data = &event.event_data;
+        if data.message_total <= 1 {
+            return Some(data.text.clone());
+        }
+        let key = (
+            event.system.computer.computer.to_lowercase(),
+            data.script_block_id.to_lowercase(),
+        );
+        match self.blocks.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let (parts, latest) = entry.get_mut();
+                parts.insert(data.message_number, data.text.clone());
+                if parts.len() < data.message_total as usize {
+                    *latest = value();
+                    return None;
+                }
+                let (parts, _) = entry.remove();
+                Some(parts.into_values().collect())
+            }
+            Entry::Vacant(entry) => {
+                let parts = BTreeMap::from([(data.message_number, data.text.clone())]);
+                entry.insert((parts, value()));
+                None
+            }
+        }
+    }
+
+    /// Blocks still missing parts, with the text of the parts seen in order
+    pub fn into_incomplete(self) -> impl Iterator<Item = (String, T)> {
+        self.blocks
+            .into_values()
+            .map(|(parts, latest)| (parts.into_values().collect(), latest))
+    }
+}
+
+type ProcessStart = (Option<DateTime<Utc>>, ProcessGuid, Image);
+
+/// Process creations seen so far, to attribute script blocks read in the same pass
+#[derive(Debug, Default)]
+pub struct ScriptBlockCorrelation {
+    /// Creation time, GUID and image of the processes by lowercase computer and process ID
+    processes: HashMap<(String, u64), Vec<ProcessStart>>,
+}
+
+impl ScriptBlockCorrelation {
+    /// Remember a process creation, other events are ignored
+    pub fn record(&mut self, event: &Event) {
+        if let Event::ProcessCreate(e) = event {
+            self.processes
+                .entry((
+                    e.system.computer.computer.to_lowercase(),
+                    e.event_data.process_id,
+                ))
+                .or_default()
+                .push((
+                    e.system.time_created.time,
+                    e.event_data.process_guid.clone(),
+                    e.event_data.image.clone(),
+                ));
+        }
+    }
+
+    /// Attribute a script block to the latest process recorded with its ID on its computer and
+    /// created before it was logged. Other events are left as they are.
+    pub fn attribute(&self, event: &mut Event) {
+        let Event::ScriptBlock(e) = event else {
+            return;
+        };
+        let key = (
+            e.system.computer.computer.to_lowercase(),
+            e.event_data.process_id,
+        );
+        let block_time = e.system.time_created.time;
+        let creation = self.processes.get(&key).and_then(|candidates| {
+            candidates
+                .iter()
+                .filter(|(created, _, _)| match (created, block_time) {
+                    (Some(created), Some(block)) => created <= &block,
+                    _ => true,
+                })
+                .max_by_key(|(created, _, _)| *created)
+        });
+        if let Some((_, guid, image)) = creation {
+            e.event_data.process_guid = guid.clone();
+            e.event_data.image = image.clone();
+        }
+    }
+}
+
+/// Attribute script blocks to the PowerShell process that ran them: the latest process created
+/// with the same ID on the same computer before the block was logged. Script blocks without
+/// such a process keep an empty image.
+pub fn correlate_script_blocks(events: &mut [Event]) {
+    let mut correlation = ScriptBlockCorrelation::default();
+    for event in events.iter() {
+        correlation.record(event);
+    }
+    if correlation.processes.is_empty() {
+        return;
+    }
+    for event in events.iter_mut() {
+        correlation.attribute(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_event;
+    use crate::helpers::HasProcess;
+    use crate::jsonl::parse_json_event;
+    use serde_json::json;
+
+    fn script_block_json(time: &str, pid: u32, text: &str) -> String {
+        let text = text.replace('\\', "\\\\").replace('"', "\\\"");
+        format!(
+            r#"{{"EventTime":"2025-01-01 {time}","Hostname":"TEST-PC","SourceName":"Microsoft-Windows-PowerShell","EventID":4104,"RecordNumber":7,"ExecutionProcessID":{pid},"ExecutionThreadID":1,"MessageNumber":"1","MessageTotal":"1","ScriptBlockText":"{text}","ScriptBlockId":"9c2a1a4e-0e6f-4f0a-9a37-1c7b0e1f4d2b"}}"#
+        )
+    }
+
+    fn powershell(time: &str, pid: u32, instance: u32) -> Event {
+        json_process_event(json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "TEST-PC",
+            "RecordNumber": pid,
+            "ProcessGuid": format!("{{11111111-2222-3333-4444-55555555{instance:04}}}"),
+            "ProcessId": pid.to_string(),
+            "Image": r"C:\Windows\System32\WindowsPowerShell\v1.0\powershell.exe",
+            "CommandLine": "powershell.exe -nop",
+        }))
+    }
+
+    #[test]
+    fn script_blocks_attributed_to_their_process() {
+        let block = parse_json_event(&script_block_json("10:00:05", 4242, "Get-Process")).unwrap();
+        let Event::ScriptBlock(data) = &block else {
+            panic!("expected a script block");
+        };
+        assert_eq!(data.event_data.text, "Get-Process");
+        assert_eq!(data.event_data.process_id, 4242);
+        let other = script_block_json("10:00:05", 4242, "Get-Process")
+            .replace("Microsoft-Windows-PowerShell", "Microsoft-Windows-WinRM");
+        assert!(parse_json_event(&other).is_err());
+        assert_eq!(
+            (
+                data.event_data.message_number,
+                data.event_data.message_total
+            ),
+            (1, 1)
+        );
+
+        // The process ID was reused, the block belongs to the process running when it was logged
+        let mut events = vec![
+            powershell("09:00:00", 4242, 1),
+            powershell("10:00:00", 4242, 2),
+            block,
+            parse_json_event(&script_block_json("10:00:06", 99, "Get-Date")).unwrap(),
+        ];
+        correlate_script_blocks(&mut events);
+        assert_eq!(events[2].process_guid(), events[1].process_guid());
+        assert_ne!(events[2].process_guid(), events[0].process_guid());
+        assert_eq!(events[2].process_name(), "powershell.exe");
+        assert_eq!(events[3].image(), "");
+    }
+}
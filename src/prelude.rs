@@ -0,0 +1,27 @@
+//! Commonly used types and functions for embedding the analyzer in other tools.
+//!
+//! ```no_run
+//! use security_log_analyser::prelude::*;
+//!
+//! let events = parse_evtx_file(std::path::Path::new("Sysmon.evtx"))?;
+//! let filtered = EventFilter::new().with_event_ids(Some(vec![1])).apply(&events);
+//! for anomaly in detect_anomalies(&filtered) {
+//!     println!("{}: {}", anomaly.severity(), anomaly.description());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+pub use crate::analyzer::detector::{CustomRule, Detector, DetectorRegistry};
+pub use crate::analyzer::{
+    AggregatedAnomaly, Anomaly, AnomalyKind, DetectionConfig, Severity, aggregate_anomalies,
+    detect_anomalies, detect_anomalies_live, detect_anomalies_live_with_config,
+    detect_anomalies_profiled, detect_anomalies_streaming, detect_anomalies_with_config,
+};
+pub use crate::filters::{EventFilter, SearchMode};
+pub use crate::helpers::HasSystem;
+pub use crate::parser::{
+    InputFormat, ParseReport, parse_evtx_file, parse_evtx_file_with_report, parse_evtx_files,
+    parse_files, parse_xml_event,
+};
+pub use crate::sysmon::{
+    Event as SysmonEvent, FileCreateEvent, NetworkEvent, ProcessCreateEvent, System,
+};
@@ -0,0 +1,319 @@
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use uuid::Uuid;
+
+/// How the process of interest is identified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessSelector {
+    Pid(u64),
+    Guid(Uuid),
+    /// Full image path or file name, case-insensitive
+    Image(String),
+}
+
+impl ProcessSelector {
+    fn matches(&self, event: &SysmonEvent) -> bool {
+        match self {
+            ProcessSelector::Pid(pid) => event.process_id() == *pid,
+            ProcessSelector::Guid(guid) => event.process_guid() == *guid,
+            ProcessSelector::Image(image) => {
+                event.image().eq_ignore_ascii_case(image)
+                    || event.process_name().eq_ignore_ascii_case(image)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ProcessSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessSelector::Pid(pid) => write!(f, "PID {pid}"),
+            ProcessSelector::Guid(guid) => write!(f, "process {{{guid}}}"),
+            ProcessSelector::Image(image) => write!(f, "image {image}"),
+        }
+    }
+}
+
+/// A process instance covered by the profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfiledProcess {
+    pub guid: Uuid,
+    pub pid: u64,
+    pub image: String,
+    pub command_line: Option<String>,
+    pub parent_image: Option<String>,
+    pub started: Option<DateTime<Utc>>,
+    /// Time of the termination event
+    pub ended: Option<DateTime<Utc>>,
+}
+
+impl ProfiledProcess {
+    /// Time the process ran, when its creation and termination are both in the log
+    pub fn lifetime(&self) -> Option<chrono::Duration> {
+        Some(self.ended? - self.started?)
+    }
+}
+
+/// Child process started by one of the profiled processes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildProcess {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub pid: u64,
+    pub image: String,
+    pub command_line: String,
+}
+
+/// Everything the selected processes did across the log
+#[derive(Debug, Clone, Default)]
+pub struct ProcessProfile {
+    pub processes: Vec<ProfiledProcess>,
+    pub children: Vec<ChildProcess>,
+    pub files_created: BTreeSet<String>,
+    pub files_deleted: BTreeSet<String>,
+    /// Destination (`ip:port`, with host name when known) to connection count
+    pub network_destinations: BTreeMap<String, usize>,
+    pub pipes: BTreeSet<String>,
+    pub dns_queries: BTreeSet<String>,
+    /// Registry values set (key followed by the value name) to the data last written
+    pub registry_values: BTreeMap<String, String>,
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub event_count: usize,
+}
+
+impl ProcessProfile {
+    pub fn is_empty(&self) -> bool {
+        self.event_count == 0
+    }
+}
+
+/// Aggregate the activity of the processes matching the selector,
+/// optionally including all of their descendants
+pub fn build_profile(
+    events: &[SysmonEvent],
+    selector: &ProcessSelector,
+    include_children: bool,
+) -> ProcessProfile {
+    let mut guids: HashSet<Uuid> = events
+        .iter()
+        .filter(|event| selector.matches(event))
+        .map(|event| event.process_guid())
+        .collect();
+    if include_children {
+        // Repeat until no new descendants are found, events may be out of order
+        loop {
+            let before = guids.len();
+            for event in events {
+                if let SysmonEvent::ProcessCreate(e) = event
+                    && guids.contains(&e.event_data.parent_process_guid.process_guid)
+                {
+                    guids.insert(e.event_data.process_guid.process_guid);
+                }
+            }
+            if guids.len() == before {
+                break;
+            }
+        }
+    }
+
+    let mut profile = ProcessProfile::default();
+    let mut seen_processes: HashSet<Uuid> = HashSet::new();
+    let mut sorted: Vec<&SysmonEvent> = events.iter().collect();
+    sorted.sort_by_key(|event| event.system().time_created.time);
+    for event in sorted {
+        if let SysmonEvent::ProcessCreate(e) = event
+            && guids.contains(&e.event_data.parent_process_guid.process_guid)
+        {
+            let data = &e.event_data;
+            profile.children.push(ChildProcess {
+                timestamp: e.system.time_created.time,
+                pid: data.process_id,
+                image: data.image.image.clone(),
+                command_line: data.command_line.command_line.clone(),
+            });
+        }
+        let guid = event.process_guid();
+        if !guids.contains(&guid) {
+            continue;
+        }
+        let timestamp = event.system().time_created.time;
+        profile.event_count += 1;
+        profile.first_seen = profile.first_seen.or(timestamp);
+        profile.last_seen = timestamp.or(profile.last_seen);
+        if seen_processes.insert(guid) {
+            profile.processes.push(ProfiledProcess {
+                guid,
+                pid: event.process_id(),
+                image: event.image().to_string(),
+                command_line: None,
+                parent_image: None,
+                started: None,
+                ended: None,
+            });
+        }
+
+        match event {
+            SysmonEvent::ProcessCreate(e) => {
+                let data = &e.event_data;
+                if let Some(process) = profile.processes.iter_mut().find(|p| p.guid == guid) {
+                    process.command_line = Some(data.command_line.command_line.clone());
+                    process.parent_image = Some(data.parent_image.image.clone());
+                    process.started = timestamp;
+                }
+            }
+            SysmonEvent::ProcessTerminate(_) => {
+                if let Some(process) = profile.processes.iter_mut().find(|p| p.guid == guid) {
+                    process.ended = timestamp;
+                }
+            }
+            SysmonEvent::FileCreate(e) => {
+                profile
+                    .files_created
+                    .insert(e.event_data.target_filename.clone());
+            }
+            SysmonEvent::FileStreamCreate(e) => {
+                profile
+                    .files_created
+                    .insert(e.event_data.target_filename.clone());
+            }
+            // Sysmon logs event 29 when the process writes an executable
+            SysmonEvent::FileExecutableDetected(e) => {
+                profile
+                    .files_created
+                    .insert(e.event_data.target_filename.clone());
+            }
+            SysmonEvent::FileDelete(e) | SysmonEvent::FileBlock(e) => {
+                profile
+                    .files_deleted
+                    .insert(e.event_data.target_filename.clone());
+            }
+            SysmonEvent::OutboundNetwork(e) => {
+                let data = &e.event_data;
+                let destination = match &data.destination_hostname {
+                    Some(host) if !host.is_empty() && host != "-" => {
+                        format!("{}:{} ({host})", data.destination_ip, data.destination_port)
+                    }
+                    _ => format!("{}:{}", data.destination_ip, data.destination_port),
+                };
+                *profile.network_destinations.entry(destination).or_default() += 1;
+            }
+            SysmonEvent::DnsQuery(e) => {
+                profile.dns_queries.insert(e.event_data.query_name.clone());
+            }
+            SysmonEvent::RegistrySetValue(e) => {
+                profile.registry_values.insert(
+                    e.event_data.target_object.clone(),
+                    e.event_data.details.clone(),
+                );
+            }
+            SysmonEvent::InboundNetwork(_)
+            | SysmonEvent::ProcessAccess(_)
+            | SysmonEvent::ServiceStateChange(_)
+            | SysmonEvent::ConfigurationChange(_)
+            | SysmonEvent::DriverLoad(_)
+            | SysmonEvent::RawAccessRead(_)
+            | SysmonEvent::ClipboardChange(_)
+            | SysmonEvent::ProcessTampering(_)
+            | SysmonEvent::WmiFilter(_)
+            | SysmonEvent::WmiConsumer(_)
+            | SysmonEvent::WmiBinding(_)
+            | SysmonEvent::Logon(_)
+            | SysmonEvent::ScriptBlock(_) => {}
+            SysmonEvent::PipeCreated(e) | SysmonEvent::PipeConnected(e) => {
+                profile.pipes.insert(e.event_data.pipe_name.clone());
+            }
+        }
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{ProcessCreate, file_event, sysmon_event, terminate_event};
+
+    #[test]
+    fn profile_with_children() {
+        let explorer = "11111111-0000-0000-0000-000000000001";
+        let word = "11111111-0000-0000-0000-000000000002";
+        let cmd = "11111111-0000-0000-0000-000000000003";
+        // Executable written by the grandchild (Sysmon event 29)
+        let dropped = sysmon_event(
+            29,
+            5,
+            "2025-01-01T10:00:04.000Z",
+            &format!(
+                r#"<Data Name="RuleName">-</Data>
+        <Data Name="ProcessGuid">{{{cmd}}}</Data>
+        <Data Name="ProcessId">1000</Data>
+        <Data Name="User">TEST-PC\Bob</Data>
+        <Data Name="Image">C:\Windows\System32\cmd.exe</Data>
+        <Data Name="TargetFilename">C:\Temp\dropper.exe</Data>
+        <Data Name="Hashes">SHA256=ABCDEF</Data>"#
+            ),
+        );
+        // The grandchild's file event comes before its process creation
+        let events = [
+            file_event(
+                "2025-01-01T10:00:03.000Z",
+                cmd,
+                r"C:\Windows\System32\cmd.exe",
+                r"C:\Temp\payload.exe",
+            ),
+            ProcessCreate {
+                time: "2025-01-01T10:00:01.000Z",
+                guid: word,
+                parent_guid: explorer,
+                image: r"C:\Office\WINWORD.EXE",
+                ..ProcessCreate::default()
+            }
+            .event(),
+            ProcessCreate {
+                time: "2025-01-01T10:00:02.000Z",
+                guid: cmd,
+                parent_guid: word,
+                ..ProcessCreate::default()
+            }
+            .event(),
+            dropped,
+            crate::jsonl::parse_json_event(&format!(r#"{{"EventTime":"2025-01-01 10:00:04","Hostname":"TEST-PC","EventID":13,"RecordNumber":46,"EventType":"SetValue","UtcTime":"2025-01-01 10:00:04.000","ProcessGuid":"{{{cmd}}}","ProcessId":"1000","Image":"C:\\Windows\\System32\\cmd.exe","TargetObject":"HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\\Dropper","Details":"C:\\Temp\\dropper.exe"}}"#)).unwrap(),
+            terminate_event(
+                "2025-01-01T10:00:05.000Z",
+                cmd,
+                r"C:\Windows\System32\cmd.exe",
+            ),
+        ];
+        let selector = ProcessSelector::Image("winword.exe".to_string());
+
+        let own = build_profile(&events, &selector, false);
+        assert_eq!(own.processes.len(), 1);
+        assert_eq!(own.children.len(), 1);
+        assert_eq!(own.children[0].image, r"C:\Windows\System32\cmd.exe");
+        assert!(own.files_created.is_empty());
+
+        let tree = build_profile(&events, &selector, true);
+        assert_eq!(tree.processes.len(), 2);
+        assert_eq!(tree.event_count, 6);
+        assert_eq!(tree.processes[0].lifetime(), None);
+        assert_eq!(
+            tree.processes[1].lifetime(),
+            Some(chrono::Duration::seconds(3))
+        );
+        assert!(tree.files_created.contains(r"C:\Temp\payload.exe"));
+        assert!(tree.files_created.contains(r"C:\Temp\dropper.exe"));
+        assert!(tree.files_deleted.is_empty());
+        assert_eq!(
+            tree.registry_values
+                .get(r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run\Dropper")
+                .map(String::as_str),
+            Some(r"C:\Temp\dropper.exe")
+        );
+        assert_eq!(tree.first_seen, "2025-01-01T10:00:01Z".parse().ok());
+
+        let missing = build_profile(&events, &ProcessSelector::Pid(7), true);
+        assert!(missing.is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+//! SARIF 2.1.0 log of detected anomalies, for viewers and platforms that ingest static analysis
+//! results (code scanning dashboards, SOAR platforms)
+use crate::analyzer::{Anomaly, Severity};
+use crate::helpers::HasSystem;
+use crate::sysmon::format_event_time;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF level of a severity
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "note",
+    }
+}
+
+/// Score out of 10 that code scanning viewers rank results by (`security-severity`)
+fn security_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "9.5",
+        Severity::High => "8.0",
+        Severity::Medium => "5.5",
+        Severity::Low => "3.0",
+    }
+}
+
+fn rule(anomaly: &Anomaly) -> Value {
+    let tags: Vec<String> = ["security".to_string()]
+        .into_iter()
+        .chain(anomaly.attack_techniques().iter().map(|t| t.to_string()))
+        .chain(anomaly.tactics().iter().map(|t| t.to_string()))
+        .collect();
+    json!({
+        "id": anomaly.rule_id(),
+        "name": anomaly.rule_name(),
+        "shortDescription": { "text": anomaly.rule_name() },
+        "defaultConfiguration": { "level": level(anomaly.default_severity()) },
+        "properties": {
+            "tags": tags,
+            "security-severity": security_severity(anomaly.default_severity()),
+        },
+    })
+}
+
+/// URI of an input file: `file://` for absolute paths, a relative reference otherwise, with
+/// forward slashes and reserved characters percent-encoded
+fn artifact_uri(file: &str) -> String {
+    let path = file.replace('\\', "/");
+    let (drive, rest) = match path.as_bytes() {
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => path.split_at(2),
+        _ => ("", path.as_str()),
+    };
+    let mut encoded = drive.to_string();
+    for byte in rest.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    if !drive.is_empty() {
+        format!("file:///{encoded}")
+    } else if encoded.starts_with("//") {
+        // UNC path, \\server\share
+        format!("file:{encoded}")
+    } else if encoded.starts_with('/') {
+        format!("file://{encoded}")
+    } else {
+        encoded
+    }
+}
+
+/// Build a SARIF log with one rule per detection rule that fired and one result per anomaly.
+/// Results are located by the computer, channel and record ID of their event, and by the input
+/// file when `files` holds a single one (events do not record which of several files they came
+/// from).
+pub fn sarif_log(anomalies: &[Anomaly], files: &[String]) -> Value {
+    let mut rules = BTreeMap::new();
+    for anomaly in anomalies {
+        rules
+            .entry(anomaly.rule_id())
+            .or_insert_with(|| rule(anomaly));
+    }
+    let rule_index: BTreeMap<&str, usize> = rules
+        .keys()
+        .enumerate()
+        .map(|(index, id)| (*id, index))
+        .collect();
+    let results: Vec<Value> = anomalies
+        .iter()
+        .map(|anomaly| {
+            let mut result = json!({
+                "ruleId": anomaly.rule_id(),
+                "ruleIndex": rule_index[anomaly.rule_id()],
+                "level": level(anomaly.severity()),
+                "message": { "text": anomaly.description() },
+                "partialFingerprints": { "dedupKey/v1": anomaly.dedup_key() },
+                "properties": {
+                    "severity": anomaly.severity().to_string(),
+                    "timestamp": anomaly.timestamp().map(format_event_time),
+                },
+            });
+            let mut location = json!({});
+            if let [file] = files {
+                location["physicalLocation"] = json!({
+                    "artifactLocation": { "uri": artifact_uri(file), "index": 0 },
+                });
+            }
+            if let Some(event) = anomaly.event() {
+                let system = event.system();
+                let record_id = system.event_record_id.event_record_id;
+                location["logicalLocations"] = json!([{
+                    "name": format!("EventRecordID {record_id}"),
+                    "fullyQualifiedName": format!(
+                        "{}/{}/{record_id}",
+                        system.computer.computer, system.channel.value
+                    ),
+                    "kind": "object",
+                }]);
+                result["properties"]["computer"] = json!(system.computer.computer);
+                result["properties"]["recordId"] = json!(record_id);
+            }
+            if location.as_object().is_some_and(|l| !l.is_empty()) {
+                result["locations"] = json!([location]);
+            }
+            result
+        })
+        .collect();
+    let artifacts: Vec<Value> = files
+        .iter()
+        .map(|file| json!({ "location": { "uri": artifact_uri(file) } }))
+        .collect();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.into_values().collect::<Vec<_>>(),
+                }
+            },
+            "artifacts": artifacts,
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::severity::SeverityOverrides;
+    use crate::analyzer::{DetectionConfig, detect_anomalies, detect_anomalies_with_config};
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn rules_results_and_locations() {
+        let events = [json_process_event(json!({}))];
+        let anomalies = detect_anomalies(&events);
+        let log = sarif_log(&anomalies, &["logs\\ws 1.jsonl".to_string()]);
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "SLA-002");
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "SLA-002");
+        assert_eq!(result["ruleIndex"], 0);
+        assert_eq!(result["level"], "error");
+        let location = &result["locations"][0];
+        assert_eq!(
+            location["physicalLocation"]["artifactLocation"]["uri"],
+            "logs/ws%201.jsonl"
+        );
+        assert_eq!(location["logicalLocations"][0]["name"], "EventRecordID 17");
+
+        // Several input files: only the logical location is known
+        let log = sarif_log(&anomalies, &["a.evtx".to_string(), "b.evtx".to_string()]);
+        let location = &log["runs"][0]["results"][0]["locations"][0];
+        assert!(location.get("physicalLocation").is_none());
+        assert_eq!(log["runs"][0]["artifacts"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            artifact_uri(r"C:\Logs\a#1.evtx"),
+            "file:///C:/Logs/a%231.evtx"
+        );
+        assert_eq!(artifact_uri("/var/log/a.evtx"), "file:///var/log/a.evtx");
+        assert_eq!(artifact_uri(r"\\nas\logs\a.evtx"), "file://nas/logs/a.evtx");
+
+        // The rule keeps its own severity when the config lowers the one of its results
+        let config = DetectionConfig {
+            severity_overrides: SeverityOverrides::new([("SLA-002".to_string(), Severity::Low)]),
+            ..DetectionConfig::default()
+        };
+        let anomalies = detect_anomalies_with_config(&events, &config);
+        let run = &sarif_log(&anomalies, &[])["runs"][0];
+        let rule = &run["tool"]["driver"]["rules"][0];
+        assert_eq!(rule["defaultConfiguration"]["level"], "error");
+        assert_eq!(rule["properties"]["security-severity"], "8.0");
+        assert_eq!(run["results"][0]["level"], "note");
+    }
+}
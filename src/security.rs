@@ -0,0 +1,390 @@
+//! Mapping of Windows Security channel events (4624, 4688, 5156) into the Sysmon event model
+use crate::sysmon::{
+    CommandLine, CurrentDirectory, Event, EventFields, Hashes, Image, IntegrityLevel,
+    IntermediaryEventData, LogonEvent, LogonEventData, LogonGuid, LogonId, NetworkEvent,
+    NetworkEventData, ProcessCreateEvent, ProcessCreateEventData, ProcessGuid, System,
+    TerminalSessionId, User, UtcTime,
+};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// High half of the GUIDs derived for Security events ("SECURITY" in ASCII)
+const SECURITY_GUID_PREFIX: u64 = 0x5345_4355_5249_5459;
+/// Windows Filtering Platform direction values
+const DIRECTION_INBOUND: &str = "%%14592";
+const DIRECTION_OUTBOUND: &str = "%%14593";
+
+#[derive(Debug, Deserialize)]
+struct SecurityEvent {
+    #[serde(rename = "System")]
+    system: System,
+    #[serde(rename = "EventData")]
+    event_data: IntermediaryEventData,
+}
+
+/// Event data fields, without the `-` placeholders Windows uses for empty values
+pub(crate) struct Fields(HashMap<String, String>);
+
+impl Fields {
+    fn new(data: IntermediaryEventData) -> Self {
+        Self(
+            data.data
+                .into_iter()
+                .filter_map(|data| Some((data.name, data.value?)))
+                .filter(|(_, value)| !value.is_empty() && value != "-")
+                .collect(),
+        )
+    }
+    pub(crate) fn take(&mut self, name: &str) -> Option<String> {
+        self.0.remove(name)
+    }
+    pub(crate) fn required(&mut self, name: &str) -> Result<String> {
+        self.take(name).ok_or_else(|| anyhow!("No field: {name}"))
+    }
+    /// Process and logon IDs are hexadecimal (`0x1a2c`) in most Security events
+    fn number(&mut self, name: &str) -> Result<u64> {
+        let value = self.required(name)?;
+        let parsed = match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => value.parse(),
+        };
+        parsed.map_err(|e| anyhow!("Invalid {name} '{value}': {e}"))
+    }
+    /// `DOMAIN\user` from a domain/user field pair
+    fn account(&mut self, domain: &str, user: &str) -> Option<String> {
+        let user = self.take(user)?;
+        Some(match self.take(domain) {
+            Some(domain) => format!("{domain}\\{user}"),
+            None => user,
+        })
+    }
+}
+
+pub(crate) fn parse(xml: &str) -> Result<(System, Fields, EventFields)> {
+    let event: SecurityEvent =
+        serde_xml_rs::from_str(xml).map_err(|e| anyhow!("Malformed Security event: {e}"))?;
+    let fields = EventFields::from(&event.event_data);
+    Ok((event.system, Fields::new(event.event_data), fields))
+}
+
+/// Security events carry no process GUIDs, so one is derived from the process ID.
+/// Process IDs are reused by Windows, so the GUID is only unique within a short time span.
+pub fn process_guid(process_id: u64) -> ProcessGuid {
+    ProcessGuid {
+        process_guid: Uuid::from_u64_pair(SECURITY_GUID_PREFIX, process_id),
+    }
+}
+
+/// Sysmon style `UtcTime` (`2025-01-01 10:00:00.000`) from the event creation time
+pub(crate) fn utc_time(system: &System) -> UtcTime {
+    let time = &system.time_created;
+    UtcTime {
+        utc_time: time
+            .time
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+            .unwrap_or_else(|| time.system_time.clone()),
+    }
+}
+
+/// Integrity level name from a mandatory label SID (e.g. `S-1-16-12288`)
+fn integrity_level(label: Option<&str>) -> String {
+    match label {
+        Some("S-1-16-0") => "Untrusted",
+        Some("S-1-16-4096") => "Low",
+        Some("S-1-16-8192") => "Medium",
+        Some("S-1-16-8448") => "MediumPlus",
+        Some("S-1-16-12288") => "High",
+        Some("S-1-16-16384") => "System",
+        _ => "",
+    }
+    .to_string()
+}
+
+/// Security 4688 (process creation) as a Sysmon process create event
+pub fn process_create(xml: &str) -> Result<ProcessCreateEvent> {
+    let (system, mut m, fields) = parse(xml)?;
+    let process_id = m.number("NewProcessId")?;
+    let parent_process_id = m.number("ProcessId")?;
+    // The target account is only set when the process runs as a different user
+    let user = m
+        .account("TargetDomainName", "TargetUserName")
+        .or_else(|| m.account("SubjectDomainName", "SubjectUserName"))
+        .unwrap_or_default();
+    let logon_id = m
+        .take("TargetLogonId")
+        .filter(|id| id != "0x0")
+        .or_else(|| m.take("SubjectLogonId"))
+        .unwrap_or_default();
+    let event_data = ProcessCreateEventData {
+        utc_time: utc_time(&system),
+        process_guid: process_guid(process_id),
+        process_id,
+        image: Image {
+            image: m.required("NewProcessName")?,
+        },
+        command_line: CommandLine {
+            command_line: m.take("CommandLine").unwrap_or_default(),
+        },
+        current_directory: CurrentDirectory {
+            current_directory: String::new(),
+        },
+        user: User { user },
+        logon_guid: LogonGuid {
+            logon_guid: Uuid::nil(),
+        },
+        logon_id: LogonId { logon_id },
+        terminal_session_id: TerminalSessionId {
+            terminal_session_id: String::new(),
+        },
+        integrity_level: IntegrityLevel {
+            integrity_level: integrity_level(m.take("MandatoryLabel").as_deref()),
+        },
+        hashes: Hashes {
+            hashes: String::new(),
+        },
+        parent_process_guid: process_guid(parent_process_id),
+        parent_process_id,
+        parent_image: Image {
+            image: m.take("ParentProcessName").unwrap_or_default(),
+        },
+        parent_command_line: CommandLine {
+            command_line: String::new(),
+        },
+        signed: None,
+        signature: None,
+        signature_status: None,
+    };
+    Ok(ProcessCreateEvent {
+        system,
+        event_data,
+        fields,
+    })
+}
+
+/// Security 4624 (successful logon)
+pub fn logon(xml: &str) -> Result<LogonEvent> {
+    let (system, mut m, fields) = parse(xml)?;
+    let process_id = m.number("ProcessId").unwrap_or_default();
+    let event_data = LogonEventData {
+        utc_time: utc_time(&system),
+        process_guid: process_guid(process_id),
+        process_id,
+        image: Image {
+            image: m.take("ProcessName").unwrap_or_default(),
+        },
+        target_user: User {
+            user: m
+                .account("TargetDomainName", "TargetUserName")
+                .unwrap_or_default(),
+        },
+        target_user_sid: m.take("TargetUserSid").unwrap_or_default(),
+        target_logon_id: LogonId {
+            logon_id: m.required("TargetLogonId")?,
+        },
+        logon_type: m.required("LogonType")?.parse()?,
+        logon_process: m.take("LogonProcessName").map(|p| p.trim().to_string()),
+        authentication_package: m.take("AuthenticationPackageName"),
+        workstation: m.take("WorkstationName"),
+        ip_address: m.take("IpAddress"),
+        ip_port: m
+            .take("IpPort")
+            .and_then(|port| port.parse().ok())
+            .filter(|&port| port != 0),
+    };
+    Ok(LogonEvent {
+        system,
+        event_data,
+        fields,
+    })
+}
+
+/// Security 5156 (connection permitted by the filtering platform) as a Sysmon network event
+pub fn network_connection(xml: &str) -> Result<Event> {
+    let (system, mut m, fields) = parse(xml)?;
+    let process_id = m.number("ProcessID")?;
+    let initiated = match m.required("Direction")?.as_str() {
+        DIRECTION_OUTBOUND => true,
+        DIRECTION_INBOUND => false,
+        direction => return Err(anyhow!("Unknown connection direction '{direction}'")),
+    };
+    let protocol = match m.take("Protocol").as_deref() {
+        Some("6") => "tcp".to_string(),
+        Some("17") => "udp".to_string(),
+        Some("1") => "icmp".to_string(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let local_ip = m.required("SourceAddress")?;
+    let local_port: u16 = m.required("SourcePort")?.parse()?;
+    let remote_ip = m.required("DestAddress")?;
+    let remote_port: u16 = m.required("DestPort")?.parse()?;
+    // The filtering platform reports the local side as the source, while Sysmon
+    // reports the side that initiated the connection
+    let ((source_ip, source_port), (destination_ip, destination_port)) = if initiated {
+        ((local_ip, local_port), (remote_ip, remote_port))
+    } else {
+        ((remote_ip, remote_port), (local_ip, local_port))
+    };
+    let event_data = NetworkEventData {
+        utc_time: utc_time(&system),
+        process_guid: process_guid(process_id),
+        process_id,
+        image: Image {
+            image: m.take("Application").unwrap_or_default(),
+        },
+        user: None,
+        protocol,
+        initiated,
+        source_is_ipv6: source_ip.contains(':').to_string(),
+        source_ip,
+        source_hostname: None,
+        source_port,
+        source_port_name: None,
+        destination_is_ipv6: destination_ip.contains(':').to_string(),
+        destination_ip,
+        destination_hostname: None,
+        destination_port,
+        destination_port_name: None,
+    };
+    let event = NetworkEvent {
+        system,
+        event_data,
+        fields,
+    };
+    Ok(if initiated {
+        Event::OutboundNetwork(event)
+    } else {
+        Event::InboundNetwork(event)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::EventFilter;
+    use crate::helpers::HasProcess;
+
+    fn security_event(event_id: u16, data: &str) -> Event {
+        let xml = format!(
+            r#"
+    <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+      <System>
+        <Provider Name="Microsoft-Windows-Security-Auditing" Guid="{{54849625-5478-4994-A5BA-3E3B0328C30D}}" />
+        <EventID>{event_id}</EventID>
+        <Version>2</Version>
+        <Level>0</Level>
+        <Task>13312</Task>
+        <Opcode>0</Opcode>
+        <Keywords>0x8020000000000000</Keywords>
+        <TimeCreated SystemTime="2025-01-01T10:00:00.1234567Z" />
+        <EventRecordID>1042</EventRecordID>
+        <Correlation />
+        <Execution ProcessID="4" ThreadID="96" />
+        <Channel>Security</Channel>
+        <Computer>TEST-PC</Computer>
+        <Security />
+      </System>
+      <EventData>{data}</EventData>
+    </Event>
+    "#
+        );
+        Event::from_str(xml).unwrap()
+    }
+
+    #[test]
+    fn process_creation_and_logon_share_session() {
+        let process = security_event(
+            4688,
+            r#"
+        <Data Name="SubjectUserSid">S-1-5-21-1-2-3-1001</Data>
+        <Data Name="SubjectUserName">rsmith</Data>
+        <Data Name="SubjectDomainName">LAB</Data>
+        <Data Name="SubjectLogonId">0x7eb05</Data>
+        <Data Name="NewProcessId">0x1a2c</Data>
+        <Data Name="NewProcessName">C:\Windows\System32\cmd.exe</Data>
+        <Data Name="TokenElevationType">%%1938</Data>
+        <Data Name="ProcessId">0x2f0</Data>
+        <Data Name="CommandLine">cmd.exe /c whoami</Data>
+        <Data Name="TargetUserSid">S-1-0-0</Data>
+        <Data Name="TargetUserName">-</Data>
+        <Data Name="TargetDomainName">-</Data>
+        <Data Name="TargetLogonId">0x0</Data>
+        <Data Name="ParentProcessName">C:\Windows\explorer.exe</Data>
+        <Data Name="MandatoryLabel">S-1-16-8192</Data>"#,
+        );
+        let Event::ProcessCreate(ref created) = process else {
+            panic!("expected process creation, got {process:?}");
+        };
+        let data = &created.event_data;
+        assert_eq!(data.process_id, 0x1a2c);
+        assert_eq!(data.user.user, r"LAB\rsmith");
+        assert_eq!(data.integrity_level.integrity_level, "Medium");
+        assert_eq!(
+            data.parent_process_guid.process_guid,
+            process_guid(0x2f0).process_guid
+        );
+        assert_eq!(data.utc_time.utc_time, "2025-01-01 10:00:00.123");
+
+        let logon = security_event(
+            4624,
+            r#"
+        <Data Name="TargetUserSid">S-1-5-21-1-2-3-1001</Data>
+        <Data Name="TargetUserName">rsmith</Data>
+        <Data Name="TargetDomainName">LAB</Data>
+        <Data Name="TargetLogonId">0x7eb05</Data>
+        <Data Name="LogonType">10</Data>
+        <Data Name="LogonProcessName">User32 </Data>
+        <Data Name="AuthenticationPackageName">Negotiate</Data>
+        <Data Name="WorkstationName">WS01</Data>
+        <Data Name="ProcessId">0x2d4</Data>
+        <Data Name="ProcessName">C:\Windows\System32\svchost.exe</Data>
+        <Data Name="IpAddress">192.168.1.20</Data>
+        <Data Name="IpPort">0</Data>"#,
+        );
+        let Event::Logon(ref session) = logon else {
+            panic!("expected logon, got {logon:?}");
+        };
+        assert_eq!(session.event_data.logon_type, 10);
+        assert_eq!(session.event_data.logon_process.as_deref(), Some("User32"));
+        assert_eq!(session.event_data.ip_port, None);
+        assert_eq!(logon.process_name(), "svchost.exe");
+
+        let filter = EventFilter::new().with_logon_id(Some(0x7eb05));
+        assert!(filter.matches(&process));
+        assert!(filter.matches(&logon));
+    }
+
+    #[test]
+    fn inbound_connection_is_reported_from_the_remote_side() {
+        let event = security_event(
+            5156,
+            r#"
+        <Data Name="ProcessID">4</Data>
+        <Data Name="Application">System</Data>
+        <Data Name="Direction">%%14592</Data>
+        <Data Name="SourceAddress">10.0.0.5</Data>
+        <Data Name="SourcePort">445</Data>
+        <Data Name="DestAddress">10.0.0.9</Data>
+        <Data Name="DestPort">52344</Data>
+        <Data Name="Protocol">6</Data>"#,
+        );
+        let Event::InboundNetwork(ref connection) = event else {
+            panic!("expected inbound connection, got {event:?}");
+        };
+        let data = &connection.event_data;
+        assert_eq!(data.protocol, "tcp");
+        assert_eq!(
+            (data.source_ip.as_str(), data.source_port),
+            ("10.0.0.9", 52344)
+        );
+        assert_eq!(
+            (data.destination_ip.as_str(), data.destination_port),
+            ("10.0.0.5", 445)
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! HTTP API of the `serve` command: upload a log file or stream JSON events, run detection and
+//! fetch the anomaly report, so a web UI or a SOAR playbook can use the analyzer without the CLI
+//!
+//! - `GET /health`
+//! - `POST /analyses?format=evtx|jsonl|csv&name=NAME` with the log file (or JSON lines) as body:
+//!   `201` with the ID and anomaly report of the analysis
+//! - `GET /analyses`: the kept analyses, most recent first
+//! - `GET /analyses/ID`, `DELETE /analyses/ID`
+use crate::analyzer::DetectionConfig;
+use crate::daemon::file_report;
+use crate::filters::EventFilter;
+use crate::mapping::FieldMapping;
+use crate::parser::InputFormat;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_http::{Header, Method, Request, Response};
+use tracing::{info, warn};
+
+/// Analyses kept for `GET /analyses/ID`, the oldest are dropped first
+pub const MAX_KEPT_ANALYSES: usize = 100;
+
+pub struct ServerConfig {
+    /// Address and port to listen on
+    pub listen: String,
+    /// Bearer token required by every endpoint but `/health`
+    pub token: Option<String>,
+    /// Largest accepted upload, in bytes
+    pub max_upload: u64,
+    /// Requests handled at the same time
+    pub threads: usize,
+    /// Column mapping of CSV uploads (rejected without one) and JSON exports of other tools
+    pub mapping: Option<FieldMapping>,
+    pub filter: EventFilter,
+    pub detection: DetectionConfig,
+    pub incident_window: chrono::Duration,
+}
+
+/// Report of an uploaded file
+struct Analysis {
+    id: String,
+    name: String,
+    received: DateTime<Utc>,
+    events: usize,
+    anomalies: usize,
+    incidents: usize,
+    report: Value,
+}
+
+impl Analysis {
+    fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "received": self.received.to_rfc3339(),
+            "events": self.events,
+            "anomalies": self.anomalies,
+            "incidents": self.incidents,
+        })
+    }
+}
+
+/// Error answered to a request, as `{"error": message}`
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+type ApiResult = std::result::Result<(u16, Value), ApiError>;
+
+pub struct ApiServer {
+    config: ServerConfig,
+    http: tiny_http::Server,
+    running: AtomicBool,
+    analyses: Mutex<VecDeque<Analysis>>,
+}
+
+impl ApiServer {
+    pub fn bind(config: ServerConfig) -> Result<Self> {
+        let http = tiny_http::Server::http(&config.listen)
+            .map_err(|e| anyhow!("Failed to listen on {}: {e}", config.listen))?;
+        Ok(Self {
+            config,
+            http,
+            running: AtomicBool::new(true),
+            analyses: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Handle requests on the configured number of threads until [`ApiServer::stop`]
+    pub fn serve(&self) {
+        std::thread::scope(|scope| {
+            for _ in 0..self.config.threads.max(1) {
+                scope.spawn(|| {
+                    while self.running.load(Ordering::SeqCst) {
+                        match self.http.recv() {
+                            Ok(request) => self.handle(request),
+                            Err(e) if self.running.load(Ordering::SeqCst) => {
+                                warn!("Failed to accept request: {}", e)
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Let the threads finish the request they are handling and return from [`ApiServer::serve`]
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        for _ in 0..self.config.threads.max(1) {
+            self.http.unblock();
+        }
+    }
+
+    fn handle(&self, mut request: Request) {
+        let method = request.method().clone();
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let result = if path != "/health" && !self.is_authorized(&request) {
+            Err(ApiError::new(401, "missing or invalid bearer token"))
+        } else {
+            self.route(&method, path, query, &mut request)
+        };
+        let (status, body) = match result {
+            Ok((status, body)) => (status, body),
+            Err(e) => (e.status, json!({ "error": e.message })),
+        };
+        info!("{} {} -> {}", method, path, status);
+        let mut response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"));
+        if status == 201
+            && let Some(id) = body["id"].as_str()
+        {
+            response.add_header(header("Location", &format!("/analyses/{id}")));
+        }
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to answer {} {}: {}", method, path, e);
+        }
+    }
+
+    fn route(&self, method: &Method, path: &str, query: &str, request: &mut Request) -> ApiResult {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            (Method::Get, ["health"]) => Ok((200, json!({ "status": "ok" }))),
+            (Method::Post, ["analyses"]) => self.analyze(query, request),
+            (Method::Get, ["analyses"]) => {
+                let analyses = self.analyses.lock().unwrap_or_else(|e| e.into_inner());
+                let summaries: Vec<_> = analyses.iter().rev().map(Analysis::summary).collect();
+                Ok((200, json!({ "analyses": summaries })))
+            }
+            (Method::Get, ["analyses", id]) => {
+                let analyses = self.analyses.lock().unwrap_or_else(|e| e.into_inner());
+                let analysis = analyses
+                    .iter()
+                    .find(|analysis| analysis.id == *id)
+                    .ok_or_else(|| ApiError::new(404, format!("no analysis {id}")))?;
+                let mut body = analysis.summary();
+                body["report"] = analysis.report.clone();
+                Ok((200, body))
+            }
+            (Method::Delete, ["analyses", id]) => {
+                let mut analyses = self.analyses.lock().unwrap_or_else(|e| e.into_inner());
+                let before = analyses.len();
+                analyses.retain(|analysis| analysis.id != *id);
+                if analyses.len() == before {
+                    return Err(ApiError::new(404, format!("no analysis {id}")));
+                }
+                Ok((200, json!({ "deleted": id })))
+            }
+            (_, ["health"] | ["analyses"] | ["analyses", _]) => Err(ApiError::new(
+                405,
+                format!("{method} not allowed on {path}"),
+            )),
+            _ => Err(ApiError::new(404, format!("no endpoint {path}"))),
+        }
+    }
+
+    /// Save the body to a temporary file, analyze it and keep its report
+    fn analyze(&self, query: &str, request: &mut Request) -> ApiResult {
+        let format = match query_param(query, "format") {
+            Some(format) => parse_format(&format)?,
+            None => format_of(request),
+        };
+        if format == InputFormat::Csv && self.config.mapping.is_none() {
+            return Err(ApiError::new(
+                400,
+                "CSV uploads need a server started with --mapping",
+            ));
+        }
+        if request
+            .body_length()
+            .is_some_and(|length| length as u64 > self.config.max_upload)
+        {
+            return Err(ApiError::new(413, "upload too large"));
+        }
+        let name = query_param(query, "name").unwrap_or_else(|| "upload".to_string());
+        let mut file = tempfile::NamedTempFile::new()
+            .map_err(|e| ApiError::new(500, format!("failed to store upload: {e}")))?;
+        // Chunked uploads have no length to check up front
+        let copied = std::io::copy(
+            &mut request.as_reader().take(self.config.max_upload + 1),
+            &mut file,
+        )
+        .and_then(|copied| file.flush().map(|_| copied))
+        .map_err(|e| ApiError::new(400, format!("failed to read upload: {e}")))?;
+        if copied > self.config.max_upload {
+            return Err(ApiError::new(413, "upload too large"));
+        }
+        let report = file_report(
+            file.path(),
+            &name,
+            format,
+            self.config.mapping.as_ref(),
+            &self.config.filter,
+            &self.config.detection,
+            self.config.incident_window,
+        )
+        .map_err(|e| ApiError::new(422, format!("{e:#}")))?;
+        let analysis = Analysis {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            received: Utc::now(),
+            events: report.events,
+            anomalies: report.anomalies,
+            incidents: report.incidents,
+            report: report.json,
+        };
+        let mut body = analysis.summary();
+        body["report"] = analysis.report.clone();
+        let mut analyses = self.analyses.lock().unwrap_or_else(|e| e.into_inner());
+        if analyses.len() == MAX_KEPT_ANALYSES {
+            analyses.pop_front();
+        }
+        analyses.push_back(analysis);
+        Ok((201, body))
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.config.token else {
+            return true;
+        };
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+/// Value of a query string parameter, percent-decoded
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_format(format: &str) -> std::result::Result<InputFormat, ApiError> {
+    match format.to_ascii_lowercase().as_str() {
+        "evtx" => Ok(InputFormat::Evtx),
+        "jsonl" | "json" | "ndjson" => Ok(InputFormat::Jsonl),
+        "csv" => Ok(InputFormat::Csv),
+        _ => Err(ApiError::new(400, format!("unknown format '{format}'"))),
+    }
+}
+
+/// Format of an upload by its content type; EVTX unless it says JSON or CSV
+fn format_of(request: &Request) -> InputFormat {
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str().to_ascii_lowercase())
+        .unwrap_or_default();
+    if content_type.contains("json") {
+        InputFormat::Jsonl
+    } else if content_type.starts_with("text/csv") {
+        InputFormat::Csv
+    } else {
+        InputFormat::Evtx
+    }
+}
+
+/// Compare secrets without leaking where they differ through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_line;
+    use std::sync::Arc;
+
+    #[test]
+    fn upload_and_fetch_reports() {
+        let server = Arc::new(
+            ApiServer::bind(ServerConfig {
+                listen: "127.0.0.1:0".to_string(),
+                token: Some("s3cret".to_string()),
+                max_upload: 4096,
+                threads: 2,
+                mapping: None,
+                filter: EventFilter::new(),
+                detection: DetectionConfig::default(),
+                incident_window: chrono::Duration::minutes(2),
+            })
+            .unwrap(),
+        );
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let serving = {
+            let server = server.clone();
+            std::thread::spawn(move || server.serve())
+        };
+        let agent = ureq::agent();
+        let status = |result: std::result::Result<ureq::Response, ureq::Error>| match result {
+            Ok(response) => response.status(),
+            Err(ureq::Error::Status(status, _)) => status,
+            Err(e) => panic!("{e}"),
+        };
+
+        assert_eq!(status(agent.get(&format!("{base}/health")).call()), 200);
+        assert_eq!(status(agent.get(&format!("{base}/analyses")).call()), 401);
+        let line = json_process_line(json!({}));
+        let created: Value = agent
+            .post(&format!("{base}/analyses?name=ws1%20events.jsonl"))
+            .set("Authorization", "Bearer s3cret")
+            .set("Content-Type", "application/x-ndjson")
+            .send_string(&format!("{line}\n"))
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(created["name"], "ws1 events.jsonl");
+        assert_eq!(created["report"]["anomalies"][0]["rule_id"], "SLA-002");
+        let id = created["id"].as_str().unwrap();
+
+        let fetched: Value = agent
+            .get(&format!("{base}/analyses/{id}"))
+            .set("Authorization", "Bearer s3cret")
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(fetched["report"], created["report"]);
+        let listed: Value = agent
+            .get(&format!("{base}/analyses"))
+            .set("Authorization", "Bearer s3cret")
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(listed["analyses"][0]["incidents"], 1);
+
+        let post = |query: &str, body: &str| {
+            status(
+                agent
+                    .post(&format!("{base}/analyses{query}"))
+                    .set("Authorization", "Bearer s3cret")
+                    .send_string(body),
+            )
+        };
+        assert_eq!(post("", "not an event log"), 422);
+        assert_eq!(post("?format=xml", ""), 400);
+        assert_eq!(post("?format=csv", ""), 400);
+        assert_eq!(post("?format=jsonl", &"x".repeat(5000)), 413);
+        let delete = |id: &str| {
+            status(
+                agent
+                    .delete(&format!("{base}/analyses/{id}"))
+                    .set("Authorization", "Bearer s3cret")
+                    .call(),
+            )
+        };
+        assert_eq!(delete(id), 200);
+        assert_eq!(delete(id), 404);
+
+        server.stop();
+        serving.join().unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+//! Logon sessions reconstructed from the logon IDs of process creations and logon events
+use crate::analyzer::{AggregatedAnomaly, Anomaly, aggregate_anomalies};
+use crate::filters::parse_logon_id;
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Sessions are told apart by computer and logon ID, as IDs are only unique on one machine
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub computer: String,
+    pub logon_id: u64,
+}
+
+/// Process started in a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionProcess {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub pid: u64,
+    pub image: String,
+    pub command_line: String,
+}
+
+/// Everything logged for one logon session
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub computer: String,
+    pub logon_id: u64,
+    pub user: Option<String>,
+    /// Logon type of the 4624 event that created the session, when it is in the log
+    pub logon_type: Option<u32>,
+    pub source_ip: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub event_count: usize,
+    pub processes: Vec<SessionProcess>,
+    pub anomalies: Vec<AggregatedAnomaly>,
+}
+
+impl Session {
+    fn new(key: &SessionKey) -> Self {
+        Self {
+            computer: key.computer.clone(),
+            logon_id: key.logon_id,
+            ..Self::default()
+        }
+    }
+}
+
+/// Sessions ordered by start time and the number of events that belong to none of them
+#[derive(Debug, Clone, Default)]
+pub struct SessionReport {
+    pub sessions: Vec<Session>,
+    pub unattributed: usize,
+}
+
+impl SessionReport {
+    /// Session with this logon ID, restricted to a computer when the log covers several
+    pub fn find(&self, logon_id: u64, computer: Option<&str>) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .filter(|session| session.logon_id == logon_id)
+            .filter(|session| computer.is_none_or(|c| session.computer.eq_ignore_ascii_case(c)))
+            .collect()
+    }
+}
+
+/// Session key of an event: logons carry the session they create, process creations the
+/// session they run in, and all other events inherit the session of their process
+fn session_key(event: &SysmonEvent, processes: &HashMap<(&str, Uuid), u64>) -> Option<SessionKey> {
+    let computer = &event.system().computer.computer;
+    let logon_id = match event {
+        SysmonEvent::Logon(e) => parse_logon_id(&e.event_data.target_logon_id.logon_id).ok(),
+        SysmonEvent::ProcessCreate(e) => parse_logon_id(&e.event_data.logon_id.logon_id).ok(),
+        _ => processes
+            .get(&(computer.as_str(), event.process_guid()))
+            .copied(),
+    }?;
+    Some(SessionKey {
+        computer: computer.clone(),
+        logon_id,
+    })
+}
+
+/// Group events into logon sessions and attach the anomalies their events triggered
+pub fn build_sessions(events: &[SysmonEvent], anomalies: &[Anomaly]) -> SessionReport {
+    let processes: HashMap<(&str, Uuid), u64> = events
+        .iter()
+        .filter_map(|event| match event {
+            SysmonEvent::ProcessCreate(e) => Some((
+                (
+                    e.system.computer.computer.as_str(),
+                    e.event_data.process_guid.process_guid,
+                ),
+                parse_logon_id(&e.event_data.logon_id.logon_id).ok()?,
+            )),
+            _ => None,
+        })
+        .collect();
+
+    let mut sorted: Vec<&SysmonEvent> = events.iter().collect();
+    sorted.sort_by_key(|event| event.system().time_created.time);
+    let mut report = SessionReport::default();
+    let mut index: HashMap<SessionKey, usize> = HashMap::new();
+    for event in sorted {
+        let Some(key) = session_key(event, &processes) else {
+            report.unattributed += 1;
+            continue;
+        };
+        let position = *index.entry(key.clone()).or_insert_with(|| {
+            report.sessions.push(Session::new(&key));
+            report.sessions.len() - 1
+        });
+        let session = &mut report.sessions[position];
+        let timestamp = event.system().time_created.time;
+        session.event_count += 1;
+        session.start = session.start.or(timestamp);
+        session.end = timestamp.or(session.end);
+        match event {
+            SysmonEvent::Logon(e) => {
+                let data = &e.event_data;
+                session.user = Some(data.target_user.user.clone());
+                session.logon_type = Some(data.logon_type);
+                session.source_ip = data.ip_address.clone();
+            }
+            SysmonEvent::ProcessCreate(e) => {
+                let data = &e.event_data;
+                session.user.get_or_insert_with(|| data.user.user.clone());
+                session.processes.push(SessionProcess {
+                    timestamp,
+                    pid: data.process_id,
+                    image: data.image.image.clone(),
+                    command_line: data.command_line.command_line.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut session_anomalies: HashMap<usize, Vec<Anomaly>> = HashMap::new();
+    for anomaly in anomalies {
+        if let Some(position) = anomaly
+            .event()
+            .and_then(|event| session_key(event, &processes))
+            .and_then(|key| index.get(&key))
+        {
+            session_anomalies
+                .entry(*position)
+                .or_default()
+                .push(anomaly.clone());
+        }
+    }
+    for (position, anomalies) in session_anomalies {
+        report.sessions[position].anomalies = aggregate_anomalies(&anomalies);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::{json_event, json_process_event};
+    use serde_json::json;
+
+    fn process(time: &str, guid: &str, logon_id: &str, image: &str, parent: &str) -> SysmonEvent {
+        json_process_event(json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "TEST-PC",
+            "ProcessGuid": format!("{{{guid}}}"),
+            "Image": image,
+            "CommandLine": image,
+            "User": r"TEST-PC\Bob",
+            "LogonId": logon_id,
+            "ParentImage": parent,
+            "ParentCommandLine": parent,
+        }))
+    }
+
+    #[test]
+    fn events_are_grouped_by_logon_session() {
+        let word = r"C:\Program Files\Microsoft Office\root\Office16\WINWORD.EXE";
+        let events = vec![
+            process(
+                "10:00:00",
+                "11111111-0000-0000-0000-000000000001",
+                "0x3e7",
+                r"C:\Windows\System32\svchost.exe",
+                r"C:\Windows\System32\services.exe",
+            ),
+            process(
+                "10:05:00",
+                "11111111-0000-0000-0000-000000000002",
+                "0x7eb05",
+                word,
+                r"C:\Windows\explorer.exe",
+            ),
+            process(
+                "10:06:00",
+                "11111111-0000-0000-0000-000000000003",
+                "0x7eb05",
+                r"C:\Windows\System32\cmd.exe",
+                word,
+            ),
+            // Query of the Word process, attributed to its session through the process GUID
+            json_event(
+                22,
+                json!({
+                    "EventTime": "2025-01-01 10:07:00",
+                    "RecordNumber": 4,
+                    "ProcessGuid": "{11111111-0000-0000-0000-000000000002}",
+                    "QueryName": "example.com",
+                    "Image": r"C:\Windows\System32\cmd.exe",
+                }),
+            ),
+            // Process whose creation is not in the log
+            json_event(
+                22,
+                json!({
+                    "EventTime": "2025-01-01 10:08:00",
+                    "RecordNumber": 5,
+                    "ProcessGuid": "{11111111-0000-0000-0000-0000000000ff}",
+                    "QueryName": "example.com",
+                    "Image": r"C:\Windows\System32\cmd.exe",
+                }),
+            ),
+        ];
+        let report = build_sessions(&events, &detect_anomalies(&events));
+        assert_eq!(report.unattributed, 1);
+        assert_eq!(report.sessions.len(), 2);
+        assert_eq!(report.sessions[0].logon_id, 0x3e7);
+        assert!(report.sessions[0].anomalies.is_empty());
+
+        let [session] = report.find(0x7eb05, Some("test-pc"))[..] else {
+            panic!("expected one session");
+        };
+        assert_eq!(session.user.as_deref(), Some(r"TEST-PC\Bob"));
+        assert_eq!(session.event_count, 3);
+        assert_eq!(session.processes.len(), 2);
+        assert_eq!(session.end, "2025-01-01T10:07:00Z".parse().ok());
+        assert!(
+            session
+                .anomalies
+                .iter()
+                .any(|group| group.anomaly.rule_name() == "SuspiciousParentChild")
+        );
+        assert!(report.find(0x7eb05, Some("OTHER-PC")).is_empty());
+    }
+}
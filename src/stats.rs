@@ -0,0 +1,305 @@
+//! Top-N reports of the `stats` command: the busiest network talkers and, with `--dns`, the
+//! domains queried, the processes whose queries fail and the domains never queried before
+use crate::analyzer::prevalence::PrevalenceStore;
+use crate::sysmon::Event as SysmonEvent;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// `QueryStatus` of queries for names that don't exist (DNS_ERROR_RCODE_NAME_ERROR)
+pub const NXDOMAIN_STATUS: &str = "9003";
+
+/// Process or destination of outbound connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Talker {
+    pub name: String,
+    pub connections: usize,
+    /// Distinct destinations of a process, or processes connecting to a destination
+    pub peers: usize,
+}
+
+/// Processes and destinations of outbound connections, most connections first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Talkers {
+    pub processes: Vec<Talker>,
+    pub destinations: Vec<Talker>,
+}
+
+/// Count the outbound connections (event 3 initiated by the process) by process and destination
+pub fn top_talkers(events: &[SysmonEvent]) -> Talkers {
+    let mut processes: HashMap<String, (String, usize, HashSet<String>)> = HashMap::new();
+    let mut destinations: HashMap<String, (usize, HashSet<String>)> = HashMap::new();
+    for event in events {
+        let SysmonEvent::OutboundNetwork(e) = event else {
+            continue;
+        };
+        let data = &e.event_data;
+        let image = data.image.image.to_lowercase();
+        let destination = match data
+            .destination_hostname
+            .as_deref()
+            .filter(|host| !host.is_empty() && *host != "-")
+        {
+            Some(host) => format!("{}:{} ({host})", data.destination_ip, data.destination_port),
+            None => format!("{}:{}", data.destination_ip, data.destination_port),
+        };
+        let process = processes
+            .entry(image.clone())
+            .or_insert_with(|| (data.image.image.clone(), 0, HashSet::new()));
+        process.1 += 1;
+        process.2.insert(destination.clone());
+        let destination = destinations.entry(destination).or_default();
+        destination.0 += 1;
+        destination.1.insert(image);
+    }
+    let mut talkers = Talkers {
+        processes: processes
+            .into_values()
+            .map(|(name, connections, peers)| Talker {
+                name,
+                connections,
+                peers: peers.len(),
+            })
+            .collect(),
+        destinations: destinations
+            .into_iter()
+            .map(|(name, (connections, peers))| Talker {
+                name,
+                connections,
+                peers: peers.len(),
+            })
+            .collect(),
+    };
+    for list in [&mut talkers.processes, &mut talkers.destinations] {
+        list.sort_by(|a, b| {
+            (Reverse(a.connections), &a.name).cmp(&(Reverse(b.connections), &b.name))
+        });
+    }
+    talkers
+}
+
+/// Queries of a domain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainStats {
+    /// Lowercase name without the trailing dot
+    pub domain: String,
+    pub queries: usize,
+    /// Distinct images that queried the domain
+    pub processes: usize,
+    pub nxdomain: usize,
+    pub first_seen: Option<DateTime<Utc>>,
+}
+
+/// Queries of all processes with the same image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessDnsStats {
+    pub image: String,
+    pub queries: usize,
+    /// Distinct domains queried
+    pub domains: usize,
+    pub nxdomain: usize,
+}
+
+/// DNS queries (event 22) of a log
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsReport {
+    pub queries: usize,
+    /// Domains by name
+    pub domains: Vec<DomainStats>,
+    /// Processes, most queries first
+    pub processes: Vec<ProcessDnsStats>,
+    /// Domains never queried in earlier runs of the prevalence store, by name; `None` without a
+    /// store or while it has too few runs to tell
+    pub new_domains: Option<Vec<String>>,
+}
+
+/// Lowercase domain without the trailing dot of fully qualified names
+pub fn normalize_domain(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+impl DnsReport {
+    pub fn new(events: &[SysmonEvent]) -> Self {
+        let mut report = Self::default();
+        let mut domains: BTreeMap<String, (DomainStats, HashSet<String>)> = BTreeMap::new();
+        let mut processes: HashMap<String, (ProcessDnsStats, HashSet<String>)> = HashMap::new();
+        for event in events {
+            let SysmonEvent::DnsQuery(e) = event else {
+                continue;
+            };
+            let data = &e.event_data;
+            let domain = normalize_domain(&data.query_name);
+            if domain.is_empty() {
+                continue;
+            }
+            let image = data.image.image.to_lowercase();
+            let nxdomain = usize::from(data.query_status.as_deref() == Some(NXDOMAIN_STATUS));
+            report.queries += 1;
+
+            let (stats, images) = domains.entry(domain.clone()).or_insert_with(|| {
+                let stats = DomainStats {
+                    domain: domain.clone(),
+                    queries: 0,
+                    processes: 0,
+                    nxdomain: 0,
+                    first_seen: None,
+                };
+                (stats, HashSet::new())
+            });
+            stats.queries += 1;
+            stats.nxdomain += nxdomain;
+            if let Some(time) = e.system.time_created.time {
+                stats.first_seen = Some(stats.first_seen.map_or(time, |first| first.min(time)));
+            }
+            images.insert(image.clone());
+
+            let (stats, queried) = processes.entry(image).or_insert_with(|| {
+                let stats = ProcessDnsStats {
+                    image: data.image.image.clone(),
+                    queries: 0,
+                    domains: 0,
+                    nxdomain: 0,
+                };
+                (stats, HashSet::new())
+            });
+            stats.queries += 1;
+            stats.nxdomain += nxdomain;
+            queried.insert(domain);
+        }
+        report.domains = domains
+            .into_values()
+            .map(|(stats, images)| DomainStats {
+                processes: images.len(),
+                ..stats
+            })
+            .collect();
+        report.processes = processes
+            .into_values()
+            .map(|(stats, queried)| ProcessDnsStats {
+                domains: queried.len(),
+                ..stats
+            })
+            .collect();
+        report
+            .processes
+            .sort_by(|a, b| (Reverse(a.queries), &a.image).cmp(&(Reverse(b.queries), &b.image)));
+        report
+    }
+
+    /// Most queried domains
+    pub fn top_queried(&self, top: usize) -> Vec<&DomainStats> {
+        let mut domains: Vec<_> = self.domains.iter().collect();
+        domains.sort_by_key(|stats| Reverse(stats.queries));
+        domains.truncate(top);
+        domains
+    }
+
+    /// Domains queried by the most distinct processes, only those queried by more than one
+    pub fn top_by_processes(&self, top: usize) -> Vec<&DomainStats> {
+        let mut domains: Vec<_> = self
+            .domains
+            .iter()
+            .filter(|stats| stats.processes > 1)
+            .collect();
+        domains.sort_by_key(|stats| Reverse((stats.processes, stats.queries)));
+        domains.truncate(top);
+        domains
+    }
+
+    /// Processes with the most queries for names that don't exist, typical of domain generation
+    /// algorithms and of scanners
+    pub fn top_nxdomain(&self, top: usize) -> Vec<&ProcessDnsStats> {
+        let mut processes: Vec<_> = self
+            .processes
+            .iter()
+            .filter(|stats| stats.nxdomain > 0)
+            .collect();
+        processes.sort_by_key(|stats| Reverse((stats.nxdomain, stats.queries)));
+        processes.truncate(top);
+        processes
+    }
+
+    /// Fill [`DnsReport::new_domains`] from the store, then add the domains of this log to it
+    pub fn check_prevalence(&mut self, store: &mut PrevalenceStore) {
+        let domains = || self.domains.iter().map(|stats| stats.domain.as_str());
+        self.new_domains = store
+            .new_domains(domains())
+            .map(|new| new.into_iter().map(str::to_string).collect::<Vec<String>>());
+        store.record_domains(domains());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::prevalence::MIN_BASELINE_RUNS;
+    use crate::jsonl::parse_json_event;
+
+    fn query(time: &str, image: &str, name: &str, status: &str) -> SysmonEvent {
+        let line = serde_json::json!({
+            "EventTime": format!("2025-01-01 {time}"),
+            "Hostname": "WS-01",
+            "EventID": 22,
+            "RecordNumber": 1,
+            "UtcTime": format!("2025-01-01 {time}.000"),
+            "ProcessGuid": "{11111111-2222-3333-4444-555555555555}",
+            "ProcessId": "42",
+            "QueryName": name,
+            "QueryStatus": status,
+            "QueryResults": "93.184.216.34;",
+            "Image": image,
+        });
+        parse_json_event(&line.to_string()).unwrap()
+    }
+
+    #[test]
+    fn dns_report() {
+        let browser = r"C:\Program Files\Mozilla Firefox\firefox.exe";
+        let edge = r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe";
+        let implant = r"C:\Users\Public\svc.exe";
+        let events = [
+            query("10:00:00", browser, "www.example.com", "0"),
+            query("10:00:01", browser, "WWW.EXAMPLE.COM.", "0"),
+            query("09:59:00", edge, "www.example.com", "0"),
+            query("10:00:02", browser, "cdn.example.net", "0"),
+            query("10:01:00", implant, "qxkzvbtr.com", NXDOMAIN_STATUS),
+            query("10:01:01", implant, "wpfmdlsa.net", NXDOMAIN_STATUS),
+            query("10:01:02", implant, "c2.example.org", "0"),
+        ];
+        let mut report = DnsReport::new(&events);
+        assert_eq!(report.queries, 7);
+        assert_eq!(report.domains.len(), 5);
+        let top = report.top_queried(1)[0];
+        assert_eq!(
+            (top.domain.as_str(), top.queries, top.processes),
+            ("www.example.com", 3, 2)
+        );
+        assert_eq!(
+            top.first_seen.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-01-01T09:59:00+00:00")
+        );
+        assert_eq!(report.top_by_processes(10).len(), 1);
+        let noisy = report.top_nxdomain(10);
+        assert_eq!(noisy.len(), 1);
+        assert_eq!((noisy[0].image.as_str(), noisy[0].nxdomain), (implant, 2));
+        assert_eq!(report.processes[0].image, browser);
+        assert_eq!(report.processes[0].domains, 2);
+
+        let mut store = PrevalenceStore::default();
+        for _ in 0..MIN_BASELINE_RUNS {
+            report.check_prevalence(&mut store);
+            assert_eq!(report.new_domains, None);
+        }
+        let mut next = DnsReport::new(&[
+            query("11:00:00", browser, "www.example.com", "0"),
+            query("11:00:01", implant, "update.evil.example", "0"),
+        ]);
+        next.check_prevalence(&mut store);
+        assert_eq!(
+            next.new_domains,
+            Some(vec!["update.evil.example".to_string()])
+        );
+        next.check_prevalence(&mut store);
+        assert_eq!(next.new_domains, Some(Vec::new()));
+    }
+}
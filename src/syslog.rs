@@ -0,0 +1,427 @@
+//! Forwarding of events and anomalies to a syslog collector as RFC 5424 messages over UDP, TCP
+//! or TLS. Stream transports frame messages by octet counting (RFC 5425).
+use crate::analyzer::{Anomaly, Severity};
+use crate::export::{anomaly_to_json, event_to_json};
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::fmt;
+use std::io::Write;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const APP_NAME: &str = "sysmon-log-analyzer";
+/// local0, so collectors can route the messages without looking into them
+const FACILITY: u8 = 16;
+/// Informational, the syslog severity of forwarded events
+const EVENT_SEVERITY: u8 = 6;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+/// Collector address given as `syslog://host[:port]` (UDP), `syslog+tcp://` or `syslog+tls://`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogTarget {
+    pub transport: Transport,
+    pub host: String,
+    pub port: u16,
+    /// PEM file of the certificate authorities trusted with TLS instead of the public roots
+    pub ca: Option<PathBuf>,
+}
+
+impl fmt::Display for SyslogTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.transport {
+            Transport::Udp => "syslog",
+            Transport::Tcp => "syslog+tcp",
+            Transport::Tls => "syslog+tls",
+        };
+        if self.host.contains(':') {
+            write!(f, "{scheme}://[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{scheme}://{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Parse a collector URL; the port defaults to 514, or 6514 with TLS
+pub fn parse_syslog_target(s: &str) -> Result<SyslogTarget, String> {
+    let (scheme, address) = s
+        .split_once("://")
+        .ok_or_else(|| format!("Expected syslog://host[:port], got '{s}'"))?;
+    let transport = match scheme.to_ascii_lowercase().as_str() {
+        "syslog" | "syslog+udp" => Transport::Udp,
+        "syslog+tcp" => Transport::Tcp,
+        "syslog+tls" => Transport::Tls,
+        _ => {
+            return Err(format!(
+                "Unknown scheme '{scheme}', expected syslog, syslog+tcp or syslog+tls"
+            ));
+        }
+    };
+    let address = address.trim_end_matches('/');
+    // IPv6 addresses are written in brackets, e.g. syslog://[::1]:514
+    let (host, port) = match address.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or("Missing ']' after IPv6 address")?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match address.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (address, None),
+        },
+    };
+    if host.is_empty() {
+        return Err(format!("Missing host in '{s}'"));
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| format!("Invalid port '{port}'"))?,
+        None if transport == Transport::Tls => 6514,
+        None => 514,
+    };
+    Ok(SyslogTarget {
+        transport,
+        host: host.to_string(),
+        port,
+        ca: None,
+    })
+}
+
+/// Certificates of a PEM file, to trust a collector whose certificate a private CA issued
+fn load_ca(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let certificates = CertificateDer::pem_file_iter(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    for certificate in certificates {
+        let certificate =
+            certificate.with_context(|| format!("Invalid PEM file {}", path.display()))?;
+        roots
+            .add(certificate)
+            .with_context(|| format!("Invalid CA certificate in {}", path.display()))?;
+    }
+    if roots.is_empty() {
+        bail!("No certificate found in {}", path.display());
+    }
+    Ok(roots)
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    fn open(target: &SyslogTarget) -> Result<Self> {
+        let address = (target.host.as_str(), target.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve {}", target.host))?
+            .next()
+            .ok_or_else(|| anyhow!("No address found for {}", target.host))?;
+        match target.transport {
+            Transport::Udp => {
+                let local: SocketAddr = if address.is_ipv6() {
+                    "[::]:0".parse()?
+                } else {
+                    "0.0.0.0:0".parse()?
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(address)?;
+                Ok(Connection::Udp(socket))
+            }
+            Transport::Tcp => Ok(Connection::Tcp(tcp_stream(address)?)),
+            Transport::Tls => {
+                let roots = match &target.ca {
+                    Some(path) => load_ca(path)?,
+                    None => RootCertStore {
+                        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                    },
+                };
+                let config = ClientConfig::builder_with_provider(Arc::new(
+                    rustls::crypto::ring::default_provider(),
+                ))
+                .with_safe_default_protocol_versions()?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+                let server_name = ServerName::try_from(target.host.clone())
+                    .map_err(|e| anyhow!("Invalid TLS server name '{}': {e}", target.host))?;
+                let connection = ClientConnection::new(Arc::new(config), server_name)?;
+                Ok(Connection::Tls(Box::new(StreamOwned::new(
+                    connection,
+                    tcp_stream(address)?,
+                ))))
+            }
+        }
+    }
+
+    fn send(&mut self, message: &str) -> std::io::Result<()> {
+        match self {
+            Connection::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Connection::Tcp(stream) => write_framed(stream, message),
+            Connection::Tls(stream) => write_framed(stream.as_mut(), message),
+        }
+    }
+}
+
+fn tcp_stream(address: SocketAddr) -> Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .with_context(|| format!("Failed to connect to {address}"))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Octet-counted framing: the message length, a space and the message
+fn write_framed(stream: &mut impl Write, message: &str) -> std::io::Result<()> {
+    write!(stream, "{} {message}", message.len())?;
+    stream.flush()
+}
+
+/// Sends events and anomalies to a syslog collector, reconnecting streams that broke
+pub struct SyslogForwarder {
+    target: SyslogTarget,
+    connection: Connection,
+}
+
+impl SyslogForwarder {
+    pub fn connect(target: SyslogTarget) -> Result<Self> {
+        let connection = Connection::open(&target)?;
+        info!("Forwarding to {}", target);
+        Ok(Self { target, connection })
+    }
+
+    /// Forward an event as an informational message with the event as JSON
+    pub fn send_event(&mut self, event: &SysmonEvent) -> Result<()> {
+        let system = event.system();
+        let message = format_message(
+            EVENT_SEVERITY,
+            system.time_created.time,
+            &system.computer.computer,
+            event.name(),
+            &event_to_json(event).to_string(),
+        );
+        self.send(&message)
+    }
+
+    /// Forward an anomaly with a syslog severity matching its own
+    pub fn send_anomaly(&mut self, anomaly: &Anomaly) -> Result<()> {
+        let severity = match anomaly.severity() {
+            Severity::Critical => 2,
+            Severity::High => 3,
+            Severity::Medium => 4,
+            Severity::Low => 5,
+        };
+        let event = anomaly.event();
+        let message = format_message(
+            severity,
+            event.and_then(|event| event.system().time_created.time),
+            event.map_or("", |event| &event.system().computer.computer),
+            anomaly.rule_id(),
+            &anomaly_to_json(anomaly).to_string(),
+        );
+        self.send(&message)
+    }
+
+    fn send(&mut self, message: &str) -> Result<()> {
+        let Err(e) = self.connection.send(message) else {
+            return Ok(());
+        };
+        if self.target.transport == Transport::Udp {
+            return Err(anyhow!("Failed to send to {}: {e}", self.target));
+        }
+        // The collector may have restarted, reconnect once before giving up on the message
+        warn!("Connection to {} lost ({}), reconnecting", self.target, e);
+        self.connection = Connection::open(&self.target)?;
+        self.connection
+            .send(message)
+            .with_context(|| format!("Failed to send to {}", self.target))
+    }
+}
+
+/// RFC 5424 message without structured data. The time is the event time, or the current time
+/// when there is no event.
+fn format_message(
+    severity: u8,
+    event_time: Option<DateTime<Utc>>,
+    hostname: &str,
+    msg_id: &str,
+    message: &str,
+) -> String {
+    // RFC 5424 allows at most microseconds, Windows logs 100 ns precision
+    let timestamp = event_time
+        .unwrap_or_else(Utc::now)
+        .to_rfc3339_opts(SecondsFormat::Micros, true);
+    format!(
+        "<{}>1 {timestamp} {} {APP_NAME} {} {} - {message}",
+        FACILITY * 8 + severity,
+        header_field(hostname, 255),
+        std::process::id(),
+        header_field(msg_id, 32),
+    )
+}
+
+/// Header fields are printable ASCII without spaces, `-` when empty
+fn header_field(value: &str, max_len: usize) -> String {
+    let field: String = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonl::parse_json_event;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn forwards_rfc5424_messages() {
+        assert_eq!(
+            parse_syslog_target("syslog+tls://siem.example.com").unwrap(),
+            SyslogTarget {
+                transport: Transport::Tls,
+                host: "siem.example.com".to_string(),
+                port: 6514,
+                ca: None,
+            }
+        );
+        let ipv6 = parse_syslog_target("syslog://[::1]:1514").unwrap();
+        assert_eq!((ipv6.host.as_str(), ipv6.port), ("::1", 1514));
+        assert_eq!(ipv6.to_string(), "syslog://[::1]:1514");
+        for invalid in [
+            "siem:514",
+            "http://siem",
+            "syslog://:514",
+            "syslog://siem:x",
+        ] {
+            assert!(parse_syslog_target(invalid).is_err(), "{invalid}");
+        }
+
+        let event = parse_json_event(
+            r#"{"EventTime":"2025-01-01 10:00:00.1234567","Hostname":"TEST PC","EventID":22,"RecordNumber":1,"UtcTime":"2025-01-01 10:00:00.123","ProcessGuid":"{11111111-2222-3333-4444-555555555555}","ProcessId":"4242","QueryName":"example.com","Image":"C:\\Windows\\System32\\cmd.exe"}"#,
+        )
+        .unwrap();
+        let expected_header = format!(
+            "<134>1 2025-01-01T10:00:00.123456Z TEST_PC {APP_NAME} {} DNSEvent - {{",
+            std::process::id()
+        );
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = parse_syslog_target(&format!(
+            "syslog://127.0.0.1:{}",
+            receiver.local_addr().unwrap().port()
+        ))
+        .unwrap();
+        SyslogForwarder::connect(target)
+            .unwrap()
+            .send_event(&event)
+            .unwrap();
+        let mut datagram = [0; 4096];
+        let length = receiver.recv(&mut datagram).unwrap();
+        let message = std::str::from_utf8(&datagram[..length]).unwrap();
+        assert!(message.starts_with(&expected_header), "{message}");
+        assert!(
+            message.contains(r#""query_name":"example.com""#),
+            "{message}"
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = parse_syslog_target(&format!(
+            "syslog+tcp://127.0.0.1:{}",
+            listener.local_addr().unwrap().port()
+        ))
+        .unwrap();
+        let mut forwarder = SyslogForwarder::connect(target).unwrap();
+        forwarder.send_event(&event).unwrap();
+        drop(forwarder);
+        let mut received = String::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_string(&mut received)
+            .unwrap();
+        let (length, message) = received.split_once(' ').unwrap();
+        assert_eq!(length.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with(&expected_header), "{message}");
+    }
+
+    #[test]
+    fn tls_collector_with_private_ca() {
+        let files = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_files/tls");
+        let certificates = CertificateDer::pem_file_iter(files.join("server.pem"))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key =
+            rustls::pki_types::PrivateKeyDer::from_pem_file(files.join("server.key")).unwrap();
+        let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .unwrap();
+        // Session tickets the client never reads would make its close reset the connection
+        config.send_tls13_tickets = 0;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let collector = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let connection = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(connection, stream);
+            let mut received = Vec::new();
+            // The client closes the TCP connection without a TLS close_notify
+            let _ = stream.read_to_end(&mut received);
+            String::from_utf8(received).unwrap()
+        });
+
+        let target = SyslogTarget {
+            ca: Some(files.join("ca.pem")),
+            ..parse_syslog_target(&format!("syslog+tls://localhost:{port}")).unwrap()
+        };
+        let mut forwarder = SyslogForwarder::connect(target).unwrap();
+        forwarder
+            .send(&format_message(6, None, "TEST-PC", "test", "hello"))
+            .unwrap();
+        drop(forwarder);
+        let received = collector.join().unwrap();
+        assert!(received.ends_with(" - hello"), "{received}");
+
+        // The CA file must hold a certificate, and only TLS targets take one
+        assert!(load_ca(&files.join("server.key")).is_err());
+        let tls = parse_syslog_target("syslog+tls://siem").unwrap();
+        assert!(
+            crate::forward::ForwardTarget::Syslog(tls)
+                .with_syslog_ca(files.join("ca.pem"))
+                .is_ok()
+        );
+        assert!(
+            crate::forward::ForwardTarget::Syslog(parse_syslog_target("syslog://siem").unwrap())
+                .with_syslog_ca(files.join("ca.pem"))
+                .is_err()
+        );
+    }
+}
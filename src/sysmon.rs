@@ -23,9 +23,13 @@
 // Original code: https://github.com/insanitybit/sysmon
 // Modified by Serhii Smoliak, 2025
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 
+use crate::powershell;
+use crate::security;
+use crate::sysmon_schema::{self, parse_flag, parse_number};
 use anyhow::{Result, anyhow};
 use chrono::prelude::*;
 use derive_is_enum_variant::is_enum_variant;
@@ -52,27 +56,199 @@ macro_rules! get_or_err {
 #[derive(Debug, Clone, Hash, is_enum_variant)]
 pub enum Event {
     ProcessCreate(ProcessCreateEvent),
+    /// Process ended (5)
+    ProcessTerminate(ProcessTerminateEvent),
     FileCreate(FileCreateEvent),
+    /// Alternate data stream created, e.g. the Mark-of-the-Web of a download (15)
+    FileStreamCreate(FileStreamEvent),
     InboundNetwork(NetworkEvent),
     OutboundNetwork(NetworkEvent),
+    /// FileDelete (23) and FileDeleteDetected (26)
+    FileDelete(FileDeleteEvent),
+    /// FileBlockExecutable (27) and FileBlockShredding (28)
+    FileBlock(FileDeleteEvent),
+    /// Executable file written to disk (29)
+    FileExecutableDetected(FileDeleteEvent),
+    PipeCreated(PipeEvent),
+    PipeConnected(PipeEvent),
+    /// Registry value set (13)
+    RegistrySetValue(RegistryEvent),
+    DnsQuery(DnsEvent),
+    ProcessAccess(ProcessAccessEvent),
+    /// Sysmon service state changed (4)
+    ServiceStateChange(SysmonServiceEvent),
+    /// Sysmon configuration changed (16)
+    ConfigurationChange(SysmonServiceEvent),
+    DriverLoad(DriverLoadEvent),
+    RawAccessRead(RawAccessReadEvent),
+    /// Clipboard content changed (24)
+    ClipboardChange(ClipboardEvent),
+    /// Process image replaced or locked, as in hollowing and herpaderping (25)
+    ProcessTampering(ProcessTamperingEvent),
+    /// WmiEventFilter activity (19)
+    WmiFilter(WmiEvent),
+    /// WmiEventConsumer activity (20)
+    WmiConsumer(WmiEvent),
+    /// WmiEventConsumerToFilter activity (21)
+    WmiBinding(WmiEvent),
+    /// Successful logon (Security 4624)
+    Logon(LogonEvent),
+    /// PowerShell script block logged before it runs (PowerShell Operational 4104)
+    ScriptBlock(ScriptBlockEvent),
+}
+
+/// Event IDs that `Event::from_str` parses, so other events can be dropped before their XML is
+/// rendered
+pub const SUPPORTED_EVENT_IDS: &[u16] = &[
+    1, 2, 3, 4, 5, 6, 9, 10, 11, 13, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29,
+    4104, 4624, 4688, 5156,
+];
+
+/// Envelope used to read the Event ID before parsing the event data
+#[derive(Debug, Deserialize)]
+struct EventHeader {
+    #[serde(rename = "System")]
+    system: System,
+    #[serde(rename = "EventData")]
+    event_data: Option<IntermediaryEventData>,
 }
 
 impl Event {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: impl AsRef<str>) -> Result<Self> {
         let s = s.as_ref();
-        serde_xml_rs::from_str::<ProcessCreateEvent>(s)
-            .map(Event::ProcessCreate)
-            .or_else(|_| serde_xml_rs::from_str::<FileCreateEvent>(s).map(Event::FileCreate))
-            .or_else(|_| {
-                serde_xml_rs::from_str::<NetworkEvent>(s).map(|n| {
-                    if n.event_data.initiated {
-                        Event::OutboundNetwork(n)
-                    } else {
-                        Event::InboundNetwork(n)
-                    }
-                })
-            })
-            .map_err(|e| anyhow!("Error : {e:?} {s}"))
+        let header: EventHeader =
+            serde_xml_rs::from_str(s).map_err(|e| anyhow!("Malformed event XML: {e}"))?;
+        let event_id = header.system.event_id.event_id;
+        if let Cow::Owned(named) =
+            sysmon_schema::name_data_elements(s, event_id, &header.system.version.version)
+        {
+            return Self::from_str(named);
+        }
+        let event = match event_id {
+            1 => serde_xml_rs::from_str::<ProcessCreateEvent>(s).map(Event::ProcessCreate),
+            5 => serde_xml_rs::from_str::<ProcessTerminateEvent>(s).map(Event::ProcessTerminate),
+            2 | 11 => serde_xml_rs::from_str::<FileCreateEvent>(s).map(Event::FileCreate),
+            15 => serde_xml_rs::from_str::<FileStreamEvent>(s).map(Event::FileStreamCreate),
+            3 => serde_xml_rs::from_str::<NetworkEvent>(s).map(|n| {
+                if n.event_data.initiated {
+                    Event::OutboundNetwork(n)
+                } else {
+                    Event::InboundNetwork(n)
+                }
+            }),
+            23 | 26 => serde_xml_rs::from_str::<FileDeleteEvent>(s).map(Event::FileDelete),
+            27 | 28 => serde_xml_rs::from_str::<FileDeleteEvent>(s).map(Event::FileBlock),
+            29 => serde_xml_rs::from_str::<FileDeleteEvent>(s).map(Event::FileExecutableDetected),
+            17 => serde_xml_rs::from_str::<PipeEvent>(s).map(Event::PipeCreated),
+            18 => serde_xml_rs::from_str::<PipeEvent>(s).map(Event::PipeConnected),
+            13 => serde_xml_rs::from_str::<RegistryEvent>(s).map(Event::RegistrySetValue),
+            4 => serde_xml_rs::from_str::<SysmonServiceEvent>(s).map(Event::ServiceStateChange),
+            16 => serde_xml_rs::from_str::<SysmonServiceEvent>(s).map(Event::ConfigurationChange),
+            6 => serde_xml_rs::from_str::<DriverLoadEvent>(s).map(Event::DriverLoad),
+            9 => serde_xml_rs::from_str::<RawAccessReadEvent>(s).map(Event::RawAccessRead),
+            10 => serde_xml_rs::from_str::<ProcessAccessEvent>(s).map(Event::ProcessAccess),
+            19 => serde_xml_rs::from_str::<WmiEvent>(s).map(Event::WmiFilter),
+            20 => serde_xml_rs::from_str::<WmiEvent>(s).map(Event::WmiConsumer),
+            21 => serde_xml_rs::from_str::<WmiEvent>(s).map(Event::WmiBinding),
+            22 => serde_xml_rs::from_str::<DnsEvent>(s).map(Event::DnsQuery),
+            24 => serde_xml_rs::from_str::<ClipboardEvent>(s).map(Event::ClipboardChange),
+            25 => serde_xml_rs::from_str::<ProcessTamperingEvent>(s).map(Event::ProcessTampering),
+            4624 => return security::logon(s).map(Event::Logon),
+            4688 => return security::process_create(s).map(Event::ProcessCreate),
+            5156 => return security::network_connection(s),
+            // Other providers log unrelated events under the same ID
+            4104 if header.system.provider.is_powershell() => {
+                return powershell::script_block(s).map(Event::ScriptBlock);
+            }
+            id => return Err(anyhow!("Unsupported Sysmon event ID: {id}")),
+        };
+        let mut event = event.map_err(|e| anyhow!("Unsupported or malformed Sysmon event: {e}"))?;
+        if let Some(data) = &header.event_data {
+            *event.fields_mut() = EventFields::from(data);
+        }
+        // Fall back on the time Sysmon recorded in the event data when SystemTime is unusable
+        if event.system_mut().time_created.time.is_none() {
+            let time = event.fields().get("UtcTime").and_then(parse_event_time);
+            event.system_mut().time_created.time = time;
+        }
+        Ok(event)
+    }
+
+    fn system_mut(&mut self) -> &mut System {
+        match self {
+            Event::ProcessCreate(e) => &mut e.system,
+            Event::ProcessTerminate(e) => &mut e.system,
+            Event::FileCreate(e) => &mut e.system,
+            Event::FileStreamCreate(e) => &mut e.system,
+            Event::InboundNetwork(e) | Event::OutboundNetwork(e) => &mut e.system,
+            Event::FileDelete(e) | Event::FileBlock(e) | Event::FileExecutableDetected(e) => {
+                &mut e.system
+            }
+            Event::PipeCreated(e) | Event::PipeConnected(e) => &mut e.system,
+            Event::RegistrySetValue(e) => &mut e.system,
+            Event::DnsQuery(e) => &mut e.system,
+            Event::ProcessAccess(e) => &mut e.system,
+            Event::ServiceStateChange(e) | Event::ConfigurationChange(e) => &mut e.system,
+            Event::DriverLoad(e) => &mut e.system,
+            Event::RawAccessRead(e) => &mut e.system,
+            Event::ClipboardChange(e) => &mut e.system,
+            Event::ProcessTampering(e) => &mut e.system,
+            Event::WmiFilter(e) | Event::WmiConsumer(e) | Event::WmiBinding(e) => &mut e.system,
+            Event::Logon(e) => &mut e.system,
+            Event::ScriptBlock(e) => &mut e.system,
+        }
+    }
+
+    /// All `<Data>` elements of the event, including the ones without a typed field
+    pub fn fields(&self) -> &EventFields {
+        match self {
+            Event::ProcessCreate(e) => &e.fields,
+            Event::ProcessTerminate(e) => &e.fields,
+            Event::FileCreate(e) => &e.fields,
+            Event::FileStreamCreate(e) => &e.fields,
+            Event::InboundNetwork(e) | Event::OutboundNetwork(e) => &e.fields,
+            Event::FileDelete(e) | Event::FileBlock(e) | Event::FileExecutableDetected(e) => {
+                &e.fields
+            }
+            Event::PipeCreated(e) | Event::PipeConnected(e) => &e.fields,
+            Event::RegistrySetValue(e) => &e.fields,
+            Event::DnsQuery(e) => &e.fields,
+            Event::ProcessAccess(e) => &e.fields,
+            Event::ServiceStateChange(e) | Event::ConfigurationChange(e) => &e.fields,
+            Event::DriverLoad(e) => &e.fields,
+            Event::RawAccessRead(e) => &e.fields,
+            Event::ClipboardChange(e) => &e.fields,
+            Event::ProcessTampering(e) => &e.fields,
+            Event::WmiFilter(e) | Event::WmiConsumer(e) | Event::WmiBinding(e) => &e.fields,
+            Event::Logon(e) => &e.fields,
+            Event::ScriptBlock(e) => &e.fields,
+        }
+    }
+
+    fn fields_mut(&mut self) -> &mut EventFields {
+        match self {
+            Event::ProcessCreate(e) => &mut e.fields,
+            Event::ProcessTerminate(e) => &mut e.fields,
+            Event::FileCreate(e) => &mut e.fields,
+            Event::FileStreamCreate(e) => &mut e.fields,
+            Event::InboundNetwork(e) | Event::OutboundNetwork(e) => &mut e.fields,
+            Event::FileDelete(e) | Event::FileBlock(e) | Event::FileExecutableDetected(e) => {
+                &mut e.fields
+            }
+            Event::PipeCreated(e) | Event::PipeConnected(e) => &mut e.fields,
+            Event::RegistrySetValue(e) => &mut e.fields,
+            Event::DnsQuery(e) => &mut e.fields,
+            Event::ProcessAccess(e) => &mut e.fields,
+            Event::ServiceStateChange(e) | Event::ConfigurationChange(e) => &mut e.fields,
+            Event::DriverLoad(e) => &mut e.fields,
+            Event::RawAccessRead(e) => &mut e.fields,
+            Event::ClipboardChange(e) => &mut e.fields,
+            Event::ProcessTampering(e) => &mut e.fields,
+            Event::WmiFilter(e) | Event::WmiConsumer(e) | Event::WmiBinding(e) => &mut e.fields,
+            Event::Logon(e) => &mut e.fields,
+            Event::ScriptBlock(e) => &mut e.fields,
+        }
     }
 }
 
@@ -84,10 +260,17 @@ pub struct Provider {
     pub provider_guid: String,
 }
 
+impl Provider {
+    /// Windows PowerShell (Microsoft-Windows-PowerShell) or PowerShell 7 (PowerShellCore)
+    pub fn is_powershell(&self) -> bool {
+        self.provider_name.to_lowercase().contains("powershell")
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Hash)]
 pub struct EventId {
     #[serde(rename = "$value")]
-    pub event_id: u8,
+    pub event_id: u16,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
@@ -121,9 +304,61 @@ pub struct Keywords {
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
+#[serde(from = "TimeCreatedAttribute")]
 pub struct TimeCreated {
-    #[serde(rename = "SystemTime")]
+    /// Value as written in the log, e.g. `2017-04-28T22:08:22.025812200Z`
     pub system_time: String,
+    /// `system_time` parsed, `None` when it is not a valid timestamp
+    pub time: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct TimeCreatedAttribute {
+    #[serde(rename = "SystemTime")]
+    system_time: String,
+}
+
+impl From<TimeCreatedAttribute> for TimeCreated {
+    fn from(attribute: TimeCreatedAttribute) -> Self {
+        Self {
+            time: parse_event_time(&attribute.system_time),
+            system_time: attribute.system_time,
+        }
+    }
+}
+
+impl TimeCreated {
+    /// Time in RFC 3339, or as written in the log when it is not a valid timestamp
+    pub fn to_rfc3339(&self) -> String {
+        match self.time {
+            Some(time) => format_event_time(time),
+            None => self.system_time.clone(),
+        }
+    }
+}
+
+/// Parse a timestamp of the log: RFC 3339 as in `SystemTime` (with up to 100 ns precision) or
+/// `YYYY-MM-DD HH:MM:SS.fff` in UTC as in Sysmon's `UtcTime` field, also accepting the US
+/// (`MM/DD/YYYY hh:mm:ss AM`) and dotted (`DD.MM.YYYY HH:MM:SS`) forms of localized exports
+pub fn parse_event_time(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(time) = s.parse::<DateTime<Utc>>() {
+        return Some(time);
+    }
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%m/%d/%Y %I:%M:%S%.f %p",
+        "%d.%m.%Y %H:%M:%S%.f",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    .map(|time| time.and_utc())
+}
+
+/// RFC 3339 in UTC with as many fractional digits as needed, the form used in JSON output
+pub fn format_event_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
@@ -154,7 +389,8 @@ pub struct Computer {
 
 #[derive(Debug, Deserialize, Clone, Hash)]
 pub struct Security {
-    #[serde(rename = "UserID")]
+    /// Not set on Security channel events
+    #[serde(rename = "UserID", default)]
     pub security: String,
 }
 
@@ -401,6 +637,12 @@ pub struct ProcessCreateEventData {
     pub parent_image: Image,
     /// <Data Name="ParentCommandLine">"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe" </Data>
     pub parent_command_line: CommandLine,
+    /// <Data Name="Signed">true</Data>, only present when the collector adds image signature info
+    pub signed: Option<bool>,
+    /// <Data Name="Signature">Google LLC</Data>
+    pub signature: Option<String>,
+    /// <Data Name="SignatureStatus">Valid</Data>
+    pub signature_status: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
@@ -409,6 +651,9 @@ pub struct ProcessCreateEvent {
     pub system: System,
     #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
     pub event_data: ProcessCreateEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
@@ -428,6 +673,56 @@ pub struct FileCreateEvent {
 
     #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
     pub event_data: FileCreateEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct FileStreamEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Program Files\Mozilla Firefox\firefox.exe</Data>
+    pub image: Image,
+    /// <Data Name="TargetFilename">C:\Users\rsmith\Downloads\invoice.exe:Zone.Identifier</Data>
+    pub target_filename: String,
+    /// <Data Name="CreationUtcTime">2021-04-21 11:24:35.102</Data>
+    pub creation_utc_time: Option<UtcTime>,
+    /// <Data Name="Hash">SHA256=7F8A1D8C...,IMPHASH=00000000000000000000000000000000</Data>
+    pub hash: Option<Hashes>,
+    /// Text of small streams, logged since Sysmon 13
+    /// <Data Name="Contents">[ZoneTransfer]  ZoneId=3  HostUrl=https://example.com/invoice.exe</Data>
+    pub contents: Option<String>,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+}
+
+impl FileStreamEventData {
+    /// File the stream belongs to and the stream name, `None` when the target has no stream
+    pub fn stream(&self) -> Option<(&str, &str)> {
+        let target = self.target_filename.trim_end_matches(":$DATA");
+        let (file, stream) = target.rsplit_once(':')?;
+        // The colon of a drive letter does not start a stream
+        if file.len() < 2 || stream.is_empty() || stream.contains('\\') {
+            return None;
+        }
+        Some((file, stream))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct FileStreamEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: FileStreamEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
@@ -451,183 +746,1215 @@ pub struct NetworkEventData {
     pub destination_port_name: Option<String>,
 }
 
+impl NetworkEventData {
+    /// Address and port of the other machine: the destination of outbound connections and the
+    /// source of inbound ones, as Sysmon reports the initiating side as the source
+    pub fn remote(&self) -> (&str, u16) {
+        if self.initiated {
+            (&self.destination_ip, self.destination_port)
+        } else {
+            (&self.source_ip, self.source_port)
+        }
+    }
+
+    /// Address and port of the logging machine
+    pub fn local(&self) -> (&str, u16) {
+        if self.initiated {
+            (&self.source_ip, self.source_port)
+        } else {
+            (&self.destination_ip, self.destination_port)
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Hash)]
 pub struct NetworkEvent {
     #[serde(rename = "System")]
     pub system: System,
     #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
     pub event_data: NetworkEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
 }
 
-impl TryFrom<IntermediaryEventData> for ProcessCreateEventData {
-    type Error = anyhow::Error;
-
-    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
-        let mut m = HashMap::with_capacity(inter.data.len());
-
-        for data in inter.data {
-            if let Some(value) = data.value {
-                m.insert(data.name, value);
-            }
-        }
-
-        let process_id = get_or_err!(m, "ProcessId");
-        let process_id: u64 = process_id.parse()?;
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct FileDeleteEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+    /// <Data Name="Image">C:\Windows\System32\cmd.exe</Data>
+    pub image: Image,
+    /// <Data Name="TargetFilename">C:\Users\rsmith\Documents\report.docx</Data>
+    pub target_filename: String,
+    /// <Data Name="Hashes">SHA256=6055A20CF7EC81843310AD37700FF67B2CF8CDE3DCE68D54BA42934177C10B57</Data>
+    pub hashes: Option<Hashes>,
+    /// <Data Name="IsExecutable">false</Data> (not in FileExecutableDetected)
+    pub is_executable: Option<bool>,
+    /// <Data Name="Archived">true</Data> (FileDelete only)
+    pub archived: Option<bool>,
+}
 
-        let parent_process_id = get_or_err!(m, "ParentProcessId");
-        let parent_process_id: u64 = parent_process_id.parse()?;
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct FileDeleteEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: FileDeleteEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
 
-        Ok(ProcessCreateEventData {
-            utc_time: UtcTime {
-                utc_time: get_or_err!(m, "UtcTime"),
-            },
-            process_guid: ProcessGuid {
-                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
-            },
-            process_id,
-            image: Image {
-                image: get_or_err!(m, "Image"),
-            },
-            command_line: CommandLine {
-                command_line: get_or_err!(m, "CommandLine"),
-            },
-            current_directory: CurrentDirectory {
-                current_directory: get_or_err!(m, "CurrentDirectory"),
-            },
-            user: User {
-                user: get_or_err!(m, "User"),
-            },
-            logon_guid: LogonGuid {
-                logon_guid: uuid::Uuid::parse_str(&get_or_err!(m, "LogonGuid"))?,
-            },
-            logon_id: LogonId {
-                logon_id: get_or_err!(m, "LogonId"),
-            },
-            terminal_session_id: TerminalSessionId {
-                terminal_session_id: get_or_err!(m, "TerminalSessionId"),
-            },
-            integrity_level: IntegrityLevel {
-                integrity_level: get_or_err!(m, "IntegrityLevel"),
-            },
-            hashes: Hashes {
-                hashes: get_or_err!(m, "Hashes"),
-            },
-            parent_process_guid: ProcessGuid {
-                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ParentProcessGuid"))?,
-            },
-            parent_process_id,
-            parent_image: Image {
-                image: get_or_err!(m, "ParentImage"),
-            },
-            parent_command_line: CommandLine {
-                command_line: get_or_err!(m, "ParentCommandLine"),
-            },
-        })
-    }
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct PipeEventData {
+    /// <Data Name="EventType">CreatePipe</Data>
+    pub event_type: Option<String>,
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="PipeName">\PSEXESVC</Data>
+    pub pipe_name: String,
+    /// <Data Name="Image">C:\Windows\PSEXESVC.exe</Data>
+    pub image: Image,
+    /// <Data Name="User">NT AUTHORITY\SYSTEM</Data>
+    pub user: Option<User>,
 }
 
-impl TryFrom<IntermediaryEventData> for FileCreateEventData {
-    type Error = anyhow::Error;
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct PipeEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: PipeEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
 
-    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
-        let mut m = HashMap::with_capacity(inter.data.len());
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct RegistryEventData {
+    /// <Data Name="EventType">SetValue</Data>
+    pub event_type: Option<String>,
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Windows\regedit.exe</Data>
+    pub image: Image,
+    /// <Data Name="TargetObject">HKU\S-1-5-21-1-2-3-1001\SOFTWARE\Microsoft\Windows\CurrentVersion\Run\Updater</Data>
+    pub target_object: String,
+    /// <Data Name="Details">DWORD (0x00000001)</Data>, see [`RegistryValue`]
+    pub details: String,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+}
 
-        for data in inter.data {
-            if let Some(value) = data.value {
-                m.insert(data.name, value);
-            }
-        }
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct RegistryEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: RegistryEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
 
-        let process_id = get_or_err!(m, "ProcessId");
-        let process_id = process_id.parse()?;
+impl RegistryEventData {
+    /// Value data written, decoded from `Details`
+    pub fn value(&self) -> RegistryValue {
+        RegistryValue::parse(&self.details)
+    }
 
-        Ok(FileCreateEventData {
-            utc_time: UtcTime {
-                utc_time: get_or_err!(m, "UtcTime"),
-            },
-            process_guid: ProcessGuid {
-                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
-            },
-            process_id,
-            image: Image {
-                image: get_or_err!(m, "Image"),
-            },
-            creation_utc_time: UtcTime {
-                utc_time: get_or_err!(m, "CreationUtcTime"),
-            },
-            target_filename: get_or_err!(m, "TargetFilename"),
-        })
+    /// Name of the value, the last component of `TargetObject`
+    pub fn value_name(&self) -> &str {
+        self.target_object
+            .rsplit('\\')
+            .next()
+            .unwrap_or(&self.target_object)
     }
 }
 
-impl TryFrom<IntermediaryEventData> for NetworkEventData {
-    type Error = anyhow::Error;
-
-    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
-        let mut m = HashMap::with_capacity(inter.data.len());
+/// Value data of a registry SetValue event, as Sysmon writes it in `Details`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryValue {
+    /// `DWORD (0x00000001)`
+    Dword(u32),
+    /// `QWORD (0x00000001-0x00000000)`, high then low half
+    Qword(u64),
+    /// `Binary Data`, Sysmon does not log the bytes
+    Binary,
+    /// `(Empty)`, or no Details at all
+    Empty,
+    /// REG_SZ, REG_EXPAND_SZ and REG_MULTI_SZ data as written
+    String(String),
+}
 
-        for data in inter.data {
-            if let Some(value) = data.value {
-                m.insert(data.name, value);
-            }
+impl RegistryValue {
+    pub fn parse(details: &str) -> Self {
+        let hex = |s: &str| {
+            s.strip_prefix("0x")
+                .and_then(|s| u64::from_str_radix(s, 16).ok())
+        };
+        if let Some(dword) = details
+            .strip_prefix("DWORD (")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(hex)
+            .and_then(|value| u32::try_from(value).ok())
+        {
+            return RegistryValue::Dword(dword);
+        }
+        if let Some(qword) = details
+            .strip_prefix("QWORD (")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(high, low)| Some((hex(high)? << 32) | hex(low)?))
+        {
+            return RegistryValue::Qword(qword);
+        }
+        match details {
+            "Binary Data" => RegistryValue::Binary,
+            "" | "(Empty)" => RegistryValue::Empty,
+            string => RegistryValue::String(string.to_string()),
         }
-
-        let user = m.remove("User").map(|user| User { user });
-
-        Ok(NetworkEventData {
-            utc_time: UtcTime {
-                utc_time: get_or_err!(m, "UtcTime"),
-            },
-            process_guid: ProcessGuid {
-                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
-            },
-            process_id: get_or_err!(m, "ProcessId").parse()?,
-            image: Image {
-                image: get_or_err!(m, "Image"),
-            },
-            user,
-            protocol: get_or_err!(m, "Protocol"),
-            source_is_ipv6: get_or_err!(m, "SourceIsIpv6"),
-            source_ip: get_or_err!(m, "SourceIp"),
-            source_hostname: m.remove("SourceHostname"),
-            source_port_name: m.remove("SourcePortName"),
-            destination_is_ipv6: get_or_err!(m, "DestinationIsIpv6"),
-            destination_ip: get_or_err!(m, "DestinationIp"),
-            destination_hostname: m.remove("DestinationHostname"),
-            destination_port_name: m.remove("DestinationPortName"),
-            initiated: get_or_err!(m, "Initiated").parse()?,
-            source_port: get_or_err!(m, "SourcePort").parse()?,
-            destination_port: get_or_err!(m, "DestinationPort").parse()?,
-        })
     }
 }
 
-fn from_intermediary_data<'de, D, T>(deserializer: D) -> Result<T, D::Error>
-where
-    D: Deserializer<'de>,
-    T: TryFrom<IntermediaryEventData>,
-{
-    let s: IntermediaryEventData = Deserialize::deserialize(deserializer)?;
-    T::try_from(s).map_err(|_| SerdeError::custom("Failed to deserialize"))
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct DnsEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="QueryName">www.example.com</Data>
+    pub query_name: String,
+    /// <Data Name="QueryStatus">0</Data>
+    pub query_status: Option<String>,
+    /// <Data Name="QueryResults">type:  5 example.net;::ffff:93.184.216.34;</Data>
+    pub query_results: Option<String>,
+    /// <Data Name="Image">C:\Program Files\Mozilla Firefox\firefox.exe</Data>
+    pub image: Image,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
-pub struct Data {
-    #[serde(rename = "Name")]
-    pub name: String,
-    #[serde(rename = "$value")]
+pub struct DnsEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: DnsEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+/// Source fields use the names of the other events so the accessing process is handled like any other
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessAccessEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="SourceProcessGUID">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="SourceProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="SourceThreadId">4512</Data>
+    pub thread_id: Option<u64>,
+    /// <Data Name="SourceImage">C:\Users\rsmith\Downloads\procdump64.exe</Data>
+    pub image: Image,
+    /// <Data Name="SourceUser">LAB\rsmith</Data>
+    pub user: Option<User>,
+    /// <Data Name="TargetProcessGUID">{A23EAE89-BD28-5903-0000-00102F345D00}</Data>
+    pub target_process_guid: ProcessGuid,
+    /// <Data Name="TargetProcessId">636</Data>
+    pub target_process_id: u64,
+    /// <Data Name="TargetImage">C:\Windows\system32\lsass.exe</Data>
+    pub target_image: Image,
+    /// <Data Name="TargetUser">NT AUTHORITY\SYSTEM</Data>
+    pub target_user: Option<User>,
+    /// <Data Name="GrantedAccess">0x1FFFFF</Data>
+    pub granted_access: String,
+    /// <Data Name="CallTrace">C:\Windows\SYSTEM32\ntdll.dll+9d4c4|...</Data>
+    pub call_trace: Option<String>,
+}
+
+impl ProcessAccessEventData {
+    /// Access mask from the hexadecimal `GrantedAccess` field
+    pub fn granted_access_mask(&self) -> Option<u32> {
+        let access = self.granted_access.trim();
+        let hex = access
+            .strip_prefix("0x")
+            .or_else(|| access.strip_prefix("0X"))
+            .unwrap_or(access);
+        u32::from_str_radix(hex, 16).ok()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessAccessEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: ProcessAccessEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+/// Fields of the Sysmon service state (4) and configuration (16) change events.
+/// They carry no process fields: `process_guid` is nil, `process_id` 0 and `image` empty.
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct SysmonServiceEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="State">Stopped</Data> (state change)
+    pub state: Option<String>,
+    /// <Data Name="Version">15.0</Data> (state change)
+    pub version: Option<String>,
+    /// <Data Name="SchemaVersion">4.90</Data> (state change)
+    pub schema_version: Option<String>,
+    /// <Data Name="Configuration">C:\Windows\config.xml</Data> (configuration change)
+    pub configuration: Option<String>,
+    /// <Data Name="ConfigurationFileHash">SHA256=...</Data> (configuration change)
+    pub configuration_file_hash: Option<String>,
+    pub process_guid: ProcessGuid,
+    pub process_id: u64,
+    pub image: Image,
+}
+
+impl SysmonServiceEventData {
+    /// Whether the state change records the service stopping
+    pub fn is_stopped(&self) -> bool {
+        self.state
+            .as_deref()
+            .is_some_and(|state| state.trim().eq_ignore_ascii_case("stopped"))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct SysmonServiceEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: SysmonServiceEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+/// Driver loaded into the kernel. The event has no process fields:
+/// `process_guid` is nil, `process_id` 0 and `image` empty.
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct DriverLoadEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ImageLoaded">C:\Windows\System32\drivers\RTCore64.sys</Data>
+    pub image_loaded: Image,
+    /// <Data Name="Hashes">SHA256=01AA278B07B58DC46C84BD0B1B5C8E9EE4E62EA0BF7A695862444AF32E87F1FD</Data>
+    pub hashes: Option<Hashes>,
+    /// <Data Name="Signed">true</Data>
+    pub signed: Option<bool>,
+    /// <Data Name="Signature">Micro-Star International CO., LTD.</Data>
+    pub signature: Option<String>,
+    /// <Data Name="SignatureStatus">Valid</Data>
+    pub signature_status: Option<String>,
+    pub process_guid: ProcessGuid,
+    pub process_id: u64,
+    pub image: Image,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct DriverLoadEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: DriverLoadEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct RawAccessReadEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Users\rsmith\Downloads\ninjacopy.exe</Data>
+    pub image: Image,
+    /// <Data Name="Device">\Device\HarddiskVolume2</Data>
+    pub device: String,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct RawAccessReadEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: RawAccessReadEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+/// Text copied to the clipboard. The content itself is only kept in the Sysmon archive
+/// directory, the event references it by hash.
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ClipboardEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Windows\System32\rdpclip.exe</Data>
+    pub image: Image,
+    /// <Data Name="Session">2</Data>
+    pub session: Option<String>,
+    /// <Data Name="ClientInfo">user: LAB\rsmith ip: 10.0.0.5 hostname: WKS-042</Data>
+    pub client_info: Option<String>,
+    /// <Data Name="Hashes">SHA256=7F8A1D8C5B1E2C9D0E3F4A5B6C7D8E9F0A1B2C3D4E5F6A7B8C9D0E1F2A3B4C5D</Data>
+    pub hashes: Option<Hashes>,
+    /// <Data Name="Archived">true</Data>
+    pub archived: Option<bool>,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ClipboardEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: ClipboardEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessTerminateEventData {
+    /// <Data Name="UtcTime">2017-04-28 22:13:20.895</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Windows\System32\wbem\WmiPrvSE.exe</Data>
+    pub image: Image,
+    /// <Data Name="User">NT AUTHORITY\NETWORK SERVICE</Data>, since Sysmon 13.30
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessTerminateEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: ProcessTerminateEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessTamperingEventData {
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">6228</Data>
+    pub process_id: u64,
+    /// <Data Name="Image">C:\Users\rsmith\AppData\Local\Temp\setup.exe</Data>
+    pub image: Image,
+    /// <Data Name="Type">Image is replaced</Data>
+    pub tampering_type: String,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct ProcessTamperingEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: ProcessTamperingEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+/// Fields of the filter (19), consumer (20) and binding (21) events.
+/// WMI events carry no process fields: `process_guid` is nil, `process_id` 0 and `image` empty.
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct WmiEventData {
+    /// <Data Name="EventType">WmiConsumerEvent</Data>
+    pub event_type: String,
+    /// <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+    pub utc_time: UtcTime,
+    /// <Data Name="Operation">Created</Data>
+    pub operation: String,
+    /// <Data Name="User">LAB\rsmith</Data>
+    pub user: Option<User>,
+    /// <Data Name="EventNamespace">"root\\CimV2"</Data> (filter)
+    pub event_namespace: Option<String>,
+    /// <Data Name="Name">"Updater"</Data> (filter and consumer)
+    pub name: Option<String>,
+    /// <Data Name="Query">"SELECT * FROM __InstanceModificationEvent ..."</Data> (filter)
+    pub query: Option<String>,
+    /// <Data Name="Type">Command Line</Data> (consumer: `Command Line` or `Script`)
+    pub consumer_type: Option<String>,
+    /// <Data Name="Destination">"powershell.exe -enc ..."</Data> (consumer)
+    pub destination: Option<String>,
+    /// <Data Name="Consumer">"CommandLineEventConsumer.Name=\"Updater\""</Data> (binding)
+    pub consumer: Option<String>,
+    /// <Data Name="Filter">"__EventFilter.Name=\"Updater\""</Data> (binding)
+    pub filter: Option<String>,
+    pub process_guid: ProcessGuid,
+    pub process_id: u64,
+    pub image: Image,
+}
+
+impl WmiEventData {
+    /// Whether the event records the creation of a WMI object (rather than its deletion)
+    pub fn is_created(&self) -> bool {
+        self.operation.eq_ignore_ascii_case("created")
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct WmiEvent {
+    #[serde(rename = "System")]
+    pub system: System,
+    #[serde(rename = "EventData", deserialize_with = "from_intermediary_data")]
+    pub event_data: WmiEventData,
+    /// Every event data field, filled in by [`Event::from_str`]
+    #[serde(skip)]
+    pub fields: EventFields,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct LogonEventData {
+    pub utc_time: UtcTime,
+    /// Derived from the ID of the process that requested the logon
+    pub process_guid: ProcessGuid,
+    /// <Data Name="ProcessId">0x2d4</Data>
+    pub process_id: u64,
+    /// <Data Name="ProcessName">C:\Windows\System32\lsass.exe</Data>
+    pub image: Image,
+    /// <Data Name="TargetDomainName">LAB</Data> and <Data Name="TargetUserName">rsmith</Data>
+    pub target_user: User,
+    /// <Data Name="TargetUserSid">S-1-5-21-...</Data>
+    pub target_user_sid: String,
+    /// <Data Name="TargetLogonId">0x7eb05</Data>
+    pub target_logon_id: LogonId,
+    /// <Data Name="LogonType">3</Data>
+    pub logon_type: u32,
+    /// <Data Name="LogonProcessName">NtLmSsp</Data>
+    pub logon_process: Option<String>,
+    /// <Data Name="AuthenticationPackageName">NTLM</Data>
+    pub authentication_package: Option<String>,
+    /// <Data Name="WorkstationName">WS01</Data>
+    pub workstation: Option<String>,
+    /// <Data Name="IpAddress">192.168.1.20</Data>
+    pub ip_address: Option<String>,
+    /// <Data Name="IpPort">49822</Data>
+    pub ip_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct LogonEvent {
+    pub system: System,
+    pub event_data: LogonEventData,
+    pub fields: EventFields,
+}
+
+/// Fields of a PowerShell script block. Scripts longer than one event are split into parts that
+/// share the script block ID.
+#[derive(Debug, Clone, Hash)]
+pub struct ScriptBlockEventData {
+    pub utc_time: UtcTime,
+    /// GUID of the PowerShell process once correlated with its process creation, derived from
+    /// the process ID until then
+    pub process_guid: ProcessGuid,
+    /// <Execution ProcessID="4242" /> of the event
+    pub process_id: u64,
+    /// Image of the PowerShell process, empty until correlated with its process creation
+    pub image: Image,
+    /// <Data Name="ScriptBlockId">9c2a1a4e-0e6f-4f0a-9a37-1c7b0e1f4d2b</Data>
+    pub script_block_id: String,
+    /// <Data Name="MessageNumber">1</Data>
+    pub message_number: u32,
+    /// <Data Name="MessageTotal">1</Data>
+    pub message_total: u32,
+    /// <Data Name="ScriptBlockText">Write-Host hello</Data>
+    pub text: String,
+    /// <Data Name="Path">C:\Scripts\setup.ps1</Data>, missing for interactive commands
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct ScriptBlockEvent {
+    pub system: System,
+    pub event_data: ScriptBlockEventData,
+    pub fields: EventFields,
+}
+
+impl TryFrom<IntermediaryEventData> for ProcessCreateEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        let process_id = get_or_err!(m, "ProcessId");
+        let process_id: u64 = parse_number(&process_id)?;
+
+        let parent_process_id = get_or_err!(m, "ParentProcessId");
+        let parent_process_id: u64 = parse_number(&parent_process_id)?;
+
+        Ok(ProcessCreateEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            command_line: CommandLine {
+                command_line: get_or_err!(m, "CommandLine"),
+            },
+            current_directory: CurrentDirectory {
+                current_directory: get_or_err!(m, "CurrentDirectory"),
+            },
+            user: User {
+                user: get_or_err!(m, "User"),
+            },
+            logon_guid: LogonGuid {
+                logon_guid: uuid::Uuid::parse_str(&get_or_err!(m, "LogonGuid"))?,
+            },
+            logon_id: LogonId {
+                logon_id: get_or_err!(m, "LogonId"),
+            },
+            terminal_session_id: TerminalSessionId {
+                terminal_session_id: get_or_err!(m, "TerminalSessionId"),
+            },
+            integrity_level: IntegrityLevel {
+                integrity_level: get_or_err!(m, "IntegrityLevel"),
+            },
+            hashes: Hashes {
+                hashes: get_or_err!(m, "Hashes"),
+            },
+            parent_process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ParentProcessGuid"))?,
+            },
+            parent_process_id,
+            parent_image: Image {
+                image: get_or_err!(m, "ParentImage"),
+            },
+            parent_command_line: CommandLine {
+                command_line: get_or_err!(m, "ParentCommandLine"),
+            },
+            signed: m.remove("Signed").and_then(|v| parse_flag(&v).ok()),
+            signature: m.remove("Signature"),
+            signature_status: m.remove("SignatureStatus"),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for FileCreateEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        let process_id = get_or_err!(m, "ProcessId");
+        let process_id = parse_number(&process_id)?;
+
+        Ok(FileCreateEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            creation_utc_time: UtcTime {
+                utc_time: get_or_err!(m, "CreationUtcTime"),
+            },
+            target_filename: get_or_err!(m, "TargetFilename"),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for FileStreamEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(FileStreamEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            target_filename: get_or_err!(m, "TargetFilename"),
+            creation_utc_time: m
+                .remove("CreationUtcTime")
+                .map(|utc_time| UtcTime { utc_time }),
+            hash: m.remove("Hash").map(|hashes| Hashes { hashes }),
+            contents: m.remove("Contents").filter(|contents| contents != "-"),
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for NetworkEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        let user = m.remove("User").map(|user| User { user });
+
+        Ok(NetworkEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            user,
+            protocol: get_or_err!(m, "Protocol"),
+            source_is_ipv6: get_or_err!(m, "SourceIsIpv6"),
+            source_ip: get_or_err!(m, "SourceIp"),
+            source_hostname: m.remove("SourceHostname"),
+            source_port_name: m.remove("SourcePortName"),
+            destination_is_ipv6: get_or_err!(m, "DestinationIsIpv6"),
+            destination_ip: get_or_err!(m, "DestinationIp"),
+            destination_hostname: m.remove("DestinationHostname"),
+            destination_port_name: m.remove("DestinationPortName"),
+            initiated: parse_flag(&get_or_err!(m, "Initiated"))?,
+            source_port: parse_number(&get_or_err!(m, "SourcePort"))?,
+            destination_port: parse_number(&get_or_err!(m, "DestinationPort"))?,
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for FileDeleteEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(FileDeleteEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            user: m.remove("User").map(|user| User { user }),
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            target_filename: get_or_err!(m, "TargetFilename"),
+            hashes: m.remove("Hashes").map(|hashes| Hashes { hashes }),
+            is_executable: m.remove("IsExecutable").and_then(|v| parse_flag(&v).ok()),
+            archived: m.remove("Archived").and_then(|v| parse_flag(&v).ok()),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for PipeEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(PipeEventData {
+            event_type: m.remove("EventType"),
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            pipe_name: get_or_err!(m, "PipeName"),
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for RegistryEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(RegistryEventData {
+            event_type: m.remove("EventType"),
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            target_object: get_or_err!(m, "TargetObject"),
+            // Sysmon leaves out empty values
+            details: m.remove("Details").unwrap_or_default(),
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for DnsEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(DnsEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            query_name: get_or_err!(m, "QueryName"),
+            query_status: m.remove("QueryStatus"),
+            query_results: m.remove("QueryResults"),
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for ProcessAccessEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(ProcessAccessEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "SourceProcessGUID"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "SourceProcessId"))?,
+            thread_id: m
+                .remove("SourceThreadId")
+                .and_then(|v| parse_number(&v).ok()),
+            image: Image {
+                image: get_or_err!(m, "SourceImage"),
+            },
+            user: m.remove("SourceUser").map(|user| User { user }),
+            target_process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "TargetProcessGUID"))?,
+            },
+            target_process_id: parse_number(&get_or_err!(m, "TargetProcessId"))?,
+            target_image: Image {
+                image: get_or_err!(m, "TargetImage"),
+            },
+            target_user: m.remove("TargetUser").map(|user| User { user }),
+            granted_access: get_or_err!(m, "GrantedAccess"),
+            call_trace: m.remove("CallTrace"),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for SysmonServiceEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(SysmonServiceEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            state: m.remove("State"),
+            version: m.remove("Version"),
+            schema_version: m.remove("SchemaVersion"),
+            configuration: m.remove("Configuration"),
+            configuration_file_hash: m.remove("ConfigurationFileHash"),
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::nil(),
+            },
+            process_id: 0,
+            image: Image {
+                image: String::new(),
+            },
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for DriverLoadEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(DriverLoadEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            image_loaded: Image {
+                image: get_or_err!(m, "ImageLoaded"),
+            },
+            hashes: m.remove("Hashes").map(|hashes| Hashes { hashes }),
+            signed: m.remove("Signed").and_then(|v| parse_flag(&v).ok()),
+            signature: m.remove("Signature"),
+            signature_status: m.remove("SignatureStatus"),
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::nil(),
+            },
+            process_id: 0,
+            image: Image {
+                image: String::new(),
+            },
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for RawAccessReadEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(RawAccessReadEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            device: get_or_err!(m, "Device"),
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for ClipboardEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(ClipboardEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            session: m.remove("Session"),
+            client_info: m.remove("ClientInfo"),
+            hashes: m.remove("Hashes").map(|hashes| Hashes { hashes }),
+            archived: m.remove("Archived").and_then(|v| parse_flag(&v).ok()),
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for ProcessTerminateEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(ProcessTerminateEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for ProcessTamperingEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(ProcessTamperingEventData {
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::parse_str(&get_or_err!(m, "ProcessGuid"))?,
+            },
+            process_id: parse_number(&get_or_err!(m, "ProcessId"))?,
+            image: Image {
+                image: get_or_err!(m, "Image"),
+            },
+            tampering_type: get_or_err!(m, "Type"),
+            user: m.remove("User").map(|user| User { user }),
+        })
+    }
+}
+
+impl TryFrom<IntermediaryEventData> for WmiEventData {
+    type Error = anyhow::Error;
+
+    fn try_from(inter: IntermediaryEventData) -> Result<Self> {
+        let mut m = HashMap::with_capacity(inter.data.len());
+
+        for data in inter.data {
+            if let Some(value) = data.value {
+                m.insert(data.name, value);
+            }
+        }
+
+        Ok(WmiEventData {
+            event_type: get_or_err!(m, "EventType"),
+            utc_time: UtcTime {
+                utc_time: get_or_err!(m, "UtcTime"),
+            },
+            operation: get_or_err!(m, "Operation"),
+            user: m.remove("User").map(|user| User { user }),
+            event_namespace: m.remove("EventNamespace"),
+            name: m.remove("Name"),
+            query: m.remove("Query"),
+            consumer_type: m.remove("Type"),
+            destination: m.remove("Destination"),
+            consumer: m.remove("Consumer"),
+            filter: m.remove("Filter"),
+            process_guid: ProcessGuid {
+                process_guid: uuid::Uuid::nil(),
+            },
+            process_id: 0,
+            image: Image {
+                image: String::new(),
+            },
+        })
+    }
+}
+
+impl DnsEventData {
+    /// Addresses returned by the query, without CNAME records (`type: 5 ...`)
+    pub fn resolved_addresses(&self) -> impl Iterator<Item = &str> {
+        self.query_results
+            .as_deref()
+            .unwrap_or_default()
+            .split(';')
+            .map(|result| result.trim().trim_start_matches("::ffff:"))
+            .filter(|result| !result.is_empty() && !result.starts_with("type:"))
+    }
+}
+
+fn from_intermediary_data<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<IntermediaryEventData, Error = anyhow::Error>,
+{
+    let s: IntermediaryEventData = Deserialize::deserialize(deserializer)?;
+    T::try_from(s).map_err(|e| SerdeError::custom(format!("{e:#}")))
+}
+
+#[derive(Debug, Deserialize, Clone, Hash)]
+pub struct Data {
+    /// Missing in some exports, see [`sysmon_schema::name_data_elements`]
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "$value")]
     pub value: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash)]
 pub struct IntermediaryEventData {
-    #[serde(rename = "Data")]
+    #[serde(rename = "Data", default)]
     pub data: Vec<Data>,
 }
 
+/// Name and value of every `<Data>` element of an event, in document order. Gives access to
+/// fields the typed event data does not keep, such as hashes, GUIDs and registry paths.
+#[derive(Debug, Default, Clone, Hash)]
+pub struct EventFields(Vec<(String, String)>);
+
+impl EventFields {
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Value of the named field
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl From<&IntermediaryEventData> for EventFields {
+    fn from(data: &IntermediaryEventData) -> Self {
+        Self(
+            data.data
+                .iter()
+                .filter_map(|data| Some((data.name.clone(), data.value.clone()?)))
+                .collect(),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::HasSystem;
 
     const NETWORK_EVENT: &str = r#"
     <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
@@ -737,6 +2064,69 @@ mod tests {
     </Event>
     "#;
 
+    const FILE_DELETE: &str = r#"
+        <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+        <System>
+            <Provider Name="Microsoft-Windows-Sysmon" Guid="{5770385F-C22A-43E0-BF4C-06F5698FFBD9}" />
+            <EventID>26</EventID>
+            <Version>5</Version>
+            <Level>4</Level>
+            <Task>26</Task>
+            <Opcode>0</Opcode>
+            <Keywords>0x8000000000000000</Keywords>
+            <TimeCreated SystemTime="2021-04-21T11:24:37.514218100Z" />
+            <EventRecordID>1893</EventRecordID>
+            <Correlation />
+            <Execution ProcessID="3216" ThreadID="4052" />
+            <Channel>Microsoft-Windows-Sysmon/Operational</Channel>
+            <Computer>rfsH.lab.local</Computer>
+            <Security UserID="S-1-5-18" />
+        </System>
+        <EventData>
+            <Data Name="RuleName">-</Data>
+            <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+            <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+            <Data Name="ProcessId">6228</Data>
+            <Data Name="User">LAB\rsmith</Data>
+            <Data Name="Image">C:\Windows\System32\cmd.exe</Data>
+            <Data Name="TargetFilename">C:\Users\rsmith\Documents\report.docx</Data>
+            <Data Name="Hashes">SHA256=6055A20CF7EC81843310AD37700FF67B2CF8CDE3DCE68D54BA42934177C10B57</Data>
+            <Data Name="IsExecutable">false</Data>
+        </EventData>
+        </Event>
+    "#;
+
+    const PIPE_CREATED: &str = r#"
+        <Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+        <System>
+            <Provider Name="Microsoft-Windows-Sysmon" Guid="{5770385F-C22A-43E0-BF4C-06F5698FFBD9}" />
+            <EventID>17</EventID>
+            <Version>1</Version>
+            <Level>4</Level>
+            <Task>17</Task>
+            <Opcode>0</Opcode>
+            <Keywords>0x8000000000000000</Keywords>
+            <TimeCreated SystemTime="2021-04-21T11:24:37.514218100Z" />
+            <EventRecordID>1894</EventRecordID>
+            <Correlation />
+            <Execution ProcessID="3216" ThreadID="4052" />
+            <Channel>Microsoft-Windows-Sysmon/Operational</Channel>
+            <Computer>rfsH.lab.local</Computer>
+            <Security UserID="S-1-5-18" />
+        </System>
+        <EventData>
+            <Data Name="RuleName">-</Data>
+            <Data Name="EventType">CreatePipe</Data>
+            <Data Name="UtcTime">2021-04-21 11:24:37.513</Data>
+            <Data Name="ProcessGuid">{A23EAE89-BD56-5903-0000-0010E9D95E00}</Data>
+            <Data Name="ProcessId">4120</Data>
+            <Data Name="PipeName">\PSEXESVC</Data>
+            <Data Name="Image">C:\Windows\PSEXESVC.exe</Data>
+            <Data Name="User">NT AUTHORITY\SYSTEM</Data>
+        </EventData>
+        </Event>
+    "#;
+
     const HEADER: &str = r#"
         <System>
             <Provider Name="Microsoft-Windows-Sysmon" Guid="{5770385F-C22A-43E0-BF4C-06F5698FFBD9}" />
@@ -785,5 +2175,87 @@ mod tests {
         );
         assert!(Event::from_str(FILE_CREATE).unwrap().is_file_create());
         assert!(Event::from_str(PROCESS_CREATE).unwrap().is_process_create());
+        assert!(Event::from_str(FILE_DELETE).unwrap().is_file_delete());
+        assert!(
+            Event::from_str(FILE_DELETE.replace("<EventID>26</EventID>", "<EventID>27</EventID>"))
+                .unwrap()
+                .is_file_block()
+        );
+    }
+
+    #[test]
+    fn supported_event_ids() {
+        for id in 0..=30 {
+            let xml = NETWORK_EVENT.replace("<EventID>3<", &format!("<EventID>{id}<"));
+            let unsupported = Event::from_str(xml)
+                .is_err_and(|e| e.to_string().starts_with("Unsupported Sysmon event ID"));
+            assert_eq!(unsupported, !SUPPORTED_EVENT_IDS.contains(&id), "{id}");
+        }
+    }
+
+    #[test]
+    fn file_delete_event() {
+        let event = serde_xml_rs::from_str::<FileDeleteEvent>(FILE_DELETE).unwrap();
+        assert_eq!(event.event_data.process_id, 6228);
+        assert_eq!(event.event_data.is_executable, Some(false));
+        assert!(event.event_data.archived.is_none());
+    }
+
+    #[test]
+    fn registry_values() {
+        let xml = PIPE_CREATED
+            .replace("<EventID>17</EventID>", "<EventID>13</EventID>")
+            .replace(
+                r#"<Data Name="PipeName">\PSEXESVC</Data>"#,
+                r#"<Data Name="TargetObject">HKLM\System\CurrentControlSet\Services\WinDefend\Start</Data><Data Name="Details">DWORD (0x00000004)</Data>"#,
+            );
+        let Event::RegistrySetValue(event) = Event::from_str(xml).unwrap() else {
+            panic!("not a registry event");
+        };
+        assert_eq!(event.event_data.value(), RegistryValue::Dword(4));
+        assert_eq!(event.event_data.value_name(), "Start");
+        assert_eq!(
+            RegistryValue::parse("QWORD (0x00000001-0x00000002)"),
+            RegistryValue::Qword(0x1_0000_0002)
+        );
+        assert_eq!(RegistryValue::parse("Binary Data"), RegistryValue::Binary);
+        assert_eq!(RegistryValue::parse("(Empty)"), RegistryValue::Empty);
+        assert_eq!(
+            RegistryValue::parse("DWORD (1)"),
+            RegistryValue::String("DWORD (1)".to_string())
+        );
+    }
+
+    #[test]
+    fn pipe_event() {
+        let event = serde_xml_rs::from_str::<PipeEvent>(PIPE_CREATED).unwrap();
+        assert_eq!(event.event_data.pipe_name, r"\PSEXESVC");
+        assert_eq!(event.event_data.event_type.as_deref(), Some("CreatePipe"));
+        assert!(Event::from_str(PIPE_CREATED).unwrap().is_pipe_created());
+        let connected = PIPE_CREATED.replace("<EventID>17</EventID>", "<EventID>18</EventID>");
+        assert!(Event::from_str(connected).unwrap().is_pipe_connected());
+    }
+
+    #[test]
+    fn timestamps_are_normalized() {
+        let event = Event::from_str(PROCESS_CREATE).unwrap();
+        let time = &event.system().time_created;
+        assert_eq!(time.to_rfc3339(), "2017-04-28T22:08:22.025812200Z");
+        // Without a usable SystemTime the UtcTime of the event data is used
+        let xml = PROCESS_CREATE.replace("2017-04-28T22:08:22.025812200Z", "invalid");
+        let event = Event::from_str(xml).unwrap();
+        let time = &event.system().time_created;
+        assert_eq!(time.system_time, "invalid");
+        assert_eq!(time.to_rfc3339(), "2017-04-28T22:08:22.025Z");
+        assert_eq!(
+            parse_event_time("2017-04-28 22:08:22"),
+            "2017-04-28T22:08:22Z".parse().ok()
+        );
+    }
+
+    #[test]
+    fn unsupported_event_id() {
+        let xml = PROCESS_CREATE.replace("<EventID>1</EventID>", "<EventID>250</EventID>");
+        assert!(Event::from_str(xml).is_err());
     }
 }
@@ -0,0 +1,511 @@
+//! Sysmon event schemas across versions, to read events rendered without `Name` attributes on
+//! their `<Data>` elements (as some exports of older logs are) and values rendered in the format
+//! of the exporting machine's locale
+use anyhow::{Result, anyhow};
+use std::borrow::Cow;
+
+/// Data fields of an event, in the order of the template of the Sysmon manifest. `version` is
+/// the `<Version>` of the event, several templates share one when Sysmon appended fields to it.
+struct Template {
+    event_id: u16,
+    version: u8,
+    fields: &'static [&'static str],
+}
+
+const TEMPLATES: &[Template] = &[
+    // Sysmon 6 and 7, without rule names nor the original file name
+    Template {
+        event_id: 1,
+        version: 3,
+        fields: &[
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "FileVersion",
+            "Description",
+            "Product",
+            "Company",
+            "CommandLine",
+            "CurrentDirectory",
+            "User",
+            "LogonGuid",
+            "LogonId",
+            "TerminalSessionId",
+            "IntegrityLevel",
+            "Hashes",
+            "ParentProcessGuid",
+            "ParentProcessId",
+            "ParentImage",
+            "ParentCommandLine",
+        ],
+    },
+    // Sysmon 8 and 9
+    Template {
+        event_id: 1,
+        version: 4,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "FileVersion",
+            "Description",
+            "Product",
+            "Company",
+            "CommandLine",
+            "CurrentDirectory",
+            "User",
+            "LogonGuid",
+            "LogonId",
+            "TerminalSessionId",
+            "IntegrityLevel",
+            "Hashes",
+            "ParentProcessGuid",
+            "ParentProcessId",
+            "ParentImage",
+            "ParentCommandLine",
+        ],
+    },
+    // Sysmon 10 and later, `ParentUser` since 13.30
+    Template {
+        event_id: 1,
+        version: 5,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "FileVersion",
+            "Description",
+            "Product",
+            "Company",
+            "OriginalFileName",
+            "CommandLine",
+            "CurrentDirectory",
+            "User",
+            "LogonGuid",
+            "LogonId",
+            "TerminalSessionId",
+            "IntegrityLevel",
+            "Hashes",
+            "ParentProcessGuid",
+            "ParentProcessId",
+            "ParentImage",
+            "ParentCommandLine",
+            "ParentUser",
+        ],
+    },
+    Template {
+        event_id: 2,
+        version: 4,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "TargetFilename",
+            "CreationUtcTime",
+            "PreviousCreationUtcTime",
+            "User",
+        ],
+    },
+    // Before Sysmon 8
+    Template {
+        event_id: 3,
+        version: 4,
+        fields: &[
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "User",
+            "Protocol",
+            "Initiated",
+            "SourceIsIpv6",
+            "SourceIp",
+            "SourceHostname",
+            "SourcePort",
+            "SourcePortName",
+            "DestinationIsIpv6",
+            "DestinationIp",
+            "DestinationHostname",
+            "DestinationPort",
+            "DestinationPortName",
+        ],
+    },
+    Template {
+        event_id: 3,
+        version: 5,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "User",
+            "Protocol",
+            "Initiated",
+            "SourceIsIpv6",
+            "SourceIp",
+            "SourceHostname",
+            "SourcePort",
+            "SourcePortName",
+            "DestinationIsIpv6",
+            "DestinationIp",
+            "DestinationHostname",
+            "DestinationPort",
+            "DestinationPortName",
+        ],
+    },
+    Template {
+        event_id: 6,
+        version: 3,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ImageLoaded",
+            "Hashes",
+            "Signed",
+            "Signature",
+            "SignatureStatus",
+        ],
+    },
+    Template {
+        event_id: 10,
+        version: 3,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "SourceProcessGUID",
+            "SourceProcessId",
+            "SourceThreadId",
+            "SourceImage",
+            "TargetProcessGUID",
+            "TargetProcessId",
+            "TargetImage",
+            "GrantedAccess",
+            "CallTrace",
+            "SourceUser",
+            "TargetUser",
+        ],
+    },
+    // Before Sysmon 8
+    Template {
+        event_id: 11,
+        version: 1,
+        fields: &[
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "TargetFilename",
+            "CreationUtcTime",
+        ],
+    },
+    // `User` since Sysmon 15
+    Template {
+        event_id: 11,
+        version: 2,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "TargetFilename",
+            "CreationUtcTime",
+            "User",
+        ],
+    },
+    Template {
+        event_id: 13,
+        version: 2,
+        fields: &[
+            "RuleName",
+            "EventType",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "TargetObject",
+            "Details",
+        ],
+    },
+    // `User` since Sysmon 15
+    Template {
+        event_id: 13,
+        version: 2,
+        fields: &[
+            "RuleName",
+            "EventType",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "Image",
+            "TargetObject",
+            "Details",
+            "User",
+        ],
+    },
+    // Sysmon 8 and 9, without the event type
+    Template {
+        event_id: 17,
+        version: 1,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "PipeName",
+            "Image",
+        ],
+    },
+    Template {
+        event_id: 17,
+        version: 1,
+        fields: &[
+            "RuleName",
+            "EventType",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "PipeName",
+            "Image",
+            "User",
+        ],
+    },
+    Template {
+        event_id: 18,
+        version: 1,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "PipeName",
+            "Image",
+        ],
+    },
+    Template {
+        event_id: 18,
+        version: 1,
+        fields: &[
+            "RuleName",
+            "EventType",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "PipeName",
+            "Image",
+            "User",
+        ],
+    },
+    // `User` since Sysmon 15
+    Template {
+        event_id: 22,
+        version: 5,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "QueryName",
+            "QueryStatus",
+            "QueryResults",
+            "Image",
+            "User",
+        ],
+    },
+    Template {
+        event_id: 23,
+        version: 5,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "User",
+            "Image",
+            "TargetFilename",
+            "Hashes",
+            "IsExecutable",
+            "Archived",
+        ],
+    },
+    Template {
+        event_id: 29,
+        version: 5,
+        fields: &[
+            "RuleName",
+            "UtcTime",
+            "ProcessGuid",
+            "ProcessId",
+            "User",
+            "Image",
+            "TargetFilename",
+            "Hashes",
+        ],
+    },
+];
+
+/// Field names of the `count` data values of an event. The template with exactly `count` fields
+/// wins, preferring the event's version; otherwise the latest template not newer than the
+/// event, whose trailing fields a shorter event lacks. `None` for events without a template.
+fn field_names(event_id: u16, version: u8, count: usize) -> Option<&'static [&'static str]> {
+    let templates = || TEMPLATES.iter().filter(|t| t.event_id == event_id);
+    templates()
+        .filter(|t| t.fields.len() == count)
+        .max_by_key(|t| (t.version == version, t.version))
+        .or_else(|| {
+            templates()
+                .filter(|t| t.version <= version)
+                .max_by_key(|t| t.version)
+        })
+        .or_else(|| templates().next_back())
+        .map(|t| t.fields)
+}
+
+/// `xml` with the `<Data>` elements lacking a `Name` attribute named after their position in
+/// the template of the event; values past the template are named `Data<N>`. Borrowed unchanged
+/// when every element is named or the event has no template.
+pub fn name_data_elements<'a>(xml: &'a str, event_id: u16, version: &str) -> Cow<'a, str> {
+    let tags: Vec<(usize, bool)> = xml
+        .match_indices("<Data")
+        .filter_map(|(start, _)| {
+            let rest = &xml[start + "<Data".len()..];
+            if !rest.starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+                return None;
+            }
+            let tag = &rest[..rest.find('>')?];
+            Some((start + "<Data".len(), tag.contains("Name=")))
+        })
+        .collect();
+    if tags.iter().all(|(_, named)| *named) {
+        return Cow::Borrowed(xml);
+    }
+    let version = version.trim().parse().unwrap_or(u8::MAX);
+    let Some(names) = field_names(event_id, version, tags.len()) else {
+        return Cow::Borrowed(xml);
+    };
+    let mut named = String::with_capacity(xml.len() + tags.len() * 24);
+    let mut copied = 0;
+    for (position, (offset, has_name)) in tags.into_iter().enumerate() {
+        if has_name {
+            continue;
+        }
+        named.push_str(&xml[copied..offset]);
+        match names.get(position) {
+            Some(name) => named.push_str(&format!(" Name=\"{name}\"")),
+            None => named.push_str(&format!(" Name=\"Data{position}\"")),
+        }
+        copied = offset;
+    }
+    named.push_str(&xml[copied..]);
+    Cow::Owned(named)
+}
+
+/// Integer written in decimal, with or without the digit grouping of a locale (`1,234`,
+/// `1.234`, `1 234`), or in hexadecimal with a `0x` prefix
+pub fn parse_number<T: TryFrom<u64>>(value: &str) -> Result<T> {
+    let value = value.trim();
+    let number = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => {
+            let digits: String = value
+                .chars()
+                .filter(|c| !matches!(c, ',' | '.' | '\'' | ' ' | '\u{a0}' | '\u{202f}'))
+                .collect();
+            (!digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+                .then(|| digits.parse().ok())
+                .flatten()
+        }
+    };
+    number
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| anyhow!("Invalid number: {value}"))
+}
+
+/// Boolean written as `true`/`false` in any case, or as `1`/`0`
+pub fn parse_flag(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(anyhow!("Invalid boolean: {value}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysmon::Event;
+
+    #[test]
+    fn events_without_data_names_or_with_localized_values() {
+        let event = r#"<Event xmlns="http://schemas.microsoft.com/win/2004/08/events/event">
+            <System>
+                <Provider Name="Microsoft-Windows-Sysmon" Guid="{5770385F-C22A-43E0-BF4C-06F5698FFBD9}" />
+                <EventID>3</EventID>
+                <Version>5</Version>
+                <Level>4</Level>
+                <Task>3</Task>
+                <Opcode>0</Opcode>
+                <Keywords>0x8000000000000000</Keywords>
+                <TimeCreated SystemTime="2019-03-18T16:57:38.016Z" />
+                <EventRecordID>1234</EventRecordID>
+                <Correlation />
+                <Execution ProcessID="1948" ThreadID="2680" />
+                <Channel>Microsoft-Windows-Sysmon/Operational</Channel>
+                <Computer>ws1.lab.local</Computer>
+                <Security UserID="S-1-5-18" />
+            </System>
+            <EventData>
+                <Data>-</Data>
+                <Data>2019-03-18 16:57:36.904</Data>
+                <Data>{A23EAE89-BD28-5903-0000-00102F345D00}</Data>
+                <Data>0x1068</Data>
+                <Data>C:\Windows\System32\rundll32.exe</Data>
+                <Data>LAB\bob</Data>
+                <Data>tcp</Data>
+                <Data>True</Data>
+                <Data>False</Data>
+                <Data>10.0.0.5</Data>
+                <Data />
+                <Data>49 733</Data>
+                <Data />
+                <Data>False</Data>
+                <Data>203.0.113.7</Data>
+                <Data />
+                <Data>4,444</Data>
+                <Data />
+            </EventData>
+        </Event>"#;
+        let Event::OutboundNetwork(network) = Event::from_str(event).unwrap() else {
+            panic!("not an outbound connection");
+        };
+        assert_eq!(network.event_data.process_id, 0x1068);
+        assert_eq!(network.event_data.source_port, 49733);
+        assert_eq!(network.event_data.destination_port, 4444);
+        assert_eq!(network.event_data.destination_ip, "203.0.113.7");
+        assert_eq!(network.fields.get("RuleName"), Some("-"));
+
+        // Named elements are left alone
+        let named = r#"<EventData><Data Name="UtcTime">x</Data></EventData>"#;
+        assert!(matches!(
+            name_data_elements(named, 3, "5"),
+            Cow::Borrowed(_)
+        ));
+        assert!(parse_number::<u16>("70000").is_err());
+        let time = |s| crate::sysmon::parse_event_time(s).map(|t| t.to_rfc3339());
+        let expected = Some("2019-03-18T16:57:36.904+00:00".to_string());
+        assert_eq!(time("3/18/2019 4:57:36.904 PM"), expected);
+        assert_eq!(time("18.03.2019 16:57:36.904"), expected);
+        assert!(parse_number::<u64>("12a").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Line-oriented event output shaped by a format string such as `{time} {event_id} {image}`,
+//! for scripts that do not want to parse JSON
+use crate::display::{format_event_details, format_timestamp};
+use crate::helpers::{HasProcess, HasSystem};
+use crate::sysmon::Event as SysmonEvent;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    /// Placeholder name, lowercase
+    Value(String),
+}
+
+/// Parsed format string. `{name}` is replaced by a value of the event: `time`, `event_id`,
+/// `event` (type name), `computer`, `record_id`, `pid`, `guid`, `image`, `process` (image file
+/// name), `cmdline`, `user`, `parent` (parent image), `details` or any event data field
+/// (`{TargetFilename}`, `{DestinationPort}`), empty when the event has none. `{{` and `}}` are
+/// literal braces, `\t` and `\n` a tab and a line break.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventTemplate {
+    parts: Vec<Part>,
+}
+
+impl EventTemplate {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '\\' if chars.next_if_eq(&'t').is_some() => literal.push('\t'),
+                '\\' if chars.next_if_eq(&'n').is_some() => literal.push('\n'),
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("unclosed placeholder '{{{name}'"));
+                    }
+                    let name = name.trim();
+                    if name.is_empty() || name.contains('{') {
+                        return Err(format!("invalid placeholder '{{{name}}}'"));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Value(name.to_lowercase()));
+                }
+                '}' => return Err("unmatched '}' (use '}}' for a literal brace)".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if !parts.iter().any(|part| matches!(part, Part::Value(_))) {
+            return Err("the format string has no {placeholder}".to_string());
+        }
+        Ok(Self { parts })
+    }
+
+    /// The event as one line. Line breaks and tabs inside values become spaces.
+    pub fn render(&self, event: &SysmonEvent) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Value(name) => line.extend(value(event, name).chars().map(|c| {
+                    if matches!(c, '\r' | '\n' | '\t') {
+                        ' '
+                    } else {
+                        c
+                    }
+                })),
+            }
+        }
+        line
+    }
+}
+
+fn value(event: &SysmonEvent, name: &str) -> String {
+    let system = event.system();
+    let field = |field: &str| {
+        event
+            .fields()
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(field))
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default()
+    };
+    match name {
+        "time" => format_timestamp(&system.time_created),
+        "event_id" => system.event_id.event_id.to_string(),
+        "event" => event.name().to_string(),
+        "computer" => system.computer.computer.clone(),
+        "record_id" => system.event_record_id.event_record_id.to_string(),
+        "pid" => event.process_id().to_string(),
+        "guid" => event.process_guid().to_string(),
+        "image" => event.image().to_string(),
+        "process" => event.process_name().to_string(),
+        "cmdline" => field("CommandLine"),
+        "user" => field("User"),
+        "parent" => field("ParentImage"),
+        "details" => format_event_details(event),
+        name => field(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn placeholders_fields_and_escapes() {
+        let event = json_process_event(json!({
+            "CommandLine": "cmd.exe /c\r\nwhoami",
+            "ParentImage": r"C:\Windows\explorer.exe",
+            "ParentCommandLine": "explorer.exe",
+        }));
+        let template =
+            EventTemplate::parse(r"{event_id}\t{process}\t{pid}\t{cmdline}\t{integritylevel}")
+                .unwrap();
+        assert_eq!(
+            template.render(&event),
+            "1\tcmd.exe\t42\tcmd.exe /c  whoami\tMedium"
+        );
+        let template = EventTemplate::parse("{{{computer}}} {user} {TargetFilename}|").unwrap();
+        assert_eq!(template.render(&event), r"{WS1} WS1\Bob |");
+        assert!(EventTemplate::parse("no placeholder").is_err());
+        assert!(EventTemplate::parse("{image").is_err());
+        assert!(EventTemplate::parse("{image}}").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Timeline in the JSON Lines format Timesketch imports (`message`, `datetime`, `timestamp_desc`
+//! and flat extra attributes), to merge Sysmon events into broader forensic timelines
+use crate::analyzer::Anomaly;
+use crate::display::format_event_details;
+use crate::export::event_to_json;
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value, json};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Meaning of the time of every row, the time the event was logged
+const TIMESTAMP_DESC: &str = "Event Logged";
+
+/// Required fields of a row, with `timestamp` in microseconds since the epoch as Plaso writes it
+fn row(time: DateTime<Utc>, message: String, data_type: &str) -> Map<String, Value> {
+    let mut row = Map::new();
+    row.insert("message".to_string(), json!(message));
+    row.insert(
+        "datetime".to_string(),
+        json!(time.to_rfc3339_opts(SecondsFormat::Micros, true)),
+    );
+    row.insert("timestamp".to_string(), json!(time.timestamp_micros()));
+    row.insert("timestamp_desc".to_string(), json!(TIMESTAMP_DESC));
+    row.insert("data_type".to_string(), json!(data_type));
+    row
+}
+
+/// Row of an event, with its fields as attributes; `None` if the event has no time
+pub fn event_row(event: &SysmonEvent) -> Option<Value> {
+    let time = event.system().time_created.time?;
+    let details = format_event_details(event);
+    let message = if details.is_empty() {
+        event.name().to_string()
+    } else {
+        format!("{}: {details}", event.name())
+    };
+    let mut row = row(time, message, "windows:evtx:sysmon");
+    if let Value::Object(fields) = event_to_json(event) {
+        // The time is already in the required fields, `kind` only tells events and anomalies apart
+        row.extend(
+            fields
+                .into_iter()
+                .filter(|(name, value)| !value.is_null() && name != "timestamp" && name != "kind"),
+        );
+    }
+    Some(Value::Object(row))
+}
+
+/// Row of an anomaly at the time of its event, with the rule and the computer as attributes
+pub fn anomaly_row(anomaly: &Anomaly) -> Option<Value> {
+    let time = anomaly.timestamp()?;
+    let message = format!(
+        "[{} {}] {}",
+        anomaly.severity(),
+        anomaly.rule_id(),
+        anomaly.description()
+    );
+    let mut row = row(time, message, "sysmon:anomaly");
+    row.insert("rule_id".to_string(), json!(anomaly.rule_id()));
+    row.insert("rule_name".to_string(), json!(anomaly.rule_name()));
+    row.insert(
+        "severity".to_string(),
+        json!(anomaly.severity().to_string()),
+    );
+    row.insert(
+        "attack_techniques".to_string(),
+        json!(anomaly.attack_techniques().join(",")),
+    );
+    if let Some(event) = anomaly.event() {
+        let system = event.system();
+        row.insert("computer".to_string(), json!(system.computer.computer));
+        row.insert(
+            "record_id".to_string(),
+            json!(system.event_record_id.event_record_id),
+        );
+    }
+    Some(Value::Object(row))
+}
+
+/// Write the events and anomalies, in time order, as a Timesketch JSON Lines file. Returns the
+/// number of rows written; events and anomalies without a time are left out.
+pub fn write_timeline(path: &Path, events: &[SysmonEvent], anomalies: &[Anomaly]) -> Result<usize> {
+    let mut rows: Vec<Value> = events
+        .iter()
+        .filter_map(event_row)
+        .chain(anomalies.iter().filter_map(anomaly_row))
+        .collect();
+    rows.sort_by_key(|row| row["timestamp"].as_i64());
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    for row in &rows {
+        serde_json::to_writer(&mut writer, row)?;
+        writer.write_all(b"\n")?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::fixtures::json_process_event;
+    use serde_json::json;
+
+    #[test]
+    fn timeline_rows() {
+        let events = [json_process_event(json!({
+            "Hostname": "WS-01",
+            "UtcTime": "2025-01-01 10:00:00.250",
+            "User": r"WS-01\Bob",
+        }))];
+        let anomalies = detect_anomalies(&events);
+        assert!(!anomalies.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timeline.jsonl");
+        let written = write_timeline(&path, &events, &anomalies).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), written);
+        assert_eq!(written, 1 + anomalies.len());
+        let event = rows
+            .iter()
+            .find(|row| row["data_type"] == "windows:evtx:sysmon")
+            .unwrap();
+        assert_eq!(event["message"], "ProcessCreate: cmd.exe /c whoami");
+        assert_eq!(event["timestamp_desc"], TIMESTAMP_DESC);
+        assert_eq!(event["image"], r"C:\Windows\System32\cmd.exe");
+        assert_eq!(event["computer"], "WS-01");
+        assert!(event.get("kind").is_none());
+        let time = event["datetime"].as_str().unwrap();
+        let parsed = DateTime::parse_from_rfc3339(time).unwrap();
+        assert_eq!(event["timestamp"], parsed.timestamp_micros());
+
+        let anomaly = rows.iter().find(|row| row["rule_id"] == "SLA-002").unwrap();
+        assert_eq!(anomaly["data_type"], "sysmon:anomaly");
+        assert_eq!(anomaly["timestamp"], event["timestamp"]);
+        assert!(
+            anomaly["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("[High SLA-002]")
+        );
+    }
+}
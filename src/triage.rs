@@ -0,0 +1,208 @@
+//! Triage verdicts of findings kept in a sidecar file, so later runs on the same data can
+//! annotate or hide the findings that were already reviewed
+use crate::analyzer::Anomaly;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::Path;
+
+/// Format version of the triage file, bumped when its layout changes
+pub const TRIAGE_VERSION: u32 = 1;
+
+/// Verdict of a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageStatus {
+    /// Benign activity, nothing to do
+    #[value(name = "fp", alias = "false-positive")]
+    FalsePositive,
+    /// Confirmed malicious or unwanted activity
+    #[value(name = "tp", alias = "true-positive")]
+    TruePositive,
+    /// Still being looked at
+    Investigating,
+}
+
+impl TriageStatus {
+    /// The finding got its final verdict and doesn't need to be reviewed again
+    pub fn is_closed(&self) -> bool {
+        !matches!(self, TriageStatus::Investigating)
+    }
+}
+
+impl Display for TriageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TriageStatus::FalsePositive => "false positive",
+            TriageStatus::TruePositive => "true positive",
+            TriageStatus::Investigating => "investigating",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Verdict recorded for one finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriageEntry {
+    pub status: TriageStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// When the verdict was recorded, RFC 3339
+    pub updated: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TriageDocument {
+    version: u32,
+    findings: BTreeMap<String, TriageEntry>,
+}
+
+/// Verdicts by finding ID (see [`finding_id`])
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TriageFile {
+    pub entries: BTreeMap<String, TriageEntry>,
+}
+
+impl TriageFile {
+    /// Verdicts saved in a file, none when the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+        Self::from_json(&json).with_context(|| format!("in {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let document: TriageDocument =
+            serde_json::from_str(json).context("Malformed triage file")?;
+        if document.version != TRIAGE_VERSION {
+            bail!(
+                "Unsupported triage file version {} (expected {TRIAGE_VERSION})",
+                document.version
+            );
+        }
+        Ok(Self {
+            entries: document.findings,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&TriageDocument {
+            version: TRIAGE_VERSION,
+            findings: self.entries.clone(),
+        })?)
+    }
+
+    /// Record the verdict of a finding, replacing an earlier one
+    pub fn set(
+        &mut self,
+        id: &str,
+        status: TriageStatus,
+        note: Option<String>,
+        updated: DateTime<Utc>,
+    ) {
+        self.entries.insert(
+            id.to_string(),
+            TriageEntry {
+                status,
+                note,
+                updated: updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+            },
+        );
+    }
+
+    /// Forget the verdict of a finding, `false` if it had none
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.entries.remove(id).is_some()
+    }
+
+    /// Verdict of the finding
+    pub fn get(&self, anomaly: &Anomaly) -> Option<&TriageEntry> {
+        self.entries.get(&finding_id(anomaly))
+    }
+
+    /// The finding got a final verdict (false or true positive)
+    pub fn is_closed(&self, anomaly: &Anomaly) -> bool {
+        self.get(anomaly)
+            .is_some_and(|entry| entry.status.is_closed())
+    }
+}
+
+/// Stable ID of a finding, shared by its repeats in this run and in later runs on the same data.
+/// It is a 48-bit FNV-1a hash of the key findings are grouped by.
+pub fn finding_id(anomaly: &Anomaly) -> String {
+    let hash = anomaly
+        .dedup_key()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:012x}", hash >> 16)
+}
+
+/// Parse a finding ID as printed by `parse --detect`
+pub fn parse_finding_id(s: &str) -> Result<String, String> {
+    let id = s.trim().trim_start_matches('#').to_ascii_lowercase();
+    if id.len() == 12 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(id)
+    } else {
+        Err(format!("invalid finding ID '{s}' (expected 12 hex digits)"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::detect_anomalies;
+    use crate::jsonl::parse_json_event;
+
+    fn tampering(record: u32, image: &str) -> crate::sysmon::Event {
+        let image = image.replace('\\', "\\\\");
+        parse_json_event(&format!(r#"{{"EventTime":"2025-01-01 10:00:0{record}","Hostname":"TEST-PC","EventID":25,"RecordNumber":{record},"UtcTime":"2025-01-01 10:00:0{record}.000","ProcessGuid":"{{11111111-2222-3333-4444-55555555555{record}}}","ProcessId":"10{record}","Image":"{image}","Type":"Image is replaced","User":"TEST-PC\\Bob"}}"#)).unwrap()
+    }
+
+    #[test]
+    fn verdicts_match_later_runs() {
+        let anomalies = detect_anomalies(&[
+            tampering(1, r"C:\Users\Bob\AppData\Local\Temp\a.exe"),
+            tampering(2, r"C:\Users\Bob\AppData\Local\Temp\a.exe"),
+            tampering(3, r"C:\Windows\System32\svchost.exe"),
+        ]);
+        assert_eq!(anomalies.len(), 3);
+        // Repeats of a finding share its ID, whatever the event
+        assert_eq!(finding_id(&anomalies[0]), finding_id(&anomalies[1]));
+        assert_ne!(finding_id(&anomalies[0]), finding_id(&anomalies[2]));
+
+        let mut triage = TriageFile::default();
+        let now = "2025-01-02T08:00:00Z".parse().unwrap();
+        let id = parse_finding_id(&format!("#{}", finding_id(&anomalies[0]).to_uppercase()));
+        triage.set(&id.unwrap(), TriageStatus::FalsePositive, None, now);
+        triage.set(
+            &finding_id(&anomalies[2]),
+            TriageStatus::Investigating,
+            Some("INC-42".to_string()),
+            now,
+        );
+        let triage = TriageFile::from_json(&triage.to_json().unwrap()).unwrap();
+
+        let rerun = detect_anomalies(&[tampering(4, r"C:\Users\Bob\AppData\Local\Temp\a.exe")]);
+        assert!(triage.is_closed(&rerun[0]));
+        assert!(!triage.is_closed(&anomalies[2]));
+        let entry = triage.get(&anomalies[2]).unwrap();
+        assert_eq!(entry.note.as_deref(), Some("INC-42"));
+        assert_eq!(entry.updated, "2025-01-02T08:00:00Z");
+
+        assert!(parse_finding_id("12345").is_err());
+        assert!(TriageFile::from_json(r#"{"version":9,"findings":{}}"#).is_err());
+    }
+}
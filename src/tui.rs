@@ -0,0 +1,305 @@
+use crate::analyzer::AggregatedAnomaly;
+use crate::display::{format_event_details, format_timestamp, get_process_and_color, pad};
+use crate::export::event_to_json;
+use crate::filters::{EventFilter, SearchMode};
+use crate::helpers::HasSystem;
+use crate::sysmon::Event as SysmonEvent;
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+const PAGE_SIZE: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Events,
+    Anomalies,
+}
+
+/// State of the interactive event browser
+pub struct App {
+    events: Vec<SysmonEvent>,
+    anomalies: Vec<AggregatedAnomaly>,
+    /// Indexes of events matching the filter box
+    visible: Vec<usize>,
+    tab: Tab,
+    events_state: ListState,
+    anomalies_state: ListState,
+    query: String,
+    editing: bool,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(events: Vec<SysmonEvent>, anomalies: Vec<AggregatedAnomaly>) -> Self {
+        let mut app = Self {
+            events,
+            anomalies,
+            visible: Vec::new(),
+            tab: Tab::Events,
+            events_state: ListState::default(),
+            anomalies_state: ListState::default(),
+            query: String::new(),
+            editing: false,
+            quit: false,
+        };
+        app.apply_query();
+        if !app.anomalies.is_empty() {
+            app.anomalies_state.select(Some(0));
+        }
+        app
+    }
+
+    /// Run the event loop until the user quits
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every whitespace-separated word of the filter box must match
+    fn apply_query(&mut self) {
+        let filter = EventFilter::new()
+            .with_search_terms(self.query.split_whitespace().map(String::from).collect())
+            .with_search_mode(SearchMode::All);
+        self.visible = (0..self.events.len())
+            .filter(|&i| filter.matches(&self.events[i]))
+            .collect();
+        self.events_state
+            .select((!self.visible.is_empty()).then_some(0));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.editing {
+            match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.editing = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.apply_query();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.apply_query();
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => {
+                self.tab = match self.tab {
+                    Tab::Events => Tab::Anomalies,
+                    Tab::Anomalies => Tab::Events,
+                }
+            }
+            KeyCode::Char('/') => {
+                self.tab = Tab::Events;
+                self.editing = true;
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+            KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.tab {
+            Tab::Events => (&mut self.events_state, self.visible.len()),
+            Tab::Anomalies => (&mut self.anomalies_state, self.anomalies.len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        let next = current.saturating_add(delta).clamp(0, len as isize - 1);
+        state.select(Some(next as usize));
+    }
+
+    fn selected_event(&self) -> Option<&SysmonEvent> {
+        match self.tab {
+            Tab::Events => self
+                .events_state
+                .selected()
+                .and_then(|i| self.visible.get(i))
+                .map(|&i| &self.events[i]),
+            Tab::Anomalies => self
+                .anomalies_state
+                .selected()
+                .and_then(|i| self.anomalies.get(i))
+                .and_then(|group| group.anomaly.event()),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, main_area, filter_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(main_area);
+
+        let titles = vec![
+            format!("Events ({}/{})", self.visible.len(), self.events.len()),
+            format!("Anomalies ({})", self.anomalies.len()),
+        ];
+        let tabs = Tabs::new(titles)
+            .select(match self.tab {
+                Tab::Events => 0,
+                Tab::Anomalies => 1,
+            })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Sysmon Log Analyzer"),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(tabs, tabs_area);
+
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+        match self.tab {
+            Tab::Events => {
+                let items: Vec<ListItem> = self
+                    .visible
+                    .iter()
+                    .map(|&i| event_item(&self.events[i]))
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, list_area, &mut self.events_state);
+            }
+            Tab::Anomalies => {
+                let items: Vec<ListItem> = self.anomalies.iter().map(anomaly_item).collect();
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, list_area, &mut self.anomalies_state);
+            }
+        }
+
+        let detail = self
+            .selected_event()
+            .and_then(|event| serde_json::to_string_pretty(&event_to_json(event)).ok())
+            .unwrap_or_default();
+        let detail = Paragraph::new(detail)
+            .block(Block::default().borders(Borders::ALL).title("Details"))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(detail, detail_area);
+
+        let (title, style) = if self.editing {
+            (
+                "Filter (Enter to apply)",
+                Style::default().fg(Color::Yellow),
+            )
+        } else {
+            (
+                "Filter (/ to edit, Tab switch view, q quit)",
+                Style::default(),
+            )
+        };
+        let filter = Paragraph::new(self.query.as_str())
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(filter, filter_area);
+    }
+}
+
+fn event_item(event: &SysmonEvent) -> ListItem<'static> {
+    let (_, process) = get_process_and_color(event);
+    ListItem::new(Line::from(format!(
+        "{} {:>2} {} {} {}",
+        format_timestamp(&event.system().time_created),
+        event.system().event_id.event_id,
+        pad(event.name(), 16),
+        pad(&process, 20),
+        format_event_details(event)
+    )))
+}
+
+fn anomaly_item(group: &AggregatedAnomaly) -> ListItem<'static> {
+    ListItem::new(Line::from(format!(
+        "[{}] x{} {}",
+        group.anomaly.severity(),
+        group.count,
+        group.anomaly.description()
+    )))
+}
+
+/// Open the interactive browser on the terminal
+pub fn run(events: Vec<SysmonEvent>, anomalies: Vec<AggregatedAnomaly>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(events, anomalies).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{PROCESS_GUID, file_event};
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn event(image: &str) -> SysmonEvent {
+        file_event(
+            "2025-01-01T10:00:00.000Z",
+            PROCESS_GUID,
+            image,
+            r"C:\Temp\out.txt",
+        )
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn filter_box_and_navigation() {
+        let events = vec![
+            event(r"C:\Windows\notepad.exe"),
+            event(r"C:\Windows\System32\cmd.exe"),
+            event(r"C:\Windows\explorer.exe"),
+        ];
+        let mut app = App::new(events, Vec::new());
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.events_state.selected(), Some(2));
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.events_state.selected(), Some(2));
+
+        press(&mut app, KeyCode::Char('/'));
+        for c in "cmd".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.visible, [1]);
+        assert_eq!(app.events_state.selected(), Some(0));
+        assert!(matches!(
+            app.selected_event(),
+            Some(SysmonEvent::FileCreate(_))
+        ));
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.quit);
+    }
+}
@@ -0,0 +1,64 @@
+//! Config, allowlist, IOC and mapping files, written in YAML or, with a `.json` extension, in JSON
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// Read a YAML or JSON file and build `T` from its content with `parse`, told whether it is JSON;
+/// `what` names the file in errors
+pub fn load_yaml_or_json<T>(
+    path: &Path,
+    what: &str,
+    parse: impl FnOnce(&str, bool) -> Result<T>,
+) -> Result<T> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {what} {}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    parse(&content, is_json).with_context(|| format!("Invalid {what} {}", path.display()))
+}
+
+/// Deserialize JSON or YAML content
+pub fn from_yaml_or_json<T: DeserializeOwned>(content: &str, is_json: bool) -> Result<T> {
+    Ok(if is_json {
+        serde_json::from_str(content)?
+    } else {
+        serde_norway::from_str(content)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn format_follows_the_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            load_yaml_or_json(
+                &path,
+                "test file",
+                from_yaml_or_json::<BTreeMap<String, u32>>,
+            )
+        };
+        assert_eq!(load("a.yaml", "a: 1\n").unwrap()["a"], 1);
+        assert_eq!(load("a.JSON", r#"{"a": 1}"#).unwrap()["a"], 1);
+        // YAML anchors and aliases
+        assert_eq!(load("b.yml", "a: &one 1\nb: *one\n").unwrap()["b"], 1);
+        let error = load("c.json", "a: 1\n").unwrap_err();
+        assert!(
+            error.to_string().starts_with("Invalid test file "),
+            "{error}"
+        );
+        let missing = load_yaml_or_json(&dir.path().join("d.yaml"), "test file", |_, _| Ok(()));
+        assert!(
+            missing
+                .unwrap_err()
+                .to_string()
+                .starts_with("Failed to read test file ")
+        );
+    }
+}